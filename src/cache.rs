@@ -0,0 +1,291 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+use crate::ast::FuncVisibility;
+use crate::builtins::Builtin;
+use crate::typed_ast::{TypedGlobalStatement, TypedProgram};
+use crate::{CompilerOptions, SourceFile, SourceMap};
+
+/// A directory of compiled modules (object files), each named after a hash of everything that
+/// went into it (see [ObjectCache::key]), so that `flick build` only has to compile the files
+/// that changed since the last build.
+///
+/// ```
+/// use flick::cache::ObjectCache;
+///
+/// let cache = ObjectCache::new(std::env::temp_dir().join("flick-doc-cache"));
+/// assert_eq!(cache.get("0123abcd", "o"), None);
+/// let path = cache.insert("0123abcd", "o", b"object code").unwrap();
+/// assert_eq!(cache.get("0123abcd", "o"), Some(path));
+/// # std::fs::remove_dir_all(&cache.dir).unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct ObjectCache {
+    pub dir: PathBuf,
+}
+
+/// A part of a program that's compiled on its own (see [split_modules]).
+#[derive(Debug, Clone)]
+pub struct Module<'a> {
+    /// The files that the module's functions are defined in (just one, unless the program has
+    /// to be compiled as a whole)
+    pub files: Vec<&'a SourceFile>,
+    /// The module's functions, and the functions from other modules (or from outside the
+    /// program) that they call, as [Extern](TypedGlobalStatement::Extern)s
+    pub program: TypedProgram,
+}
+
+impl ObjectCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Returns where the cache is kept by default: a `flick` directory in the user's cache
+    /// directory (like `~/.cache/flick`, or `$XDG_CACHE_HOME/flick`), or in the temporary
+    /// directory if there's no such thing.
+    pub fn default_dir() -> PathBuf {
+        let user_cache_dir = match std::env::var_os("XDG_CACHE_HOME").filter(|dir| !dir.is_empty()) {
+            Some(dir) => Some(PathBuf::from(dir)),
+            None if cfg!(windows) => std::env::var_os("LOCALAPPDATA").map(PathBuf::from),
+            None if cfg!(target_vendor = "apple") => {
+                std::env::var_os("HOME").map(|home| Path::new(&home).join("Library").join("Caches"))
+            }
+            None => std::env::var_os("HOME").map(|home| Path::new(&home).join(".cache")),
+        };
+        user_cache_dir.unwrap_or_else(std::env::temp_dir).join("flick")
+    }
+
+    /// Returns the key that `module` is cached under when it's compiled with `options`: a hash
+    /// of the paths and source code of its files (the paths end up in the code, e.g. in debug
+    /// info and coverage counters), the prototypes of its functions (and of the ones it
+    /// calls), and the options (and the version of Flick, since that changes the code too).
+    pub fn key(module: &Module, options: &CompilerOptions) -> String {
+        let mut hasher = Sha256::new();
+        let mut add = |bytes: &[u8]| {
+            // Each part starts with its length, so that no two lists of parts run together
+            hasher.update((bytes.len() as u64).to_le_bytes());
+            hasher.update(bytes);
+        };
+        add(env!("CARGO_PKG_VERSION").as_bytes());
+        add(format!("{:?}", options).as_bytes());
+        // What the default relocation model means isn't part of the options themselves
+        add(format!("{:?}", options.effective_reloc_model()).as_bytes());
+        for file in &module.files {
+            add(file.path.as_os_str().as_encoded_bytes());
+            add(file.source_code.as_bytes());
+        }
+        for global_statement in &module.program.global_statements {
+            let proto = match global_statement {
+                TypedGlobalStatement::Extern(proto) => proto,
+                TypedGlobalStatement::FuncDef(func_def) => &func_def.proto,
+            };
+            add(proto.to_string().as_bytes());
+        }
+        hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    /// Returns the path of the file that's cached under `key` (with the extension `extension`,
+    /// like `o`), if there is one.
+    pub fn get(&self, key: &str, extension: &str) -> Option<PathBuf> {
+        let path = self.path(key, extension);
+        path.is_file().then_some(path)
+    }
+
+    /// Caches `contents` under `key` (with the extension `extension`), and returns the path of
+    /// the cached file.
+    pub fn insert(&self, key: &str, extension: &str, contents: &[u8]) -> io::Result<PathBuf> {
+        fs::create_dir_all(&self.dir)?;
+        let path = self.path(key, extension);
+        // Write to a temporary file first, so that another build never sees half a file
+        let temporary_path = self.dir.join(format!("{}.{}.tmp", key, std::process::id()));
+        fs::write(&temporary_path, contents)?;
+        fs::rename(&temporary_path, &path)?;
+        Ok(path)
+    }
+
+    fn path(&self, key: &str, extension: &str) -> PathBuf {
+        self.dir.join(format!("{}.{}", key, extension))
+    }
+}
+
+/// Splits `program` (whose files are in `sources`) into one module for each file, which can be
+/// compiled on its own: each module declares the functions from other files that it calls, and
+/// a private function that's called from another file is made public.
+///
+/// A program that reads its arguments (see [Builtin::reads_args]) or counts its statements
+/// (see [coverage](crate::coverage)) is one module, since its functions share globals.
+pub fn split_modules<'a>(program: &TypedProgram, sources: &'a SourceMap) -> Vec<Module<'a>> {
+    let reads_args = Builtin::ALL.iter().any(|builtin| builtin.reads_args() && program.calls(builtin.name()));
+    if reads_args || program.coverage.is_some() || sources.files().len() < 2 {
+        let files = sources.files().iter().collect();
+        return vec![Module { files, program: program.clone() }];
+    }
+
+    let file_index = |proto: &crate::ast::FuncProto| {
+        let (file, _) = sources.locate(proto.span);
+        sources.files().iter().position(|other| other.start == file.start).unwrap()
+    };
+    let mut func_defs = Vec::new();
+    let mut defined_in = HashMap::new();
+    let mut externs = HashMap::new();
+    for global_statement in &program.global_statements {
+        match global_statement {
+            TypedGlobalStatement::FuncDef(func_def) => {
+                let index = file_index(&func_def.proto);
+                defined_in.insert(func_def.proto.name.as_str(), (index, &func_def.proto));
+                func_defs.push((index, func_def));
+            }
+            TypedGlobalStatement::Extern(proto) => {
+                externs.insert(proto.name.as_str(), proto);
+            }
+        }
+    }
+
+    // The functions that each module calls from elsewhere, in alphabetical order (so that the
+    // same calls always make the same module)
+    let mut imports = vec![BTreeSet::new(); sources.files().len()];
+    let mut exported = HashSet::new();
+    for (index, func_def) in &func_defs {
+        let mut calls = Vec::new();
+        func_def.body.iter().for_each(|stmt| stmt.collect_calls(&mut calls));
+        for callee in calls {
+            match defined_in.get(callee) {
+                Some((callee_index, _)) if callee_index == index => {}
+                Some(_) => {
+                    exported.insert(callee);
+                    imports[*index].insert(callee);
+                }
+                None if externs.contains_key(callee) => {
+                    imports[*index].insert(callee);
+                }
+                None => {}
+            }
+        }
+    }
+
+    let mut modules: Vec<Module> = sources
+        .files()
+        .iter()
+        .zip(imports)
+        .map(|(file, imports)| {
+            let global_statements = imports
+                .into_iter()
+                .map(|name| {
+                    let mut proto = match defined_in.get(name) {
+                        Some((_, proto)) => (*proto).clone(),
+                        None => externs[name].clone(),
+                    };
+                    proto.func_visibility = FuncVisibility::Extern;
+                    TypedGlobalStatement::Extern(proto)
+                })
+                .collect();
+            let program = TypedProgram { global_statements, coverage: None };
+            Module { files: vec![file], program }
+        })
+        .collect();
+    for (index, func_def) in func_defs {
+        let mut func_def = func_def.clone();
+        if exported.contains(func_def.proto.name.as_str()) {
+            func_def.proto.func_visibility = FuncVisibility::Public;
+        }
+        modules[index].program.global_statements.push(TypedGlobalStatement::FuncDef(func_def));
+    }
+    modules
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::Arena;
+    use crate::token::Span;
+    use crate::{Lexer, Parser, Typer};
+
+    /// Type checks the files `files` (paths and source code) as one program.
+    fn check(files: &[(&str, &str)]) -> (SourceMap, TypedProgram) {
+        let mut sources = SourceMap::default();
+        for (path, source_code) in files {
+            sources.add_file(*path, source_code.to_string());
+        }
+        let (mut tokens, mut spans) = (Vec::new(), Vec::new());
+        for file in sources.files() {
+            let (file_tokens, file_spans) = Lexer::lex_with_spans(&file.source_code).unwrap();
+            tokens.extend(file_tokens);
+            spans.extend(file_spans.iter().map(|span| Span { start: span.start + file.start, end: span.end + file.start }));
+        }
+        let arena = Arena::new();
+        let program = Parser::parse_program_with_spans(&arena, &tokens, &spans).unwrap();
+        let typed_program = Typer::new().type_program(&program).unwrap();
+        (sources, typed_program)
+    }
+
+    /// Returns the prototypes in `module`, like `extern fn double(u8 x) u8`.
+    fn protos(module: &Module) -> Vec<String> {
+        module
+            .program
+            .global_statements
+            .iter()
+            .map(|global_statement| match global_statement {
+                TypedGlobalStatement::Extern(proto) => proto.to_string(),
+                TypedGlobalStatement::FuncDef(func_def) => func_def.proto.to_string(),
+            })
+            .collect()
+    }
+
+    const MAIN: &str = "extern fn puts(str s) i32\n\npub fn main() u8 {\n    puts(\"hi\")\n    ret double(2)\n}\n";
+    const DOUBLE: &str = "fn double(u8 x) u8 {\n    ret x * 2\n}\n\nfn unused() {\n}\n";
+
+    #[test]
+    fn each_file_is_a_module() {
+        let (sources, program) = check(&[("main.fl", MAIN), ("double.fl", DOUBLE)]);
+        let modules = split_modules(&program, &sources);
+        assert_eq!(modules.len(), 2);
+        assert_eq!(modules[0].files[0].path, Path::new("main.fl"));
+        assert_eq!(protos(&modules[0]), ["extern fn double(u8 x) u8", "extern fn puts(str s) i32", "pub fn main() u8"]);
+        // `double` is called from main.fl, so it has to be visible there
+        assert_eq!(protos(&modules[1]), ["pub fn double(u8 x) u8", "fn unused() void"]);
+    }
+
+    #[test]
+    fn programs_that_read_their_arguments_are_one_module() {
+        let main = "pub fn main() u8 {\n    ret (u8) arg_count()\n}\n";
+        let (sources, program) = check(&[("main.fl", main), ("double.fl", DOUBLE)]);
+        let modules = split_modules(&program, &sources);
+        assert_eq!(modules.len(), 1);
+        assert_eq!(modules[0].files.len(), 2);
+        assert_eq!(modules[0].program, program);
+    }
+
+    #[test]
+    fn keys_change_with_what_goes_into_a_module() {
+        let key = |files: &[(&str, &str)], options: &CompilerOptions| {
+            let (sources, program) = check(files);
+            let modules = split_modules(&program, &sources);
+            modules.iter().map(|module| ObjectCache::key(module, options)).collect::<Vec<_>>()
+        };
+        let options = CompilerOptions::default();
+        let keys = key(&[("main.fl", MAIN), ("double.fl", DOUBLE)], &options);
+        assert_eq!(keys, key(&[("main.fl", MAIN), ("double.fl", DOUBLE)], &options));
+
+        // Only the module whose file changed is compiled again
+        let changed = key(&[("main.fl", MAIN), ("double.fl", &DOUBLE.replace("2", "3"))], &options);
+        assert_eq!(changed[0], keys[0]);
+        assert_ne!(changed[1], keys[1]);
+
+        // ...unless the prototype of a function that it calls changed
+        let changed = key(&[("main.fl", MAIN), ("double.fl", &DOUBLE.replace("x", "n"))], &options);
+        assert_ne!(changed[0], keys[0]);
+
+        // Two files with the same code are different modules
+        let renamed = key(&[("main.fl", MAIN), ("triple.fl", DOUBLE)], &options);
+        assert_eq!(renamed[0], keys[0]);
+        assert_ne!(renamed[1], keys[1]);
+
+        let optimized = CompilerOptions { opt_level: crate::OptLevel::O3, ..CompilerOptions::default() };
+        let changed = key(&[("main.fl", MAIN), ("double.fl", DOUBLE)], &optimized);
+        assert!(changed.iter().zip(&keys).all(|(changed, key)| changed != key));
+    }
+}
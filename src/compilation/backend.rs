@@ -0,0 +1,46 @@
+use std::path::Path;
+
+use crate::compilation::error::CompilationError;
+use crate::compilation::options::EmitKind;
+use crate::typed_ast::TypedProgram;
+
+/// A code generator that turns a [TypedProgram] into some output file, like an object file
+/// (see [Compiler](crate::Compiler)) or C source code (see [CBackend](crate::CBackend)).
+///
+/// The methods are meant to be called in order: [compile][a], then [optimize][b], then
+/// [to_file][c] (or [emit_to_memory][d]).
+///
+/// [a]: Backend::compile
+/// [b]: Backend::optimize
+/// [c]: Backend::to_file
+/// [d]: Backend::emit_to_memory
+pub trait Backend {
+    /// Generates code for `program`.
+    fn compile(&mut self, program: &TypedProgram) -> Result<(), CompilationError>;
+
+    /// Optimizes the code generated so far (this may do nothing, if the backend leaves
+    /// optimization to another tool).
+    fn optimize(&mut self) -> Result<(), CompilationError>;
+
+    /// Prints the code generated so far (e.g. LLVM IR) to stderr.
+    fn print_ir(&self);
+
+    /// Returns the code generated so far (e.g. LLVM IR) as text, e.g. to compare it before and
+    /// after [optimize](Backend::optimize).
+    fn ir_to_string(&self) -> String;
+
+    /// Like [ir_to_string](Backend::ir_to_string), but returns just the code for the function
+    /// named `func_name`, or `None` if there's no such function (e.g. because it was inlined
+    /// into its callers and removed during optimization).
+    fn function_ir_to_string(&self, func_name: &str) -> Option<String>;
+
+    /// Writes the output file to `path`, returning an error if it can't be written.
+    fn to_file(&self, path: &Path) -> Result<(), CompilationError>;
+
+    /// Like [to_file](Backend::to_file), but writes `kind` of file (like assembly), returning
+    /// an error if the backend can't generate that kind (or if the file can't be written).
+    fn to_file_as(&self, path: &Path, kind: EmitKind) -> Result<(), CompilationError>;
+
+    /// Returns the contents of the output file, or an error if it can't be generated.
+    fn emit_to_memory(&self) -> Result<Vec<u8>, CompilationError>;
+}
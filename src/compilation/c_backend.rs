@@ -0,0 +1,813 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::path::Path;
+
+use crate::ast::*;
+use crate::builtins::Builtin;
+use crate::compilation::backend::Backend;
+use crate::compilation::error::CompilationError;
+use crate::compilation::options::EmitKind;
+use crate::typed_ast::*;
+use crate::types::{IntType, Type};
+use crate::{Name, ScopeManager};
+
+/// C's keywords (and the macros from `<stdbool.h>`), which Flick variables are allowed to use
+/// (e.g. `int`, or `r#while`), but which no C identifier can be.
+const C_KEYWORDS: &[&str] = &[
+    "auto", "break", "case", "char", "const", "continue", "default", "do", "double", "else",
+    "enum", "extern", "float", "for", "goto", "if", "inline", "int", "long", "register",
+    "restrict", "return", "short", "signed", "sizeof", "static", "struct", "switch", "typedef",
+    "union", "unsigned", "void", "volatile", "while", "_Bool", "_Complex", "_Imaginary", "bool",
+    "true", "false",
+];
+
+/// The C functions and globals that the generated code can use, which Flick variables can't be
+/// named.
+const RUNTIME_FUNCTIONS: &[&str] = &[
+    "abort", "raise", "flick_argc", "flick_argv", "flick_arg", "fopen", "fprintf", "fclose", "atexit",
+    "flick_coverage", "flick_coverage_write",
+];
+
+/// A [Backend] that converts a [typed abstract syntax tree][a] into portable C99 source code,
+/// instead of going through LLVM.
+///
+/// This is useful on platforms that LLVM doesn't support (any C compiler can build the output),
+/// and for auditing what a program does. The generated code keeps Flick's semantics: integer
+/// arithmetic wraps around (rather than being undefined on overflow, like signed arithmetic in
+/// C), and casts extend according to the signedness of the type being cast to.
+///
+/// # Example usage
+///
+/// ```
+/// # use flick::{typed_ast, Backend, CBackend};
+/// let mut backend = CBackend::new();
+/// let syntax_tree = typed_ast::TypedProgram {
+///     // generated during parsing
+///     # global_statements: vec![],
+///     # coverage: None,
+/// };
+/// backend.compile(&syntax_tree).unwrap();
+/// assert!(backend.source().contains("#include <stdint.h>"));
+/// ```
+///
+/// [a]: crate::typed_ast
+#[derive(Default)]
+pub struct CBackend {
+    source: String,
+    /// Whether the program has an `unreachable` statement (which needs `abort` to be declared).
+    uses_abort: bool,
+    /// Whether the program calls `breakpoint()` (which needs `<signal.h>` to be included).
+    uses_breakpoint: bool,
+    /// Whether the program reads its command-line arguments (see [Builtin::reads_args]), in
+    /// which case `main` saves them in globals.
+    reads_args: bool,
+    /// Whether the program has been [instrumented](crate::coverage::instrument) for coverage, in
+    /// which case `main` registers the function that writes the profile with `atexit`.
+    has_coverage: bool,
+    /// The C name of each variable in scope, keyed by its Flick name.
+    scope_manager: ScopeManager<String>,
+    /// The names that can't be given to new variables in the function being converted.
+    used_names: HashSet<String>,
+    /// The names of every function in the program.
+    func_names: Vec<String>,
+    /// The definition of each function in the program, keyed by the function's name.
+    func_defs: HashMap<Name, String>,
+}
+
+impl CBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the C source code generated so far (via [compile](Backend::compile)).
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Returns the C code for the whole program: the headers, every function's declaration (so
+    /// that functions can call functions defined later), and every function's definition.
+    fn program(&mut self, program: &TypedProgram) -> Result<String, CompilationError> {
+        self.func_names = program
+            .global_statements
+            .iter()
+            .map(|global_statement| match global_statement {
+                TypedGlobalStatement::Extern(proto) => proto.name.to_string(),
+                TypedGlobalStatement::FuncDef(func_def) => func_def.proto.name.to_string(),
+            })
+            .collect();
+        self.reads_args = Builtin::ALL.iter().any(|builtin| builtin.reads_args() && program.calls(builtin.name()));
+        self.has_coverage = program.coverage.is_some();
+
+        let mut declarations = String::new();
+        let mut definitions = String::new();
+        for global_statement in program.global_statements.iter() {
+            match global_statement {
+                TypedGlobalStatement::Extern(proto) => {
+                    writeln!(declarations, "{};", self.func_signature(proto, false)?).unwrap();
+                }
+                TypedGlobalStatement::FuncDef(func_def) => {
+                    writeln!(declarations, "{};", self.func_signature(&func_def.proto, false)?).unwrap();
+                    let definition = self.func_def(func_def)?;
+                    writeln!(definitions, "\n{}", definition).unwrap();
+                    self.func_defs.insert(func_def.proto.name, definition);
+                }
+            }
+        }
+
+        let mut source = String::new();
+        writeln!(source, concat!("/* Generated by flick ", env!("CARGO_PKG_VERSION"), " */")).unwrap();
+        writeln!(source, "#include <stdbool.h>").unwrap();
+        writeln!(source, "#include <stdint.h>").unwrap();
+        if self.uses_abort {
+            writeln!(source, "\nvoid abort(void);").unwrap();
+        }
+        if self.uses_breakpoint {
+            // SIGTRAP stops the program in an attached debugger, but it's POSIX rather than C99
+            writeln!(source, "\n#include <signal.h>").unwrap();
+            writeln!(source, "#ifdef SIGTRAP").unwrap();
+            writeln!(source, "#define FLICK_BREAKPOINT() raise(SIGTRAP)").unwrap();
+            writeln!(source, "#else").unwrap();
+            writeln!(source, "#define FLICK_BREAKPOINT() ((void)0)").unwrap();
+            writeln!(source, "#endif").unwrap();
+        }
+        if self.reads_args {
+            writeln!(source, "\nstatic int flick_argc;").unwrap();
+            writeln!(source, "static char **flick_argv;").unwrap();
+            writeln!(source, "static char *flick_arg(uint32_t index) {{").unwrap();
+            writeln!(source, "    return index < (uint32_t)flick_argc ? flick_argv[index] : \"\";").unwrap();
+            writeln!(source, "}}").unwrap();
+        }
+        if let Some(coverage) = &program.coverage {
+            source.push_str(&Self::coverage_writer(coverage));
+        }
+        if !declarations.is_empty() {
+            write!(source, "\n{}", declarations).unwrap();
+        }
+        source.push_str(&definitions);
+        Ok(source)
+    }
+
+    /// Returns a function's definition, i.e. its signature and body.
+    fn func_def(&mut self, func_def: &TypedFuncDef) -> Result<String, CompilationError> {
+        tracing::debug!(function = %func_def.proto.name, "generating C");
+        self.used_names = C_KEYWORDS.iter().chain(RUNTIME_FUNCTIONS).map(|name| name.to_string()).collect();
+        self.used_names.extend(self.func_names.iter().cloned());
+        self.scope_manager.enter_scope();
+        for param in func_def.proto.params.iter() {
+            self.declare_var(&param.param_name);
+        }
+        if self.saves_args(&func_def.proto) {
+            self.used_names.extend(["argc".to_string(), "argv".to_string()]);
+        }
+        let signature = self.func_signature(&func_def.proto, true)?;
+        let mut body = self.block(&func_def.body, 0)?;
+        if self.saves_args(&func_def.proto) {
+            body.insert_str(1, "\n    flick_argc = argc;\n    flick_argv = argv;");
+        }
+        if self.has_coverage && func_def.proto.name == "main" && func_def.proto.func_visibility == FuncVisibility::Public {
+            body.insert_str(1, "\n    atexit(flick_coverage_write);");
+        }
+        self.scope_manager.exit_scope();
+        Ok(format!("{} {}", signature, body))
+    }
+
+    /// Returns the counters of an [instrumented](crate::coverage::instrument) program, and the
+    /// function that writes them to its profile, one line per counter.
+    ///
+    /// The C library functions are declared without their headers, since `<stdio.h>` would
+    /// clash with the program's own declarations of functions like `puts`.
+    fn coverage_writer(coverage: &Coverage) -> String {
+        let mut writer = String::new();
+        writeln!(writer, "\nvoid *fopen(const char *, const char *);").unwrap();
+        writeln!(writer, "int fprintf(void *, const char *, ...);").unwrap();
+        writeln!(writer, "int fclose(void *);").unwrap();
+        writeln!(writer, "int atexit(void (*)(void));").unwrap();
+        writeln!(writer, "\nstatic uint64_t flick_coverage[{}];", coverage.locations.len().max(1)).unwrap();
+        writeln!(writer, "\nstatic void flick_coverage_write(void) {{").unwrap();
+        writeln!(writer, "    void *profile = fopen({}, \"w\");", Self::str_literal(&coverage.profile_path)).unwrap();
+        writeln!(writer, "    if (!profile) {{\n        return;\n    }}").unwrap();
+        for (index, location) in coverage.locations.iter().enumerate() {
+            let format = Self::str_literal(&format!("{} %llu\n", location.replace('%', "%%")));
+            writeln!(writer, "    fprintf(profile, {}, (unsigned long long)flick_coverage[{}]);", format, index).unwrap();
+        }
+        writeln!(writer, "    fclose(profile);\n}}").unwrap();
+        writer
+    }
+
+    /// Returns a function's signature, like `static uint8_t square(uint8_t x)`, or (without
+    /// `param_names`) just `static uint8_t square(uint8_t)`.
+    ///
+    /// Note: C requires `main` to return an `int`, so Flick's `main` (which returns an exit
+    /// code, like `u8`) is declared as returning one. If the program reads its command-line
+    /// arguments, `main` also takes C's `argc` and `argv`.
+    fn func_signature(&self, proto: &FuncProto, param_names: bool) -> Result<String, CompilationError> {
+        // Unlike a variable, a function can't be renamed, since other code links to it by name
+        if C_KEYWORDS.contains(&proto.name.as_str()) {
+            return Err(CompilationError::UnsupportedByBackend {
+                backend: "C",
+                message: format!("a function can't be named '{}', since that's a C keyword", proto.name),
+            });
+        }
+        let is_main = proto.name == "main" && proto.func_visibility == FuncVisibility::Public;
+        let linkage = match proto.func_visibility {
+            FuncVisibility::Private => "static ",
+            FuncVisibility::Public | FuncVisibility::Extern => "",
+        };
+        let return_type = match is_main {
+            true => "int".to_string(),
+            false => Self::type_name(&proto.return_type)?,
+        };
+        let params = match proto.params.is_empty() {
+            true if self.saves_args(proto) => match param_names {
+                true => "int argc, char **argv".to_string(),
+                false => "int, char **".to_string(),
+            },
+            true => "void".to_string(),
+            false => proto
+                .params
+                .iter()
+                .map(|param| {
+                    let type_name = Self::type_name(&param.param_type)?;
+                    match param_names {
+                        true => Ok(format!("{} {}", type_name, self.var_name(&param.param_name))),
+                        false => Ok(type_name),
+                    }
+                })
+                .collect::<Result<Vec<_>, CompilationError>>()?
+                .join(", "),
+        };
+        Ok(format!("{}{} {}({})", linkage, return_type, proto.name, params))
+    }
+
+    /// Returns a block of statements in braces, where `depth` is the indentation of the braces.
+    fn block(&mut self, body: &[TypedStatement], depth: usize) -> Result<String, CompilationError> {
+        let mut block = String::from("{\n");
+        self.scope_manager.enter_scope();
+        for statement in body {
+            writeln!(block, "{}{}", "    ".repeat(depth + 1), self.statement(statement, depth + 1)?).unwrap();
+        }
+        self.scope_manager.exit_scope();
+        block.push_str(&"    ".repeat(depth));
+        block.push('}');
+        Ok(block)
+    }
+
+    /// Returns the C code for a statement (without indenting its first line).
+    fn statement(&mut self, statement: &TypedStatement, depth: usize) -> Result<String, CompilationError> {
+        Ok(match statement {
+            TypedStatement::VarDeclaration(var_declaration) => {
+                // The value is converted first, since it can refer to a variable being shadowed
+                let value = self.expr(&var_declaration.var_value)?;
+                let type_name = Self::type_name(&var_declaration.var_type)?;
+                format!("{} {} = {};", type_name, self.declare_var(&var_declaration.var_name), value)
+            }
+            TypedStatement::Assignment(assign) => {
+                format!("{} = {};", self.var_name(&assign.name), self.expr(&assign.value)?)
+            }
+            TypedStatement::WhileLoop(while_loop) => format!(
+                "while ({}) {}",
+                self.expr(&while_loop.condition)?,
+                self.block(&while_loop.body, depth)?,
+            ),
+            TypedStatement::If(if_statement) => {
+                let mut c = format!(
+                    "if ({}) {}",
+                    self.expr(&if_statement.condition)?,
+                    self.block(&if_statement.then_body, depth)?,
+                );
+                if let Some(else_body) = &if_statement.else_body {
+                    write!(c, " else {}", self.block(else_body, depth)?).unwrap();
+                }
+                c
+            }
+            TypedStatement::Return(Some(expr)) => format!("return {};", self.expr(expr)?),
+            TypedStatement::Return(None) => "return;".to_string(),
+            TypedStatement::Call(call) => format!("{};", self.call(call)?),
+            TypedStatement::Count(index) => format!("flick_coverage[{}] += 1;", index),
+            TypedStatement::Unreachable => {
+                self.uses_abort = true;
+                "abort();".to_string()
+            }
+        })
+    }
+
+    /// Returns the C code for an expression, which is parenthesized if it has any operators.
+    fn expr(&mut self, expr: &TypedExpr) -> Result<String, CompilationError> {
+        Ok(match expr {
+            TypedExpr::Identifier(id) => self.var_name(&id.name),
+            TypedExpr::IntLiteral(int_literal) => Self::int_literal(int_literal)?,
+            TypedExpr::BoolLiteral(bool_literal) => bool_literal.to_string(),
+            TypedExpr::StrLiteral(str_literal) => Self::str_literal(str_literal),
+            TypedExpr::Binary(bin_expr) => self.bin_expr(bin_expr)?,
+            TypedExpr::Comparison(comparison) => {
+                let operator = match comparison.operator {
+                    ComparisonOperator::EqualTo => "==",
+                    ComparisonOperator::NotEqualTo => "!=",
+                    ComparisonOperator::LessThan => "<",
+                    ComparisonOperator::GreaterThan => ">",
+                    ComparisonOperator::LessOrEqualTo => "<=",
+                    ComparisonOperator::GreaterOrEqualTo => ">=",
+                };
+                format!("({} {} {})", self.expr(&comparison.left)?, operator, self.expr(&comparison.right)?)
+            }
+            TypedExpr::Call(call) => self.call(call)?,
+            TypedExpr::Unary(unary) => self.unary(unary)?,
+        })
+    }
+
+    /// Returns an integer literal, cast to its type (e.g. `((uint8_t)3)`).
+    fn int_literal(int_literal: &TypedIntLiteral) -> Result<String, CompilationError> {
+        let type_name = Self::int_type_name(int_literal.int_type)?;
+        match int_literal.int_type.width {
+            // Without a suffix, literals too big for a `long long` aren't valid C
+            64 => Ok(format!("(({})UINT64_C({}))", type_name, int_literal.int_value)),
+            _ => Ok(format!("(({}){})", type_name, int_literal.int_value)),
+        }
+    }
+
+    /// Returns a C string literal with the same bytes as `s`.
+    ///
+    /// Anything that isn't printable ASCII is written as a three-digit octal escape, which (unlike
+    /// `\x`) can't run into the characters after it.
+    fn str_literal(s: &str) -> String {
+        let mut literal = String::from("\"");
+        for byte in s.bytes() {
+            match byte {
+                b'"' | b'\\' | b'?' => write!(literal, "\\{}", byte as char).unwrap(),
+                b' '..=b'~' => literal.push(byte as char),
+                _ => write!(literal, "\\{:03o}", byte).unwrap(),
+            }
+        }
+        literal.push('"');
+        literal
+    }
+
+    /// Returns the C code for a binary expression.
+    ///
+    /// Addition, subtraction, and multiplication are done on unsigned integers (which wrap around
+    /// in C) that are at least as wide as an `int` (so that they aren't promoted to `int`).
+    fn bin_expr(&mut self, bin_expr: &TypedBinary) -> Result<String, CompilationError> {
+        let int_type = match bin_expr.result_type {
+            Type::Int(int_type) => int_type,
+            _ => panic!("Unsupported lhs and rhs types for binary expr; can only handle integers"),
+        };
+        let type_name = Self::int_type_name(int_type)?;
+        let lhs = self.expr(&bin_expr.left)?;
+        let rhs = self.expr(&bin_expr.right)?;
+
+        let wrapping_type = Self::wrapping_type_name(int_type);
+        Ok(match bin_expr.operator {
+            BinaryOperator::Add => format!("(({})(({}){} + ({}){}))", type_name, wrapping_type, lhs, wrapping_type, rhs),
+            BinaryOperator::Subtract => format!("(({})(({}){} - ({}){}))", type_name, wrapping_type, lhs, wrapping_type, rhs),
+            BinaryOperator::Multiply => format!("(({})(({}){} * ({}){}))", type_name, wrapping_type, lhs, wrapping_type, rhs),
+            BinaryOperator::Divide => format!("(({})({} / {}))", type_name, lhs, rhs),
+            BinaryOperator::Remainder => format!("(({})({} % {}))", type_name, lhs, rhs),
+        })
+    }
+
+    /// Returns the C code for a unary expression.
+    fn unary(&mut self, unary: &TypedUnary) -> Result<String, CompilationError> {
+        let operand = self.expr(&unary.operand)?;
+        let source_type = unary.operand.get_result_type();
+        match (&unary.operator, source_type) {
+            (UnaryOperator::Cast(Type::Int(cast)), Type::Int(source)) => {
+                let cast_name = Self::int_type_name(*cast)?;
+                if cast.width <= source.width {
+                    return Ok(format!("(({}){})", cast_name, operand));
+                }
+                // Like LLVM's sext/zext, widening reads the operand as signed if the type being
+                // cast to is signed (and as unsigned otherwise)
+                let reinterpreted = IntType { signed: cast.signed, width: source.width };
+                Ok(format!("(({})({}){})", cast_name, Self::int_type_name(reinterpreted)?, operand))
+            }
+            (UnaryOperator::Negate, Type::Int(int_type)) => {
+                let wrapping_type = Self::wrapping_type_name(int_type);
+                let type_name = Self::int_type_name(int_type)?;
+                Ok(format!("(({})(({})0 - ({}){}))", type_name, wrapping_type, wrapping_type, operand))
+            }
+            (UnaryOperator::Cast(cast), source) => {
+                panic!("Unsupported cast from {} to {} should've been handled by the typer", source, cast)
+            }
+            (UnaryOperator::Negate, _) => {
+                panic!("Unsupported type for negation, can only handle integers; this should have been handled by typer")
+            }
+        }
+    }
+
+    /// Returns the C code for a function call.
+    fn call(&mut self, call: &TypedCall) -> Result<String, CompilationError> {
+        match Builtin::from_name(&call.function_name) {
+            Some(Builtin::Breakpoint) => {
+                self.uses_breakpoint = true;
+                return Ok("FLICK_BREAKPOINT()".to_string());
+            }
+            Some(Builtin::ArgCount) => return Ok("((uint32_t)flick_argc)".to_string()),
+            Some(Builtin::Arg) => return Ok(format!("flick_arg({})", self.expr(&call.args[0])?)),
+            None => {}
+        }
+        if self.saves_args(&call.function_proto) {
+            // `main` is called again with the arguments that it was first called with
+            return Ok(format!("{}(flick_argc, flick_argv)", call.function_name));
+        }
+
+        let args = call
+            .args
+            .iter()
+            .map(|arg| self.expr(arg))
+            .collect::<Result<Vec<_>, CompilationError>>()?;
+        Ok(format!("{}({})", call.function_name, args.join(", ")))
+    }
+
+    /// Returns `true` if `proto` is the `main` that saves the program's command-line arguments
+    /// for the builtins that read them.
+    fn saves_args(&self, proto: &FuncProto) -> bool {
+        self.reads_args
+            && proto.name == "main"
+            && proto.func_visibility == FuncVisibility::Public
+            && proto.params.is_empty()
+    }
+
+    /// Picks the C name of a new variable, which is its Flick name unless that's already taken
+    /// in the current function (e.g. by a C keyword, a function, or a variable that this one
+    /// shadows), in which case a number is added to it (like `x_1`).
+    fn declare_var(&mut self, name: &str) -> String {
+        let mut c_name = name.to_string();
+        let mut suffix = 0;
+        while self.used_names.contains(&c_name) {
+            suffix += 1;
+            c_name = format!("{}_{}", name, suffix);
+        }
+        self.used_names.insert(c_name.clone());
+        self.scope_manager.set(name, c_name.clone());
+        c_name
+    }
+
+    /// Returns the C name of the variable `name` (see [CBackend::declare_var]).
+    fn var_name(&self, name: &str) -> String {
+        match self.scope_manager.get(name) {
+            Some(c_name) => c_name.clone(),
+            None => panic!("Compiler error: undefined identifier '{}'", name),
+        }
+    }
+
+    /// Returns the C type that corresponds to `t`, like `uint8_t` for `u8`.
+    fn type_name(t: &Type) -> Result<String, CompilationError> {
+        match t {
+            Type::Int(int_type) => Self::int_type_name(*int_type),
+            Type::Bool => Ok("bool".to_string()),
+            Type::Str => Ok("char *".to_string()),
+            Type::Void => Ok("void".to_string()),
+            Type::Func(_) => Err(CompilationError::UnsupportedByBackend {
+                backend: "C",
+                message: format!("function values like '{}' aren't supported", t),
+            }),
+        }
+    }
+
+    /// Returns the `<stdint.h>` type that corresponds to `int_type`, like `int32_t` for `i32`.
+    fn int_type_name(int_type: IntType) -> Result<String, CompilationError> {
+        match int_type.width {
+            8 | 16 | 32 | 64 => match int_type.signed {
+                true => Ok(format!("int{}_t", int_type.width)),
+                false => Ok(format!("uint{}_t", int_type.width)),
+            },
+            _ => Err(CompilationError::UnsupportedByBackend {
+                backend: "C",
+                message: format!("'{}' isn't supported (only 8-, 16-, 32-, and 64-bit integers are)", int_type),
+            }),
+        }
+    }
+
+    /// Returns the unsigned type that arithmetic on `int_type` should be done in, so that it
+    /// wraps around instead of overflowing.
+    fn wrapping_type_name(int_type: IntType) -> &'static str {
+        match int_type.width {
+            0..=32 => "uint32_t",
+            _ => "uint64_t",
+        }
+    }
+}
+
+impl Backend for CBackend {
+    fn compile(&mut self, program: &TypedProgram) -> Result<(), CompilationError> {
+        self.source = self.program(program)?;
+        Ok(())
+    }
+
+    /// Does nothing, since optimization is left to the C compiler.
+    fn optimize(&mut self) -> Result<(), CompilationError> {
+        Ok(())
+    }
+
+    fn print_ir(&self) {
+        eprint!("{}", self.source);
+    }
+
+    fn ir_to_string(&self) -> String {
+        self.source.clone()
+    }
+
+    fn function_ir_to_string(&self, func_name: &str) -> Option<String> {
+        self.func_defs.get(func_name).map(|definition| format!("{}\n", definition))
+    }
+
+    fn to_file(&self, path: &Path) -> Result<(), CompilationError> {
+        std::fs::write(path, &self.source)
+            .map_err(|err| CompilationError::UnwritableOutput { path: Some(path.to_path_buf()), message: err.to_string() })
+    }
+
+    /// Writes the C source code for [EmitKind::Object] (since the C compiler makes the object
+    /// file), and returns an error for any other kind.
+    fn to_file_as(&self, path: &Path, kind: EmitKind) -> Result<(), CompilationError> {
+        let kind = match kind {
+            EmitKind::Object => return self.to_file(path),
+            EmitKind::Assembly => "assembly",
+            EmitKind::Bitcode => "LLVM bitcode",
+        };
+        Err(CompilationError::UnsupportedByBackend {
+            backend: "C",
+            message: format!("it generates C source code, not {}", kind),
+        })
+    }
+
+    fn emit_to_memory(&self) -> Result<Vec<u8>, CompilationError> {
+        Ok(self.source.clone().into_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Lexer, Parser, SourceMap, Typer};
+    use std::process::Command;
+
+    /// Converts `source_code` into C.
+    fn compile_to_c(source_code: &str) -> String {
+        let tokens = Lexer::lex(source_code).unwrap();
+        let typed_program = Typer::new().type_program(&Parser::parse_program(&Arena::new(), &tokens).unwrap()).unwrap();
+
+        let mut backend = CBackend::new();
+        backend.compile(&typed_program).unwrap();
+        backend.source().to_string()
+    }
+
+    /// Converts `source_code` into C, builds it with `gcc`, runs it, and returns its exit code.
+    fn compile_and_run(test_name: &str, source_code: &str) -> i32 {
+        let c_source = compile_to_c(source_code);
+
+        let build_dir = std::env::temp_dir().join(format!("flick-c-{}-{}", test_name, std::process::id()));
+        std::fs::create_dir_all(&build_dir).unwrap();
+        let c_path = build_dir.join("test.c");
+        let executable_path = build_dir.join("test");
+        std::fs::write(&c_path, c_source).unwrap();
+
+        let build_status = Command::new("gcc")
+            .arg("-std=c99")
+            .arg("-Werror")
+            .arg(&c_path)
+            .arg("-o")
+            .arg(&executable_path)
+            .status()
+            .unwrap();
+        assert!(build_status.success());
+
+        let exit_code = Command::new(&executable_path).status().unwrap().code().unwrap();
+        std::fs::remove_dir_all(&build_dir).unwrap();
+        exit_code
+    }
+
+    #[test]
+    fn functions_loops_and_ifs() {
+        let source_code = "
+            fn fib(u64 n) u64 {
+                u64 one = 1
+                if n <= one {
+                    ret n
+                }
+                ret fib(n - 1) + fib(n - 2)
+            }
+
+            pub fn main() u8 {
+                u8 sum = 0
+                u8 i = 0
+                u8 limit = 4
+                while i < limit {
+                    sum += i
+                    i += 1
+                }
+                ret (u8) fib(10) + sum
+            }
+        ";
+        // fib(10) == 55 and 0 + 1 + 2 + 3 == 6
+        assert_eq!(compile_and_run("functions_loops_and_ifs", source_code), 61);
+    }
+
+    #[test]
+    fn arithmetic_wraps_around() {
+        let source_code = "
+            pub fn main() u8 {
+                i32 big = 2147483647
+                i32 one = 1
+                i32 wrapped = big + one
+                i64 widened = (i64) wrapped
+                i64 limit = 0
+                if widened < limit {
+                    u8 x = 250
+                    u8 y = 10
+                    ret x + y
+                }
+                ret 1
+            }
+        ";
+        assert_eq!(compile_and_run("arithmetic_wraps_around", source_code), 4);
+    }
+
+    #[test]
+    fn str_literals_are_escaped() {
+        assert_eq!(CBackend::str_literal("a\"b\\c\n?\0"), r#""a\"b\\c\012\?\000""#);
+    }
+
+    #[test]
+    fn function_source() {
+        let source_code = "
+            fn square(u8 x) u8 {
+                ret x * x
+            }
+
+            pub fn main() u8 {
+                ret square(3)
+            }
+        ";
+        let tokens = Lexer::lex(source_code).unwrap();
+        let typed_program = Typer::new().type_program(&Parser::parse_program(&Arena::new(), &tokens).unwrap()).unwrap();
+
+        let mut backend = CBackend::new();
+        backend.compile(&typed_program).unwrap();
+        let square_source = backend.function_ir_to_string("square").unwrap();
+        assert!(square_source.starts_with("static uint8_t square(uint8_t x) {"));
+        assert!(!square_source.contains("main"));
+        assert_eq!(backend.function_ir_to_string("cube"), None);
+    }
+
+    #[test]
+    fn c_keywords_are_renamed() {
+        let source_code = "
+            pub fn main() u8 {
+                u8 int = 3
+                ret int
+            }
+        ";
+        let c_source = compile_to_c(source_code);
+        assert!(c_source.contains("int main(void)"));
+        assert!(c_source.contains("uint8_t int_1 = ((uint8_t)3);"));
+        assert!(c_source.contains("return int_1;"));
+    }
+
+    #[test]
+    fn raw_identifiers_are_renamed() {
+        let source_code = "
+            pub fn main() u8 {
+                u8 r#while = 3
+                ret r#while
+            }
+        ";
+        let c_source = compile_to_c(source_code);
+        assert!(c_source.contains("uint8_t while_1 = ((uint8_t)3);"));
+        assert!(c_source.contains("return while_1;"));
+    }
+
+    #[test]
+    fn function_named_after_c_keyword_is_an_error() {
+        let source_code = "
+            fn r#if() u8 {
+                ret 1
+            }
+
+            pub fn main() u8 {
+                ret r#if()
+            }
+        ";
+        let tokens = Lexer::lex(source_code).unwrap();
+        let typed_program = Typer::new().type_program(&Parser::parse_program(&Arena::new(), &tokens).unwrap()).unwrap();
+
+        let result = CBackend::new().compile(&typed_program);
+        assert!(matches!(result, Err(CompilationError::UnsupportedByBackend { backend: "C", .. })));
+    }
+
+    #[test]
+    fn breakpoint_raises_sigtrap() {
+        let source_code = "
+            pub fn main() u8 {
+                breakpoint()
+                ret 0
+            }
+        ";
+        let c_source = compile_to_c(source_code);
+        assert!(c_source.contains("#define FLICK_BREAKPOINT() raise(SIGTRAP)"));
+        assert!(c_source.contains("FLICK_BREAKPOINT();"));
+    }
+
+    #[test]
+    fn program_args() {
+        let source_code = "
+            extern fn strlen(str s) u64
+
+            pub fn main() u8 {
+                u64 zero = 0
+                if strlen(arg(0)) == zero {
+                    ret 100
+                }
+                ret (u8) arg_count() + (u8) strlen(arg(7))
+            }
+        ";
+        assert!(compile_to_c(source_code).contains("int main(int argc, char **argv) {"));
+        // The program is run without any arguments, so `argv` is just its name
+        assert_eq!(compile_and_run("program_args", source_code), 1);
+    }
+
+    #[test]
+    fn redeclared_variables() {
+        let source_code = "
+            pub fn main() u8 {
+                u8 a = 2
+                u8 a = a * a
+                if true {
+                    u8 a = a + a
+                    ret a
+                }
+                ret 0
+            }
+        ";
+        assert_eq!(compile_and_run("redeclared_variables", source_code), 8);
+    }
+
+    #[test]
+    fn unsupported_int_width_is_an_error() {
+        let source_code = "
+            pub fn main() u8 {
+                u7 x = 3
+                ret 0
+            }
+        ";
+        let tokens = Lexer::lex(source_code).unwrap();
+        let typed_program = Typer::new().type_program(&Parser::parse_program(&Arena::new(), &tokens).unwrap()).unwrap();
+
+        let result = CBackend::new().compile(&typed_program);
+        assert!(matches!(result, Err(CompilationError::UnsupportedByBackend { backend: "C", .. })));
+    }
+
+    #[test]
+    fn only_c_source_can_be_emitted() {
+        let backend = CBackend::new();
+        let path = std::env::temp_dir().join(format!("flick-c-emit-{}.s", std::process::id()));
+        let result = backend.to_file_as(&path, EmitKind::Assembly);
+        assert!(matches!(result, Err(CompilationError::UnsupportedByBackend { backend: "C", .. })));
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn unwritable_output() {
+        let backend = CBackend::new();
+        let path = std::env::temp_dir().join(format!("flick-c-missing-{}", std::process::id())).join("main.c");
+        for result in [backend.to_file(&path), backend.to_file_as(&path, EmitKind::Object)] {
+            assert!(matches!(result, Err(CompilationError::UnwritableOutput { path: Some(ref p), .. }) if *p == path));
+        }
+    }
+
+    #[test]
+    fn coverage() {
+        let source_code = "extern fn puts(str s) i32\n\npub fn main() u8 {\n    if arg_count() > (u32) 5 {\n        puts(\"many\")\n    }\n    ret 0\n}\n"
+            .to_string();
+        let (tokens, spans) = Lexer::lex_with_spans(&source_code).unwrap();
+        let arena = Arena::new();
+        let program = Parser::parse_program_with_spans(&arena, &tokens, &spans).unwrap();
+        let mut typed_program = Typer::new().type_program(&program).unwrap();
+        let build_dir = std::env::temp_dir().join(format!("flick-c-coverage-{}", std::process::id()));
+        std::fs::create_dir_all(&build_dir).unwrap();
+        let profile_path = build_dir.join("test.flcov");
+        let sources = SourceMap::single_file("100%.fl", source_code.clone());
+        crate::coverage::instrument(&program, &mut typed_program, &sources, profile_path.to_str().unwrap());
+
+        let mut backend = CBackend::new();
+        backend.compile(&typed_program).unwrap();
+        assert!(backend.source().contains("    atexit(flick_coverage_write);\n    flick_argc = argc;"));
+        assert!(backend.source().contains("        flick_coverage[1] += 1;\n        puts(\"many\");"));
+
+        let c_path = build_dir.join("test.c");
+        let executable_path = build_dir.join("test");
+        std::fs::write(&c_path, backend.source()).unwrap();
+        let build_status = Command::new("gcc")
+            .args(["-std=c99", "-Werror"])
+            .arg(&c_path)
+            .arg("-o")
+            .arg(&executable_path)
+            .status()
+            .unwrap();
+        assert!(build_status.success());
+        assert_eq!(Command::new(&executable_path).status().unwrap().code(), Some(0));
+
+        let profile = std::fs::read_to_string(&profile_path).unwrap();
+        std::fs::remove_dir_all(&build_dir).unwrap();
+        assert_eq!(profile, "100%.fl:4:5 1\n100%.fl:5:9 0\n100%.fl:7:5 1\n");
+    }
+}
@@ -1,290 +1,572 @@
-use llvm_sys::analysis::LLVMVerifierFailureAction::LLVMPrintMessageAction;
-use llvm_sys::analysis::LLVMVerifyFunction;
-use std::ffi::{c_char, c_uint, CStr, CString};
-use std::mem::MaybeUninit;
+use std::collections::HashMap;
 use std::path::Path;
 
-use llvm_sys::core::*;
+use llvm_sys::target_machine::LLVMCodeGenFileType::{LLVMAssemblyFile, LLVMObjectFile};
 use llvm_sys::LLVMIntPredicate;
-use llvm_sys::error::LLVMGetErrorMessage;
-use llvm_sys::prelude::*;
-use llvm_sys::target::{
-    LLVMSetModuleDataLayout, LLVM_InitializeNativeAsmParser, LLVM_InitializeNativeAsmPrinter,
-    LLVM_InitializeNativeTarget,
-};
-use llvm_sys::target_machine::LLVMCodeGenFileType::LLVMObjectFile;
-use llvm_sys::target_machine::LLVMCodeGenOptLevel::LLVMCodeGenLevelDefault;
-use llvm_sys::target_machine::LLVMCodeModel::LLVMCodeModelDefault;
-use llvm_sys::target_machine::LLVMRelocMode::LLVMRelocDefault;
-use llvm_sys::target_machine::{
-    LLVMCreateTargetDataLayout, LLVMCreateTargetMachine, LLVMDisposeTargetMachine,
-    LLVMGetDefaultTargetTriple, LLVMGetTargetFromTriple, LLVMTarget, LLVMTargetMachineEmitToFile,
-    LLVMTargetMachineRef,
-};
-use llvm_sys::transforms::pass_builder::*;
 use llvm_sys::LLVMLinkage::{LLVMExternalLinkage, LLVMInternalLinkage};
 
 use crate::ast::*;
+use crate::builtins::Builtin;
+use crate::compilation::backend::Backend;
+use crate::compilation::error::CompilationError;
+use crate::compilation::llvm::{
+    self, BasicBlock, Builder, Context, DIBuilder, ExecutionEngine, LlvmType, Metadata, Module,
+    PassBuilderOptions, TargetMachine, Value,
+};
+use crate::compilation::options::{CompilerOptions, EmitKind, OptLevel};
 use crate::typed_ast::*;
 use crate::types::{Type, IntType};
-use crate::ScopeManager;
-
-/// Converts a `&str`, like `"hi"`, into a pointer to a null-terminated C-style str.
-macro_rules! cstr {
-    ($str_literal:expr) => {
-        concat!($str_literal, "\0").as_ptr() as *const _
-    };
-}
+use crate::{Name, ScopeManager};
 
 /// A struct that takes an [abstract syntax tree][a] and converts it into LLVM code.
 ///
 /// # Example usage
 ///
 /// ```
-/// # use flick::{typed_ast, Compiler};
-/// let mut compiler = Compiler::new();
+/// # use flick::{typed_ast, Compiler, CompilerOptions};
+/// let mut compiler = Compiler::new(CompilerOptions::default()).unwrap();
 /// let syntax_tree = typed_ast::TypedProgram {
 ///     // generated during parsing
-///     # global_statements: vec![]
+///     # global_statements: vec![],
+///     # coverage: None,
 /// };
-/// compiler.compile(&syntax_tree);
-/// compiler.optimize();
+/// compiler.compile(&syntax_tree).expect("generated IR should be valid");
+/// compiler.optimize().unwrap();
 /// compiler.print_ir();  // or compiler.to_file("out")
 /// ```
 ///
 /// [a]: crate::parsing::ast
 pub struct Compiler {
-    context: LLVMContextRef,
-    module: LLVMModuleRef,
-    builder: LLVMBuilderRef,
-    target_machine: LLVMTargetMachineRef,
-    scope_manager: ScopeManager<LLVMValueRef>,
-    pass_builder: LLVMPassBuilderOptionsRef,
+    // Note: fields are dropped in declaration order, and the module must be dropped before the
+    // context that it lives in
+    module: Module,
+    target_machine: TargetMachine,
+    pass_builder: PassBuilderOptions,
+    context: Context,
+    options: CompilerOptions,
+}
+
+/// The state needed while generating the code for a program (see [Compiler::compile]).
+///
+/// Every LLVM handle here borrows the [Compiler]'s context, so none of them can outlive it.
+struct CodeGen<'ctx> {
+    context: &'ctx Context,
+    module: &'ctx Module,
+    options: &'ctx CompilerOptions,
+    builder: Builder<'ctx>,
+    scope_manager: ScopeManager<Value<'ctx>>,
+    /// The global for each distinct string literal in the module, keyed by its contents, so
+    /// that identical literals share a single global.
+    str_literals: HashMap<String, Value<'ctx>>,
+    /// Where self-tail-calls in the function currently being compiled jump to (if it has any).
+    tail_call_target: Option<TailCallTarget<'ctx>>,
+    /// The debug info being built for the module, if [CompilerOptions::debug_info] is set.
+    debug_info: Option<DebugInfo<'ctx>>,
+    /// Where `main` saves the program's command-line arguments, if the program reads them (see
+    /// [Builtin::reads_args]).
+    program_args: Option<ProgramArgs<'ctx>>,
+    /// The coverage counters, if the program has been [instrumented](crate::coverage::instrument)
+    coverage: Option<CoverageCounters<'ctx>>,
+}
+
+/// The globals that `main` saves its `argc` and `argv` parameters in, so that the builtins that
+/// read command-line arguments can get at them from any function.
+///
+/// On WebAssembly, `main` isn't given any arguments, so these stay zero (and null).
+struct ProgramArgs<'ctx> {
+    /// An `i32`, like C's `argc`
+    count: Value<'ctx>,
+    /// A pointer to the first of `count` strings, like C's `argv`
+    values: Value<'ctx>,
+}
+
+/// The DWARF debug info for a module.
+///
+/// For now, this describes just the compile unit and its functions: the AST doesn't know where
+/// in the source code each function is, so all locations are on line 0.
+struct DebugInfo<'ctx> {
+    builder: DIBuilder<'ctx>,
+    file: Metadata<'ctx>,
+    compile_unit: Metadata<'ctx>,
+}
+
+impl<'ctx> DebugInfo<'ctx> {
+    /// Creates the compile unit for `module`.
+    fn new(module: &'ctx Module, options: &CompilerOptions) -> Self {
+        let builder = DIBuilder::new(module);
+        let file_name = options.source_file_name.as_deref().unwrap_or("<unknown>");
+        let file = builder.create_file(file_name, "");
+        let producer = concat!("flick ", env!("CARGO_PKG_VERSION"));
+        let compile_unit = builder.create_compile_unit(file, producer, options.opt_level != OptLevel::O0);
+        Self { builder, file, compile_unit }
+    }
+
+    /// Describes the function `func` (defined by `func_proto`) in the debug info, returning the
+    /// location that its instructions should have.
+    fn add_function(&self, context: &'ctx Context, func: Value<'ctx>, func_proto: &FuncProto) -> Metadata<'ctx> {
+        let subroutine_type = self.builder.create_untyped_subroutine_type(self.file);
+        let is_local = func_proto.func_visibility == FuncVisibility::Private;
+        let subprogram = self.builder.create_function(
+            self.compile_unit,
+            &func_proto.name,
+            self.file,
+            0,
+            subroutine_type,
+            is_local,
+        );
+        func.set_subprogram(subprogram);
+        context.debug_location(0, 0, subprogram)
+    }
+}
+
+/// The globals and functions of a program that's been [instrumented](crate::coverage::instrument)
+/// for coverage.
+struct CoverageCounters<'ctx> {
+    /// A pointer to the first of the counters (an array of `i64`s)
+    counters: Value<'ctx>,
+    /// The function that writes the counters to the profile, which `main` registers with C's
+    /// `atexit`, so that it runs however the program exits
+    write: Value<'ctx>,
+}
+
+/// The loop that self-tail-calls in a function are lowered into.
+///
+/// Instead of calling itself, `ret foo(a, b)` inside `foo` stores `a` and `b` into the
+/// parameters' allocas and branches back to `loop_block`, which is right after the parameters
+/// are first stored. This keeps deep (tail) recursion from growing the stack, even at `-O0`.
+struct TailCallTarget<'ctx> {
+    func_name: Name,
+    loop_block: BasicBlock<'ctx>,
+    param_allocas: Vec<Value<'ctx>>,
 }
 
 impl Compiler {
-    /// Creates a new instance, setting up relevant llvm-sys boilerplate.
-    pub fn new() -> Self {
-        unsafe {
-            let context = LLVMContextCreate();
-            let module = LLVMModuleCreateWithNameInContext(cstr!("module"), context);
-            let builder = LLVMCreateBuilderInContext(context);
-            let scope_manager = ScopeManager::new();
-
-            // TODO: make this an option for the compiler to choose which target to initialize
-            if LLVM_InitializeNativeTarget() == 1 {
-                panic!("Error initializing native target")
-            }
-            if LLVM_InitializeNativeAsmParser() == 1 {
-                panic!("Error initializing native ASM Parser")
-            }
-            if LLVM_InitializeNativeAsmPrinter() == 1 {
-                panic!("Error initializing native ASM printer")
-            }
-
-            // Configure module
-            let triple = LLVMGetDefaultTargetTriple(); // this computer's OS triple
-            LLVMSetTarget(module, triple);
-
-            let cpu = cstr!("generic");
-            let features = cstr!("");
-            let target = Self::get_target_from_triple(triple);
-            let target_machine = LLVMCreateTargetMachine(
-                target,
-                triple,
-                cpu,
-                features,
-                LLVMCodeGenLevelDefault,
-                LLVMRelocDefault,
-                LLVMCodeModelDefault,
-            );
+    /// Creates a new instance configured by `options`, setting up relevant llvm-sys boilerplate.
+    ///
+    /// Returns an `Err()` if the target can't be set up, e.g. if the target triple is unknown.
+    pub fn new(options: CompilerOptions) -> Result<Self, CompilationError> {
+        // Set up the target first, so that nothing needs to be cleaned up if it fails
+        let triple = match &options.target_triple {
+            Some(triple) if options.targets_wasm() => {
+                llvm::initialize_wasm_target();
+                triple.clone()
+            }
+            Some(triple) => {
+                llvm::initialize_all_targets();
+                triple.clone()
+            }
+            None => {
+                llvm::initialize_native_target().map_err(CompilationError::TargetInitialization)?;
+                llvm::default_target_triple() // this computer's OS triple
+            }
+        };
 
-            let target_data_layout = LLVMCreateTargetDataLayout(target_machine);
-            LLVMSetModuleDataLayout(module, target_data_layout);
+        let (cpu, features) = Self::get_cpu_and_features(&options);
+        tracing::debug!(%triple, %cpu, %features, "creating target machine");
+        let target_machine = TargetMachine::new(
+            &triple,
+            &cpu,
+            &features,
+            options.opt_level.codegen_opt_level(),
+            options.effective_reloc_model().llvm_reloc_mode(),
+        )
+        .map_err(|message| match message {
+            Some(message) => CompilationError::UnknownTarget { triple: triple.clone(), message },
+            None => CompilationError::TargetMachineCreation { triple: triple.clone() },
+        })?;
 
-            // Configure pass manager
-            let pass_builder = LLVMCreatePassBuilderOptions();
+        if options.time_passes {
+            llvm::enable_pass_timing();
+        }
 
-            Self {
-                context,
-                module,
-                builder,
-                target_machine,
-                scope_manager,
-                pass_builder,
+        let context = Context::new();
+        context.set_discard_value_names(!options.readable_ir);
+        // The module (and source file) name is fixed, rather than the input path, so that
+        // the output doesn't depend on where the source code lives
+        let module = Module::new("module", &context);
+        module.set_target(&target_machine);
+        if options.debug_info {
+            module.add_int_flag(&context, "Debug Info Version", llvm::debug_metadata_version());
+            if options.targets_msvc() {
+                module.add_int_flag(&context, "CodeView", 1);
             }
         }
+        Self::add_toolchain_metadata(&context, &module, &options);
+
+        let pass_builder = PassBuilderOptions::new();
+        let vectorize = options.opt_level.vectorizes() && !options.disable_vectorization;
+        pass_builder.set_loop_vectorization(vectorize);
+        pass_builder.set_slp_vectorization(vectorize);
+        pass_builder.set_loop_unrolling(!options.disable_loop_unrolling);
+
+        Ok(Self {
+            module,
+            target_machine,
+            pass_builder,
+            context,
+            options,
+        })
     }
 
-    /// Converts a string like `x86_64-unknown-freebsd` into the corresponding [LLVMTarget].
-    unsafe fn get_target_from_triple(triple: *const c_char) -> *mut LLVMTarget {
-        let mut target = std::ptr::null_mut();
-        let mut err_str = MaybeUninit::uninit();
-        if LLVMGetTargetFromTriple(triple, &mut target, err_str.as_mut_ptr()) != 0 {
-            panic!(
-                "Error getting target from triple ({:?})",
-                err_str.assume_init()
-            );
+    /// Records which toolchain built the module, so that objects can be traced back to it: the
+    /// compiler version goes into `llvm.ident` (the `.comment` section of ELF objects), and the
+    /// options go into `llvm.commandline` (the `.GCC.command.line` section).
+    ///
+    /// Note: only the source file's name is recorded (not its path), so the output doesn't
+    /// depend on where the source code lives.
+    fn add_toolchain_metadata(context: &Context, module: &Module, options: &CompilerOptions) {
+        if let Some(source_file_name) = &options.source_file_name {
+            module.set_source_file_name(source_file_name);
+        }
+        let ident = concat!("flick version ", env!("CARGO_PKG_VERSION"));
+        module.add_named_metadata_string(context, "llvm.ident", ident);
+        let command_line = format!("flick {}", options.command_line_flags());
+        module.add_named_metadata_string(context, "llvm.commandline", &command_line);
+    }
+
+    /// Returns the CPU name and the feature string to create the target machine with.
+    ///
+    /// If the CPU is `native`, then the host's CPU name is used, and so are the host's features
+    /// (unless features are given explicitly).
+    fn get_cpu_and_features(options: &CompilerOptions) -> (String, String) {
+        let cpu = options.target_cpu.as_deref().unwrap_or("generic");
+        let features = options.target_features.as_deref();
+
+        match (cpu, features) {
+            ("native", Some(features)) => (llvm::host_cpu_name(), features.to_string()),
+            ("native", None) => (llvm::host_cpu_name(), llvm::host_cpu_features()),
+            (cpu, features) => (cpu.to_string(), features.unwrap_or("").to_string()),
         }
-        target
     }
 
     /// This function prints the LLVM IR generated so far (via methods like [compile][a]).
     ///
     /// [a]: Compiler::compile
     pub fn print_ir(&self) {
-        unsafe { LLVMDumpModule(self.module) }
+        self.module.dump()
     }
 
-    /// This function optimizes the LLVM IR generated so far (via methods like [compile][a]).
+    /// Like [print_ir][a], but returns the LLVM IR as a string instead of printing it.
+    ///
+    /// [a]: Compiler::print_ir
+    pub fn ir_to_string(&self) -> String {
+        self.module.print_to_string()
+    }
+
+    /// Like [ir_to_string][a], but returns just the LLVM IR of the function named `func_name`,
+    /// or `None` if the module has no such function (optimization may remove private functions
+    /// once they've been inlined everywhere).
+    ///
+    /// [a]: Compiler::ir_to_string
+    pub fn function_ir_to_string(&self, func_name: &str) -> Option<String> {
+        self.module.get_function(func_name).map(|func| func.print_to_string())
+    }
+
+    /// This function optimizes the LLVM IR generated so far (via methods like [compile][a]),
+    /// running the pass pipeline that corresponds to the configured [OptLevel](crate::OptLevel)
+    /// (or the exact pipeline in [CompilerOptions::passes], if there is one).
+    ///
+    /// Returns an `Err()` if LLVM rejects the pipeline, e.g. because it names an unknown pass.
     ///
     /// [a]: Compiler::compile
-    pub fn optimize(&mut self) {
-        unsafe {
-            let passes = cstr!("default<O1>");
-            let res = LLVMRunPasses(self.module, passes, self.target_machine, self.pass_builder);
-            if !res.is_null() {
-                let error_string = CStr::from_ptr(LLVMGetErrorMessage(res));
-                panic!("Error running optimizations: {:?}", error_string);
-            }
-        }
+    pub fn optimize(&mut self) -> Result<(), CompilationError> {
+        let passes = match (&self.options.passes, self.options.lto) {
+            (Some(passes), _) => passes.as_str(),
+            (None, true) => self.options.opt_level.lto_pre_link_pass_pipeline(),
+            (None, false) => self.options.opt_level.pass_pipeline(),
+        };
+        tracing::debug!(%passes, "running LLVM passes");
+        self.pass_builder
+            .run_passes(&self.module, passes, &self.target_machine)
+            .map_err(|message| CompilationError::InvalidPassPipeline { passes: passes.to_string(), message })
     }
 
     /// This function dumps to a file the LLVM IR generated so far (via methods like [compile][a]).
     ///
+    /// The kind of file (object, assembly, or bitcode) is decided by [CompilerOptions::emit_kind].
+    /// If the file can't be written (e.g. because its directory doesn't exist), a
+    /// [CompilationError::UnwritableOutput](crate::CompilationError::UnwritableOutput) is returned.
+    ///
     /// [a]: Compiler::compile
-    pub fn to_file(&self, path: &impl AsRef<Path>) {
-        unsafe {
-            let mut path_cchars: Vec<_> = path
-                .as_ref()
-                .to_string_lossy()
-                .chars()
-                .map(|b| b as c_char)
-                .collect();
-            path_cchars.push(0); // Null character
-
-            let mut err_str = MaybeUninit::uninit();
-            let result = LLVMTargetMachineEmitToFile(
-                self.target_machine,
-                self.module,
-                path_cchars.as_mut_ptr(),
-                LLVMObjectFile,
-                err_str.as_mut_ptr(),
-            );
+    pub fn to_file(&self, path: &impl AsRef<Path>) -> Result<(), CompilationError> {
+        self.to_file_as(path, self.options.emit_kind)
+    }
 
-            if result == 1 {
-                // TODO: Actually print error
-                panic!("Error emitting object file ({:?})", err_str.assume_init());
+    /// Like [to_file][a], but writes `kind` of file, whatever [CompilerOptions::emit_kind] says
+    /// (e.g. to write both assembly and an object file).
+    ///
+    /// [a]: Compiler::to_file
+    pub fn to_file_as(&self, path: &impl AsRef<Path>, kind: EmitKind) -> Result<(), CompilationError> {
+        let path = path.as_ref();
+        tracing::debug!(path = %path.display(), ?kind, "writing module");
+        let unwritable = |message| CompilationError::UnwritableOutput { path: Some(path.to_path_buf()), message };
+        let file_type = match kind {
+            EmitKind::Object => LLVMObjectFile,
+            EmitKind::Assembly => LLVMAssemblyFile,
+            // LLVM doesn't say why writing bitcode to a file failed, so it's written by Rust instead
+            EmitKind::Bitcode => {
+                return std::fs::write(path, self.module.write_bitcode_to_memory())
+                    .map_err(|err| unwritable(err.to_string()));
             }
-        }
+        };
+        self.target_machine.emit_to_file(&self.module, path, file_type).map_err(unwritable)
+    }
+
+    /// Like [to_file][a], but returns the file's contents instead of writing them to disk (e.g.
+    /// for embedders that load or link the object themselves).
+    ///
+    /// [a]: Compiler::to_file
+    pub fn emit_to_memory(&self) -> Result<Vec<u8>, CompilationError> {
+        self.emit_to_memory_as(self.options.emit_kind)
+    }
+
+    /// Like [to_file_as][a], but returns the file's contents instead of writing them to disk.
+    ///
+    /// [a]: Compiler::to_file_as
+    pub fn emit_to_memory_as(&self, kind: EmitKind) -> Result<Vec<u8>, CompilationError> {
+        tracing::debug!(?kind, "emitting module to memory");
+        let file_type = match kind {
+            EmitKind::Object => LLVMObjectFile,
+            EmitKind::Assembly => LLVMAssemblyFile,
+            EmitKind::Bitcode => return Ok(self.module.write_bitcode_to_memory()),
+        };
+        self.target_machine
+            .emit_to_memory(&self.module, file_type)
+            .map_err(|message| CompilationError::UnwritableOutput { path: None, message })
+    }
+
+    /// Generates machine code in memory for the code compiled so far, returning LLVM's JIT
+    /// compiler (which the functions can be looked up in) and the context that it has to be
+    /// dropped before (see [Engine](crate::Engine)).
+    pub(crate) fn into_execution_engine(self) -> Result<(ExecutionEngine, Context), CompilationError> {
+        let Self { module, context, options, .. } = self;
+        let execution_engine = ExecutionEngine::new(module, options.opt_level.codegen_opt_level())
+            .map_err(CompilationError::ExecutionEngineCreation)?;
+        Ok((execution_engine, context))
     }
 
     /// This function compiles the provided program; once compiled, its LLVM IR can be [optimized][a],
     /// [printed][b], or [written to a file][c].
     ///
-    /// The general idea is that `LLVMValueRef` instances are created recursively for various expressions,
+    /// The general idea is that LLVM values are created recursively for various expressions,
     /// function calls, and function definitions. See implementation for details.
     ///
+    /// Compilation happens in two passes over the global statements:
+    /// 1. Every function signature is declared, so that functions can call functions defined
+    ///    later in the file (or each other).
+    /// 2. Every function body is compiled.
+    ///
+    /// Afterwards, the whole module is run through LLVM's verifier. If the generated IR is
+    /// broken, a [CompilationError::InvalidModule](crate::CompilationError::InvalidModule) with
+    /// the verifier's message is returned (instead of going on to emit garbage).
+    ///
     /// [a]: Compiler::optimize
     /// [b]: Compiler::print_ir
     /// [c]: Compiler::to_file
-    pub fn compile(&mut self, program: &TypedProgram) {
-        unsafe {
-            self.scope_manager.enter_scope();
-            for global_statement in program.global_statements.iter() {
-                // TODO: In the future when we have additional global statements, maybe move this into a new function called 'preprocess_global_statement' or something like that
-                match global_statement {
-                    TypedGlobalStatement::Extern(p) => self.compile_func_proto(p),
-                    TypedGlobalStatement::FuncDef(f) => self.compile_func_proto(&f.proto)
-                }
-            }
-            for global_statement in program.global_statements.iter() {
-                self.compile_global_statement(global_statement);
-            }
-            self.scope_manager.exit_scope()
+    pub fn compile(&mut self, program: &TypedProgram) -> Result<(), CompilationError> {
+        if program.coverage.is_some() && self.options.targets_wasm() {
+            let message = "WebAssembly modules can't be instrumented for coverage, since they can't write files";
+            return Err(CompilationError::UnsupportedByBackend { backend: "LLVM", message: message.to_string() });
         }
+        let mut codegen = CodeGen::new(&self.context, &self.module, &self.options);
+        codegen.compile_program(program);
+        // Dropping the code generator finalizes the debug info, which has to happen first
+        drop(codegen);
+        self.module.verify().map_err(CompilationError::InvalidModule)
+    }
+}
+
+impl Backend for Compiler {
+    fn compile(&mut self, program: &TypedProgram) -> Result<(), CompilationError> {
+        Compiler::compile(self, program)
     }
 
-    /// Compiles a function prototype (or a )
-    unsafe fn compile_global_statement(&mut self, global_statement: &TypedGlobalStatement) {
+    fn optimize(&mut self) -> Result<(), CompilationError> {
+        Compiler::optimize(self)
+    }
+
+    fn print_ir(&self) {
+        Compiler::print_ir(self)
+    }
+
+    fn ir_to_string(&self) -> String {
+        Compiler::ir_to_string(self)
+    }
+
+    fn function_ir_to_string(&self, func_name: &str) -> Option<String> {
+        Compiler::function_ir_to_string(self, func_name)
+    }
+
+    fn to_file(&self, path: &Path) -> Result<(), CompilationError> {
+        Compiler::to_file(self, &path)
+    }
+
+    fn to_file_as(&self, path: &Path, kind: EmitKind) -> Result<(), CompilationError> {
+        Compiler::to_file_as(self, &path, kind)
+    }
+
+    fn emit_to_memory(&self) -> Result<Vec<u8>, CompilationError> {
+        Compiler::emit_to_memory(self)
+    }
+}
+
+impl<'ctx> CodeGen<'ctx> {
+    fn new(context: &'ctx Context, module: &'ctx Module, options: &'ctx CompilerOptions) -> Self {
+        Self {
+            context,
+            module,
+            options,
+            builder: Builder::new(context),
+            scope_manager: ScopeManager::new(),
+            str_literals: HashMap::new(),
+            tail_call_target: None,
+            debug_info: options.debug_info.then(|| DebugInfo::new(module, options)),
+            program_args: None,
+            coverage: None,
+        }
+    }
+
+    /// Compiles every global statement in `program`, in the two passes described in
+    /// [Compiler::compile].
+    fn compile_program(&mut self, program: &TypedProgram) {
+        if Builtin::ALL.iter().any(|builtin| builtin.reads_args() && program.calls(builtin.name())) {
+            let count = self.module.add_global(self.context.int_type(32), "flick.argc");
+            count.make_internal_zeroed(self.context.int_type(32));
+            let values = self.module.add_global(self.argv_type(), "flick.argv");
+            values.make_internal_zeroed(self.argv_type());
+            self.program_args = Some(ProgramArgs { count, values });
+        }
+        if let Some(coverage) = &program.coverage {
+            self.coverage = Some(self.add_coverage_counters(coverage));
+        }
+
+        self.scope_manager.enter_scope();
+        for global_statement in program.global_statements.iter() {
+            self.declare_global_statement(global_statement);
+        }
+        for global_statement in program.global_statements.iter() {
+            self.compile_global_statement(global_statement);
+        }
+        self.scope_manager.exit_scope();
+    }
+
+    /// Declares the signature of a global statement (first pass of [Compiler::compile]).
+    fn declare_global_statement(&mut self, global_statement: &TypedGlobalStatement) {
+        match global_statement {
+            TypedGlobalStatement::Extern(p) => self.compile_func_proto(p),
+            TypedGlobalStatement::FuncDef(f) => self.compile_func_proto(&f.proto),
+        }
+    }
+
+    /// Compiles the body of a global statement, assuming its signature has already been
+    /// declared (second pass of [Compiler::compile]).
+    fn compile_global_statement(&mut self, global_statement: &TypedGlobalStatement) {
         if let TypedGlobalStatement::FuncDef(func_def) = global_statement {
             self.compile_func_def(func_def);
         }
     }
 
     /// Registers a function prototype, panicking if the function has already been defined.
-    unsafe fn compile_func_proto(&mut self, func_proto: &FuncProto) {
-        if self.scope_manager.get(&func_proto.name).is_some() {
+    fn compile_func_proto(&mut self, func_proto: &FuncProto) {
+        if self.scope_manager.get(func_proto.name).is_some() {
             panic!("Cannot redefine '{}'", func_proto.name);
         }
 
-        let func_type = Type::Func(func_proto.clone());
-        let func_llvm_type = self.to_llvm_type(&func_type);
-        let func_name = CString::new(func_proto.name.as_str()).unwrap();
-        let func = LLVMAddFunction(self.module, func_name.as_ptr(), func_llvm_type);
-
-        if LLVMIsNull(func) == 1 {
-            panic!("Error defining function '{}'", func_proto.name);
-        }
+        let func_llvm_type = self.func_llvm_type(func_proto);
+        let func = self.module.add_function(&func_proto.name, func_llvm_type);
 
         for (i, param) in func_proto.params.iter().enumerate() {
-            let param_value_ref = LLVMGetParam(func, i as c_uint);
-            let param_name = CString::new(param.param_name.as_str()).unwrap();
-            let param_name_len = param.param_name.len();
-            LLVMSetValueName2(param_value_ref, param_name.as_ptr(), param_name_len);
+            func.param(i).set_name(&param.param_name);
         }
 
         match func_proto.func_visibility {
-            FuncVisibility::Public => LLVMSetLinkage(func, LLVMExternalLinkage),
-            FuncVisibility::Private => LLVMSetLinkage(func, LLVMInternalLinkage),
-            FuncVisibility::Extern => LLVMSetLinkage(func, LLVMExternalLinkage),
+            FuncVisibility::Public => func.set_linkage(LLVMExternalLinkage),
+            FuncVisibility::Private => func.set_linkage(LLVMInternalLinkage),
+            FuncVisibility::Extern => func.set_linkage(LLVMExternalLinkage),
         }
 
-        // TODO: Design: Remove variable shadowing
-        //  By that, do you think we meant allow variables to shadow functions inside a scope?
-        //  If so, I think we do that...? Not sure.
+        if self.options.targets_wasm() && func_proto.func_visibility == FuncVisibility::Public {
+            self.context.add_function_string_attribute(func, "wasm-export-name", &func_proto.name);
+        }
+
+        // Functions are in the global scope, so a variable with the same name only shadows one
+        // until the end of the variable's scope
         let func_name = &func_proto.name;
         self.scope_manager.set(func_name, func);
     }
 
     /// Complies a function definition, assuming the function's prototype has been compiled.
-    unsafe fn compile_func_def(&mut self, func_def: &TypedFuncDef) {
-        let func_name = CString::new(func_def.proto.name.as_str()).unwrap();
-        let func = LLVMGetNamedFunction(self.module, func_name.as_ptr());
-        if LLVMIsNull(func) == 1 {
-            panic!(
+    fn compile_func_def(&mut self, func_def: &TypedFuncDef) {
+        tracing::debug!(function = %func_def.proto.name, "generating LLVM IR");
+        let func = match self.module.get_function(&func_def.proto.name) {
+            Some(func) => func,
+            None => panic!(
                 "The prototype for function '{}' has not been defined",
                 func_def.proto.name
-            );
+            ),
+        };
+
+        for attribute in self.options.opt_level.size_attributes() {
+            self.context.add_function_enum_attribute(func, attribute);
+        }
+
+        let entry_block = self.context.append_basic_block(func, "entry");
+        self.builder.position_at_end(entry_block);
+
+        if let Some(debug_info) = &self.debug_info {
+            let location = debug_info.add_function(self.context, func, &func_def.proto);
+            self.builder.set_debug_location(Some(location));
         }
 
-        let entry_block = LLVMAppendBasicBlockInContext(self.context, func, cstr!("entry"));
-        LLVMPositionBuilderAtEnd(self.builder, entry_block);
+        if self.saves_program_args(&func_def.proto) {
+            let program_args = self.program_args.as_ref().unwrap();
+            self.builder.store(func.param(0), program_args.count);
+            self.builder.store(func.param(1), program_args.values);
+        }
+
+        if let Some(coverage) = &self.coverage {
+            if func_def.proto.name == "main" && func_def.proto.func_visibility == FuncVisibility::Public {
+                // A function's type is already a pointer to it
+                let atexit_type = self.context.function_type(self.context.int_type(32), &[coverage.write.type_of()]);
+                let atexit = self.libc_function("atexit", atexit_type);
+                self.builder.call(atexit_type, atexit, &[coverage.write], "");
+            }
+        }
 
         self.scope_manager.enter_scope();
 
+        let mut param_allocas = Vec::with_capacity(func_def.proto.params.len());
         for (i, param) in func_def.proto.params.iter().enumerate() {
             let param_name = param.param_name.as_str();
             let param_type = &param.param_type;
-            let param_value_ref = LLVMGetParam(func, i as c_uint);
-            let alloca = self.create_alloca(param_name, param_type);
+            let alloca = self.create_alloca(&format!("{}.addr", param_name), param_type);
 
-            LLVMBuildStore(self.builder, param_value_ref, alloca);
+            self.builder.store(func.param(i), alloca);
             self.scope_manager.set(param_name, alloca);
+            param_allocas.push(alloca);
+        }
+
+        let func_name = &func_def.proto.name;
+        if func_def.body.iter().any(|stmt| stmt.contains_tail_call_to(func_name)) {
+            let loop_block = self.context.append_basic_block(func, "tailrecurse");
+            self.builder.br(loop_block);
+            self.builder.position_at_end(loop_block);
+            self.tail_call_target = Some(TailCallTarget {
+                func_name: *func_name,
+                loop_block,
+                param_allocas,
+            });
         }
 
         self.compile_body(&func_def.body);
 
+        self.tail_call_target = None;
         self.scope_manager.exit_scope();
-
-        // TODO: Convert LLVM into Flick error by using LLVMReturnStatusAction
-        if LLVMVerifyFunction(func, LLVMPrintMessageAction) == 1 {
-            panic!("Function '{}' is not valid", func_def.proto.name);
-        }
+        self.builder.set_debug_location(None);
     }
 
     /// Compiles a statement, assuming the LLVM builder is building inside a function body.
-    unsafe fn compile_statement(&mut self, statement: &TypedStatement) {
+    fn compile_statement(&mut self, statement: &TypedStatement) {
         match statement {
             TypedStatement::VarDeclaration(v) => self.compile_var_declaration(v),
             TypedStatement::WhileLoop(w) => self.compile_while_loop(w),
@@ -292,165 +574,219 @@ impl Compiler {
             TypedStatement::Return(r) => self.compile_ret_statement(r),
             TypedStatement::Call(c) => _ = self.compile_call(c),
             TypedStatement::If(i) => self.compile_if_statement(i),
-            TypedStatement::Unreachable => self.compile_unreachable(),
+            TypedStatement::Count(index) => self.compile_count(*index),
+            TypedStatement::Unreachable => self.builder.unreachable(),
         }
     }
 
+    /// Compiles adding one to the coverage counter at `index`.
+    fn compile_count(&mut self, index: usize) {
+        let coverage = self.coverage.as_ref().expect("only instrumented programs have counters");
+        let counter_type = self.context.int_type(64);
+        let index = self.context.const_int(counter_type, index as u64);
+        let counter = self.builder.element_pointer(counter_type, coverage.counters, index, "counter");
+        let count = self.builder.load(counter_type, counter, "count");
+        let one = self.context.const_int(counter_type, 1);
+        self.builder.store(self.builder.add(count, one, "count"), counter);
+    }
+
     /// Compiles a variable declaration.
-    unsafe fn compile_var_declaration(&mut self, var_declaration: &TypedVarDeclaration) {
+    fn compile_var_declaration(&mut self, var_declaration: &TypedVarDeclaration) {
         if self.get_cur_function().is_none() {
             panic!("Cannot compile var declaration outside of a function");
         }
-        
+
         let var_name = var_declaration.var_name.as_str();
         let var_type = &var_declaration.var_type;
         let alloca = self.create_alloca(var_name, var_type);
 
-        self.scope_manager.set(var_name, alloca);
-
+        // The value is compiled first, since it can refer to a variable being shadowed
         let value = self.compile_expr(&var_declaration.var_value);
-        LLVMBuildStore(self.builder, value, alloca);
+        self.builder.store(value, alloca);
+
+        self.scope_manager.set(var_name, alloca);
     }
 
     /// Compiles a while loop, assuming the LLVM builder is building inside a function body.
-    unsafe fn compile_while_loop(&mut self, while_loop: &TypedWhileLoop) {
+    fn compile_while_loop(&mut self, while_loop: &TypedWhileLoop) {
         let cur_func = match self.get_cur_function() {
             Some(func) => func,
             None => panic!("Cannot compile while declaration outside of a function"),
         };
-        let cond_block = LLVMAppendBasicBlockInContext(self.context, cur_func, cstr!("cond"));
-        let loop_block = LLVMCreateBasicBlockInContext(self.context, cstr!("loop"));
+        let cond_block = self.context.append_basic_block(cur_func, "cond");
+        let loop_block = self.context.create_basic_block("loop");
         // Build block to go to after loop is done executing
-        let after_block = LLVMCreateBasicBlockInContext(self.context, cstr!("after"));
+        let after_block = self.context.create_basic_block("after");
 
-        LLVMBuildBr(self.builder, cond_block);
-        LLVMPositionBuilderAtEnd(self.builder, cond_block);
+        self.builder.br(cond_block);
+        self.builder.position_at_end(cond_block);
 
         let condition = self.compile_expr(&while_loop.condition);
-        LLVMBuildCondBr(self.builder, condition, loop_block, after_block);
+        self.builder.cond_br(condition, loop_block, after_block);
 
         // Start insertion in loop_block.
-        LLVMAppendExistingBasicBlock(cur_func, loop_block);
-        LLVMPositionBuilderAtEnd(self.builder, loop_block);
-        
+        cur_func.append_basic_block(loop_block);
+        self.builder.position_at_end(loop_block);
+
         if !self.compile_body(&while_loop.body) {
-            LLVMBuildBr(self.builder, cond_block);
+            self.builder.br(cond_block);
         }
-        
-        LLVMAppendExistingBasicBlock(cur_func, after_block);
-        LLVMPositionBuilderAtEnd(self.builder, after_block);
+
+        cur_func.append_basic_block(after_block);
+        self.builder.position_at_end(after_block);
     }
 
     /// Compiles an assignment expression like `foo = 28` (and panics if `foo`'s type can't store 28).
     // TODO: should we remove the panics from here since they're already in Typer
-    unsafe fn compile_assignment_statement(&mut self, assign: &TypedAssignment) {
+    fn compile_assignment_statement(&mut self, assign: &TypedAssignment) {
         let alloca = match self.scope_manager.get(&assign.name) {
             Some(v) => *v,
             None => panic!("Setting a variable that has not been declared"),
         };
 
         // TODO: allow assigning functions with matching types to each other
-        if !LLVMIsAFunction(alloca).is_null() {
+        if alloca.is_function() {
             panic!("Cannot assign a value to function '{}'", assign.name);
         }
 
         let value = self.compile_expr(&assign.value);
-        LLVMBuildStore(self.builder, value, alloca);
+        self.builder.store(value, alloca);
     }
 
     /// Compiles a return statement, panicking if the builder isn't inside a function.
-    unsafe fn compile_ret_statement(&mut self, ret_value: &Option<TypedExpr>) {
+    fn compile_ret_statement(&mut self, ret_value: &Option<TypedExpr>) {
         if self.get_cur_function().is_none() {
             panic!("Cannot compile ret statement outside of a function");
         }
 
         match ret_value {
-            Some(expr) => LLVMBuildRet(self.builder, self.compile_expr(expr)),
-            None => LLVMBuildRetVoid(self.builder),
+            Some(TypedExpr::Call(call)) if self.is_self_tail_call(call) => self.compile_self_tail_call(call),
+            Some(expr) => {
+                let value = self.compile_expr(expr);
+                self.builder.ret(value);
+            }
+            None => self.builder.ret_void(),
         };
     }
 
+    /// Returns true if `call` (which is being returned) calls the function being compiled.
+    fn is_self_tail_call(&self, call: &TypedCall) -> bool {
+        self.tail_call_target
+            .as_ref()
+            .is_some_and(|target| target.func_name == call.function_name)
+    }
+
+    /// Compiles `ret foo(...)` inside `foo` into a jump back to the start of the function; see
+    /// [TailCallTarget].
+    fn compile_self_tail_call(&mut self, call: &TypedCall) {
+        // Every argument has to be evaluated before any parameter is overwritten, since the
+        // arguments can depend on the parameters (e.g. `ret foo(b, a)`)
+        let arg_values: Vec<_> = call.args.iter().map(|arg| self.compile_expr(arg)).collect();
+
+        let target = self.tail_call_target.as_ref().expect("checked by is_self_tail_call");
+        for (&value, &alloca) in arg_values.iter().zip(target.param_allocas.iter()) {
+            self.builder.store(value, alloca);
+        }
+        self.builder.br(target.loop_block);
+    }
+
     /// Compiles a function body, assuming the LLVM builder is building inside a function body.
-    /// 
+    ///
     /// Returns `true` if the body returns (contains a return statement), `false` otherwise.
-    unsafe fn compile_body(&mut self, body: &[TypedStatement]) -> bool {
+    fn compile_body(&mut self, body: &[TypedStatement]) -> bool {
         let mut body_returns = false;
         self.scope_manager.enter_scope();
         for statement in body {
             self.compile_statement(statement);
-            // If we have a termination instruction, stop compiling statements
-            if let TypedStatement::Return(_) = statement { 
+            // If the statement terminated the current block, stop compiling statements
+            if Self::terminates_block(statement) {
                 body_returns = true;
                 break;
-             }
+            }
         }
         self.scope_manager.exit_scope();
         body_returns
     }
 
+    /// Returns true if compiling `statement` terminates the current basic block (e.g. because
+    /// the statement always returns), in which case no statements can be compiled after it.
+    fn terminates_block(statement: &TypedStatement) -> bool {
+        match statement {
+            TypedStatement::Unreachable => true,
+            statement => statement.always_returns(),
+        }
+    }
+
     /// This method compiles an typed if statementi.
-    /// 
+    ///
     /// # Notes
     /// - If `if_statement` has no else block, this method will still produce an empty else block;
     ///   it will be optimized away during LLVM's optimization passes anyway.
-    unsafe fn compile_if_statement(&mut self, if_statement: &TypedIf) {
+    fn compile_if_statement(&mut self, if_statement: &TypedIf) {
         let cur_func = match self.get_cur_function() {
             Some(func) => func,
             None => panic!("Cannot compile if statement outside of a function"),
         };
 
-        let cond_block = LLVMAppendBasicBlockInContext(self.context, cur_func, cstr!("cond"));
+        let cond_block = self.context.append_basic_block(cur_func, "cond");
         // then_block will be appended once cond_block is built
-        let then_block = LLVMCreateBasicBlockInContext(self.context, cstr!("then"));
+        let then_block = self.context.create_basic_block("then");
         // else_block will be appended once then_block is built
-        let else_block = LLVMCreateBasicBlockInContext(self.context, cstr!("else"));
-        // merge_block will be appended once else_block is built
-        let merge_block = LLVMCreateBasicBlockInContext(self.context, cstr!("merge"));
+        let else_block = self.context.create_basic_block("else");
+        // merge_block will be appended once else_block is built; if both branches always
+        // return, then no code can follow the if statement, so there's no merge block at all
+        let merge_block = match if_statement.always_returns() {
+            true => None,
+            false => Some(self.context.create_basic_block("merge")),
+        };
 
-        LLVMBuildBr(self.builder, cond_block);
-        LLVMPositionBuilderAtEnd(self.builder, cond_block);
+        self.builder.br(cond_block);
+        self.builder.position_at_end(cond_block);
 
         let condition = self.compile_expr(&if_statement.condition);
 
-        let target_else_block = match &if_statement.else_body {
-            Some(_) => else_block,
-            None => merge_block,
+        let target_else_block = match (&if_statement.else_body, merge_block) {
+            (None, Some(merge_block)) => merge_block,
+            _ => else_block,
         };
-        LLVMBuildCondBr(self.builder, condition, then_block, target_else_block);
+        self.builder.cond_br(condition, then_block, target_else_block);
 
         // ------------------------ THEN BLOCK ------------------------------
         // Start insertion in then_block.
-        LLVMAppendExistingBasicBlock(cur_func, then_block);
-        LLVMPositionBuilderAtEnd(self.builder, then_block);
+        cur_func.append_basic_block(then_block);
+        self.builder.position_at_end(then_block);
         if !self.compile_body(&if_statement.then_body) {
-            // Build branch to merge_block after if statement 
-            LLVMBuildBr(self.builder, merge_block);
+            // Build branch to merge_block after if statement
+            self.builder.br(merge_block.unwrap());
         }
 
         // ------------------------ ELSE BLOCK ------------------------------
         if let Some(else_body) = &if_statement.else_body {
-            LLVMAppendExistingBasicBlock(cur_func, else_block); // start building else block
-            LLVMPositionBuilderAtEnd(self.builder, else_block);
+            cur_func.append_basic_block(else_block); // start building else block
+            self.builder.position_at_end(else_block);
             if !self.compile_body(else_body) {
-                LLVMBuildBr(self.builder, merge_block);
+                self.builder.br(merge_block.unwrap());
             }
         }
 
         // ------------------------ MERGE BLOCK ------------------------------
 
-        LLVMAppendExistingBasicBlock(cur_func, merge_block);
-        LLVMPositionBuilderAtEnd(self.builder, merge_block);
+        if let Some(merge_block) = merge_block {
+            cur_func.append_basic_block(merge_block);
+            self.builder.position_at_end(merge_block);
+        }
     }
 
     /// Compiles an expression, panicking if its value's type doesn't match the expected type.
     ///
     /// Note: if `expected_type` is `None`, then no type-checking is performed (because the caller
     /// doesn't actually know what the type must be).
-    unsafe fn compile_expr(&mut self, expr: &TypedExpr) -> LLVMValueRef {
+    fn compile_expr(&mut self, expr: &TypedExpr) -> Value<'ctx> {
         match expr {
             TypedExpr::Identifier(id) => self.compile_identifier(id),
             TypedExpr::IntLiteral(int_literal) => self.compile_int_literal(int_literal),
             TypedExpr::BoolLiteral(bool_literal) => self.compile_bool_literal(*bool_literal),
+            TypedExpr::StrLiteral(str_literal) => self.compile_str_literal(str_literal),
             TypedExpr::Binary(bin_expr) => self.compile_bin_expr(bin_expr),
             TypedExpr::Comparison(comparison) => self.compile_comparison_expr(comparison),
             TypedExpr::Call(call) => self.compile_call(call),
@@ -459,42 +795,58 @@ impl Compiler {
     }
 
     /// Compiles an identifier expression (variable value) with an expected type.
-    unsafe fn compile_identifier(&mut self, id: &TypedIdentifier) -> LLVMValueRef {
+    fn compile_identifier(&mut self, id: &TypedIdentifier) -> Value<'ctx> {
         let alloca = match self.scope_manager.get(id.name.as_str()) {
             Some(v) => *v,
             None => panic!("Compiler error: undefined identifier '{}'", id.name),
         };
 
         let alloca_type = self.to_llvm_type(&id.id_type);
-        let name = CString::new(id.name.as_str()).unwrap();
-        LLVMBuildLoad2(self.builder, alloca_type, alloca, name.as_ptr())
+        self.builder.load(alloca_type, alloca, &id.name)
     }
 
     /// Compiles an integer literal expression.
-    /// 
+    ///
     /// This function converts a `TypedIntLiteral` into an LLVM constant integer value.
     /// If `int_literal.negative` is true, the value is negated.
-    unsafe fn compile_int_literal(&self, int_literal: &TypedIntLiteral) -> LLVMValueRef {
+    fn compile_int_literal(&self, int_literal: &TypedIntLiteral) -> Value<'ctx> {
         let int_type = self.to_llvm_type(&Type::Int(int_literal.int_type));
-        let value_str = int_literal.int_value.clone();
-        let value_cstr = CString::new(value_str).unwrap();
-        LLVMConstIntOfString(int_type, value_cstr.as_ptr(), 10)
+        self.context.const_int_from_str(int_type, &int_literal.int_value)
     }
 
     /// Compiles an bool literal expression (true/false, also known as 1/0).
-    unsafe fn compile_bool_literal(&self, bool_literal: bool) -> LLVMValueRef {
+    fn compile_bool_literal(&self, bool_literal: bool) -> Value<'ctx> {
         let bool_type = self.to_llvm_type(&Type::Bool);
-        LLVMConstInt(bool_type, bool_literal as u64, 0)
+        self.context.const_int(bool_type, bool_literal as u64)
+    }
+
+    /// Compiles a string literal into a pointer to a null-terminated constant.
+    ///
+    /// Identical literals are interned: each distinct string gets a single private,
+    /// `unnamed_addr` global in the module, no matter how many times it occurs.
+    fn compile_str_literal(&mut self, str_literal: &str) -> Value<'ctx> {
+        if let Some(&global) = self.str_literals.get(str_literal) {
+            return global;
+        }
+
+        let contents = self.context.const_string(str_literal);
+        let global = self.module.add_global(contents.type_of(), ".str");
+        global.make_private_constant(contents);
+
+        // With typed pointers, the global is a pointer to an array rather than to its first byte
+        let str_pointer = global.const_pointer_cast(self.to_llvm_type(&Type::Str));
+        self.str_literals.insert(str_literal.to_string(), str_pointer);
+        str_pointer
     }
 
     /// Compiles a binary expression (recursively compiling left- and right-hand sides).
-    unsafe fn compile_bin_expr(&mut self, bin_expr: &TypedBinary) -> LLVMValueRef {
+    fn compile_bin_expr(&mut self, bin_expr: &TypedBinary) -> Value<'ctx> {
         use BinaryOperator::*;
 
         let lhs = self.compile_expr(&bin_expr.left);
         let rhs = self.compile_expr(&bin_expr.right);
 
-        if LLVMTypeOf(lhs) != LLVMTypeOf(rhs) {
+        if lhs.type_of() != rhs.type_of() {
             panic!("Binary expr: type(LHS) != type(RHS) should've been handled by Typer")
         }
 
@@ -504,23 +856,23 @@ impl Compiler {
         };
 
         match bin_expr.operator {
-            Add => LLVMBuildAdd(self.builder, lhs, rhs, cstr!("add")),
-            Subtract => LLVMBuildSub(self.builder, lhs, rhs, cstr!("sub")),
-            Multiply => LLVMBuildMul(self.builder, lhs, rhs, cstr!("mul")),
+            Add => self.builder.add(lhs, rhs, "add"),
+            Subtract => self.builder.sub(lhs, rhs, "sub"),
+            Multiply => self.builder.mul(lhs, rhs, "mul"),
             // TODO: signed-ints: Signed vs unsigned division
             Divide => match int_type {
-                IntType { signed: true, .. } => LLVMBuildSDiv(self.builder, lhs, rhs, cstr!("sdiv")),
-                IntType { signed: false, .. } => LLVMBuildUDiv(self.builder, lhs, rhs, cstr!("udiv")),
+                IntType { signed: true, .. } => self.builder.sdiv(lhs, rhs, "sdiv"),
+                IntType { signed: false, .. } => self.builder.udiv(lhs, rhs, "udiv"),
             },
             Remainder => match int_type {
-                IntType { signed: true, .. } => LLVMBuildSRem(self.builder, lhs, rhs, cstr!("srem")),
-                IntType { signed: false, .. } => LLVMBuildURem(self.builder, lhs, rhs, cstr!("urem")),
+                IntType { signed: true, .. } => self.builder.srem(lhs, rhs, "srem"),
+                IntType { signed: false, .. } => self.builder.urem(lhs, rhs, "urem"),
             }
         }
     }
 
     /// Compiles a unary expression.
-    unsafe fn compile_unary(&mut self, unary: &TypedUnary) -> LLVMValueRef {
+    fn compile_unary(&mut self, unary: &TypedUnary) -> Value<'ctx> {
         let operand = self.compile_expr(&unary.operand);
         let source_type = &unary.operand.get_result_type();
         match &unary.operator {
@@ -530,7 +882,7 @@ impl Compiler {
     }
 
     /// Compiles a cast expression.
-    unsafe fn compile_cast(&mut self, operand: LLVMValueRef, cast_type: &Type, source_type: &Type) -> LLVMValueRef {
+    fn compile_cast(&mut self, operand: Value<'ctx>, cast_type: &Type, source_type: &Type) -> Value<'ctx> {
         let (cast_int_type, source_int_type) = match (cast_type, source_type) {
             (Type::Int(cast), Type::Int(source)) => (cast, source),
             (cast, source) => {
@@ -539,12 +891,12 @@ impl Compiler {
         };
 
         if cast_int_type.width < source_int_type.width {
-            LLVMBuildTrunc(self.builder, operand, self.to_llvm_type(cast_type), cstr!("trunc"))
+            self.builder.trunc(operand, self.to_llvm_type(cast_type), "trunc")
         } else if cast_int_type.width > source_int_type.width {
             if cast_int_type.signed {
-                LLVMBuildSExt(self.builder, operand, self.to_llvm_type(cast_type), cstr!("sext"))
+                self.builder.sext(operand, self.to_llvm_type(cast_type), "sext")
             } else {
-                LLVMBuildZExt(self.builder, operand, self.to_llvm_type(cast_type), cstr!("zext"))
+                self.builder.zext(operand, self.to_llvm_type(cast_type), "zext")
             }
         } else {
             // Width is the same. No need to cast.
@@ -554,31 +906,31 @@ impl Compiler {
     }
 
     /// Compiles a negation expression.
-    unsafe fn compile_negation(&mut self, operand: LLVMValueRef, source_type: &Type) -> LLVMValueRef {
+    fn compile_negation(&mut self, operand: Value<'ctx>, source_type: &Type) -> Value<'ctx> {
         match source_type {
-            Type::Int(IntType { signed: true, .. }) => LLVMBuildNeg(self.builder, operand, cstr!("neg")),
+            Type::Int(IntType { signed: true, .. }) => self.builder.neg(operand, "neg"),
             _ => panic!("Unsupported type for negation, can only handle integers; this should have been handled by typer"),
         }
     }
 
     /// Compiles a comparison expression.
-    unsafe fn compile_comparison_expr(&mut self, comparison: &TypedComparison) -> LLVMValueRef {
+    fn compile_comparison_expr(&mut self, comparison: &TypedComparison) -> Value<'ctx> {
         let lhs = self.compile_expr(&comparison.left);
         let rhs = self.compile_expr(&comparison.right);
 
-        if LLVMTypeOf(lhs) != LLVMTypeOf(rhs) {
+        if lhs.type_of() != rhs.type_of() {
             panic!("Comparison: type(LHS) != type(RHS) should've been handled by Typer")
         }
 
         match comparison.operand_type {
-            Type::Int(int_type) => LLVMBuildICmp(self.builder, self.comparison_int_op(comparison.operator, int_type), lhs, rhs, cstr!("")),
+            Type::Int(int_type) => self.builder.icmp(Self::comparison_int_op(comparison.operator, int_type), lhs, rhs, "cmp"),
             _ => panic!("Unsupported lhs and rhs types for comparison; can only handle integers"),
         }
     }
 
-    unsafe fn comparison_int_op(&mut self, operator: ComparisonOperator, result_type: IntType) -> LLVMIntPredicate {
+    fn comparison_int_op(operator: ComparisonOperator, result_type: IntType) -> LLVMIntPredicate {
         use LLVMIntPredicate::*;
-        
+
         match operator {
             ComparisonOperator::NotEqualTo => LLVMIntNE,
             ComparisonOperator::EqualTo => LLVMIntEQ,
@@ -602,13 +954,17 @@ impl Compiler {
     }
 
     /// Compiles a typed function call
-    unsafe fn compile_call(&mut self, call: &TypedCall) -> LLVMValueRef {
+    fn compile_call(&mut self, call: &TypedCall) -> Value<'ctx> {
+        if let Some(builtin) = Builtin::from_name(&call.function_name) {
+            return self.compile_builtin_call(builtin, call);
+        }
+
         let func = match self.scope_manager.get(&call.function_name) {
             Some(v) => *v,
             None => panic!("Undefined functions should be handled by typer"),
         };
 
-        if LLVMIsAFunction(func).is_null() {
+        if !func.is_function() {
             panic!(
                 "Calls like foo() where foo isn't callable (e.g. i32) should be handled by typer"
             )
@@ -619,84 +975,954 @@ impl Compiler {
             panic!("Number of arguments should be handled by typer");
         }
 
-        let mut arg_values = Vec::with_capacity(call.args.len());
-        for i in 0..num_params {
-            let arg = call.args.get(i).unwrap();
-            let value = self.compile_expr(arg);
-            arg_values.push(value);
+        let mut arg_values: Vec<_> = call.args.iter().map(|arg| self.compile_expr(arg)).collect();
+        if self.saves_program_args(&call.function_proto) {
+            // `main` is called again with the arguments that it was first called with
+            let program_args = self.program_args.as_ref().unwrap();
+            arg_values.push(self.builder.load(self.context.int_type(32), program_args.count, "argc"));
+            arg_values.push(self.builder.load(self.argv_type(), program_args.values, "argv"));
         }
 
-        let func_type = self.to_llvm_type(&Type::Func(call.function_proto.clone()));
-        LLVMBuildCall2(
-            self.builder,
-            func_type,
-            func,
-            arg_values.as_mut_ptr(),
-            arg_values.len() as c_uint,
-            cstr!(""),
-        )
+        // Name the result after the function (e.g. `%fib`), unless there is no result to name
+        let result_name = match call.function_proto.return_type.as_ref() {
+            Type::Void => "",
+            _ => call.function_name.as_str(),
+        };
+
+        let func_type = self.func_llvm_type(&call.function_proto);
+        self.builder.call(func_type, func, &arg_values, result_name)
+    }
+
+    /// Compiles a call to a [Builtin] function (the typer has already checked its arguments).
+    fn compile_builtin_call(&mut self, builtin: Builtin, call: &TypedCall) -> Value<'ctx> {
+        let intrinsic_name = match builtin {
+            Builtin::Breakpoint => "llvm.debugtrap",
+            Builtin::ArgCount => {
+                let program_args = self.program_args.as_ref().unwrap();
+                return self.builder.load(self.context.int_type(32), program_args.count, "arg_count");
+            }
+            Builtin::Arg => return self.compile_arg_call(&call.args[0]),
+        };
+        let func_type = self.to_llvm_type(&Type::Func(builtin.proto()));
+        let intrinsic = match self.module.get_function(intrinsic_name) {
+            Some(intrinsic) => intrinsic,
+            None => self.module.add_function(intrinsic_name, func_type),
+        };
+        self.builder.call(func_type, intrinsic, &[], "")
+    }
+
+    /// Compiles `arg(index)`, which is `argv[index]` if `index` is less than `argc`, and `""`
+    /// otherwise (without reading `argv`, which is null on WebAssembly).
+    fn compile_arg_call(&mut self, index: &TypedExpr) -> Value<'ctx> {
+        let cur_func = self.get_cur_function().expect("arg() is only called inside functions");
+        let index = self.compile_expr(index);
+        let arg = self.create_alloca("arg.addr", &Type::Str);
+        let empty_str = self.compile_str_literal("");
+        self.builder.store(empty_str, arg);
+
+        let program_args = self.program_args.as_ref().unwrap();
+        let count = self.builder.load(self.context.int_type(32), program_args.count, "argc");
+        let in_range = self.builder.icmp(LLVMIntPredicate::LLVMIntULT, index, count, "in_range");
+        let load_block = self.context.append_basic_block(cur_func, "arg.load");
+        let merge_block = self.context.append_basic_block(cur_func, "arg.merge");
+        self.builder.cond_br(in_range, load_block, merge_block);
+
+        self.builder.position_at_end(load_block);
+        let values = self.builder.load(self.argv_type(), program_args.values, "argv");
+        let value_pointer = self.builder.element_pointer(self.context.byte_pointer_type(), values, index, "argv.elem");
+        let value = self.builder.load(self.context.byte_pointer_type(), value_pointer, "arg.value");
+        self.builder.store(value, arg);
+        self.builder.br(merge_block);
+
+        self.builder.position_at_end(merge_block);
+        self.builder.load(self.context.byte_pointer_type(), arg, "arg")
+    }
+
+    /// Adds the counters of an [instrumented](crate::coverage::instrument) program, and the
+    /// function that writes them to its profile (with C's `fopen` and `fprintf`, one line per
+    /// counter).
+    fn add_coverage_counters(&mut self, coverage: &Coverage) -> CoverageCounters<'ctx> {
+        let counter_type = self.context.int_type(64);
+        let counters_type = self.context.array_type(counter_type, coverage.locations.len());
+        let global = self.module.add_global(counters_type, "flick.coverage");
+        global.make_internal_zeroed(counters_type);
+        // With typed pointers, the global is a pointer to an array rather than to its first counter
+        let counters = global.const_pointer_cast(self.context.pointer_type(counter_type));
+
+        let write = self.module.add_function("flick.coverage.write", self.context.function_type(self.context.void_type(), &[]));
+        write.set_linkage(LLVMInternalLinkage);
+        let entry_block = self.context.append_basic_block(write, "entry");
+        let write_block = self.context.append_basic_block(write, "write");
+        let done_block = self.context.append_basic_block(write, "done");
+
+        self.builder.position_at_end(entry_block);
+        let file_type = self.context.byte_pointer_type();
+        let fopen_type = self.context.function_type(file_type, &[file_type, file_type]);
+        let fopen = self.libc_function("fopen", fopen_type);
+        let path = self.compile_str_literal(&coverage.profile_path);
+        let mode = self.compile_str_literal("w");
+        let profile = self.builder.call(fopen_type, fopen, &[path, mode], "profile");
+        let opened = self.builder.icmp(LLVMIntPredicate::LLVMIntNE, profile, self.context.const_null(file_type), "opened");
+        self.builder.cond_br(opened, write_block, done_block);
+
+        self.builder.position_at_end(write_block);
+        let fprintf_type = self.context.var_arg_function_type(self.context.int_type(32), &[file_type, file_type]);
+        let fprintf = self.libc_function("fprintf", fprintf_type);
+        for (index, location) in coverage.locations.iter().enumerate() {
+            let index = self.context.const_int(counter_type, index as u64);
+            let counter = self.builder.element_pointer(counter_type, counters, index, "counter");
+            let count = self.builder.load(counter_type, counter, "count");
+            let format = self.compile_str_literal(&format!("{} %llu\n", location.replace('%', "%%")));
+            self.builder.call(fprintf_type, fprintf, &[profile, format, count], "");
+        }
+        let fclose_type = self.context.function_type(self.context.int_type(32), &[file_type]);
+        let fclose = self.libc_function("fclose", fclose_type);
+        self.builder.call(fclose_type, fclose, &[profile], "");
+        self.builder.br(done_block);
+
+        self.builder.position_at_end(done_block);
+        self.builder.ret_void();
+        CoverageCounters { counters, write }
+    }
+
+    /// Returns the C library function called `name` (of type `func_type`), declaring it if the
+    /// program doesn't.
+    fn libc_function(&self, name: &str, func_type: LlvmType<'ctx>) -> Value<'ctx> {
+        match self.module.get_function(name) {
+            Some(func) => func,
+            None => self.module.add_function(name, func_type),
+        }
+    }
+
+    /// Returns `true` if `func_proto` is the `main` that saves the program's command-line
+    /// arguments (see [ProgramArgs]), which takes `argc` and `argv` as parameters, like in C.
+    fn saves_program_args(&self, func_proto: &FuncProto) -> bool {
+        self.program_args.is_some()
+            && !self.options.targets_wasm()
+            && func_proto.name == "main"
+            && func_proto.func_visibility == FuncVisibility::Public
+            && func_proto.params.is_empty()
+    }
+
+    /// Returns the LLVM type of the function with the prototype `func_proto`, which is
+    /// `main(i32, i8**)` if it [saves the program's arguments](Self::saves_program_args).
+    fn func_llvm_type(&self, func_proto: &FuncProto) -> LlvmType<'ctx> {
+        if !self.saves_program_args(func_proto) {
+            return self.to_llvm_type(&Type::Func(func_proto.clone()));
+        }
+        let return_type = self.to_llvm_type(&func_proto.return_type);
+        self.context.function_type(return_type, &[self.context.int_type(32), self.argv_type()])
     }
 
-    /// Converts Flick's [Type] enum to llvm-sys's [LLVMTypeRef].
-    unsafe fn to_llvm_type(&self, t: &Type) -> LLVMTypeRef {
+    /// Returns the LLVM type of C's `argv` (`i8**`, or just `ptr` with opaque pointers).
+    fn argv_type(&self) -> LlvmType<'ctx> {
+        self.context.pointer_type(self.context.byte_pointer_type())
+    }
+
+    /// Converts Flick's [Type] enum to the corresponding LLVM type.
+    fn to_llvm_type(&self, t: &Type) -> LlvmType<'ctx> {
         match t {
-            Type::Int(int_type) => LLVMIntTypeInContext(self.context, int_type.width),
-            Type::Bool => LLVMInt1TypeInContext(self.context),
-            Type::Void => LLVMVoidTypeInContext(self.context),
+            Type::Int(int_type) => self.context.int_type(int_type.width),
+            Type::Bool => self.context.bool_type(),
+            Type::Str => self.context.byte_pointer_type(),
+            Type::Void => self.context.void_type(),
             Type::Func(func_proto) => {
                 let return_type = self.to_llvm_type(func_proto.return_type.as_ref());
-                let num_params = func_proto.params.len() as c_uint;
-                let mut param_types: Vec<_> = func_proto
+                let param_types: Vec<_> = func_proto
                     .params
                     .iter()
                     .map(|p| self.to_llvm_type(&p.param_type))
                     .collect();
 
-                LLVMFunctionType(return_type, param_types.as_mut_ptr(), num_params, 0)
+                self.context.function_type(return_type, &param_types)
             }
         }
     }
 
     /// Creates an LLVM 'alloca', which can then be used to set up a local variable.
-    unsafe fn create_alloca(&self, var_name: &str, var_type: &Type) -> LLVMValueRef {
-        let var_name_c = CString::new(var_name).unwrap();
-        LLVMBuildAlloca(self.builder, self.to_llvm_type(var_type), var_name_c.as_ptr())
+    ///
+    /// The alloca is placed at the start of the function's entry block, no matter where the
+    /// builder currently is, so that loops don't grow the stack and LLVM can promote the
+    /// variable to a register.
+    fn create_alloca(&self, var_name: &str, var_type: &Type) -> Value<'ctx> {
+        let cur_func = self.get_cur_function().expect("allocas are only created inside functions");
+        let entry_block = cur_func.entry_block().expect("the builder is inside a block of cur_func");
+
+        let alloca_builder = Builder::new(self.context);
+        match entry_block.first_instruction() {
+            Some(first_instruction) => alloca_builder.position_before(first_instruction),
+            None => alloca_builder.position_at_end(entry_block),
+        }
+        alloca_builder.alloca(self.to_llvm_type(var_type), var_name)
     }
 
     /// Returns the function currently being built by the compiler.
-    unsafe fn get_cur_function(&self) -> Option<LLVMValueRef> {
-        let cur_func = LLVMGetBasicBlockParent(LLVMGetInsertBlock(self.builder));
-        if LLVMIsNull(cur_func) == 1 {
-            return None;
-        }
-        Some(cur_func)
+    fn get_cur_function(&self) -> Option<Value<'ctx>> {
+        self.builder.insert_block().and_then(|block| block.parent())
     }
-    
-    /// Compiles an unreachable instruction, terminating the current block.
-    unsafe fn compile_unreachable(&self) {
-        LLVMBuildUnreachable(self.builder);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Lexer, OptLevel, Parser, SourceMap, Typer};
+    use std::process::Command;
+
+    /// Compiles `source_code` into an executable (linked with `gcc`), runs it, and returns its
+    /// exit code. The `test_name` keeps the build files of concurrently running tests apart.
+    fn compile_and_run(test_name: &str, source_code: &str) -> i32 {
+        compile_and_run_with_options(test_name, source_code, CompilerOptions::default())
     }
+
+    /// Like [compile_and_run], but with a compiler configured by `options`.
+    fn compile_and_run_with_options(test_name: &str, source_code: &str, options: CompilerOptions) -> i32 {
+        let tokens = Lexer::lex(source_code).unwrap();
+        let arena = Arena::new();
+        let program = Parser::parse_program(&arena, &tokens).unwrap();
+        let typed_program = Typer::new().type_program(&program).unwrap();
+
+        let mut compiler = Compiler::new(options).unwrap();
+        compiler.compile(&typed_program).unwrap();
+        compiler.optimize().unwrap();
+
+        let build_dir = std::env::temp_dir().join(format!("flick-{}-{}", test_name, std::process::id()));
+        std::fs::create_dir_all(&build_dir).unwrap();
+        let object_path = build_dir.join("test.o");
+        let executable_path = build_dir.join("test");
+        compiler.to_file(&object_path).unwrap();
+
+        let link_status = Command::new("gcc")
+            .arg(&object_path)
+            .arg("-o")
+            .arg(&executable_path)
+            .status()
+            .unwrap();
+        assert!(link_status.success());
+
+        let exit_code = Command::new(&executable_path).status().unwrap().code().unwrap();
+        std::fs::remove_dir_all(&build_dir).unwrap();
+        exit_code
+    }
+
+    #[test]
+    fn coverage_profile() {
+        let source_code = "\
+pub fn main() u8 {
+    u64 i = 0
+    while i < 3 {
+        i += 1
+    }
+    if i == 0 {
+        ret 1
+    }
+    ret 0
 }
+"
+        .to_string();
+        let (tokens, spans) = Lexer::lex_with_spans(&source_code).unwrap();
+        let arena = Arena::new();
+        let program = Parser::parse_program_with_spans(&arena, &tokens, &spans).unwrap();
+        let mut typed_program = Typer::new().type_program(&program).unwrap();
+        let build_dir = std::env::temp_dir().join(format!("flick-coverage_profile-{}", std::process::id()));
+        std::fs::create_dir_all(&build_dir).unwrap();
+        let profile_path = build_dir.join("test.flcov");
+        let sources = SourceMap::single_file("main.fl", source_code.clone());
+        crate::coverage::instrument(&program, &mut typed_program, &sources, profile_path.to_str().unwrap());
+
+        let mut compiler = Compiler::new(CompilerOptions::default()).unwrap();
+        compiler.compile(&typed_program).unwrap();
+        compiler.optimize().unwrap();
+        let object_path = build_dir.join("test.o");
+        let executable_path = build_dir.join("test");
+        compiler.to_file(&object_path).unwrap();
+        let link_status = Command::new("gcc")
+            .arg(&object_path)
+            .arg("-o")
+            .arg(&executable_path)
+            .status()
+            .unwrap();
+        assert!(link_status.success());
+        assert_eq!(Command::new(&executable_path).status().unwrap().code(), Some(0));
+
+        let profile = std::fs::read_to_string(&profile_path).unwrap();
+        std::fs::remove_dir_all(&build_dir).unwrap();
+        assert_eq!(profile, "main.fl:2:9 1\nmain.fl:3:5 1\nmain.fl:4:9 3\nmain.fl:6:5 1\nmain.fl:7:9 0\nmain.fl:9:5 1\n");
+    }
+
+    #[test]
+    fn while_loop() {
+        let source_code = "
+            pub fn main() u8 {
+                u8 sum = 0
+                u8 i = 1
+                u8 limit = 10
+                while i <= limit {
+                    sum += i
+                    i += 1
+                }
+                ret sum
+            }
+        ";
+
+        assert_eq!(compile_and_run("while_loop", source_code), 55);
+    }
+
+    #[test]
+    fn nested_while_loops() {
+        let source_code = "
+            pub fn main() u8 {
+                u8 count = 0
+                u8 i = 0
+                u8 limit = 5
+                while i < limit {
+                    u8 j = 0
+                    while j < i {
+                        count += 1
+                        j += 1
+                    }
+                    i += 1
+                }
+                ret count
+            }
+        ";
+
+        assert_eq!(compile_and_run("nested_while_loops", source_code), 10);
+    }
+
+    #[test]
+    fn shadowing_in_while_body() {
+        let source_code = "
+            pub fn main() u8 {
+                u8 x = 1
+                u8 i = 0
+                u8 limit = 3
+                while i < limit {
+                    u8 x = x + 10
+                    i += x
+                }
+                ret x + i
+            }
+        ";
 
-impl Drop for Compiler {
-    /// Disposes the underlying llvm-sys C objects so that we don't leak memory.
-    fn drop(&mut self) {
-        unsafe {
-            LLVMDisposePassBuilderOptions(self.pass_builder);
-            LLVMDisposeTargetMachine(self.target_machine);
-            LLVMDisposeBuilder(self.builder);
-            LLVMDisposeModule(self.module);
-            LLVMContextDispose(self.context);
+        // The inner `x` is 11 in each iteration, and the outer `x` is still 1 after the loop
+        assert_eq!(compile_and_run("shadowing_in_while_body", source_code), 12);
+    }
+
+    #[test]
+    fn shadowing_in_nested_blocks() {
+        let source_code = "
+            fn pick(u8 x) u8 {
+                u8 ten = 10
+                if x > ten {
+                    u8 x = 20
+                    if x > ten {
+                        u8 x = 30
+                        x += 1
+                    }
+                    ret x
+                }
+                ret x
+            }
+
+            pub fn main() u8 {
+                ret pick(11) + pick(1)
+            }
+        ";
+
+        assert_eq!(compile_and_run("shadowing_in_nested_blocks", source_code), 21);
+    }
+
+    #[test]
+    fn return_inside_while_loop() {
+        let source_code = "
+            pub fn main() u8 {
+                u8 i = 0
+                u8 limit = 100
+                u8 answer = 42
+                while i < limit {
+                    if i == answer {
+                        ret i
+                    }
+                    i += 1
+                }
+                ret 0
+            }
+        ";
+
+        assert_eq!(compile_and_run("return_inside_while_loop", source_code), 42);
+    }
+
+    #[test]
+    fn while_loop_that_never_runs() {
+        let source_code = "
+            pub fn main() u8 {
+                u8 i = 7
+                u8 limit = 5
+                while i < limit {
+                    i += 1
+                }
+                ret i
+            }
+        ";
+
+        assert_eq!(compile_and_run("while_loop_that_never_runs", source_code), 7);
+    }
+
+    #[test]
+    fn if_else_that_always_returns() {
+        let source_code = "
+            fn pick(bool first) u8 {
+                if first {
+                    ret 3
+                } else {
+                    ret 4
+                }
+            }
+
+            pub fn main() u8 {
+                ret pick(true) + pick(false)
+            }
+        ";
+
+        assert_eq!(compile_and_run("if_else_that_always_returns", source_code), 7);
+    }
+
+    #[test]
+    fn invalid_module_is_an_error() {
+        let mut compiler = Compiler::new(CompilerOptions::default()).unwrap();
+        // A function whose only block has no terminator
+        let func_type = compiler.context.function_type(compiler.context.void_type(), &[]);
+        let func = compiler.module.add_function("broken", func_type);
+        compiler.context.append_basic_block(func, "entry");
+
+        let result = compiler.compile(&TypedProgram { global_statements: vec![], coverage: None });
+        assert!(matches!(result, Err(CompilationError::InvalidModule(message)) if message.contains("terminator")));
+    }
+
+    #[test]
+    fn str_literals() {
+        let source_code = r#"
+            extern fn strlen(str s) u64
+
+            pub fn main() u8 {
+                u64 a = strlen("Hello\n")
+                u64 b = strlen("\tworld")
+                ret (u8) (a + b)
+            }
+        "#;
+        assert_eq!(compile_and_run("str_literals", source_code), 12);
+    }
+
+    #[test]
+    fn hello_world() {
+        let source_code = r#"
+            extern fn puts(str s) i32
+
+            pub fn main() u8 {
+                i32 a = puts("Hello, world!")
+                ret 0
+            }
+        "#;
+        assert_eq!(compile_and_run("hello_world", source_code), 0);
+    }
+
+    #[test]
+    fn identical_str_literals_share_a_global() {
+        let source_code = r#"
+            extern fn puts(str s) i32
+
+            pub fn main() u8 {
+                i32 a = puts("hello")
+                i32 b = puts("world")
+                i32 c = puts("hello")
+                ret 0
+            }
+        "#;
+        let tokens = Lexer::lex(source_code).unwrap();
+        let typed_program = Typer::new().type_program(&Parser::parse_program(&Arena::new(), &tokens).unwrap()).unwrap();
+
+        let mut compiler = Compiler::new(CompilerOptions::default()).unwrap();
+        compiler.compile(&typed_program).unwrap();
+
+        let global_count = compiler.module.globals().count();
+        assert_eq!(global_count, 2);
+    }
+
+    #[test]
+    fn lto_emits_bitcode() {
+        let source_code = "
+            pub fn main() u8 {
+                ret 0
+            }
+        ";
+        let tokens = Lexer::lex(source_code).unwrap();
+        let typed_program = Typer::new().type_program(&Parser::parse_program(&Arena::new(), &tokens).unwrap()).unwrap();
+
+        let options = CompilerOptions::builder().lto(true).emit_kind(EmitKind::Bitcode).build();
+        let mut compiler = Compiler::new(options).unwrap();
+        compiler.compile(&typed_program).unwrap();
+        compiler.optimize().unwrap();
+
+        let bitcode_path = std::env::temp_dir().join(format!("flick-lto-{}.o", std::process::id()));
+        compiler.to_file(&bitcode_path).unwrap();
+        let bitcode = std::fs::read(&bitcode_path).unwrap();
+        std::fs::remove_file(&bitcode_path).unwrap();
+
+        assert!(bitcode.starts_with(b"BC\xC0\xDE"));
+    }
+
+    #[test]
+    fn deep_self_tail_recursion() {
+        // Without the tail call being turned into a loop, this would overflow the stack at -O0
+        let source_code = "
+            fn count_down(u64 n, u8 acc) u8 {
+                u64 zero = 0
+                if n == zero {
+                    ret acc
+                }
+                u8 one = 1
+                ret count_down(n - 1, acc + one)
+            }
+
+            pub fn main() u8 {
+                ret count_down(10000000, 0)
+            }
+        ";
+        let options = CompilerOptions { opt_level: OptLevel::O0, ..Default::default() };
+        // 10000000 % 256 == 128
+        assert_eq!(compile_and_run_with_options("deep_self_tail_recursion", source_code, options), 128);
+    }
+
+    #[test]
+    fn self_tail_call_with_swapped_args() {
+        let source_code = "
+            fn swap(u8 a, u8 b, u8 times) u8 {
+                u8 zero = 0
+                if times == zero {
+                    u8 ten = 10
+                    ret a * ten + b
+                }
+                u8 one = 1
+                ret swap(b, a, times - one)
+            }
+
+            pub fn main() u8 {
+                ret swap(3, 5, 1)
+            }
+        ";
+        let options = CompilerOptions { opt_level: OptLevel::O0, ..Default::default() };
+        assert_eq!(compile_and_run_with_options("swapped_tail_call_args", source_code, options), 53);
+    }
+
+    /// Compiles `source_code` without optimizing it and returns the module's IR as text.
+    fn compile_to_ir(source_code: &str, options: CompilerOptions) -> String {
+        let tokens = Lexer::lex(source_code).unwrap();
+        let typed_program = Typer::new().type_program(&Parser::parse_program(&Arena::new(), &tokens).unwrap()).unwrap();
+
+        let mut compiler = Compiler::new(options).unwrap();
+        compiler.compile(&typed_program).unwrap();
+        compiler.module.print_to_string()
+    }
+
+    #[test]
+    fn readable_ir_names_values_after_source() {
+        let source_code = "
+            fn double(u8 x) u8 {
+                u8 two = 2
+                ret x * two
+            }
+
+            pub fn main() u8 {
+                u8 y = double(3)
+                ret y
+            }
+        ";
+        let ir = compile_to_ir(source_code, CompilerOptions { readable_ir: true, ..Default::default() });
+
+        assert!(ir.contains("%x.addr = alloca i8"));
+        assert!(ir.contains("%two = alloca i8"));
+        assert!(ir.contains("%x1 = load i8, i8* %x.addr") || ir.contains("%x1 = load i8, ptr %x.addr"));
+        assert!(ir.contains("%mul = mul i8"));
+        assert!(ir.contains("%double = call i8 @double(i8 3)"));
+    }
+
+    #[test]
+    fn value_names_are_discarded_by_default() {
+        let source_code = "
+            pub fn main() u8 {
+                u8 y = 3
+                ret y
+            }
+        ";
+        let ir = compile_to_ir(source_code, CompilerOptions::default());
+
+        assert!(!ir.contains("%y"));
+        assert!(ir.contains("define i8 @main()"));
+    }
+
+    #[test]
+    fn output_is_reproducible() {
+        let source_code = r#"
+            extern fn puts(str s) i32
+
+            fn fib(u64 n) u64 {
+                u64 one = 1
+                if n <= one {
+                    ret n
+                }
+                ret fib(n - 1) + fib(n - 2)
+            }
+
+            pub fn main() u8 {
+                i32 a = puts("first")
+                i32 b = puts("second")
+                i32 c = puts("first")
+                ret (u8) fib(10)
+            }
+        "#;
+        let tokens = Lexer::lex(source_code).unwrap();
+        let typed_program = Typer::new().type_program(&Parser::parse_program(&Arena::new(), &tokens).unwrap()).unwrap();
+
+        let build_dir = std::env::temp_dir().join(format!("flick-reproducible-{}", std::process::id()));
+        std::fs::create_dir_all(&build_dir).unwrap();
+
+        // Compile in two separate directories, to make sure no paths end up in the output
+        let objects: Vec<_> = ["a", "b"]
+            .iter()
+            .map(|dir_name| {
+                let object_dir = build_dir.join(dir_name);
+                std::fs::create_dir_all(&object_dir).unwrap();
+                let object_path = object_dir.join("test.o");
+
+                let mut compiler = Compiler::new(CompilerOptions::default()).unwrap();
+                compiler.compile(&typed_program).unwrap();
+                compiler.optimize().unwrap();
+                compiler.to_file(&object_path).unwrap();
+                std::fs::read(&object_path).unwrap()
+            })
+            .collect();
+        std::fs::remove_dir_all(&build_dir).unwrap();
+
+        assert!(objects[0] == objects[1], "compiling the same program twice gave different objects");
+    }
+
+    #[test]
+    fn unknown_target_is_an_error() {
+        let options = CompilerOptions::builder().target("not-a-real-triple").build();
+        let result = Compiler::new(options);
+        assert!(matches!(result, Err(CompilationError::UnknownTarget { triple, .. }) if triple == "not-a-real-triple"));
+    }
+
+    #[test]
+    fn debug_info() {
+        let source_code = "
+            fn square(u8 x) u8 {
+                ret x * x
+            }
+
+            pub fn main() u8 {
+                ret square(3)
+            }
+        ";
+        let options = CompilerOptions::builder()
+            .debug_info(true)
+            .source_file_name("square.fl")
+            .opt_level(OptLevel::O0)
+            .build();
+        let ir = compile_to_ir(source_code, options.clone());
+
+        assert!(ir.contains("!DICompileUnit("));
+        assert!(ir.contains("!DIFile(filename: \"square.fl\""));
+        assert!(ir.contains("!DISubprogram(name: \"square\""));
+        assert!(ir.contains("!DISubprogram(name: \"main\""));
+        assert!(ir.contains("\"Debug Info Version\""));
+
+        assert_eq!(compile_and_run_with_options("debug_info", source_code, options), 9);
+    }
+
+    #[test]
+    fn emit_assembly() {
+        let source_code = "
+            pub fn main() u8 {
+                ret 0
+            }
+        ";
+        let tokens = Lexer::lex(source_code).unwrap();
+        let typed_program = Typer::new().type_program(&Parser::parse_program(&Arena::new(), &tokens).unwrap()).unwrap();
+
+        let options = CompilerOptions::builder().emit_kind(EmitKind::Assembly).build();
+        let mut compiler = Compiler::new(options).unwrap();
+        compiler.compile(&typed_program).unwrap();
+
+        let assembly_path = std::env::temp_dir().join(format!("flick-asm-{}.s", std::process::id()));
+        compiler.to_file(&assembly_path).unwrap();
+        let assembly = std::fs::read_to_string(&assembly_path).unwrap();
+        std::fs::remove_file(&assembly_path).unwrap();
+
+        assert!(assembly.contains("main:"));
+
+        // The options can be overridden for a single file
+        let object_path = std::env::temp_dir().join(format!("flick-asm-{}.o", std::process::id()));
+        compiler.to_file_as(&object_path, EmitKind::Object).unwrap();
+        let object = std::fs::read(&object_path).unwrap();
+        std::fs::remove_file(&object_path).unwrap();
+
+        assert!(object.starts_with(b"\x7fELF"));
+    }
+
+    #[test]
+    fn emit_to_memory() {
+        let source_code = "
+            pub fn main() u8 {
+                ret 0
+            }
+        ";
+        let tokens = Lexer::lex(source_code).unwrap();
+        let typed_program = Typer::new().type_program(&Parser::parse_program(&Arena::new(), &tokens).unwrap()).unwrap();
+
+        let mut compiler = Compiler::new(CompilerOptions::default()).unwrap();
+        compiler.compile(&typed_program).unwrap();
+        let object = compiler.emit_to_memory().unwrap();
+
+        let object_path = std::env::temp_dir().join(format!("flick-memory-{}.o", std::process::id()));
+        compiler.to_file(&object_path).unwrap();
+        let object_from_file = std::fs::read(&object_path).unwrap();
+        std::fs::remove_file(&object_path).unwrap();
+
+        assert!(object.starts_with(b"\x7fELF"));
+        assert!(object == object_from_file, "the in-memory object differs from the one on disk");
+    }
+
+    #[test]
+    fn unwritable_output() {
+        let tokens = Lexer::lex("pub fn main() u8 {\n    ret 0\n}\n").unwrap();
+        let typed_program = Typer::new().type_program(&Parser::parse_program(&Arena::new(), &tokens).unwrap()).unwrap();
+
+        let mut compiler = Compiler::new(CompilerOptions::default()).unwrap();
+        compiler.compile(&typed_program).unwrap();
+
+        let path = std::env::temp_dir().join(format!("flick-missing-{}", std::process::id())).join("main.o");
+        for kind in [EmitKind::Object, EmitKind::Assembly, EmitKind::Bitcode] {
+            let result = compiler.to_file_as(&path, kind);
+            assert!(
+                matches!(&result, Err(CompilationError::UnwritableOutput { path: Some(p), .. }) if *p == path),
+                "{:?}: {:?}",
+                kind,
+                result
+            );
         }
+
+        // Through the Backend trait (like `flick build` emits), too
+        let backend: &dyn Backend = &compiler;
+        assert!(matches!(backend.to_file(&path), Err(CompilationError::UnwritableOutput { .. })));
+        assert!(matches!(backend.to_file_as(&path, EmitKind::Assembly), Err(CompilationError::UnwritableOutput { .. })));
     }
-}
 
-/// As suggested by Clippy's [new_without_default][a], since [Compiler::new()] doesn't
-/// take any arguments, Compiler should implement Default.
-///
-/// [a]: https://rust-lang.github.io/rust-clippy/master/index.html#/new_without_default
-impl Default for Compiler {
-    fn default() -> Self {
-        Compiler::new()
+    #[test]
+    fn windows_msvc_target() {
+        let source_code = "
+            pub fn main() u8 {
+                ret 0
+            }
+        ";
+        let tokens = Lexer::lex(source_code).unwrap();
+        let typed_program = Typer::new().type_program(&Parser::parse_program(&Arena::new(), &tokens).unwrap()).unwrap();
+
+        let options = CompilerOptions::builder()
+            .target("x86_64-pc-windows-msvc")
+            .debug_info(true)
+            .build();
+        let mut compiler = Compiler::new(options).unwrap();
+        compiler.compile(&typed_program).unwrap();
+        assert!(compiler.module.print_to_string().contains("\"CodeView\""));
+
+        // A COFF object starts with the machine type, which is 0x8664 for x86-64
+        let object = compiler.emit_to_memory().unwrap();
+        assert!(object.starts_with(b"\x64\x86"));
+
+        // Paths with spaces and non-ASCII characters (common on Windows) are written as-is
+        let object_dir = std::env::temp_dir().join(format!("flick msvc ü-{}", std::process::id()));
+        std::fs::create_dir_all(&object_dir).unwrap();
+        let object_path = object_dir.join("main.obj");
+        compiler.to_file(&object_path).unwrap();
+        assert!(std::fs::read(&object_path).unwrap() == object);
+        std::fs::remove_dir_all(&object_dir).unwrap();
+    }
+
+    #[test]
+    fn toolchain_metadata() {
+        let source_code = "
+            pub fn main() u8 {
+                ret 0
+            }
+        ";
+        let options = CompilerOptions::builder()
+            .opt_level(OptLevel::O2)
+            .source_file_name("zero.fl")
+            .debug_info(true)
+            .build();
+        let ir = compile_to_ir(source_code, options.clone());
+
+        assert!(ir.contains("source_filename = \"zero.fl\""));
+        assert!(ir.contains(concat!("!{!\"flick version ", env!("CARGO_PKG_VERSION"), "\"}")));
+        assert!(ir.contains("!{!\"flick -O2 -g\"}"));
+
+        let tokens = Lexer::lex(source_code).unwrap();
+        let typed_program = Typer::new().type_program(&Parser::parse_program(&Arena::new(), &tokens).unwrap()).unwrap();
+        let mut compiler = Compiler::new(options).unwrap();
+        compiler.compile(&typed_program).unwrap();
+        let object = compiler.emit_to_memory().unwrap();
+        let ident = concat!("flick version ", env!("CARGO_PKG_VERSION"));
+        assert!(object.windows(ident.len()).any(|window| window == ident.as_bytes()));
+    }
+
+    #[test]
+    fn optimizing_promotes_variables_to_registers() {
+        let source_code = "
+            fn sum_to(u64 n) u64 {
+                u64 sum = 0
+                u64 i = 1
+                while i <= n {
+                    sum += i
+                    i += 1
+                }
+                ret sum
+            }
+
+            pub fn main() u8 {
+                ret (u8) sum_to(10)
+            }
+        ";
+        let tokens = Lexer::lex(source_code).unwrap();
+        let typed_program = Typer::new().type_program(&Parser::parse_program(&Arena::new(), &tokens).unwrap()).unwrap();
+
+        let mut compiler = Compiler::new(CompilerOptions::default()).unwrap();
+        compiler.compile(&typed_program).unwrap();
+        assert!(compiler.module.print_to_string().contains("alloca"));
+        compiler.optimize().unwrap();
+        assert!(!compiler.module.print_to_string().contains("alloca"));
+    }
+
+    #[test]
+    fn loops_without_vectorization_or_unrolling() {
+        let source_code = "
+            pub fn main() u8 {
+                u8 product = 1
+                u8 i = 1
+                u8 limit = 5
+                while i <= limit {
+                    product *= i
+                    i += 1
+                }
+                ret product
+            }
+        ";
+        let options = CompilerOptions::builder()
+            .opt_level(OptLevel::O3)
+            .disable_vectorization(true)
+            .disable_loop_unrolling(true)
+            .build();
+        assert_eq!(options.command_line_flags(), "-O3 --no-vectorize --no-unroll");
+        assert_eq!(compile_and_run_with_options("no_vectorize_or_unroll", source_code, options), 120);
+    }
+
+    #[test]
+    fn custom_pass_pipeline() {
+        let source_code = "
+            pub fn main() u8 {
+                u8 x = 4
+                ret x
+            }
+        ";
+        let tokens = Lexer::lex(source_code).unwrap();
+        let typed_program = Typer::new().type_program(&Parser::parse_program(&Arena::new(), &tokens).unwrap()).unwrap();
+
+        let options = CompilerOptions::builder().opt_level(OptLevel::O0).passes("sroa").build();
+        let mut compiler = Compiler::new(options).unwrap();
+        compiler.compile(&typed_program).unwrap();
+        compiler.optimize().unwrap();
+        assert!(!compiler.module.print_to_string().contains("alloca"));
+
+        let options = CompilerOptions::builder().passes("sroa,not-a-pass").build();
+        let mut compiler = Compiler::new(options).unwrap();
+        compiler.compile(&typed_program).unwrap();
+        let result = compiler.optimize();
+        assert!(matches!(result, Err(CompilationError::InvalidPassPipeline { passes, .. }) if passes == "sroa,not-a-pass"));
+    }
+
+    #[test]
+    fn optimizing_for_size() {
+        let source_code = "
+            pub fn main() u8 {
+                u8 x = 6
+                ret x * x
+            }
+        ";
+        let ir = compile_to_ir(source_code, CompilerOptions::builder().opt_level(OptLevel::Oz).build());
+        assert!(ir.contains("minsize") && ir.contains("optsize"));
+
+        let ir = compile_to_ir(source_code, CompilerOptions::builder().opt_level(OptLevel::Os).build());
+        assert!(!ir.contains("minsize") && ir.contains("optsize"));
+
+        let options = CompilerOptions::builder().opt_level(OptLevel::Oz).build();
+        assert_eq!(compile_and_run_with_options("optimizing_for_size", source_code, options), 36);
+    }
+
+    #[test]
+    fn breakpoint_is_a_debug_trap() {
+        let source_code = "
+            pub fn main() u8 {
+                breakpoint()
+                ret 0
+            }
+        ";
+        let ir = compile_to_ir(source_code, CompilerOptions::default());
+        assert!(ir.contains("call void @llvm.debugtrap()"));
+    }
+
+    #[test]
+    fn program_args() {
+        let source_code = "
+            extern fn strlen(str s) u64
+
+            pub fn main() u8 {
+                u64 zero = 0
+                if strlen(arg(0)) == zero {
+                    ret 100
+                }
+                ret (u8) arg_count() + (u8) strlen(arg(7))
+            }
+        ";
+        let ir = compile_to_ir(source_code, CompilerOptions::default());
+        assert!(ir.contains("define i8 @main(i32 %0, "));
+        // The program is run without any arguments, so `argv` is just its name
+        assert_eq!(compile_and_run("program_args", source_code), 1);
+    }
+
+    #[test]
+    fn function_ir() {
+        let source_code = "
+            fn square(u8 x) u8 {
+                ret x * x
+            }
+
+            pub fn main() u8 {
+                ret square(3)
+            }
+        ";
+        let tokens = Lexer::lex(source_code).unwrap();
+        let typed_program = Typer::new().type_program(&Parser::parse_program(&Arena::new(), &tokens).unwrap()).unwrap();
+
+        let mut compiler = Compiler::new(CompilerOptions::builder().opt_level(OptLevel::O2).build()).unwrap();
+        compiler.compile(&typed_program).unwrap();
+        let square_ir = compiler.function_ir_to_string("square").unwrap();
+        assert!(square_ir.trim_start().starts_with("define internal i8 @square("));
+        assert!(!square_ir.contains("@main"));
+        assert_eq!(compiler.function_ir_to_string("cube"), None);
+
+        // Once `square` is inlined into `main`, it's removed
+        compiler.optimize().unwrap();
+        assert_eq!(compiler.function_ir_to_string("square"), None);
+        assert!(compiler.function_ir_to_string("main").unwrap().contains("ret i8 9"));
     }
 }
@@ -0,0 +1,236 @@
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fmt::{self, Write};
+use std::path::PathBuf;
+
+use crate::ast::{FuncDef, GlobalStatement, Program, Statement};
+use crate::diagnostics::source_map::SourceMap;
+use crate::typing::typed_ast::{Coverage, TypedGlobalStatement, TypedProgram, TypedStatement};
+
+/// Instruments `typed_program` (the typed version of `program`, whose files are in `sources`)
+/// for source coverage: every statement gets a counter of how many times it runs (a
+/// [TypedStatement::Count] right before it), and the program writes every counter to a profile
+/// at `profile_path` when it exits (see [Profile]).
+///
+/// ```
+/// use flick::ast::Arena;
+/// use flick::{coverage, Parser, Lexer, SourceMap, Typer};
+///
+/// let source_code = "pub fn main() u8 {\n    u8 x = 1\n    ret x\n}\n";
+/// let (tokens, spans) = Lexer::lex_with_spans(source_code).unwrap();
+/// let arena = Arena::new();
+/// let program = Parser::parse_program_with_spans(&arena, &tokens, &spans).unwrap();
+/// let mut typed_program = Typer::new().type_program(&program).unwrap();
+///
+/// let sources = SourceMap::single_file("main.fl", source_code.to_string());
+/// coverage::instrument(&program, &mut typed_program, &sources, "main.flcov");
+/// let coverage = typed_program.coverage.unwrap();
+/// assert_eq!(coverage.locations, ["main.fl:2:8", "main.fl:3:5"]);
+/// ```
+pub fn instrument(program: &Program, typed_program: &mut TypedProgram, sources: &SourceMap, profile_path: &str) {
+    let func_defs: HashMap<&str, &FuncDef> = program
+        .global_statements
+        .iter()
+        .filter_map(|global_statement| match global_statement {
+            GlobalStatement::FuncDef(func_def) => Some((func_def.proto.name.as_str(), func_def)),
+            GlobalStatement::Extern(_) => None,
+        })
+        .collect();
+
+    let mut instrumenter = Instrumenter { sources, locations: Vec::new() };
+    for global_statement in typed_program.global_statements.iter_mut() {
+        if let TypedGlobalStatement::FuncDef(typed_func_def) = global_statement {
+            let func_def = func_defs[typed_func_def.proto.name.as_str()];
+            instrumenter.instrument_body(&func_def.body, &mut typed_func_def.body);
+        }
+    }
+    typed_program.coverage = Some(Coverage {
+        profile_path: profile_path.to_string(),
+        locations: instrumenter.locations,
+    });
+}
+
+struct Instrumenter<'a> {
+    sources: &'a SourceMap,
+    /// Where each counter added so far is (see [Coverage::locations])
+    locations: Vec<String>,
+}
+
+impl Instrumenter<'_> {
+    /// Adds a counter before each statement of `typed_body`, which is the typed version of `body`.
+    ///
+    /// The typer keeps the statements of each body in order, and only ever adds statements to
+    /// the end of a function's body (like the `ret` of a `void` function), which aren't counted.
+    fn instrument_body(&mut self, body: &[Statement], typed_body: &mut Vec<TypedStatement>) {
+        let mut typed_statements = std::mem::take(typed_body).into_iter();
+        for (statement, mut typed_statement) in body.iter().zip(typed_statements.by_ref()) {
+            let (file, span) = self.sources.locate(statement.span());
+            let position = span.start_position(&file.source_code);
+            typed_body.push(TypedStatement::Count(self.locations.len()));
+            self.locations.push(format!("{}:{}", file.path.display(), position));
+
+            match (statement, &mut typed_statement) {
+                (Statement::If(if_statement), TypedStatement::If(typed_if)) => {
+                    self.instrument_body(&if_statement.then_body, &mut typed_if.then_body);
+                    if let (Some(else_body), Some(typed_else_body)) = (&if_statement.else_body, &mut typed_if.else_body) {
+                        self.instrument_body(else_body, typed_else_body);
+                    }
+                }
+                (Statement::WhileLoop(while_loop), TypedStatement::WhileLoop(typed_while_loop)) => {
+                    self.instrument_body(&while_loop.body, &mut typed_while_loop.body);
+                }
+                _ => {}
+            }
+
+            typed_body.push(typed_statement);
+        }
+        typed_body.extend(typed_statements);
+    }
+}
+
+/// A coverage profile, which an [instrumented](instrument) program writes when it exits: a line
+/// for each statement, with where the statement is and how many times it ran, like:
+///
+/// ```text
+/// src/main.fl:2:8 1
+/// src/main.fl:4:9 0
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Profile {
+    /// How many times the statements on each line ran (the most that any of them did), by file
+    pub files: BTreeMap<PathBuf, FileCoverage>,
+}
+
+impl Profile {
+    /// Reads a profile, returning an error if a line isn't a location and a count.
+    ///
+    /// ```
+    /// use flick::coverage::Profile;
+    /// let profile = Profile::parse("main.fl:2:5 3\nmain.fl:3:5 0\n").unwrap();
+    /// let main = &profile.files[std::path::Path::new("main.fl")];
+    /// assert_eq!(main.covered_lines(), 1);
+    /// assert_eq!(main.lines.len(), 2);
+    /// ```
+    pub fn parse(profile: &str) -> Result<Self, ProfileError> {
+        let mut files: BTreeMap<PathBuf, FileCoverage> = BTreeMap::new();
+        for (i, line) in profile.lines().enumerate() {
+            let parse_line = || {
+                let (location, count) = line.rsplit_once(' ')?;
+                let mut parts = location.rsplitn(3, ':');
+                let (_column, line_number, path) = (parts.next()?, parts.next()?, parts.next()?);
+                Some((PathBuf::from(path), line_number.parse().ok()?, count.parse().ok()?))
+            };
+            let (path, line_number, count): (_, usize, u64) = parse_line().ok_or(ProfileError { line: i + 1 })?;
+            let line_count = files.entry(path).or_default().lines.entry(line_number).or_default();
+            *line_count = (*line_count).max(count);
+        }
+        Ok(Self { files })
+    }
+}
+
+/// The coverage of one file in a [Profile].
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct FileCoverage {
+    /// How many times the statements on each line (numbered from 1) ran, for the lines that
+    /// have statements
+    pub lines: BTreeMap<usize, u64>,
+}
+
+impl FileCoverage {
+    /// Returns how many of the lines with statements ran at least once.
+    pub fn covered_lines(&self) -> usize {
+        self.lines.values().filter(|&&count| count > 0).count()
+    }
+
+    /// Returns `source_code` (the file's) with how many times each line ran before it, like
+    /// `gcov`: `-` for lines without statements, and `#####` for lines that never ran.
+    ///
+    /// ```
+    /// use flick::coverage::Profile;
+    /// let profile = Profile::parse("main.fl:2:5 1\nmain.fl:3:5 0\n").unwrap();
+    /// let annotated = profile.files[std::path::Path::new("main.fl")].annotate("fn f() {\n    ret\n    ret\n}\n");
+    /// let lines: Vec<_> = annotated.lines().collect();
+    /// assert_eq!(lines, [
+    ///     "        -:    1:fn f() {",
+    ///     "        1:    2:    ret",
+    ///     "    #####:    3:    ret",
+    ///     "        -:    4:}",
+    /// ]);
+    /// ```
+    pub fn annotate(&self, source_code: &str) -> String {
+        let mut annotated = String::new();
+        for (i, line) in source_code.lines().enumerate() {
+            let count = match self.lines.get(&(i + 1)) {
+                None => "-".to_string(),
+                Some(0) => "#####".to_string(),
+                Some(count) => count.to_string(),
+            };
+            writeln!(annotated, "{:>9}:{:>5}:{}", count, i + 1, line).unwrap();
+        }
+        annotated
+    }
+}
+
+/// An error saying that a line of a [Profile] isn't a location and a count.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ProfileError {
+    /// The number of the line, starting at 1
+    pub line: usize,
+}
+
+impl fmt::Display for ProfileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {} of the profile isn't a location and a count", self.line)
+    }
+}
+
+impl Error for ProfileError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Lexer, Parser, Typer};
+    use crate::ast::Arena;
+
+    #[test]
+    fn counts_every_statement() {
+        let source_code = "\
+fn f(u64 n) {
+    while n > 0 {
+        if n == 2 {
+            ret
+        } else {
+            n -= 1
+        }
+    }
+}
+
+pub fn main() u8 {
+    f(3)
+    ret 0
+}
+"
+        .to_string();
+        let (tokens, spans) = Lexer::lex_with_spans(&source_code).unwrap();
+        let arena = Arena::new();
+        let program = Parser::parse_program_with_spans(&arena, &tokens, &spans).unwrap();
+        let mut typed_program = Typer::new().type_program(&program).unwrap();
+        instrument(&program, &mut typed_program, &SourceMap::single_file("f.fl", source_code.clone()), "f.flcov");
+
+        let coverage = typed_program.coverage.as_ref().unwrap();
+        assert_eq!(coverage.locations, ["f.fl:2:5", "f.fl:3:9", "f.fl:4:13", "f.fl:6:13", "f.fl:12:5", "f.fl:13:5"]);
+        let TypedGlobalStatement::FuncDef(f) = &typed_program.global_statements[0] else { panic!() };
+        // The implicit `ret` at the end of `f` isn't counted
+        assert!(matches!(f.body[..], [TypedStatement::Count(0), TypedStatement::WhileLoop(_), TypedStatement::Return(None)]));
+    }
+
+    #[test]
+    fn profiles() {
+        let profile = Profile::parse("a.fl:1:5 2\na.fl:1:9 3\nC:\\b.fl:2:1 0\n").unwrap();
+        assert_eq!(profile.files[&PathBuf::from("a.fl")].lines, BTreeMap::from([(1, 3)]));
+        assert_eq!(profile.files[&PathBuf::from("C:\\b.fl")].lines, BTreeMap::from([(2, 0)]));
+
+        assert_eq!(Profile::parse("a.fl:1:5 2\na.fl:1 3\n"), Err(ProfileError { line: 2 }));
+        assert_eq!(Profile::parse("a.fl:1:5 many\n"), Err(ProfileError { line: 1 }));
+    }
+}
@@ -0,0 +1,102 @@
+use std::error::Error;
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::types::Type;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum CompilationError {
+    /// LLVM rejected the generated module; contains the verifier's message.
+    InvalidModule(String),
+    /// An LLVM backend couldn't be initialized; contains the name of the failing component.
+    TargetInitialization(&'static str),
+    /// The target triple isn't supported by this build of LLVM.
+    UnknownTarget { triple: String, message: String },
+    /// LLVM couldn't create a target machine for the target, CPU, and features.
+    TargetMachineCreation { triple: String },
+    /// LLVM couldn't parse or run a pass pipeline, like one from [CompilerOptions::passes].
+    ///
+    /// [CompilerOptions::passes]: crate::CompilerOptions::passes
+    InvalidPassPipeline { passes: String, message: String },
+    /// The program uses something that a backend (like the C backend) can't generate code for.
+    UnsupportedByBackend { backend: &'static str, message: String },
+    /// A source file couldn't be read, e.g. by [compile_file](crate::compile_file).
+    UnreadableSource { path: PathBuf, message: String },
+    /// A [profiling hook](crate::profiling::instrument_functions) that the program declares (or
+    /// defines) doesn't have the parameters and return type that the hook must have.
+    InvalidProfilingHook { name: &'static str, expected: &'static str },
+    /// LLVM couldn't create the JIT compiler for an [Engine](crate::Engine); contains LLVM's
+    /// message.
+    ExecutionEngineCreation(String),
+    /// An [Engine](crate::Engine) was asked to call a function that the program doesn't define.
+    UnknownFunction(String),
+    /// An [Engine](crate::Engine) was asked to call a function (whose prototype is `proto`)
+    /// without arguments, and to return a `return_type`, which the function doesn't.
+    UncallableFunction { proto: String, return_type: Box<Type> },
+    /// The output file couldn't be generated or written to `path` (or to memory, if `path` is
+    /// `None`); contains the reason, e.g. the OS's or LLVM's message.
+    UnwritableOutput { path: Option<PathBuf>, message: String },
+}
+
+impl CompilationError {
+    /// Returns the code that identifies this kind of error, like `E0401` (see
+    /// [Diagnostic::code](crate::Diagnostic::code)).
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::InvalidModule(_) => "E0401",
+            Self::TargetInitialization(_) => "E0402",
+            Self::UnknownTarget { .. } => "E0403",
+            Self::TargetMachineCreation { .. } => "E0404",
+            Self::InvalidPassPipeline { .. } => "E0405",
+            Self::UnsupportedByBackend { .. } => "E0406",
+            Self::UnreadableSource { .. } => "E0407",
+            Self::InvalidProfilingHook { .. } => "E0408",
+            Self::ExecutionEngineCreation(_) => "E0409",
+            Self::UnknownFunction(_) => "E0410",
+            Self::UncallableFunction { .. } => "E0411",
+            Self::UnwritableOutput { .. } => "E0412",
+        }
+    }
+}
+
+impl fmt::Display for CompilationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidModule(message) => write!(f, "generated invalid LLVM module: {}", message.trim_end()),
+            Self::TargetInitialization(component) => write!(f, "failed to initialize the {}", component),
+            Self::UnknownTarget { triple, message } => {
+                write!(f, "unknown target '{}': {}", triple, message.trim_end())
+            }
+            Self::TargetMachineCreation { triple } => {
+                write!(f, "failed to create a target machine for '{}'", triple)
+            }
+            Self::InvalidPassPipeline { passes, message } => {
+                write!(f, "invalid pass pipeline '{}': {}", passes, message.trim_end())
+            }
+            Self::UnsupportedByBackend { backend, message } => {
+                write!(f, "unsupported by the {} backend: {}", backend, message)
+            }
+            Self::UnreadableSource { path, message } => write!(f, "couldn't read '{}': {}", path.display(), message),
+            Self::InvalidProfilingHook { name, expected } => {
+                write!(f, "the profiling hook '{}' must be declared like '{}'", name, expected)
+            }
+            Self::ExecutionEngineCreation(message) => {
+                write!(f, "failed to create a JIT compiler: {}", message.trim_end())
+            }
+            Self::UnknownFunction(name) => write!(f, "there's no function named '{}' to call", name),
+            Self::UncallableFunction { proto, return_type } => write!(
+                f,
+                "'{}' can't be called as a function that takes no arguments and returns '{}'",
+                proto, return_type
+            ),
+            Self::UnwritableOutput { path: Some(path), message } => {
+                write!(f, "couldn't write '{}': {}", path.display(), message.trim_end())
+            }
+            Self::UnwritableOutput { path: None, message } => {
+                write!(f, "couldn't generate the output file: {}", message.trim_end())
+            }
+        }
+    }
+}
+
+impl Error for CompilationError {}
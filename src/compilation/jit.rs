@@ -0,0 +1,285 @@
+use std::collections::HashMap;
+use std::ffi::{c_char, CStr};
+
+use crate::ast::{FuncProto, FuncVisibility};
+use crate::builtins::Builtin;
+use crate::compilation::compiler::Compiler;
+use crate::compilation::error::CompilationError;
+use crate::compilation::llvm::{Context, ExecutionEngine};
+use crate::compilation::options::CompilerOptions;
+use crate::lexing::name::Name;
+use crate::compilation::pipeline::{check_with_typer, Diagnostics};
+use crate::diagnostics::diagnostic::Diagnostic;
+use crate::diagnostics::source_map::SourceMap;
+use crate::typed_ast::{TypedGlobalStatement, TypedProgram};
+use crate::types::{IntType, Type};
+use crate::Typer;
+
+/// A Flick program that's been compiled into machine code in memory (by LLVM's JIT compiler), so
+/// that a Rust program can call its functions, e.g. to use Flick as a scripting language.
+///
+/// Unlike a program that's compiled on its own, the program doesn't need a `main` function, and
+/// every function can be called (with [Engine::call]), even if it isn't `pub`.
+///
+/// ```
+/// use flick::{CompilerOptions, Engine};
+///
+/// let source_code = "fn answer() i64 {\n    ret 6 * 7\n}\n\nfn greeting() str {\n    ret \"hello\"\n}\n";
+/// let engine = Engine::new(source_code, &CompilerOptions::default()).unwrap();
+/// assert_eq!(engine.call::<i64>("answer").unwrap(), 42);
+/// assert_eq!(engine.call::<String>("greeting").unwrap(), "hello");
+/// assert_eq!(engine.call::<bool>("answer").unwrap_err().code(), "E0411");
+/// ```
+pub struct Engine {
+    // Note: fields are dropped in declaration order, and the execution engine (which owns the
+    // module) must be dropped before the context that the module lives in
+    execution_engine: ExecutionEngine,
+    _context: Context,
+    /// The prototype of each function that the program defines, by name
+    protos: HashMap<Name, FuncProto>,
+    /// The warnings found while compiling the program
+    warnings: Diagnostics,
+}
+
+/// A Rust type that a Flick function can return (see [Engine::call]).
+pub trait FlickValue: Sized {
+    /// Returns the Flick type that's returned as this Rust type.
+    fn flick_type() -> Type;
+
+    /// Calls the function whose machine code is at `address`.
+    ///
+    /// # Safety
+    /// `address` must be the address of a function that takes no arguments and returns a
+    /// [FlickValue::flick_type], like one found by [Engine::call].
+    unsafe fn call(address: usize) -> Self;
+}
+
+impl Engine {
+    /// Compiles `source_code` for the machine that the compiler is running on (so `options`
+    /// shouldn't give another [target](CompilerOptions::target_triple)), returning every error
+    /// (and warning) found if it can't be compiled.
+    ///
+    /// Diagnostics point into a file named after [CompilerOptions::source_file_name] (or
+    /// `<input>`, if it isn't given).
+    pub fn new(source_code: &str, options: &CompilerOptions) -> Result<Self, Diagnostics> {
+        let path = options.source_file_name.as_deref().unwrap_or("<input>");
+        let sources = SourceMap::single_file(path, source_code.to_string());
+        let mut diagnostics = Vec::new();
+        let engine = check_with_typer(&sources, &mut diagnostics, Typer::without_main()).and_then(|typed_program| {
+            match Self::compile(typed_program, options) {
+                Ok(engine) => Some(engine),
+                Err(err) => {
+                    diagnostics.push(Diagnostic::from(&err));
+                    None
+                }
+            }
+        });
+        let diagnostics = Diagnostics { sources, diagnostics };
+        match engine {
+            Some(mut engine) => {
+                engine.warnings = diagnostics;
+                Ok(engine)
+            }
+            None => Err(diagnostics),
+        }
+    }
+
+    /// Compiles and optimizes `typed_program`, and generates its machine code.
+    fn compile(mut typed_program: TypedProgram, options: &CompilerOptions) -> Result<Self, CompilationError> {
+        if let Some(builtin) = Builtin::ALL.iter().find(|builtin| builtin.reads_args() && typed_program.calls(builtin.name())) {
+            let message = format!("an embedded program has no command-line arguments for '{}' to read", builtin.name());
+            return Err(CompilationError::UnsupportedByBackend { backend: "JIT", message });
+        }
+
+        let mut protos = HashMap::new();
+        for global_statement in &mut typed_program.global_statements {
+            if let TypedGlobalStatement::FuncDef(func_def) = global_statement {
+                protos.insert(func_def.proto.name, func_def.proto.clone());
+                // Otherwise, LLVM could inline a private function everywhere and remove it
+                func_def.proto.func_visibility = FuncVisibility::Public;
+            }
+        }
+
+        let mut compiler = Compiler::new(options.clone())?;
+        compiler.compile(&typed_program)?;
+        compiler.optimize()?;
+        let (execution_engine, context) = compiler.into_execution_engine()?;
+        let warnings = Diagnostics { sources: SourceMap::default(), diagnostics: Vec::new() };
+        Ok(Self { execution_engine, _context: context, protos, warnings })
+    }
+
+    /// Returns the warnings found while compiling the program.
+    pub fn warnings(&self) -> &Diagnostics {
+        &self.warnings
+    }
+
+    /// Calls the function named `name`, which has to take no arguments and return the Flick type
+    /// that corresponds to `T` (see [FlickValue]), returning what it returns.
+    ///
+    /// Returns an `Err()` if the program doesn't define the function, or if its prototype
+    /// doesn't match.
+    pub fn call<T: FlickValue>(&self, name: &str) -> Result<T, CompilationError> {
+        let proto = self.protos.get(name).ok_or_else(|| CompilationError::UnknownFunction(name.to_string()))?;
+        if !proto.params.is_empty() || *proto.return_type != T::flick_type() {
+            return Err(CompilationError::UncallableFunction { proto: proto.to_string(), return_type: Box::new(T::flick_type()) });
+        }
+        let address = self
+            .execution_engine
+            .function_address(name)
+            .ok_or_else(|| CompilationError::UnknownFunction(name.to_string()))?;
+        // The prototype was just checked, and the function was compiled from it
+        Ok(unsafe { T::call(address) })
+    }
+}
+
+/// Compiles `source_code` (see [Engine::new]), then calls its `main` function (see
+/// [Engine::call]) and returns what it returns, or every error found along the way.
+///
+/// ```
+/// let answer = flick::eval::<i64>("fn main() i64 {\n    ret 42\n}\n").unwrap();
+/// assert_eq!(answer, 42);
+///
+/// let errors = flick::eval::<i64>("fn main() u8 {\n    ret 42\n}\n").unwrap_err();
+/// assert_eq!(errors.errors().next().unwrap().code, "E0411");
+/// ```
+pub fn eval<T: FlickValue>(source_code: &str) -> Result<T, Diagnostics> {
+    let engine = Engine::new(source_code, &CompilerOptions::default())?;
+    engine.call("main").map_err(|err| Diagnostics {
+        sources: engine.warnings.sources.clone(),
+        diagnostics: vec![Diagnostic::from(&err)],
+    })
+}
+
+/// Calls the function at `address` as an `extern "C" fn() -> R`.
+///
+/// # Safety
+/// `address` must be the address of a function that takes no arguments and returns an `R`.
+unsafe fn call_as<R>(address: usize) -> R {
+    let func = std::mem::transmute::<usize, extern "C" fn() -> R>(address);
+    func()
+}
+
+macro_rules! impl_flick_value_for_ints {
+    ($($int:ty),*) => {
+        $(
+            impl FlickValue for $int {
+                fn flick_type() -> Type {
+                    Type::Int(IntType { signed: <$int>::MIN != 0, width: <$int>::BITS })
+                }
+
+                unsafe fn call(address: usize) -> Self {
+                    call_as(address)
+                }
+            }
+        )*
+    };
+}
+
+impl_flick_value_for_ints!(i8, i16, i32, i64, u8, u16, u32, u64);
+
+impl FlickValue for bool {
+    fn flick_type() -> Type {
+        Type::Bool
+    }
+
+    unsafe fn call(address: usize) -> Self {
+        // LLVM returns an `i1` in the lowest bit of a byte, and the other bits can be anything
+        call_as::<u8>(address) & 1 == 1
+    }
+}
+
+impl FlickValue for String {
+    fn flick_type() -> Type {
+        Type::Str
+    }
+
+    /// Copies the returned string (replacing invalid UTF-8, like [String::from_utf8_lossy]); a
+    /// null pointer is an empty string.
+    unsafe fn call(address: usize) -> Self {
+        let string = call_as::<*const c_char>(address);
+        match string.is_null() {
+            true => String::new(),
+            false => CStr::from_ptr(string).to_string_lossy().into_owned(),
+        }
+    }
+}
+
+impl FlickValue for () {
+    fn flick_type() -> Type {
+        Type::Void
+    }
+
+    unsafe fn call(address: usize) -> Self {
+        call_as(address)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calls_functions_with_each_return_type() {
+        let source_code = "\
+fn small() i8 {
+    ret -5
+}
+
+fn big() u64 {
+    ret 18446744073709551615
+}
+
+fn yes() bool {
+    ret 1 < 2
+}
+
+fn no() bool {
+    ret false
+}
+
+fn nothing() {
+}
+
+pub fn name() str {
+    ret \"flick\"
+}
+";
+        let engine = Engine::new(source_code, &CompilerOptions::default()).unwrap();
+        assert_eq!(engine.call::<i8>("small").unwrap(), -5);
+        assert_eq!(engine.call::<u64>("big").unwrap(), u64::MAX);
+        assert!(engine.call::<bool>("yes").unwrap());
+        assert!(!engine.call::<bool>("no").unwrap());
+        engine.call::<()>("nothing").unwrap();
+        assert_eq!(engine.call::<String>("name").unwrap(), "flick");
+    }
+
+    #[test]
+    fn private_functions_can_be_called_after_being_inlined() {
+        let source_code = "fn double(i64 x) i64 {\n    ret x * 2\n}\n\nfn main() i64 {\n    ret double(21)\n}\n";
+        let options = CompilerOptions { opt_level: crate::OptLevel::O3, ..CompilerOptions::default() };
+        let engine = Engine::new(source_code, &options).unwrap();
+        assert_eq!(engine.call::<i64>("main").unwrap(), 42);
+        assert_eq!(engine.warnings().diagnostics, []);
+    }
+
+    #[test]
+    fn only_defined_functions_with_matching_prototypes_can_be_called() {
+        let source_code = "extern fn abs(i32 x) i32\n\nfn double(i64 x) i64 {\n    ret x * 2\n}\n";
+        let engine = Engine::new(source_code, &CompilerOptions::default()).unwrap();
+        assert_eq!(engine.call::<i32>("abs"), Err(CompilationError::UnknownFunction("abs".to_string())));
+        let err = engine.call::<i64>("double").unwrap_err();
+        assert_eq!(err.to_string(), "'fn double(i64 x) i64' can't be called as a function that takes no arguments and returns 'i64'");
+    }
+
+    #[test]
+    fn reports_errors_in_the_program() {
+        let errors = eval::<i64>("fn main() i64 {\n    ret x\n}\n").unwrap_err();
+        assert_eq!(errors.errors().next().unwrap().code, "E0201");
+
+        let errors = eval::<u32>("fn main() u32 {\n    ret arg_count()\n}\n").unwrap_err();
+        assert_eq!(errors.errors().next().unwrap().code, "E0406");
+
+        let errors = eval::<i64>("fn answer() i64 {\n    ret 42\n}\n").unwrap_err();
+        assert_eq!(errors.errors().next().unwrap().code, "E0410");
+    }
+}
@@ -0,0 +1,1033 @@
+//! A thin, safe layer over the parts of llvm-sys that the [Compiler](crate::Compiler) uses.
+//!
+//! Objects that LLVM expects us to free (like [Context] and [Builder]) are owned types that
+//! dispose of themselves when dropped. Everything else (like [Value] and [BasicBlock]) is a
+//! `Copy` handle that borrows the [Context] it was created in, so the borrow checker makes sure
+//! that no handle outlives the LLVM objects it points into.
+//!
+//! All of the `unsafe` calls into llvm-sys live in this module, and so do the (few) differences
+//! between the LLVM versions that the `llvm-*` features choose from.
+
+use std::ffi::{c_char, c_uint, CStr, CString};
+use std::marker::PhantomData;
+use std::path::Path;
+use std::ptr;
+use std::sync::Once;
+
+use llvm_sys::analysis::LLVMVerifierFailureAction::LLVMReturnStatusAction;
+use llvm_sys::analysis::LLVMVerifyModule;
+use llvm_sys::bit_writer::LLVMWriteBitcodeToMemoryBuffer;
+use llvm_sys::core::*;
+use llvm_sys::debuginfo::*;
+use llvm_sys::error::{LLVMDisposeErrorMessage, LLVMGetErrorMessage};
+use llvm_sys::execution_engine::{
+    LLVMCreateJITCompilerForModule, LLVMDisposeExecutionEngine, LLVMExecutionEngineRef, LLVMGetFunctionAddress,
+    LLVMLinkInMCJIT,
+};
+use llvm_sys::support::LLVMParseCommandLineOptions;
+use llvm_sys::prelude::*;
+use llvm_sys::target::{
+    LLVMDisposeTargetData, LLVMInitializeWebAssemblyAsmParser, LLVMInitializeWebAssemblyAsmPrinter,
+    LLVMInitializeWebAssemblyTarget, LLVMInitializeWebAssemblyTargetInfo,
+    LLVMInitializeWebAssemblyTargetMC, LLVMSetModuleDataLayout, LLVM_InitializeAllAsmParsers,
+    LLVM_InitializeAllAsmPrinters, LLVM_InitializeAllTargetInfos, LLVM_InitializeAllTargetMCs,
+    LLVM_InitializeAllTargets, LLVM_InitializeNativeAsmParser, LLVM_InitializeNativeAsmPrinter,
+    LLVM_InitializeNativeTarget,
+};
+use llvm_sys::target_machine::LLVMCodeModel::LLVMCodeModelDefault;
+use llvm_sys::target_machine::{
+    LLVMCodeGenFileType, LLVMCodeGenOptLevel, LLVMCreateTargetDataLayout, LLVMCreateTargetMachine,
+    LLVMDisposeTargetMachine, LLVMGetDefaultTargetTriple, LLVMGetHostCPUFeatures,
+    LLVMGetHostCPUName, LLVMGetTargetFromTriple, LLVMRelocMode, LLVMTargetMachineEmitToFile,
+    LLVMTargetMachineEmitToMemoryBuffer, LLVMTargetMachineRef,
+};
+use llvm_sys::transforms::pass_builder::*;
+use llvm_sys::{LLVMAttributeFunctionIndex, LLVMIntPredicate, LLVMLinkage, LLVMModuleFlagBehavior};
+use llvm_sys::LLVMUnnamedAddr::LLVMGlobalUnnamedAddr;
+
+/// Converts `s` into a [CString], panicking if it contains a null byte.
+fn to_cstring(s: &str) -> CString {
+    CString::new(s).unwrap_or_else(|_| panic!("'{}' shouldn't contain null bytes", s))
+}
+
+/// Converts `path` into the null-terminated string that LLVM's file APIs expect.
+///
+/// On Unix, paths are arbitrary bytes, which are passed through unchanged. Elsewhere (i.e. on
+/// Windows), LLVM expects UTF-8, which it converts to the native UTF-16 when opening the file.
+fn path_to_cstring(path: &Path) -> CString {
+    #[cfg(unix)]
+    let bytes = std::os::unix::ffi::OsStrExt::as_bytes(path.as_os_str()).to_vec();
+    #[cfg(not(unix))]
+    let bytes = match path.to_str() {
+        Some(path) => path.as_bytes().to_vec(),
+        None => panic!("'{}' isn't valid Unicode", path.display()),
+    };
+    CString::new(bytes).unwrap_or_else(|_| panic!("'{}' shouldn't contain null bytes", path.display()))
+}
+
+/// Copies a string allocated by LLVM into a [String], then frees the original.
+///
+/// # Safety
+/// `message` must be a string allocated by LLVM that nothing else frees.
+unsafe fn take_message(message: *mut c_char) -> String {
+    let owned = CStr::from_ptr(message).to_string_lossy().into_owned();
+    LLVMDisposeMessage(message);
+    owned
+}
+
+/// Copies the contents of an LLVM memory buffer into a [Vec], then frees the buffer.
+///
+/// # Safety
+/// `buffer` must be a memory buffer that nothing else frees.
+unsafe fn take_memory_buffer(buffer: LLVMMemoryBufferRef) -> Vec<u8> {
+    let start = LLVMGetBufferStart(buffer) as *const u8;
+    let bytes = std::slice::from_raw_parts(start, LLVMGetBufferSize(buffer)).to_vec();
+    LLVMDisposeMemoryBuffer(buffer);
+    bytes
+}
+
+/// Initializes the LLVM backend for the machine that the compiler is running on, returning
+/// the name of the component that failed (if any).
+pub fn initialize_native_target() -> Result<(), &'static str> {
+    unsafe {
+        if LLVM_InitializeNativeTarget() == 1 {
+            return Err("native target");
+        }
+        if LLVM_InitializeNativeAsmParser() == 1 {
+            return Err("native ASM parser");
+        }
+        if LLVM_InitializeNativeAsmPrinter() == 1 {
+            return Err("native ASM printer");
+        }
+    }
+    Ok(())
+}
+
+/// Initializes every LLVM backend that llvm-sys was built with.
+pub fn initialize_all_targets() {
+    unsafe {
+        LLVM_InitializeAllTargetInfos();
+        LLVM_InitializeAllTargets();
+        LLVM_InitializeAllTargetMCs();
+        LLVM_InitializeAllAsmParsers();
+        LLVM_InitializeAllAsmPrinters();
+    }
+}
+
+/// Initializes just the WebAssembly LLVM backend.
+pub fn initialize_wasm_target() {
+    unsafe {
+        LLVMInitializeWebAssemblyTargetInfo();
+        LLVMInitializeWebAssemblyTarget();
+        LLVMInitializeWebAssemblyTargetMC();
+        LLVMInitializeWebAssemblyAsmParser();
+        LLVMInitializeWebAssemblyAsmPrinter();
+    }
+}
+
+/// Makes LLVM report (on stderr) how long each pass takes whenever it runs a pass pipeline.
+///
+/// LLVM's options are global to the process, and can only be parsed once, so calling this more
+/// than once has no further effect.
+pub fn enable_pass_timing() {
+    static ENABLE_PASS_TIMING: Once = Once::new();
+    ENABLE_PASS_TIMING.call_once(|| {
+        let args = [to_cstring("flick"), to_cstring("-time-passes")];
+        let arg_pointers: Vec<_> = args.iter().map(|arg| arg.as_ptr()).collect();
+        unsafe { LLVMParseCommandLineOptions(arg_pointers.len() as i32, arg_pointers.as_ptr(), ptr::null()) }
+    });
+}
+
+/// Returns the triple of the machine that the compiler is running on.
+pub fn default_target_triple() -> String {
+    unsafe { take_message(LLVMGetDefaultTargetTriple()) }
+}
+
+/// Returns the name of the host's CPU, like `skylake`.
+pub fn host_cpu_name() -> String {
+    unsafe { take_message(LLVMGetHostCPUName()) }
+}
+
+/// Returns the host CPU's features, like `+sse2,+avx,...`.
+pub fn host_cpu_features() -> String {
+    unsafe { take_message(LLVMGetHostCPUFeatures()) }
+}
+
+/// An LLVM context, which owns all of the types and constants created in it.
+pub struct Context {
+    raw: LLVMContextRef,
+}
+
+impl Context {
+    pub fn new() -> Self {
+        Self { raw: unsafe { LLVMContextCreate() } }
+    }
+
+    /// Sets whether LLVM should drop the names of (non-global) values, to save memory.
+    pub fn set_discard_value_names(&self, discard: bool) {
+        unsafe { LLVMContextSetDiscardValueNames(self.raw, discard as LLVMBool) }
+    }
+
+    pub fn int_type(&self, width: u32) -> LlvmType<'_> {
+        unsafe { LlvmType::new(LLVMIntTypeInContext(self.raw, width)) }
+    }
+
+    pub fn bool_type(&self) -> LlvmType<'_> {
+        unsafe { LlvmType::new(LLVMInt1TypeInContext(self.raw)) }
+    }
+
+    pub fn void_type(&self) -> LlvmType<'_> {
+        unsafe { LlvmType::new(LLVMVoidTypeInContext(self.raw)) }
+    }
+
+    /// Returns the type of a pointer to a byte (`i8*`, or just `ptr` with opaque pointers).
+    pub fn byte_pointer_type(&self) -> LlvmType<'_> {
+        // LLVM 17 removed typed pointers, so there's only one pointer type per address space
+        #[cfg(any(feature = "llvm-15", feature = "llvm-16"))]
+        let pointer_type = unsafe { LLVMPointerType(LLVMInt8TypeInContext(self.raw), 0) };
+        #[cfg(not(any(feature = "llvm-15", feature = "llvm-16")))]
+        let pointer_type = unsafe { LLVMPointerTypeInContext(self.raw, 0) };
+        unsafe { LlvmType::new(pointer_type) }
+    }
+
+    /// Returns the type of a pointer to `pointee` (just `ptr` with opaque pointers).
+    pub fn pointer_type<'ctx>(&'ctx self, pointee: LlvmType<'ctx>) -> LlvmType<'ctx> {
+        #[cfg(any(feature = "llvm-15", feature = "llvm-16"))]
+        let pointer_type = unsafe { LLVMPointerType(pointee.raw, 0) };
+        #[cfg(not(any(feature = "llvm-15", feature = "llvm-16")))]
+        let pointer_type = {
+            let _ = pointee;
+            unsafe { LLVMPointerTypeInContext(self.raw, 0) }
+        };
+        unsafe { LlvmType::new(pointer_type) }
+    }
+
+    pub fn function_type<'ctx>(&'ctx self, return_type: LlvmType<'ctx>, param_types: &[LlvmType<'ctx>]) -> LlvmType<'ctx> {
+        let mut param_types: Vec<_> = param_types.iter().map(|t| t.raw).collect();
+        unsafe {
+            LlvmType::new(LLVMFunctionType(
+                return_type.raw,
+                param_types.as_mut_ptr(),
+                param_types.len() as c_uint,
+                0,
+            ))
+        }
+    }
+
+    /// Returns the type of a C-style variadic function, like `printf`, whose parameters start
+    /// with `param_types`.
+    pub fn var_arg_function_type<'ctx>(&'ctx self, return_type: LlvmType<'ctx>, param_types: &[LlvmType<'ctx>]) -> LlvmType<'ctx> {
+        let mut param_types: Vec<_> = param_types.iter().map(|t| t.raw).collect();
+        unsafe {
+            LlvmType::new(LLVMFunctionType(
+                return_type.raw,
+                param_types.as_mut_ptr(),
+                param_types.len() as c_uint,
+                1,
+            ))
+        }
+    }
+
+    /// Returns the type of an array of `len` `element_type`s.
+    pub fn array_type<'ctx>(&'ctx self, element_type: LlvmType<'ctx>, len: usize) -> LlvmType<'ctx> {
+        unsafe { LlvmType::new(LLVMArrayType(element_type.raw, len as c_uint)) }
+    }
+
+    /// Returns the zero value of `value_type` (like a null pointer).
+    pub fn const_null<'ctx>(&'ctx self, value_type: LlvmType<'ctx>) -> Value<'ctx> {
+        unsafe { Value::new(LLVMConstNull(value_type.raw)) }
+    }
+
+    /// Returns the constant `value` of the integer type `int_type`.
+    pub fn const_int<'ctx>(&'ctx self, int_type: LlvmType<'ctx>, value: u64) -> Value<'ctx> {
+        unsafe { Value::new(LLVMConstInt(int_type.raw, value, 0)) }
+    }
+
+    /// Returns the constant of the integer type `int_type` written (in base 10) as `value`.
+    pub fn const_int_from_str<'ctx>(&'ctx self, int_type: LlvmType<'ctx>, value: &str) -> Value<'ctx> {
+        let value = to_cstring(value);
+        unsafe { Value::new(LLVMConstIntOfString(int_type.raw, value.as_ptr(), 10)) }
+    }
+
+    /// Returns a constant, null-terminated array of the bytes of `s`.
+    pub fn const_string(&self, s: &str) -> Value<'_> {
+        unsafe {
+            Value::new(LLVMConstStringInContext(
+                self.raw,
+                s.as_ptr() as *const c_char,
+                s.len() as c_uint,
+                0, // add a null terminator
+            ))
+        }
+    }
+
+    /// Appends a new basic block to the end of the function `func`.
+    pub fn append_basic_block<'ctx>(&'ctx self, func: Value<'ctx>, name: &str) -> BasicBlock<'ctx> {
+        let name = to_cstring(name);
+        unsafe { BasicBlock::new(LLVMAppendBasicBlockInContext(self.raw, func.raw, name.as_ptr())) }
+    }
+
+    /// Creates a basic block that isn't in any function yet (see [Value::append_basic_block]).
+    pub fn create_basic_block(&self, name: &str) -> BasicBlock<'_> {
+        let name = to_cstring(name);
+        unsafe { BasicBlock::new(LLVMCreateBasicBlockInContext(self.raw, name.as_ptr())) }
+    }
+
+    /// Adds a `key="value"` attribute to the function `func`.
+    pub fn add_function_string_attribute<'ctx>(&'ctx self, func: Value<'ctx>, key: &str, value: &str) {
+        unsafe {
+            let attribute = LLVMCreateStringAttribute(
+                self.raw,
+                key.as_ptr() as *const c_char,
+                key.len() as c_uint,
+                value.as_ptr() as *const c_char,
+                value.len() as c_uint,
+            );
+            LLVMAddAttributeAtIndex(func.raw, LLVMAttributeFunctionIndex, attribute);
+        }
+    }
+
+    /// Adds an attribute without a value, like `minsize`, to the function `func`.
+    pub fn add_function_enum_attribute<'ctx>(&'ctx self, func: Value<'ctx>, name: &str) {
+        unsafe {
+            let kind = LLVMGetEnumAttributeKindForName(name.as_ptr() as *const c_char, name.len());
+            assert!(kind != 0, "'{}' isn't an LLVM attribute", name);
+            let attribute = LLVMCreateEnumAttribute(self.raw, kind, 0);
+            LLVMAddAttributeAtIndex(func.raw, LLVMAttributeFunctionIndex, attribute);
+        }
+    }
+
+    /// Returns a debug location at `line` and `column` inside `scope`.
+    pub fn debug_location<'ctx>(&'ctx self, line: u32, column: u32, scope: Metadata<'ctx>) -> Metadata<'ctx> {
+        unsafe {
+            Metadata::new(LLVMDIBuilderCreateDebugLocation(self.raw, line, column, scope.raw, ptr::null_mut()))
+        }
+    }
+}
+
+impl Drop for Context {
+    fn drop(&mut self) {
+        unsafe { LLVMContextDispose(self.raw) }
+    }
+}
+
+/// An LLVM module, i.e. a collection of functions and globals.
+///
+/// Note: a module must be dropped before the [Context] that it was created in.
+pub struct Module {
+    raw: LLVMModuleRef,
+}
+
+impl Module {
+    pub fn new(name: &str, context: &Context) -> Self {
+        let name = to_cstring(name);
+        Self { raw: unsafe { LLVMModuleCreateWithNameInContext(name.as_ptr(), context.raw) } }
+    }
+
+    /// Sets the target triple and data layout of the module to those of `target_machine`.
+    pub fn set_target(&self, target_machine: &TargetMachine) {
+        unsafe {
+            let triple = to_cstring(&target_machine.triple);
+            LLVMSetTarget(self.raw, triple.as_ptr());
+            let target_data_layout = LLVMCreateTargetDataLayout(target_machine.raw);
+            LLVMSetModuleDataLayout(self.raw, target_data_layout);
+            LLVMDisposeTargetData(target_data_layout);
+        }
+    }
+
+    /// Adds a module flag, like `"Debug Info Version"`, whose value is an `i32`.
+    pub fn add_int_flag(&self, context: &Context, key: &str, value: u32) {
+        unsafe {
+            let value = LLVMConstInt(LLVMInt32TypeInContext(context.raw), value as u64, 0);
+            LLVMAddModuleFlag(
+                self.raw,
+                LLVMModuleFlagBehavior::LLVMModuleFlagBehaviorWarning,
+                key.as_ptr() as *const c_char,
+                key.len(),
+                LLVMValueAsMetadata(value),
+            );
+        }
+    }
+
+    /// Sets the name of the source file that the module was compiled from.
+    pub fn set_source_file_name(&self, name: &str) {
+        unsafe { LLVMSetSourceFileName(self.raw, name.as_ptr() as *const c_char, name.len()) }
+    }
+
+    /// Appends a node holding just the string `s` to the named metadata `name`, like
+    /// `!llvm.ident = !{!0}` with `!0 = !{!"s"}`.
+    pub fn add_named_metadata_string(&self, context: &Context, name: &str, s: &str) {
+        let name = to_cstring(name);
+        unsafe {
+            let mut string = LLVMMDStringInContext2(context.raw, s.as_ptr() as *const c_char, s.len());
+            let node = LLVMMDNodeInContext2(context.raw, &mut string, 1);
+            LLVMAddNamedMetadataOperand(self.raw, name.as_ptr(), LLVMMetadataAsValue(context.raw, node));
+        }
+    }
+
+    /// Adds a function named `name` of type `func_type` (see [Context::function_type]).
+    pub fn add_function<'ctx>(&'ctx self, name: &str, func_type: LlvmType<'ctx>) -> Value<'ctx> {
+        let name = to_cstring(name);
+        unsafe { Value::new(LLVMAddFunction(self.raw, name.as_ptr(), func_type.raw)) }
+    }
+
+    /// Returns the function named `name`, if there is one.
+    pub fn get_function(&self, name: &str) -> Option<Value<'_>> {
+        let name = to_cstring(name);
+        unsafe { Value::new_nullable(LLVMGetNamedFunction(self.raw, name.as_ptr())) }
+    }
+
+    /// Adds a global variable named `name` of type `global_type`.
+    pub fn add_global<'ctx>(&'ctx self, global_type: LlvmType<'ctx>, name: &str) -> Value<'ctx> {
+        let name = to_cstring(name);
+        unsafe { Value::new(LLVMAddGlobal(self.raw, global_type.raw, name.as_ptr())) }
+    }
+
+    /// Returns an iterator over the module's global variables.
+    #[cfg(test)]
+    pub fn globals(&self) -> impl Iterator<Item = Value<'_>> {
+        let first = unsafe { Value::new_nullable(LLVMGetFirstGlobal(self.raw)) };
+        std::iter::successors(first, |global| unsafe { Value::new_nullable(LLVMGetNextGlobal(global.raw)) })
+    }
+
+    /// Runs LLVM's verifier over the module, returning its message if the module is invalid.
+    pub fn verify(&self) -> Result<(), String> {
+        unsafe {
+            let mut message = ptr::null_mut();
+            let is_invalid = LLVMVerifyModule(self.raw, LLVMReturnStatusAction, &mut message) == 1;
+            let message = take_message(message);
+            match is_invalid {
+                true => Err(message),
+                false => Ok(()),
+            }
+        }
+    }
+
+    /// Returns the module's IR as text.
+    pub fn print_to_string(&self) -> String {
+        unsafe { take_message(LLVMPrintModuleToString(self.raw)) }
+    }
+
+    /// Prints the module's IR to stderr.
+    pub fn dump(&self) {
+        unsafe { LLVMDumpModule(self.raw) }
+    }
+
+    /// Returns the module as LLVM bitcode.
+    pub fn write_bitcode_to_memory(&self) -> Vec<u8> {
+        unsafe { take_memory_buffer(LLVMWriteBitcodeToMemoryBuffer(self.raw)) }
+    }
+}
+
+impl Drop for Module {
+    fn drop(&mut self) {
+        unsafe { LLVMDisposeModule(self.raw) }
+    }
+}
+
+/// A machine that LLVM can generate code for (a target, CPU, features, etc.).
+pub struct TargetMachine {
+    raw: LLVMTargetMachineRef,
+    triple: String,
+}
+
+impl TargetMachine {
+    /// Creates a target machine for `triple`, returning LLVM's error message if the triple
+    /// is unknown, and `Err(None)` if LLVM couldn't create the machine for another reason.
+    pub fn new(
+        triple: &str,
+        cpu: &str,
+        features: &str,
+        opt_level: LLVMCodeGenOptLevel,
+        reloc_mode: LLVMRelocMode,
+    ) -> Result<Self, Option<String>> {
+        let triple_c = to_cstring(triple);
+        let cpu = to_cstring(cpu);
+        let features = to_cstring(features);
+        unsafe {
+            let mut target = ptr::null_mut();
+            let mut message = ptr::null_mut();
+            if LLVMGetTargetFromTriple(triple_c.as_ptr(), &mut target, &mut message) != 0 {
+                return Err(Some(take_message(message)));
+            }
+
+            let raw = LLVMCreateTargetMachine(
+                target,
+                triple_c.as_ptr(),
+                cpu.as_ptr(),
+                features.as_ptr(),
+                opt_level,
+                reloc_mode,
+                LLVMCodeModelDefault,
+            );
+            match raw.is_null() {
+                true => Err(None),
+                false => Ok(Self { raw, triple: triple.to_string() }),
+            }
+        }
+    }
+
+    /// Generates code for `module` and writes it to `path`, returning LLVM's error message if
+    /// that fails.
+    pub fn emit_to_file(&self, module: &Module, path: &Path, file_type: LLVMCodeGenFileType) -> Result<(), String> {
+        let path = path_to_cstring(path);
+        unsafe {
+            let mut message = ptr::null_mut();
+            // LLVM doesn't actually modify the path, despite taking a `*mut`
+            let failed = LLVMTargetMachineEmitToFile(
+                self.raw,
+                module.raw,
+                path.as_ptr() as *mut c_char,
+                file_type,
+                &mut message,
+            ) == 1;
+            match failed {
+                true => Err(take_message(message)),
+                false => Ok(()),
+            }
+        }
+    }
+
+    /// Generates code for `module` and returns it, or LLVM's error message if that fails.
+    pub fn emit_to_memory(&self, module: &Module, file_type: LLVMCodeGenFileType) -> Result<Vec<u8>, String> {
+        unsafe {
+            let mut message = ptr::null_mut();
+            let mut buffer = ptr::null_mut();
+            let failed =
+                LLVMTargetMachineEmitToMemoryBuffer(self.raw, module.raw, file_type, &mut message, &mut buffer) == 1;
+            match failed {
+                true => Err(take_message(message)),
+                false => Ok(take_memory_buffer(buffer)),
+            }
+        }
+    }
+}
+
+impl Drop for TargetMachine {
+    fn drop(&mut self) {
+        unsafe { LLVMDisposeTargetMachine(self.raw) }
+    }
+}
+
+/// LLVM's JIT compiler (MCJIT), which generates machine code for a module in memory, so that the
+/// module's functions can be called from Rust.
+///
+/// Note: an execution engine must be dropped before the [Context] that its module was created in.
+pub struct ExecutionEngine {
+    raw: LLVMExecutionEngineRef,
+}
+
+impl ExecutionEngine {
+    /// Generates code for `module` (which the execution engine then owns) for the machine that
+    /// the compiler is running on, returning LLVM's error message if that fails.
+    pub fn new(module: Module, opt_level: LLVMCodeGenOptLevel) -> Result<Self, String> {
+        // LLVM takes the module either way, so it mustn't be disposed of twice
+        let module = std::mem::ManuallyDrop::new(module);
+        unsafe {
+            LLVMLinkInMCJIT();
+            let mut raw = ptr::null_mut();
+            let mut message = ptr::null_mut();
+            match LLVMCreateJITCompilerForModule(&mut raw, module.raw, opt_level as c_uint, &mut message) == 1 {
+                true => Err(take_message(message)),
+                false => Ok(Self { raw }),
+            }
+        }
+    }
+
+    /// Returns the address of the machine code of the function named `name`, or `None` if the
+    /// module has no such function.
+    pub fn function_address(&self, name: &str) -> Option<usize> {
+        let name = to_cstring(name);
+        match unsafe { LLVMGetFunctionAddress(self.raw, name.as_ptr()) } {
+            0 => None,
+            address => Some(address as usize),
+        }
+    }
+}
+
+impl Drop for ExecutionEngine {
+    fn drop(&mut self) {
+        unsafe { LLVMDisposeExecutionEngine(self.raw) }
+    }
+}
+
+/// Options for LLVM's pass builder, used to run optimization pipelines.
+pub struct PassBuilderOptions {
+    raw: LLVMPassBuilderOptionsRef,
+}
+
+impl PassBuilderOptions {
+    pub fn new() -> Self {
+        Self { raw: unsafe { LLVMCreatePassBuilderOptions() } }
+    }
+
+    /// Sets whether the pipeline vectorizes loops (and interleaves their iterations).
+    pub fn set_loop_vectorization(&self, enabled: bool) {
+        unsafe {
+            LLVMPassBuilderOptionsSetLoopVectorization(self.raw, enabled as LLVMBool);
+            LLVMPassBuilderOptionsSetLoopInterleaving(self.raw, enabled as LLVMBool);
+        }
+    }
+
+    /// Sets whether the pipeline vectorizes straight-line code (SLP vectorization).
+    pub fn set_slp_vectorization(&self, enabled: bool) {
+        unsafe { LLVMPassBuilderOptionsSetSLPVectorization(self.raw, enabled as LLVMBool) }
+    }
+
+    /// Sets whether the pipeline unrolls loops.
+    pub fn set_loop_unrolling(&self, enabled: bool) {
+        unsafe { LLVMPassBuilderOptionsSetLoopUnrolling(self.raw, enabled as LLVMBool) }
+    }
+
+    /// Runs `passes` (in LLVM's textual pipeline syntax, like `default<O2>`) over `module`,
+    /// returning LLVM's error message if that fails.
+    pub fn run_passes(&self, module: &Module, passes: &str, target_machine: &TargetMachine) -> Result<(), String> {
+        let passes = to_cstring(passes);
+        unsafe {
+            let error = LLVMRunPasses(module.raw, passes.as_ptr(), target_machine.raw, self.raw);
+            if error.is_null() {
+                return Ok(());
+            }
+            let message = LLVMGetErrorMessage(error);
+            let owned = CStr::from_ptr(message).to_string_lossy().into_owned();
+            LLVMDisposeErrorMessage(message);
+            Err(owned)
+        }
+    }
+}
+
+impl Drop for PassBuilderOptions {
+    fn drop(&mut self) {
+        unsafe { LLVMDisposePassBuilderOptions(self.raw) }
+    }
+}
+
+/// An IR builder, which inserts instructions at a position inside a basic block.
+pub struct Builder<'ctx> {
+    raw: LLVMBuilderRef,
+    _context: PhantomData<&'ctx Context>,
+}
+
+/// Defines `Builder` methods for instructions with two operands, like `add`.
+macro_rules! binary_instructions {
+    ($($method:ident => $llvm_fn:ident),* $(,)?) => {
+        $(
+            pub fn $method(&self, lhs: Value<'ctx>, rhs: Value<'ctx>, name: &str) -> Value<'ctx> {
+                let name = to_cstring(name);
+                unsafe { Value::new($llvm_fn(self.raw, lhs.raw, rhs.raw, name.as_ptr())) }
+            }
+        )*
+    };
+}
+
+/// Defines `Builder` methods for casts, like `trunc`.
+macro_rules! cast_instructions {
+    ($($method:ident => $llvm_fn:ident),* $(,)?) => {
+        $(
+            pub fn $method(&self, value: Value<'ctx>, dest_type: LlvmType<'ctx>, name: &str) -> Value<'ctx> {
+                let name = to_cstring(name);
+                unsafe { Value::new($llvm_fn(self.raw, value.raw, dest_type.raw, name.as_ptr())) }
+            }
+        )*
+    };
+}
+
+impl<'ctx> Builder<'ctx> {
+    pub fn new(context: &'ctx Context) -> Self {
+        Self {
+            raw: unsafe { LLVMCreateBuilderInContext(context.raw) },
+            _context: PhantomData,
+        }
+    }
+
+    pub fn position_at_end(&self, block: BasicBlock<'ctx>) {
+        unsafe { LLVMPositionBuilderAtEnd(self.raw, block.raw) }
+    }
+
+    pub fn position_before(&self, instruction: Value<'ctx>) {
+        unsafe { LLVMPositionBuilderBefore(self.raw, instruction.raw) }
+    }
+
+    /// Returns the block that instructions are being inserted into, if any.
+    pub fn insert_block(&self) -> Option<BasicBlock<'ctx>> {
+        unsafe { BasicBlock::new_nullable(LLVMGetInsertBlock(self.raw)) }
+    }
+
+    /// Sets the debug location of the instructions built from now on (`None` for no location).
+    pub fn set_debug_location(&self, location: Option<Metadata<'ctx>>) {
+        let location = location.map_or(ptr::null_mut(), |location| location.raw);
+        unsafe { LLVMSetCurrentDebugLocation2(self.raw, location) }
+    }
+
+    pub fn alloca(&self, var_type: LlvmType<'ctx>, name: &str) -> Value<'ctx> {
+        let name = to_cstring(name);
+        unsafe { Value::new(LLVMBuildAlloca(self.raw, var_type.raw, name.as_ptr())) }
+    }
+
+    pub fn load(&self, value_type: LlvmType<'ctx>, pointer: Value<'ctx>, name: &str) -> Value<'ctx> {
+        let name = to_cstring(name);
+        unsafe { Value::new(LLVMBuildLoad2(self.raw, value_type.raw, pointer.raw, name.as_ptr())) }
+    }
+
+    pub fn store(&self, value: Value<'ctx>, pointer: Value<'ctx>) {
+        unsafe { LLVMBuildStore(self.raw, value.raw, pointer.raw) };
+    }
+
+    pub fn br(&self, dest: BasicBlock<'ctx>) {
+        unsafe { LLVMBuildBr(self.raw, dest.raw) };
+    }
+
+    pub fn cond_br(&self, condition: Value<'ctx>, then_block: BasicBlock<'ctx>, else_block: BasicBlock<'ctx>) {
+        unsafe { LLVMBuildCondBr(self.raw, condition.raw, then_block.raw, else_block.raw) };
+    }
+
+    pub fn ret(&self, value: Value<'ctx>) {
+        unsafe { LLVMBuildRet(self.raw, value.raw) };
+    }
+
+    pub fn ret_void(&self) {
+        unsafe { LLVMBuildRetVoid(self.raw) };
+    }
+
+    pub fn unreachable(&self) {
+        unsafe { LLVMBuildUnreachable(self.raw) };
+    }
+
+    binary_instructions! {
+        add => LLVMBuildAdd,
+        sub => LLVMBuildSub,
+        mul => LLVMBuildMul,
+        sdiv => LLVMBuildSDiv,
+        udiv => LLVMBuildUDiv,
+        srem => LLVMBuildSRem,
+        urem => LLVMBuildURem,
+    }
+
+    cast_instructions! {
+        trunc => LLVMBuildTrunc,
+        sext => LLVMBuildSExt,
+        zext => LLVMBuildZExt,
+    }
+
+    pub fn neg(&self, value: Value<'ctx>, name: &str) -> Value<'ctx> {
+        let name = to_cstring(name);
+        unsafe { Value::new(LLVMBuildNeg(self.raw, value.raw, name.as_ptr())) }
+    }
+
+    pub fn icmp(&self, predicate: LLVMIntPredicate, lhs: Value<'ctx>, rhs: Value<'ctx>, name: &str) -> Value<'ctx> {
+        let name = to_cstring(name);
+        unsafe { Value::new(LLVMBuildICmp(self.raw, predicate, lhs.raw, rhs.raw, name.as_ptr())) }
+    }
+
+    /// Builds a pointer to element `index` of the array of `element_type`s that `pointer` points
+    /// to (like `&pointer[index]` in C).
+    pub fn element_pointer(&self, element_type: LlvmType<'ctx>, pointer: Value<'ctx>, index: Value<'ctx>, name: &str) -> Value<'ctx> {
+        let name = to_cstring(name);
+        let mut indices = [index.raw];
+        unsafe {
+            Value::new(LLVMBuildInBoundsGEP2(
+                self.raw,
+                element_type.raw,
+                pointer.raw,
+                indices.as_mut_ptr(),
+                indices.len() as c_uint,
+                name.as_ptr(),
+            ))
+        }
+    }
+
+    /// Builds a call to `func` (of type `func_type`); `name` must be empty if it returns `void`.
+    pub fn call(&self, func_type: LlvmType<'ctx>, func: Value<'ctx>, args: &[Value<'ctx>], name: &str) -> Value<'ctx> {
+        let name = to_cstring(name);
+        let mut args: Vec<_> = args.iter().map(|arg| arg.raw).collect();
+        unsafe {
+            Value::new(LLVMBuildCall2(
+                self.raw,
+                func_type.raw,
+                func.raw,
+                args.as_mut_ptr(),
+                args.len() as c_uint,
+                name.as_ptr(),
+            ))
+        }
+    }
+}
+
+impl Drop for Builder<'_> {
+    fn drop(&mut self) {
+        unsafe { LLVMDisposeBuilder(self.raw) }
+    }
+}
+
+/// A builder for DWARF debug info in a module.
+///
+/// The debug info is finalized when the builder is dropped.
+pub struct DIBuilder<'ctx> {
+    raw: LLVMDIBuilderRef,
+    _context: PhantomData<&'ctx Context>,
+}
+
+impl<'ctx> DIBuilder<'ctx> {
+    pub fn new(module: &'ctx Module) -> Self {
+        Self {
+            raw: unsafe { LLVMCreateDIBuilder(module.raw) },
+            _context: PhantomData,
+        }
+    }
+
+    pub fn create_file(&self, file_name: &str, directory: &str) -> Metadata<'ctx> {
+        unsafe {
+            Metadata::new(LLVMDIBuilderCreateFile(
+                self.raw,
+                file_name.as_ptr() as *const c_char,
+                file_name.len(),
+                directory.as_ptr() as *const c_char,
+                directory.len(),
+            ))
+        }
+    }
+
+    /// Creates a compile unit for `file` (in C, since DWARF has no language code for Flick).
+    pub fn create_compile_unit(&self, file: Metadata<'ctx>, producer: &str, is_optimized: bool) -> Metadata<'ctx> {
+        unsafe {
+            Metadata::new(LLVMDIBuilderCreateCompileUnit(
+                self.raw,
+                LLVMDWARFSourceLanguage::LLVMDWARFSourceLanguageC,
+                file.raw,
+                producer.as_ptr() as *const c_char,
+                producer.len(),
+                is_optimized as LLVMBool,
+                ptr::null(),
+                0,
+                0,
+                ptr::null(),
+                0,
+                LLVMDWARFEmissionKind::LLVMDWARFEmissionKindFull,
+                0,
+                0,
+                0,
+                ptr::null(),
+                0,
+                ptr::null(),
+                0,
+            ))
+        }
+    }
+
+    /// Creates a subroutine type without any parameter or return types.
+    pub fn create_untyped_subroutine_type(&self, file: Metadata<'ctx>) -> Metadata<'ctx> {
+        unsafe {
+            Metadata::new(LLVMDIBuilderCreateSubroutineType(self.raw, file.raw, ptr::null_mut(), 0, LLVMDIFlagZero))
+        }
+    }
+
+    /// Creates a definition of the function `name` at `line` in `file`.
+    pub fn create_function(
+        &self,
+        scope: Metadata<'ctx>,
+        name: &str,
+        file: Metadata<'ctx>,
+        line: u32,
+        subroutine_type: Metadata<'ctx>,
+        is_local: bool,
+    ) -> Metadata<'ctx> {
+        unsafe {
+            Metadata::new(LLVMDIBuilderCreateFunction(
+                self.raw,
+                scope.raw,
+                name.as_ptr() as *const c_char,
+                name.len(),
+                name.as_ptr() as *const c_char,
+                name.len(),
+                file.raw,
+                line,
+                subroutine_type.raw,
+                is_local as LLVMBool,
+                1,
+                line,
+                LLVMDIFlagZero,
+                0,
+            ))
+        }
+    }
+}
+
+impl Drop for DIBuilder<'_> {
+    fn drop(&mut self) {
+        unsafe {
+            LLVMDIBuilderFinalize(self.raw);
+            LLVMDisposeDIBuilder(self.raw);
+        }
+    }
+}
+
+/// Returns the version of the debug metadata that this LLVM produces.
+pub fn debug_metadata_version() -> u32 {
+    unsafe { LLVMDebugMetadataVersion() }
+}
+
+/// A handle to an LLVM value, like a function, an instruction, or a constant.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Value<'ctx> {
+    raw: LLVMValueRef,
+    _context: PhantomData<&'ctx Context>,
+}
+
+impl<'ctx> Value<'ctx> {
+    /// # Safety
+    /// `raw` must be a valid, non-null value that lives as long as `'ctx`.
+    unsafe fn new(raw: LLVMValueRef) -> Self {
+        debug_assert!(!raw.is_null());
+        Self { raw, _context: PhantomData }
+    }
+
+    /// # Safety
+    /// `raw` must be null, or a valid value that lives as long as `'ctx`.
+    unsafe fn new_nullable(raw: LLVMValueRef) -> Option<Self> {
+        (!raw.is_null()).then(|| Self::new(raw))
+    }
+
+    pub fn type_of(&self) -> LlvmType<'ctx> {
+        unsafe { LlvmType::new(LLVMTypeOf(self.raw)) }
+    }
+
+    pub fn is_function(&self) -> bool {
+        unsafe { !LLVMIsAFunction(self.raw).is_null() }
+    }
+
+    /// Returns the value's IR as text (for a function, its whole definition).
+    pub fn print_to_string(&self) -> String {
+        unsafe { take_message(LLVMPrintValueToString(self.raw)) }
+    }
+
+    pub fn set_name(&self, name: &str) {
+        unsafe { LLVMSetValueName2(self.raw, name.as_ptr() as *const c_char, name.len()) }
+    }
+
+    pub fn set_linkage(&self, linkage: LLVMLinkage) {
+        unsafe { LLVMSetLinkage(self.raw, linkage) }
+    }
+
+    /// Returns the `index`-th parameter of this function.
+    pub fn param(&self, index: usize) -> Value<'ctx> {
+        assert!(self.is_function() && index < unsafe { LLVMCountParams(self.raw) } as usize);
+        unsafe { Value::new(LLVMGetParam(self.raw, index as c_uint)) }
+    }
+
+    /// Returns this function's entry block, if it has any blocks.
+    pub fn entry_block(&self) -> Option<BasicBlock<'ctx>> {
+        assert!(self.is_function());
+        unsafe {
+            match LLVMCountBasicBlocks(self.raw) {
+                0 => None,
+                _ => Some(BasicBlock::new(LLVMGetEntryBasicBlock(self.raw))),
+            }
+        }
+    }
+
+    /// Appends `block` (which must not be in a function yet) to the end of this function.
+    pub fn append_basic_block(&self, block: BasicBlock<'ctx>) {
+        unsafe { LLVMAppendExistingBasicBlock(self.raw, block.raw) }
+    }
+
+    /// Attaches the debug info `subprogram` to this function.
+    pub fn set_subprogram(&self, subprogram: Metadata<'ctx>) {
+        unsafe { LLVMSetSubprogram(self.raw, subprogram.raw) }
+    }
+
+    /// Makes this global an internal (i.e. not exported) variable, which starts out as zero.
+    pub fn make_internal_zeroed(&self, value_type: LlvmType<'ctx>) {
+        unsafe {
+            LLVMSetInitializer(self.raw, LLVMConstNull(value_type.raw));
+            LLVMSetLinkage(self.raw, LLVMLinkage::LLVMInternalLinkage);
+        }
+    }
+
+    /// Makes this global a private constant initialized to `value`, whose address doesn't
+    /// matter (so that LLVM is allowed to merge it with identical constants).
+    pub fn make_private_constant(&self, value: Value<'ctx>) {
+        unsafe {
+            LLVMSetInitializer(self.raw, value.raw);
+            LLVMSetGlobalConstant(self.raw, 1);
+            LLVMSetLinkage(self.raw, LLVMLinkage::LLVMPrivateLinkage);
+            LLVMSetUnnamedAddress(self.raw, LLVMGlobalUnnamedAddr);
+        }
+    }
+
+    /// Returns this constant pointer as a `dest_type`, another pointer type (like a pointer to an
+    /// array as a pointer to its first element).
+    pub fn const_pointer_cast(&self, dest_type: LlvmType<'ctx>) -> Value<'ctx> {
+        // LLVM 17 removed typed pointers, so the pointer already has the only pointer type there is
+        #[cfg(any(feature = "llvm-15", feature = "llvm-16"))]
+        let pointer = unsafe { Value::new(LLVMConstBitCast(self.raw, dest_type.raw)) };
+        #[cfg(not(any(feature = "llvm-15", feature = "llvm-16")))]
+        let pointer = {
+            let _ = dest_type;
+            *self
+        };
+        pointer
+    }
+}
+
+/// A handle to an LLVM type, like `i32`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct LlvmType<'ctx> {
+    raw: LLVMTypeRef,
+    _context: PhantomData<&'ctx Context>,
+}
+
+impl LlvmType<'_> {
+    /// # Safety
+    /// `raw` must be a valid, non-null type that lives as long as `'ctx`.
+    unsafe fn new(raw: LLVMTypeRef) -> Self {
+        debug_assert!(!raw.is_null());
+        Self { raw, _context: PhantomData }
+    }
+}
+
+/// A handle to an LLVM basic block.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct BasicBlock<'ctx> {
+    raw: LLVMBasicBlockRef,
+    _context: PhantomData<&'ctx Context>,
+}
+
+impl<'ctx> BasicBlock<'ctx> {
+    /// # Safety
+    /// `raw` must be a valid, non-null block that lives as long as `'ctx`.
+    unsafe fn new(raw: LLVMBasicBlockRef) -> Self {
+        debug_assert!(!raw.is_null());
+        Self { raw, _context: PhantomData }
+    }
+
+    /// # Safety
+    /// `raw` must be null, or a valid block that lives as long as `'ctx`.
+    unsafe fn new_nullable(raw: LLVMBasicBlockRef) -> Option<Self> {
+        (!raw.is_null()).then(|| Self::new(raw))
+    }
+
+    /// Returns the function that this block is in, if any.
+    pub fn parent(&self) -> Option<Value<'ctx>> {
+        unsafe { Value::new_nullable(LLVMGetBasicBlockParent(self.raw)) }
+    }
+
+    pub fn first_instruction(&self) -> Option<Value<'ctx>> {
+        unsafe { Value::new_nullable(LLVMGetFirstInstruction(self.raw)) }
+    }
+}
+
+/// A handle to LLVM metadata, like debug info.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Metadata<'ctx> {
+    raw: LLVMMetadataRef,
+    _context: PhantomData<&'ctx Context>,
+}
+
+impl Metadata<'_> {
+    /// # Safety
+    /// `raw` must be a valid, non-null metadata node that lives as long as `'ctx`.
+    unsafe fn new(raw: LLVMMetadataRef) -> Self {
+        debug_assert!(!raw.is_null());
+        Self { raw, _context: PhantomData }
+    }
+}
@@ -2,4 +2,34 @@
 /// into LLVM code.
 ///
 /// [a]: crate::parser::ast;
+#[cfg(feature = "llvm")]
 pub mod compiler;
+/// This module defines [CompilerOptions](options::CompilerOptions), which configure the
+/// [Compiler](compiler::Compiler).
+pub mod options;
+/// This module defines the [Backend](backend::Backend) trait, which every code generator
+/// (like the [Compiler](compiler::Compiler)) implements.
+pub mod backend;
+/// This module defines the [CBackend](c_backend::CBackend), which converts typed abstract syntax
+/// trees into C source code instead of LLVM code.
+pub mod c_backend;
+/// This module defines [compile_file](pipeline::compile_file) and
+/// [compile_str](pipeline::compile_str), which run every part of the compiler in one go, and
+/// [check](pipeline::check), which runs everything before code generation.
+pub mod pipeline;
+/// This module defines the [Engine](jit::Engine), which compiles programs into machine code in
+/// memory so that Rust programs can call their functions, and [eval](jit::eval).
+#[cfg(feature = "llvm")]
+pub mod jit;
+/// This module defines [instrument](coverage::instrument), which adds source coverage counters
+/// to programs, and the [Profile](coverage::Profile)s that instrumented programs write.
+pub mod coverage;
+/// This module defines [instrument_functions](profiling::instrument_functions), which makes every
+/// function call profiling hooks when it starts and when it returns.
+pub mod profiling;
+/// Module that defines the compilation errors.
+pub mod error;
+/// Module that wraps the parts of llvm-sys that the [Compiler](compiler::Compiler) uses in a
+/// safe API.
+#[cfg(feature = "llvm")]
+mod llvm;
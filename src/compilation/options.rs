@@ -0,0 +1,441 @@
+use std::fmt;
+use std::str::FromStr;
+
+#[cfg(feature = "llvm")]
+use llvm_sys::target_machine::{LLVMCodeGenOptLevel, LLVMRelocMode};
+
+/// Options that configure how the [Compiler](crate::Compiler) generates code.
+///
+/// # Example usage
+///
+/// Options can be built with [CompilerOptions::builder]:
+///
+/// ```
+/// use flick::{CompilerOptions, EmitKind, OptLevel};
+///
+/// let options = CompilerOptions::builder()
+///     .target("wasm32-unknown-unknown")
+///     .opt_level(OptLevel::Os)
+///     .emit_kind(EmitKind::Assembly)
+///     .build();
+/// assert!(options.targets_wasm());
+/// ```
+///
+/// or, equivalently, with struct update syntax:
+///
+/// ```
+/// use flick::{CompilerOptions, OptLevel};
+///
+/// let options = CompilerOptions {
+///     opt_level: OptLevel::O3,
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct CompilerOptions {
+    /// How aggressively the compiler should optimize (see [OptLevel])
+    pub opt_level: OptLevel,
+    /// The target triple to compile for, like `aarch64-apple-darwin` (defaults to the triple
+    /// of the machine that the compiler is running on)
+    pub target_triple: Option<String>,
+    /// The relocation model of the generated code (see [RelocModel])
+    pub reloc_model: RelocModel,
+    /// The CPU to generate code for, like `skylake` or `native` (defaults to `generic`)
+    pub target_cpu: Option<String>,
+    /// Comma-separated CPU features to enable or disable, like `+avx2,-sse4.1` (defaults to the
+    /// host's features if `target_cpu` is `native`, and to none otherwise)
+    pub target_features: Option<String>,
+    /// What kind of file [Compiler::to_file](crate::Compiler::to_file) writes (see [EmitKind])
+    pub emit_kind: EmitKind,
+    /// Whether to prepare for link-time optimization, i.e. to only run the LTO pre-link
+    /// pipeline (this is usually combined with [EmitKind::Bitcode], for an LTO-capable linker
+    /// like `clang -flto`)
+    pub lto: bool,
+    /// Whether to emit DWARF debug info, so that debuggers can see the program's functions
+    pub debug_info: bool,
+    /// The name of the source file being compiled, like `factorial.fl` (used in debug info)
+    pub source_file_name: Option<String>,
+    /// Whether to keep the names of values in the LLVM IR (like `%x.addr` or `%fib`), which
+    /// makes [printed IR](crate::Compiler::print_ir) much easier to read. When `false`, LLVM
+    /// discards the names, which saves memory and compile time.
+    pub readable_ir: bool,
+    /// Whether to keep the optimizer from vectorizing loops and straight-line code (which it
+    /// otherwise does at [OptLevel::O2] and [OptLevel::O3])
+    pub disable_vectorization: bool,
+    /// Whether to keep the optimizer from unrolling loops
+    pub disable_loop_unrolling: bool,
+    /// An exact pass pipeline to optimize with (in LLVM's textual pipeline syntax, like
+    /// `sroa,instcombine,simplifycfg`), instead of the one chosen by `opt_level` and `lto`
+    pub passes: Option<String>,
+    /// Whether LLVM should report how long each of its passes takes (on stderr), e.g. to find
+    /// out which part of [optimization](crate::Compiler::optimize) is slow
+    pub time_passes: bool,
+}
+
+impl CompilerOptions {
+    /// Returns `true` if the compiler is targeting WebAssembly (e.g. `wasm32-unknown-unknown`).
+    ///
+    /// On WebAssembly, public functions are exported from the module, and `extern` functions
+    /// become imports that the host (e.g. the browser) must provide.
+    pub fn targets_wasm(&self) -> bool {
+        self.target_triple
+            .as_deref()
+            .is_some_and(|triple| triple.starts_with("wasm"))
+    }
+
+    /// Returns `true` if the compiler is targeting Windows with the MSVC toolchain (e.g.
+    /// `x86_64-pc-windows-msvc`), either explicitly or because that's the host's triple.
+    ///
+    /// On MSVC targets, object files are COFF (`.obj`), and debug info is CodeView, rather than
+    /// DWARF, since that's what `link.exe` and Windows debuggers understand.
+    pub fn targets_msvc(&self) -> bool {
+        match &self.target_triple {
+            Some(triple) => triple.contains("-windows-msvc"),
+            None => cfg!(all(target_os = "windows", target_env = "msvc")),
+        }
+    }
+
+    /// Returns the relocation model that the code is generated with: `reloc_model`, unless it's
+    /// [RelocModel::Default], which means position-independent code (except on WebAssembly).
+    ///
+    /// Linkers like `gcc` make position-independent executables (PIEs) by default, which can't
+    /// have absolute relocations in them (like the ones that string literals need otherwise).
+    pub fn effective_reloc_model(&self) -> RelocModel {
+        match self.reloc_model {
+            RelocModel::Default if !self.targets_wasm() => RelocModel::Pic,
+            reloc_model => reloc_model,
+        }
+    }
+
+    /// Returns the `flick` command-line flags that correspond to these options, like
+    /// `-O2 --target wasm32-unknown-unknown -g` (options left at their defaults are omitted,
+    /// except for the optimization level).
+    pub fn command_line_flags(&self) -> String {
+        let mut flags = vec![format!("-O{}", self.opt_level)];
+        if let Some(target_triple) = &self.target_triple {
+            flags.push(format!("--target {}", target_triple));
+        }
+        if self.reloc_model != RelocModel::Default {
+            flags.push(format!("--reloc {}", self.reloc_model));
+        }
+        if let Some(target_cpu) = &self.target_cpu {
+            flags.push(format!("--cpu {}", target_cpu));
+        }
+        if let Some(target_features) = &self.target_features {
+            flags.push(format!("--features {}", target_features));
+        }
+        if self.debug_info {
+            flags.push("-g".to_string());
+        }
+        if self.readable_ir {
+            flags.push("--readable-ir".to_string());
+        }
+        if self.lto {
+            flags.push("--lto".to_string());
+        }
+        if self.disable_vectorization {
+            flags.push("--no-vectorize".to_string());
+        }
+        if self.disable_loop_unrolling {
+            flags.push("--no-unroll".to_string());
+        }
+        if let Some(passes) = &self.passes {
+            flags.push(format!("--passes {}", passes));
+        }
+        if self.time_passes {
+            flags.push("--time-passes".to_string());
+        }
+        flags.join(" ")
+    }
+
+    /// Returns a [CompilerOptionsBuilder] that starts out with the default options.
+    pub fn builder() -> CompilerOptionsBuilder {
+        CompilerOptionsBuilder::default()
+    }
+}
+
+/// A builder for [CompilerOptions]; see [CompilerOptions::builder].
+///
+/// Every option that isn't set keeps its default value.
+#[derive(Debug, Clone, Default)]
+pub struct CompilerOptionsBuilder {
+    options: CompilerOptions,
+}
+
+impl CompilerOptionsBuilder {
+    /// Sets the target triple to compile for, like `aarch64-apple-darwin`.
+    pub fn target(mut self, target_triple: impl Into<String>) -> Self {
+        self.options.target_triple = Some(target_triple.into());
+        self
+    }
+
+    /// Sets the optimization level.
+    pub fn opt_level(mut self, opt_level: OptLevel) -> Self {
+        self.options.opt_level = opt_level;
+        self
+    }
+
+    /// Sets the relocation model.
+    pub fn reloc_model(mut self, reloc_model: RelocModel) -> Self {
+        self.options.reloc_model = reloc_model;
+        self
+    }
+
+    /// Sets the CPU to generate code for, like `skylake` or `native`.
+    pub fn cpu(mut self, target_cpu: impl Into<String>) -> Self {
+        self.options.target_cpu = Some(target_cpu.into());
+        self
+    }
+
+    /// Sets the CPU features to enable or disable, like `+avx2,-sse4.1`.
+    pub fn features(mut self, target_features: impl Into<String>) -> Self {
+        self.options.target_features = Some(target_features.into());
+        self
+    }
+
+    /// Sets what kind of file the compiler writes.
+    pub fn emit_kind(mut self, emit_kind: EmitKind) -> Self {
+        self.options.emit_kind = emit_kind;
+        self
+    }
+
+    /// Sets whether to prepare the module for link-time optimization.
+    pub fn lto(mut self, lto: bool) -> Self {
+        self.options.lto = lto;
+        self
+    }
+
+    /// Sets whether to emit DWARF debug info.
+    pub fn debug_info(mut self, debug_info: bool) -> Self {
+        self.options.debug_info = debug_info;
+        self
+    }
+
+    /// Sets the name of the source file being compiled, like `factorial.fl`.
+    pub fn source_file_name(mut self, source_file_name: impl Into<String>) -> Self {
+        self.options.source_file_name = Some(source_file_name.into());
+        self
+    }
+
+    /// Sets whether to keep value names in the LLVM IR.
+    pub fn readable_ir(mut self, readable_ir: bool) -> Self {
+        self.options.readable_ir = readable_ir;
+        self
+    }
+
+    /// Sets whether to keep the optimizer from vectorizing code.
+    pub fn disable_vectorization(mut self, disable_vectorization: bool) -> Self {
+        self.options.disable_vectorization = disable_vectorization;
+        self
+    }
+
+    /// Sets whether to keep the optimizer from unrolling loops.
+    pub fn disable_loop_unrolling(mut self, disable_loop_unrolling: bool) -> Self {
+        self.options.disable_loop_unrolling = disable_loop_unrolling;
+        self
+    }
+
+    /// Sets an exact pass pipeline to optimize with, like `sroa,instcombine,simplifycfg`.
+    pub fn passes(mut self, passes: impl Into<String>) -> Self {
+        self.options.passes = Some(passes.into());
+        self
+    }
+
+    /// Sets whether LLVM should report how long each of its passes takes.
+    pub fn time_passes(mut self, time_passes: bool) -> Self {
+        self.options.time_passes = time_passes;
+        self
+    }
+
+    /// Returns the configured options.
+    pub fn build(self) -> CompilerOptions {
+        self.options
+    }
+}
+
+/// The kind of file that [Compiler::to_file](crate::Compiler::to_file) writes.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum EmitKind {
+    /// A native object file, like `test.o` (this is the default)
+    #[default]
+    Object,
+    /// Native assembly, like `test.s`
+    Assembly,
+    /// LLVM bitcode, like `test.bc` (e.g. for link-time optimization)
+    Bitcode,
+}
+
+/// An optimization level, like `-O2` or `-Oz`.
+///
+/// Each level decides both the LLVM pass pipeline (see [OptLevel::pass_pipeline]) and the
+/// target machine's code generation level (see [OptLevel::codegen_opt_level]).
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum OptLevel {
+    /// No optimizations
+    O0,
+    /// Light optimizations (this is the default)
+    #[default]
+    O1,
+    /// Most optimizations
+    O2,
+    /// All optimizations, including those that may increase code size
+    O3,
+    /// Optimize for code size
+    Os,
+    /// Optimize for code size even more aggressively than [OptLevel::Os], at the expense of speed
+    Oz,
+}
+
+impl OptLevel {
+    /// Returns the LLVM pass pipeline (in LLVM's textual pipeline syntax) for this level.
+    ///
+    /// Every level above [OptLevel::O0] starts with SROA (which includes mem2reg), so the
+    /// allocas that the compiler creates for every variable are promoted to SSA registers.
+    pub fn pass_pipeline(&self) -> &'static str {
+        match self {
+            Self::O0 => "default<O0>",
+            Self::O1 => "default<O1>",
+            Self::O2 => "default<O2>",
+            Self::O3 => "default<O3>",
+            Self::Os => "default<Os>",
+            Self::Oz => "default<Oz>",
+        }
+    }
+
+    /// Returns `true` if this level's pipeline vectorizes code (like clang, only [OptLevel::O2]
+    /// and [OptLevel::O3] do, since vectorizing tends to make code bigger).
+    ///
+    /// Note: the loop optimizations themselves (like LICM, loop rotation, and unrolling) are
+    /// part of every pipeline above [OptLevel::O0].
+    pub fn vectorizes(&self) -> bool {
+        matches!(self, Self::O2 | Self::O3)
+    }
+
+    /// Returns the LLVM pass pipeline to run on a module before it's handed to the linker for
+    /// link-time optimization (the rest of the optimizations happen at link time).
+    pub fn lto_pre_link_pass_pipeline(&self) -> &'static str {
+        match self {
+            Self::O0 => "lto-pre-link<O0>",
+            Self::O1 => "lto-pre-link<O1>",
+            Self::O2 => "lto-pre-link<O2>",
+            Self::O3 => "lto-pre-link<O3>",
+            Self::Os => "lto-pre-link<Os>",
+            Self::Oz => "lto-pre-link<Oz>",
+        }
+    }
+
+    /// Returns the function attributes that tell LLVM's code generator to optimize for size
+    /// (like clang, `optsize` for [OptLevel::Os], and `minsize` too for [OptLevel::Oz]).
+    pub fn size_attributes(&self) -> &'static [&'static str] {
+        match self {
+            Self::Os => &["optsize"],
+            Self::Oz => &["optsize", "minsize"],
+            _ => &[],
+        }
+    }
+
+    /// Returns the code generation level to use when creating the LLVM target machine.
+    ///
+    /// Note: LLVM's code generator has no level for optimizing for size (that's decided by
+    /// [OptLevel::size_attributes]), so [OptLevel::Os] and [OptLevel::Oz] use LLVM's default
+    /// code generation level (like clang does).
+    #[cfg(feature = "llvm")]
+    pub fn codegen_opt_level(&self) -> LLVMCodeGenOptLevel {
+        match self {
+            Self::O0 => LLVMCodeGenOptLevel::LLVMCodeGenLevelNone,
+            Self::O1 => LLVMCodeGenOptLevel::LLVMCodeGenLevelLess,
+            Self::O2 | Self::Os | Self::Oz => LLVMCodeGenOptLevel::LLVMCodeGenLevelDefault,
+            Self::O3 => LLVMCodeGenOptLevel::LLVMCodeGenLevelAggressive,
+        }
+    }
+}
+
+impl fmt::Display for OptLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::O0 => write!(f, "0"),
+            Self::O1 => write!(f, "1"),
+            Self::O2 => write!(f, "2"),
+            Self::O3 => write!(f, "3"),
+            Self::Os => write!(f, "s"),
+            Self::Oz => write!(f, "z"),
+        }
+    }
+}
+
+/// Parses the part of an optimization flag after `-O`; for example, `"2"` or `"s"`.
+impl FromStr for OptLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "0" => Ok(Self::O0),
+            "1" => Ok(Self::O1),
+            "2" => Ok(Self::O2),
+            "3" => Ok(Self::O3),
+            "s" => Ok(Self::Os),
+            "z" => Ok(Self::Oz),
+            _ => Err(format!("unknown optimization level '{}' (expected 0, 1, 2, 3, s, or z)", s)),
+        }
+    }
+}
+
+/// A relocation model, which decides whether the generated code can be loaded at any address.
+///
+/// For example, building shared libraries (and some distros' toolchains) requires
+/// position-independent code, i.e. [RelocModel::Pic].
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum RelocModel {
+    /// Position-independent code, except on WebAssembly, where it's whatever LLVM's default
+    /// is (see [CompilerOptions::effective_reloc_model])
+    #[default]
+    Default,
+    /// Non-relocatable code
+    Static,
+    /// Position-independent code
+    Pic,
+    /// Code that isn't position-independent, but whose external references are relocatable
+    DynamicNoPic,
+}
+
+#[cfg(feature = "llvm")]
+impl RelocModel {
+    /// Returns the corresponding llvm-sys [LLVMRelocMode].
+    pub fn llvm_reloc_mode(&self) -> LLVMRelocMode {
+        match self {
+            Self::Default => LLVMRelocMode::LLVMRelocDefault,
+            Self::Static => LLVMRelocMode::LLVMRelocStatic,
+            Self::Pic => LLVMRelocMode::LLVMRelocPIC,
+            Self::DynamicNoPic => LLVMRelocMode::LLVMRelocDynamicNoPic,
+        }
+    }
+}
+
+impl fmt::Display for RelocModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Default => write!(f, "default"),
+            Self::Static => write!(f, "static"),
+            Self::Pic => write!(f, "pic"),
+            Self::DynamicNoPic => write!(f, "dynamic-no-pic"),
+        }
+    }
+}
+
+/// Parses a relocation model flag, like `"pic"`.
+impl FromStr for RelocModel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "default" => Ok(Self::Default),
+            "static" => Ok(Self::Static),
+            "pic" => Ok(Self::Pic),
+            "dynamic-no-pic" => Ok(Self::DynamicNoPic),
+            _ => Err(format!(
+                "unknown relocation model '{}' (expected static, pic, or dynamic-no-pic)",
+                s
+            )),
+        }
+    }
+}
@@ -0,0 +1,603 @@
+use std::cell::Cell;
+use std::error::Error;
+use std::fmt;
+use std::time::{Duration, Instant};
+#[cfg(feature = "llvm")]
+use std::{fs, path::Path};
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use crate::diagnostics::diagnostic::{Diagnostic, Severity};
+use crate::diagnostics::fix;
+use crate::diagnostics::sink::DiagnosticSink;
+use crate::diagnostics::source_map::{SourceFile, SourceMap};
+use crate::error::{ErrorKind, FlickError};
+use crate::lexing::{lexer::{IdentifierPolicy, Lexer}, token::{Span, Token}};
+use crate::parsing::{ast::{Arena, Program}, parser::Parser};
+use crate::resolving::resolver::Resolver;
+use crate::typing::{error::TypingError, typed_ast::TypedProgram, typer::Typer};
+use crate::compilation::{backend::Backend, error::CompilationError};
+#[cfg(feature = "llvm")]
+use crate::compilation::{
+    compiler::Compiler,
+    options::{CompilerOptions, EmitKind},
+};
+
+/// The errors and warnings found while compiling some files (by [compile_file] or
+/// [compile_str]), along with the files, which their spans point into.
+#[derive(Debug, Clone)]
+pub struct Diagnostics {
+    pub sources: SourceMap,
+    /// Every diagnostic, in the order that they were found
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    pub fn errors(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.with_severity(Severity::Error)
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.with_severity(Severity::Warning)
+    }
+
+    fn with_severity(&self, severity: Severity) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics
+            .iter()
+            .filter(move |diagnostic| diagnostic.severity == severity)
+    }
+
+    /// Returns every diagnostic [rendered](Diagnostic::render) one after another, as `flick`
+    /// would show them.
+    pub fn render(&self) -> String {
+        self.diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.render(&self.sources))
+            .collect()
+    }
+}
+
+impl fmt::Display for Diagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.render())
+    }
+}
+
+impl Error for Diagnostics {}
+
+/// What [compile_file] and [compile_str] produce from a program without errors, all in memory
+/// (nothing is written to disk).
+#[cfg(feature = "llvm")]
+#[derive(Debug, Clone)]
+pub struct Artifacts {
+    /// The program's (optimized) LLVM IR
+    pub ir: String,
+    /// The program's assembly, for the target given by the options
+    pub assembly: String,
+    /// The object file's contents, or LLVM bitcode if [CompilerOptions::emit_kind] is
+    /// [EmitKind::Bitcode] (e.g. for LTO)
+    pub object: Vec<u8>,
+    /// The warnings found while compiling the program
+    pub warnings: Diagnostics,
+}
+
+/// Runs the whole compiler on the file at `path`, returning the code generated for it, or every
+/// error (and warning) found if it can't be compiled (including if the file can't be read).
+///
+/// If `options` doesn't give a [source file name](CompilerOptions::source_file_name), the
+/// file's name is used.
+#[cfg(feature = "llvm")]
+pub fn compile_file(path: impl AsRef<Path>, options: &CompilerOptions) -> Result<Artifacts, Diagnostics> {
+    let path = path.as_ref();
+    let source_code = fs::read_to_string(path).map_err(|err| {
+        let error = CompilationError::UnreadableSource { path: path.to_path_buf(), message: err.to_string() };
+        Diagnostics { sources: SourceMap::default(), diagnostics: vec![Diagnostic::from(&error)] }
+    })?;
+
+    let mut options = options.clone();
+    if options.source_file_name.is_none() {
+        options.source_file_name = path.file_name().map(|file_name| file_name.to_string_lossy().into_owned());
+    }
+    compile_sources(SourceMap::single_file(path, source_code.to_string()), &options, &CheckOptions::default())
+}
+
+/// Like [compile_file], but compiles `source_code` itself; diagnostics point into a file named
+/// after [CompilerOptions::source_file_name] (or `<input>`, if it isn't given).
+///
+/// ```
+/// let program = "pub fn main() u8 {\n    ret 0\n}\n";
+/// let artifacts = flick::compile_str(program, &Default::default()).unwrap();
+/// assert!(artifacts.ir.contains("@main"));
+///
+/// let errors = flick::compile_str("pub fn main() u8 {\n    ret x\n}\n", &Default::default()).unwrap_err();
+/// assert_eq!(errors.errors().next().unwrap().code, "E0201");
+/// ```
+#[cfg(feature = "llvm")]
+pub fn compile_str(source_code: &str, options: &CompilerOptions) -> Result<Artifacts, Diagnostics> {
+    let path = options.source_file_name.as_deref().unwrap_or("<input>");
+    compile_sources(SourceMap::single_file(path, source_code.to_string()), options, &CheckOptions::default())
+}
+
+/// Like [compile_file], but compiles every file in `sources` together (like `flick build` does),
+/// lexing and parsing them with `check_options`.
+#[cfg(feature = "llvm")]
+pub fn compile_sources(
+    sources: SourceMap,
+    options: &CompilerOptions,
+    check_options: &CheckOptions,
+) -> Result<Artifacts, Diagnostics> {
+    let mut diagnostics = Vec::new();
+    let mut times = PhaseTimes::default();
+    let generated = check_with(&sources, check_options, &mut diagnostics, &mut times).and_then(|typed_program| {
+        match generate_artifacts(&typed_program, options) {
+            Ok(generated) => Some(generated),
+            Err(err) => {
+                diagnostics.push(Diagnostic::from(&err));
+                None
+            }
+        }
+    });
+    let diagnostics = Diagnostics { sources, diagnostics };
+    match generated {
+        Some(mut artifacts) => {
+            artifacts.warnings = diagnostics;
+            Ok(artifacts)
+        }
+        None => Err(diagnostics),
+    }
+}
+
+/// The settings for how the files are lexed and parsed (see [check_with]), which `flick` takes
+/// from its flags; the defaults are the same as `flick`'s.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct CheckOptions {
+    /// Which characters identifiers can contain (see [IdentifierPolicy])
+    pub identifier_policy: IdentifierPolicy,
+    /// How deeply expressions and bodies can be nested (e.g. in parentheses) before parsing
+    /// fails (see [Parser::DEFAULT_MAX_DEPTH])
+    pub max_nesting_depth: usize,
+}
+
+impl Default for CheckOptions {
+    fn default() -> Self {
+        Self { identifier_policy: IdentifierPolicy::default(), max_nesting_depth: Parser::DEFAULT_MAX_DEPTH }
+    }
+}
+
+/// Records how long each phase of the compiler takes, and how big the program is (what `flick`
+/// prints with `--time-passes`, which is what [Display](fmt::Display) shows).
+#[derive(Debug, Clone, Default)]
+pub struct PhaseTimes {
+    phases: Vec<(&'static str, Duration)>,
+    /// How many there are of each kind of thing (like tokens) in the program
+    counts: Vec<(&'static str, usize)>,
+}
+
+impl PhaseTimes {
+    /// Runs `phase`, recording how long it took under `name` (added to the time of any earlier
+    /// phase with that name, e.g. lexing another file).
+    ///
+    /// The phase is logged (at the info level) as it starts and ends, so that a phase that never
+    /// ends can be told apart from one that's just slow.
+    pub fn time<T>(&mut self, name: &'static str, phase: impl FnOnce() -> T) -> T {
+        let _span = tracing::info_span!("phase", name).entered();
+        tracing::info!("started");
+        let start = Instant::now();
+        let result = phase();
+        let elapsed = start.elapsed();
+        tracing::info!(?elapsed, "finished");
+        match self.phases.iter_mut().find(|(phase_name, _)| *phase_name == name) {
+            Some((_, duration)) => *duration += elapsed,
+            None => self.phases.push((name, elapsed)),
+        }
+        result
+    }
+
+    /// Records that there are `count` more of `what` (like `"tokens"`) in the program.
+    pub fn count(&mut self, what: &'static str, count: usize) {
+        match self.counts.iter_mut().find(|(counted, _)| *counted == what) {
+            Some((_, total)) => *total += count,
+            None => self.counts.push((what, count)),
+        }
+    }
+}
+
+/// A table of the recorded phases (and their total), followed by the counts
+impl fmt::Display for PhaseTimes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let total: Duration = self.phases.iter().map(|(_, duration)| *duration).sum();
+        writeln!(f, "===-------------------------------------------------------------------------===")?;
+        writeln!(f, "                          Flick compilation time report")?;
+        writeln!(f, "===-------------------------------------------------------------------------===")?;
+        for (name, duration) in self.phases.iter().chain([("total", total)].iter()) {
+            let percent = match total.is_zero() {
+                true => 0.0,
+                false => 100.0 * duration.as_secs_f64() / total.as_secs_f64(),
+            };
+            writeln!(f, "  {:>10.4}s ({:>5.1}%)  {}", duration.as_secs_f64(), percent, name)?;
+        }
+        if !self.counts.is_empty() {
+            writeln!(f)?;
+        }
+        for (what, count) in &self.counts {
+            writeln!(f, "  {:>10}  {}", count, what)?;
+        }
+        Ok(())
+    }
+}
+
+/// Lexes, parses, and checks the files in `sources` (everything that the compiler does before
+/// generating code), returning their typed program without unused functions, or `None` if
+/// there are errors.
+///
+/// Errors and warnings are reported to `sink` as they're found (see [DiagnosticSink]).
+pub fn check(sources: &SourceMap, sink: &mut dyn DiagnosticSink) -> Option<TypedProgram> {
+    check_with(sources, &CheckOptions::default(), sink, &mut PhaseTimes::default())
+}
+
+/// Like [check], but lexes and parses with `options`, and records how long each phase takes in
+/// `times` (this is what `flick check` does).
+pub fn check_with(
+    sources: &SourceMap,
+    options: &CheckOptions,
+    sink: &mut dyn DiagnosticSink,
+    times: &mut PhaseTimes,
+) -> Option<TypedProgram> {
+    let mut arenas = Vec::new();
+    let program = parse_sources(sources, &mut arenas, options, sink, times)?;
+    check_program(&program, sink, times)
+}
+
+/// Like [check], but types the program with `typer` (e.g. one that doesn't require a `main`
+/// function), and keeps every function, even the unused ones.
+#[cfg(feature = "llvm")]
+pub(crate) fn check_with_typer(sources: &SourceMap, sink: &mut dyn DiagnosticSink, typer: Typer) -> Option<TypedProgram> {
+    let mut times = PhaseTimes::default();
+    let mut arenas = Vec::new();
+    let program = parse_sources(sources, &mut arenas, &CheckOptions::default(), sink, &mut times)?;
+    check_program_with(&program, typer, sink, &mut times)
+}
+
+/// Lexes each file in `sources` into tokens (and their spans, which point into `sources`), or
+/// returns `None` if there are errors (which are reported to `sink`).
+pub fn lex_sources<'a>(
+    sources: &'a SourceMap,
+    options: &CheckOptions,
+    sink: &mut dyn DiagnosticSink,
+    times: &mut PhaseTimes,
+) -> Option<Vec<(Vec<Token<'a>>, Vec<Span>)>> {
+    let mut files_tokens = Vec::new();
+    for file in sources.files() {
+        let _span = tracing::info_span!("file", path = %file.path.display()).entered();
+        times.count("files", 1);
+        times.count("lines", file.source_code.matches('\n').count());
+        match times.time("lexing", || lex_file(file, options.identifier_policy)) {
+            Ok((tokens, spans)) => {
+                times.count("tokens", tokens.len());
+                files_tokens.push((tokens, spans));
+            }
+            Err(errors) => {
+                for err in errors {
+                    sink.report(Diagnostic::error(&err).offset_by(file.start));
+                }
+            }
+        }
+    }
+    match files_tokens.len() == sources.files().len() {
+        true => Some(files_tokens),
+        false => None,
+    }
+}
+
+/// Lexes `file` into tokens and their spans (which point into the [SourceMap] that it's in), or
+/// returns every lexing error in it.
+fn lex_file(file: &SourceFile, identifier_policy: IdentifierPolicy) -> Result<(Vec<Token<'_>>, Vec<Span>), Vec<FlickError>> {
+    let mut tokens = Vec::new();
+    let mut spans = Vec::new();
+    let mut errors = Vec::new();
+    for result in Lexer::new(&file.source_code, identifier_policy) {
+        match result {
+            Ok((token, span)) => {
+                tokens.push(token);
+                spans.push(Span { start: span.start + file.start, end: span.end + file.start });
+            }
+            Err(err) => errors.push(err),
+        }
+    }
+    match errors.is_empty() {
+        true => Ok((tokens, spans)),
+        false => Err(errors),
+    }
+}
+
+/// Lexes and parses the files in `sources` into one program (whose expressions, and the tokens
+/// that its names are borrowed from, are allocated in `arenas`, one for each file), or returns
+/// `None` if there are errors (which are reported to `sink`, with fixes for the syntax errors
+/// that have one).
+///
+/// With the `parallel` feature, the files are lexed and parsed in parallel. Each file is parsed
+/// as it's lexed (see [Parser::parse_token_stream]), so a lexing error is found without any
+/// extra pass over the file; only syntax errors have the file lexed again, for the fixes that
+/// need all of its tokens.
+pub fn parse_sources<'a>(
+    sources: &'a SourceMap,
+    arenas: &'a mut Vec<Arena<'a>>,
+    options: &CheckOptions,
+    sink: &mut dyn DiagnosticSink,
+    times: &mut PhaseTimes,
+) -> Option<Program<'a>> {
+    let CheckOptions { identifier_policy, max_nesting_depth } = *options;
+    // An arena can only be used by one thread at a time, so each file has its own
+    arenas.resize_with(sources.files().len(), Arena::new);
+    let parse_file = |(file, arena): (&'a SourceFile, &'a mut Arena<'a>)| {
+        let _span = tracing::info_span!("file", path = %file.path.display()).entered();
+        let arena: &'a Arena<'a> = arena;
+        let token_count = Cell::new(0);
+        let tokens = Lexer::new(&file.source_code, identifier_policy).map(|result| {
+            let (token, span) = result?;
+            token_count.set(token_count.get() + 1);
+            Ok((token, Span { start: span.start + file.start, end: span.end + file.start }))
+        });
+        let parsed = Parser::parse_token_stream(arena, tokens, max_nesting_depth);
+        (parsed, token_count.get())
+    };
+    let parsed_files: Vec<_> = times.time("lexing and parsing", || {
+        #[cfg(feature = "parallel")]
+        let parsed_files = sources.files().par_iter().zip(arenas.par_iter_mut()).map(parse_file).collect();
+        #[cfg(not(feature = "parallel"))]
+        let parsed_files = sources.files().iter().zip(arenas.iter_mut()).map(parse_file).collect();
+        parsed_files
+    });
+
+    let mut program = Program { global_statements: Vec::new() };
+    let mut has_errors = false;
+    for (file, (parsed, token_count)) in sources.files().iter().zip(parsed_files) {
+        times.count("files", 1);
+        times.count("lines", file.source_code.matches('\n').count());
+        times.count("tokens", token_count);
+        match parsed {
+            Ok(file_program) => {
+                times.count("AST nodes", file_program.node_count());
+                program.extend(file_program);
+            }
+            Err(errors) => {
+                let (tokens, spans) = lex_file(file, identifier_policy).unwrap_or_default();
+                for err in errors {
+                    let diagnostic = match err.kind {
+                        // Lexing errors point into the file, rather than into `sources`
+                        ErrorKind::LexingError(_) => Diagnostic::error(&err).offset_by(file.start),
+                        _ => Diagnostic::error(&err).with_fix(fix::suggest_in_file(&err, file, &tokens, &spans)),
+                    };
+                    sink.report(diagnostic);
+                }
+                has_errors = true;
+            }
+        }
+    }
+    match has_errors {
+        false => Some(program),
+        true => None,
+    }
+}
+
+/// Resolves the names in `program` (e.g. from [parse_sources]) and type checks it, returning its
+/// typed program without unused functions, or `None` if there are errors (see [check]).
+///
+/// With the `parallel` feature, the functions are resolved and typed in parallel (see
+/// [Typer::type_program_in_parallel]).
+pub fn check_program(program: &Program, sink: &mut dyn DiagnosticSink, times: &mut PhaseTimes) -> Option<TypedProgram> {
+    let mut typed_program = check_program_with(program, Typer::new(), sink, times)?;
+    for proto in typed_program.remove_unused_functions() {
+        let kind = ErrorKind::TypingError(TypingError::UnusedFunction { name: proto.name.to_string(), span: proto.span });
+        sink.report(Diagnostic::warning(&FlickError { index: proto.span.start, kind }));
+    }
+    Some(typed_program)
+}
+
+/// Like [check_program], but types the program with `typer`, and keeps every function.
+fn check_program_with(
+    program: &Program,
+    mut typer: Typer,
+    sink: &mut dyn DiagnosticSink,
+    times: &mut PhaseTimes,
+) -> Option<TypedProgram> {
+    #[cfg(feature = "parallel")]
+    let resolved = times.time("name resolution", || Resolver::resolve_program_in_parallel(program));
+    #[cfg(not(feature = "parallel"))]
+    let resolved = times.time("name resolution", || Resolver::resolve_program(program));
+    match resolved {
+        Ok(warnings) => {
+            for warning in warnings {
+                sink.report(Diagnostic::warning(&warning));
+            }
+        }
+        Err(errors) => {
+            for err in errors {
+                sink.report(Diagnostic::error(&err));
+            }
+            return None;
+        }
+    }
+
+    #[cfg(feature = "parallel")]
+    let typed = times.time("type checking", || typer.type_program_in_parallel(program));
+    #[cfg(not(feature = "parallel"))]
+    let typed = times.time("type checking", || typer.type_program(program));
+    match typed {
+        Ok(typed_program) => Some(typed_program),
+        Err(errors) => {
+            for err in errors {
+                sink.report(Diagnostic::from(&err));
+            }
+            None
+        }
+    }
+}
+
+/// Generates code for `typed_program` with `backend`, and then optimizes it, recording both
+/// phases in `times` (this is what [compile_file] and `flick build` do once the program has been
+/// checked).
+///
+/// `before_optimization` is given the backend in between, e.g. to print the unoptimized IR (like
+/// `flick build --emit-ir=pre` does).
+pub fn generate(
+    backend: &mut dyn Backend,
+    typed_program: &TypedProgram,
+    times: &mut PhaseTimes,
+    before_optimization: impl FnOnce(&dyn Backend),
+) -> Result<(), CompilationError> {
+    times.time("code generation", || backend.compile(typed_program))?;
+    before_optimization(backend);
+    times.time("optimization", || backend.optimize())
+}
+
+/// Compiles and optimizes `typed_program` (see [generate]), returning everything generated for
+/// it (without any warnings).
+#[cfg(feature = "llvm")]
+fn generate_artifacts(typed_program: &TypedProgram, options: &CompilerOptions) -> Result<Artifacts, CompilationError> {
+    let mut compiler = Compiler::new(options.clone())?;
+    generate(&mut compiler, typed_program, &mut PhaseTimes::default(), |_| {})?;
+    let object_kind = match options.emit_kind {
+        EmitKind::Bitcode => EmitKind::Bitcode,
+        EmitKind::Object | EmitKind::Assembly => EmitKind::Object,
+    };
+    Ok(Artifacts {
+        ir: compiler.ir_to_string(),
+        assembly: String::from_utf8_lossy(&compiler.emit_to_memory_as(EmitKind::Assembly)?).into_owned(),
+        object: compiler.emit_to_memory_as(object_kind)?,
+        warnings: Diagnostics { sources: SourceMap::default(), diagnostics: Vec::new() },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_reports_to_sink() {
+        let source_code = "fn unused() {\n}\n\npub fn main() u8 {\n    u8 x = 1\n    if true {\n        u8 x = 2\n    }\n    ret x\n}\n";
+        let sources = SourceMap::single_file("main.fl", source_code.to_string());
+        let mut codes = Vec::new();
+        let typed_program = check(&sources, &mut |diagnostic: Diagnostic| codes.push(diagnostic.code));
+        assert!(typed_program.is_some());
+        assert_eq!(codes, ["W0201", "W0301"]);
+
+        let sources = SourceMap::single_file("main.fl", "pub fn main() u8 {\n    ret $\n}\n".to_string());
+        let mut diagnostics = Vec::new();
+        assert!(check(&sources, &mut diagnostics).is_none());
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, "E0001");
+    }
+
+    #[test]
+    fn check_with_options() {
+        let source_code = "pub fn main() u8 {\n    u8 café = ((1))\n    ret café\n}\n";
+        let sources = SourceMap::single_file("main.fl", source_code.to_string());
+        let mut times = PhaseTimes::default();
+        assert!(check_with(&sources, &CheckOptions::default(), &mut Vec::new(), &mut times).is_some());
+        let report = times.to_string();
+        assert!(report.contains("type checking") && report.contains("4  lines"), "{}", report);
+
+        let code_with = |options: CheckOptions| {
+            let mut diagnostics = Vec::new();
+            check_with(&sources, &options, &mut diagnostics, &mut PhaseTimes::default());
+            diagnostics.first().map(|diagnostic| diagnostic.code)
+        };
+        assert_eq!(code_with(CheckOptions { identifier_policy: IdentifierPolicy::Ascii, ..Default::default() }), Some("E0001"));
+        assert_eq!(code_with(CheckOptions { max_nesting_depth: 2, ..Default::default() }), Some("E0104"));
+    }
+
+    #[test]
+    fn typing_errors_point_at_the_code() {
+        let source_code = "pub fn main() u8 {\n    i64 x = 1\n    if x < true {\n        ret 1\n    }\n    ret 0\n}\n";
+        let sources = SourceMap::single_file("main.fl", source_code.to_string());
+        let mut diagnostics = Vec::new();
+        assert!(check(&sources, &mut diagnostics).is_none());
+        let expected = "\
+error[E0318]: operator '<' needs left-hand-side (i64) and right-hand-side (bool) to be the same type
+ --> main.fl:3:10
+  │
+3 │     if x < true {
+  │          ^
+";
+        assert_eq!(diagnostics[0].render(&sources), expected);
+    }
+
+    #[test]
+    fn int_literals_must_fit_in_their_type() {
+        let code_of = |literal: &str| {
+            let source_code = format!("pub fn main() u8 {{\n    u8 z = {}\n    ret z\n}}\n", literal);
+            let sources = SourceMap::single_file("main.fl", source_code);
+            let mut diagnostics = Vec::new();
+            check(&sources, &mut diagnostics);
+            diagnostics.first().map(|diagnostic| diagnostic.code)
+        };
+        assert_eq!(code_of("255"), None);
+        assert_eq!(code_of("256"), Some("E0328"));
+        assert_eq!(code_of("99999999999999999999999"), Some("E0328"));
+        assert_eq!(code_of("999999999999999999999999999999999999999999"), Some("E0004"));
+    }
+
+    #[cfg(feature = "llvm")]
+    #[test]
+    fn compiles_to_ir_and_output() {
+        let source_code = "fn unused() {\n}\n\npub fn main() u8 {\n    ret 0\n}\n";
+        let options = CompilerOptions { emit_kind: EmitKind::Bitcode, ..Default::default() };
+        let artifacts = compile_str(source_code, &options).unwrap();
+        assert!(artifacts.ir.contains("define i8 @main()"));
+        assert!(artifacts.object.starts_with(b"BC"));
+        assert!(artifacts.assembly.contains("main:"));
+        let warnings: Vec<_> = artifacts.warnings.warnings().map(|warning| warning.code).collect();
+        assert_eq!(warnings, ["W0301"]);
+    }
+
+    #[cfg(feature = "llvm")]
+    #[test]
+    fn reports_every_error() {
+        let source_code = "fn answer() u8 {\n    ret x\n}\n\npub fn main() u8 {\n    ret y\n}\n";
+        let options = CompilerOptions { source_file_name: Some("bad.fl".to_string()), ..Default::default() };
+        let diagnostics = compile_str(source_code, &options).unwrap_err();
+        assert_eq!(diagnostics.errors().count(), 2);
+        assert!(diagnostics.render().contains("--> bad.fl:6:9"));
+    }
+
+    #[cfg(feature = "llvm")]
+    #[test]
+    fn compiles_files() {
+        let path = std::env::temp_dir().join(format!("flick-compile-file-{}.fl", std::process::id()));
+        fs::write(&path, "fn unused() {\n}\n\npub fn main() u8 {\n    ret 0\n}\n").unwrap();
+        let options = CompilerOptions { debug_info: true, ..Default::default() };
+        let artifacts = compile_file(&path, &options);
+        fs::remove_file(&path).unwrap();
+
+        let artifacts = artifacts.unwrap();
+        assert!(artifacts.object.starts_with(b"\x7fELF"));
+        // The file's name is used for the debug info, and the warnings point into the file
+        let file_name = path.file_name().unwrap().to_str().unwrap();
+        assert!(artifacts.ir.contains(&format!("filename: \"{}\"", file_name)), "{}", artifacts.ir);
+        assert!(artifacts.warnings.render().contains(&format!("--> {}:1:4", path.display())));
+    }
+
+    #[cfg(feature = "llvm")]
+    #[test]
+    fn compiles_sources_with_check_options() {
+        let mut sources = SourceMap::single_file("main.fl", "pub fn main() u8 {\n    ret forty_two()\n}\n".to_string());
+        sources.add_file("café.fl", "fn forty_two() u8 {\n    u8 café = 42\n    ret café\n}\n".to_string());
+        let artifacts = compile_sources(sources.clone(), &Default::default(), &CheckOptions::default()).unwrap();
+        assert!(artifacts.ir.contains("ret i8 42"), "{}", artifacts.ir);
+
+        let ascii = CheckOptions { identifier_policy: IdentifierPolicy::Ascii, ..Default::default() };
+        let diagnostics = compile_sources(sources, &Default::default(), &ascii).unwrap_err();
+        assert!(diagnostics.render().contains("--> café.fl:2:11"), "{}", diagnostics);
+    }
+
+    #[cfg(feature = "llvm")]
+    #[test]
+    fn reports_unreadable_files() {
+        let diagnostics = compile_file("does/not/exist.fl", &Default::default()).unwrap_err();
+        let codes: Vec<_> = diagnostics.errors().map(|error| error.code).collect();
+        assert_eq!(codes, ["E0407"]);
+        assert!(diagnostics.to_string().contains("couldn't read 'does/not/exist.fl'"));
+    }
+}
@@ -0,0 +1,277 @@
+use std::collections::HashSet;
+
+use crate::ast::{FuncParam, FuncProto, FuncVisibility, NodeId};
+use crate::compilation::error::CompilationError;
+use crate::lexing::name::Name;
+use crate::lexing::token::Span;
+use crate::typing::typed_ast::{
+    TypedCall, TypedExpr, TypedGlobalStatement, TypedIdentifier, TypedProgram, TypedStatement, TypedVarDeclaration,
+};
+use crate::types::Type;
+
+/// The hook that instrumented functions call (with their name) when they start
+pub const ENTER_HOOK: &str = "__flick_enter";
+/// The hook that instrumented functions call just before they return
+pub const EXIT_HOOK: &str = "__flick_exit";
+
+/// The variable that holds a function's return value while it calls [EXIT_HOOK]
+const RETURN_VALUE: &str = "__flick_ret";
+
+/// Instruments every function in `typed_program` for profiling: each function calls
+/// `__flick_enter` with its name when it starts, and `__flick_exit` just before it returns
+/// (after its return value is evaluated), so that the hooks can record a trace or build a
+/// flamegraph.
+///
+/// The program can define the hooks itself (as `pub` functions, so that they aren't removed for
+/// being unused), or declare them as `extern` functions; otherwise, they're declared as `extern`
+/// functions, for the linker to find. Either way, they must be like `fn __flick_enter(str name)`
+/// and `fn __flick_exit()`, or this returns [CompilationError::InvalidProfilingHook]. The hooks,
+/// and the functions that they call, aren't instrumented, so the hooks never call themselves.
+///
+/// ```
+/// use flick::ast::Arena;
+/// use flick::{profiling, Parser, Lexer, Typer};
+/// use flick::typed_ast::{TypedGlobalStatement, TypedStatement};
+///
+/// let source_code = "pub fn main() u8 {\n    ret 0\n}\n";
+/// let (tokens, spans) = Lexer::lex_with_spans(source_code).unwrap();
+/// let arena = Arena::new();
+/// let program = Parser::parse_program_with_spans(&arena, &tokens, &spans).unwrap();
+/// let mut typed_program = Typer::new().type_program(&program).unwrap();
+///
+/// profiling::instrument_functions(&mut typed_program).unwrap();
+/// let TypedGlobalStatement::FuncDef(main) = &typed_program.global_statements[0] else { panic!() };
+/// assert!(matches!(main.body[..], [
+///     TypedStatement::Call(_),           // __flick_enter("main")
+///     TypedStatement::VarDeclaration(_), // __flick_ret = 0
+///     TypedStatement::Call(_),           // __flick_exit()
+///     TypedStatement::Return(Some(_)),   // ret __flick_ret
+/// ]));
+/// ```
+pub fn instrument_functions(typed_program: &mut TypedProgram) -> Result<(), CompilationError> {
+    let enter_hook = hook_proto(ENTER_HOOK, vec![(Type::Str, "name")]);
+    let exit_hook = hook_proto(EXIT_HOOK, vec![]);
+    let mut uninstrumented = HashSet::new();
+    let hooks = [(ENTER_HOOK, &enter_hook, "fn __flick_enter(str name)"), (EXIT_HOOK, &exit_hook, "fn __flick_exit()")];
+    for (name, hook, expected) in hooks {
+        let declared = typed_program
+            .global_statements
+            .iter()
+            .map(|global_statement| match global_statement {
+                TypedGlobalStatement::Extern(proto) => proto,
+                TypedGlobalStatement::FuncDef(func_def) => &func_def.proto,
+            })
+            .find(|proto| proto.name == name);
+        match declared {
+            Some(proto) if !same_signature(proto, hook) => {
+                return Err(CompilationError::InvalidProfilingHook { name, expected });
+            }
+            Some(_) => uninstrumented.extend(called_from(typed_program, name)),
+            None => typed_program.global_statements.push(TypedGlobalStatement::Extern(hook.clone())),
+        }
+    }
+
+    for global_statement in typed_program.global_statements.iter_mut() {
+        let TypedGlobalStatement::FuncDef(func_def) = global_statement else { continue };
+        if uninstrumented.contains(func_def.proto.name.as_str()) {
+            continue;
+        }
+        let enter = call(&enter_hook, vec![TypedExpr::StrLiteral(func_def.proto.name.to_string())]);
+        let body = std::mem::take(&mut func_def.body);
+        func_def.body = std::iter::once(enter).chain(exit_before_returns(body, &exit_hook)).collect();
+    }
+    Ok(())
+}
+
+/// Returns `body` with a call to the exit hook before each of its `ret`s (including the ones
+/// in the bodies inside it). A returned value is stored in a variable first, so that a call in
+/// it (like `ret f(n)`) finishes before the exit hook is called.
+fn exit_before_returns(body: Vec<TypedStatement>, exit_hook: &FuncProto) -> Vec<TypedStatement> {
+    let mut instrumented = Vec::with_capacity(body.len());
+    for statement in body {
+        match statement {
+            TypedStatement::Return(None) => {
+                instrumented.push(call(exit_hook, vec![]));
+                instrumented.push(TypedStatement::Return(None));
+            }
+            TypedStatement::Return(Some(value)) => {
+                let id_type = value.get_result_type();
+                instrumented.push(TypedStatement::VarDeclaration(TypedVarDeclaration {
+                    var_name: RETURN_VALUE.to_string(),
+                    var_type: id_type.clone(),
+                    var_value: value,
+                }));
+                instrumented.push(call(exit_hook, vec![]));
+                let name = RETURN_VALUE.to_string();
+                instrumented.push(TypedStatement::Return(Some(TypedExpr::Identifier(TypedIdentifier { name, id_type }))));
+            }
+            TypedStatement::WhileLoop(mut while_loop) => {
+                while_loop.body = exit_before_returns(while_loop.body, exit_hook);
+                instrumented.push(TypedStatement::WhileLoop(while_loop));
+            }
+            TypedStatement::If(mut if_statement) => {
+                if_statement.then_body = exit_before_returns(if_statement.then_body, exit_hook);
+                if_statement.else_body = if_statement.else_body.map(|else_body| exit_before_returns(else_body, exit_hook));
+                instrumented.push(TypedStatement::If(if_statement));
+            }
+            statement => instrumented.push(statement),
+        }
+    }
+    instrumented
+}
+
+/// Returns the names of the function called `func_name` and of every function that it calls,
+/// directly or indirectly.
+fn called_from(typed_program: &TypedProgram, func_name: &str) -> HashSet<String> {
+    let mut called = HashSet::from([func_name.to_string()]);
+    let mut unvisited = vec![func_name.to_string()];
+    while let Some(func_name) = unvisited.pop() {
+        for global_statement in &typed_program.global_statements {
+            let TypedGlobalStatement::FuncDef(func_def) = global_statement else { continue };
+            if func_def.proto.name != func_name {
+                continue;
+            }
+            let mut callees = Vec::new();
+            func_def.body.iter().for_each(|stmt| stmt.collect_calls(&mut callees));
+            for callee in callees {
+                if called.insert(callee.to_string()) {
+                    unvisited.push(callee.to_string());
+                }
+            }
+        }
+    }
+    called
+}
+
+/// Returns the prototype of an `extern` hook called `name` that returns nothing.
+fn hook_proto(name: &str, params: Vec<(Type, &str)>) -> FuncProto {
+    FuncProto {
+        func_visibility: FuncVisibility::Extern,
+        name: Name::intern(name),
+        params: params
+            .into_iter()
+            .map(|(param_type, param_name)| FuncParam {
+                param_type,
+                param_name: Name::intern(param_name),
+                span: Span::default(),
+                id: NodeId::default(),
+            })
+            .collect(),
+        return_type: Box::new(Type::Void),
+        span: Span::default(),
+        id: NodeId::default(),
+        library: None,
+    }
+}
+
+/// Returns `true` if `a` and `b` take the same types of parameters and return the same type.
+fn same_signature(a: &FuncProto, b: &FuncProto) -> bool {
+    a.return_type == b.return_type
+        && a.params.iter().map(|param| &param.param_type).eq(b.params.iter().map(|param| &param.param_type))
+}
+
+fn call(proto: &FuncProto, args: Vec<TypedExpr>) -> TypedStatement {
+    TypedStatement::Call(TypedCall { function_name: proto.name.to_string(), function_proto: proto.clone(), args })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Lexer, Parser, Typer};
+    use crate::ast::Arena;
+
+    fn typed_program(source_code: &str) -> TypedProgram {
+        let (tokens, spans) = Lexer::lex_with_spans(source_code).unwrap();
+        let arena = Arena::new();
+        let program = Parser::parse_program_with_spans(&arena, &tokens, &spans).unwrap();
+        Typer::new().type_program(&program).unwrap()
+    }
+
+    fn func_body<'a>(typed_program: &'a TypedProgram, name: &str) -> &'a [TypedStatement] {
+        typed_program
+            .global_statements
+            .iter()
+            .find_map(|global_statement| match global_statement {
+                TypedGlobalStatement::FuncDef(func_def) if func_def.proto.name == name => Some(&func_def.body[..]),
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn calls_the_hooks() {
+        let mut typed_program = typed_program(
+            "\
+fn f(u64 n) {
+    if n > 2 {
+        ret
+    }
+}
+
+pub fn main() u8 {
+    ret 0
+}
+",
+        );
+        instrument_functions(&mut typed_program).unwrap();
+
+        let TypedStatement::Call(enter) = &func_body(&typed_program, "f")[0] else { panic!() };
+        assert_eq!(enter.function_name, ENTER_HOOK);
+        assert_eq!(enter.args, [TypedExpr::StrLiteral("f".to_string())]);
+        let [_, TypedStatement::If(if_statement), TypedStatement::Call(exit), TypedStatement::Return(None)] =
+            func_body(&typed_program, "f")
+        else {
+            panic!()
+        };
+        assert_eq!(exit.function_name, EXIT_HOOK);
+        assert!(matches!(if_statement.then_body[..], [TypedStatement::Call(_), TypedStatement::Return(None)]));
+
+        // The hooks are declared, for the linker to find
+        let externs: Vec<_> = typed_program
+            .global_statements
+            .iter()
+            .filter_map(|global_statement| match global_statement {
+                TypedGlobalStatement::Extern(proto) => Some(proto.name.as_str()),
+                TypedGlobalStatement::FuncDef(_) => None,
+            })
+            .collect();
+        assert_eq!(externs, [ENTER_HOOK, EXIT_HOOK]);
+    }
+
+    #[test]
+    fn hooks_in_flick() {
+        let mut typed_program = typed_program(
+            "\
+extern fn puts(str s) i32
+
+fn log(str s) {
+    puts(s)
+}
+
+pub fn __flick_enter(str name) {
+    log(name)
+}
+
+pub fn __flick_exit() {}
+
+pub fn main() u8 {
+    log(\"hi\")
+    ret 0
+}
+",
+        );
+        instrument_functions(&mut typed_program).unwrap();
+
+        assert!(matches!(func_body(&typed_program, "log"), [TypedStatement::Call(_), TypedStatement::Return(None)]));
+        assert!(matches!(func_body(&typed_program, EXIT_HOOK), [TypedStatement::Return(None)]));
+        assert_eq!(func_body(&typed_program, "main").len(), 5);
+        assert_eq!(typed_program.global_statements.len(), 5);
+    }
+
+    #[test]
+    fn invalid_hooks() {
+        let mut typed_program = typed_program("extern fn __flick_exit(u8 code)\n\npub fn main() u8 {\n    ret 0\n}\n");
+        let expected = CompilationError::InvalidProfilingHook { name: EXIT_HOOK, expected: "fn __flick_exit()" };
+        assert_eq!(instrument_functions(&mut typed_program), Err(expected));
+    }
+}
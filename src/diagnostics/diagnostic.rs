@@ -0,0 +1,428 @@
+use std::fmt;
+use std::fmt::Write;
+
+use crate::compilation::error::CompilationError;
+use crate::error::{ErrorKind, FlickError};
+use crate::lexing::error::LexingError;
+use crate::lexing::token::{Position, Span};
+use crate::parsing::error::ParsingError;
+use crate::typing::error::TypingError;
+
+use super::fix::{self, Fix};
+use super::source_map::SourceMap;
+
+/// How serious a [Diagnostic] is.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Severity {
+    /// The program can't be compiled.
+    Error,
+    /// The program can be compiled, but it's probably not what was meant.
+    Warning,
+}
+
+impl Severity {
+    /// Returns the ANSI style that the severity (and the underline) are colored with.
+    fn style(&self) -> &'static str {
+        match self {
+            Self::Error => ERROR,
+            Self::Warning => WARNING,
+        }
+    }
+
+    /// Returns `text` (like `error: aborting due to 2 previous errors`) with the severity at the
+    /// start colored as in [Diagnostic::render_colored], or `text` as it is if not `colored`.
+    pub fn paint(&self, text: &str, colored: bool) -> String {
+        let severity = self.to_string();
+        match text.strip_prefix(&severity) {
+            Some(rest) => format!("{}{}", paint(&severity, self.style(), colored), rest),
+            None => text.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Error => write!(f, "error"),
+            Self::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// An error or warning about a program, with everything needed to show it to the user; see
+/// [Diagnostic::render].
+///
+/// Every error that the compiler reports (from the [Lexer](crate::Lexer) through to the
+/// [Backend](crate::Backend)) can be turned into a diagnostic.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// The code that identifies the kind of problem, like `E0201` (or `W0201` for a warning)
+    pub code: &'static str,
+    pub message: String,
+    /// Where the problem is, if it's somewhere in particular; an empty span points at the word
+    /// (or the character) that starts there
+    pub span: Option<Span>,
+    /// Other places in the code that are related to the problem
+    pub notes: Vec<Note>,
+    /// Suggestions for how to fix the problem
+    pub help: Vec<String>,
+    /// A fix that `flick fix` can apply without a person looking at it, if there's one
+    pub fix: Option<Fix>,
+}
+
+/// Extra information attached to a [Diagnostic], like where something was first defined.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Note {
+    pub message: String,
+    pub span: Option<Span>,
+}
+
+impl Diagnostic {
+    /// Returns a diagnostic for `error`, reported as an error.
+    pub fn error(error: &FlickError) -> Self {
+        Self::from_flick_error(Severity::Error, error)
+    }
+
+    /// Returns a diagnostic for `error`, reported as a warning (like a shadowed variable).
+    pub fn warning(error: &FlickError) -> Self {
+        Self::from_flick_error(Severity::Warning, error)
+    }
+
+    fn from_flick_error(severity: Severity, error: &FlickError) -> Self {
+        let span = match &error.kind {
+            ErrorKind::ParsingError(ParsingError::UnexpectedToken { span, .. }) => Some(*span),
+            ErrorKind::TypingError(err) => err.span(),
+            _ => None,
+        };
+        let span = span.unwrap_or(Span { start: error.index, end: error.index });
+        let note = match &error.kind {
+            ErrorKind::LexingError(err) => err.literal_start().map(|start| ("the string literal starts here", start)),
+            ErrorKind::ResolvingError(err) => err.first_definition().map(|span| ("first declared here", span.start)),
+            _ => None,
+        };
+        let notes = note.into_iter().map(|(message, start)| Note {
+            message: message.to_string(),
+            span: Some(Span { start, end: start }),
+        });
+
+        Self {
+            severity,
+            code: error.kind.code(),
+            message: error.kind.to_string(),
+            span: Some(span),
+            notes: notes.collect(),
+            help: help(&error.kind).into_iter().collect(),
+            fix: fix::suggest(error),
+        }
+    }
+
+    /// Returns the diagnostic with `fix` attached, if there is one (like the fixes that
+    /// [fix::suggest_in_file] finds, which depend on the code around the problem).
+    pub fn with_fix(mut self, fix: Option<Fix>) -> Self {
+        if fix.is_some() {
+            self.fix = fix;
+        }
+        self
+    }
+
+    /// Returns the part of `source_code` that `span` (the span of a diagnostic or of one of its
+    /// notes) points at: the span itself, unless it's empty, in which case it's the word (or the
+    /// one character) that starts there.
+    pub fn highlighted(span: Span, source_code: &str) -> Span {
+        if span.end > span.start {
+            return span;
+        }
+        let rest = &source_code[span.start..];
+        let word_len = match rest.find(|c: char| !c.is_alphanumeric() && c != '_') {
+            Some(0) => rest.chars().next().map_or(0, char::len_utf8),
+            Some(len) => len,
+            None => rest.len(),
+        };
+        Span { start: span.start, end: span.start + word_len }
+    }
+
+    /// Returns the diagnostic with its spans (and its notes' spans) moved `offset` bytes
+    /// later, e.g. from where they are in a file to where that file starts in a [SourceMap].
+    pub fn offset_by(mut self, offset: usize) -> Self {
+        let offset_span = |span: Span| Span { start: span.start + offset, end: span.end + offset };
+        self.span = self.span.map(offset_span);
+        for note in &mut self.notes {
+            note.span = note.span.map(offset_span);
+        }
+        if let Some(fix) = &mut self.fix {
+            fix.edits = fix.edits.iter().map(|edit| edit.offset_by(offset)).collect();
+        }
+        self
+    }
+
+    /// Returns the diagnostic as text for the user, like `rustc` shows it: the message, then
+    /// where the problem is in `sources` (the files being compiled), underlined, then any notes
+    /// and help.
+    ///
+    /// ```text
+    /// error[E0201]: cannot find variable 'y' in this scope
+    ///  --> example.fl:2:9
+    ///   │
+    /// 2 │     x = y + 1
+    ///   │         ^
+    /// ```
+    pub fn render(&self, sources: &SourceMap) -> String {
+        self.render_styled(sources, false)
+    }
+
+    /// Returns the diagnostic as text for a terminal: the same as [Diagnostic::render], but
+    /// colored with ANSI escape codes (the severity in red or yellow, the gutter in blue, and so
+    /// on, like `rustc`).
+    pub fn render_colored(&self, sources: &SourceMap) -> String {
+        self.render_styled(sources, true)
+    }
+
+    fn render_styled(&self, sources: &SourceMap, colored: bool) -> String {
+        let paint = |text: &str, style: &str| paint(text, style, colored);
+        let severity_style = self.severity.style();
+        let severity = paint(&format!("{}[{}]", self.severity, self.code), severity_style);
+        let mut rendered = format!("{}{}\n", severity, paint(&format!(": {}", self.message), BOLD));
+        if let Some(span) = self.span {
+            render_snippet(&mut rendered, span, sources, paint, severity_style);
+        }
+        for note in &self.notes {
+            writeln!(rendered, "{}: {}", paint("note", NOTE), note.message).unwrap();
+            if let Some(span) = note.span {
+                render_snippet(&mut rendered, span, sources, paint, NOTE);
+            }
+        }
+        for help in &self.help {
+            writeln!(rendered, "{}: {}", paint("help", HELP), help).unwrap();
+        }
+        rendered
+    }
+}
+
+impl From<&TypingError> for Diagnostic {
+    /// Returns a diagnostic for an error that the [Typer](crate::Typer) found (see
+    /// [TypingError::span] for where it is).
+    fn from(error: &TypingError) -> Self {
+        let kind = ErrorKind::TypingError(error.clone());
+        Self {
+            span: error.span(),
+            ..Self::error(&FlickError { index: 0, kind })
+        }
+    }
+}
+
+impl From<&CompilationError> for Diagnostic {
+    /// Returns a diagnostic for an error that a [Backend](crate::Backend) found, which isn't
+    /// anywhere in particular in the code.
+    fn from(error: &CompilationError) -> Self {
+        Self {
+            severity: Severity::Error,
+            code: error.code(),
+            message: error.to_string(),
+            span: None,
+            notes: Vec::new(),
+            help: Vec::new(),
+            fix: None,
+        }
+    }
+}
+
+/// Returns a suggestion for fixing `error`, if there's an obvious one.
+fn help(error: &ErrorKind) -> Option<String> {
+    let help = match error {
+        ErrorKind::ResolvingError(err) => return err.suggestion().map(|name| format!("did you mean '{}'?", name)),
+        ErrorKind::LintingError(err) => return err.help(),
+        ErrorKind::LexingError(LexingError::UnterminatedStrLiteral { .. }) => {
+            Some("add a '\"' where the string literal should end")
+        }
+        ErrorKind::LexingError(LexingError::InvalidEscapeSequence { .. }) => {
+            Some("the escape sequences are '\\n', '\\t', '\\0', '\\\\', and '\\\"'")
+        }
+        ErrorKind::ParsingError(ParsingError::NestedFunction) => {
+            Some("move the function out of the one it's in (or add the '}' that's missing before it)")
+        }
+        ErrorKind::ParsingError(ParsingError::AssignmentInCondition) => Some("to check that the two sides are equal, use '=='"),
+        ErrorKind::TypingError(
+            TypingError::MissingMain
+            | TypingError::PrivateMain { .. }
+            | TypingError::MainWithParams { .. }
+            | TypingError::InvalidMainReturnType { .. },
+        ) => Some("the 'main' function should be declared as 'pub fn main() u8'"),
+        _ => None,
+    };
+    help.map(str::to_string)
+}
+
+// The ANSI styles (Select Graphic Rendition parameters) that colored diagnostics use
+const BOLD: &str = "1";
+const ERROR: &str = "1;31";
+const WARNING: &str = "1;33";
+const NOTE: &str = "1;32";
+const HELP: &str = "1;36";
+const GUTTER: &str = "1;34";
+
+/// Returns `text` in the ANSI `style` (like `1;31`, bold red) if `colored`, and as it is otherwise.
+fn paint(text: &str, style: &str, colored: bool) -> String {
+    match colored {
+        true => format!("\x1b[{}m{}\x1b[0m", style, text),
+        false => text.to_string(),
+    }
+}
+
+/// Appends where `span` is in `sources` (like ` --> example.fl:2:9`), followed by its line of
+/// code with the span underlined (in `underline_style`), to `rendered`.
+fn render_snippet(
+    rendered: &mut String,
+    span: Span,
+    sources: &SourceMap,
+    paint: impl Fn(&str, &str) -> String,
+    underline_style: &str,
+) {
+    let (file, span) = sources.locate(span);
+    let source_code = &file.source_code[..];
+    let position = Position::of(span.start, source_code);
+    let line_start = source_code[..span.start].rfind('\n').map_or(0, |newline| newline + 1);
+    let line_len = source_code[line_start..].find('\n').unwrap_or(source_code.len() - line_start);
+    let line = &source_code[line_start..line_start + line_len];
+
+    let end = Diagnostic::highlighted(span, source_code).end.min(line_start + line_len);
+
+    // Tabs are shown as 4 spaces, so the underline has to count them that way, too
+    let width = |code: &str| code.chars().map(|c| if c == '\t' { 4 } else { 1 }).sum::<usize>();
+    let indent = width(&source_code[line_start..span.start]);
+    let underline_width = match end > span.start {
+        true => width(&source_code[span.start..end]),
+        false => 1,
+    };
+    let line = line.replace('\t', "    ");
+
+    let gutter = " ".repeat(position.line.to_string().len());
+    let bar = paint(&format!("{} │", gutter), GUTTER);
+    let underline = paint(&"^".repeat(underline_width), underline_style);
+    writeln!(rendered, "{}{} {}:{}", gutter, paint("-->", GUTTER), file.path.display(), position).unwrap();
+    writeln!(rendered, "{}", bar).unwrap();
+    writeln!(rendered, "{} {}", paint(&format!("{} │", position.line), GUTTER), line).unwrap();
+    writeln!(rendered, "{} {}{}", bar, " ".repeat(indent), underline).unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resolving::error::ResolvingError;
+
+    fn render(diagnostic: &Diagnostic, source_code: &str) -> String {
+        diagnostic.render(&SourceMap::single_file("test.fl", source_code.to_string()))
+    }
+
+    #[test]
+    fn underlines_the_word_at_the_error() {
+        let error = FlickError {
+            index: 19,
+            kind: ErrorKind::ResolvingError(ResolvingError::UndefinedVariable { name: "bar".to_string(), suggestion: None }),
+        };
+        let expected = "\
+error[E0201]: cannot find variable 'bar' in this scope
+ --> test.fl:2:9
+  │
+2 │     x = bar + 1
+  │         ^^^
+";
+
+        let source_code = "fn foo() {\n    x = bar + 1\n}";
+        assert_eq!(render(&Diagnostic::error(&error), source_code), expected);
+    }
+
+    #[test]
+    fn notes_and_help() {
+        let error = FlickError {
+            index: 14,
+            kind: ErrorKind::LexingError(LexingError::UnterminatedStrLiteral {
+                literal: "\"oops".to_string(),
+                literal_start: 9,
+            }),
+        };
+        let expected = "\
+error[E0002]: unterminated string literal: \"oops (a '\"' is missing before the end of the line)
+ --> test.fl:1:15
+  │
+1 │ \tstr s = \"oops
+  │ \t             ^
+note: the string literal starts here
+ --> test.fl:1:10
+  │
+1 │ \tstr s = \"oops
+  │ \t        ^
+help: add a '\"' where the string literal should end
+";
+
+        // The tab is shown as 4 spaces, and the underline lines up with it
+        let source_code = "\tstr s = \"oops\n";
+        assert_eq!(render(&Diagnostic::error(&error), source_code), expected.replace('\t', "    "));
+    }
+
+    #[test]
+    fn colors() {
+        let error = FlickError {
+            index: 4,
+            kind: ErrorKind::ResolvingError(ResolvingError::ShadowedVariable {
+                name: "x".to_string(),
+                shadowed: Span { start: 0, end: 0 },
+            }),
+        };
+        let expected = "\
+\x1b[1;33mwarning[W0201]\x1b[0m\x1b[1m: variable 'x' shadows a variable from an outer scope\x1b[0m
+ \x1b[1;34m-->\x1b[0m test.fl:1:5
+\x1b[1;34m  │\x1b[0m
+\x1b[1;34m1 │\x1b[0m x = x
+\x1b[1;34m  │\x1b[0m     \x1b[1;33m^\x1b[0m
+\x1b[1;32mnote\x1b[0m: first declared here
+ \x1b[1;34m-->\x1b[0m test.fl:1:1
+\x1b[1;34m  │\x1b[0m
+\x1b[1;34m1 │\x1b[0m x = x
+\x1b[1;34m  │\x1b[0m \x1b[1;32m^\x1b[0m
+";
+
+        let sources = SourceMap::single_file("test.fl", "x = x".to_string());
+        let rendered = Diagnostic::warning(&error).render_colored(&sources);
+        assert_eq!(rendered, expected);
+        assert_eq!(Severity::Error.paint("error: oops", true), "\x1b[1;31merror\x1b[0m: oops");
+        assert_eq!(Severity::Error.paint("error: oops", false), "error: oops");
+    }
+
+    #[test]
+    fn notes_in_other_files() {
+        let mut sources = SourceMap::default();
+        sources.add_file("a.fl", "fn foo() {\n}\n".to_string());
+        let b_start = sources.add_file("b.fl", "\nfn foo() {\n}\n".to_string()).start;
+        let error = FlickError {
+            index: b_start + 4,
+            kind: ErrorKind::ResolvingError(ResolvingError::FunctionRedefinition {
+                name: "foo".to_string(),
+                first_definition: Span { start: 3, end: 6 },
+            }),
+        };
+        let expected = "\
+error[E0204]: function 'foo' is defined more than once
+ --> b.fl:2:4
+  │
+2 │ fn foo() {
+  │    ^^^
+note: first declared here
+ --> a.fl:1:4
+  │
+1 │ fn foo() {
+  │    ^^^
+";
+
+        // The error is in the second file, but the note is in the first one
+        assert_eq!(Diagnostic::error(&error).render(&sources), expected);
+    }
+
+    #[test]
+    fn errors_without_a_location() {
+        let error = CompilationError::TargetMachineCreation { triple: "foo".to_string() };
+        let expected = "error[E0404]: failed to create a target machine for 'foo'\n";
+
+        assert_eq!(render(&Diagnostic::from(&error), ""), expected);
+    }
+}
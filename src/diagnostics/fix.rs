@@ -0,0 +1,487 @@
+use std::fmt::Write;
+
+use crate::diagnostics::source_map::SourceFile;
+use crate::error::{ErrorKind, FlickError};
+use crate::lexing::token::{OperatorSymbol, Span, Token};
+use crate::linting::error::LintingError;
+use crate::parsing::error::{Expected, ParsingError};
+
+/// A change to the source code that fixes the problem that a [Diagnostic](super::diagnostic::Diagnostic)
+/// is about, which can be applied without a person looking at it (see [apply]).
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Fix {
+    /// What the fix does, like `insert the missing '}'`
+    pub message: String,
+    pub edits: Vec<Edit>,
+}
+
+/// One change that a [Fix] makes.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Edit {
+    /// Replaces the code at `span` with `replacement` (so an empty span inserts it)
+    Replace { span: Span, replacement: String },
+    /// Removes the whole line (with its newline) that the character at `index` is on
+    DeleteLine { index: usize },
+}
+
+impl Edit {
+    /// Returns where the edit starts (or, for [Edit::DeleteLine], a character on the line).
+    pub fn start(&self) -> usize {
+        match self {
+            Self::Replace { span, .. } => span.start,
+            Self::DeleteLine { index } => *index,
+        }
+    }
+
+    /// Returns the edit moved `offset` bytes later (see
+    /// [Diagnostic::offset_by](super::diagnostic::Diagnostic::offset_by)).
+    pub fn offset_by(&self, offset: usize) -> Self {
+        match self {
+            Self::Replace { span, replacement } => Self::Replace {
+                span: Span { start: span.start + offset, end: span.end + offset },
+                replacement: replacement.clone(),
+            },
+            Self::DeleteLine { index } => Self::DeleteLine { index: index + offset },
+        }
+    }
+
+    /// Returns the part of `source_code` that the edit replaces, where the edit is `start` bytes
+    /// after it.
+    fn range(&self, source_code: &str, start: usize) -> Span {
+        match self {
+            Self::Replace { span, .. } => Span { start: span.start - start, end: span.end - start },
+            Self::DeleteLine { index } => {
+                let index = index - start;
+                let start = source_code[..index].rfind('\n').map_or(0, |i| i + 1);
+                let end = source_code[index..].find('\n').map_or(source_code.len(), |i| index + i + 1);
+                Span { start, end }
+            }
+        }
+    }
+}
+
+/// Returns a fix for `error` that doesn't depend on the code around it, if there is one:
+/// replacing the `=` of an assignment that's used as a condition with `==`, or removing a
+/// variable that's never read (and everything assigned to it).
+pub fn suggest(error: &FlickError) -> Option<Fix> {
+    match &error.kind {
+        ErrorKind::ParsingError(ParsingError::AssignmentInCondition) => Some(Fix {
+            message: "replace '=' with '=='".to_string(),
+            edits: vec![Edit::Replace {
+                span: Span { start: error.index, end: error.index + 1 },
+                replacement: "==".to_string(),
+            }],
+        }),
+        ErrorKind::LintingError(LintingError::UnusedVariable { name, removable, .. }) if !removable.is_empty() => {
+            Some(Fix {
+                message: format!("remove '{}'", name),
+                edits: removable.iter().map(|&index| Edit::DeleteLine { index }).collect(),
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Returns a fix for `error`, an error in `file` (which lexes to `tokens`, at `spans`), if
+/// there is one. Besides [suggest]'s, these fixes depend on the code around the error: inserting
+/// a missing `}`, `)`, or `,`, and splitting a chained comparison (like `if 0 < x < 10 {`) into
+/// two nested `if`s.
+///
+/// Like the error, `tokens`' spans and the fix point into the [SourceMap](crate::SourceMap)
+/// that `file` is in.
+///
+/// ```
+/// use flick::ast::Arena;
+/// use flick::{fix, Diagnostic, Lexer, Parser, SourceMap};
+///
+/// let sources = SourceMap::single_file("a.fl", "fn f() {\n    f(1 2)\n".to_string());
+/// let file = &sources.files()[0];
+/// let (tokens, spans) = Lexer::lex_with_spans(&file.source_code).unwrap();
+/// let errors = Parser::parse_program_with_spans(&Arena::new(), &tokens, &spans).unwrap_err();
+///
+/// let fixes: Vec<_> = errors.iter().filter_map(|err| fix::suggest_in_file(err, file, &tokens, &spans)).collect();
+/// assert_eq!(fix::apply(file, &fixes), ("fn f() {\n    f(1, 2)\n}\n".to_string(), 2));
+/// ```
+pub fn suggest_in_file(error: &FlickError, file: &SourceFile, tokens: &[Token], spans: &[Span]) -> Option<Fix> {
+    let fix = match &error.kind {
+        ErrorKind::ParsingError(ParsingError::UnexpectedToken { expected, found, span }) => {
+            insert_missing_token(expected, found.as_deref(), *span, file, spans)
+        }
+        ErrorKind::ParsingError(ParsingError::ChainedComparison) => {
+            split_chained_comparison(error.index, file, tokens, spans)
+        }
+        _ => None,
+    };
+    fix.or_else(|| suggest(error))
+}
+
+/// Returns a fix that inserts the `}`, `)`, or `,` that the parser expected at `span` (rather than
+/// `found`), if it's clear that that's what's missing.
+fn insert_missing_token(
+    expected: &[Expected],
+    found: Option<&Token>,
+    span: Span,
+    file: &SourceFile,
+    spans: &[Span],
+) -> Option<Fix> {
+    // A missing `)` or `,` goes right after the token before, rather than before the next one
+    let previous_end = spans[..spans.partition_point(|other| other.end <= span.start)].last()?.end;
+    let ends_list = matches!(
+        found,
+        None | Some(Token::Newline | Token::Comment(_) | Token::Docstring(_) | Token::LSquirly)
+    );
+    let starts_item = matches!(
+        found,
+        Some(
+            Token::Identifier(_)
+                | Token::IntLiteral(_)
+                | Token::StrLiteral(_)
+                | Token::True
+                | Token::False
+                | Token::Type(_)
+                | Token::LParen
+                | Token::OperatorSymbol(OperatorSymbol::Minus)
+        )
+    );
+    let (token, index, text) = match expected {
+        // The parser only expects a `}` at the end of a body when the file ends
+        [Expected::Token(Token::RSquirly)] if found.is_none() => match file.source_code.chars().last() {
+            None | Some('\n') => ('}', span.start, "}\n"),
+            Some(_) => ('}', span.start, "\n}\n"),
+        },
+        [Expected::Token(Token::RParen)] | [Expected::Token(Token::Comma), Expected::Token(Token::RParen)]
+            if ends_list =>
+        {
+            (')', previous_end, ")")
+        }
+        [Expected::Token(Token::Comma), Expected::Token(Token::RParen)] if starts_item => match previous_end < span.start {
+            true => (',', previous_end, ","),
+            false => (',', previous_end, ", "),
+        },
+        _ => return None,
+    };
+    Some(Fix {
+        message: format!("insert the missing '{}'", token),
+        edits: vec![Edit::Replace { span: Span { start: index, end: index }, replacement: text.to_string() }],
+    })
+}
+
+/// Returns a fix that splits the condition of an `if`, a chained comparison like `a < b < c`
+/// whose second comparator is at `index`, into two nested `if`s (`if a < b {` and `if b < c {`),
+/// moving the `if`'s body into the inner one.
+///
+/// There's only a fix if the `if` starts its line and doesn't have an `else` (so it's clear where
+/// each part goes), and if `b` doesn't call a function (since it's evaluated twice).
+fn split_chained_comparison(index: usize, file: &SourceFile, tokens: &[Token], spans: &[Span]) -> Option<Fix> {
+    let in_condition = |token: &Token| {
+        matches!(
+            token,
+            Token::Identifier(_)
+                | Token::IntLiteral(_)
+                | Token::StrLiteral(_)
+                | Token::True
+                | Token::False
+                | Token::Type(_)
+                | Token::LParen
+                | Token::RParen
+                | Token::Comma
+                | Token::OperatorSymbol(_)
+                | Token::ComparatorSymbol(_)
+        )
+    };
+    let byte_at = |index: usize| file.source_code.as_bytes()[index - file.start];
+    let code = |start: usize, end: usize| &file.source_code[start - file.start..end - file.start];
+    let second = spans.iter().position(|span| span.start == index)?;
+    let if_index = tokens[..second].iter().rposition(|token| !in_condition(token))?;
+    let open = second + tokens[second..].iter().position(|token| !in_condition(token))?;
+    if tokens[if_index] != Token::If || if_index > 0 && tokens[if_index - 1] == Token::Else {
+        return None;
+    }
+    if tokens[open] != Token::LSquirly || tokens[second + 1..open].iter().any(|t| matches!(t, Token::ComparatorSymbol(_))) {
+        return None;
+    }
+
+    // The first comparator is the last one before the second that isn't in parentheses
+    let mut depth = 0;
+    let mut first = None;
+    for (i, token) in tokens.iter().enumerate().take(second).skip(if_index + 1) {
+        match token {
+            Token::LParen => depth += 1,
+            Token::RParen => depth -= 1,
+            Token::ComparatorSymbol(_) if depth == 0 => first = Some(i),
+            _ => {}
+        }
+    }
+    let first = first?;
+    let middle = &tokens[first + 1..second];
+    if depth != 0 || middle.windows(2).any(|pair| matches!(pair, [Token::Identifier(_), Token::LParen])) {
+        return None;
+    }
+
+    let mut depth = 0;
+    let close = open + tokens[open..].iter().position(|token| {
+        match token {
+            Token::LSquirly => depth += 1,
+            Token::RSquirly => depth -= 1,
+            _ => {}
+        }
+        depth == 0
+    })?;
+    if tokens.get(close + 1) == Some(&Token::Else) {
+        return None;
+    }
+    let if_start = spans[if_index].start;
+    let line_start = code(file.start, if_start).rfind('\n').map_or(file.start, |i| file.start + i + 1);
+    let indent = code(line_start, if_start);
+    if !indent.chars().all(|c| c == ' ' || c == '\t') {
+        return None;
+    }
+    let middle = code(spans[first + 1].start, spans[second - 1].end);
+
+    let insert = |index: usize, text: String| Edit::Replace { span: Span { start: index, end: index }, replacement: text };
+    let mut edits = vec![Edit::Replace {
+        span: Span { start: spans[second - 1].end, end: spans[second].start },
+        replacement: format!(" {{\n{}    if {} ", indent, middle),
+    }];
+    // Each line of the body moves in a level, down to the `}` (which becomes the inner `if`'s)
+    for i in spans[open].end..=spans[close].start {
+        if byte_at(i - 1) == b'\n' && byte_at(i) != b'\n' {
+            edits.push(insert(i, "    ".to_string()));
+        }
+    }
+    edits.push(insert(spans[close].end, format!("\n{}}}", indent)));
+    Some(Fix { message: "split the comparison into two nested 'if's".to_string(), edits })
+}
+
+/// Returns the source code of `file` with `fixes` applied, and how many of them were. A fix is
+/// skipped if it changes the same code as a fix before it, or if it would delete a line that
+/// has more than one statement on it (like `if c { x = 1`).
+///
+/// The fixes point into the [SourceMap](crate::SourceMap) that `file` is in, and only the ones
+/// in `file` should be given.
+///
+/// ```
+/// use flick::fix::{apply, Edit, Fix};
+/// use flick::token::Span;
+/// use flick::SourceMap;
+///
+/// let sources = SourceMap::single_file("a.fl", "fn f() {\n    u8 x = 1\n    ret\n".to_string());
+/// let fixes = [
+///     Fix { message: "insert '}'".to_string(), edits: vec![Edit::Replace { span: Span { start: 30, end: 30 }, replacement: "}\n".to_string() }] },
+///     Fix { message: "remove 'x'".to_string(), edits: vec![Edit::DeleteLine { index: 16 }] },
+/// ];
+/// assert_eq!(apply(&sources.files()[0], &fixes), ("fn f() {\n    ret\n}\n".to_string(), 2));
+/// ```
+pub fn apply(file: &SourceFile, fixes: &[Fix]) -> (String, usize) {
+    let source_code = &file.source_code;
+    let mut edits = Vec::new();
+    let mut applied = 0;
+    for fix in fixes {
+        let ranges: Vec<_> = fix.edits.iter().map(|edit| (edit.range(source_code, file.start), edit)).collect();
+        let overlaps = ranges.iter().any(|(range, _)| {
+            edits.iter().any(|(other, _): &(Span, &Edit)| range.start < other.end && other.start < range.end)
+                || (range.start == range.end && edits.iter().any(|(other, _)| other.start == range.start))
+        });
+        let deletes_too_much = ranges.iter().any(|(range, edit)| {
+            matches!(edit, Edit::DeleteLine { .. })
+                && source_code[range.start..range.end].contains(['{', '}'])
+        });
+        if !overlaps && !deletes_too_much {
+            edits.extend(ranges);
+            applied += 1;
+        }
+    }
+
+    edits.sort_by_key(|(range, _)| (range.start, range.end));
+    let mut fixed = String::new();
+    let mut cursor = 0;
+    for (range, edit) in edits {
+        fixed.push_str(&source_code[cursor..range.start]);
+        if let Edit::Replace { replacement, .. } = edit {
+            fixed.push_str(replacement);
+        }
+        cursor = range.end;
+    }
+    fixed.push_str(&source_code[cursor..]);
+    (fixed, applied)
+}
+
+/// Returns a unified diff (like `diff -u`'s) from `old` to `new`, the contents of the file at
+/// `path`, with 3 lines of context around each change; it's empty if they're the same.
+///
+/// ```
+/// let diff = flick::fix::diff("a.fl", "a\nb\nc\n", "a\nB\nc\n");
+/// assert_eq!(diff, "--- a.fl\n+++ a.fl\n@@ -1,3 +1,3 @@\n a\n-b\n+B\n c\n");
+/// ```
+pub fn diff(path: &str, old: &str, new: &str) -> String {
+    const CONTEXT: usize = 3;
+    let (old, new): (Vec<_>, Vec<_>) = (old.lines().collect(), new.lines().collect());
+
+    // lengths[i][j] is the length of the longest common subsequence of old[i..] and new[j..]
+    let mut lengths = vec![vec![0; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i][j] = match old[i] == new[j] {
+                true => lengths[i + 1][j + 1] + 1,
+                false => lengths[i + 1][j].max(lengths[i][j + 1]),
+            };
+        }
+    }
+    // Each line of the diff: its sign, the line, and its index in the old and the new file
+    let mut lines = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            lines.push((' ', old[i], i, j));
+            (i, j) = (i + 1, j + 1);
+        } else if i < old.len() && (j == new.len() || lengths[i + 1][j] >= lengths[i][j + 1]) {
+            lines.push(('-', old[i], i, j));
+            i += 1;
+        } else {
+            lines.push(('+', new[j], i, j));
+            j += 1;
+        }
+    }
+
+    let mut diff = String::new();
+    let changes: Vec<_> = (0..lines.len()).filter(|&k| lines[k].0 != ' ').collect();
+    let mut k = 0;
+    while k < changes.len() {
+        // A hunk takes in every change that's within twice the context of the one before it
+        let start = changes[k].saturating_sub(CONTEXT);
+        while k + 1 < changes.len() && changes[k + 1] - changes[k] <= 2 * CONTEXT {
+            k += 1;
+        }
+        let end = (changes[k] + CONTEXT + 1).min(lines.len());
+        k += 1;
+
+        if diff.is_empty() {
+            writeln!(diff, "--- {}\n+++ {}", path, path).unwrap();
+        }
+        let hunk = &lines[start..end];
+        let old_lines = hunk.iter().filter(|line| line.0 != '+').count();
+        let new_lines = hunk.iter().filter(|line| line.0 != '-').count();
+        let (_, _, old_start, new_start) = hunk[0];
+        let first_line = |start: usize, count: usize| if count == 0 { start } else { start + 1 };
+        writeln!(
+            diff,
+            "@@ -{},{} +{},{} @@",
+            first_line(old_start, old_lines),
+            old_lines,
+            first_line(new_start, new_lines),
+            new_lines
+        )
+        .unwrap();
+        for (sign, line, _, _) in hunk {
+            writeln!(diff, "{}{}", sign, line).unwrap();
+        }
+    }
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Lexer, Parser, SourceMap};
+    use crate::ast::Arena;
+
+    fn insert(index: usize, text: &str) -> Fix {
+        let edit = Edit::Replace { span: Span { start: index, end: index }, replacement: text.to_string() };
+        Fix { message: format!("insert '{}'", text), edits: vec![edit] }
+    }
+
+    /// Returns `source_code` with the fixes for its parsing errors applied.
+    fn fix_parsing_errors(source_code: &str) -> String {
+        let sources = SourceMap::single_file("a.fl", source_code.to_string());
+        let file = &sources.files()[0];
+        let (tokens, spans) = Lexer::lex_with_spans(&file.source_code).unwrap();
+        let errors = Parser::parse_program_with_spans(&Arena::new(), &tokens, &spans).unwrap_err();
+        let fixes: Vec<_> = errors.iter().filter_map(|err| suggest_in_file(err, file, &tokens, &spans)).collect();
+        apply(file, &fixes).0
+    }
+
+    #[test]
+    fn skips_overlapping_fixes() {
+        let sources = SourceMap::single_file("a.fl", "f(a b c)\n".to_string());
+        let (fixed, applied) = apply(&sources.files()[0], &[insert(3, ","), insert(3, ";"), insert(5, ",")]);
+        assert_eq!((fixed.as_str(), applied), ("f(a, b, c)\n", 2));
+
+        let sources = SourceMap::single_file("a.fl", "if c { x = 1\n    y = 2\n".to_string());
+        let delete = |index| Fix { message: "remove".to_string(), edits: vec![Edit::DeleteLine { index }] };
+        let (fixed, applied) = apply(&sources.files()[0], &[delete(7), delete(17)]);
+        assert_eq!((fixed.as_str(), applied), ("if c { x = 1\n", 1));
+    }
+
+    #[test]
+    fn inserts_missing_tokens() {
+        assert_eq!(fix_parsing_errors("fn f(u8 a u8 b) {\n}\n"), "fn f(u8 a, u8 b) {\n}\n");
+        assert_eq!(
+            fix_parsing_errors("fn f() {\n    f(a,b\n    if f(a {\n    }\n}\n"),
+            "fn f() {\n    f(a,b)\n    if f(a) {\n    }\n}\n"
+        );
+        assert_eq!(fix_parsing_errors("fn f() {\n    ret"), "fn f() {\n    ret\n}\n");
+        assert_eq!(
+            fix_parsing_errors("fn f(bool b) {\n    if b = true {\n    }\n}\n"),
+            "fn f(bool b) {\n    if b == true {\n    }\n}\n"
+        );
+    }
+
+    #[test]
+    fn splits_chained_comparisons() {
+        let source_code = "\
+fn f(u8 x) u8 {
+    if 0 < x + 1 < (10) {
+        // In range
+
+        ret 1
+    }
+    ret 0
+}
+";
+        let expected = "\
+fn f(u8 x) u8 {
+    if 0 < x + 1 {
+        if x + 1 < (10) {
+            // In range
+
+            ret 1
+        }
+    }
+    ret 0
+}
+";
+        assert_eq!(fix_parsing_errors(source_code), expected);
+
+        // `b` would be called twice, and where an `else` would go isn't clear
+        for source_code in [
+            "fn f(u8 x) {\n    if 0 < f(x) < 10 {\n    }\n}\n",
+            "fn f(u8 x) {\n    if 0 < x < 10 {\n    } else {\n    }\n}\n",
+        ] {
+            assert_eq!(fix_parsing_errors(source_code), source_code);
+        }
+    }
+
+    #[test]
+    fn diffs() {
+        let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\n12\n";
+        let new = "0\n1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n12\n";
+        let expected = "\
+--- f.fl
++++ f.fl
+@@ -1,3 +1,4 @@
++0
+ 1
+ 2
+ 3
+@@ -8,5 +9,4 @@
+ 8
+ 9
+ 10
+-11
+ 12
+";
+        assert_eq!(diff("f.fl", old, new), expected);
+        assert_eq!(diff("f.fl", old, old), "");
+        assert_eq!(diff("f.fl", "", "a\n"), "--- f.fl\n+++ f.fl\n@@ -0,0 +1,1 @@\n+a\n");
+    }
+}
@@ -0,0 +1,227 @@
+use std::collections::HashMap;
+
+use super::diagnostic::{Diagnostic, Note, Severity};
+
+/// A kind of warning, which can be silenced or turned into an error (see [LintLevels]).
+#[derive(Debug, PartialEq, Eq)]
+pub struct Lint {
+    /// The name that the `-A`, `-W`, and `-D` flags take, like `shadowed-variables`
+    pub name: &'static str,
+    /// The code of the warning, like `W0201`
+    pub code: &'static str,
+    /// The level that the lint has unless it's given another one
+    pub default_level: LintLevel,
+    pub description: &'static str,
+}
+
+/// Every lint, in the order of their codes.
+///
+/// The compiler reports the first two whenever it checks a program; the rest come from the
+/// [Linter](crate::Linter), which only `flick lint` runs.
+pub const LINTS: &[Lint] = &[
+    Lint {
+        name: "shadowed-variables",
+        code: "W0201",
+        default_level: LintLevel::Warn,
+        description: "a variable has the same name as a variable from an outer body",
+    },
+    Lint {
+        name: "unused-functions",
+        code: "W0301",
+        default_level: LintLevel::Warn,
+        description: "a private function is never called",
+    },
+    Lint {
+        name: "unused-variables",
+        code: "W0501",
+        default_level: LintLevel::Warn,
+        description: "a variable or parameter is never read",
+    },
+    Lint {
+        name: "constant-conditions",
+        code: "W0502",
+        default_level: LintLevel::Warn,
+        description: "the condition of an `if` or `while` is always true, or always false",
+    },
+    Lint {
+        name: "endless-loops",
+        code: "W0503",
+        default_level: LintLevel::Warn,
+        description: "a `while true` loop has no `ret` in it, so it never ends",
+    },
+];
+
+impl Lint {
+    /// Returns the lint called `name`, which can also be its code (like `W0201`).
+    pub fn find(name: &str) -> Option<&'static Lint> {
+        LINTS
+            .iter()
+            .find(|lint| lint.name == name || lint.code.eq_ignore_ascii_case(name))
+    }
+}
+
+/// What happens when a [Lint] is triggered.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize), serde(rename_all = "lowercase"))]
+pub enum LintLevel {
+    /// Nothing is reported.
+    Allow,
+    /// A warning is reported (the default for every lint, so far).
+    Warn,
+    /// An error is reported, so the program isn't compiled.
+    Deny,
+}
+
+/// The level of each [Lint], as set on the command line (or in a project's `flick.toml`).
+///
+/// A lint's own level (from `-A`, `-W`, or `-D`) takes priority over `--deny-warnings`, so
+/// `--deny-warnings -W unused-functions` denies every warning except unused functions.
+#[derive(Debug, Default, Clone)]
+pub struct LintLevels {
+    /// The levels that lints were explicitly given, by code, and whether they were given in a
+    /// project's config (rather than on the command line)
+    levels: HashMap<&'static str, (LintLevel, bool)>,
+    /// Whether lints that would only warn are denied instead
+    deny_warnings: bool,
+}
+
+impl LintLevels {
+    /// Sets the level of `lint`, overriding any level it was given before.
+    pub fn set(&mut self, lint: &Lint, level: LintLevel) {
+        self.levels.insert(lint.code, (level, false));
+    }
+
+    /// Like [LintLevels::set], but for a level from a project's `flick.toml` (which a denied
+    /// warning's note points to).
+    pub fn set_from_config(&mut self, lint: &Lint, level: LintLevel) {
+        self.levels.insert(lint.code, (level, true));
+    }
+
+    /// Sets whether every lint without its own level is denied.
+    pub fn set_deny_warnings(&mut self, deny_warnings: bool) {
+        self.deny_warnings = deny_warnings;
+    }
+
+    /// Returns the level of the lint with the warning code `code`: the one it was given, or its
+    /// [default level](Lint::default_level) (which `--deny-warnings` turns from a warning into an
+    /// error).
+    pub fn level(&self, code: &str) -> LintLevel {
+        if let Some((level, _)) = self.levels.get(code) {
+            return *level;
+        }
+        match Lint::find(code).map_or(LintLevel::Warn, |lint| lint.default_level) {
+            LintLevel::Warn if self.deny_warnings => LintLevel::Deny,
+            level => level,
+        }
+    }
+
+    /// Applies these levels to `diagnostic`: returns `None` if it's a warning that's allowed, or
+    /// the diagnostic as an error if it's a warning that's denied (with a note saying why).
+    /// Errors are returned as they are.
+    pub fn apply(&self, mut diagnostic: Diagnostic) -> Option<Diagnostic> {
+        if diagnostic.severity != Severity::Warning {
+            return Some(diagnostic);
+        }
+        match self.level(diagnostic.code) {
+            LintLevel::Allow => None,
+            LintLevel::Warn => Some(diagnostic),
+            LintLevel::Deny => {
+                let reason = match (self.levels.get(diagnostic.code), Lint::find(diagnostic.code)) {
+                    (Some((_, true)), Some(lint)) => format!("'{} = \"deny\"' in 'flick.toml'", lint.name),
+                    (Some(_), Some(lint)) => format!("'-D {}'", lint.name),
+                    (None, Some(lint)) if lint.default_level == LintLevel::Deny => "the lint's default level".to_string(),
+                    _ => "'--deny-warnings'".to_string(),
+                };
+                diagnostic.severity = Severity::Error;
+                diagnostic.notes.push(Note {
+                    message: format!("this warning is an error because of {}", reason),
+                    span: None,
+                });
+                Some(diagnostic)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::registry;
+
+    fn warning(code: &'static str) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Warning,
+            code,
+            message: "oops".to_string(),
+            span: None,
+            notes: Vec::new(),
+            help: Vec::new(),
+            fix: None,
+        }
+    }
+
+    #[test]
+    fn every_lint_is_a_warning_code() {
+        for lint in LINTS {
+            assert!(lint.code.starts_with('W'), "{}", lint.code);
+            assert!(registry::explain(lint.code).is_some(), "{}", lint.code);
+        }
+    }
+
+    #[test]
+    fn lints_are_found_by_name_or_code() {
+        assert_eq!(Lint::find("shadowed-variables").map(|lint| lint.code), Some("W0201"));
+        assert_eq!(Lint::find("w0301").map(|lint| lint.name), Some("unused-functions"));
+        assert_eq!(Lint::find("E0201"), None);
+        assert_eq!(Lint::find("unused"), None);
+    }
+
+    #[test]
+    fn levels() {
+        let shadowed = Lint::find("shadowed-variables").unwrap();
+        let unused = Lint::find("unused-functions").unwrap();
+        let mut levels = LintLevels::default();
+        assert_eq!(levels.level("W0201"), LintLevel::Warn);
+
+        levels.set(shadowed, LintLevel::Allow);
+        assert_eq!(levels.level("W0201"), LintLevel::Allow);
+        assert_eq!(levels.level("W0301"), LintLevel::Warn);
+
+        levels.set_deny_warnings(true);
+        assert_eq!(levels.level("W0201"), LintLevel::Allow);
+        assert_eq!(levels.level("W0301"), LintLevel::Deny);
+
+        levels.set(unused, LintLevel::Warn);
+        assert_eq!(levels.level("W0301"), LintLevel::Warn);
+    }
+
+    #[test]
+    fn applying_levels() {
+        let mut levels = LintLevels::default();
+        assert_eq!(levels.apply(warning("W0201")), Some(warning("W0201")));
+
+        levels.set(Lint::find("W0201").unwrap(), LintLevel::Allow);
+        assert_eq!(levels.apply(warning("W0201")), None);
+
+        levels.set(Lint::find("W0201").unwrap(), LintLevel::Deny);
+        let denied = levels.apply(warning("W0201")).unwrap();
+        assert_eq!(denied.severity, Severity::Error);
+        assert_eq!(denied.notes[0].message, "this warning is an error because of '-D shadowed-variables'");
+
+        levels.set_deny_warnings(true);
+        let denied = levels.apply(warning("W0301")).unwrap();
+        assert_eq!(denied.notes[0].message, "this warning is an error because of '--deny-warnings'");
+
+        levels.set_from_config(Lint::find("W0501").unwrap(), LintLevel::Deny);
+        let denied = levels.apply(warning("W0501")).unwrap();
+        assert_eq!(
+            denied.notes[0].message,
+            "this warning is an error because of 'unused-variables = \"deny\"' in 'flick.toml'"
+        );
+
+        // Errors can't be allowed
+        let error = Diagnostic { severity: Severity::Error, ..warning("W0201") };
+        levels.set(Lint::find("W0201").unwrap(), LintLevel::Allow);
+        assert_eq!(levels.apply(error.clone()), Some(error));
+    }
+}
@@ -0,0 +1,18 @@
+/// Module that defines [Diagnostic](diagnostic::Diagnostic), which every error (and warning) is
+/// turned into to show it to the user.
+pub mod diagnostic;
+/// Module that defines the [Fix](fix::Fix)es that diagnostics can come with, and applies them to
+/// source code (see [fix::apply]).
+pub mod fix;
+/// Module that lets warnings be silenced or turned into errors (see [lints::LintLevels]).
+pub mod lints;
+/// Module that explains what each error (and warning) code means (see [registry::explain]).
+pub mod registry;
+/// Module that defines [DiagnosticSink](sink::DiagnosticSink), which diagnostics are reported to
+/// as they're found.
+pub mod sink;
+/// Module that keeps track of the files being compiled, for diagnostics to point into (see
+/// [source_map::SourceMap]).
+pub mod source_map;
+/// Module that finds likely fixes for typos in names (see [suggestions::closest_name]).
+pub mod suggestions;
@@ -0,0 +1,810 @@
+/// The explanation of every error (and warning) code, in order; see [explain].
+///
+/// Each explanation starts with a one-line summary, followed by an example of code with the
+/// problem and how to fix it. Codes are never reused, so that they stay searchable.
+const EXPLANATIONS: &[(&str, &str)] = &[
+    ("E0001", r#"A character can't start any token.
+
+Erroneous code example:
+
+    u8 x = 5 $ 3
+
+Flick has no `$` operator. Outside of string literals and comments, only letters, digits, `_`,
+whitespace, and Flick's operators and punctuation (like `+`, `<=`, `(`, and `{`) can appear.
+"#),
+    ("E0002", r#"A string literal isn't closed before the end of its line.
+
+Erroneous code example:
+
+    str greeting = "hello
+
+String literals can't span multiple lines. Add the closing `"` (and use `\n` for a newline in the
+string):
+
+    str greeting = "hello\n"
+"#),
+    ("E0003", r#"A string literal contains an unknown escape sequence.
+
+Erroneous code example:
+
+    str path = "C:\Users"
+
+The escape sequences are `\n` (newline), `\t` (tab), `\0` (null), `\\` (backslash), and `\"`
+(quote). To include a backslash itself, escape it:
+
+    str path = "C:\\Users"
+"#),
+    ("E0004", r#"An integer literal is too large for any integer type.
+
+Erroneous code example:
+
+    u128 x = 999999999999999999999999999999999999999
+
+The largest integer literal is 2^128 - 1 (340282366920938463463374607431768211455), the largest
+value of a `u128`.
+"#),
+    ("E0005", r#"An integer type is wider than the widest type that LLVM supports.
+
+Erroneous code example:
+
+    u99999999 x = 1
+
+Integer types can be up to 8388607 bits wide (like `u8388607`), though types wider than `u128`
+are rarely useful.
+"#),
+    ("E0101", r#"The parser found a token where it expected something else.
+
+Erroneous code example:
+
+    fn add(u8 a u8 b) u8 {
+        ret a + b
+    }
+
+The error says what the parser expected (here, a `,` or `)` after the parameter `a`). Usually,
+something is missing just before the token that it points at:
+
+    fn add(u8 a, u8 b) u8 {
+        ret a + b
+    }
+"#),
+    ("E0102", r#"A function is defined inside another function.
+
+Erroneous code example:
+
+    fn outer() {
+        fn inner() {
+        }
+    }
+
+Functions can only be defined at the top level of a file. Move the inner function out:
+
+    fn outer() {
+    }
+
+    fn inner() {
+    }
+
+This error also shows up when a function is missing its closing `}`, since the next function then
+looks like it's inside of it.
+"#),
+    ("E0103", r#"Comparisons are chained.
+
+Erroneous code example:
+
+    if 0 < x < 10 {
+        ret 1
+    }
+
+Unlike in math, `0 < x < 10` doesn't check that `x` is between 0 and 10. Compare one pair at a
+time instead:
+
+    if 0 < x {
+        if x < 10 {
+            ret 1
+        }
+    }
+"#),
+    ("E0104", r#"Expressions or bodies are nested too deeply.
+
+Erroneous code example:
+
+    u8 x = ((((((((((((((((((((((((((((((((1))))))))))))))))))))))))))))))))
+    // ...and so on, more than 128 levels deep
+
+The compiler limits how deeply code can be nested (128 levels, by default), so that it doesn't run
+out of stack space. Code this deeply nested is almost always generated by another program, which
+can raise the limit with `--max-nesting-depth`.
+"#),
+    ("E0105", r#"An assignment is used as the condition of an `if` or a `while`.
+
+Erroneous code example:
+
+    if x = 1 {
+        ret 1
+    }
+
+Assignments are statements in Flick, so they don't have a value, and can't be conditions. To check
+whether two values are equal, use `==`:
+
+    if x == 1 {
+        ret 1
+    }
+"#),
+    ("E0201", r#"A variable is used, but there's no variable with that name in scope.
+
+Erroneous code example:
+
+    pub fn main() u8 {
+        ret count
+    }
+
+Declare the variable before using it, in the same body or in one that contains it:
+
+    pub fn main() u8 {
+        u8 count = 0
+        ret count
+    }
+
+Variables declared in a body (like the body of an `if`) can't be used after it ends.
+"#),
+    ("E0202", r#"A function is called, but there's no function with that name.
+
+Erroneous code example:
+
+    pub fn main() u8 {
+        ret sqaure(3)
+    }
+
+Check the function's name, or define (or, for a C function, declare with `extern`) the function:
+
+    fn square(u8 x) u8 {
+        ret x * x
+    }
+"#),
+    ("E0203", r#"A variable is called as if it were a function.
+
+Erroneous code example:
+
+    fn foo(u8 count) {
+        count()
+    }
+
+Only functions can be called. Rename the variable if it has the same name as the function that you
+meant to call.
+"#),
+    ("E0204", r#"A function is defined (or declared) more than once.
+
+Erroneous code example:
+
+    fn foo() {
+    }
+
+    fn foo() {
+    }
+
+Every function needs a unique name; rename or remove one of them.
+"#),
+    ("E0205", r#"A function has two parameters with the same name.
+
+Erroneous code example:
+
+    fn add(u8 x, u8 x) u8 {
+        ret x + x
+    }
+
+Each parameter needs a unique name:
+
+    fn add(u8 x, u8 y) u8 {
+        ret x + y
+    }
+"#),
+    ("E0206", r#"A variable is declared twice in the same body.
+
+Erroneous code example:
+
+    u8 total = 0
+    u8 total = 1
+
+To change the variable's value, assign to it instead of declaring it again:
+
+    u8 total = 0
+    total = 1
+"#),
+    ("W0201", r#"A variable shadows (has the same name as) a variable from an outer body.
+
+Example:
+
+    u8 x = 1
+    if true {
+        u8 x = 2
+    }
+
+Inside the `if`, `x` refers to the new variable, so the outer `x` can't be used (or changed)
+there. If that's what you meant, rename one of the variables; if you meant to change the outer
+`x`, assign to it instead (`x = 2`).
+
+This warning is the `shadowed-variables` lint: `-A shadowed-variables` silences it, and
+`-D shadowed-variables` makes it an error.
+"#),
+    ("E0301", r#"The program has no `main` function.
+
+Execution starts at `main`, so every program needs one:
+
+    pub fn main() u8 {
+        ret 0
+    }
+"#),
+    ("E0302", r#"`main` is declared as something other than a function.
+
+`main` must be a function, declared as `pub fn main() u8`.
+"#),
+    ("E0303", r#"The `main` function isn't public.
+
+Erroneous code example:
+
+    fn main() u8 {
+        ret 0
+    }
+
+`main` is called from outside the program, so it has to be `pub`:
+
+    pub fn main() u8 {
+        ret 0
+    }
+"#),
+    ("E0304", r#"The `main` function takes parameters.
+
+Erroneous code example:
+
+    pub fn main(u8 argc) u8 {
+        ret 0
+    }
+
+`main` can't take any parameters (command line arguments aren't supported yet).
+"#),
+    ("E0305", r#"The `main` function doesn't return a `u8`.
+
+Erroneous code example:
+
+    pub fn main() {
+    }
+
+`main` returns the program's exit code, which is a `u8` (0 means success):
+
+    pub fn main() u8 {
+        ret 0
+    }
+"#),
+    ("E0306", r#"A function is defined (or declared) more than once.
+
+This is the same problem as E0204, found while type checking.
+"#),
+    ("E0307", r#"A function has the same name as a builtin function.
+
+Erroneous code example:
+
+    fn breakpoint() {
+    }
+
+Builtin functions (like `breakpoint`) are always defined, so pick another name.
+"#),
+    ("E0308", r#"A function has the same name as a variable.
+
+Functions and variables share names, so pick a name that isn't taken.
+"#),
+    ("E0309", r#"A function that returns a value might end without a `ret`.
+
+Erroneous code example:
+
+    fn sign(i64 x) i64 {
+        if x < 0 {
+            ret -1
+        }
+    }
+
+Every path through the function must end with a `ret` with a value:
+
+    fn sign(i64 x) i64 {
+        if x < 0 {
+            ret -1
+        }
+        ret 1
+    }
+"#),
+    ("E0310", r#"The condition of an `if` or `while` isn't a `bool`.
+
+Erroneous code example:
+
+    u8 count = 3
+    while count {
+        count -= 1
+    }
+
+Flick doesn't treat numbers as true or false. Compare instead:
+
+    while count > 0 {
+        count -= 1
+    }
+"#),
+    ("E0311", r#"A variable is used or assigned to before it's declared.
+
+This is the same problem as E0201, found while type checking.
+"#),
+    ("E0312", r#"A variable is used where a value of a different type is expected.
+
+Erroneous code example:
+
+    i64 big = 300
+    u8 small = big
+
+Flick doesn't convert between types implicitly. Declare the variable with the right type, or cast
+it (see E0325 for which casts are allowed):
+
+    i64 small = big
+"#),
+    ("E0313", r#"A function is assigned to, as if it were a variable.
+
+Erroneous code example:
+
+    fn foo() {
+    }
+
+    pub fn main() u8 {
+        foo = 1
+        ret 0
+    }
+
+Only variables can be assigned to.
+"#),
+    ("E0314", r#"A function that doesn't return anything has a `ret` with a value.
+
+Erroneous code example:
+
+    fn log(u8 x) {
+        ret x
+    }
+
+Either remove the value (`ret`), or give the function a return type:
+
+    fn log(u8 x) u8 {
+        ret x
+    }
+"#),
+    ("E0315", r#"A function that returns a value has a `ret` without one.
+
+Erroneous code example:
+
+    fn answer() u8 {
+        ret
+    }
+
+Return a value of the function's return type:
+
+    fn answer() u8 {
+        ret 42
+    }
+"#),
+    ("E0316", r#"A `ret` returns a value of the wrong type.
+
+Erroneous code example:
+
+    fn is_big(u8 x) u8 {
+        ret x > 100
+    }
+
+The value must have the function's return type; here, the function should return a `bool`:
+
+    fn is_big(u8 x) bool {
+        ret x > 100
+    }
+"#),
+    ("E0317", r#"An integer literal is used where a value of a non-integer type is expected.
+
+Erroneous code example:
+
+    bool done = 0
+
+Use a value of the right type (here, `false`).
+"#),
+    ("E0318", r#"The two sides of an operator have different types.
+
+Erroneous code example:
+
+    u8 a = 1
+    u64 b = 2
+    u64 c = a + b
+
+Both sides must have the same type; cast one of them:
+
+    u64 c = (u64) a + b
+"#),
+    ("E0319", r#"A comparison is used where a value of a type other than `bool` is expected.
+
+Erroneous code example:
+
+    u8 bigger = 3 > 2
+
+Comparisons are `bool`s:
+
+    bool bigger = 3 > 2
+"#),
+    ("E0320", r#"A variable is called as if it were a function.
+
+This is the same problem as E0203, found while type checking.
+"#),
+    ("E0321", r#"A function is called, but it isn't defined.
+
+This is the same problem as E0202, found while type checking.
+"#),
+    ("E0322", r#"A function's return value is used where a value of a different type is expected.
+
+Erroneous code example:
+
+    fn five() u64 {
+        ret 5
+    }
+
+    u8 x = five()
+
+Declare the variable with the function's return type, or cast the return value:
+
+    u8 x = (u8) five()
+"#),
+    ("E0323", r#"A function is called with the wrong number of arguments.
+
+Erroneous code example:
+
+    fn add(u8 a, u8 b) u8 {
+        ret a + b
+    }
+
+    u8 sum = add(1)
+
+Pass one argument for each of the function's parameters:
+
+    u8 sum = add(1, 2)
+"#),
+    ("E0324", r#"A cast is used where a value of a different type is expected.
+
+Erroneous code example:
+
+    u8 x = (u64) y
+
+Cast to the type that's expected:
+
+    u8 x = (u8) y
+"#),
+    ("E0325", r#"A value can't be cast to the given type.
+
+Erroneous code example:
+
+    i64 x = -1
+    u64 y = (u64) x
+
+Integers can only be cast between types with the same signedness (like `u8` to `u64`, or `i64` to
+`i8`), since it's unclear what, e.g., casting -1 to an unsigned type should mean.
+"#),
+    ("E0326", r#"A negation is used where a value of an unsigned or non-integer type is expected.
+
+Erroneous code example:
+
+    u8 x = -y
+
+Negations result in signed integers; declare the variable with a signed type:
+
+    i8 x = -y
+"#),
+    ("E0327", r#"A value of an unsigned or non-integer type is negated.
+
+Erroneous code example:
+
+    u8 x = 5
+    i64 y = -x
+
+Only signed integers can be negated. Use a signed type (cast to a wider one, if needed):
+
+    i64 y = -(i64) x
+"#),
+    ("E0328", r#"A constant expression overflows its type.
+
+Erroneous code example:
+
+    u8 x = 255 + 1
+
+The compiler evaluates expressions made of literals, and rejects any whose value doesn't fit in
+its type (like 256, which doesn't fit in a `u8`). Use a wider type:
+
+    u16 x = 255 + 1
+"#),
+    ("W0301", r#"A private function is never used.
+
+Example:
+
+    fn helper() {
+    }
+
+    pub fn main() u8 {
+        ret 0
+    }
+
+Private functions (those without `pub`) that aren't called, directly or indirectly, from a public
+function are left out of the output. Call the function, make it `pub`, or remove it.
+
+This warning is the `unused-functions` lint: `-A unused-functions` silences it, and
+`-D unused-functions` makes it an error.
+"#),
+    ("E0401", r#"The compiler generated invalid LLVM IR.
+
+This is a bug in the compiler; please report it, along with the program that caused it.
+"#),
+    ("E0402", r#"LLVM's support for the target couldn't be initialized.
+
+This usually means that the compiler was built against an LLVM that doesn't support the target.
+"#),
+    ("E0403", r#"The target (from `--target`) isn't supported.
+
+Erroneous command example:
+
+    flick main.fl --target x86_64-unknown-linux-gnuu
+
+Check the target triple for typos; `llc --version` lists the architectures that your LLVM
+supports.
+"#),
+    ("E0404", r#"LLVM couldn't create a target machine for the target, CPU, and features.
+
+Check the values given to `--target`, `--cpu`, and `--features`.
+"#),
+    ("E0405", r#"The pass pipeline (from `--passes`) is invalid.
+
+Erroneous command example:
+
+    flick main.fl --passes "sroa,instcombne"
+
+The pipeline uses LLVM's syntax, as in `opt -passes=...`; check the pass names for typos.
+"#),
+    ("E0406", r#"The program uses something that the chosen backend can't generate code for.
+
+Erroneous code example (with `--backend c`):
+
+    extern fn int()
+
+C reserves some names (like its keywords), so functions with those names can't be compiled to C.
+Rename the function, or use the LLVM backend.
+"#),
+    ("E0407", r#"A source file couldn't be read.
+
+This is reported by `flick::compile_file` (the `flick` command reports it without a code) when the
+file doesn't exist, can't be opened, or isn't valid UTF-8. Check the path and the file's
+permissions.
+"#),
+    ("E0408", r#"A function that `--instrument-functions` calls has the wrong parameters or return type.
+
+Erroneous code example (built with `--instrument-functions`):
+
+    extern fn __flick_enter(u64 depth)
+
+Every instrumented function calls `__flick_enter` with its name when it starts, and `__flick_exit`
+just before it returns, so they must be declared (or defined) like this:
+
+    extern fn __flick_enter(str name)
+    extern fn __flick_exit()
+
+Fix the parameters and return type, or rename your function if it isn't meant to be a hook.
+"#),
+    ("E0409", r#"LLVM couldn't create the JIT compiler that runs an embedded program.
+
+This is reported by `flick::Engine` (and `flick::eval`), which compile programs for the machine that
+they run on. Check that the options don't give another `target_triple`, and that your LLVM supports
+this machine.
+"#),
+    ("E0410", r#"An embedded program was asked to call a function that it doesn't define.
+
+Erroneous Rust code example:
+
+    let engine = flick::Engine::new("fn answer() i64 {\n    ret 42\n}\n", &Default::default())?;
+    let answer: i64 = engine.call("anwser")?;
+
+Check the name for typos. `flick::eval` calls `main`, so the program has to define it.
+"#),
+    ("E0411", r#"An embedded program's function can't be called with the Rust types it was called with.
+
+Erroneous Rust code example:
+
+    let engine = flick::Engine::new("fn answer() i64 {\n    ret 42\n}\n", &Default::default())?;
+    let answer: bool = engine.call("answer")?;
+
+Functions are called from Rust without arguments, so they can't have parameters, and the Rust type
+that they return must match their return type: `i64` for `i64` (and likewise for the other int
+types), `bool` for `bool`, `String` for `str`, and `()` for `void`.
+"#),
+    ("E0412", r#"An output file (like the object file) couldn't be written.
+
+This is reported by `flick::Compiler::to_file` and the other backends' methods that write files (the
+`flick` command reports it without a code) when the output's directory doesn't exist, or the file
+can't be created or written. Check the output path (`-o`) and its directory's permissions.
+"#),
+    ("W0501", r#"A variable (or a parameter) is never read.
+
+Example:
+
+    fn area(u64 width, u64 height) u64 {
+        u64 perimeter = 2 * (width + height)
+        ret width * width
+    }
+
+`height` and `perimeter` are never read (assigning to a variable doesn't count), which often means
+that the code uses the wrong variable, like `width * width` here. If a variable is unused on
+purpose, start its name with `_` (like `_height`).
+
+This warning is the `unused-variables` lint, which `flick lint` checks for: `-A unused-variables`
+silences it, and `-D unused-variables` makes it an error.
+"#),
+    ("W0502", r#"The condition of an `if` or `while` is always true, or always false.
+
+Example:
+
+    if 1 > 2 {
+        ret 1
+    }
+
+A condition that's made up of nothing but literals (like `false`, or `1 > 2`) always has the same
+value, so one of the branches never runs. It's usually left over from debugging; remove the `if`
+(or the `while`), or use a variable in the condition.
+
+`while true` is the way to write a loop that only ends with a `ret`, so it isn't reported (see
+W0503).
+
+This warning is the `constant-conditions` lint, which `flick lint` checks for:
+`-A constant-conditions` silences it, and `-D constant-conditions` makes it an error.
+"#),
+    ("W0503", r#"A loop's condition is always true, and there's no `ret` in it.
+
+Example:
+
+    while true {
+        count = count + 1
+    }
+
+Flick has no `break`, so the only way out of a `while true` loop is a `ret`. Without one, the loop
+(and the function it's in) never ends. Add a `ret` for when the loop should stop, or use a
+condition that can become false:
+
+    while count < 10 {
+        count = count + 1
+    }
+
+This warning is the `endless-loops` lint, which `flick lint` checks for: `-A endless-loops`
+silences it (e.g. for a program that should run until it's killed), and `-D endless-loops` makes
+it an error.
+"#),
+];
+
+/// Returns the explanation of an error (or warning) code like `E0201` (or `e0201`), which
+/// `flick explain` prints, or `None` if there's no such code.
+///
+/// ```
+/// use flick::registry;
+/// assert!(registry::explain("E0201").unwrap().starts_with("A variable is used"));
+/// assert_eq!(registry::explain("E9999"), None);
+/// ```
+pub fn explain(code: &str) -> Option<&'static str> {
+    EXPLANATIONS
+        .iter()
+        .find(|(known_code, _)| known_code.eq_ignore_ascii_case(code))
+        .map(|(_, explanation)| *explanation)
+}
+
+/// Returns every error (and warning) code, in order.
+pub fn codes() -> impl Iterator<Item = &'static str> {
+    EXPLANATIONS.iter().map(|(code, _)| *code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compilation::error::CompilationError;
+    use crate::error::ErrorKind;
+    use crate::lexing::error::LexingError;
+    use crate::lexing::token::{Span, Token};
+    use crate::linting::error::LintingError;
+    use crate::parsing::error::ParsingError;
+    use crate::resolving::error::ResolvingError;
+    use crate::typing::error::TypingError;
+    use crate::types::{IntType, Type};
+
+    #[test]
+    fn codes_are_unique() {
+        let mut codes: Vec<_> = codes().collect();
+        codes.sort();
+        codes.dedup();
+        assert_eq!(codes.len(), EXPLANATIONS.len());
+    }
+
+    #[test]
+    fn every_error_is_explained() {
+        let name = || "x".to_string();
+        let t = || Box::new(Type::Bool);
+        let errors = [
+            ErrorKind::LexingError(LexingError::UnexpectedCharacter('$')),
+            ErrorKind::LexingError(LexingError::UnterminatedStrLiteral { literal: name(), literal_start: 0 }),
+            ErrorKind::LexingError(LexingError::InvalidEscapeSequence { sequence: 'q', literal_start: 0 }),
+            ErrorKind::LexingError(LexingError::IntLiteralTooLarge(name())),
+            ErrorKind::LexingError(LexingError::IntTypeTooWide(name())),
+            ErrorKind::ParsingError(ParsingError::UnexpectedToken {
+                expected: vec![],
+                found: Some(Box::new(Token::Comma)),
+                span: Span::default(),
+            }),
+            ErrorKind::ParsingError(ParsingError::NestedFunction),
+            ErrorKind::ParsingError(ParsingError::ChainedComparison),
+            ErrorKind::ParsingError(ParsingError::TooDeeplyNested { what: "body", max_depth: 1 }),
+            ErrorKind::ParsingError(ParsingError::AssignmentInCondition),
+            ErrorKind::ResolvingError(ResolvingError::UndefinedVariable { name: name(), suggestion: None }),
+            ErrorKind::ResolvingError(ResolvingError::UndefinedFunction { name: name(), suggestion: None }),
+            ErrorKind::ResolvingError(ResolvingError::NotAFunction(name())),
+            ErrorKind::ResolvingError(ResolvingError::FunctionRedefinition { name: name(), first_definition: Span::default() }),
+            ErrorKind::ResolvingError(ResolvingError::ParamRedefinition { name: name(), first_definition: Span::default() }),
+            ErrorKind::ResolvingError(ResolvingError::VariableRedeclaration { name: name(), first_definition: Span::default() }),
+            ErrorKind::ResolvingError(ResolvingError::ShadowedVariable { name: name(), shadowed: Span::default() }),
+            ErrorKind::TypingError(TypingError::MissingMain),
+            ErrorKind::TypingError(TypingError::MainNotAFunction { found: t(), span: Span::default() }),
+            ErrorKind::TypingError(TypingError::PrivateMain { span: Span::default() }),
+            ErrorKind::TypingError(TypingError::MainWithParams { span: Span::default() }),
+            ErrorKind::TypingError(TypingError::InvalidMainReturnType { found: t(), span: Span::default() }),
+            ErrorKind::TypingError(TypingError::FunctionRedefinition { name: name(), span: Span::default() }),
+            ErrorKind::TypingError(TypingError::BuiltinRedefinition { name: name(), span: Span::default() }),
+            ErrorKind::TypingError(TypingError::FunctionNameTaken { name: name(), span: Span::default() }),
+            ErrorKind::TypingError(TypingError::MissingReturn { func_name: name(), span: Span::default() }),
+            ErrorKind::TypingError(TypingError::NonBoolCondition { statement_kind: "if", found: t(), span: Span::default() }),
+            ErrorKind::TypingError(TypingError::UndeclaredIdentifier { name: name(), span: Span::default() }),
+            ErrorKind::TypingError(TypingError::IdentifierTypeMismatch { name: name(), found: t(), expected: t(), span: Span::default() }),
+            ErrorKind::TypingError(TypingError::AssignmentToFunction { name: name(), span: Span::default() }),
+            ErrorKind::TypingError(TypingError::UnexpectedReturnValue { span: Span::default() }),
+            ErrorKind::TypingError(TypingError::MissingReturnValue { expected: t(), span: Span::default() }),
+            ErrorKind::TypingError(TypingError::ReturnValueTypeMismatch { expected: t(), found: t(), span: Span::default() }),
+            ErrorKind::TypingError(TypingError::IntLiteralTypeMismatch { literal: name(), expected: t(), span: Span::default() }),
+            ErrorKind::TypingError(TypingError::OperandTypeMismatch { operator: name(), left: t(), right: t(), span: Span::default() }),
+            ErrorKind::TypingError(TypingError::ComparisonTypeMismatch { expected: t(), span: Span::default() }),
+            ErrorKind::TypingError(TypingError::NotAFunction { name: name(), span: Span::default() }),
+            ErrorKind::TypingError(TypingError::UndefinedFunction { name: name(), span: Span::default() }),
+            ErrorKind::TypingError(TypingError::ReturnTypeMismatch { func_name: name(), expected: t(), found: t(), span: Span::default() }),
+            ErrorKind::TypingError(TypingError::ArgumentCountMismatch { func_name: name(), expected: 1, found: 2, span: Span::default() }),
+            ErrorKind::TypingError(TypingError::CastTypeMismatch { cast_type: t(), expected: t(), span: Span::default() }),
+            ErrorKind::TypingError(TypingError::InvalidCast { from: t(), to: t(), span: Span::default() }),
+            ErrorKind::TypingError(TypingError::NegationTypeMismatch { expected: t(), span: Span::default() }),
+            ErrorKind::TypingError(TypingError::InvalidNegation { found: t(), span: Span::default() }),
+            ErrorKind::TypingError(TypingError::ConstantOverflow {
+                value: "256".to_string(),
+                int_type: IntType { width: 8, signed: false },
+                span: Span::default(),
+            }),
+            ErrorKind::TypingError(TypingError::UnusedFunction { name: name(), span: Span::default() }),
+        ];
+        let compilation_errors = [
+            CompilationError::InvalidModule(name()),
+            CompilationError::TargetInitialization("x"),
+            CompilationError::UnknownTarget { triple: name(), message: name() },
+            CompilationError::TargetMachineCreation { triple: name() },
+            CompilationError::InvalidPassPipeline { passes: name(), message: name() },
+            CompilationError::UnsupportedByBackend { backend: "C", message: name() },
+            CompilationError::UnreadableSource { path: name().into(), message: name() },
+            CompilationError::InvalidProfilingHook { name: "x", expected: "fn x()" },
+            CompilationError::ExecutionEngineCreation(name()),
+            CompilationError::UnknownFunction(name()),
+            CompilationError::UncallableFunction { proto: name(), return_type: Box::new(Type::Void) },
+            CompilationError::UnwritableOutput { path: None, message: name() },
+        ];
+
+        let lint_warnings = [
+            ErrorKind::LintingError(LintingError::UnusedVariable { name: name(), kind: "variable", removable: Vec::new() }),
+            ErrorKind::LintingError(LintingError::ConstantCondition { statement_kind: "if", value: true }),
+            ErrorKind::LintingError(LintingError::EndlessLoop),
+        ];
+
+        let error_codes = errors.iter().map(ErrorKind::code);
+        let compilation_codes = compilation_errors.iter().map(CompilationError::code);
+        let lint_codes = lint_warnings.iter().map(ErrorKind::code);
+        let used_codes: Vec<_> = error_codes.chain(compilation_codes).chain(lint_codes).collect();
+        assert_eq!(used_codes, codes().collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,34 @@
+use super::diagnostic::Diagnostic;
+
+/// Something that's given each [Diagnostic] as soon as it's found, e.g. to print it (like the
+/// `flick` binary does) or to collect it.
+///
+/// Diagnostics can be collected into a `Vec<Diagnostic>`, or handled by a closure:
+///
+/// ```
+/// use flick::{check, Diagnostic, SourceMap};
+///
+/// let sources = SourceMap::single_file("main.fl", "pub fn main() u8 {\n    ret x\n}\n".to_string());
+/// let mut diagnostics: Vec<Diagnostic> = Vec::new();
+/// assert!(check(&sources, &mut diagnostics).is_none());
+/// assert_eq!(diagnostics[0].code, "E0201");
+///
+/// let mut errors = 0;
+/// check(&sources, &mut |_| errors += 1);
+/// assert_eq!(errors, 1);
+/// ```
+pub trait DiagnosticSink {
+    fn report(&mut self, diagnostic: Diagnostic);
+}
+
+impl DiagnosticSink for Vec<Diagnostic> {
+    fn report(&mut self, diagnostic: Diagnostic) {
+        self.push(diagnostic);
+    }
+}
+
+impl<F: FnMut(Diagnostic)> DiagnosticSink for F {
+    fn report(&mut self, diagnostic: Diagnostic) {
+        self(diagnostic)
+    }
+}
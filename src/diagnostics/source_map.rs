@@ -0,0 +1,237 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use memmap2::Mmap;
+
+use crate::lexing::token::Span;
+
+/// The source code of every file being compiled together, laid end to end, so that one [Span]
+/// (or one error index) can point into any of them.
+///
+/// Spans are byte offsets. Each file is lexed on its own, so its spans start from 0; [SourceFile::start] has to be added
+/// to them before the files' programs are combined.
+///
+/// ```
+/// use flick::{token::Span, SourceMap};
+/// let mut sources = SourceMap::default();
+/// sources.add_file("a.fl", "fn a() {\n}\n".to_string());
+/// let b_start = sources.add_file("b.fl", "fn b() {\n}\n".to_string()).start;
+///
+/// let (file, span) = sources.locate(Span { start: b_start + 3, end: b_start + 4 });
+/// assert_eq!(file.path.to_str(), Some("b.fl"));
+/// assert_eq!(span, Span { start: 3, end: 4 });
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+/// A file in a [SourceMap].
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+    pub path: PathBuf,
+    pub source_code: SourceCode,
+    /// Where the file starts in the [SourceMap]
+    pub start: usize,
+}
+
+/// The source code of a [SourceFile], which derefs to a `str`: either a `String`, or (for a big
+/// file, see [SourceCode::map]) the file itself, mapped into memory.
+#[derive(Clone)]
+pub enum SourceCode {
+    Owned(String),
+    Mapped(MappedFile),
+}
+
+/// A file that's mapped into memory, whose contents were checked to be UTF-8 (only
+/// [SourceCode::map] can make one).
+#[derive(Clone)]
+pub struct MappedFile(Arc<Mmap>);
+
+impl SourceCode {
+    /// How big a file has to be (in bytes) for [SourceCode::map] to map it into memory.
+    pub const MAP_THRESHOLD: u64 = 1 << 20;
+
+    /// Reads the file at `path`, which has to be UTF-8, into a `String`.
+    pub fn read(path: &Path) -> io::Result<Self> {
+        let mut source_code = String::new();
+        File::open(path)?.read_to_string(&mut source_code)?;
+        Ok(Self::Owned(source_code))
+    }
+
+    /// Like [read](SourceCode::read), but a file of at least [SourceCode::MAP_THRESHOLD] bytes is
+    /// mapped into memory, rather than copied into a `String`, so that it's lexed right where the
+    /// OS keeps it. Anything that can't be mapped (like a pipe, e.g. `/dev/stdin`) is read instead.
+    ///
+    /// # Safety
+    ///
+    /// The file mustn't be changed (e.g. truncated, or rewritten by another process) while the
+    /// source code, or any clone of it, is alive. Its contents are only checked to be UTF-8 here,
+    /// and the `str` that the source code derefs to is the file itself.
+    pub unsafe fn map(path: &Path) -> io::Result<Self> {
+        let mut file = File::open(path)?;
+        let is_big = file.metadata().is_ok_and(|metadata| metadata.is_file() && metadata.len() >= Self::MAP_THRESHOLD);
+        if is_big {
+            // SAFETY: the caller makes sure that the file isn't changed while it's mapped
+            if let Ok(map) = unsafe { Mmap::map(&file) } {
+                std::str::from_utf8(&map).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+                return Ok(Self::Mapped(MappedFile(Arc::new(map))));
+            }
+        }
+        let mut source_code = String::new();
+        file.read_to_string(&mut source_code)?;
+        Ok(Self::Owned(source_code))
+    }
+}
+
+impl Deref for SourceCode {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        match self {
+            Self::Owned(source_code) => source_code,
+            // SAFETY: the map was checked to be UTF-8 when the file was mapped, and the caller of
+            // `SourceCode::map` made sure that the file doesn't change
+            Self::Mapped(MappedFile(map)) => unsafe { std::str::from_utf8_unchecked(map) },
+        }
+    }
+}
+
+impl From<String> for SourceCode {
+    fn from(source_code: String) -> Self {
+        Self::Owned(source_code)
+    }
+}
+
+impl fmt::Debug for SourceCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl SourceMap {
+    /// Returns a map with just one file in it, which starts at 0 (so its spans don't need to be
+    /// moved).
+    pub fn single_file(path: impl Into<PathBuf>, source_code: String) -> Self {
+        let mut sources = Self::default();
+        sources.add_file(path, source_code);
+        sources
+    }
+
+    /// Adds a file after the ones already in the map, and returns it.
+    pub fn add_file(&mut self, path: impl Into<PathBuf>, source_code: impl Into<SourceCode>) -> &SourceFile {
+        // Leave a gap after each file, so that an index just past its end (e.g. where an error
+        // says that the file ended) is still in it
+        let start = self.files.last().map_or(0, |last| last.start + last.source_code.len() + 1);
+        self.files.push(SourceFile { path: path.into(), source_code: source_code.into(), start });
+        self.files.last().unwrap()
+    }
+
+    /// Reads the file at `path` (see [SourceCode::read]) and adds it after the ones already in
+    /// the map.
+    pub fn read_file(&mut self, path: impl Into<PathBuf>) -> io::Result<&SourceFile> {
+        let path = path.into();
+        let source_code = SourceCode::read(&path)?;
+        Ok(self.add_file(path, source_code))
+    }
+
+    /// Like [read_file](SourceMap::read_file), but maps a big file into memory (see
+    /// [SourceCode::map]).
+    ///
+    /// # Safety
+    ///
+    /// The file mustn't be changed while the map (or any clone of its files) is alive (see
+    /// [SourceCode::map]).
+    pub unsafe fn map_file(&mut self, path: impl Into<PathBuf>) -> io::Result<&SourceFile> {
+        let path = path.into();
+        // SAFETY: the caller makes sure that the file isn't changed
+        let source_code = unsafe { SourceCode::map(&path)? };
+        Ok(self.add_file(path, source_code))
+    }
+
+    pub fn files(&self) -> &[SourceFile] {
+        &self.files
+    }
+
+    /// Returns the file that `span` is in, and where it is within that file.
+    ///
+    /// Panics if the map is empty.
+    pub fn locate(&self, span: Span) -> (&SourceFile, Span) {
+        let file = self
+            .files
+            .iter()
+            .rev()
+            .find(|file| file.start <= span.start)
+            .unwrap_or(&self.files[0]);
+        let start = span.start - file.start.min(span.start);
+        let end = span.end.saturating_sub(file.start).max(start);
+        (file, Span { start, end })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn files_are_laid_end_to_end() {
+        let mut sources = SourceMap::default();
+        assert_eq!(sources.add_file("a.fl", "abc".to_string()).start, 0);
+        assert_eq!(sources.add_file("b.fl", "".to_string()).start, 4);
+        assert_eq!(sources.add_file("c.fl", "de".to_string()).start, 5);
+
+        let locate = |start, end| {
+            let (file, span) = sources.locate(Span { start, end });
+            (file.path.to_str().unwrap(), span.start, span.end)
+        };
+        assert_eq!(locate(1, 2), ("a.fl", 1, 2));
+        // Just past the end of a file is still in it
+        assert_eq!(locate(3, 3), ("a.fl", 3, 3));
+        assert_eq!(locate(4, 4), ("b.fl", 0, 0));
+        assert_eq!(locate(6, 7), ("c.fl", 1, 2));
+    }
+
+    #[test]
+    fn big_files_are_mapped() {
+        let dir = std::env::temp_dir().join(format!("flick-source-map-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let line = "// comment\n";
+        let big = line.repeat(SourceCode::MAP_THRESHOLD as usize / line.len() + 1);
+        assert!(big.len() as u64 >= SourceCode::MAP_THRESHOLD);
+        std::fs::write(dir.join("big.fl"), &big).unwrap();
+        std::fs::write(dir.join("small.fl"), line).unwrap();
+        std::fs::write(dir.join("invalid.fl"), [b'a'; SourceCode::MAP_THRESHOLD as usize].iter().chain(&[0xff]).copied().collect::<Vec<_>>()).unwrap();
+
+        let mut sources = SourceMap::default();
+        // SAFETY: nothing changes the files until the map is dropped
+        unsafe {
+            let big_file = sources.map_file(dir.join("big.fl")).unwrap();
+            assert!(matches!(big_file.source_code, SourceCode::Mapped(_)));
+            assert_eq!(&*big_file.source_code, big);
+            let small_file = sources.map_file(dir.join("small.fl")).unwrap();
+            assert!(matches!(small_file.source_code, SourceCode::Owned(_)));
+            assert_eq!(&*small_file.source_code, line);
+            let err = sources.map_file(dir.join("invalid.fl")).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+        // The mapped file is lexed where it is
+        let tokens = crate::Lexer::lex(&sources.files()[0].source_code).unwrap();
+        assert_eq!(tokens.len(), big.len() / line.len() * 2);
+        drop(sources);
+
+        // Reading never maps, so the file can be changed afterwards
+        let mut sources = SourceMap::default();
+        let big_file = sources.read_file(dir.join("big.fl")).unwrap();
+        assert!(matches!(big_file.source_code, SourceCode::Owned(_)));
+        std::fs::write(dir.join("big.fl"), line).unwrap();
+        assert_eq!(&*sources.files()[0].source_code, big);
+        let err = sources.read_file(dir.join("invalid.fl")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,70 @@
+/// Returns the candidate that's spelled the most like `name`, if any is close enough that `name`
+/// is likely a typo of it (so that a diagnostic can ask "did you mean ...?").
+///
+/// Ties go to the earliest candidate, so candidates should be ordered from most to least likely
+/// (e.g. innermost scope first).
+pub fn closest_name<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    // Like rustc, allow about one typo per three characters
+    let max_distance = name.chars().count().max(3) / 3;
+    candidates
+        .into_iter()
+        .filter(|&candidate| candidate != name)
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|&(distance, _)| distance <= max_distance)
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Returns the edit distance between `a` and `b`: the fewest characters that have to be inserted,
+/// deleted, or replaced, or pairs of adjacent characters that have to be swapped, to turn one into
+/// the other (so that `cuont` is as close to `count` as `cont` is).
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    // `distances[i][j]` is the distance between `a[..i]` and `b[..j]`
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, distance) in distances[0].iter_mut().enumerate() {
+        *distance = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let replaced = distances[i - 1][j - 1] + usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = replaced.min(distances[i - 1][j] + 1).min(distances[i][j - 1] + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
+        }
+    }
+    distances[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edit_distances() {
+        assert_eq!(edit_distance("count", "count"), 0);
+        assert_eq!(edit_distance("cuont", "count"), 1);
+        assert_eq!(edit_distance("coutn", "count"), 1);
+        assert_eq!(edit_distance("cotun", "count"), 2);
+        assert_eq!(edit_distance("cont", "count"), 1);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn closest_names() {
+        let names = ["total", "count", "counter", "i"];
+        assert_eq!(closest_name("cont", names), Some("count"));
+        assert_eq!(closest_name("cuont", names), Some("count"));
+        assert_eq!(closest_name("countr", names), Some("count")); // ties go to the first
+        assert_eq!(closest_name("j", names), Some("i"));
+        assert_eq!(closest_name("xyz", names), None);
+        assert_eq!(closest_name("sum", names), None);
+    }
+}
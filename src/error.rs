@@ -2,6 +2,11 @@ use std::fmt;
 use std::error::Error;
 
 use crate::lexing::error::LexingError;
+use crate::lexing::token::Position;
+use crate::linting::error::LintingError;
+use crate::parsing::error::ParsingError;
+use crate::resolving::error::ResolvingError;
+use crate::typing::error::TypingError;
 
 pub type Result<T> = std::result::Result<T, FlickError>;
 
@@ -11,6 +16,14 @@ pub struct FlickError {
     pub kind: ErrorKind,
 }
 
+impl FlickError {
+    /// Returns the line and column that the error points at in `source_code` (the code that was
+    /// compiled when the error happened).
+    pub fn position(&self, source_code: &str) -> Position {
+        Position::of(self.index, source_code)
+    }
+}
+
 impl fmt::Display for FlickError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         self.kind.fmt(f)
@@ -22,15 +35,36 @@ impl Error for FlickError {}
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum ErrorKind {
     LexingError(LexingError),
-    // ParsingError(ParsingError),
-    // TypingError(TypingError),
+    ParsingError(ParsingError),
+    ResolvingError(ResolvingError),
+    TypingError(TypingError),
+    /// A warning from the [Linter](crate::Linter)
+    LintingError(LintingError),
     // CompilationError(CompilationError),
 }
 
+impl ErrorKind {
+    /// Returns the code that identifies this kind of error, like `E0101` (see
+    /// [Diagnostic::code](crate::Diagnostic::code)).
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::LexingError(err) => err.code(),
+            Self::ParsingError(err) => err.code(),
+            Self::ResolvingError(err) => err.code(),
+            Self::TypingError(err) => err.code(),
+            Self::LintingError(err) => err.code(),
+        }
+    }
+}
+
 impl fmt::Display for ErrorKind {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::LexingError(err) => err.fmt(f),
+            Self::ParsingError(err) => err.fmt(f),
+            Self::ResolvingError(err) => err.fmt(f),
+            Self::TypingError(err) => err.fmt(f),
+            Self::LintingError(err) => err.fmt(f),
         }
     }
 }
@@ -0,0 +1,209 @@
+use std::fmt;
+
+use crate::lexing::lexer::{IdentifierPolicy, Lexer};
+use crate::lexing::token::{Span, Token};
+
+/// The concrete syntax of a source file: every token, spelled exactly as it was written, along
+/// with the whitespace and comments around it (its [trivia](Trivia)), so that the file can be
+/// reproduced byte for byte (see [Cst::to_source]).
+///
+/// The [AST](crate::ast) only keeps what a program means; tools that rewrite source code (like a
+/// formatter) use the CST to keep everything else. The tokens' spans are the same ones that the
+/// AST's nodes point into, so the two can be matched up.
+///
+/// ```
+/// use flick::cst::Cst;
+/// let source_code = "fn foo() {  // does nothing\r\n}\n";
+/// let cst = Cst::new(source_code).unwrap();
+/// assert_eq!(cst.to_source(), "fn foo() {  // does nothing\r\n}\n");
+/// assert_eq!(cst.tokens.len(), 8);
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Cst {
+    pub tokens: Vec<SyntaxToken>,
+    /// The trivia after the last token (e.g. a comment on the file's last line)
+    pub trailing_trivia: Vec<Trivia>,
+}
+
+/// A token in a [Cst].
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SyntaxToken {
+    pub token: Token<'static>,
+    /// The token's exact source code (which can differ from `token`, e.g. for a raw or
+    /// non-normalized identifier, or a string literal with escape sequences)
+    pub text: String,
+    pub span: Span,
+    /// The whitespace and comments between the previous token (or the start of the file) and
+    /// this one
+    pub leading_trivia: Vec<Trivia>,
+}
+
+/// Source code that doesn't affect a program's meaning. Newlines aren't trivia, since they end
+/// statements (they're [Token::Newline]s).
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Trivia {
+    /// A run of whitespace other than `\n` (which includes the `\r` of a `\r\n`)
+    Whitespace(String),
+    /// A `//` comment, up to (but not including) the end of its line
+    Comment(String),
+    /// A `///` docstring, up to (but not including) the end of its line
+    Docstring(String),
+}
+
+impl Trivia {
+    /// Returns the trivia's exact source code.
+    pub fn text(&self) -> &str {
+        match self {
+            Self::Whitespace(text) | Self::Comment(text) | Self::Docstring(text) => text,
+        }
+    }
+}
+
+impl Cst {
+    /// Lexes `source_code` into its concrete syntax.
+    ///
+    /// Returns an `Err()` if lexing fails (see [Lexer::lex]).
+    pub fn new(source_code: &str) -> crate::Result<Self> {
+        Self::with_policy(source_code, IdentifierPolicy::default())
+    }
+
+    /// Like [Cst::new], but identifiers can only contain the characters that
+    /// `identifier_policy` allows (see [Lexer::lex_with_policy]).
+    pub fn with_policy(source_code: &str, identifier_policy: IdentifierPolicy) -> crate::Result<Self> {
+        let (tokens, spans) = Lexer::lex_with_policy(source_code, identifier_policy)?;
+        let text = |start: usize, end: usize| source_code[start..end].to_string();
+
+        let mut syntax_tokens = Vec::new();
+        let mut trivia = Vec::new();
+        let mut cursor = 0;
+        for (token, span) in tokens.into_iter().zip(spans) {
+            // the lexer only skips whitespace between tokens
+            if cursor < span.start {
+                trivia.push(Trivia::Whitespace(text(cursor, span.start)));
+            }
+            cursor = span.end;
+
+            match token {
+                Token::Comment(_) => trivia.push(Trivia::Comment(text(span.start, span.end))),
+                Token::Docstring(_) => trivia.push(Trivia::Docstring(text(span.start, span.end))),
+                token => syntax_tokens.push(SyntaxToken {
+                    token: token.into_owned(),
+                    text: text(span.start, span.end),
+                    span,
+                    leading_trivia: std::mem::take(&mut trivia),
+                }),
+            }
+        }
+        if cursor < source_code.len() {
+            trivia.push(Trivia::Whitespace(text(cursor, source_code.len())));
+        }
+
+        Ok(Self { tokens: syntax_tokens, trailing_trivia: trivia })
+    }
+
+    /// Returns the tokens that the parser needs, and their spans (like [Lexer::lex_with_spans],
+    /// but without comments).
+    pub fn tokens_with_spans(&self) -> (Vec<Token<'static>>, Vec<Span>) {
+        self.tokens
+            .iter()
+            .map(|syntax_token| (syntax_token.token.clone(), syntax_token.span))
+            .unzip()
+    }
+
+    /// Returns the source code that the CST was made from, exactly.
+    pub fn to_source(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl fmt::Display for SyntaxToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for trivia in &self.leading_trivia {
+            write!(f, "{}", trivia.text())?;
+        }
+        write!(f, "{}", self.text)
+    }
+}
+
+impl fmt::Display for Cst {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for token in &self.tokens {
+            write!(f, "{}", token)?;
+        }
+        for trivia in &self.trailing_trivia {
+            write!(f, "{}", trivia.text())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::Arena;
+    use crate::parsing::parser::Parser;
+    use std::fs;
+    use std::path::Path;
+
+    fn round_trip(source_code: &str) -> Cst {
+        let cst = Cst::new(source_code).unwrap();
+        assert_eq!(cst.to_source(), source_code);
+        cst
+    }
+
+    #[test]
+    fn reproduces_examples() {
+        let examples = Path::new(env!("CARGO_MANIFEST_DIR")).join("examples");
+        for entry in fs::read_dir(examples).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_none_or(|extension| extension != "fl") {
+                continue;
+            }
+            let source_code = fs::read_to_string(&path).unwrap();
+            // some examples (like `test.fl`) aren't valid Flick
+            if let Ok(cst) = Cst::new(&source_code) {
+                assert_eq!(cst.to_source(), source_code, "{}", path.display());
+            }
+        }
+    }
+
+    #[test]
+    fn reproduces_whitespace_and_comments() {
+        round_trip("");
+        round_trip("  \t\n\n");
+        round_trip("// only a comment");
+        round_trip("/// docs\r\npub fn main() u8 {\r\n\tret  0 // done\r\n}   \n\n// the end  ");
+
+        let cst = round_trip("fn foo() {\n    u8 x = 1  // one\n}");
+        let one = &cst.tokens[9];
+        assert_eq!(one.text, "1");
+        let newline = &cst.tokens[10];
+        assert_eq!(newline.token, Token::Newline);
+        assert_eq!(
+            newline.leading_trivia,
+            [Trivia::Whitespace("  ".to_string()), Trivia::Comment("// one".to_string())]
+        );
+        assert!(cst.trailing_trivia.is_empty());
+    }
+
+    #[test]
+    fn keeps_exact_token_text() {
+        // `é` written as an `e` and a combining accent, which the lexer normalizes
+        let cst = round_trip("r#while = \"a\\tb\" + cafe\u{301}");
+        let texts: Vec<_> = cst.tokens.iter().map(|token| token.text.as_str()).collect();
+        assert_eq!(texts, ["r#while", "=", "\"a\\tb\"", "+", "cafe\u{301}"]);
+        assert_eq!(cst.tokens[0].token, Token::Identifier("while".into()));
+        assert_eq!(cst.tokens[4].token, Token::Identifier("caf\u{e9}".into()));
+    }
+
+    #[test]
+    fn tokens_can_be_parsed() {
+        let source_code = "// adds\nfn add(u8 a, u8 b) u8 {\n    ret a + b\n}\n";
+        let (tokens, spans) = Cst::new(source_code).unwrap().tokens_with_spans();
+        assert!(!tokens.iter().any(|token| matches!(token, Token::Comment(_))));
+        let arena = Arena::new();
+        let from_cst = Parser::parse_program_with_spans(&arena, &tokens, &spans).unwrap();
+        let (tokens, spans) = Lexer::lex_with_spans(source_code).unwrap();
+        assert_eq!(from_cst, Parser::parse_program_with_spans(&Arena::new(), &tokens, &spans).unwrap());
+    }
+}
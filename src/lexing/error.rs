@@ -1,14 +1,69 @@
 use std::fmt;
 
+use crate::types::IntType;
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum LexingError {
     UnexpectedCharacter(char),
+    /// A string literal isn't closed before the end of its line; `literal` is its source code
+    /// (from the opening `"` up to the end of the line) and `literal_start` is the index of the
+    /// opening `"`.
+    UnterminatedStrLiteral { literal: String, literal_start: usize },
+    /// A string literal contains `\` followed by `sequence`, which isn't a known escape
+    /// sequence; `literal_start` is the index of the literal's opening `"`.
+    InvalidEscapeSequence { sequence: char, literal_start: usize },
+    /// An integer literal is larger than a `u128` can hold; contains the literal.
+    IntLiteralTooLarge(String),
+    /// An int type (like `u99999999`) is wider than [IntType::MAX_WIDTH] bits; contains the
+    /// type's name.
+    ///
+    /// [IntType::MAX_WIDTH]: crate::types::IntType::MAX_WIDTH
+    IntTypeTooWide(String),
+}
+
+impl LexingError {
+    /// Returns the code that identifies this kind of error, like `E0001` (see
+    /// [Diagnostic::code](crate::Diagnostic::code)).
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::UnexpectedCharacter(_) => "E0001",
+            Self::UnterminatedStrLiteral { .. } => "E0002",
+            Self::InvalidEscapeSequence { .. } => "E0003",
+            Self::IntLiteralTooLarge(_) => "E0004",
+            Self::IntTypeTooWide(_) => "E0005",
+        }
+    }
+
+    /// Returns where the string literal in question begins, if the error is inside one (so that
+    /// the error can point at both the problem and the start of the literal).
+    pub fn literal_start(&self) -> Option<usize> {
+        match self {
+            Self::UnterminatedStrLiteral { literal_start, .. }
+            | Self::InvalidEscapeSequence { literal_start, .. } => Some(*literal_start),
+            _ => None,
+        }
+    }
 }
 
 impl fmt::Display for LexingError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::UnexpectedCharacter(c) => write!(f, "unexpected character: '{}'", c),
+            Self::UnterminatedStrLiteral { literal, .. } => {
+                write!(f, "unterminated string literal: {} (a '\"' is missing before the end of the line)", literal)
+            }
+            Self::InvalidEscapeSequence { sequence, .. } => {
+                write!(f, "invalid escape sequence: '\\{}'", sequence)
+            }
+            Self::IntLiteralTooLarge(literal) => {
+                write!(f, "integer literal is too large: '{}' (the largest is {})", literal, u128::MAX)
+            }
+            Self::IntTypeTooWide(name) => write!(
+                f,
+                "int type is too wide: '{}' (the widest is {} bits)",
+                name,
+                IntType::MAX_WIDTH
+            ),
         }
     }
 }
@@ -0,0 +1,121 @@
+use crate::lexing::cst::{Cst, Trivia};
+use crate::lexing::token::{Token, TokenCategory};
+
+/// Returns the source code of `cst` (exactly, like [Cst::to_source]) colored with ANSI escape
+/// codes for a terminal, by the [category](Token::category) of each token.
+///
+/// ```
+/// use flick::cst::Cst;
+/// use flick::highlight::highlight_ansi;
+/// let cst = Cst::new("ret 1 // one").unwrap();
+/// assert_eq!(highlight_ansi(&cst), "\x1b[1;35mret\x1b[0m \x1b[33m1\x1b[0m \x1b[2;37m// one\x1b[0m");
+/// ```
+pub fn highlight_ansi(cst: &Cst) -> String {
+    highlight(cst, |category, text| match ansi_style(category) {
+        Some(style) => format!("\x1b[{}m{}\x1b[0m", style, text),
+        None => text.to_string(),
+    })
+}
+
+/// Returns the source code of `cst` as HTML: a `<pre class="flick">` with each token in a
+/// `<span>` whose class is its [category](TokenCategory::name), for a stylesheet to color.
+///
+/// ```
+/// use flick::cst::Cst;
+/// use flick::highlight::highlight_html;
+/// let cst = Cst::new("str s = \"<b>\"").unwrap();
+/// let expected = r#"<pre class="flick"><code><span class="type">str</span> <span class="identifier">s</span> <span class="operator">=</span> <span class="string">&quot;&lt;b&gt;&quot;</span></code></pre>"#;
+/// assert_eq!(highlight_html(&cst), expected);
+/// ```
+pub fn highlight_html(cst: &Cst) -> String {
+    let html = highlight(cst, |category, text| match category {
+        Some(category) => format!("<span class=\"{}\">{}</span>", category.name(), escape_html(text)),
+        None => escape_html(text),
+    });
+    format!("<pre class=\"flick\"><code>{}</code></pre>", html)
+}
+
+/// Returns the source code of `cst`, with each piece of it (and its category, unless it's
+/// whitespace or a newline) passed through `paint`.
+fn highlight(cst: &Cst, paint: impl Fn(Option<TokenCategory>, &str) -> String) -> String {
+    let paint_trivia = |highlighted: &mut String, trivia: &[Trivia]| {
+        for trivia in trivia {
+            let category = match trivia {
+                Trivia::Whitespace(_) => None,
+                Trivia::Comment(_) => Some(TokenCategory::Comment),
+                Trivia::Docstring(_) => Some(TokenCategory::Docstring),
+            };
+            highlighted.push_str(&paint(category, trivia.text()));
+        }
+    };
+
+    let mut highlighted = String::new();
+    for syntax_token in &cst.tokens {
+        paint_trivia(&mut highlighted, &syntax_token.leading_trivia);
+        let category = match syntax_token.token {
+            Token::Newline => None,
+            _ => Some(syntax_token.token.category()),
+        };
+        highlighted.push_str(&paint(category, &syntax_token.text));
+    }
+    paint_trivia(&mut highlighted, &cst.trailing_trivia);
+    highlighted
+}
+
+/// Returns the ANSI style (Select Graphic Rendition parameters) that tokens in `category` are
+/// colored with, if any; identifiers, operators, and punctuation are left as they are.
+fn ansi_style(category: Option<TokenCategory>) -> Option<&'static str> {
+    match category? {
+        TokenCategory::Keyword => Some("1;35"),
+        TokenCategory::Type => Some("36"),
+        TokenCategory::Number | TokenCategory::Boolean => Some("33"),
+        TokenCategory::String => Some("32"),
+        TokenCategory::Comment => Some("2;37"),
+        TokenCategory::Docstring => Some("2;32"),
+        TokenCategory::Identifier | TokenCategory::Operator | TokenCategory::Punctuation => None,
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cst(source_code: &str) -> Cst {
+        Cst::new(source_code).unwrap()
+    }
+
+    /// Removes the ANSI escape codes from `text`.
+    fn strip_ansi(text: &str) -> String {
+        let mut stripped = String::new();
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\x1b' => while chars.next().is_some_and(|c| c != 'm') {},
+                c => stripped.push(c),
+            }
+        }
+        stripped
+    }
+
+    #[test]
+    fn keeps_the_source_code() {
+        let source_code = "/// Adds\r\nfn add(u8 a, u8 b) u8 {\n\tret a + b  // sum\n}\n\n// the end";
+        assert_eq!(strip_ansi(&highlight_ansi(&cst(source_code))), source_code);
+    }
+
+    #[test]
+    fn html() {
+        let html = highlight_html(&cst("/// Says \"hi\"\nif true {\n    x -= 1\n}\n"));
+        let expected = "<pre class=\"flick\"><code>\
+<span class=\"docstring\">/// Says &quot;hi&quot;</span>
+<span class=\"keyword\">if</span> <span class=\"boolean\">true</span> <span class=\"punctuation\">{</span>
+    <span class=\"identifier\">x</span> <span class=\"operator\">-=</span> <span class=\"number\">1</span>
+<span class=\"punctuation\">}</span>
+</code></pre>";
+        assert_eq!(html, expected);
+    }
+}
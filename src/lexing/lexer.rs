@@ -1,10 +1,15 @@
 use crate::lexing::token::AssignmentSymbol::*;
 use crate::lexing::token::ComparatorSymbol::*;
 use crate::lexing::token::OperatorSymbol::*;
-use crate::lexing::token::Token;
+use crate::lexing::token::{Span, Token};
 use crate::types::IntType;
 use crate::types::Type;
 
+use std::borrow::Cow;
+
+use unicode_ident::{is_xid_continue, is_xid_start};
+use unicode_normalization::UnicodeNormalization;
+
 use crate::error::{FlickError, ErrorKind};
 use super::error::LexingError;
 
@@ -15,55 +20,140 @@ use super::error::LexingError;
 /// # Examples
 /// ```
 /// use flick::Lexer;
-/// let source_code: Vec<_> = "foo(42)".to_string().chars().collect();
-/// let mut tokens = Lexer::lex(&source_code);
+/// let mut tokens = Lexer::lex("foo(42)");
 /// ```
 pub struct Lexer<'a> {
     /// Source code slice
-    source_code: &'a [char],
+    source_code: &'a str,
 
-    /// Current location in source code
+    /// Current location in source code, as a byte offset
     ///
-    /// In other words, the character at index `self.cursor` of `self.chars` hasn't
-    /// been processed yet.
+    /// In other words, the character that starts at byte `self.cursor` of `self.source_code`
+    /// hasn't been processed yet.
     cursor: usize,
+
+    /// Which characters identifiers can contain
+    identifier_policy: IdentifierPolicy,
+}
+
+/// Which characters identifiers can contain (see [Lexer::lex_with_policy]).
+///
+/// Keywords and type names (like `while` and `u8`) are ASCII either way.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum IdentifierPolicy {
+    /// Identifiers follow Unicode's [UAX #31][a] (like Rust's): they start with a letter or `_`,
+    /// followed by letters, digits, combining marks, or `_`. Each identifier is normalized to
+    /// NFC, so that names which look the same (like `café` written with a precomposed `é`, or
+    /// with an `e` and a combining accent) are the same name.
+    ///
+    /// [a]: https://www.unicode.org/reports/tr31/
+    #[default]
+    Unicode,
+    /// Identifiers only contain ASCII letters, digits, and `_`.
+    Ascii,
+}
+
+impl IdentifierPolicy {
+    fn is_identifier_start(self, c: char) -> bool {
+        match self {
+            Self::Unicode => c == '_' || is_xid_start(c),
+            Self::Ascii => c == '_' || c.is_ascii_alphabetic(),
+        }
+    }
+
+    fn is_identifier_continue(self, c: char) -> bool {
+        match self {
+            Self::Unicode => is_xid_continue(c),
+            Self::Ascii => c == '_' || c.is_ascii_alphanumeric(),
+        }
+    }
 }
 
 impl<'a> Lexer<'a> {
+    /// The words that are keywords, rather than identifiers (unless they're written as raw
+    /// identifiers, like `r#while`).
+    pub const KEYWORDS: &'static [&'static str] = &["while", "pub", "fn", "ret", "if", "extern", "else", "true", "false"];
+
     /// Converts the source code into a vector of tokens
     /// 
     /// Returns an `Err()` if lexing fails.
-    pub fn lex(source_code: &'a [char]) -> crate::Result<Vec<Token>> {
-        let mut lexer = Self {
-            source_code,
-            cursor: 0,
-        };
+    pub fn lex(source_code: &'a str) -> crate::Result<Vec<Token<'a>>> {
+        Self::lex_with_spans(source_code).map(|(tokens, _)| tokens)
+    }
+
+    /// Like [Lexer::lex], but also returns where each token is in the source code (the `i`-th
+    /// [Span] is the location of the `i`-th token), so that errors can point at the code that
+    /// caused them.
+    pub fn lex_with_spans(source_code: &'a str) -> crate::Result<(Vec<Token<'a>>, Vec<Span>)> {
+        Self::lex_with_policy(source_code, IdentifierPolicy::default())
+    }
 
+    /// Like [Lexer::lex_with_spans], but identifiers can only contain the characters that
+    /// `identifier_policy` allows (e.g. just ASCII characters, with [IdentifierPolicy::Ascii]).
+    pub fn lex_with_policy(
+        source_code: &'a str,
+        identifier_policy: IdentifierPolicy,
+    ) -> crate::Result<(Vec<Token<'a>>, Vec<Span>)> {
         let mut tokens = Vec::new();
-        while lexer.cursor < lexer.source_code.len() {
-            tokens.push(lexer.next_token()?);
+        let mut spans = Vec::new();
+        for result in Self::new(source_code, identifier_policy) {
+            let (token, span) = result?;
+            tokens.push(token);
+            spans.push(span);
+        }
+        tracing::debug!(bytes = source_code.len(), tokens = tokens.len(), "lexed source code");
+        Ok((tokens, spans))
+    }
+
+    /// Returns a lexer that lexes `source_code` one token at a time, as an iterator of tokens and
+    /// their spans (e.g. for the [Parser](crate::Parser) to parse them as they're lexed; see
+    /// [Parser::parse_token_stream](crate::Parser::parse_token_stream)).
+    ///
+    /// The iterator keeps going after an error, from the end of the token that's wrong (like an
+    /// integer literal that's too large, or the rest of a string literal with an unknown escape
+    /// sequence), or from the next character if it's a character that can't start a token.
+    ///
+    /// ```
+    /// use flick::token::{Span, Token};
+    /// use flick::{IdentifierPolicy, Lexer};
+    ///
+    /// let mut lexer = Lexer::new("x $ y", IdentifierPolicy::default());
+    /// assert_eq!(lexer.next(), Some(Ok((Token::Identifier("x".into()), Span { start: 0, end: 1 }))));
+    /// assert!(lexer.next().unwrap().is_err());
+    /// assert_eq!(lexer.next(), Some(Ok((Token::Identifier("y".into()), Span { start: 4, end: 5 }))));
+    /// assert_eq!(lexer.next(), None);
+    /// ```
+    pub fn new(source_code: &'a str, identifier_policy: IdentifierPolicy) -> Self {
+        Self {
+            source_code,
+            cursor: 0,
+            identifier_policy,
         }
-        Ok(tokens)
     }
 
-    /// Returns (and consumes) a reference to the next character in the source code.
-    fn next_char(&mut self) -> Option<&char> {
-        let char = self.source_code.get(self.cursor);
-        self.cursor += 1;
-        char
+    /// Returns (and consumes) the next character in the source code.
+    fn next_char(&mut self) -> Option<char> {
+        let char = self.source_code[self.cursor..].chars().next()?;
+        self.cursor += char.len_utf8();
+        Some(char)
     }
 
-    /// Returns a reference to the `n`-th character out of the remaining source code.
+    /// Returns the `n`-th character out of the remaining source code.
     ///
     /// Note: this function returns `None` if fewer than `n` characters remain.
     ///
     /// This function doesn't affect the internal state of the lexer (i.e., it doesn't consume
     /// any characters / it doesn't advance the internal cursor)
-    fn peek_char(&self, n: usize) -> Option<&char> {
-        self.source_code.get(self.cursor + (n - 1)) // n-1 to fix indexing
+    fn peek_char(&self, n: usize) -> Option<char> {
+        self.source_code[self.cursor..].chars().nth(n - 1) // n-1 to fix indexing
     }
 
     /// Advances the cursor past the next `n` characters without returning anything.
+    ///
+    /// # Assumptions:
+    ///
+    /// - The next `n` characters are ASCII (like those of a symbol token, or a `r#`), so they're
+    ///   one byte each.
     fn skip_chars(&mut self, n: usize) {
         self.cursor += n;
     }
@@ -75,15 +165,16 @@ impl<'a> Lexer<'a> {
     ///
     /// # Assumptions
     /// - There's at least one character of the source code left (`self.cursor < self.source_code`)
-    fn next_token(&mut self) -> crate::Result<Token> {
+    fn next_token(&mut self) -> crate::Result<Token<'a>> {
         self.skip_non_newline_whitespace();
 
         let first_token = self.peek_char(1).expect("see assumptions in docstring");
 
         // Figure out what type the next token is and call handling function
         let peeked_token = match (first_token, self.peek_char(2)) {
-            ('a'..='z' | 'A'..='Z' | '_', _) => return Ok(self.read_word()),
-            ('0'..='9', _) => return Ok(self.read_int_literal()),
+            (c, _) if self.identifier_policy.is_identifier_start(c) => return self.read_word(),
+            ('0'..='9', _) => return self.read_int_literal(),
+            ('"', _) => return self.read_str_literal(),
             ('/', Some('/')) => return Ok(self.read_comment()),
 
             ('>', Some('=')) => Token::ComparatorSymbol(GreaterOrEqualTo),
@@ -111,7 +202,7 @@ impl<'a> Lexer<'a> {
             ('}', _) => Token::RSquirly,
             ('\n', _) => Token::Newline,
 
-            (c, _) => return Err(self.err(LexingError::UnexpectedCharacter(*c)))
+            (c, _) => return Err(self.err(LexingError::UnexpectedCharacter(c)))
         };
 
         self.skip_chars(peeked_token.get_char_count());
@@ -119,24 +210,24 @@ impl<'a> Lexer<'a> {
     }
 
     fn err(&self, kind: LexingError) -> FlickError {
+        Self::err_at(self.cursor, kind)
+    }
+
+    /// Like [Lexer::err], but for an error at `index` (e.g. the start of the token that was
+    /// just read) instead of at the cursor.
+    fn err_at(index: usize, kind: LexingError) -> FlickError {
         FlickError {
-            index: self.cursor,
+            index,
             kind: ErrorKind::LexingError(kind),
         }
     }
 
     /// Returns (and consumes) source code characters while `predicate` evaluates to `true`
     /// when applied to each character.
-    fn take_chars_while(&mut self, predicate: impl Fn(&char) -> bool) -> String {
-        let mut string = String::new();
-        while let Some(c) = self.peek_char(1) {
-            if predicate(c) {
-                string.push(*self.next_char().unwrap());
-            } else {
-                break;
-            }
-        }
-        string
+    fn take_chars_while(&mut self, predicate: impl Fn(char) -> bool) -> String {
+        let start = self.cursor;
+        self.skip_chars_while(predicate);
+        self.source_code[start..self.cursor].to_string()
     }
 
     /// Consumes source code characters while `predicate` evaluates to `true` when applied
@@ -144,10 +235,10 @@ impl<'a> Lexer<'a> {
     ///
     /// This function is equivalent to [Lexer::take_chars_while], except it doesn't return
     /// anything.
-    fn skip_chars_while(&mut self, predicate: impl Fn(&char) -> bool) {
+    fn skip_chars_while(&mut self, predicate: impl Fn(char) -> bool) {
         while let Some(c) = self.peek_char(1) {
             if predicate(c) {
-                self.skip_chars(1);
+                self.cursor += c.len_utf8();
             } else {
                 break;
             }
@@ -157,31 +248,49 @@ impl<'a> Lexer<'a> {
     /// This functions skips non-newline whitespace using [Lexer::skip_chars_while] and an
     /// appropriate predicate.
     fn skip_non_newline_whitespace(&mut self) {
-        self.skip_chars_while(|&c| c.is_whitespace() && c != '\n');
+        self.skip_chars_while(|c| c.is_whitespace() && c != '\n');
     }
 
     /// Consumes source code characters and returns the corresponding [Token], either a keyword
-    /// (e.g., `while`) or an identifier (e.g., `foo`).
+    /// (e.g., `while`) or an identifier (e.g., `foo`, normalized as described in
+    /// [IdentifierPolicy::Unicode]).
+    ///
+    /// A raw identifier, like `r#while`, is always an identifier (`while`), even if it's spelled
+    /// like a keyword or a type; that way, Flick code can use (e.g. call) anything, like the C
+    /// function `set`, even if its name is reserved in Flick.
     ///
     /// # Assumptions:
     ///
-    /// - The next source code character is one of `a-z`, `A-Z`, or `_`.
-    fn read_word(&mut self) -> Token {
-        let s = self.take_chars_while(|&c| c.is_ascii_alphanumeric() || c == '_');
+    /// - The next source code character can start an identifier (see [IdentifierPolicy]).
+    fn read_word(&mut self) -> crate::Result<Token<'a>> {
+        let is_raw = self.peek_char(1) == Some('r')
+            && self.peek_char(2) == Some('#')
+            && self.peek_char(3).is_some_and(|c| self.identifier_policy.is_identifier_start(c));
+        if is_raw {
+            self.skip_chars(2); // skip the `r#`
+            return Ok(Token::Identifier(self.read_identifier().into()));
+        }
+
+        let start = self.cursor;
+        let s = self.read_identifier();
 
         if (s.starts_with('u') || s.starts_with('i')) && s.len() > 1 && s.chars().skip(1).all(|c| c.is_ascii_digit()) {
             let num: String = s.chars().skip(1).collect();
-            let width: u32 = num.parse().unwrap();
+            let width = match num.parse() {
+                Ok(width) if width <= IntType::MAX_WIDTH => width,
+                _ => return Err(Self::err_at(start, LexingError::IntTypeTooWide(s))),
+            };
             match s.chars().next().unwrap() {
-                'u' => return Token::Type(Type::Int(IntType { width, signed: false })),
-                'i' => return Token::Type(Type::Int(IntType { width, signed: true })),
+                'u' => return Ok(Token::Type(Box::new(Type::Int(IntType { width, signed: false })))),
+                'i' => return Ok(Token::Type(Box::new(Type::Int(IntType { width, signed: true })))),
                 _ => unreachable!(),
             }
         }
 
-        match s.as_str() {
-            "bool" => Token::Type(Type::Bool),
-            "void" => Token::Type(Type::Void),
+        Ok(match s.as_str() {
+            "bool" => Token::Type(Box::new(Type::Bool)),
+            "str" => Token::Type(Box::new(Type::Str)),
+            "void" => Token::Type(Box::new(Type::Void)),
             "while" => Token::While,
             "pub" => Token::Pub,
             "fn" => Token::Fn,
@@ -191,18 +300,121 @@ impl<'a> Lexer<'a> {
             "else" => Token::Else,
             "true" => Token::True,
             "false" => Token::False,
-            _ => Token::Identifier(s),
+            _ => Token::Identifier(s.into()),
+        })
+    }
+
+    /// Consumes the characters of an identifier (or keyword) and returns it, normalized as
+    /// described in [IdentifierPolicy::Unicode].
+    fn read_identifier(&mut self) -> String {
+        let policy = self.identifier_policy;
+        let identifier = self.take_chars_while(|c| policy.is_identifier_continue(c));
+        match identifier.is_ascii() {
+            true => identifier,
+            false => identifier.nfc().collect(),
         }
     }
 
     /// Consumes source code characters and returns the corresponding [Token::IntLiteral].
     ///
+    /// Returns an `Err()` if the literal doesn't fit in a `u128`, since no later pass could
+    /// represent it. Its type isn't known yet, so the [Typer](crate::Typer) is what checks that
+    /// it fits in its type (see [TypingError::ConstantOverflow](crate::TypingError::ConstantOverflow)).
+    ///
     /// # Assumptions:
     ///
     /// - The next source code character is a digit or a '-'
-    fn read_int_literal(&mut self) -> Token {
-        let number = self.take_chars_while(|&c| c.is_ascii_digit());
-        Token::IntLiteral(number)
+    fn read_int_literal(&mut self) -> crate::Result<Token<'a>> {
+        let start = self.cursor;
+        let number = self.take_chars_while(|c| c.is_ascii_digit());
+        if number.parse::<u128>().is_err() {
+            return Err(Self::err_at(start, LexingError::IntLiteralTooLarge(number)));
+        }
+        Ok(Token::IntLiteral(number.into()))
+    }
+
+    /// Consumes source code characters and returns the corresponding [Token::StrLiteral].
+    ///
+    /// The escape sequences `\n`, `\t`, `\0`, `\\`, and `\"` are replaced by the characters
+    /// they represent. Returns an `Err()` if the literal has an unknown escape sequence or
+    /// isn't closed before the end of the line.
+    ///
+    /// A literal without escape sequences (like most of them) is borrowed from the source code,
+    /// rather than copied.
+    ///
+    /// # Assumptions:
+    ///
+    /// - The next source code character is a `"`.
+    fn read_str_literal(&mut self) -> crate::Result<Token<'a>> {
+        let start = self.cursor;
+        self.skip_chars(1);
+
+        let contents = &self.source_code[self.cursor..];
+        let length = contents.find(['"', '\\', '\n']).unwrap_or(contents.len());
+        if contents[length..].starts_with('"') {
+            self.skip_chars(length + 1);
+            return Ok(Token::StrLiteral(Cow::Borrowed(&contents[..length])));
+        }
+
+        // The contents up to the first escape sequence can be copied as they are
+        let mut string = contents[..length].to_string();
+        self.skip_chars(length);
+        loop {
+            match self.next_char() {
+                Some('"') => return Ok(Token::StrLiteral(Cow::Owned(string))),
+                Some('\\') => {
+                    let escaped = match self.next_char() {
+                        Some('n') => '\n',
+                        Some('t') => '\t',
+                        Some('0') => '\0',
+                        Some('\\') => '\\',
+                        Some('"') => '"',
+                        Some('\n') | None => break,
+                        Some(sequence) => {
+                            let kind = LexingError::InvalidEscapeSequence { sequence, literal_start: start };
+                            let backslash = self.cursor - sequence.len_utf8() - 1;
+                            self.skip_rest_of_str_literal();
+                            return Err(Self::err_at(backslash, kind));
+                        }
+                    };
+                    string.push(escaped);
+                }
+                Some('\n') | None => break,
+                Some(c) => string.push(c),
+            }
+        }
+
+        // point at the end of the line, where the closing `"` should have been
+        let end = self.source_code[start..]
+            .find('\n')
+            .map_or(self.source_code.len(), |offset| start + offset);
+        let literal = self.source_code[start..end].to_string();
+        self.cursor = end; // the newline is still a token of its own
+        Err(Self::err_at(end, LexingError::UnterminatedStrLiteral { literal, literal_start: start }))
+    }
+
+    /// Consumes the rest of a string literal (after an invalid escape sequence in it), up to its
+    /// closing `"`, or up to the end of the line if it isn't closed.
+    fn skip_rest_of_str_literal(&mut self) {
+        while let Some(c) = self.peek_char(1) {
+            match c {
+                '\n' => return,
+                '"' => {
+                    self.skip_chars(1);
+                    return;
+                }
+                '\\' => {
+                    self.skip_chars(1);
+                    // an escaped `"` doesn't close the literal
+                    if self.peek_char(1).is_some_and(|c| c != '\n') {
+                        self.next_char();
+                    }
+                }
+                _ => {
+                    self.next_char();
+                }
+            }
+        }
     }
 
     /// Consumes source code characters and returns the corresponding [Token::Comment] or
@@ -211,10 +423,33 @@ impl<'a> Lexer<'a> {
     /// # Assumptions:
     ///
     /// - The next two/three source code characters are `//` or `///`.
-    fn read_comment(&mut self) -> Token {
+    fn read_comment(&mut self) -> Token<'a> {
         match self.peek_char(3) {
-            Some('/') => Token::Docstring(self.take_chars_while(|&c| c != '\n')),
-            _ => Token::Comment(self.take_chars_while(|&c| c != '\n')),
+            Some('/') => Token::Docstring(self.take_chars_while(|c| c != '\n').into()),
+            _ => Token::Comment(self.take_chars_while(|c| c != '\n').into()),
+        }
+    }
+}
+
+impl<'a> Iterator for Lexer<'a> {
+    type Item = crate::Result<(Token<'a>, Span)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.skip_non_newline_whitespace(); // so that trailing whitespace doesn't form a token
+        if self.cursor == self.source_code.len() {
+            return None;
+        }
+        let start = self.cursor;
+        match self.next_token() {
+            Ok(token) => Some(Ok((token, Span { start, end: self.cursor }))),
+            Err(err) => {
+                // A character that can't start a token is skipped (every other error comes after
+                // the token that's wrong)
+                if self.cursor == start {
+                    self.next_char();
+                }
+                Some(Err(err))
+            }
         }
     }
 }
@@ -222,19 +457,19 @@ impl<'a> Lexer<'a> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::lexing::token::Position;
     // todo make macro to avoid last three lines of boilerplate
 
     #[test]
     fn comments() {
         let source_code = "//    simple comment\n/// // / docstring";
         let expected_tokens = vec![
-            Token::Comment("//    simple comment".to_string()),
+            Token::Comment("//    simple comment".into()),
             Token::Newline,
-            Token::Docstring("/// // / docstring".to_string()),
+            Token::Docstring("/// // / docstring".into()),
         ];
 
-        let source_code_chars: Vec<_> = source_code.chars().collect();
-        let received_tokens = Lexer::lex(&source_code_chars).unwrap();
+        let received_tokens = Lexer::lex(source_code).unwrap();
 
         assert_eq!(received_tokens, expected_tokens);
     }
@@ -243,19 +478,18 @@ mod tests {
     fn variables() {
         let source_code = "i64 this_is_a_LONG_VARIABLE_NAME = 5\ni64 shortInt = 5";
         let expected_tokens = vec![
-            Token::Type(Type::Int(IntType { signed: true, width: 64 })),
-            Token::Identifier("this_is_a_LONG_VARIABLE_NAME".to_string()),
+            Token::Type(Box::new(Type::Int(IntType { signed: true, width: 64 }))),
+            Token::Identifier("this_is_a_LONG_VARIABLE_NAME".into()),
             Token::AssignmentSymbol(Eq),
-            Token::IntLiteral("5".to_string()),
+            Token::IntLiteral("5".into()),
             Token::Newline,
-            Token::Type(Type::Int(IntType { signed: true, width: 64 })),
-            Token::Identifier("shortInt".to_string()),
+            Token::Type(Box::new(Type::Int(IntType { signed: true, width: 64 }))),
+            Token::Identifier("shortInt".into()),
             Token::AssignmentSymbol(Eq),
-            Token::IntLiteral("5".to_string()),
+            Token::IntLiteral("5".into()),
         ];
 
-        let source_code_chars: Vec<_> = source_code.chars().collect();
-        let received_tokens = Lexer::lex(&source_code_chars).unwrap();
+        let received_tokens = Lexer::lex(source_code).unwrap();
 
         assert_eq!(received_tokens, expected_tokens);
     }
@@ -265,15 +499,14 @@ mod tests {
         let source_code = "while x <= 5 {}";
         let expected_tokens = vec![
             Token::While,
-            Token::Identifier("x".to_string()),
+            Token::Identifier("x".into()),
             Token::ComparatorSymbol(LessOrEqualTo),
-            Token::IntLiteral("5".to_string()),
+            Token::IntLiteral("5".into()),
             Token::LSquirly,
             Token::RSquirly,
         ];
 
-        let source_code_chars: Vec<_> = source_code.chars().collect();
-        let received_tokens = Lexer::lex(&source_code_chars).unwrap();
+        let received_tokens = Lexer::lex(source_code).unwrap();
 
         assert_eq!(received_tokens, expected_tokens);
     }
@@ -283,28 +516,264 @@ mod tests {
         let source_code = "(a +3) /4 * 5 % 3*(-2) -2";
         let expected_tokens = vec![
             Token::LParen,
-            Token::Identifier("a".to_string()),
+            Token::Identifier("a".into()),
             Token::OperatorSymbol(Plus),
-            Token::IntLiteral("3".to_string()),
+            Token::IntLiteral("3".into()),
             Token::RParen,
             Token::OperatorSymbol(Slash),
-            Token::IntLiteral("4".to_string()),
+            Token::IntLiteral("4".into()),
             Token::OperatorSymbol(Asterisk),
-            Token::IntLiteral("5".to_string()),
+            Token::IntLiteral("5".into()),
             Token::OperatorSymbol(Modulo),
-            Token::IntLiteral("3".to_string()),
+            Token::IntLiteral("3".into()),
             Token::OperatorSymbol(Asterisk),
             Token::LParen,
             Token::OperatorSymbol(Minus),
-            Token::IntLiteral("2".to_string()),
+            Token::IntLiteral("2".into()),
             Token::RParen,
             Token::OperatorSymbol(Minus),
-            Token::IntLiteral("2".to_string()),
+            Token::IntLiteral("2".into()),
         ];
 
-        let source_code_chars: Vec<_> = source_code.chars().collect();
-        let received_tokens = Lexer::lex(&source_code_chars).unwrap();
+        let received_tokens = Lexer::lex(source_code).unwrap();
 
         assert_eq!(received_tokens, expected_tokens);
     }
+
+    #[test]
+    fn str_literals() {
+        let source_code = r#"str s = "Hello, \"world\"!\n""#;
+        let expected_tokens = vec![
+            Token::Type(Box::new(Type::Str)),
+            Token::Identifier("s".into()),
+            Token::AssignmentSymbol(Eq),
+            Token::StrLiteral("Hello, \"world\"!\n".into()),
+        ];
+
+        let received_tokens = Lexer::lex(source_code).unwrap();
+
+        assert_eq!(received_tokens, expected_tokens);
+    }
+
+    #[test]
+    fn str_literals_are_only_copied_if_they_have_escape_sequences() {
+        let tokens = Lexer::lex(r#"foo("plain", "tab\tbed", "")"#).unwrap();
+        let literals: Vec<_> = tokens
+            .iter()
+            .filter_map(|token| match token {
+                Token::StrLiteral(string) => Some(string),
+                _ => None,
+            })
+            .collect();
+
+        assert!(matches!(literals[0], Cow::Borrowed("plain")));
+        assert!(matches!(literals[1], Cow::Owned(string) if string == "tab\tbed"));
+        assert!(matches!(literals[2], Cow::Borrowed("")));
+    }
+
+    #[test]
+    fn unterminated_str_literal() {
+        let source_code = "x = 1\nfoo(\"oops)\nbar()\n";
+        let expected_error = FlickError {
+            index: 16,
+            kind: ErrorKind::LexingError(LexingError::UnterminatedStrLiteral {
+                literal: "\"oops)".to_string(),
+                literal_start: 10,
+            }),
+        };
+
+        let received_error = Lexer::lex(source_code).unwrap_err();
+
+        assert_eq!(received_error, expected_error);
+        assert_eq!(received_error.position(source_code), Position { line: 2, column: 11 });
+    }
+
+    #[test]
+    fn unterminated_str_literal_at_end_of_file() {
+        let source_code = "foo(\"a\\";
+        let expected_error = FlickError {
+            index: 7,
+            kind: ErrorKind::LexingError(LexingError::UnterminatedStrLiteral {
+                literal: "\"a\\".to_string(),
+                literal_start: 4,
+            }),
+        };
+
+        let received_error = Lexer::lex(source_code).unwrap_err();
+
+        assert_eq!(received_error, expected_error);
+    }
+
+    #[test]
+    fn invalid_escape_sequence() {
+        let source_code = r#""a\qb""#;
+        let expected_error = FlickError {
+            index: 2,
+            kind: ErrorKind::LexingError(LexingError::InvalidEscapeSequence {
+                sequence: 'q',
+                literal_start: 0,
+            }),
+        };
+
+        let received_error = Lexer::lex(source_code).unwrap_err();
+
+        assert_eq!(received_error, expected_error);
+    }
+
+    #[test]
+    fn int_literal_too_large() {
+        let source_code = "x = 999999999999999999999999999999999999999\n";
+        let expected_error = FlickError {
+            index: 4,
+            kind: ErrorKind::LexingError(LexingError::IntLiteralTooLarge(
+                "999999999999999999999999999999999999999".to_string(),
+            )),
+        };
+
+        let received_error = Lexer::lex(source_code).unwrap_err();
+
+        assert_eq!(received_error, expected_error);
+    }
+
+    #[test]
+    fn keeps_lexing_after_errors() {
+        let source_code = "x = 999999999999999999999999999999999999999 + 1\ny = \"a\\qb\" $ 2\nfoo(\"oops)\nbar\n";
+        let received: Vec<_> = Lexer::new(source_code, IdentifierPolicy::default())
+            .map(|result| result.map(|(token, _)| token).map_err(|err| err.kind))
+            .collect();
+        let expected = vec![
+            Ok(Token::Identifier("x".into())),
+            Ok(Token::AssignmentSymbol(Eq)),
+            Err(ErrorKind::LexingError(LexingError::IntLiteralTooLarge(
+                "999999999999999999999999999999999999999".to_string(),
+            ))),
+            Ok(Token::OperatorSymbol(Plus)),
+            Ok(Token::IntLiteral("1".into())),
+            Ok(Token::Newline),
+            Ok(Token::Identifier("y".into())),
+            Ok(Token::AssignmentSymbol(Eq)),
+            Err(ErrorKind::LexingError(LexingError::InvalidEscapeSequence { sequence: 'q', literal_start: 52 })),
+            Err(ErrorKind::LexingError(LexingError::UnexpectedCharacter('$'))),
+            Ok(Token::IntLiteral("2".into())),
+            Ok(Token::Newline),
+            Ok(Token::Identifier("foo".into())),
+            Ok(Token::LParen),
+            Err(ErrorKind::LexingError(LexingError::UnterminatedStrLiteral {
+                literal: "\"oops)".to_string(),
+                literal_start: 67,
+            })),
+            Ok(Token::Newline),
+            Ok(Token::Identifier("bar".into())),
+            Ok(Token::Newline),
+        ];
+
+        assert_eq!(received, expected);
+    }
+
+    #[test]
+    fn int_type_too_wide() {
+        let source_code = "u99999999999 x = 1\n";
+        let expected_error = FlickError {
+            index: 0,
+            kind: ErrorKind::LexingError(LexingError::IntTypeTooWide("u99999999999".to_string())),
+        };
+
+        let received_error = Lexer::lex(source_code).unwrap_err();
+
+        assert_eq!(received_error, expected_error);
+    }
+
+    #[test]
+    fn unicode_identifiers() {
+        // The first `café` has a precomposed `é`, and the second has an `e` and a combining accent
+        let source_code = "u8 caf\u{e9} = 1\ncafe\u{301} += \u{3c0}\n";
+        let expected_tokens = vec![
+            Token::Type(Box::new(Type::Int(IntType { width: 8, signed: false }))),
+            Token::Identifier("caf\u{e9}".into()),
+            Token::AssignmentSymbol(Eq),
+            Token::IntLiteral("1".into()),
+            Token::Newline,
+            Token::Identifier("caf\u{e9}".into()),
+            Token::AssignmentSymbol(PlusEq),
+            Token::Identifier("\u{3c0}".into()),
+            Token::Newline,
+        ];
+
+        let received_tokens = Lexer::lex(source_code).unwrap();
+
+        assert_eq!(received_tokens, expected_tokens);
+    }
+
+    #[test]
+    fn raw_identifiers() {
+        let source_code = "extern fn r#if(u8 r#u8)\nr#x = r\n";
+        let expected_tokens = vec![
+            Token::Extern,
+            Token::Fn,
+            Token::Identifier("if".into()),
+            Token::LParen,
+            Token::Type(Box::new(Type::Int(IntType { width: 8, signed: false }))),
+            Token::Identifier("u8".into()),
+            Token::RParen,
+            Token::Newline,
+            Token::Identifier("x".into()),
+            Token::AssignmentSymbol(Eq),
+            Token::Identifier("r".into()),
+            Token::Newline,
+        ];
+
+        let received_tokens = Lexer::lex(source_code).unwrap();
+
+        assert_eq!(received_tokens, expected_tokens);
+    }
+
+    #[test]
+    fn ascii_only_identifiers() {
+        let source_code = "u8 ça = 1\n";
+        let expected_error = FlickError {
+            index: 3,
+            kind: ErrorKind::LexingError(LexingError::UnexpectedCharacter('ç')),
+        };
+
+        let received_error = Lexer::lex_with_policy(source_code, IdentifierPolicy::Ascii).unwrap_err();
+
+        assert_eq!(received_error, expected_error);
+    }
+
+    #[test]
+    fn spans() {
+        let source_code = "foo(\"a\\nb\",  42)";
+        let (tokens, spans) = Lexer::lex_with_spans(source_code).unwrap();
+
+        assert_eq!(tokens.len(), spans.len());
+        let spanned_code: Vec<String> = spans
+            .iter()
+            .map(|span| source_code[span.start..span.end].to_string())
+            .collect();
+        assert_eq!(spanned_code, vec!["foo", "(", "\"a\\nb\"", ",", "42", ")"]);
+    }
+
+    #[test]
+    fn positions() {
+        let source_code = "pub fn main() u8 {\n\tret 0\n}\n";
+        let (tokens, spans) = Lexer::lex_with_spans(source_code).unwrap();
+
+        let ret_index = tokens.iter().position(|token| *token == Token::Ret).unwrap();
+        assert_eq!(spans[ret_index].start_position(source_code), Position { line: 2, column: 2 });
+        assert_eq!(spans[ret_index].end_position(source_code), Position { line: 2, column: 5 });
+        let last_span = spans.last().unwrap(); // the last newline
+        assert_eq!(last_span.end_position(source_code), Position { line: 4, column: 1 });
+    }
+
+    #[test]
+    fn spans_are_byte_offsets() {
+        let source_code = "caf\u{e9}(\"\u{3c0}\") \u{a0}\u{3c0}\n";
+        let (tokens, spans) = Lexer::lex_with_spans(source_code).unwrap();
+
+        assert_eq!(tokens[2], Token::StrLiteral("\u{3c0}".into()));
+        let starts: Vec<usize> = spans.iter().map(|span| span.start).collect();
+        assert_eq!(starts, vec![0, 5, 6, 10, 14, 16]);
+        // columns still count characters
+        assert_eq!(spans[4].start_position(source_code), Position { line: 1, column: 12 });
+    }
 }
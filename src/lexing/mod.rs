@@ -6,3 +6,12 @@ pub mod token;
 
 /// Module that defines the lexer errors.
 pub mod error;
+
+/// Module that defines the lossless [Cst](cst::Cst), which keeps whitespace and comments.
+pub mod cst;
+
+/// Module that defines syntax highlighting, by the [category](token::Token::category) of each token.
+pub mod highlight;
+
+/// Module that defines the interned [Name](name::Name) of a function or a parameter.
+pub mod name;
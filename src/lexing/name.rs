@@ -0,0 +1,123 @@
+use std::borrow::Borrow;
+use std::collections::HashSet;
+use std::fmt;
+use std::ops::Deref;
+use std::sync::{Mutex, OnceLock, PoisonError};
+
+/// An interned name, like a function's or a parameter's: every `Name` with the same text shares
+/// one copy of it, so a name is copied (e.g. into the prototype of each call to a function) as
+/// cheaply as a pointer, rather than cloned like a `String`.
+///
+/// Interned names are never freed (which is why a `Name` can be `Copy`), so there's one copy of
+/// each different name for as long as the compiler runs.
+///
+/// ```
+/// use flick::Name;
+/// let name = Name::intern("factorial");
+/// assert_eq!(name, "factorial");
+/// assert!(std::ptr::eq(name.as_str(), Name::intern(&"factorial".to_string()).as_str()));
+/// ```
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub struct Name(&'static str);
+
+impl Name {
+    /// Returns the name whose text is `text`, adding it to the names if it isn't there yet.
+    pub fn intern(text: &str) -> Self {
+        static NAMES: OnceLock<Mutex<HashSet<&'static str>>> = OnceLock::new();
+        let mut names = NAMES.get_or_init(Default::default).lock().unwrap_or_else(PoisonError::into_inner);
+        match names.get(text) {
+            Some(name) => Self(name),
+            None => {
+                let name: &'static str = Box::leak(text.into());
+                names.insert(name);
+                Self(name)
+            }
+        }
+    }
+
+    /// Returns the text of the name.
+    pub fn as_str(&self) -> &'static str {
+        self.0
+    }
+}
+
+impl Deref for Name {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.0
+    }
+}
+
+impl AsRef<str> for Name {
+    fn as_ref(&self) -> &str {
+        self.0
+    }
+}
+
+// A name hashes and compares like its text, so maps of names can be looked up by a `&str`
+impl Borrow<str> for Name {
+    fn borrow(&self) -> &str {
+        self.0
+    }
+}
+
+impl From<&str> for Name {
+    fn from(text: &str) -> Self {
+        Self::intern(text)
+    }
+}
+
+impl PartialEq<str> for Name {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<&str> for Name {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
+
+impl PartialEq<String> for Name {
+    fn eq(&self, other: &String) -> bool {
+        self.0 == other
+    }
+}
+
+impl fmt::Display for Name {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+impl fmt::Debug for Name {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.0, f)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Name {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_are_interned() {
+        let text = String::from("fibonacci");
+        let name = Name::intern(&text);
+        drop(text);
+        assert_eq!(name, "fibonacci");
+        assert_eq!(name, Name::from("fibonacci"));
+        assert!(std::ptr::eq(name.as_str(), Name::intern("fibonacci").as_str()));
+        assert_ne!(name, Name::intern("fib"));
+        assert_eq!(format!("{} {:?}", name, name), "fibonacci \"fibonacci\"");
+    }
+}
@@ -1,20 +1,24 @@
 use crate::types::Type;
+use std::borrow::Cow;
 use std::fmt;
 
 /// An enum to represent any given non-whitespace token in a source code
 ///
 /// For example, `foo(42)` consists of four tokens:
-/// 1. `Token::Identifier("foo".to_string())`
+/// 1. `Token::Identifier("foo".into())`
 /// 1. `Token::LParen`
-/// 1. `Token::IntLiteral("42".to_string())`
+/// 1. `Token::IntLiteral("42".into())`
 /// 1. `Token::RParen`
 #[derive(Debug, PartialEq, Eq, Clone)]
-pub enum Token {
-    Docstring(String),
-    Comment(String),
+pub enum Token<'a> {
+    Docstring(Box<str>),
+    Comment(Box<str>),
 
-    IntLiteral(String),
-    Identifier(String),
+    IntLiteral(Box<str>),
+    /// The contents of a string literal, with escape sequences already processed (borrowed from
+    /// the source code, unless it had escape sequences to process)
+    StrLiteral(Cow<'a, str>),
+    Identifier(Box<str>),
 
     // Keywords
     Extern,
@@ -27,8 +31,9 @@ pub enum Token {
     True,
     False,
 
-    /// The built-in Flick types, like `void`
-    Type(Type),
+    /// The built-in Flick types, like `void` (boxed, since a [Type] can be a whole function
+    /// prototype, which would make every token as big)
+    Type(Box<Type>),
 
     // Brackets
     LSquirly,
@@ -48,19 +53,115 @@ pub enum Token {
     AssignmentSymbol(AssignmentSymbol),
 }
 
-impl Token {
+// Programs are lexed into a lot of tokens, so they're kept small: their text is a `Box<str>`
+// rather than a `String` (it never grows), and their type is boxed
+const _: () = assert!(std::mem::size_of::<Token>() <= 24);
+
+impl Token<'_> {
+    /// Returns a copy of the token that doesn't borrow from the source code (e.g. to keep it in
+    /// an error, after the source code is gone).
+    pub fn into_owned(self) -> Token<'static> {
+        match self {
+            Self::Docstring(docstring) => Token::Docstring(docstring),
+            Self::Comment(comment) => Token::Comment(comment),
+            Self::IntLiteral(int) => Token::IntLiteral(int),
+            Self::StrLiteral(string) => Token::StrLiteral(Cow::Owned(string.into_owned())),
+            Self::Identifier(id) => Token::Identifier(id),
+            Self::Extern => Token::Extern,
+            Self::Pub => Token::Pub,
+            Self::Fn => Token::Fn,
+            Self::Ret => Token::Ret,
+            Self::While => Token::While,
+            Self::If => Token::If,
+            Self::Else => Token::Else,
+            Self::True => Token::True,
+            Self::False => Token::False,
+            Self::Type(var_type) => Token::Type(var_type),
+            Self::LSquirly => Token::LSquirly,
+            Self::RSquirly => Token::RSquirly,
+            Self::LParen => Token::LParen,
+            Self::RParen => Token::RParen,
+            Self::Newline => Token::Newline,
+            Self::Comma => Token::Comma,
+            Self::OperatorSymbol(operator_symbol) => Token::OperatorSymbol(operator_symbol),
+            Self::ComparatorSymbol(comparator_symbol) => Token::ComparatorSymbol(comparator_symbol),
+            Self::AssignmentSymbol(assignment_symbol) => Token::AssignmentSymbol(assignment_symbol),
+        }
+    }
+
     pub fn get_char_count(&self) -> usize {
         self.to_string().len()
     }
+
+    /// Returns what kind of token this is, for syntax highlighting (see
+    /// [highlight](crate::highlight)).
+    pub fn category(&self) -> TokenCategory {
+        match self {
+            Self::Docstring(_) => TokenCategory::Docstring,
+            Self::Comment(_) => TokenCategory::Comment,
+            Self::IntLiteral(_) => TokenCategory::Number,
+            Self::StrLiteral(_) => TokenCategory::String,
+            Self::Identifier(_) => TokenCategory::Identifier,
+            Self::Extern | Self::Pub | Self::Fn | Self::Ret | Self::While | Self::If | Self::Else => {
+                TokenCategory::Keyword
+            }
+            Self::True | Self::False => TokenCategory::Boolean,
+            Self::Type(_) => TokenCategory::Type,
+            Self::LSquirly | Self::RSquirly | Self::LParen | Self::RParen | Self::Newline | Self::Comma => {
+                TokenCategory::Punctuation
+            }
+            Self::OperatorSymbol(_) | Self::ComparatorSymbol(_) | Self::AssignmentSymbol(_) => {
+                TokenCategory::Operator
+            }
+        }
+    }
 }
 
-impl fmt::Display for Token {
+/// The kinds of tokens that syntax highlighting tells apart (see [Token::category]).
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum TokenCategory {
+    Comment,
+    Docstring,
+    Keyword,
+    /// A built-in type, like `u8`
+    Type,
+    Identifier,
+    Number,
+    String,
+    /// `true` or `false`
+    Boolean,
+    /// An arithmetic operator, a comparator, or an assignment symbol
+    Operator,
+    /// A bracket, a comma, or a newline
+    Punctuation,
+}
+
+impl TokenCategory {
+    /// Returns the category's name, like `"keyword"`, which HTML output uses as a class name.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Comment => "comment",
+            Self::Docstring => "docstring",
+            Self::Keyword => "keyword",
+            Self::Type => "type",
+            Self::Identifier => "identifier",
+            Self::Number => "number",
+            Self::String => "string",
+            Self::Boolean => "boolean",
+            Self::Operator => "operator",
+            Self::Punctuation => "punctuation",
+        }
+    }
+}
+
+impl fmt::Display for Token<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::Docstring(docstring) => write!(f, "{}", docstring),
             Self::Comment(comment) => write!(f, "{}", comment),
 
             Self::IntLiteral(int) => write!(f, "{}", int),
+            Self::StrLiteral(string) => write!(f, "\"{}\"", string.escape_default()),
             Self::Identifier(id) => write!(f, "{}", id),
 
             Self::Pub => write!(f, "pub"),
@@ -89,6 +190,64 @@ impl fmt::Display for Token {
     }
 }
 
+/// The location of a token (or of an AST node) in the source code, as a range of byte offsets:
+/// `start` is the offset of its first byte, and `end` is one past its last one.
+///
+/// Spans let later passes point at the code that an error is about; see
+/// [Lexer::lex_with_spans](crate::Lexer::lex_with_spans).
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    /// Returns the line and column where the span starts in `source_code` (which should be the
+    /// code that the span's token was lexed from).
+    pub fn start_position(&self, source_code: &str) -> Position {
+        Position::of(self.start, source_code)
+    }
+
+    /// Returns the line and column just past the end of the span in `source_code`.
+    pub fn end_position(&self, source_code: &str) -> Position {
+        Position::of(self.end, source_code)
+    }
+}
+
+/// A line and column in the source code, both starting at 1 (like editors show them). The
+/// column counts characters, so a tab or a `é` is one column.
+///
+/// ```
+/// use flick::token::Position;
+/// let source_code = "fn foo() {\n    ret\n}";
+/// assert_eq!(Position::of(15, source_code), Position { line: 2, column: 5 }); // `ret`
+/// ```
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Position {
+    /// Returns the position of the byte at `index` in `source_code` (`index` can also be
+    /// `source_code.len()`, the end of the code).
+    pub fn of(index: usize, source_code: &str) -> Self {
+        let before = &source_code[..index];
+        let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+        Self {
+            line: before.bytes().filter(|&b| b == b'\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+        }
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
 /// An enum to store one of `+`, `-`, `*`, and `/`
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub enum OperatorSymbol {
@@ -1,35 +1,105 @@
 #![doc = include_str!("../README.md")]
 
+// The `llvm-*` features pick which llvm-sys (and so which LLVM) the compiler is built against. With
+// none of them, there's no `Compiler`, but everything up to type checking (and the `CBackend`) works.
+#[cfg(all(
+    feature = "llvm",
+    not(any(feature = "llvm-15", feature = "llvm-16", feature = "llvm-17", feature = "llvm-18"))
+))]
+compile_error!("the `llvm` feature needs one of the features `llvm-15`, `llvm-16`, `llvm-17`, or `llvm-18`");
+#[cfg(any(
+    all(feature = "llvm-15", any(feature = "llvm-16", feature = "llvm-17", feature = "llvm-18")),
+    all(feature = "llvm-16", any(feature = "llvm-17", feature = "llvm-18")),
+    all(feature = "llvm-17", feature = "llvm-18"),
+))]
+compile_error!("only one of the features `llvm-15`, `llvm-16`, `llvm-17`, and `llvm-18` can be enabled");
+
+#[cfg(feature = "llvm-15")]
+extern crate llvm_sys_150 as llvm_sys;
+#[cfg(feature = "llvm-16")]
+extern crate llvm_sys_160 as llvm_sys;
+#[cfg(feature = "llvm-17")]
+extern crate llvm_sys_170 as llvm_sys;
+#[cfg(feature = "llvm-18")]
+extern crate llvm_sys_181 as llvm_sys;
+
 /// Module to convert [abstract syntax trees](ast) into LLVM using llvm-sys
 ///
 /// The general idea is to take code objects (e.g. variables or functions) and form
-/// instances of [LLVMValueRef][a]. Then, after we're done, we ask llvm-sys to
-/// generate LLVM code.
-///
-/// [a]: llvm_sys::prelude::LLVMValueRef
+/// LLVM values (through a safe wrapper around llvm-sys). Then, after we're done, we ask
+/// LLVM to generate code.
 mod compilation;
 /// Module to convert source files into token streams
 mod lexing;
 /// Module to convert token streams into [abstract syntax trees](ast)
 mod parsing;
+/// Module to check that the names in [abstract syntax trees](ast) refer to something
+mod resolving;
 /// This module is used by [Compiler](compiler::Compiler), because it manages namespaces/scopes.
 ///
-/// See [ScopeManager] for more details.
+/// See [ScopeManager] and [Symbol] for more details.
 mod scope_manager;
 /// Module to store all the Flick types
 pub mod types;
 /// Module to add types to [abstract syntax trees](ast)
 mod typing;
+/// Module to find code in [abstract syntax trees](ast) that's probably a mistake
+mod linting;
 /// Module to represent global Flick errors
 pub mod error;
+/// Module to show errors (and warnings) to the user
+mod diagnostics;
+/// Module for the cache of compiled modules that lets `flick build` skip the files that haven't
+/// changed (see [cache::ObjectCache])
+#[cfg(feature = "cache")]
+pub mod cache;
+/// Module to read the `flick.toml` files that configure projects
+#[cfg(feature = "project")]
+pub mod project;
+/// Module for `flick lsp`, a language server that gives editors errors, go-to-definition, and
+/// more (see [lsp::server::Server])
+#[cfg(feature = "lsp")]
+pub mod lsp;
 
 // TODO (Max): Should we remove pub use and just make users use absolute path (I kinda like the idea of that if we somehow make the paths nicer)
+pub use compilation::backend::Backend;
+pub use compilation::c_backend::CBackend;
+#[cfg(feature = "llvm")]
 pub use compilation::compiler::Compiler;
-pub use lexing::lexer::Lexer;
+pub use compilation::coverage;
+pub use compilation::profiling;
+pub use compilation::error::CompilationError;
+pub use compilation::options::{CompilerOptions, CompilerOptionsBuilder, EmitKind, OptLevel, RelocModel};
+pub use compilation::pipeline::{check, check_program, check_with, generate, lex_sources, parse_sources, CheckOptions, Diagnostics, PhaseTimes};
+#[cfg(feature = "llvm")]
+pub use compilation::pipeline::{compile_file, compile_sources, compile_str, Artifacts};
+#[cfg(feature = "llvm")]
+pub use compilation::jit::{eval, Engine, FlickValue};
+pub use diagnostics::diagnostic::{Diagnostic, Note, Severity};
+pub use diagnostics::lints::{Lint, LintLevel, LintLevels, LINTS};
+pub use diagnostics::fix;
+pub use diagnostics::registry;
+pub use diagnostics::sink::DiagnosticSink;
+pub use diagnostics::source_map::{MappedFile, SourceCode, SourceFile, SourceMap};
+pub use lexing::lexer::{IdentifierPolicy, Lexer};
+pub use lexing::cst;
+pub use lexing::highlight;
+pub use lexing::name::Name;
 pub use lexing::token;
+pub use linting::linter::Linter;
 pub use parsing::ast;
+pub use parsing::grammar;
+pub use parsing::format::{format_source, format_source_with_policy};
 pub use parsing::parser::Parser;
-pub use scope_manager::ScopeManager;
+pub use parsing::printer::{print_dot, print_tree};
+pub use parsing::unparse::{unparse, unparse_expr};
+pub use parsing::stats;
+pub use parsing::visit;
+pub use resolving::rename;
+pub use resolving::resolver::Resolver;
+pub use scope_manager::{ScopeManager, Symbol, SymbolKind};
+pub use typing::builtins;
+pub use typing::error::TypingError;
 pub use typing::typed_ast;
 pub use typing::typer::Typer;
 pub use error::Result;
@@ -0,0 +1,54 @@
+use std::fmt;
+
+/// A warning from the [Linter](crate::Linter) (each of which is a [Lint](crate::Lint)).
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum LintingError {
+    /// A variable (or a parameter, if `kind` says so) is never read. `removable` is where the
+    /// variable's declaration and every assignment to it are, if they can all be removed without
+    /// changing what the program does (a parameter can't be, and neither can a variable that's
+    /// given the result of a call).
+    UnusedVariable { name: String, kind: &'static str, removable: Vec<usize> },
+    /// The condition of an `if` (or a `while`) is always `value`.
+    ConstantCondition { statement_kind: &'static str, value: bool },
+    /// A `while` loop's condition is always true, and there's no `ret` in it to leave it with.
+    EndlessLoop,
+}
+
+impl LintingError {
+    /// Returns the code that identifies this kind of warning, like `W0501` (see
+    /// [Diagnostic::code](crate::Diagnostic::code)).
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::UnusedVariable { .. } => "W0501",
+            Self::ConstantCondition { .. } => "W0502",
+            Self::EndlessLoop => "W0503",
+        }
+    }
+
+    /// Returns a suggestion for fixing the code (or for saying that it's on purpose).
+    pub fn help(&self) -> Option<String> {
+        match self {
+            Self::UnusedVariable { name, kind, .. } => Some(format!(
+                "if the {} is unused on purpose, start its name with '_' (like '_{}')",
+                kind, name
+            )),
+            Self::ConstantCondition { statement_kind: "while", value: false } => {
+                Some("the loop's body never runs".to_string())
+            }
+            Self::ConstantCondition { .. } => None,
+            Self::EndlessLoop => Some("add a 'ret' to the loop, so that it can end".to_string()),
+        }
+    }
+}
+
+impl fmt::Display for LintingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnusedVariable { name, kind, .. } => write!(f, "{} '{}' is never read", kind, name),
+            Self::ConstantCondition { statement_kind, value } => {
+                write!(f, "the condition of this '{}' is always {}", statement_kind, value)
+            }
+            Self::EndlessLoop => write!(f, "this loop never ends"),
+        }
+    }
+}
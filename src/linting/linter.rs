@@ -0,0 +1,331 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::ast::{BinaryOperator, ComparisonOperator, Expr, FuncDef, NodeMap, Program, Statement, UnaryOperator};
+use crate::error::{ErrorKind, FlickError};
+use crate::lexing::token::Span;
+use crate::linting::error::LintingError;
+use crate::resolving::resolver::Resolver;
+use crate::scope_manager::Symbol;
+use crate::typing::const_eval;
+use crate::types::Type;
+use crate::visit::{self, Visitor};
+
+/// A struct that looks for code that compiles, but probably isn't what was meant: variables
+/// that are never read, conditions that are always true (or always false), and loops that can
+/// never end (see [LINTS](crate::LINTS)).
+///
+/// The linter is a pass of its own, which `flick lint` runs after checking a program; it
+/// expects a program without errors.
+///
+/// ```
+/// use flick::ast::Arena;
+/// use flick::{Lexer, Linter, Parser};
+///
+/// let source_code = "pub fn main() u8 {\n    u8 x = 1\n    if 1 < 2 {\n        ret 1\n    }\n    ret 0\n}\n";
+/// let (tokens, spans) = Lexer::lex_with_spans(source_code).unwrap();
+/// let arena = Arena::new();
+/// let program = Parser::parse_program_with_spans(&arena, &tokens, &spans).unwrap();
+///
+/// let warnings = Linter::lint_program(&program);
+/// assert_eq!(warnings[0].to_string(), "variable 'x' is never read");
+/// assert_eq!(warnings[1].to_string(), "the condition of this 'if' is always true");
+/// ```
+pub struct Linter {
+    /// Where the variables (and parameters) that are read somewhere are declared
+    read: HashSet<Span>,
+    /// Where the variables are assigned to (by where they're declared; see
+    /// [ReadCollector::assignments])
+    assignments: HashMap<Span, Vec<(usize, bool)>>,
+    warnings: Vec<FlickError>,
+}
+
+impl Linter {
+    /// Returns a warning for everything in `program` that a lint is about, in the order that
+    /// it's written.
+    pub fn lint_program(program: &Program) -> Vec<FlickError> {
+        let mut read_collector = ReadCollector {
+            symbols: Resolver::resolve_symbols(program),
+            read: HashSet::new(),
+            assignments: HashMap::new(),
+        };
+        read_collector.visit_program(program);
+
+        let mut linter = Self { read: read_collector.read, assignments: read_collector.assignments, warnings: Vec::new() };
+        linter.visit_program(program);
+        linter.warnings
+    }
+
+    fn warn(&mut self, index: usize, warning: LintingError) {
+        self.warnings.push(FlickError { index, kind: ErrorKind::LintingError(warning) });
+    }
+
+    /// Warns about the variable (or parameter) called `name`, declared at `span`, if it's never
+    /// read (unless its name starts with `_`, which says that's on purpose). `value` is the
+    /// variable's initial value (parameters don't have one, and can't be removed).
+    fn check_read(&mut self, name: &str, span: Span, kind: &'static str, value: Option<&Expr>) {
+        if name.starts_with('_') || self.read.contains(&span) {
+            return;
+        }
+        // The variable can be removed along with its assignments, unless one of them calls a
+        // function, which might do something that matters
+        let assignments = self.assignments.get(&span).map_or(&[][..], Vec::as_slice);
+        let removable = match value {
+            Some(value) if !calls_a_function(value) && assignments.iter().all(|&(_, calls)| !calls) => {
+                std::iter::once(span.start).chain(assignments.iter().map(|&(index, _)| index)).collect()
+            }
+            _ => Vec::new(),
+        };
+        self.warn(span.start, LintingError::UnusedVariable { name: name.to_string(), kind, removable });
+    }
+
+    /// Warns about the condition of an `if` or `while` (whose keyword is at `span`) if it's
+    /// constant, and returns its value if so.
+    ///
+    /// `while true` is how Flick spells a loop that only ends with a `ret`, so it's left to the
+    /// endless loop lint.
+    fn check_condition(&mut self, condition: &Expr, span: Span, statement_kind: &'static str) -> Option<bool> {
+        let value = match condition {
+            Expr::BoolLiteral(..) | Expr::Comparison(_) => constant_value(condition)? != 0,
+            _ => return None,
+        };
+        if !(statement_kind == "while" && matches!(condition, Expr::BoolLiteral(true, ..))) {
+            self.warn(span.start, LintingError::ConstantCondition { statement_kind, value });
+        }
+        Some(value)
+    }
+}
+
+impl Visitor<'_> for Linter {
+    fn visit_func_def(&mut self, func_def: &FuncDef) {
+        for param in &func_def.proto.params {
+            self.check_read(&param.param_name, param.span, "parameter", None);
+        }
+        visit::walk_func_def(self, func_def);
+    }
+
+    fn visit_statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::VarDeclaration(var_declaration) => {
+                let value = Some(&var_declaration.var_value);
+                self.check_read(var_declaration.var_name, var_declaration.span, "variable", value);
+            }
+            Statement::If(if_statement) => {
+                self.check_condition(&if_statement.condition, if_statement.span, "if");
+            }
+            Statement::WhileLoop(while_loop) => {
+                let always_true = self.check_condition(&while_loop.condition, while_loop.span, "while") == Some(true);
+                if always_true && !returns_somewhere(&while_loop.body) {
+                    self.warn(while_loop.span.start, LintingError::EndlessLoop);
+                }
+            }
+            Statement::Assignment(_) | Statement::Return(..) | Statement::Call(_) => {}
+        }
+        visit::walk_statement(self, statement);
+    }
+}
+
+/// Finds where each variable that's read is declared (see [Linter::read]). Assigning to a
+/// variable doesn't count as reading it.
+struct ReadCollector {
+    /// What each name refers to (see [Resolver::resolve_symbols])
+    symbols: NodeMap<Symbol>,
+    read: HashSet<Span>,
+    /// Where each variable is assigned to (by where it's declared), and whether the value
+    /// assigned calls a function
+    assignments: HashMap<Span, Vec<(usize, bool)>>,
+}
+
+impl Visitor<'_> for ReadCollector {
+    fn visit_statement(&mut self, statement: &Statement) {
+        if let Statement::Assignment(assignment) = statement {
+            if let Some(symbol) = self.symbols.get(&assignment.id) {
+                let assignment_info = (assignment.span.start, calls_a_function(assignment.value));
+                self.assignments.entry(symbol.span).or_default().push(assignment_info);
+            }
+        }
+        visit::walk_statement(self, statement);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        if let Expr::Identifier(identifier) = expr {
+            if let Some(symbol) = self.symbols.get(&identifier.id) {
+                self.read.insert(symbol.span);
+            }
+        }
+        visit::walk_expr(self, expr);
+    }
+}
+
+/// Returns `true` if there's a `ret` anywhere in `body`, including in the bodies in it.
+fn returns_somewhere(body: &[Statement]) -> bool {
+    body.iter().any(|statement| match statement {
+        Statement::Return(..) => true,
+        Statement::If(if_statement) => {
+            returns_somewhere(&if_statement.then_body)
+                || if_statement.else_body.as_deref().is_some_and(returns_somewhere)
+        }
+        Statement::WhileLoop(while_loop) => returns_somewhere(&while_loop.body),
+        Statement::VarDeclaration(_) | Statement::Assignment(_) | Statement::Call(_) => false,
+    })
+}
+
+/// Returns `true` if `expr` calls a function anywhere in it.
+fn calls_a_function(expr: &Expr) -> bool {
+    match expr {
+        Expr::Call(_) => true,
+        Expr::Binary(binary) => calls_a_function(binary.left) || calls_a_function(binary.right),
+        Expr::Comparison(comparison) => calls_a_function(comparison.left) || calls_a_function(comparison.right),
+        Expr::Unary(unary) => calls_a_function(unary.operand),
+        Expr::Identifier(_) | Expr::IntLiteral(..) | Expr::BoolLiteral(..) | Expr::StrLiteral(..) => false,
+    }
+}
+
+/// Returns the value of `expr` if it's made up of nothing but literals, arithmetic, casts, and
+/// comparisons, with `true` and `false` as 1 and 0.
+///
+/// The program has been type checked, so the values of constant integer expressions fit in their
+/// types (see [const_eval]), and only casts have to wrap.
+fn constant_value(expr: &Expr) -> Option<i128> {
+    match expr {
+        Expr::IntLiteral(value, ..) => value.parse().ok(),
+        Expr::BoolLiteral(value, ..) => Some(i128::from(*value)),
+        Expr::Binary(binary) => {
+            let (left, right) = (constant_value(binary.left)?, constant_value(binary.right)?);
+            match binary.operator {
+                BinaryOperator::Add => left.checked_add(right),
+                BinaryOperator::Subtract => left.checked_sub(right),
+                BinaryOperator::Multiply => left.checked_mul(right),
+                BinaryOperator::Divide => left.checked_div(right),
+                BinaryOperator::Remainder => left.checked_rem(right),
+            }
+        }
+        Expr::Comparison(comparison) => {
+            let (left, right) = (constant_value(comparison.left)?, constant_value(comparison.right)?);
+            let value = match comparison.operator {
+                ComparisonOperator::NotEqualTo => left != right,
+                ComparisonOperator::EqualTo => left == right,
+                ComparisonOperator::LessThan => left < right,
+                ComparisonOperator::GreaterThan => left > right,
+                ComparisonOperator::LessOrEqualTo => left <= right,
+                ComparisonOperator::GreaterOrEqualTo => left >= right,
+            };
+            Some(i128::from(value))
+        }
+        Expr::Unary(unary) => {
+            let operand = constant_value(unary.operand)?;
+            match &unary.operator {
+                UnaryOperator::Negate => Some(-operand),
+                UnaryOperator::Cast(Type::Int(int_type)) => const_eval::wrap(operand, *int_type),
+                UnaryOperator::Cast(_) => None,
+            }
+        }
+        Expr::Identifier(_) | Expr::StrLiteral(..) | Expr::Call(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Lexer, Parser};
+    use crate::ast::Arena;
+
+    /// Returns the warnings about `source_code`, as their messages and where they are.
+    fn lint(source_code: &str) -> Vec<(String, usize)> {
+        let (tokens, spans) = Lexer::lex_with_spans(source_code).unwrap();
+        let arena = Arena::new();
+        let program = Parser::parse_program_with_spans(&arena, &tokens, &spans).unwrap();
+        Linter::lint_program(&program)
+            .into_iter()
+            .map(|warning| (warning.to_string(), warning.index))
+            .collect()
+    }
+
+    #[test]
+    fn unused_variables() {
+        let source_code = "fn f(u8 a, u8 b, u8 _c) u8 {\n    u8 x = a\n    u8 y = 1\n    y = 2\n    ret x\n}\n";
+        assert_eq!(
+            lint(source_code),
+            [("parameter 'b' is never read".to_string(), 14), ("variable 'y' is never read".to_string(), 49)]
+        );
+    }
+
+    #[test]
+    fn unused_variables_can_be_removed_without_calls() {
+        let source_code = "fn f(u8 a) {\n    u8 x = a\n    x = 2\n    u8 y = 1\n    y = f(1)\n}\n";
+        let (tokens, spans) = Lexer::lex_with_spans(source_code).unwrap();
+        let arena = Arena::new();
+        let program = Parser::parse_program_with_spans(&arena, &tokens, &spans).unwrap();
+        let removable: Vec<_> = Linter::lint_program(&program)
+            .into_iter()
+            .map(|warning| match warning.kind {
+                ErrorKind::LintingError(LintingError::UnusedVariable { removable, .. }) => removable,
+                _ => panic!("{}", warning),
+            })
+            .collect();
+        // `y` is given the result of a call
+        assert_eq!(removable, [vec![20, 30], vec![]]);
+    }
+
+    #[test]
+    fn shadowed_variables_are_told_apart() {
+        // The inner `x` is read, but the outer one isn't
+        let source_code = "fn f() u8 {\n    u8 x = 1\n    if true {\n        u8 x = 2\n        ret x\n    }\n    ret 0\n}\n";
+        let warnings = lint(source_code);
+        assert_eq!(warnings[0], ("variable 'x' is never read".to_string(), 19));
+        assert_eq!(warnings.len(), 2);
+    }
+
+    #[test]
+    fn constant_conditions() {
+        let source_code = "\
+fn f(u8 n) {
+    if false {
+    }
+    if (u8) 300 > 40 + 3 {
+    }
+    while 1 == 2 {
+    }
+    if n == 1 {
+    }
+}
+";
+        let messages: Vec<_> = lint(source_code).into_iter().map(|(message, _)| message).collect();
+        assert_eq!(
+            messages,
+            [
+                "the condition of this 'if' is always false",
+                "the condition of this 'if' is always true",
+                "the condition of this 'while' is always false",
+            ]
+        );
+    }
+
+    #[test]
+    fn endless_loops() {
+        let source_code = "\
+fn f() u8 {
+    while true {
+        if true {
+            ret 1
+        }
+    }
+    while true {
+    }
+    while 1 < 2 {
+    }
+}
+";
+        let warnings = lint(source_code);
+        let messages: Vec<_> = warnings.iter().map(|(message, _)| message.as_str()).collect();
+        assert_eq!(
+            messages,
+            [
+                "the condition of this 'if' is always true",
+                "this loop never ends",
+                "the condition of this 'while' is always true",
+                "this loop never ends",
+            ]
+        );
+        assert_eq!(warnings[1].1, 85);
+    }
+}
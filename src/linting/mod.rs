@@ -0,0 +1,6 @@
+/// Module that defines the [Linter](linter::Linter) struct for finding code that compiles, but
+/// probably isn't what was meant.
+pub mod linter;
+
+/// Module that defines the warnings that the linter reports.
+pub mod error;
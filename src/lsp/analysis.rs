@@ -0,0 +1,348 @@
+use crate::ast::{Arena, FuncProto, GlobalStatement, NodeId, NodeMap, Program, Statement};
+use crate::compilation::pipeline::check;
+use crate::diagnostics::diagnostic::Diagnostic;
+use crate::diagnostics::source_map::SourceMap;
+use crate::lexing::lexer::Lexer;
+use crate::lexing::token::{Span, Token};
+use crate::linting::linter::Linter;
+use crate::parsing::parser::Parser;
+use crate::resolving::rename::collect_names;
+use crate::resolving::resolver::Resolver;
+use crate::scope_manager::{Symbol, SymbolKind};
+use crate::types::Type;
+
+/// Everything that the language server knows about a document: the errors and warnings in it,
+/// and what each name in it refers to (for [Analysis::definition] and [Analysis::hover]).
+///
+/// A document is checked on its own, as if it were the only file being compiled.
+///
+/// ```
+/// use flick::lsp::analysis::Analysis;
+/// let source_code = "/// Doubles `n`\nfn double(u8 n) u8 {\n    ret n * 2\n}\n";
+/// let analysis = Analysis::new("double.fl", source_code.to_string());
+/// assert_eq!(analysis.diagnostics[0].code, "E0301"); // there's no `main`
+///
+/// let n = 45; // the `n` in `n * 2`
+/// assert_eq!(analysis.definition(n).unwrap().start, 29);
+/// assert_eq!(analysis.hover(n).unwrap(), "```flick\nu8 n\n```");
+/// ```
+#[derive(Debug)]
+pub struct Analysis {
+    source_code: String,
+    /// Every error and warning in the document (including the ones that `flick lint` finds)
+    pub diagnostics: Vec<Diagnostic>,
+    /// The functions in the document (see [Analysis::outline]), unless it has syntax errors
+    outline: Vec<OutlineItem>,
+    /// The document's tokens, and their spans
+    tokens: Vec<(Token<'static>, Span)>,
+    /// The name that each node which has one uses or declares, and where it is
+    names: Vec<(Span, NodeId, String)>,
+    /// What each name refers to (see [Resolver::resolve_symbols])
+    symbols: NodeMap<Symbol>,
+}
+
+/// A function (or a variable in one) in a document's outline (see [Analysis::outline]).
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct OutlineItem {
+    pub name: String,
+    /// A function's prototype, or a variable's type
+    pub detail: String,
+    pub kind: SymbolKind,
+    /// Where the function or variable is named
+    pub span: Span,
+    /// All of the function (or just the variable's name)
+    pub extent: Span,
+    /// The function's parameters and local variables, in the order that they're declared
+    pub children: Vec<OutlineItem>,
+}
+
+impl Analysis {
+    /// Checks `source_code`, the contents of the document at `path`.
+    pub fn new(path: &str, source_code: String) -> Self {
+        let sources = SourceMap::single_file(path, source_code);
+        let mut diagnostics = Vec::new();
+        let checked = check(&sources, &mut diagnostics).is_some();
+        let source_code = sources.files()[0].source_code.to_string();
+
+        let (tokens, spans) = Lexer::lex_with_spans(&source_code).unwrap_or_default();
+        // The tokens are kept, and the source code is moved into the analysis
+        let tokens: Vec<_> = tokens.into_iter().map(Token::into_owned).collect();
+        let arena = Arena::new();
+        let program = Parser::parse_program_with_spans(&arena, &tokens, &spans).ok();
+        let names = program.as_ref().map(collect_names).unwrap_or_default();
+        let symbols = program.as_ref().map(Resolver::resolve_symbols).unwrap_or_default();
+        // Like `flick lint`, since an editor is where these warnings help the most
+        if let (true, Some(program)) = (checked, &program) {
+            diagnostics.extend(Linter::lint_program(program).iter().map(Diagnostic::warning));
+        }
+
+        let mut analysis = Self {
+            source_code,
+            diagnostics,
+            outline: Vec::new(),
+            tokens: tokens.iter().cloned().zip(spans.iter().copied()).collect(),
+            names,
+            symbols,
+        };
+        // The tree borrows from `arena` and from `tokens`, so the outline is all that's kept of it
+        if let Some(program) = &program {
+            analysis.outline = analysis.outline_of(program);
+        }
+        analysis
+    }
+
+    pub fn source_code(&self) -> &str {
+        &self.source_code
+    }
+
+    /// Returns the name at `offset` (a byte offset), and what it refers to.
+    fn symbol_at(&self, offset: usize) -> Option<(&str, &Symbol)> {
+        let (_, id, name) = self
+            .names
+            .iter()
+            .find(|(span, _, _)| span.start <= offset && offset <= span.end)?;
+        Some((name, self.symbols.get(id)?))
+    }
+
+    /// Returns where the name at `offset` is declared, unless it's a builtin (or there's no name
+    /// there).
+    pub fn definition(&self, offset: usize) -> Option<Span> {
+        let (_, symbol) = self.symbol_at(offset)?;
+        match symbol.kind {
+            SymbolKind::Builtin => None,
+            _ => Some(symbol.span),
+        }
+    }
+
+    /// Returns a description of the name at `offset`, in Markdown: its type (or its prototype,
+    /// for a function), followed by the function's docstring, if it has one.
+    pub fn hover(&self, offset: usize) -> Option<String> {
+        let (name, symbol) = self.symbol_at(offset)?;
+        let declaration = match symbol.kind {
+            SymbolKind::Builtin | SymbolKind::Function => symbol.symbol_type.to_string(),
+            SymbolKind::Param | SymbolKind::Local => format!("{} {}", symbol.symbol_type, name),
+        };
+        let mut hover = format!("```flick\n{}\n```", declaration);
+        if symbol.kind == SymbolKind::Function {
+            let docs = self.docstring(symbol.span);
+            if !docs.is_empty() {
+                hover.push_str("\n\n");
+                hover.push_str(&docs);
+            }
+        }
+        Some(hover)
+    }
+
+    /// Returns the docstring (the `///` lines right above the line that `span` is on), without
+    /// the `///`s.
+    fn docstring(&self, span: Span) -> String {
+        let before = &self.source_code[..span.start];
+        let mut lines: Vec<_> = before
+            .lines()
+            .rev()
+            .skip(1) // the declaration's own line
+            .map(str::trim)
+            .map_while(|line| line.strip_prefix("///"))
+            .map(|line| line.strip_prefix(' ').unwrap_or(line))
+            .collect();
+        lines.reverse();
+        lines.join("\n")
+    }
+
+    /// Returns the functions in the document, each with its parameters and local variables.
+    pub fn outline(&self) -> &[OutlineItem] {
+        &self.outline
+    }
+
+    fn outline_of(&self, program: &Program) -> Vec<OutlineItem> {
+        program
+            .global_statements
+            .iter()
+            .map(|global_statement| {
+                let (proto, body) = match global_statement {
+                    GlobalStatement::Extern(proto) => (proto, &[][..]),
+                    GlobalStatement::FuncDef(func_def) => (&func_def.proto, &func_def.body[..]),
+                };
+                let mut children: Vec<_> = proto
+                    .params
+                    .iter()
+                    .map(|param| variable_item(SymbolKind::Param, &param.param_name, &param.param_type, param.span))
+                    .collect();
+                collect_locals(body, &mut children);
+                OutlineItem {
+                    name: proto.name.to_string(),
+                    detail: proto.to_string(),
+                    kind: SymbolKind::Function,
+                    span: proto.span,
+                    extent: self.extent(proto),
+                    children,
+                }
+            })
+            .collect()
+    }
+
+    /// Returns where the function declared by `proto` starts and ends: from its first keyword
+    /// to the end of its body (or of the line, for an `extern` function).
+    fn extent(&self, proto: &FuncProto) -> Span {
+        let Some(name_index) = self.tokens.iter().position(|(_, span)| *span == proto.span) else {
+            return proto.span;
+        };
+        let first = self.tokens[..name_index]
+            .iter()
+            .rev()
+            .take_while(|(token, _)| matches!(token, Token::Fn | Token::Pub | Token::Extern))
+            .last()
+            .map_or(proto.span, |(_, span)| *span);
+
+        let mut last = proto.span;
+        let mut open_bodies = 0;
+        for (token, span) in &self.tokens[name_index..] {
+            match token {
+                Token::Newline if open_bodies == 0 => break,
+                Token::LSquirly => open_bodies += 1,
+                Token::RSquirly => {
+                    open_bodies -= 1;
+                    if open_bodies == 0 {
+                        last = *span;
+                        break;
+                    }
+                }
+                _ => {}
+            }
+            last = *span;
+        }
+        Span { start: first.start, end: last.end }
+    }
+}
+
+fn variable_item(kind: SymbolKind, name: &str, var_type: &Type, span: Span) -> OutlineItem {
+    OutlineItem {
+        name: name.to_string(),
+        detail: var_type.to_string(),
+        kind,
+        span,
+        extent: span,
+        children: Vec::new(),
+    }
+}
+
+/// Appends an item for each variable declared in `body` (and in the bodies in it) to `items`.
+fn collect_locals(body: &[Statement], items: &mut Vec<OutlineItem>) {
+    for statement in body {
+        match statement {
+            Statement::VarDeclaration(declaration) => items.push(variable_item(
+                SymbolKind::Local,
+                declaration.var_name,
+                &declaration.var_type,
+                declaration.span,
+            )),
+            Statement::WhileLoop(while_loop) => collect_locals(&while_loop.body, items),
+            Statement::If(if_statement) => {
+                collect_locals(&if_statement.then_body, items);
+                collect_locals(if_statement.else_body.as_deref().unwrap_or_default(), items);
+            }
+            Statement::Assignment(_) | Statement::Return(..) | Statement::Call(_) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE_CODE: &str = "\
+extern fn putchar(i8 c) i8
+
+/// Returns the larger of `a` and `b`
+///
+/// (or `b`, if they're equal)
+fn max(u8 a, u8 b) u8 {
+    if a > b {
+        u8 larger = a
+        ret larger
+    }
+    ret b
+}
+
+pub fn main() u8 {
+    u8 x = max(1, 2)
+    x += 1
+    putchar(72)
+    ret arg_count()
+}
+";
+
+    fn analysis() -> Analysis {
+        Analysis::new("max.fl", SOURCE_CODE.to_string())
+    }
+
+    /// Returns the index of the `n`-th (from 0) `text` in the source code.
+    fn offset_of(text: &str, n: usize) -> usize {
+        SOURCE_CODE.match_indices(text).nth(n).unwrap().0
+    }
+
+    #[test]
+    fn finds_definitions() {
+        let analysis = analysis();
+        let definition = |text, n| analysis.definition(offset_of(text, n)).map(|span| span.start);
+        assert_eq!(definition("larger", 1), Some(offset_of("larger", 1))); // `larger = a`
+        assert_eq!(definition("larger", 2), Some(offset_of("larger", 1)));
+        assert_eq!(definition("max(1", 0), Some(offset_of("max(", 0)));
+        assert_eq!(definition("x += 1", 0), Some(offset_of("x =", 0)));
+        assert_eq!(definition("putchar(72", 0), Some(offset_of("putchar", 0)));
+        assert_eq!(definition("a\n", 0), Some(offset_of("a,", 0)));
+        assert_eq!(definition("arg_count", 0), None); // a builtin
+        assert_eq!(definition("ret", 0), None);
+    }
+
+    #[test]
+    fn hovers_with_types_and_docs() {
+        let analysis = analysis();
+        let hover = |text, n| analysis.hover(offset_of(text, n));
+        assert_eq!(
+            hover("max(1", 0).unwrap(),
+            "```flick\nfn max(u8 a, u8 b) u8\n```\n\nReturns the larger of `a` and `b`\n\n(or `b`, if they're equal)"
+        );
+        assert_eq!(hover("b\n", 0).unwrap(), "```flick\nu8 b\n```");
+        assert_eq!(hover("putchar(72", 0).unwrap(), "```flick\nextern fn putchar(i8 c) i8\n```");
+        assert_eq!(hover("arg_count", 0).unwrap(), "```flick\nextern fn arg_count() u32\n```");
+        assert_eq!(hover("1, 2", 0), None);
+    }
+
+    #[test]
+    fn outlines_functions() {
+        let analysis = analysis();
+        let outline = analysis.outline();
+        let names: Vec<_> = outline.iter().map(|item| item.name.as_str()).collect();
+        assert_eq!(names, ["putchar", "max", "main"]);
+
+        let max = &outline[1];
+        assert_eq!(max.detail, "fn max(u8 a, u8 b) u8");
+        let children: Vec<_> = max.children.iter().map(|item| (item.name.as_str(), item.detail.as_str())).collect();
+        assert_eq!(children, [("a", "u8"), ("b", "u8"), ("larger", "u8")]);
+        let extent: String = SOURCE_CODE.chars().skip(max.extent.start).take(max.extent.end - max.extent.start).collect();
+        assert!(extent.starts_with("fn max("));
+        assert!(extent.ends_with("ret b\n}"));
+
+        let putchar = &outline[0];
+        assert_eq!(&SOURCE_CODE[putchar.extent.start..putchar.extent.end], "extern fn putchar(i8 c) i8");
+    }
+
+    #[test]
+    fn reports_diagnostics() {
+        let analysis = Analysis::new("bad.fl", "pub fn main() u8 {\n    ret y\n}\n".to_string());
+        let codes: Vec<_> = analysis.diagnostics.iter().map(|diagnostic| diagnostic.code).collect();
+        assert_eq!(codes, ["E0201"]);
+
+        // Lints run on code without errors
+        let analysis = Analysis::new("lint.fl", "pub fn main() u8 {\n    u8 x = 1\n    ret 0\n}\n".to_string());
+        let codes: Vec<_> = analysis.diagnostics.iter().map(|diagnostic| diagnostic.code).collect();
+        assert_eq!(codes, ["W0501"]);
+
+        // Names can't be looked up in code that doesn't parse
+        let analysis = Analysis::new("bad.fl", "pub fn main() u8 {\n    u8 x = 1\n    ret x +\n}\n".to_string());
+        assert_eq!(analysis.diagnostics.len(), 1);
+        assert_eq!(analysis.definition(37), None);
+        assert!(analysis.outline().is_empty());
+    }
+}
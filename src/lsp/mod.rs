@@ -0,0 +1,5 @@
+/// Module that checks a document for the language server, and finds what's at each position
+/// in it (see [analysis::Analysis]).
+pub mod analysis;
+/// Module that defines the language server itself, which talks to editors (see [server::Server]).
+pub mod server;
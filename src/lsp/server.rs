@@ -0,0 +1,422 @@
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+
+use serde_json::{json, Value};
+
+use super::analysis::{Analysis, OutlineItem};
+use crate::diagnostics::diagnostic::{Diagnostic, Severity};
+use crate::lexing::token::Span;
+use crate::scope_manager::SymbolKind;
+
+/// The JSON-RPC error code for a message that isn't valid JSON
+const PARSE_ERROR: i64 = -32700;
+/// The JSON-RPC error code for a request that can't be handled (e.g. after `shutdown`)
+const INVALID_REQUEST: i64 = -32600;
+/// The JSON-RPC error code for a request that the server doesn't support
+const METHOD_NOT_FOUND: i64 = -32601;
+/// The JSON-RPC error code for a request whose parameters are missing something
+const INVALID_PARAMS: i64 = -32602;
+
+/// A [Language Server Protocol][a] server for Flick, which editors talk to (with JSON-RPC
+/// messages) for errors and warnings as you type, go-to-definition, hover, and document
+/// outlines (see [Server::run]).
+///
+/// Each open document is [analyzed](Analysis) again whenever it changes. Editors send just the
+/// parts of it that changed, which are applied to the server's copy.
+///
+/// [a]: https://microsoft.github.io/language-server-protocol/
+#[derive(Debug, Default)]
+pub struct Server {
+    /// The open documents, by URI
+    documents: HashMap<String, Analysis>,
+    /// Whether the client has asked the server to shut down (which it does once it's told to
+    /// `exit`)
+    shut_down: bool,
+}
+
+/// What went wrong handling a request: a JSON-RPC error code, and a message
+type RequestError = (i64, String);
+
+impl Server {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Handles the messages read from `input` until the client says to `exit` (or `input`
+    /// ends), writing responses and diagnostics to `output`.
+    ///
+    /// Returns whether the client asked the server to shut down first, as it should: the
+    /// process should exit with 0 if so, and 1 otherwise.
+    pub fn run(&mut self, mut input: impl BufRead, mut output: impl Write) -> io::Result<bool> {
+        while let Some(content) = read_message(&mut input)? {
+            let message: Value = match serde_json::from_str(&content) {
+                Ok(message) => message,
+                Err(err) => {
+                    let error = json!({ "code": PARSE_ERROR, "message": err.to_string() });
+                    write_message(&mut output, &json!({ "jsonrpc": "2.0", "id": null, "error": error }))?;
+                    continue;
+                }
+            };
+            if message["method"] == "exit" {
+                break;
+            }
+            for reply in self.handle(&message) {
+                write_message(&mut output, &reply)?;
+            }
+        }
+        Ok(self.shut_down)
+    }
+
+    /// Handles one request or notification from the client, returning the messages to send
+    /// back: the response to a request, and any diagnostics to publish.
+    pub fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let Some(method) = message["method"].as_str() else {
+            return Vec::new(); // a response to a request from the server, which never sends any
+        };
+        let params = &message["params"];
+        let Some(id) = message.get("id") else {
+            tracing::debug!(method, "received a notification");
+            return self.notify(method, params);
+        };
+
+        tracing::debug!(method, %id, "received a request");
+        let response = match self.respond(method, params) {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => {
+                json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+            }
+        };
+        vec![response]
+    }
+
+    /// Returns the result of the request `method`.
+    fn respond(&mut self, method: &str, params: &Value) -> Result<Value, RequestError> {
+        if self.shut_down {
+            return Err((INVALID_REQUEST, "the server has been shut down".to_string()));
+        }
+        match method {
+            "initialize" => Ok(json!({
+                "capabilities": {
+                    // 2 is incremental sync: clients send just the parts of a document that changed
+                    "textDocumentSync": { "openClose": true, "change": 2 },
+                    "definitionProvider": true,
+                    "hoverProvider": true,
+                    "documentSymbolProvider": true,
+                },
+                "serverInfo": { "name": "flick", "version": env!("CARGO_PKG_VERSION") },
+            })),
+            "shutdown" => {
+                self.shut_down = true;
+                Ok(Value::Null)
+            }
+            "textDocument/definition" => {
+                let (uri, analysis, offset) = self.locate(params)?;
+                Ok(match analysis.definition(offset) {
+                    Some(span) => json!({ "uri": uri, "range": range(analysis.source_code(), span) }),
+                    None => Value::Null,
+                })
+            }
+            "textDocument/hover" => {
+                let (_, analysis, offset) = self.locate(params)?;
+                Ok(match analysis.hover(offset) {
+                    Some(hover) => json!({ "contents": { "kind": "markdown", "value": hover } }),
+                    None => Value::Null,
+                })
+            }
+            "textDocument/documentSymbol" => {
+                let analysis = self.document(params)?;
+                let outline = analysis.outline();
+                Ok(outline.iter().map(|item| document_symbol(analysis.source_code(), item)).collect())
+            }
+            _ => Err((METHOD_NOT_FOUND, format!("'{}' isn't supported", method))),
+        }
+    }
+
+    /// Handles the notification `method`, returning any diagnostics to publish.
+    fn notify(&mut self, method: &str, params: &Value) -> Vec<Value> {
+        let Some(uri) = params["textDocument"]["uri"].as_str() else { return Vec::new() };
+        match method {
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                self.documents.insert(uri.to_string(), Analysis::new(uri, text.to_string()));
+            }
+            "textDocument/didChange" => {
+                let Some(analysis) = self.documents.get(uri) else { return Vec::new() };
+                let mut source_code = analysis.source_code().to_string();
+                for change in params["contentChanges"].as_array().into_iter().flatten() {
+                    let text = change["text"].as_str().unwrap_or_default();
+                    match (offset(&source_code, &change["range"]["start"]), offset(&source_code, &change["range"]["end"])) {
+                        (Some(start), Some(end)) => source_code.replace_range(start..end.max(start), text),
+                        _ => source_code = text.to_string(),
+                    }
+                }
+                self.documents.insert(uri.to_string(), Analysis::new(uri, source_code));
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(uri);
+                return vec![publish_diagnostics(uri, "", &[])];
+            }
+            _ => return Vec::new(),
+        }
+        let analysis = &self.documents[uri];
+        vec![publish_diagnostics(uri, analysis.source_code(), &analysis.diagnostics)]
+    }
+
+    /// Returns the open document that `params` refers to.
+    fn document(&self, params: &Value) -> Result<&Analysis, RequestError> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        self.documents
+            .get(uri)
+            .ok_or_else(|| (INVALID_PARAMS, format!("'{}' isn't open", uri)))
+    }
+
+    /// Returns the document that `params` refers to (and its URI), and the offset of the
+    /// position in it that `params` refers to.
+    fn locate<'a>(&self, params: &'a Value) -> Result<(&'a Value, &Analysis, usize), RequestError> {
+        let analysis = self.document(params)?;
+        let offset = offset(analysis.source_code(), &params["position"])
+            .ok_or_else(|| (INVALID_PARAMS, "the request has no position".to_string()))?;
+        Ok((&params["textDocument"]["uri"], analysis, offset))
+    }
+}
+
+/// Reads one message's content, after its headers (like `Content-Length: 52\r\n\r\n`), or
+/// returns `None` if `input` has ended.
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut content_length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                content_length = value.trim().parse().ok();
+            }
+        }
+    }
+
+    let Some(content_length) = content_length else {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "a message has no 'Content-Length' header"));
+    };
+    let mut content = vec![0; content_length];
+    input.read_exact(&mut content)?;
+    String::from_utf8(content).map(Some).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+fn write_message(output: &mut impl Write, message: &Value) -> io::Result<()> {
+    let content = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", content.len(), content)?;
+    output.flush()
+}
+
+/// Returns the `textDocument/publishDiagnostics` notification for the document at `uri`.
+fn publish_diagnostics(uri: &str, source_code: &str, diagnostics: &[Diagnostic]) -> Value {
+    let diagnostics: Vec<_> = diagnostics
+        .iter()
+        .map(|diagnostic| {
+            let highlighted = |span: Option<Span>| {
+                let span = span.map(|span| Diagnostic::highlighted(span, source_code)).unwrap_or_default();
+                range(source_code, span)
+            };
+            let mut message = diagnostic.message.clone();
+            for help in &diagnostic.help {
+                message.push_str(&format!("\nhelp: {}", help));
+            }
+            let related: Vec<_> = diagnostic
+                .notes
+                .iter()
+                .map(|note| {
+                    let location = json!({ "uri": uri, "range": highlighted(note.span) });
+                    json!({ "location": location, "message": note.message })
+                })
+                .collect();
+            json!({
+                "range": highlighted(diagnostic.span),
+                "severity": match diagnostic.severity {
+                    Severity::Error => 1,
+                    Severity::Warning => 2,
+                },
+                "code": diagnostic.code,
+                "source": "flick",
+                "message": message,
+                "relatedInformation": related,
+            })
+        })
+        .collect();
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/publishDiagnostics",
+        "params": { "uri": uri, "diagnostics": diagnostics },
+    })
+}
+
+/// Returns an outline item as an LSP `DocumentSymbol`.
+fn document_symbol(source_code: &str, item: &OutlineItem) -> Value {
+    let children: Vec<_> = item.children.iter().map(|child| document_symbol(source_code, child)).collect();
+    json!({
+        "name": item.name,
+        "detail": item.detail,
+        // LSP's `SymbolKind`s for a function and a variable
+        "kind": match item.kind {
+            SymbolKind::Builtin | SymbolKind::Function => 12,
+            SymbolKind::Param | SymbolKind::Local => 13,
+        },
+        "range": range(source_code, item.extent),
+        "selectionRange": range(source_code, item.span),
+        "children": children,
+    })
+}
+
+fn range(source_code: &str, span: Span) -> Value {
+    json!({ "start": position(source_code, span.start), "end": position(source_code, span.end) })
+}
+
+/// Returns the LSP position of `offset` in `source_code`: its line, and its column in UTF-16
+/// code units (so a `é` is one, but an emoji is two), both starting at 0.
+fn position(source_code: &str, offset: usize) -> Value {
+    let before = &source_code[..offset.min(source_code.len())];
+    let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+    let line = before[..line_start].matches('\n').count();
+    let character: usize = before[line_start..].chars().map(char::len_utf16).sum();
+    json!({ "line": line, "character": character })
+}
+
+/// Returns the offset of an LSP `position` in `source_code` (the opposite of [position]), or
+/// `None` if it isn't a position. A position past the end of its line is at the end of the line.
+fn offset(source_code: &str, position: &Value) -> Option<usize> {
+    let line = position["line"].as_u64()? as usize;
+    let character = position["character"].as_u64()? as usize;
+    let line_start = match line {
+        0 => 0,
+        _ => match source_code.match_indices('\n').nth(line - 1) {
+            Some((newline, _)) => newline + 1,
+            None => return Some(source_code.len()),
+        },
+    };
+
+    let mut offset = line_start;
+    let mut units = 0;
+    for c in source_code[line_start..].chars().take_while(|&c| c != '\n') {
+        if units >= character {
+            break;
+        }
+        units += c.len_utf16();
+        offset += c.len_utf8();
+    }
+    Some(offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs a server on `messages`, returning what it sends back, and whether it was shut down.
+    fn run(messages: &[Value]) -> (Vec<Value>, bool) {
+        let mut input = Vec::new();
+        for message in messages {
+            write_message(&mut input, message).unwrap();
+        }
+        let mut output = Vec::new();
+        let shut_down = Server::new().run(&input[..], &mut output).unwrap();
+
+        let mut output = &output[..];
+        let mut replies = Vec::new();
+        while let Some(content) = read_message(&mut output).unwrap() {
+            replies.push(serde_json::from_str(&content).unwrap());
+        }
+        (replies, shut_down)
+    }
+
+    fn request(id: u64, method: &str, params: Value) -> Value {
+        json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })
+    }
+
+    fn notification(method: &str, params: Value) -> Value {
+        json!({ "jsonrpc": "2.0", "method": method, "params": params })
+    }
+
+    const URI: &str = "file:///project/main.fl";
+
+    fn at(line: u64, character: u64) -> Value {
+        json!({ "textDocument": { "uri": URI }, "position": { "line": line, "character": character } })
+    }
+
+    #[test]
+    fn serves_a_session() {
+        let source_code = "fn double(u8 n) u8 {\n    ret n * 2\n}\n\npub fn main() u8 {\n    ret double(x)\n}\n";
+        let (replies, shut_down) = run(&[
+            request(1, "initialize", json!({ "capabilities": {} })),
+            notification("initialized", json!({})),
+            notification(
+                "textDocument/didOpen",
+                json!({ "textDocument": { "uri": URI, "languageId": "flick", "version": 1, "text": source_code } }),
+            ),
+            request(2, "textDocument/definition", at(5, 9)),
+            request(3, "textDocument/hover", at(1, 8)),
+            // `x` becomes `2`, which fixes the error
+            notification(
+                "textDocument/didChange",
+                json!({
+                    "textDocument": { "uri": URI, "version": 2 },
+                    "contentChanges": [{
+                        "range": { "start": { "line": 5, "character": 15 }, "end": { "line": 5, "character": 16 } },
+                        "text": "2",
+                    }],
+                }),
+            ),
+            request(4, "textDocument/documentSymbol", json!({ "textDocument": { "uri": URI } })),
+            request(5, "textDocument/references", at(0, 3)),
+            request(6, "shutdown", Value::Null),
+            notification("exit", Value::Null),
+        ]);
+        assert!(shut_down);
+        assert_eq!(replies.len(), 8);
+
+        assert_eq!(replies[0]["id"], 1);
+        assert_eq!(replies[0]["result"]["capabilities"]["textDocumentSync"]["change"], 2);
+
+        let diagnostics = &replies[1]["params"]["diagnostics"];
+        assert_eq!(diagnostics.as_array().unwrap().len(), 1);
+        assert_eq!(diagnostics[0]["code"], "E0201");
+        assert_eq!(diagnostics[0]["severity"], 1);
+        assert_eq!(diagnostics[0]["range"]["start"], json!({ "line": 5, "character": 15 }));
+
+        let definition = &replies[2]["result"];
+        assert_eq!(definition["uri"], URI);
+        assert_eq!(definition["range"]["start"], json!({ "line": 0, "character": 3 }));
+        assert_eq!(replies[3]["result"]["contents"]["value"], "```flick\nu8 n\n```");
+
+        assert_eq!(replies[4]["params"]["diagnostics"], json!([]));
+
+        let symbols = replies[5]["result"].as_array().unwrap();
+        let names: Vec<_> = symbols.iter().map(|symbol| symbol["name"].as_str().unwrap()).collect();
+        assert_eq!(names, ["double", "main"]);
+        assert_eq!(symbols[0]["children"][0]["name"], "n");
+        assert_eq!(symbols[1]["range"]["end"], json!({ "line": 6, "character": 1 }));
+
+        assert_eq!(replies[6]["error"]["code"], METHOD_NOT_FOUND);
+        assert_eq!(replies[7], json!({ "jsonrpc": "2.0", "id": 6, "result": null }));
+    }
+
+    #[test]
+    fn exits_without_shutting_down() {
+        let (replies, shut_down) = run(&[notification("exit", Value::Null), request(1, "shutdown", Value::Null)]);
+        assert!(replies.is_empty());
+        assert!(!shut_down);
+    }
+
+    #[test]
+    fn converts_positions() {
+        let source_code = "ab\n\u{e9}\u{1f600}x\n";
+        assert_eq!(position(source_code, 9), json!({ "line": 1, "character": 3 }));
+        assert_eq!(offset(source_code, &json!({ "line": 1, "character": 3 })), Some(9));
+        assert_eq!(offset(source_code, &json!({ "line": 0, "character": 9 })), Some(2));
+        assert_eq!(offset(source_code, &json!({ "line": 9, "character": 0 })), Some(11));
+        assert_eq!(offset(source_code, &Value::Null), None);
+    }
+}
@@ -1,170 +1,1858 @@
-use std::fs::File;
-use std::io::Read;
+use std::ffi::OsString;
+use std::fmt::Write;
+use std::io::IsTerminal;
 use std::path::{PathBuf, Path};
-use std::process::Command;
+use std::process::{Command, ExitCode};
 
-use anyhow::Result;
-use clap::Parser as ClapParser;
+use anyhow::{bail, Context, Result};
+use clap::{ArgAction, Args, Parser as ClapParser, Subcommand, ValueEnum};
+use serde_json::json;
+use tracing_subscriber::filter::{EnvFilter, LevelFilter};
 
-use flick::{error::FlickError, Compiler, Lexer, Parser, Typer};
+use flick::{
+    ast::{Arena, Program}, coverage::{self, Profile}, profiling, cst::Cst, fix::{self, Fix}, highlight::{highlight_ansi, highlight_html}, print_dot, print_tree, project::{self, Project}, registry, rename::{self, RenameError}, stats::FileStats, token::{Span, Token}, typed_ast::TypedProgram,
+    format_source_with_policy, grammar, lex_sources, parse_sources, Backend, CBackend, CheckOptions, CompilationError, CompilerOptions, Diagnostic, DiagnosticSink, EmitKind,
+    IdentifierPolicy, Lexer, Lint, LintLevel, LintLevels, Linter, OptLevel, Parser, PhaseTimes, RelocModel, Severity, SourceMap, LINTS,
+};
+#[cfg(feature = "llvm")]
+use flick::{cache::{self, ObjectCache}, Compiler};
 
 /// A command line interface using [clap]
 #[derive(ClapParser)]
 #[command(author, version, about, long_about = None)]
 struct Cli {
-    /// Input path for source code
-    source_path: PathBuf,
+    #[command(subcommand)]
+    command: CliCommand,
 
-    /// Whether to print LLVM intermediate representation during compilation
-    #[arg(short, long)]
-    emit_ir: bool,
+    #[command(flatten)]
+    log: LogArgs,
+}
 
-    /// Output path for the executable
-    #[arg(short, long)]
-    output_path: Option<PathBuf>,
+/// The flags that decide how much `flick` logs (to stderr) about what it's doing, which every
+/// command takes
+#[derive(Args)]
+struct LogArgs {
+    /// Log what the compiler is doing: `-v` for each phase and file, `-vv` for each function too,
+    /// and `-vvv` for everything (`FLICK_LOG` can refine this, like `FLICK_LOG=flick::typing=trace`)
+    #[arg(short, long, action = ArgAction::Count, global = true)]
+    verbose: u8,
+
+    /// Don't log anything (errors and warnings in the program are still reported)
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+}
+
+impl LogArgs {
+    /// Returns the most detailed level of log messages to show (unless `FLICK_LOG` says
+    /// otherwise).
+    fn level(&self) -> LevelFilter {
+        match (self.quiet, self.verbose) {
+            (true, _) => LevelFilter::OFF,
+            (false, 0) => LevelFilter::WARN,
+            (false, 1) => LevelFilter::INFO,
+            (false, 2) => LevelFilter::DEBUG,
+            (false, _) => LevelFilter::TRACE,
+        }
+    }
+
+    /// Sends log messages to stderr, at the level given by `-v` or `--quiet`, with `FLICK_LOG`
+    /// (like `RUST_LOG`, e.g. `FLICK_LOG=debug` or `FLICK_LOG=flick::compilation=trace`) adding
+    /// to or overriding it.
+    fn init_logging(&self) {
+        let filter = EnvFilter::builder()
+            .with_default_directive(self.level().into())
+            .with_env_var("FLICK_LOG")
+            .from_env_lossy();
+        tracing_subscriber::fmt()
+            .with_env_filter(filter)
+            .with_writer(std::io::stderr)
+            .with_ansi(std::io::stderr().is_terminal())
+            .with_timer(tracing_subscriber::fmt::time::Uptime::default())
+            .init();
+    }
+}
+
+/// The commands that the CLI can run, like `flick build`
+#[derive(Subcommand)]
+enum CliCommand {
+    /// Compile a file into an executable (or an object file, with `--no-link`)
+    Build(BuildArgs),
+    /// Compile a file into an executable, then run it
+    Run(RunArgs),
+    /// Check a file for errors (and warnings), without generating any code
+    Check(CheckArgs),
+    /// Print the tokens that a file is made of
+    Lex(SourceArgs),
+    /// Print the abstract syntax tree of a file
+    Parse {
+        #[command(flatten)]
+        args: ParseArgs,
+
+        /// How to print the tree: `pretty` (indented, one node per line), `json`, `debug`
+        /// (Rust's `{:#?}` format, with every field), or `dot` (a Graphviz graph)
+        #[arg(long, value_enum, default_value_t = TreeFormat::Pretty)]
+        format: TreeFormat,
+    },
+    /// Check files for errors, and for code that's probably a mistake, like variables that are
+    /// never read (each lint can be configured in the `[lints]` table of a `flick.toml`)
+    Lint(CheckArgs),
+    /// Apply the fixes that errors and warnings suggest (like inserting a missing `}`, or removing
+    /// a variable that's never read) to files, and report the problems that are left
+    Fix(FixArgs),
+    /// Format files in place (or, with `--check`, check that they're formatted)
+    Fmt(FmtArgs),
+    /// Print files with syntax highlighting, for a terminal or a web page
+    Highlight {
+        #[command(flatten)]
+        source: SourceArgs,
+
+        /// How to highlight the files: `ansi` (with colors, for a terminal), or `html` (with each
+        /// token in a `<span>` whose class is its kind, like `keyword`, for a stylesheet to color)
+        #[arg(long, value_enum, default_value_t = HighlightFormat::Ansi)]
+        format: HighlightFormat,
+    },
+    /// Rename a function or a variable where it's declared and everywhere that it's used (and
+    /// nothing else with the same name), rewriting the file
+    Rename {
+        /// The file to rename it in
+        path: PathBuf,
 
-    /// Output path for the object file
+        /// What it's called now
+        old_name: String,
+
+        /// What to call it
+        new_name: String,
+
+        /// A line that it's declared or used on, if more than one thing in the file has its name
+        #[arg(long)]
+        line: Option<usize>,
+    },
+    /// Print statistics about files: their lines of code, tokens, functions, and comments
+    Stats(SourceArgs),
+    /// Print Flick's grammar in EBNF, with the function of the parser that parses each rule
+    Grammar,
+    /// Print a longer description of an error (or warning) code, like `E0201`, with examples
+    Explain {
+        /// The code to explain
+        code: String,
+    },
+    /// Create a new project: a `flick.toml`, and a `src/main.fl` to start from
+    Init {
+        /// The directory to create the project in (which is created if it doesn't exist)
+        #[arg(default_value = ".")]
+        path: PathBuf,
+
+        /// The name of the project, which the executable is named after (default: the name of
+        /// the directory)
+        #[arg(long)]
+        name: Option<String>,
+    },
+    /// Run a language server, which editors talk to over stdin and stdout for errors as you
+    /// type, go-to-definition, hover, and outlines
+    Lsp,
+    /// Work with the coverage profiles that programs built with `--coverage` write
+    Cov {
+        #[command(subcommand)]
+        command: CovCommand,
+    },
+}
+
+/// The subcommands of `flick cov`
+#[derive(Subcommand)]
+enum CovCommand {
+    /// Print each file in a profile with how many times each of its lines ran, and how many of
+    /// its lines ran at all
+    Report {
+        /// The profile, like `test.flcov`
+        profile: PathBuf,
+    },
+}
+
+/// The flags that every command which reads a source file takes
+#[derive(Args)]
+struct SourceArgs {
+    /// Input paths for source code: Flick files, or directories (whose `.fl` files are used),
+    /// which are all compiled together (default: the entry of the project that the current
+    /// directory is in, from its `flick.toml`)
+    #[arg(value_name = "SOURCE_PATHS")]
+    source_paths: Vec<PathBuf>,
+
+    /// Whether to only allow ASCII letters, digits, and `_` in identifiers (rather than any
+    /// Unicode letters)
     #[arg(long)]
+    ascii_identifiers: bool,
+
+    #[command(flatten)]
+    diagnostics: DiagnosticArgs,
+}
+
+/// The flags for `flick parse` (and every command after it, which parses the file first)
+#[derive(Args)]
+struct ParseArgs {
+    #[command(flatten)]
+    source: SourceArgs,
+
+    /// How deeply expressions and bodies can be nested (e.g. in parentheses)
+    #[arg(long, value_name = "LEVELS", default_value_t = Parser::DEFAULT_MAX_DEPTH)]
+    max_nesting_depth: usize,
+}
+
+/// The flags for `flick fmt`
+#[derive(Args)]
+struct FmtArgs {
+    #[command(flatten)]
+    source: SourceArgs,
+
+    /// Whether to only check that the files are formatted, without changing them: `flick fmt`
+    /// lists the files that aren't, and fails if there are any (e.g. for CI)
+    #[arg(long)]
+    check: bool,
+}
+
+/// The flags for `flick fix`
+#[derive(Args)]
+struct FixArgs {
+    #[command(flatten)]
+    check: CheckArgs,
+
+    /// Whether to only print the changes as a diff, without changing the files
+    #[arg(long)]
+    dry_run: bool,
+}
+
+/// The flags for `flick check` (and for building, which checks the file first)
+#[derive(Args)]
+struct CheckArgs {
+    #[command(flatten)]
+    parse: ParseArgs,
+
+    #[command(flatten)]
+    lints: LintArgs,
+
+    /// Whether to report how long each phase of the compiler (and each LLVM pass) took, and how
+    /// many lines, tokens, and AST nodes there were
+    #[arg(long, visible_alias = "timings")]
+    time_passes: bool,
+}
+
+/// The flags for `flick build`
+#[derive(Args)]
+struct BuildArgs {
+    #[command(flatten)]
+    check: CheckArgs,
+
+    /// Print the LLVM intermediate representation before and/or after optimization, like
+    /// `--emit-ir=pre` (`-e` alone prints both, i.e. `--emit-ir=pre,post`)
+    #[arg(
+        short,
+        long,
+        value_enum,
+        value_delimiter = ',',
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_values = ["pre", "post"]
+    )]
+    emit_ir: Vec<IrStage>,
+
+    /// Print only the IR of the function with this name (before and after optimization, unless
+    /// `--emit-ir` says otherwise)
+    #[arg(long = "print-ir-fn", value_name = "NAME")]
+    print_ir_func: Option<String>,
+
+    /// Output path for the executable (or for the object file, with `--no-link`); with more
+    /// than one kind of output, the others are named after it
+    #[arg(short = 'o', long = "output")]
+    output_path: Option<PathBuf>,
+
+    /// Output path for the object file, which is kept next to the executable (deprecated: use
+    /// `--emit=obj,exe`, which names it after the executable)
+    #[arg(long, value_name = "PATH", hide = true)]
     object_output_path: Option<PathBuf>,
 
-    /// Path to linker (default: 'gcc')
+    /// Kinds of output to write, like `--emit=asm,exe`: any of `tokens`, `ast`, `llvm-ir`,
+    /// `asm`, `obj`, and `exe` (default: `exe`)
+    #[arg(long, value_enum, value_delimiter = ',', value_name = "KINDS")]
+    emit: Vec<OutputKind>,
+
+    /// Path to linker (default: 'gcc', or 'wasm-ld' for WebAssembly targets, or 'link.exe' for
+    /// Windows MSVC targets)
     #[arg(long)]
     linker_path: Option<PathBuf>,
 
-    /// Whether to just compile without running the linker to generate an executable
-    #[arg(long)]
+    /// Whether to just compile without running the linker to generate an executable (the same
+    /// as `--emit=obj`)
+    #[arg(long, conflicts_with = "emit")]
     no_link: bool,
+
+    /// Libraries to link with, like `-l m` for `libm` (besides the ones that `extern`
+    /// functions are declared in, like `extern "m" fn cos(...)`)
+    #[arg(short = 'l', long = "library", value_name = "NAME")]
+    libraries: Vec<String>,
+
+    /// Directories to look for libraries in, before the linker's own ones, like `-L /opt/lib`
+    #[arg(short = 'L', long = "library-dir", value_name = "DIR")]
+    library_dirs: Vec<PathBuf>,
+
+    /// Arguments to pass to the linker as they are, after all of the others, like
+    /// `--link-arg=-Wl,-rpath,/opt/lib`
+    #[arg(long = "link-arg", value_name = "ARG", allow_hyphen_values = true)]
+    link_args: Vec<String>,
+
+    /// What to name the output after when there's no `-o` (without an extension), if it's not
+    /// the first source file: the project's output, from its `flick.toml`
+    #[arg(skip)]
+    project_output: Option<PathBuf>,
+
+    /// Optimization level: one of `-O0`, `-O1`, `-O2`, `-O3`, `-Os`, or `-Oz` (smallest code)
+    /// (default: `-O1`)
+    #[arg(short = 'O')]
+    opt_level: Option<OptLevel>,
+
+    /// Target triple to compile for, like `aarch64-apple-darwin` (default: this machine's triple)
+    #[arg(long = "target")]
+    target_triple: Option<String>,
+
+    /// Relocation model: one of `static`, `pic`, or `dynamic-no-pic` (default: `pic`, except for
+    /// WebAssembly)
+    #[arg(long = "reloc", default_value_t = RelocModel::default())]
+    reloc_model: RelocModel,
+
+    /// CPU to generate code for, like `skylake`, or `native` for this machine's CPU (default: `generic`)
+    #[arg(long = "cpu")]
+    target_cpu: Option<String>,
+
+    /// Comma-separated CPU features to enable/disable, like `+avx2,-sse4.1`
+    #[arg(long = "features")]
+    target_features: Option<String>,
+
+    /// Whether to emit debug info, so that debuggers like gdb and lldb can see Flick functions
+    #[arg(short = 'g')]
+    debug_info: bool,
+
+    /// Whether to keep value names (like `%x.addr`) in the LLVM IR, e.g. for debugging with `-e`
+    #[arg(long)]
+    readable_ir: bool,
+
+    /// Whether to emit LLVM bitcode and optimize at link time (the default linker becomes `clang`)
+    #[arg(long)]
+    lto: bool,
+
+    /// Whether to keep the optimizer from vectorizing code (which it does at `-O2` and `-O3`)
+    #[arg(long)]
+    no_vectorize: bool,
+
+    /// Whether to keep the optimizer from unrolling loops
+    #[arg(long)]
+    no_unroll: bool,
+
+    /// Exact optimization pipeline to run instead of the `-O` preset, in LLVM's syntax (like
+    /// `"sroa,instcombine,simplifycfg"`)
+    #[arg(long)]
+    passes: Option<String>,
+
+    /// Code generator to use: `llvm`, or `c` to generate C source code (which is then compiled
+    /// by the linker, i.e. `gcc` by default)
+    #[arg(long, value_enum, default_value_t = BackendKind::Llvm)]
+    backend: BackendKind,
+
+    /// Whether to strip symbols from the executable and let the linker drop unused sections
+    #[arg(long)]
+    strip: bool,
+
+    /// Whether to count how many times each statement runs, for source coverage: the program
+    /// writes the counts to `<name>.flcov` in the directory it runs in when it exits (see
+    /// `flick cov report`)
+    #[arg(long)]
+    coverage: bool,
+
+    /// Whether to make every function call `__flick_enter(name)` when it starts and
+    /// `__flick_exit()` when it returns, for profiling; the program defines the hooks (as `pub`
+    /// functions), or they're linked in, like from a C library given with `-l`
+    #[arg(long)]
+    instrument_functions: bool,
+
+    /// Whether to compile every file again, rather than reusing what earlier builds compiled
+    /// (see `--cache-dir`)
+    #[arg(long)]
+    no_cache: bool,
+
+    /// Directory to cache each file's compiled code in, so that a build only compiles the files
+    /// that changed (default: `flick` in the user's cache directory, like `~/.cache/flick`)
+    #[arg(long, value_name = "DIR")]
+    cache_dir: Option<PathBuf>,
+}
+
+/// The flags for `flick run`
+#[derive(Args)]
+struct RunArgs {
+    #[command(flatten)]
+    build: BuildArgs,
+
+    /// Arguments to pass to the program, after a `--` (like `flick run test.fl -- 1 2 3`)
+    #[arg(last = true)]
+    args: Vec<OsString>,
+}
+
+/// The flags that control how errors and warnings are reported
+#[derive(Args)]
+struct DiagnosticArgs {
+    /// How to report errors and warnings: `human` (to stderr), or `json` (one object per line,
+    /// to stdout)
+    #[arg(long, value_enum, default_value_t = MessageFormat::Human)]
+    message_format: MessageFormat,
+
+    /// Whether to color errors and warnings: `auto` colors them if stderr is a terminal (and the
+    /// `NO_COLOR` environment variable isn't set)
+    #[arg(long, value_enum, value_name = "WHEN", default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
+
+    /// How many errors to report before leaving the rest out (0 reports them all)
+    #[arg(long, value_name = "COUNT", default_value_t = 20)]
+    error_limit: usize,
+}
+
+/// The flags that silence warnings or turn them into errors (see [LintLevels])
+#[derive(Args)]
+struct LintArgs {
+    /// Lints to report as warnings, even with `--deny-warnings` (like `-W unused-functions`)
+    #[arg(short = 'W', long = "warn", value_name = "LINT", value_parser = parse_lint)]
+    warn: Vec<&'static Lint>,
+
+    /// Lints to not report at all (like `-A shadowed-variables`)
+    #[arg(short = 'A', long = "allow", value_name = "LINT", value_parser = parse_lint)]
+    allow: Vec<&'static Lint>,
+
+    /// Lints to report as errors (like `-D shadowed-variables`)
+    #[arg(short = 'D', long = "deny", value_name = "LINT", value_parser = parse_lint)]
+    deny: Vec<&'static Lint>,
+
+    /// Whether to report every warning as an error (except for lints given to `-W` or `-A`)
+    #[arg(long)]
+    deny_warnings: bool,
+
+    /// The levels of lints from the project's `flick.toml`, which the flags override
+    #[arg(skip)]
+    config_levels: Vec<(&'static Lint, LintLevel)>,
 }
 
-impl Cli {
-    /// Retrieves the provided executable output path (returns a default if none provided)
+/// The code generators that the compiler can use (see [Backend])
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum BackendKind {
+    /// Generate machine code with LLVM
     ///
-    /// Note that the default executable output path for a file like `test.fl` is `test`.
-    fn get_executable_output_path(&self) -> PathBuf {
-        match &self.output_path {
-            Some(path) => path.clone(),
-            None => {
-                let mut path = self.source_path.clone();
-                path.set_extension("");
-                path
+    /// See [Compiler].
+    Llvm,
+    /// Generate C source code
+    ///
+    /// See [CBackend].
+    C,
+}
+
+/// The ways that errors and warnings can be reported (see `--message-format`)
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum MessageFormat {
+    /// Rendered for people to read
+    ///
+    /// See [Diagnostic::render].
+    Human,
+    /// As JSON objects, for editors and other tools
+    ///
+    /// See [diagnostic_to_json].
+    Json,
+}
+
+/// The ways that `flick parse` can print abstract syntax trees (see `--format`)
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum TreeFormat {
+    /// As an indented tree
+    ///
+    /// See [print_tree].
+    Pretty,
+    /// As JSON, with every field, for other tools
+    Json,
+    /// With Rust's debug formatting
+    ///
+    /// See [Debug].
+    Debug,
+    /// As a Graphviz graph, e.g. for `dot -Tsvg`
+    ///
+    /// See [print_dot].
+    Dot,
+}
+
+/// The ways that `flick highlight` can print source code (see `--format`)
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum HighlightFormat {
+    /// With ANSI escape codes
+    ///
+    /// See [highlight_ansi].
+    Ansi,
+    /// As HTML
+    ///
+    /// See [highlight_html].
+    Html,
+}
+
+/// When errors and warnings are colored (see `--color`)
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ColorChoice {
+    /// When they're printed to a terminal, unless `NO_COLOR` is set
+    Auto,
+    Always,
+    Never,
+}
+
+/// The points during compilation at which the IR can be printed (see `--emit-ir`)
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum IrStage {
+    /// Right after code generation, before any optimization
+    Pre,
+    /// After optimization
+    Post,
+}
+
+/// The kinds of files that the compiler can write (see `--emit`), from the earliest stage of
+/// compilation to the last
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+enum OutputKind {
+    /// The tokens of the source files, like `test.tokens` (as `flick lex` prints them)
+    Tokens,
+    /// The abstract syntax tree, like `test.ast` (as `flick parse` prints it)
+    Ast,
+    /// The LLVM IR after optimization, like `test.ll`
+    LlvmIr,
+    /// Assembly, like `test.s`
+    #[value(name = "asm")]
+    Assembly,
+    /// An object file, like `test.o`
+    #[value(name = "obj")]
+    Object,
+    /// A linked executable, like `test`
+    #[value(name = "exe")]
+    Executable,
+}
+
+impl OutputKind {
+    /// Returns the file extension used for this kind of output (without a leading dot).
+    ///
+    /// Note that WebAssembly executables are `.wasm` modules, that Windows MSVC uses `.obj` and
+    /// `.exe`, and that the C backend's "object files" are C source files.
+    fn extension(&self, args: &BuildArgs) -> &'static str {
+        let options = args.get_compiler_options();
+        match self {
+            Self::Tokens => "tokens",
+            Self::Ast => "ast",
+            Self::LlvmIr => "ll",
+            Self::Assembly => "s",
+            Self::Object if args.backend == BackendKind::C => "c",
+            Self::Object if options.targets_msvc() => "obj",
+            Self::Object => "o",
+            Self::Executable if options.targets_wasm() => "wasm",
+            Self::Executable if options.targets_msvc() => "exe",
+            Self::Executable => "",
+        }
+    }
+}
+
+impl CliCommand {
+    /// Returns the flags that say which files the command reads, if it reads any.
+    fn source_args_mut(&mut self) -> Option<&mut SourceArgs> {
+        match self {
+            Self::Build(args) | Self::Run(RunArgs { build: args, .. }) => Some(&mut args.check.parse.source),
+            Self::Check(args) | Self::Lint(args) | Self::Fix(FixArgs { check: args, .. }) => {
+                Some(&mut args.parse.source)
             }
+            Self::Lex(args) | Self::Stats(args) => Some(args),
+            Self::Parse { args, .. } => Some(&mut args.source),
+            Self::Fmt(args) => Some(&mut args.source),
+            Self::Highlight { source, .. } => Some(source),
+            Self::Rename { .. } | Self::Grammar | Self::Explain { .. } | Self::Init { .. } | Self::Lsp | Self::Cov { .. } => None,
         }
     }
 
-    /// Retrieves the provided object output path (returns a default if none provided)
+    /// Fills in whatever the command line left out from `project`'s `flick.toml`: the files to
+    /// compile, the levels of lints, and the defaults for `flick build`'s flags.
+    fn apply_project(&mut self, project: &Project) {
+        if let Some(source_args) = self.source_args_mut() {
+            source_args.source_paths.push(project.entry_path());
+        }
+        let lint_args = match self {
+            Self::Build(args) | Self::Run(RunArgs { build: args, .. }) => Some(&mut args.check.lints),
+            Self::Check(args) | Self::Lint(args) | Self::Fix(FixArgs { check: args, .. }) => Some(&mut args.lints),
+            _ => None,
+        };
+        if let Some(lint_args) = lint_args {
+            lint_args.config_levels = project.config.lints.clone();
+        }
+        let args = match self {
+            Self::Build(args) | Self::Run(RunArgs { build: args, .. }) => args,
+            _ => return,
+        };
+        let build = &project.config.build;
+        args.project_output = Some(project.output_path());
+        args.opt_level = args.opt_level.or(build.opt_level);
+        if args.target_triple.is_none() {
+            args.target_triple = build.target.clone();
+        }
+        if args.cache_dir.is_none() {
+            args.cache_dir = project.cache_dir();
+        }
+        // The project's libraries come first, since they're less specific
+        args.libraries.splice(0..0, build.libraries.iter().cloned());
+    }
+}
+
+impl SourceArgs {
+    /// Returns which characters identifiers can contain (see `--ascii-identifiers`).
+    fn identifier_policy(&self) -> IdentifierPolicy {
+        match self.ascii_identifiers {
+            true => IdentifierPolicy::Ascii,
+            false => IdentifierPolicy::Unicode,
+        }
+    }
+
+    /// Replaces each directory in the source paths with the `.fl` files in it (and in the
+    /// directories in it), in alphabetical order.
+    fn expand_directories(&mut self) -> Result<()> {
+        let mut source_paths = Vec::new();
+        for path in &self.source_paths {
+            match path.is_dir() {
+                true => {
+                    let files_before = source_paths.len();
+                    find_source_files(path, &mut source_paths)?;
+                    if source_paths.len() == files_before {
+                        bail!("there are no '.fl' files in '{}'", path.display());
+                    }
+                }
+                false => source_paths.push(path.clone()),
+            }
+        }
+        self.source_paths = source_paths;
+        Ok(())
+    }
+}
+
+impl ParseArgs {
+    /// Returns the settings for lexing and parsing that the flags give.
+    fn check_options(&self) -> CheckOptions {
+        CheckOptions { identifier_policy: self.source.identifier_policy(), max_nesting_depth: self.max_nesting_depth }
+    }
+}
+
+impl DiagnosticArgs {
+    /// Returns `true` if errors and warnings should be colored (see `--color`).
     ///
-    /// Note that the default object output path for a file like `test.fl` is `test.o`.
-    fn get_object_output_path(&self) -> PathBuf {
-        match &self.object_output_path {
-            Some(path) => path.clone(),
-            None => {
-                let mut path = self.source_path.clone();
-                path.set_extension("o");
-                path
+    /// Following <https://no-color.org>, a `NO_COLOR` environment variable that isn't empty turns
+    /// colors off, unless they're asked for with `--color=always`.
+    fn uses_color(&self) -> bool {
+        match self.color {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                let no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+                self.message_format == MessageFormat::Human && !no_color && std::io::stderr().is_terminal()
+            }
+        }
+    }
+}
+
+impl LintArgs {
+    /// Collects the `-W`, `-A`, `-D`, and `--deny-warnings` flags (and the levels from the
+    /// project's `flick.toml`, for lints that none of the flags mention) into [LintLevels].
+    ///
+    /// A lint given to more than one of `-W`, `-A`, and `-D` gets the strictest level.
+    fn get_lint_levels(&self) -> LintLevels {
+        let mut levels = LintLevels::default();
+        levels.set_deny_warnings(self.deny_warnings);
+        for &(lint, level) in &self.config_levels {
+            levels.set_from_config(lint, level);
+        }
+        for (lints, level) in [(&self.allow, LintLevel::Allow), (&self.warn, LintLevel::Warn), (&self.deny, LintLevel::Deny)] {
+            for lint in lints {
+                levels.set(lint, level);
             }
         }
+        levels
+    }
+}
+
+impl BuildArgs {
+    /// Returns the path of the first file to compile, which the output is named after.
+    fn source_path(&self) -> &Path {
+        &self.check.parse.source.source_paths[0]
+    }
+
+    /// Returns the kinds of files that the compiler should write, in the order that they're
+    /// made (see `--emit`, `--no-link`, and `--object-output-path`).
+    fn get_output_kinds(&self) -> Vec<OutputKind> {
+        let mut kinds = match (self.emit.is_empty(), self.no_link) {
+            (false, _) => self.emit.clone(),
+            (true, true) => vec![OutputKind::Object],
+            (true, false) => vec![OutputKind::Executable],
+        };
+        if self.object_output_path.is_some() {
+            kinds.push(OutputKind::Object);
+        }
+        kinds.sort();
+        kinds.dedup();
+        kinds
+    }
+
+    /// Returns `true` if the compiler should write `kind` of file.
+    fn emits(&self, kind: OutputKind) -> bool {
+        self.get_output_kinds().contains(&kind)
+    }
+
+    /// Retrieves the path that the compiler's final output (the last kind of file it writes,
+    /// like the executable) should be written to: the provided output path, or a default if
+    /// none was provided.
+    ///
+    /// Note that the default output path for a file like `test.fl` is the input stem with the
+    /// extension of the output kind; that is, `test` for executables and `test.o` for object files.
+    /// With more than one input, it's named after the first file, and in a project, it's named
+    /// after the project's output.
+    fn get_output_path(&self) -> PathBuf {
+        let final_kind = *self.get_output_kinds().last().unwrap();
+        self.get_output_path_for(final_kind)
+    }
+
+    /// Retrieves the path that `kind` of file should be written to. The final output goes to
+    /// the output path (see [BuildArgs::get_output_path]), and every other kind of file is
+    /// named after it, with its own extension (like `test.s` next to `test`), unless it's an
+    /// object file with `--object-output-path`.
+    fn get_output_path_for(&self, kind: OutputKind) -> PathBuf {
+        if let (OutputKind::Object, Some(path)) = (kind, &self.object_output_path) {
+            return path.clone();
+        }
+        let is_final = self.get_output_kinds().last() == Some(&kind);
+        let mut path = match (&self.output_path, &self.project_output) {
+            (Some(path), _) if is_final => return path.clone(),
+            (Some(path), _) | (None, Some(path)) => path.clone(),
+            (None, None) => self.source_path().to_path_buf(),
+        };
+        path.set_extension(kind.extension(self));
+        path
+    }
+
+    /// Retrieves the path that the object file should be written to.
+    ///
+    /// If the object file is one of the outputs, this is its output path. Otherwise, the object
+    /// file is an intermediate artifact that sits next to the executable (e.g. `test.o` next to
+    /// `test`), which is removed once it's linked.
+    fn get_object_output_path(&self) -> PathBuf {
+        if self.emits(OutputKind::Object) {
+            return self.get_output_path_for(OutputKind::Object);
+        }
+        let mut path = self.get_output_path_for(OutputKind::Executable).into_os_string();
+        path.push(".");
+        path.push(OutputKind::Object.extension(self));
+        PathBuf::from(path)
     }
 
     /// Retrieves the provided linker path (returns a default if none provided)
     ///
-    /// Note that the default linker path is `gcc`.
+    /// Note that the default linker path is `gcc`, or `wasm-ld` when targeting WebAssembly, or
+    /// `clang` with `--lto` (since `gcc` can't read LLVM bitcode).
+    ///
+    /// For Windows MSVC targets, the default is `link.exe` on Windows, and `lld-link` (LLVM's
+    /// drop-in replacement for it) when cross-compiling or with `--lto`.
     fn get_linker_path(&self) -> PathBuf {
+        let options = self.get_compiler_options();
         match &self.linker_path {
             Some(path) => path.clone(),
+            None if options.targets_wasm() => PathBuf::from("wasm-ld"),
+            None if options.targets_msvc() && cfg!(windows) && !self.lto => PathBuf::from("link.exe"),
+            None if options.targets_msvc() => PathBuf::from("lld-link"),
+            None if self.lto => PathBuf::from("clang"),
             None => PathBuf::from("gcc"),
         }
     }
+
+    /// Returns the extra arguments that the linker needs for the current target.
+    ///
+    /// For WebAssembly, there's no `_start` entry point (the host calls exported functions
+    /// directly), and `extern` functions are imports that the host provides at load time.
+    ///
+    /// Otherwise, unless the code is position-independent (which it is by default), the object
+    /// file may contain absolute relocations (e.g. for string literals), so it can't be linked
+    /// into a PIE.
+    ///
+    /// With `--lto`, `clang` is told to optimize the bitcode that it's given (`wasm-ld` does
+    /// this for bitcode inputs on its own).
+    ///
+    /// With `--strip`, the linker removes symbols and unused sections from the executable.
+    ///
+    /// MSVC-style linkers take `/FLAG`s instead, and need to be told to link in the C runtime,
+    /// which calls `main`.
+    fn get_linker_args(&self) -> Vec<&'static str> {
+        if self.get_compiler_options().targets_msvc() {
+            let mut args = vec!["/NOLOGO", "/SUBSYSTEM:CONSOLE", "/DEFAULTLIB:libcmt"];
+            if self.debug_info {
+                args.push("/DEBUG");
+            }
+            if self.strip {
+                args.extend(["/OPT:REF", "/OPT:ICF"]);
+            }
+            return args;
+        }
+
+        let options = self.get_compiler_options();
+        let mut args = match (options.targets_wasm(), options.effective_reloc_model()) {
+            (true, _) => vec!["--no-entry", "--allow-undefined"],
+            (false, RelocModel::Pic) => vec![],
+            (false, _) => vec!["-no-pie"],
+        };
+        if self.lto && !options.targets_wasm() {
+            args.push("-flto");
+        }
+        if self.strip {
+            match options.targets_wasm() {
+                true => args.extend(["--strip-all", "--gc-sections"]),
+                // Apple's linker calls it dead stripping, and has no flag for garbage collection
+                false if self.targets_apple() => args.extend(["-s", "-Wl,-dead_strip"]),
+                false => args.extend(["-s", "-Wl,--gc-sections"]),
+            }
+        }
+        args
+    }
+
+    /// Returns `true` if the executable is for an Apple platform (like macOS), whose linker
+    /// takes different flags than GNU-style linkers do.
+    fn targets_apple(&self) -> bool {
+        match &self.target_triple {
+            Some(triple) => triple.contains("-apple-"),
+            None => cfg!(target_vendor = "apple"),
+        }
+    }
+
+    /// Returns the command that links the object files at `object_paths` into an executable at
+    /// `executable_path` (and with the libraries given by `-l`, and then the rest of
+    /// `libraries`, which come after them, since GNU-style linkers only use a library for the
+    /// files before it).
+    fn get_linker_command(&self, object_paths: &[PathBuf], libraries: &[&str], executable_path: &Path) -> Command {
+        let mut command = Command::new(self.get_linker_path());
+        command.args(object_paths);
+        let targets_msvc = self.get_compiler_options().targets_msvc();
+        match targets_msvc {
+            true => {
+                let mut output_arg = OsString::from("/OUT:");
+                output_arg.push(executable_path);
+                command.arg(output_arg)
+            }
+            false => command.arg("-o").arg(executable_path),
+        };
+        command.args(self.get_linker_args());
+        for library_dir in &self.library_dirs {
+            match targets_msvc {
+                true => {
+                    let mut library_dir_arg = OsString::from("/LIBPATH:");
+                    library_dir_arg.push(library_dir);
+                    command.arg(library_dir_arg)
+                }
+                false => command.arg("-L").arg(library_dir),
+            };
+        }
+        let program_libraries = libraries.iter().filter(|library| !self.libraries.iter().any(|given| given == *library));
+        for library in self.libraries.iter().map(String::as_str).chain(program_libraries.copied()) {
+            match targets_msvc {
+                true => command.arg(format!("{}.lib", library)),
+                false => command.arg(format!("-l{}", library)),
+            };
+        }
+        command.args(&self.link_args);
+        command
+    }
+
+    /// Returns the cache that the compiled code of each file is kept in (see `--cache-dir`), or
+    /// `None` if the build shouldn't use it: with `--no-cache`, or when the build writes (or
+    /// prints) anything but an executable, since that's all that's cached.
+    #[cfg(feature = "llvm")]
+    fn get_object_cache(&self) -> Option<ObjectCache> {
+        let uses_cache = !self.no_cache
+            && self.backend == BackendKind::Llvm
+            && self.get_output_kinds() == [OutputKind::Executable]
+            && self.emit_ir.is_empty()
+            && self.print_ir_func.is_none();
+        uses_cache.then(|| ObjectCache::new(self.cache_dir.clone().unwrap_or_else(ObjectCache::default_dir)))
+    }
+
+    /// Returns `true` if the IR should be printed at `stage` (see `--emit-ir` and `--print-ir-fn`).
+    fn prints_ir_at(&self, stage: IrStage) -> bool {
+        match self.emit_ir.is_empty() {
+            true => self.print_ir_func.is_some(),
+            false => self.emit_ir.contains(&stage),
+        }
+    }
+
+    /// Collects the flags that configure code generation into [CompilerOptions].
+    fn get_compiler_options(&self) -> CompilerOptions {
+        CompilerOptions {
+            opt_level: self.opt_level.unwrap_or_default(),
+            target_triple: self.target_triple.clone(),
+            reloc_model: self.reloc_model,
+            target_cpu: self.target_cpu.clone(),
+            target_features: self.target_features.clone(),
+            emit_kind: match self.lto {
+                true => EmitKind::Bitcode,
+                false => EmitKind::Object,
+            },
+            lto: self.lto,
+            debug_info: self.debug_info,
+            source_file_name: self
+                .source_path()
+                .file_name()
+                .map(|file_name| file_name.to_string_lossy().into_owned()),
+            readable_ir: self.readable_ir,
+            disable_vectorization: self.no_vectorize,
+            disable_loop_unrolling: self.no_unroll,
+            passes: self.passes.clone(),
+            time_passes: self.check.time_passes,
+        }
+    }
 }
 
-/// Runs the command line interface for the compiler; see [Cli] for details
-fn main() -> Result<()> {
+/// Reports errors and warnings in the format given by `--message-format`, counting them for the
+/// summary at the end (see [Reporter::finish])
+struct Reporter<'a> {
+    args: &'a DiagnosticArgs,
+    /// The files being compiled, which diagnostics point into
+    sources: &'a SourceMap,
+    lint_levels: LintLevels,
+    /// Whether to color what's reported (see `--color`)
+    colored: bool,
+    /// How many errors were found (including any past `--error-limit` that weren't reported)
+    errors: usize,
+    warnings: usize,
+    /// How many of the problems reported have a fix that `flick fix` can apply
+    fixable: usize,
+    /// The fixes of the problems found, which `flick fix` collects instead of reporting them, or
+    /// `None` if problems are reported whether they can be fixed or not
+    fixes: Option<Vec<Fix>>,
+}
+
+impl<'a> Reporter<'a> {
+    /// Returns a reporter for diagnostics in `sources`, the files given by `args`, that reports
+    /// warnings at `lint_levels`.
+    fn new(args: &'a SourceArgs, sources: &'a SourceMap, lint_levels: LintLevels) -> Self {
+        Self {
+            args: &args.diagnostics,
+            sources,
+            lint_levels,
+            colored: args.diagnostics.uses_color(),
+            errors: 0,
+            warnings: 0,
+            fixable: 0,
+            fixes: None,
+        }
+    }
+
+    /// Prints a summary of what was reported (for people; not with `--message-format=json`), like
+    /// `error: aborting due to 2 previous errors; 1 warning emitted`, and returns the exit code:
+    /// a failure if there were any errors.
+    fn finish(&self) -> ExitCode {
+        let plural = |count: usize, noun: &str| match count {
+            1 => format!("1 {}", noun),
+            _ => format!("{} {}s", count, noun),
+        };
+        let (severity, mut summary) = match self.errors {
+            0 => (Severity::Warning, format!("warning: {} emitted", plural(self.warnings, "warning"))),
+            _ => (Severity::Error, format!("error: aborting due to {}", plural(self.errors, "previous error"))),
+        };
+        match self.args.error_limit {
+            0 => {}
+            limit if self.errors <= limit => {}
+            1 => summary.push_str(" (only the first was shown)"),
+            limit => summary.push_str(&format!(" (only the first {} were shown)", limit)),
+        }
+        if self.errors > 0 && self.warnings > 0 {
+            summary.push_str(&format!("; {} emitted", plural(self.warnings, "warning")));
+        }
+
+        if self.args.message_format == MessageFormat::Human && self.errors + self.warnings > 0 {
+            eprintln!("{}", severity.paint(&summary, self.colored));
+            match self.fixable {
+                0 => {}
+                1 => eprintln!("help: run 'flick fix' to apply 1 suggested fix"),
+                fixable => eprintln!("help: run 'flick fix' to apply {} suggested fixes", fixable),
+            }
+        }
+        match self.errors {
+            0 => ExitCode::SUCCESS,
+            _ => ExitCode::from(EXIT_ERRORS),
+        }
+    }
+}
+
+impl DiagnosticSink for Reporter<'_> {
+    /// Reports `diagnostic`, at the level that `-W`, `-A`, `-D`, and `--deny-warnings` give it if
+    /// it's a warning. Errors past `--error-limit` are counted, but not reported.
+    fn report(&mut self, diagnostic: Diagnostic) {
+        let diagnostic = match self.lint_levels.apply(diagnostic) {
+            Some(diagnostic) => diagnostic,
+            None => return,
+        };
+        if let (Some(fixes), Some(fix)) = (&mut self.fixes, &diagnostic.fix) {
+            fixes.push(fix.clone());
+            return;
+        }
+        self.fixable += usize::from(diagnostic.fix.is_some());
+        match diagnostic.severity {
+            Severity::Error => {
+                self.errors += 1;
+                if self.args.error_limit != 0 && self.errors > self.args.error_limit {
+                    return;
+                }
+            }
+            Severity::Warning => self.warnings += 1,
+        }
+
+        match self.args.message_format {
+            MessageFormat::Human if self.colored => eprint!("{}", diagnostic.render_colored(self.sources)),
+            MessageFormat::Human => eprint!("{}", diagnostic.render(self.sources)),
+            MessageFormat::Json => println!("{}", diagnostic_to_json(&diagnostic, self.sources)),
+        }
+    }
+}
+
+/// The exit code when the program being compiled has errors (or fails to link)
+const EXIT_ERRORS: u8 = 1;
+/// The exit code when `flick` is used wrong, or can't read or write a file (clap also exits with
+/// this for invalid flags)
+const EXIT_USAGE: u8 = 2;
+/// The exit code when `flick` itself has a bug (it panicked), like `rustc`'s
+const EXIT_INTERNAL_ERROR: u8 = 101;
+
+/// Runs the command line interface for the compiler (see [run_cli]), and decides what it exits
+/// with: 0 for success, [EXIT_ERRORS], [EXIT_USAGE], or [EXIT_INTERNAL_ERROR].
+///
+/// A panic is reported as an internal compiler error (see [report_internal_error]), rather than
+/// with a raw backtrace.
+fn main() -> ExitCode {
+    std::panic::set_hook(Box::new(report_internal_error));
+    match std::panic::catch_unwind(run_cli) {
+        Ok(Ok(exit_code)) => exit_code,
+        Ok(Err(err)) => {
+            eprintln!("error: {:#}", err);
+            ExitCode::from(EXIT_USAGE)
+        }
+        Err(payload) if is_stdout_error(panic_message(payload.as_ref())) => ExitCode::from(EXIT_USAGE),
+        Err(_) => ExitCode::from(EXIT_INTERNAL_ERROR),
+    }
+}
+
+/// Reports a panic as an internal compiler error, with where it happened and how to report it
+/// (and a backtrace, if `RUST_BACKTRACE` asks for one).
+fn report_internal_error(info: &std::panic::PanicHookInfo) {
+    let message = panic_message(info.payload());
+    // `println!` panics when stdout is closed (e.g. `flick lex test.fl | head`), which isn't a bug
+    if is_stdout_error(message) {
+        return;
+    }
+
+    eprintln!("error: internal compiler error: {}", message);
+    if let Some(location) = info.location() {
+        eprintln!("  --> {}", location);
+    }
+    eprintln!("note: this is a bug in flick; please report it at {}/issues", env!("CARGO_PKG_REPOSITORY"));
+    let command_line: Vec<_> = std::env::args().collect();
+    eprintln!("note: flick {} was running '{}'", env!("CARGO_PKG_VERSION"), command_line.join(" "));
+    let backtrace = std::backtrace::Backtrace::capture();
+    match backtrace.status() {
+        std::backtrace::BacktraceStatus::Captured => eprintln!("\nstack backtrace:\n{}", backtrace),
+        _ => eprintln!("note: run with 'RUST_BACKTRACE=1' to see a backtrace"),
+    }
+}
+
+/// Returns the message that a panic was started with (like `panic!("oops")`'s `oops`).
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> &str {
+    match (payload.downcast_ref::<&str>(), payload.downcast_ref::<String>()) {
+        (Some(message), _) => message,
+        (_, Some(message)) => message,
+        _ => "(no message)",
+    }
+}
+
+/// Returns `true` if a panic's `message` says that printing to stdout failed.
+fn is_stdout_error(message: &str) -> bool {
+    message.starts_with("failed printing to stdout")
+}
+
+/// Runs the command given on the command line, returning an error if it's used wrong or a file
+/// can't be read or written.
+fn run_cli() -> Result<ExitCode> {
     let cli = Cli::parse();
+    cli.log.init_logging();
+    let mut command = cli.command;
+    if let Some(source_args) = command.source_args_mut() {
+        if source_args.source_paths.is_empty() {
+            match Project::find(&std::env::current_dir()?)? {
+                Some(project) => {
+                    tracing::info!(root = %project.root.display(), "building the project");
+                    command.apply_project(&project)
+                }
+                None => bail!(
+                    "no source files were given, and there's no '{}' in this directory or any above it \
+                     (see 'flick init')",
+                    project::CONFIG_FILE_NAME
+                ),
+            }
+        }
+    }
+    if let Some(source_args) = command.source_args_mut() {
+        source_args.expand_directories()?;
+    }
 
-    let mut file = File::open(&cli.source_path)?;
-    let mut file_contents = String::new();
-    file.read_to_string(&mut file_contents)?;
-    let file_chars: Vec<_> = file_contents.chars().collect();
+    match command {
+        CliCommand::Build(args) => build(&args),
+        CliCommand::Run(args) => run(&args),
+        CliCommand::Check(args) => {
+            let sources = map_sources(&args.parse.source)?;
+            let mut reporter = Reporter::new(&args.parse.source, &sources, args.lints.get_lint_levels());
+            let mut times = PhaseTimes::default();
+            flick::check_with(&sources, &args.parse.check_options(), &mut reporter, &mut times);
+            if args.time_passes {
+                eprint!("{}", times);
+            }
+            Ok(reporter.finish())
+        }
+        CliCommand::Lint(args) => {
+            let sources = map_sources(&args.parse.source)?;
+            let mut reporter = Reporter::new(&args.parse.source, &sources, args.lints.get_lint_levels());
+            let mut times = PhaseTimes::default();
+            lint(&args, &sources, &mut reporter, &mut times);
+            if args.time_passes {
+                eprint!("{}", times);
+            }
+            Ok(reporter.finish())
+        }
+        CliCommand::Lex(args) => {
+            let sources = map_sources(&args)?;
+            let mut reporter = Reporter::new(&args, &sources, LintLevels::default());
+            let options = CheckOptions { identifier_policy: args.identifier_policy(), ..Default::default() };
+            if let Some(files_tokens) = lex_sources(&sources, &options, &mut reporter, &mut PhaseTimes::default()) {
+                print!("{}", tokens_to_string(&files_tokens, &sources));
+            }
+            Ok(reporter.finish())
+        }
+        CliCommand::Parse { args, format } => {
+            let sources = map_sources(&args.source)?;
+            let mut reporter = Reporter::new(&args.source, &sources, LintLevels::default());
+            let mut arenas = Vec::new();
+            if let Some(program) = parse_sources(&sources, &mut arenas, &args.check_options(), &mut reporter, &mut PhaseTimes::default()) {
+                match format {
+                    TreeFormat::Pretty => print!("{}", print_tree(&program)),
+                    TreeFormat::Json => println!("{}", serde_json::to_string_pretty(&program)?),
+                    TreeFormat::Debug => println!("{:#?}", program),
+                    TreeFormat::Dot => print!("{}", print_dot(&program)),
+                }
+            }
+            Ok(reporter.finish())
+        }
+        CliCommand::Fix(args) => fix(&args),
+        CliCommand::Fmt(args) => fmt(&args),
+        CliCommand::Highlight { source, format } => {
+            let sources = map_sources(&source)?;
+            let mut reporter = Reporter::new(&source, &sources, LintLevels::default());
+            for file in sources.files() {
+                match Cst::with_policy(&file.source_code, source.identifier_policy()) {
+                    Ok(cst) => match format {
+                        HighlightFormat::Ansi => print!("{}", highlight_ansi(&cst)),
+                        HighlightFormat::Html => println!("{}", highlight_html(&cst)),
+                    },
+                    Err(err) => reporter.report(Diagnostic::error(&err).offset_by(file.start)),
+                }
+            }
+            Ok(reporter.finish())
+        }
+        CliCommand::Stats(args) => stats(&args),
+        CliCommand::Rename { path, old_name, new_name, line } => rename(&path, &old_name, &new_name, line),
+        CliCommand::Grammar => {
+            print!("{}", grammar::ebnf());
+            Ok(ExitCode::SUCCESS)
+        }
+        CliCommand::Explain { code } => Ok(explain(&code)),
+        CliCommand::Init { path, name } => init(&path, name),
+        CliCommand::Lsp => {
+            let shut_down = flick::lsp::server::Server::new().run(std::io::stdin().lock(), std::io::stdout())?;
+            // the protocol says to exit with 1 if the client didn't ask the server to shut down first
+            Ok(if shut_down { ExitCode::SUCCESS } else { ExitCode::from(EXIT_ERRORS) })
+        }
+        CliCommand::Cov { command: CovCommand::Report { profile } } => cov_report(&profile),
+    }
+}
 
-    let tokens = match Lexer::lex(&file_chars) {
-        Ok(tokens) => tokens,
-        Err(err) => {
-            print_error(&cli.source_path, &file_chars, err);
-            return Ok(());
+/// Appends the paths of the `.fl` files in `directory` (and in the directories in it) to
+/// `source_paths`, in alphabetical order.
+fn find_source_files(directory: &Path, source_paths: &mut Vec<PathBuf>) -> Result<()> {
+    let mut entries: Vec<_> = std::fs::read_dir(directory)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<std::io::Result<_>>()?;
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            find_source_files(&path, source_paths)?;
+        } else if path.extension().is_some_and(|extension| extension == "fl") {
+            source_paths.push(path);
         }
-    };
+    }
+    Ok(())
+}
+
+/// Reads the source code of the files given by `args` (for the commands that rewrite them).
+fn read_sources(args: &SourceArgs) -> Result<SourceMap> {
+    let mut sources = SourceMap::default();
+    for path in &args.source_paths {
+        sources.read_file(path).with_context(|| format!("couldn't read '{}'", path.display()))?;
+    }
+    Ok(sources)
+}
+
+/// Like [read_sources], but maps big files into memory (see [SourceMap::map_file]), for the
+/// commands that only read them.
+fn map_sources(args: &SourceArgs) -> Result<SourceMap> {
+    let mut sources = SourceMap::default();
+    for path in &args.source_paths {
+        // SAFETY: these commands never write to the files, and, like any compiler, flick requires
+        // that nothing else changes them while it runs (`fix` and `fmt`, which rewrite the files,
+        // use `read_sources` instead)
+        let mapped = unsafe { sources.map_file(path) };
+        mapped.with_context(|| format!("couldn't read '{}'", path.display()))?;
+    }
+    Ok(sources)
+}
+
+/// Resolves the names in `program` and type checks it (see [flick::check_program]), returning
+/// `None` if there are errors, including warnings that `-D` turned into errors.
+fn check_program(program: &Program, reporter: &mut Reporter, times: &mut PhaseTimes) -> Option<TypedProgram> {
+    let typed_program = flick::check_program(program, reporter, times)?;
+    // Warnings that `-D` turned into errors stop compilation here
+    match reporter.errors {
+        0 => Some(typed_program),
+        _ => None,
+    }
+}
+
+/// Checks the files in `sources` for errors, and then (if there aren't any) for everything that a
+/// lint is about, reporting what's found (for `flick lint` and `flick fix`).
+fn lint(args: &CheckArgs, sources: &SourceMap, reporter: &mut Reporter, times: &mut PhaseTimes) {
+    let mut arenas = Vec::new();
+    if let Some(program) = parse_sources(sources, &mut arenas, &args.parse.check_options(), reporter, times) {
+        if check_program(&program, reporter, times).is_some() {
+            for warning in times.time("linting", || Linter::lint_program(&program)) {
+                reporter.report(Diagnostic::warning(&warning));
+            }
+        }
+    }
+}
+
+/// Compiles the file given by `args` (for `flick build`), returning a failure if it has errors.
+fn build(args: &BuildArgs) -> Result<ExitCode> {
+    if args.backend == BackendKind::C {
+        for kind in [OutputKind::LlvmIr, OutputKind::Assembly] {
+            if args.emits(kind) {
+                let name = kind.to_possible_value().unwrap();
+                bail!("the C backend generates C source code, so it can't emit '{}'", name.get_name());
+            }
+        }
+    }
+
+    if args.object_output_path.is_some() {
+        eprintln!("warning: '--object-output-path' is deprecated; use '--emit=obj,exe' to keep the object file");
+    }
 
-    let program = Parser::parse_program(&tokens);
+    let sources = map_sources(&args.check.parse.source)?;
+    let mut reporter = Reporter::new(&args.check.parse.source, &sources, args.check.lints.get_lint_levels());
+    let mut times = PhaseTimes::default();
 
-    let mut typer = Typer::new();
-    let typed_program = typer.type_program(&program);
+    let mut arenas = Vec::new();
+    let program = match parse_sources(&sources, &mut arenas, &args.check.parse.check_options(), &mut reporter, &mut times) {
+        Some(program) => program,
+        None => return Ok(reporter.finish()),
+    };
+    let mut typed_program = match check_program(&program, &mut reporter, &mut times) {
+        Some(typed_program) => typed_program,
+        None => return Ok(reporter.finish()),
+    };
+    if let Some(func_name) = &args.print_ir_func {
+        // Unused functions were already removed, so they aren't compiled either
+        if !typed_program.defines(func_name) {
+            bail!("there's no function named '{}' to print the IR of (functions that are never called aren't compiled)", func_name);
+        }
+    }
+    if args.coverage {
+        // The profile is named after the executable, in whichever directory it's run in
+        let mut profile_path = args.get_output_path_for(OutputKind::Executable);
+        profile_path.set_extension("flcov");
+        let profile_name = profile_path.file_name().unwrap().to_string_lossy();
+        coverage::instrument(&program, &mut typed_program, &sources, &profile_name);
+    }
+    if args.instrument_functions {
+        if let Err(err) = profiling::instrument_functions(&mut typed_program) {
+            reporter.report(Diagnostic::from(&err));
+            return Ok(reporter.finish());
+        }
+    }
 
-    let mut compiler = Compiler::new();
-    compiler.compile(&typed_program);
+    emit_tokens_and_ast(args, &sources, &mut reporter)?;
+    if args.get_output_kinds().iter().all(|kind| *kind < OutputKind::LlvmIr) {
+        // There's no code to generate
+        if args.check.time_passes {
+            eprint!("{}", times);
+        }
+        return Ok(reporter.finish());
+    }
 
-    if cli.emit_ir {
-        println!("\nIR before optimization:");
-        compiler.print_ir();
+    #[cfg(feature = "llvm")]
+    if let Some(cache) = args.get_object_cache() {
+        return build_with_cache(args, &cache, &sources, &typed_program, reporter, times);
     }
 
-    compiler.optimize();
+    let mut backend: Box<dyn Backend> = match args.backend {
+        #[cfg(feature = "llvm")]
+        BackendKind::Llvm => match Compiler::new(args.get_compiler_options()) {
+            Ok(compiler) => Box::new(compiler),
+            Err(err) => {
+                reporter.report(Diagnostic::from(&err));
+                return Ok(reporter.finish());
+            }
+        },
+        #[cfg(not(feature = "llvm"))]
+        BackendKind::Llvm => bail!("this flick was built without LLVM, so it can only generate C ('--backend c')"),
+        BackendKind::C => Box::new(CBackend::new()),
+    };
+    let print_unoptimized_ir = |backend: &dyn Backend| {
+        if args.prints_ir_at(IrStage::Pre) {
+            println!("\nIR before optimization:");
+            print_ir(args, backend);
+        }
+    };
+    if let Err(err) = flick::generate(backend.as_mut(), &typed_program, &mut times, print_unoptimized_ir) {
+        reporter.report(Diagnostic::from(&err));
+        return Ok(reporter.finish());
+    }
 
-    if cli.emit_ir {
+    if args.prints_ir_at(IrStage::Post) {
         println!("\nIR after optimization:");
-        compiler.print_ir();
+        print_ir(args, backend.as_ref());
+    }
+
+    if args.emits(OutputKind::LlvmIr) {
+        write_output(&args.get_output_path_for(OutputKind::LlvmIr), backend.ir_to_string())?;
+    }
+    if args.emits(OutputKind::Assembly) {
+        let assembly_output_path = args.get_output_path_for(OutputKind::Assembly);
+        match times.time("emission", || backend.to_file_as(&assembly_output_path, EmitKind::Assembly)) {
+            Ok(()) => {}
+            // Like any other output that can't be written, this is an I/O error, not the program's
+            Err(err @ CompilationError::UnwritableOutput { .. }) => return Err(err.into()),
+            Err(err) => {
+                reporter.report(Diagnostic::from(&err));
+                return Ok(reporter.finish());
+            }
+        }
     }
 
-    let object_output_path = cli.get_object_output_path();
-    compiler.to_file(&object_output_path);
+    let object_output_path = args.get_object_output_path();
+    if args.emits(OutputKind::Object) || args.emits(OutputKind::Executable) {
+        times.time("emission", || backend.to_file(&object_output_path))?;
+    }
 
-    if cli.no_link {
-        return Ok(());
+    if args.emits(OutputKind::Executable) {
+        let linked = link(args, std::slice::from_ref(&object_output_path), &typed_program.libraries(), &mut times)?;
+        if !args.emits(OutputKind::Object) {
+            std::fs::remove_file(&object_output_path)?;
+        }
+        if !linked {
+            return Ok(ExitCode::from(EXIT_ERRORS));
+        }
     }
 
-    let executable_output_path = cli.get_executable_output_path();
-    let linker_path = cli.get_linker_path();
-    Command::new(linker_path)
-        .arg(&object_output_path)
-        .arg("-o")
-        .arg(&executable_output_path)
-        .output()?;
+    if args.check.time_passes {
+        eprint!("{}", times);
+    }
 
-    if cli.object_output_path.is_none() {
-        std::fs::remove_file(&object_output_path)?;
+    Ok(reporter.finish())
+}
+
+/// Builds the executable (like [build]) from the object files in `cache`, compiling (and
+/// caching) just the modules of `typed_program` that aren't there yet: with more than one
+/// file, each file is a module of its own (see [cache::split_modules]).
+#[cfg(feature = "llvm")]
+fn build_with_cache(
+    args: &BuildArgs,
+    cache: &ObjectCache,
+    sources: &SourceMap,
+    typed_program: &TypedProgram,
+    mut reporter: Reporter,
+    mut times: PhaseTimes,
+) -> Result<ExitCode> {
+    let extension = OutputKind::Object.extension(args);
+    let mut object_paths = Vec::new();
+    for module in cache::split_modules(typed_program, sources) {
+        let mut options = args.get_compiler_options();
+        if let [file] = module.files[..] {
+            // The debug info of a file's module is about that file
+            options.source_file_name = file.path.file_name().map(|file_name| file_name.to_string_lossy().into_owned());
+        }
+        let key = ObjectCache::key(&module, &options);
+        if let Some(object_path) = cache.get(&key, extension) {
+            tracing::info!(path = %object_path.display(), "reusing a cached module");
+            times.count("modules reused from the cache", 1);
+            object_paths.push(object_path);
+            continue;
+        }
+
+        let compiled = Compiler::new(options).and_then(|mut compiler| {
+            flick::generate(&mut compiler, &module.program, &mut times, |_| {})?;
+            Ok(compiler)
+        });
+        let compiler = match compiled {
+            Ok(compiler) => compiler,
+            Err(err) => {
+                reporter.report(Diagnostic::from(&err));
+                return Ok(reporter.finish());
+            }
+        };
+        let object = times.time("emission", || compiler.emit_to_memory())?;
+        let object_path = cache
+            .insert(&key, extension, &object)
+            .with_context(|| format!("couldn't write to the cache in '{}'", cache.dir.display()))?;
+        times.count("modules compiled", 1);
+        object_paths.push(object_path);
     }
 
+    if !link(args, &object_paths, &typed_program.libraries(), &mut times)? {
+        return Ok(ExitCode::from(EXIT_ERRORS));
+    }
+    if args.check.time_passes {
+        eprint!("{}", times);
+    }
+    Ok(reporter.finish())
+}
+
+/// Links the object files at `object_paths` into the executable, along with `libraries` (the
+/// ones that the program's `extern` functions are in), returning `false` (after printing the
+/// linker's errors) if the linker fails.
+fn link(args: &BuildArgs, object_paths: &[PathBuf], libraries: &[&str], times: &mut PhaseTimes) -> Result<bool> {
+    let executable_output_path = args.get_output_path_for(OutputKind::Executable);
+    let mut linker = args.get_linker_command(object_paths, libraries, &executable_output_path);
+    tracing::info!(command = ?linker, "linking");
+    let output = times.time("linking", || linker.output())?;
+    if !output.status.success() {
+        eprintln!("error: linking with '{}' failed ({})", linker.get_program().to_string_lossy(), output.status);
+        eprint!("{}", String::from_utf8_lossy(&output.stderr));
+    }
+    Ok(output.status.success())
+}
+
+/// Compiles the file given by `args` into an executable, then runs it (for `flick run`),
+/// returning its exit code.
+fn run(args: &RunArgs) -> Result<ExitCode> {
+    if !args.build.emits(OutputKind::Executable) {
+        bail!("'flick run' needs an executable to run, so it can't take '--no-link' (or an '--emit' without 'exe')");
+    }
+    if args.build.get_compiler_options().targets_wasm() {
+        bail!("'flick run' can't run WebAssembly modules (they need a host, like a browser)");
+    }
+
+    let exit_code = build(&args.build)?;
+    if exit_code != ExitCode::SUCCESS {
+        return Ok(exit_code);
+    }
+
+    // A relative path like `test` would be looked up in `PATH`, rather than in this directory
+    let executable_path = match args.build.get_output_path() {
+        path if path.is_relative() => Path::new(".").join(path),
+        path => path,
+    };
+    tracing::info!(path = %executable_path.display(), args = ?args.args, "running the program");
+    let status = Command::new(executable_path).args(&args.args).status()?;
+    Ok(match status.code() {
+        Some(code) => ExitCode::from(code as u8),
+        // The program was killed by a signal
+        None => ExitCode::FAILURE,
+    })
+}
+
+/// Writes the tokens and the abstract syntax tree of the files in `sources` (which have already
+/// been checked), if `--emit` asks for them.
+fn emit_tokens_and_ast(args: &BuildArgs, sources: &SourceMap, reporter: &mut Reporter) -> Result<()> {
+    // The files were already lexed and parsed (and timed) while checking them
+    let mut times = PhaseTimes::default();
+    if args.emits(OutputKind::Tokens) {
+        if let Some(files_tokens) = lex_sources(sources, &args.check.parse.check_options(), reporter, &mut times) {
+            write_output(&args.get_output_path_for(OutputKind::Tokens), tokens_to_string(&files_tokens, sources))?;
+        }
+    }
+    if args.emits(OutputKind::Ast) {
+        let mut arenas = Vec::new();
+        if let Some(program) = parse_sources(sources, &mut arenas, &args.check.parse.check_options(), reporter, &mut times) {
+            write_output(&args.get_output_path_for(OutputKind::Ast), print_tree(&program))?;
+        }
+    }
     Ok(())
 }
 
+/// Writes `contents` to the output file at `path`.
+fn write_output(path: &Path, contents: String) -> Result<()> {
+    tracing::info!(path = %path.display(), "writing output");
+    std::fs::write(path, contents).with_context(|| format!("couldn't write '{}'", path.display()))
+}
 
-fn print_error(source_path: impl AsRef<Path>, file_chars: &[char], error: FlickError) {
-    // TODO: Write to string then print string to stderr instead of printing to stderr directly
+/// Returns the tokens of each file in `sources` (as `flick lex` prints them), one per line, after
+/// where it is in its file (like `1:5-1:7`). With more than one file, each file's tokens come after
+/// its path.
+fn tokens_to_string(files_tokens: &[(Vec<Token>, Vec<Span>)], sources: &SourceMap) -> String {
+    let mut string = String::new();
+    for (file, (tokens, spans)) in sources.files().iter().zip(files_tokens) {
+        if sources.files().len() > 1 {
+            writeln!(string, "{}:", file.path.display()).unwrap();
+        }
+        for (token, span) in tokens.iter().zip(spans) {
+            let (_, span) = sources.locate(*span);
+            let (start, end) = (span.start_position(&file.source_code), span.end_position(&file.source_code));
+            writeln!(string, "{:<12} {:?}", format!("{}-{}", start, end), token).unwrap();
+        }
+    }
+    string
+}
 
-    let line_start_index = file_chars
-        .iter()
-        .take(error.index)
-        .take_while(|c: &&char| **c != '\n')
-        .count() + 1;
-    let line_num = file_chars[..line_start_index].iter().filter(|&&c| c == '\n').count() + 1;
-    let col_num = error.index - line_start_index + 1;
-    let line = &file_chars[line_start_index..]
-        .iter()
-        .take_while(|&&c| c != '\n')
-        .collect::<String>();
+/// Prints the IR generated by `backend` so far to stdout: either the whole module, or just the
+/// function named by `--print-ir-fn` (which [build] already checked that the program defines).
+fn print_ir(args: &BuildArgs, backend: &dyn Backend) {
+    match &args.print_ir_func {
+        None => print!("{}", backend.ir_to_string()),
+        Some(func_name) => match backend.function_ir_to_string(func_name) {
+            Some(ir) => print!("{}", ir),
+            None => println!("; no function named '{}' (it may have been inlined)", func_name),
+        },
+    }
+}
+
+/// Creates a project in `path` for `flick init`, named `name` (or after the directory).
+fn init(path: &Path, name: Option<String>) -> Result<ExitCode> {
+    let name = match name {
+        Some(name) => name,
+        // `.` has no file name of its own, so look at the directory it refers to
+        None => match std::path::absolute(path)?.components().next_back() {
+            Some(std::path::Component::Normal(name)) => name.to_string_lossy().into_owned(),
+            _ => bail!("can't name a project after '{}'; pass '--name'", path.display()),
+        },
+    };
+    let project = Project::init(path, &name)?;
+    eprintln!(
+        "Created project '{}' in '{}'; build it with 'flick build' (or 'flick run') from there",
+        name,
+        project.root.display()
+    );
+    Ok(ExitCode::SUCCESS)
+}
 
-    eprintln!("{}: {}", "error", error);
+/// Applies the fixes that the problems in the files given by `args` suggest (for `flick fix`),
+/// or, with `--dry-run`, prints them as a diff instead, and then reports the problems that are
+/// left.
+///
+/// The fixes come from checking the files once, so problems that other problems hide (like the
+/// lints, which only run on programs without errors) need another `flick fix` to be fixed.
+fn fix(args: &FixArgs) -> Result<ExitCode> {
+    let sources = read_sources(&args.check.parse.source)?;
+    let mut reporter = Reporter::new(&args.check.parse.source, &sources, args.check.lints.get_lint_levels());
+    reporter.fixes = Some(Vec::new());
+    let mut times = PhaseTimes::default();
+    lint(&args.check, &sources, &mut reporter, &mut times);
+    if args.check.time_passes {
+        eprint!("{}", times);
+    }
 
-    let max_line_num_width = (line_num+1).to_string().len();
-    // if line_num > 1 {
-    //     write_source_code_line(&file_chars[line_start_index - line.len()..line_start_index], line_num - 1, max_line_num_width);
-    // }
-    write_source_code_line(line, line_num, max_line_num_width);
-    // let num_lines = file_chars.iter().filter(|&&c| c == '\n').count() + 1;
-    // if line_num < num_lines {
-    //     write_source_code_line(&file_chars[line_start_index + line.len()..], line_num + 1, max_line_num_width);
-    // }
-    eprintln!("in {}:{}:{}", source_path.as_ref().display(), line_num, col_num);
+    let fixes = reporter.fixes.take().unwrap_or_default();
+    let mut skipped = 0;
+    for file in sources.files() {
+        let file_fixes: Vec<_> = fixes
+            .iter()
+            .filter(|fix| {
+                let start = fix.edits[0].start();
+                sources.locate(Span { start, end: start }).0.start == file.start
+            })
+            .cloned()
+            .collect();
+        if file_fixes.is_empty() {
+            continue;
+        }
+        let (fixed, applied) = fix::apply(file, &file_fixes);
+        skipped += file_fixes.len() - applied;
+        if args.dry_run {
+            print!("{}", fix::diff(&file.path.display().to_string(), &file.source_code, &fixed));
+            continue;
+        }
+        std::fs::write(&file.path, fixed).with_context(|| format!("couldn't write '{}'", file.path.display()))?;
+        match applied {
+            1 => eprintln!("fixed {} (1 fix)", file.path.display()),
+            _ => eprintln!("fixed {} ({} fixes)", file.path.display(), applied),
+        }
+    }
+    match skipped {
+        0 => {}
+        1 => eprintln!("note: 1 fix changes the same code as another one (run 'flick fix' again to apply it)"),
+        _ => eprintln!("note: {} fixes change the same code as others (run 'flick fix' again to apply them)", skipped),
+    }
+    Ok(reporter.finish())
 }
 
-fn write_source_code_line(line: &str, line_num: usize, max_line_num_width: usize) {
-    eprintln!(" {:0>width$} │ {}", line_num, line, width = max_line_num_width);
+/// Formats the files given by `args` for `flick fmt` (see [format_source_with_policy]),
+/// rewriting the ones that change, or with `--check`, listing them (with the first line that
+/// changes) and failing if there are any. Files with syntax errors are reported, and left alone.
+fn fmt(args: &FmtArgs) -> Result<ExitCode> {
+    let sources = read_sources(&args.source)?;
+    let mut reporter = Reporter::new(&args.source, &sources, LintLevels::default());
+    let mut unformatted = 0;
+    for file in sources.files() {
+        let formatted = match format_source_with_policy(&file.source_code, args.source.identifier_policy()) {
+            Ok(formatted) => formatted,
+            Err(errors) => {
+                for err in errors {
+                    reporter.report(Diagnostic::error(&err).offset_by(file.start));
+                }
+                continue;
+            }
+        };
+        let source_code: &str = &file.source_code;
+        if formatted == source_code {
+            continue;
+        }
+
+        if !args.check {
+            std::fs::write(&file.path, formatted)
+                .with_context(|| format!("couldn't write '{}'", file.path.display()))?;
+            continue;
+        }
+        unformatted += 1;
+        let same_lines = source_code.lines().zip(formatted.lines()).take_while(|(old, new)| old == new).count();
+        println!("Diff in {} at line {}:", file.path.display(), same_lines + 1);
+        match (source_code.lines().nth(same_lines), formatted.lines().nth(same_lines)) {
+            (None, None) => println!("(only the line endings, or the newlines at the end, differ)"),
+            (old, new) => {
+                for (sign, line) in [('-', old), ('+', new)] {
+                    if let Some(line) = line {
+                        println!("{}{}", sign, line);
+                    }
+                }
+            }
+        }
+    }
+
+    let exit_code = reporter.finish();
+    match unformatted {
+        0 => return Ok(exit_code),
+        1 => eprintln!("error: 1 file isn't formatted (run 'flick fmt' to format it)"),
+        _ => eprintln!("error: {} files aren't formatted (run 'flick fmt' to format them)", unformatted),
+    }
+    Ok(ExitCode::from(EXIT_ERRORS))
+}
+
+/// Renames `old_name` to `new_name` in the file at `path` (for `flick rename`; see
+/// [rename::rename]), or reports the file's errors if it has any.
+fn rename(path: &Path, old_name: &str, new_name: &str, line: Option<usize>) -> Result<ExitCode> {
+    let source_code = std::fs::read_to_string(path).with_context(|| format!("couldn't read {}", path.display()))?;
+    match rename::rename(&source_code, old_name, new_name, line) {
+        Ok(renamed) => {
+            std::fs::write(path, renamed).with_context(|| format!("couldn't write '{}'", path.display()))?;
+            Ok(ExitCode::SUCCESS)
+        }
+        Err(RenameError::Errors(errors)) => {
+            let sources = SourceMap::single_file(path, source_code);
+            for err in &errors {
+                eprint!("{}", Diagnostic::error(err).render(&sources));
+            }
+            eprintln!("error: {}", RenameError::Errors(errors));
+            Ok(ExitCode::from(EXIT_ERRORS))
+        }
+        Err(err) => bail!(err),
+    }
+}
+
+/// Prints statistics about each file given by `args`, and about all of them together, in a
+/// table (for `flick stats`).
+fn stats(args: &SourceArgs) -> Result<ExitCode> {
+    let sources = map_sources(args)?;
+    let mut reporter = Reporter::new(args, &sources, LintLevels::default());
+    let mut files_stats = Vec::new();
+    for file in sources.files() {
+        let file_stats = Lexer::lex_with_policy(&file.source_code, args.identifier_policy())
+            .map_err(|err| vec![err])
+            .and_then(|(tokens, spans)| {
+                let arena = Arena::new();
+                let program = Parser::parse_program_with_spans(&arena, &tokens, &spans)?;
+                Ok(FileStats::new(&file.source_code, &tokens, &spans, &program))
+            });
+        match file_stats {
+            Ok(file_stats) => files_stats.push((file.path.display().to_string(), file_stats)),
+            Err(errors) => {
+                for err in errors {
+                    reporter.report(Diagnostic::error(&err).offset_by(file.start));
+                }
+            }
+        }
+    }
+    if files_stats.len() > 1 {
+        let mut total = FileStats::default();
+        files_stats.iter().for_each(|(_, file_stats)| total += *file_stats);
+        files_stats.push(("total".to_string(), total));
+    }
+
+    let width = files_stats.iter().map(|(path, _)| path.len()).chain([4]).max().unwrap();
+    let columns = ["lines", "code", "comments", "blank", "tokens", "functions", "avg fn lines", "comment %"];
+    println!("{:width$}  {}", "file", columns.map(|column| format!("{:>12}", column)).join(" "));
+    for (path, file_stats) in &files_stats {
+        let average_function_length = file_stats.average_function_length().map(|length| format!("{:.1}", length));
+        let comment_percent = file_stats.comment_ratio().map(|ratio| format!("{:.1}", ratio * 100.0));
+        let cells = [
+            file_stats.lines.to_string(),
+            file_stats.code_lines.to_string(),
+            file_stats.comment_lines.to_string(),
+            file_stats.blank_lines.to_string(),
+            file_stats.tokens.to_string(),
+            file_stats.functions.to_string(),
+            average_function_length.unwrap_or_else(|| "-".to_string()),
+            comment_percent.unwrap_or_else(|| "-".to_string()),
+        ];
+        println!("{:width$}  {}", path, cells.map(|cell| format!("{:>12}", cell)).join(" "));
+    }
+    Ok(reporter.finish())
+}
+
+/// Prints the explanation of an error (or warning) code for `flick explain`.
+fn explain(code: &str) -> ExitCode {
+    match registry::explain(code) {
+        Some(explanation) => {
+            print!("{}", explanation);
+            ExitCode::SUCCESS
+        }
+        None => {
+            eprintln!("error: '{}' isn't an error code (they look like 'E0201' or 'W0201')", code);
+            ExitCode::from(EXIT_USAGE)
+        }
+    }
+}
+
+/// Prints each file in the coverage profile at `profile_path` with how many times its lines
+/// ran, like `gcov`, and then how many of each file's lines ran (for `flick cov report`).
+fn cov_report(profile_path: &Path) -> Result<ExitCode> {
+    let profile = std::fs::read_to_string(profile_path)
+        .with_context(|| format!("couldn't read the profile at {}", profile_path.display()))?;
+    let profile = Profile::parse(&profile).with_context(|| format!("{} isn't a coverage profile", profile_path.display()))?;
+    for (path, file_coverage) in &profile.files {
+        let source_code =
+            std::fs::read_to_string(path).with_context(|| format!("couldn't read {}", path.display()))?;
+        println!("{:>9}:{:>5}:Source:{}", "-", 0, path.display());
+        print!("{}", file_coverage.annotate(&source_code));
+    }
+    for (path, file_coverage) in &profile.files {
+        let (covered, total) = (file_coverage.covered_lines(), file_coverage.lines.len());
+        let percent = match total {
+            0 => 100.0,
+            _ => 100.0 * covered as f64 / total as f64,
+        };
+        println!("{}: {} of {} lines covered ({:.1}%)", path.display(), covered, total, percent);
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Parses the name (or code) of a lint for `-W`, `-A`, and `-D`.
+fn parse_lint(name: &str) -> Result<&'static Lint, String> {
+    Lint::find(name).ok_or_else(|| {
+        let names: Vec<_> = LINTS.iter().map(|lint| lint.name).collect();
+        format!("there's no lint called '{}' (the lints are {})", name, names.join(", "))
+    })
+}
+
+/// Returns `diagnostic` as a JSON object (for `--message-format=json`), like:
+///
+/// ```json
+/// {
+///   "severity": "error",
+///   "code": "E0201",
+///   "message": "cannot find variable 'y' in this scope",
+///   "file": "example.fl",
+///   "span": { "file": "example.fl", "start": 19, "end": 20, "line": 2, "column": 9, "end_line": 2, "end_column": 10 },
+///   "notes": [],
+///   "suggestions": []
+/// }
+/// ```
+///
+/// Spans count characters (not bytes) from the start of their file, starting from 0, and their
+/// lines and columns start from 1; `end` is just past the end of the span. A diagnostic that
+/// isn't anywhere in particular in the code has a `null` span (and its `file` is the first file),
+/// and so can a note.
+fn diagnostic_to_json(diagnostic: &Diagnostic, sources: &SourceMap) -> serde_json::Value {
+    let span_to_json = |span: Option<Span>| match span {
+        Some(span) => {
+            let (file, span) = sources.locate(span);
+            let span = Diagnostic::highlighted(span, &file.source_code);
+            let (start, end) = (span.start_position(&file.source_code), span.end_position(&file.source_code));
+            json!({
+                "file": file.path.display().to_string(),
+                "start": span.start,
+                "end": span.end,
+                "line": start.line,
+                "column": start.column,
+                "end_line": end.line,
+                "end_column": end.column,
+            })
+        }
+        None => serde_json::Value::Null,
+    };
+    let notes: Vec<_> = diagnostic
+        .notes
+        .iter()
+        .map(|note| json!({ "message": note.message, "span": span_to_json(note.span) }))
+        .collect();
+    let file = match diagnostic.span {
+        Some(span) => sources.locate(span).0,
+        None => &sources.files()[0],
+    };
+
+    json!({
+        "severity": diagnostic.severity.to_string(),
+        "code": diagnostic.code,
+        "message": diagnostic.message,
+        "file": file.path.display().to_string(),
+        "span": span_to_json(diagnostic.span),
+        "notes": notes,
+        "suggestions": diagnostic.help,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn typing_errors_in_json_have_spans() {
+        let sources = SourceMap::single_file("t.fl", "pub fn main() u8 {\n    ret true\n}\n".to_string());
+        let mut diagnostics = Vec::new();
+        assert!(flick::check(&sources, &mut diagnostics).is_none());
+
+        let json = diagnostic_to_json(&diagnostics[0], &sources);
+        assert_eq!(json["code"], "E0316");
+        assert_eq!(json["file"], "t.fl");
+        let expected_span = json!({
+            "file": "t.fl",
+            "start": 23,
+            "end": 26,
+            "line": 2,
+            "column": 5,
+            "end_line": 2,
+            "end_column": 8,
+        });
+        assert_eq!(json["span"], expected_span);
+    }
+
+    #[test]
+    fn object_output_path_keeps_the_object_file() {
+        let cli = Cli::try_parse_from(["flick", "build", "test.fl", "--object-output-path", "keep.o"]).unwrap();
+        let CliCommand::Build(args) = cli.command else { panic!("not a build") };
+        assert!(args.emits(OutputKind::Object));
+        assert_eq!(args.get_object_output_path(), Path::new("keep.o"));
+        assert_eq!(args.get_output_path(), Path::new("test"));
+    }
 }
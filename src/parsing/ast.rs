@@ -1,38 +1,231 @@
 use crate::lexing::token::ComparatorSymbol::*;
 use crate::lexing::token::OperatorSymbol::*;
-use crate::lexing::token::{ComparatorSymbol, OperatorSymbol};
+use crate::lexing::name::Name;
+use crate::lexing::token::{ComparatorSymbol, OperatorSymbol, Span, Token};
+use crate::parsing::visit::{self, Visitor, VisitorMut};
 use crate::types::Type;
+use std::collections::HashMap;
 use std::fmt;
 
+/// Identifies a node of a [Program], so that passes can keep information about nodes in side
+/// tables (see [NodeMap]) instead of adding it to the tree.
+///
+/// The [Parser](crate::Parser) numbers the nodes of each program it returns from 0, in the order
+/// that it finishes parsing them (so a node's children come before it). Nodes that are built some
+/// other way (e.g. in tests) can all use `NodeId::default()`, if nothing looks them up.
+#[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct NodeId(pub u32);
+
+/// Information about some of the nodes of a [Program], like the type of each expression (see
+/// [Typer::expr_types](crate::Typer::expr_types)).
+pub type NodeMap<T> = HashMap<NodeId, T>;
+
+/// Where the [Parser](crate::Parser) allocates the expressions of a [Program] (which borrows
+/// them for as long as the arena lives), so that they're freed all at once, rather than one
+/// `Box` at a time.
+///
+/// When the parser parses tokens as they're lexed (see
+/// [Parser::parse_token_stream](crate::Parser::parse_token_stream)), the tokens are kept here too,
+/// since the program's names and literals are borrowed from them.
+///
+/// One arena can hold several programs (e.g. one per file, so that they can be
+/// [combined](Program::extend)).
+#[derive(Default)]
+pub struct Arena<'a> {
+    exprs: typed_arena::Arena<Expr<'a>>,
+    tokens: typed_arena::Arena<Token<'a>>,
+}
+
+impl<'a> Arena<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Moves `expr` into the arena, returning a reference to it.
+    pub fn alloc(&self, expr: Expr<'a>) -> &mut Expr<'a> {
+        self.exprs.alloc(expr)
+    }
+
+    /// Moves `token` into the arena, returning a reference to it.
+    pub(crate) fn alloc_token(&self, token: Token<'a>) -> &Token<'a> {
+        self.tokens.alloc(token)
+    }
+}
+
 /// A program consisting of at least one [GlobalStatement].
-#[derive(Debug, PartialEq, Eq, Clone)]
-pub struct Program {
-    pub global_statements: Vec<GlobalStatement>,
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Program<'a> {
+    pub global_statements: Vec<GlobalStatement<'a>>,
+}
+
+impl<'a> Program<'a> {
+    /// Returns how many nodes the tree has: its global statements, plus every statement and
+    /// expression in them (e.g. `ret a + 1` is 4 nodes).
+    pub fn node_count(&self) -> usize {
+        let mut counter = NodeCounter(0);
+        counter.visit_program(self);
+        counter.0
+    }
+
+    /// Adds the global statements of `other` (e.g. another file's program) to the end of this
+    /// program, renumbering their nodes to come after this program's, so that every [NodeId] in
+    /// the combined program is still unique.
+    pub fn extend(&mut self, mut other: Program<'a>) {
+        let mut max_id = MaxNodeId(None);
+        max_id.visit_program(self);
+        if let Some(NodeId(max_id)) = max_id.0 {
+            IdShifter(max_id + 1).visit_program_mut(&mut other);
+        }
+        self.global_statements.extend(other.global_statements);
+    }
+}
+
+/// Finds the highest [NodeId] in a tree (see [Program::extend]).
+struct MaxNodeId(Option<NodeId>);
+
+impl MaxNodeId {
+    fn see(&mut self, id: NodeId) {
+        self.0 = self.0.max(Some(id));
+    }
+}
+
+impl Visitor<'_> for MaxNodeId {
+    fn visit_func_proto(&mut self, func_proto: &FuncProto) {
+        self.see(func_proto.id);
+        for param in &func_proto.params {
+            self.see(param.id);
+        }
+    }
+
+    fn visit_statement(&mut self, statement: &Statement) {
+        self.see(statement.id());
+        visit::walk_statement(self, statement);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        self.see(expr.id());
+        visit::walk_expr(self, expr);
+    }
+
+    fn visit_call(&mut self, call: &Call) {
+        self.see(call.id);
+        visit::walk_call(self, call);
+    }
+}
+
+/// Adds the same amount to every [NodeId] in a tree (see [Program::extend]).
+struct IdShifter(u32);
+
+impl IdShifter {
+    fn shift(&self, id: &mut NodeId) {
+        id.0 += self.0;
+    }
+}
+
+impl VisitorMut for IdShifter {
+    fn visit_func_proto_mut(&mut self, func_proto: &mut FuncProto) {
+        self.shift(&mut func_proto.id);
+        for param in &mut func_proto.params {
+            self.shift(&mut param.id);
+        }
+    }
+
+    fn visit_statement_mut(&mut self, statement: &mut Statement) {
+        // A call is shifted once, by visit_call_mut
+        if !matches!(statement, Statement::Call(_)) {
+            self.shift(statement.id_mut());
+        }
+        visit::walk_statement_mut(self, statement);
+    }
+
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        if !matches!(expr, Expr::Call(_)) {
+            self.shift(expr.id_mut());
+        }
+        visit::walk_expr_mut(self, expr);
+    }
+
+    fn visit_call_mut(&mut self, call: &mut Call) {
+        self.shift(&mut call.id);
+        visit::walk_call_mut(self, call);
+    }
+}
+
+/// Counts the nodes in a tree (see [Program::node_count]).
+struct NodeCounter(usize);
+
+impl Visitor<'_> for NodeCounter {
+    fn visit_global_statement(&mut self, global_statement: &GlobalStatement) {
+        self.0 += 1;
+        visit::walk_global_statement(self, global_statement);
+    }
+
+    fn visit_statement(&mut self, statement: &Statement) {
+        self.0 += 1;
+        visit::walk_statement(self, statement);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        self.0 += 1;
+        visit::walk_expr(self, expr);
+    }
 }
 
 /// A global statement is something that can be written in the "global" scope, as opposed
 /// to inside of a function body. So, for example, function definitions and external function
 /// declarations are "global" statements.
-#[derive(Debug, PartialEq, Eq, Clone)]
-pub enum GlobalStatement {
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum GlobalStatement<'a> {
     Extern(FuncProto),
-    FuncDef(FuncDef),
+    FuncDef(FuncDef<'a>),
+}
+
+impl GlobalStatement<'_> {
+    /// Returns the id of the function's prototype, which identifies the function.
+    pub fn id(&self) -> NodeId {
+        match self {
+            Self::Extern(func_proto) => func_proto.id,
+            Self::FuncDef(func_def) => func_def.proto.id,
+        }
+    }
 }
 
 /// A function definition (metadata, prototype, and body).
-#[derive(Debug, PartialEq, Eq, Clone)]
-pub struct FuncDef {
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct FuncDef<'a> {
     pub proto: FuncProto,
-    pub body: Vec<Statement>,
+    pub body: Vec<Statement<'a>>,
 }
 
 /// A function prototype (name, parameters, and return type).
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct FuncProto {
     pub func_visibility: FuncVisibility,
-    pub name: String,
+    pub name: Name,
     pub params: Vec<FuncParam>,
     pub return_type: Box<Type>,
+    /// Where the function's name is in the source code
+    pub span: Span,
+    pub id: NodeId,
+    /// The library that an `extern` function is in, like `m` in `extern "m" fn cos(...)`, which
+    /// the program is linked with (like with `-l m`)
+    pub library: Option<String>,
+}
+
+impl FuncProto {
+    /// Returns the keywords that the prototype starts with, like `pub fn`, or `extern "m" fn`
+    /// for an `extern` function in a library.
+    pub fn keywords(&self) -> String {
+        match &self.library {
+            Some(library) => format!("extern \"{}\" fn", library.escape_default()),
+            None => self.func_visibility.to_string(),
+        }
+    }
 }
 
 impl fmt::Display for FuncProto {
@@ -43,11 +236,12 @@ impl fmt::Display for FuncProto {
             .map(|p| format!("{} {}", p.param_type, p.param_name))
             .collect::<Vec<String>>()
             .join(", ");
-        write!(f, "{} {}({}) {}", self.func_visibility, self.name, params, self.return_type)
+        write!(f, "{} {}({}) {}", self.keywords(), self.name, params, self.return_type)
     }
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum FuncVisibility {
     Public,
     Private,
@@ -66,9 +260,13 @@ impl fmt::Display for FuncVisibility {
 
 /// A function parameter (its name and its data type).
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct FuncParam {
     pub param_type: Type,
-    pub param_name: String,
+    pub param_name: Name,
+    /// Where the parameter's name is in the source code
+    pub span: Span,
+    pub id: NodeId,
 }
 
 /// A statement (the equivalent of 'a line of code').
@@ -88,64 +286,171 @@ pub struct FuncParam {
 /// `i = 1`, our code becomes a statement.
 ///
 /// See also: [Expr].
-#[derive(Debug, PartialEq, Eq, Clone)]
-pub enum Statement {
-    VarDeclaration(VarDeclaration),
-    WhileLoop(WhileLoop),
-    Assignment(Assignment),
-    Return(Option<Expr>),
-    Call(Call),
-    If(If),
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Statement<'a> {
+    VarDeclaration(VarDeclaration<'a>),
+    WhileLoop(WhileLoop<'a>),
+    Assignment(Assignment<'a>),
+    /// A `ret`, with its value (if any) and where its keyword is
+    Return(Option<Expr<'a>>, Span, NodeId),
+    Call(Call<'a>),
+    If(If<'a>),
+}
+
+impl Statement<'_> {
+    pub fn id(&self) -> NodeId {
+        match self {
+            Self::VarDeclaration(var_declaration) => var_declaration.id,
+            Self::WhileLoop(while_loop) => while_loop.id,
+            Self::Assignment(assignment) => assignment.id,
+            Self::Return(.., id) => *id,
+            Self::Call(call) => call.id,
+            Self::If(if_statement) => if_statement.id,
+        }
+    }
+
+    /// Returns where the statement is in the source code: the span of its keyword (like `ret`),
+    /// or of the name that it declares, assigns to, or calls.
+    pub fn span(&self) -> Span {
+        match self {
+            Self::VarDeclaration(var_declaration) => var_declaration.span,
+            Self::WhileLoop(while_loop) => while_loop.span,
+            Self::Assignment(assignment) => assignment.span,
+            Self::Return(_, span, _) => *span,
+            Self::Call(call) => call.span,
+            Self::If(if_statement) => if_statement.span,
+        }
+    }
+
+    fn id_mut(&mut self) -> &mut NodeId {
+        match self {
+            Self::VarDeclaration(var_declaration) => &mut var_declaration.id,
+            Self::WhileLoop(while_loop) => &mut while_loop.id,
+            Self::Assignment(assignment) => &mut assignment.id,
+            Self::Return(.., id) => id,
+            Self::Call(call) => &mut call.id,
+            Self::If(if_statement) => &mut if_statement.id,
+        }
+    }
 }
 
 /// A variable declaration.
 ///
 /// This struct stores the name and type of the declared variable, as well as its
 /// initial value.
-#[derive(Debug, PartialEq, Eq, Clone)]
-pub struct VarDeclaration {
-    pub var_name: String,
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct VarDeclaration<'a> {
+    pub var_name: &'a str,
     pub var_type: Type,
-    pub var_value: Expr,
+    pub var_value: Expr<'a>,
+    /// Where the variable's name is in the source code
+    pub span: Span,
+    pub id: NodeId,
 }
 
 /// An if statement.
 ///
 /// Note, `then_body` corresponds to the statements to be executed if the condition is true,
 /// and `else_body` (optional) corresponds to the "else" block of the if statement.
-#[derive(Debug, PartialEq, Eq, Clone)]
-pub struct If {
-    pub condition: Expr,
-    pub then_body: Vec<Statement>,
-    pub else_body: Option<Vec<Statement>>,
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct If<'a> {
+    pub condition: Expr<'a>,
+    pub then_body: Vec<Statement<'a>>,
+    pub else_body: Option<Vec<Statement<'a>>>,
+    /// Where the `if` keyword is in the source code
+    pub span: Span,
+    pub id: NodeId,
 }
 
 /// A while loop (its 'while condition' and its body).
-#[derive(Debug, PartialEq, Eq, Clone)]
-pub struct WhileLoop {
-    pub condition: Expr,
-    pub body: Vec<Statement>,
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct WhileLoop<'a> {
+    pub condition: Expr<'a>,
+    pub body: Vec<Statement<'a>>,
+    /// Where the `while` keyword is in the source code
+    pub span: Span,
+    pub id: NodeId,
 }
 
 /// An expression, which is any piece of code that has a value.
 ///
 /// For example, `current_length` or `1 + 2` or `foo("bye")` are expressions.
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Expr<'a> {
+    Identifier(Identifier<'a>),
+    /// An integer literal, with where it is in the source code
+    IntLiteral(&'a str, Span, NodeId),
+    /// A bool literal, with where it is in the source code
+    BoolLiteral(bool, Span, NodeId),
+    /// A string literal (without its quotes), with where it is in the source code
+    StrLiteral(&'a str, Span, NodeId),
+    Binary(Binary<'a>),
+    Comparison(Comparison<'a>),
+    Call(Call<'a>),
+    Unary(Unary<'a>),
+}
+
+impl Expr<'_> {
+    pub fn id(&self) -> NodeId {
+        match self {
+            Self::Identifier(identifier) => identifier.id,
+            Self::IntLiteral(.., id) | Self::BoolLiteral(.., id) | Self::StrLiteral(.., id) => *id,
+            Self::Binary(binary) => binary.id,
+            Self::Comparison(comparison) => comparison.id,
+            Self::Call(call) => call.id,
+            Self::Unary(unary) => unary.id,
+        }
+    }
+
+    /// Returns where the expression is in the source code: the span of its name or literal, or
+    /// of its (outermost) operator.
+    pub fn span(&self) -> Span {
+        match self {
+            Self::Identifier(identifier) => identifier.span,
+            Self::IntLiteral(_, span, _) | Self::BoolLiteral(_, span, _) | Self::StrLiteral(_, span, _) => *span,
+            Self::Binary(binary) => binary.span,
+            Self::Comparison(comparison) => comparison.span,
+            Self::Call(call) => call.span,
+            Self::Unary(unary) => unary.span,
+        }
+    }
+
+    fn id_mut(&mut self) -> &mut NodeId {
+        match self {
+            Self::Identifier(identifier) => &mut identifier.id,
+            Self::IntLiteral(.., id) | Self::BoolLiteral(.., id) | Self::StrLiteral(.., id) => id,
+            Self::Binary(binary) => &mut binary.id,
+            Self::Comparison(comparison) => &mut comparison.id,
+            Self::Call(call) => &mut call.id,
+            Self::Unary(unary) => &mut unary.id,
+        }
+    }
+}
+
+/// A variable that's used by name, like `x` in `x + 1`.
 #[derive(Debug, PartialEq, Eq, Clone)]
-pub enum Expr {
-    Identifier(String),
-    IntLiteral(String),
-    BoolLiteral(bool),
-    Binary(Binary),
-    Comparison(Comparison),
-    Call(Call),
-    Unary(Unary),
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Identifier<'a> {
+    pub name: &'a str,
+    /// Where the name is in the source code
+    pub span: Span,
+    pub id: NodeId,
 }
 
 /// An assignment statement (the variable name and the new value).
-#[derive(Debug, PartialEq, Eq, Clone)]
-pub struct Assignment {
-    pub name: String,
-    pub value: Box<Expr>,
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Assignment<'a> {
+    pub name: &'a str,
+    pub value: &'a mut Expr<'a>,
+    /// Where the variable's name is in the source code
+    pub span: Span,
+    pub id: NodeId,
 }
 
 /// A binary expression (the operator and the left/right-hand sides).
@@ -154,15 +459,20 @@ pub struct Assignment {
 /// - left: `a`
 /// - operator: `+`
 /// - right: `foo(1)`
-#[derive(Debug, PartialEq, Eq, Clone)]
-pub struct Binary {
-    pub left: Box<Expr>,
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Binary<'a> {
+    pub left: &'a mut Expr<'a>,
     pub operator: BinaryOperator,
-    pub right: Box<Expr>,
+    pub right: &'a mut Expr<'a>,
+    /// Where the operator is in the source code
+    pub span: Span,
+    pub id: NodeId,
 }
 
 /// An operator for the [Binary] expression.
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum BinaryOperator {
     Add,
     Subtract,
@@ -201,15 +511,20 @@ impl From<OperatorSymbol> for BinaryOperator {
 /// - left: `a`
 /// - operator: `<`
 /// - right: `foo(1)`
-#[derive(Debug, PartialEq, Eq, Clone)]
-pub struct Comparison {
-    pub left: Box<Expr>,
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Comparison<'a> {
+    pub left: &'a mut Expr<'a>,
     pub operator: ComparisonOperator,
-    pub right: Box<Expr>,
+    pub right: &'a mut Expr<'a>,
+    /// Where the operator is in the source code
+    pub span: Span,
+    pub id: NodeId,
 }
 
 /// An operator for the [Comparison] expression.
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum ComparisonOperator {
     NotEqualTo,
     EqualTo,
@@ -248,24 +563,104 @@ impl From<ComparatorSymbol> for ComparisonOperator {
 /// A call expression (the name of the function to call and the arguments to pass).
 ///
 /// For example, `foo(a, 12 - b, "test")` is a call expression with 3 args.
-#[derive(Debug, PartialEq, Eq, Clone)]
-pub struct Call {
-    pub function_name: String,
-    pub args: Vec<Expr>,
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Call<'a> {
+    pub function_name: &'a str,
+    pub args: Vec<Expr<'a>>,
+    /// Where the function's name is in the source code
+    pub span: Span,
+    pub id: NodeId,
 }
 
 /// A unary expression, which consists of an operator (e.g. "cast to u32") and a value.
-#[derive(Clone, Debug, Eq, PartialEq)]
-pub struct Unary {
+#[derive(Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Unary<'a> {
     pub operator: UnaryOperator,
-    pub operand: Box<Expr>,
+    pub operand: &'a mut Expr<'a>,
+    /// Where the operator (like the `-` in `-x`) is in the source code
+    pub span: Span,
+    pub id: NodeId,
 }
 
 /// A unary operator, like "cast to u32" or "not".
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum UnaryOperator {
     /// A cast converts its operand into the specified destination [Type].
     Cast(Type),
     /// Logical negation.
     Negate
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Lexer, Parser};
+
+    fn parse<'a>(arena: &'a Arena<'a>, source_code: &'a str) -> Program<'a> {
+        // Leaked, so that the program can borrow its names from them for the rest of the test
+        let tokens = Lexer::lex(source_code).unwrap().leak();
+        Parser::parse_program(arena, tokens).unwrap()
+    }
+
+    /// Collects the id of every node in a tree.
+    struct IdCollector(Vec<NodeId>);
+
+    impl Visitor<'_> for IdCollector {
+        fn visit_func_proto(&mut self, func_proto: &FuncProto) {
+            self.0.push(func_proto.id);
+            self.0.extend(func_proto.params.iter().map(|param| param.id));
+        }
+
+        fn visit_statement(&mut self, statement: &Statement) {
+            if !matches!(statement, Statement::Call(_)) {
+                self.0.push(statement.id());
+            }
+            visit::walk_statement(self, statement);
+        }
+
+        fn visit_expr(&mut self, expr: &Expr) {
+            if !matches!(expr, Expr::Call(_)) {
+                self.0.push(expr.id());
+            }
+            visit::walk_expr(self, expr);
+        }
+
+        fn visit_call(&mut self, call: &Call) {
+            self.0.push(call.id);
+            visit::walk_call(self, call);
+        }
+    }
+
+    #[test]
+    fn node_count() {
+        let source_code = "extern fn f(u8 x)\npub fn main() u8 {\n    if 1 < 2 {\n        f(-3)\n    }\n    ret 0\n}\n";
+        let arena = Arena::new();
+        let program = parse(&arena, source_code);
+
+        // extern, main, if, <, 1, 2, call, -, 3, ret, 0
+        assert_eq!(program.node_count(), 11);
+    }
+
+    #[test]
+    fn node_ids() {
+        let arena = Arena::new();
+        let mut program = parse(&arena, "extern fn f(u8 x)\npub fn main() u8 {\n    f(-3)\n    ret 0\n}\n");
+        let mut collector = IdCollector(Vec::new());
+        collector.visit_program(&program);
+        // Every node is numbered once, in the order that it was parsed
+        let mut ids = collector.0;
+        ids.sort();
+        assert_eq!(ids, (0..8).map(NodeId).collect::<Vec<_>>());
+
+        program.extend(parse(&arena, "fn g() {\n    ret\n}\n"));
+        let mut collector = IdCollector(Vec::new());
+        collector.visit_program(&program);
+        let mut ids = collector.0;
+        ids.sort();
+        assert_eq!(ids, (0..10).map(NodeId).collect::<Vec<_>>());
+        assert_eq!(program.global_statements[2].id(), NodeId(8));
+    }
+}
@@ -0,0 +1,96 @@
+use std::fmt;
+
+use crate::lexing::token::{Span, Token};
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ParsingError {
+    /// The parser expected one of `expected` where the span says, but found `found` (which is
+    /// `None` if the file ended).
+    UnexpectedToken { expected: Vec<Expected>, found: Option<Box<Token<'static>>>, span: Span },
+    /// A function is defined inside another function.
+    NestedFunction,
+    /// Comparisons are chained, like `a < b < c`.
+    ChainedComparison,
+    /// Expressions or bodies are nested more deeply than the parser allows (see
+    /// [Parser::parse_program_with_max_depth](crate::Parser::parse_program_with_max_depth)).
+    TooDeeplyNested { what: &'static str, max_depth: usize },
+    /// An assignment is used as the condition of an `if` or `while`, like `if x = 1`.
+    AssignmentInCondition,
+}
+
+/// Something that the parser can expect to find next (see [ParsingError::UnexpectedToken]).
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Expected {
+    Token(Token<'static>),
+    Identifier,
+    Type,
+    Expression,
+    Statement,
+    /// A function definition or an `extern` function declaration
+    GlobalStatement,
+}
+
+impl ParsingError {
+    /// Returns the code that identifies this kind of error, like `E0101` (see
+    /// [Diagnostic::code](crate::Diagnostic::code)).
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::UnexpectedToken { .. } => "E0101",
+            Self::NestedFunction => "E0102",
+            Self::ChainedComparison => "E0103",
+            Self::TooDeeplyNested { .. } => "E0104",
+            Self::AssignmentInCondition => "E0105",
+        }
+    }
+}
+
+impl fmt::Display for ParsingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedToken { expected, found, .. } => {
+                write!(f, "expected ")?;
+                for (i, item) in expected.iter().enumerate() {
+                    match i {
+                        0 => {}
+                        _ if i == expected.len() - 1 => write!(f, " or ")?,
+                        _ => write!(f, ", ")?,
+                    }
+                    write!(f, "{}", item)?;
+                }
+                match found {
+                    Some(token) => write!(f, ", but found {}", describe(token)),
+                    None => write!(f, ", but the file ended"),
+                }
+            }
+            Self::NestedFunction => write!(f, "functions can't be defined inside other functions"),
+            Self::ChainedComparison => write!(f, "comparisons can't be chained (like 'a < b < c')"),
+            Self::TooDeeplyNested { what, max_depth } => {
+                write!(f, "{} is too deeply nested (more than {} levels)", what, max_depth)
+            }
+            Self::AssignmentInCondition => write!(f, "assignments can't be used as conditions"),
+        }
+    }
+}
+
+impl fmt::Display for Expected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Token(token) => write!(f, "{}", describe(token)),
+            Self::Identifier => write!(f, "an identifier"),
+            Self::Type => write!(f, "a type"),
+            Self::Expression => write!(f, "an expression"),
+            Self::Statement => write!(f, "a statement"),
+            Self::GlobalStatement => write!(f, "a function"),
+        }
+    }
+}
+
+/// Returns how `token` should be shown in an error message, like `'{'` or `a newline`.
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Newline => "a newline".to_string(),
+        Token::Comment(_) => "a comment".to_string(),
+        Token::Docstring(_) => "a docstring".to_string(),
+        token => format!("'{}'", token),
+    }
+}
@@ -0,0 +1,308 @@
+use crate::error::FlickError;
+use crate::lexing::cst::{Cst, SyntaxToken, Trivia};
+use crate::lexing::lexer::IdentifierPolicy;
+use crate::lexing::token::{OperatorSymbol, Token};
+use crate::parsing::ast::Arena;
+use crate::parsing::parser::Parser;
+
+/// Returns `source_code` formatted the way `flick fmt` formats it, keeping its comments:
+/// - Bodies are indented by four spaces, with `{` at the end of the line that starts them, and
+///   `}` on its own line (or followed by `else`).
+/// - Binary operators, comparisons, and assignments have a space on each side; there's a space
+///   after each `,`, but none inside parentheses or before a call's `(`.
+/// - Blank lines are kept, but runs of them become one, and they're dropped at the start and
+///   end of bodies and of the file. Functions are separated by a blank line (but `extern`
+///   functions can be grouped together).
+/// - Trailing whitespace is removed, and lines end with `\n`.
+///
+/// Unlike [unparse](crate::unparse), this works on the [concrete syntax](Cst), so tokens keep
+/// their exact text (like `r#while`), and nothing but whitespace changes.
+///
+/// Returns the errors in the code instead if it doesn't parse, since it can't be formatted.
+///
+/// ```
+/// let source_code = "fn  add(u8 a,u8 b) u8 {  ret a+b // sum\n}";
+/// let formatted = flick::format_source(source_code).unwrap();
+/// assert_eq!(formatted, "fn add(u8 a, u8 b) u8 {\n    ret a + b // sum\n}\n");
+/// ```
+pub fn format_source(source_code: &str) -> Result<String, Vec<FlickError>> {
+    format_source_with_policy(source_code, IdentifierPolicy::default())
+}
+
+/// Like [format_source], but identifiers can only contain the characters that
+/// `identifier_policy` allows (see [Lexer::lex_with_policy](crate::Lexer::lex_with_policy)).
+pub fn format_source_with_policy(
+    source_code: &str,
+    identifier_policy: IdentifierPolicy,
+) -> Result<String, Vec<FlickError>> {
+    let cst = Cst::with_policy(source_code, identifier_policy).map_err(|err| vec![err])?;
+    let (tokens, spans) = cst.tokens_with_spans();
+    Parser::parse_program_with_spans(&Arena::new(), &tokens, &spans)?;
+
+    let lines = split_lines(&cst);
+    let mut formatter = Formatter { source: String::new(), depth: 0, blank_line: false, after_open: true, after_item: None };
+    for (i, line) in lines.iter().enumerate() {
+        // A comment before a function belongs to it, so it's set apart the same way
+        let next_code_line = lines[i..].iter().find(|line| !line.tokens.is_empty());
+        formatter.line(line, next_code_line);
+    }
+    Ok(formatter.source)
+}
+
+/// A line of formatted code: its tokens (without the newline that ends it), and the comment at
+/// the end of it (if it has one)
+#[derive(Default)]
+struct Line<'a> {
+    tokens: Vec<&'a SyntaxToken>,
+    comment: Option<&'a str>,
+}
+
+impl Line<'_> {
+    fn is_blank(&self) -> bool {
+        self.tokens.is_empty() && self.comment.is_none()
+    }
+
+    fn starts_with(&self, token: &Token) -> bool {
+        self.tokens.first().is_some_and(|first| first.token == *token)
+    }
+
+    /// Returns `true` if the line is an `extern` function declaration.
+    fn is_extern(&self) -> bool {
+        self.starts_with(&Token::Extern)
+    }
+}
+
+/// Splits the CST into lines, breaking lines after each `{` and around each `}`, so that bodies
+/// always span lines (like `fn foo() {}`, which stays on one line, or `} else {`).
+fn split_lines(cst: &Cst) -> Vec<Line<'_>> {
+    let mut lines = Vec::new();
+    let mut line = Line::default();
+    for syntax_token in &cst.tokens {
+        // Comments end lines, so they're always right before a newline (or the end of the file)
+        if let Some(comment) = comment_in(&syntax_token.leading_trivia) {
+            line.comment = Some(comment);
+        }
+        if syntax_token.token == Token::Newline {
+            lines.push(std::mem::take(&mut line));
+            continue;
+        }
+
+        let previous = line.tokens.last().map(|previous| &previous.token);
+        let breaks_line = match (previous, &syntax_token.token) {
+            (None, _) => false,
+            (Some(Token::LSquirly), next) => *next != Token::RSquirly,
+            (Some(_), Token::RSquirly) => true,
+            (Some(Token::RSquirly), next) => *next != Token::Else,
+            _ => false,
+        };
+        if breaks_line {
+            lines.push(std::mem::take(&mut line));
+        }
+        line.tokens.push(syntax_token);
+    }
+    if let Some(comment) = comment_in(&cst.trailing_trivia) {
+        line.comment = Some(comment);
+    }
+    lines.push(line);
+    lines
+}
+
+/// Returns the comment (or docstring) in `trivia`, without trailing whitespace (like the `\r` of
+/// a `\r\n`).
+fn comment_in(trivia: &[Trivia]) -> Option<&str> {
+    trivia.iter().find_map(|trivia| match trivia {
+        Trivia::Comment(text) | Trivia::Docstring(text) => Some(text.trim_end()),
+        Trivia::Whitespace(_) => None,
+    })
+}
+
+/// Builds up the formatted source code line by line, keeping track of how deeply the current
+/// line is indented and which blank lines it needs
+struct Formatter {
+    source: String,
+    depth: usize,
+    /// Whether there were blank lines since the last line that was written
+    blank_line: bool,
+    /// Whether the last line that was written opened a body (or nothing has been written yet)
+    after_open: bool,
+    /// Whether the last line that was written ended a function at the top level, and if so,
+    /// whether it's an `extern` function
+    after_item: Option<bool>,
+}
+
+impl Formatter {
+    /// Appends `line` (along with the blank line before it, if it needs one), where
+    /// `next_code_line` is the first line from `line` on that isn't just a comment.
+    fn line(&mut self, line: &Line, next_code_line: Option<&Line>) {
+        if line.is_blank() {
+            self.blank_line = true;
+            return;
+        }
+
+        let closes_body = line.starts_with(&Token::RSquirly);
+        if closes_body {
+            self.depth = self.depth.saturating_sub(1);
+        }
+        let separates_items = match (self.after_item, next_code_line) {
+            (Some(after_extern), Some(next)) if self.depth == 0 => !(after_extern && next.is_extern()),
+            _ => false,
+        };
+        if !self.after_open && !closes_body && (self.blank_line || separates_items) {
+            self.source.push('\n');
+        }
+        self.blank_line = false;
+
+        self.source.push_str(&"    ".repeat(self.depth));
+        self.source.push_str(&join_tokens(&line.tokens));
+        if let Some(comment) = line.comment {
+            if !line.tokens.is_empty() {
+                self.source.push(' ');
+            }
+            self.source.push_str(comment);
+        }
+        self.source.push('\n');
+
+        let skipped = usize::from(closes_body);
+        for syntax_token in &line.tokens[skipped..] {
+            match syntax_token.token {
+                Token::LSquirly => self.depth += 1,
+                Token::RSquirly => self.depth = self.depth.saturating_sub(1),
+                _ => {}
+            }
+        }
+        self.after_open = line.tokens.last().is_some_and(|last| last.token == Token::LSquirly);
+        self.after_item = match (self.depth, line.tokens.is_empty()) {
+            (0, false) => Some(line.is_extern()),
+            _ => None,
+        };
+    }
+}
+
+/// Returns the tokens of a line with the spaces that go between them.
+fn join_tokens(tokens: &[&SyntaxToken]) -> String {
+    let kinds: Vec<_> = tokens.iter().map(|syntax_token| &syntax_token.token).collect();
+    let mut source = String::new();
+    for (i, syntax_token) in tokens.iter().enumerate() {
+        if i > 0 && has_space_between(&kinds[..i], kinds[i]) {
+            source.push(' ');
+        }
+        source.push_str(&syntax_token.text);
+    }
+    source
+}
+
+/// Returns `true` if there's a space between `next` and the (non-empty) tokens `before` it on
+/// its line, like in `x + 1` and `(u8) x`, but not `foo(x, -1)` or `{}`.
+fn has_space_between(before: &[&Token], next: &Token) -> bool {
+    let (previous, earlier) = before.split_last().expect("there's a token before `next`");
+    if **previous == Token::OperatorSymbol(OperatorSymbol::Minus) && starts_operand(earlier) {
+        return false; // `previous` negates `next`
+    }
+    !matches!(
+        (previous, next),
+        (Token::LParen, _)
+            | (_, Token::RParen | Token::Comma)
+            | (Token::Identifier(_), Token::LParen)
+            | (Token::LSquirly, Token::RSquirly)
+    )
+}
+
+/// Returns `true` if an operand (rather than an operator) comes after the tokens `before` it,
+/// so a `-` after them is a negation, like the `-`s in `ret -x`, `(-1)`, and `(i8) -2`.
+fn starts_operand(before: &[&Token]) -> bool {
+    match before {
+        [] | [.., Token::LParen, Token::Type(_), Token::RParen] => true,
+        [.., previous] => matches!(
+            previous,
+            Token::OperatorSymbol(_)
+                | Token::ComparatorSymbol(_)
+                | Token::AssignmentSymbol(_)
+                | Token::LParen
+                | Token::Comma
+                | Token::Ret
+                | Token::If
+                | Token::While
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexing::lexer::Lexer;
+    use crate::parsing::unparse::unparse;
+
+    fn format(source_code: &str) -> String {
+        let formatted = format_source(source_code).unwrap();
+
+        // Formatting doesn't change what the code means, and formatted code stays the same
+        let unparsed = |source_code: &str| {
+            unparse(&Parser::parse_program(&Arena::new(), &Lexer::lex(source_code).unwrap()).unwrap())
+        };
+        assert_eq!(unparsed(&formatted), unparsed(source_code));
+        assert_eq!(format_source(&formatted).unwrap(), formatted);
+        formatted
+    }
+
+    #[test]
+    fn indents_and_spaces() {
+        let source_code = "pub fn main() u8 {\nu8 x=-1\n  if x>=(2*-x) {\n\t\tx+=foo( x ,-3 )\n}else{\nret  -( x%2 )\n}\nret (i8)-x\n}";
+        let expected = "pub fn main() u8 {\n    u8 x = -1\n    if x >= (2 * -x) {\n        x += foo(x, -3)\n    } else {\n        ret -(x % 2)\n    }\n    ret (i8) -x\n}\n";
+        assert_eq!(format(source_code), expected);
+    }
+
+    #[test]
+    fn places_braces() {
+        let source_code = "fn foo() { ret\n}\nfn bar() {}\nfn baz() {\nwhile true { bar()\n}\n}";
+        let expected = "fn foo() {\n    ret\n}\n\nfn bar() {}\n\nfn baz() {\n    while true {\n        bar()\n    }\n}\n";
+        assert_eq!(format(source_code), expected);
+    }
+
+    #[test]
+    fn keeps_comments() {
+        let source_code = "// header\r\n\r\n/// docs   \r\nfn foo() {   // starts\r\n  // inside\r\n  ret 0   // done\r\n}\r\n// the end";
+        let expected = "// header\n\n/// docs\nfn foo() { // starts\n    // inside\n    ret 0 // done\n}\n// the end\n";
+        assert_eq!(format(source_code), expected);
+    }
+
+    #[test]
+    fn normalizes_blank_lines() {
+        let source_code = "\n\nextern fn a()\nextern fn b()\n// main\npub fn main() u8 {\n\n    u8 x = 1\n\n\n\n    ret x\n\n}\n\n\n";
+        let expected = "extern fn a()\nextern fn b()\n\n// main\npub fn main() u8 {\n    u8 x = 1\n\n    ret x\n}\n";
+        assert_eq!(format(source_code), expected);
+
+        assert_eq!(format(""), "");
+        assert_eq!(format("\n  \n"), "");
+    }
+
+    #[test]
+    fn keeps_exact_token_text() {
+        let source_code = "fn r#if(u8 cafe\u{301}) {\nret\n}";
+        let expected = "fn r#if(u8 cafe\u{301}) {\n    ret\n}\n";
+        assert_eq!(format(source_code), expected);
+    }
+
+    #[test]
+    fn formats_examples() {
+        let examples = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("examples");
+        for entry in std::fs::read_dir(examples).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_none_or(|extension| extension != "fl") {
+                continue;
+            }
+            let source_code = std::fs::read_to_string(&path).unwrap();
+            // some examples (like `test.fl`) aren't valid Flick
+            if format_source(&source_code).is_ok() {
+                format(&source_code);
+            }
+        }
+    }
+
+    #[test]
+    fn reports_syntax_errors() {
+        let source_code = "fn foo() {\n    ret ret\n}\n";
+        assert!(format_source(source_code).is_err());
+        let source_code = "fn foo() {\n    ret $\n}\n";
+        assert!(format_source(source_code).is_err());
+    }
+}
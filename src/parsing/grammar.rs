@@ -0,0 +1,261 @@
+use std::fmt::Write;
+
+/// A rule of Flick's grammar: a symbol, and what it can be (in EBNF).
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Rule {
+    /// The symbol, like `statement`; tokens (whose rules describe characters rather than other
+    /// tokens) are in capitals, like `IDENTIFIER`
+    pub name: &'static str,
+    /// What the symbol can be, in EBNF, like `"ret" , [ expression ]`
+    pub definition: &'static str,
+    /// The function that parses (or lexes) the symbol, like `Parser::parse_statement`
+    pub parsed_by: &'static str,
+}
+
+/// Flick's grammar, one rule for each function of the [Parser](crate::Parser) (and then one for
+/// each kind of token that the [Lexer](crate::Lexer) makes), starting with the whole program.
+///
+/// The rules follow the parser's structure, so when the parser changes, its rules here should
+/// change with it; the tests check that every keyword and symbol that the lexer knows is in the
+/// grammar, and that programs written from the grammar parse.
+pub const GRAMMAR: &[Rule] = &[
+    Rule {
+        name: "program",
+        definition: r#"{ NEWLINE } , { global_statement , { NEWLINE } }"#,
+        parsed_by: "Parser::parse_program",
+    },
+    Rule {
+        name: "global_statement",
+        definition: r#""extern" , [ STR_LITERAL ] , function_signature | function_definition"#,
+        parsed_by: "Parser::parse_global_statement",
+    },
+    Rule {
+        name: "function_definition",
+        definition: r#"[ "pub" ] , function_signature , body"#,
+        parsed_by: "Parser::parse_func_def",
+    },
+    Rule {
+        name: "function_signature",
+        definition: r#""fn" , IDENTIFIER , parameters , [ TYPE ]"#,
+        parsed_by: "Parser::parse_func_proto",
+    },
+    Rule {
+        name: "parameters",
+        definition: r#""(" , [ TYPE , IDENTIFIER , { "," , TYPE , IDENTIFIER } ] , ")""#,
+        parsed_by: "Parser::parse_func_params",
+    },
+    Rule {
+        name: "body",
+        definition: r#""{" , { NEWLINE | statement , NEWLINE } , "}""#,
+        parsed_by: "Parser::parse_body",
+    },
+    Rule {
+        name: "statement",
+        definition: r#"var_declaration | while_loop | return | if_statement | assignment | call"#,
+        parsed_by: "Parser::parse_statement",
+    },
+    Rule {
+        name: "var_declaration",
+        definition: r#"TYPE , IDENTIFIER , "=" , expression"#,
+        parsed_by: "Parser::parse_var_declaration",
+    },
+    Rule {
+        name: "while_loop",
+        definition: r#""while" , expression , body"#,
+        parsed_by: "Parser::parse_while_loop",
+    },
+    Rule {
+        name: "return",
+        definition: r#""ret" , [ expression ]"#,
+        parsed_by: "Parser::parse_return_statement",
+    },
+    Rule {
+        name: "if_statement",
+        definition: r#""if" , expression , body , [ "else" , ( if_statement | body ) ]"#,
+        parsed_by: "Parser::parse_if_statement",
+    },
+    Rule {
+        name: "assignment",
+        definition: r#"IDENTIFIER , ( "=" | "+=" | "-=" | "*=" | "/=" ) , expression"#,
+        parsed_by: "Parser::parse_assignment",
+    },
+    Rule {
+        name: "expression",
+        definition: r#"comparison"#,
+        parsed_by: "Parser::parse_expr",
+    },
+    Rule {
+        name: "comparison",
+        definition: r#"sum , [ ( "==" | "!=" | "<" | ">" | "<=" | ">=" ) , sum ]"#,
+        parsed_by: "Parser::parse_comparison_expression",
+    },
+    Rule {
+        name: "sum",
+        definition: r#"product , { ( "+" | "-" ) , product }"#,
+        parsed_by: "Parser::parse_add_sub_expr",
+    },
+    Rule {
+        name: "product",
+        definition: r#"unary , { ( "*" | "/" | "%" ) , unary }"#,
+        parsed_by: "Parser::parse_mul_div_rem_expr",
+    },
+    Rule {
+        name: "unary",
+        definition: r#""-" , unary | "(" , TYPE , ")" , unary | primary"#,
+        parsed_by: "Parser::parse_unary_expr",
+    },
+    Rule {
+        name: "primary",
+        definition: r#""(" , expression , ")" | call | atom"#,
+        parsed_by: "Parser::parse_primary_expr",
+    },
+    Rule {
+        name: "call",
+        definition: r#"IDENTIFIER , "(" , [ expression , { "," , expression } ] , ")""#,
+        parsed_by: "Parser::parse_call",
+    },
+    Rule {
+        name: "atom",
+        definition: r#"IDENTIFIER | INT_LITERAL | STR_LITERAL | "true" | "false""#,
+        parsed_by: "Parser::parse_atom",
+    },
+    Rule {
+        name: "TYPE",
+        definition: r#""bool" | "str" | "void" | ( "i" | "u" ) , DIGIT , { DIGIT }"#,
+        parsed_by: "Lexer::read_word",
+    },
+    Rule {
+        name: "IDENTIFIER",
+        definition: r#"? a letter or "_", then any letters, digits, and "_"s, that isn't a keyword or a TYPE ? | "r#" , ? a letter or "_", then any letters, digits, and "_"s (even a keyword or a TYPE) ?"#,
+        parsed_by: "Lexer::read_word",
+    },
+    Rule {
+        name: "INT_LITERAL",
+        definition: r#"DIGIT , { DIGIT }"#,
+        parsed_by: "Lexer::read_int_literal",
+    },
+    Rule {
+        name: "STR_LITERAL",
+        definition: r#"'"' , { ? any character but '"', "\", and a line break ? | "\" , ( "n" | "t" | "0" | "\" | '"' ) } , '"'"#,
+        parsed_by: "Lexer::read_str_literal",
+    },
+    Rule {
+        name: "DIGIT",
+        definition: r#""0" | "1" | "2" | "3" | "4" | "5" | "6" | "7" | "8" | "9""#,
+        parsed_by: "Lexer::read_int_literal",
+    },
+    Rule {
+        name: "NEWLINE",
+        definition: r#"? a line break, or a comment ("//" to the end of the line) or a docstring ("///" to the end of the line) ?"#,
+        parsed_by: "Lexer::next_token",
+    },
+];
+
+/// Returns [Flick's grammar](GRAMMAR) in EBNF (as in ISO/IEC 14977), with the function that
+/// parses each rule in a comment before it.
+///
+/// ```
+/// let ebnf = flick::grammar::ebnf();
+/// assert!(ebnf.contains("return              = \"ret\" , [ expression ] ;"));
+/// ```
+pub fn ebnf() -> String {
+    let width = GRAMMAR.iter().map(|rule| rule.name.len()).max().unwrap_or(0);
+    let mut ebnf = String::new();
+    writeln!(ebnf, "(* The grammar of Flick {}, in EBNF (ISO/IEC 14977). *)", env!("CARGO_PKG_VERSION")).unwrap();
+    writeln!(ebnf, "(* Spaces and tabs only separate tokens, so they're left out. *)").unwrap();
+    for rule in GRAMMAR {
+        writeln!(ebnf, "\n(* {} *)", rule.parsed_by).unwrap();
+        writeln!(ebnf, "{:width$} = {} ;", rule.name, rule.definition, width = width).unwrap();
+    }
+    ebnf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexing::token::Token;
+    use crate::{Lexer, Parser};
+    use crate::ast::Arena;
+
+    /// Returns the symbols that `definition` refers to, and the terminals (the quoted strings)
+    /// in it, leaving out special sequences (`? ... ?`).
+    fn symbols_and_terminals(definition: &str) -> (Vec<&str>, Vec<&str>) {
+        let (mut symbols, mut terminals) = (Vec::new(), Vec::new());
+        let mut rest = definition;
+        while let Some(c) = rest.chars().next() {
+            match c {
+                '"' | '\'' | '?' => {
+                    let end = rest[1..].find(c).unwrap() + 1;
+                    if c != '?' {
+                        terminals.push(&rest[1..end]);
+                    }
+                    rest = &rest[end + 1..];
+                }
+                c if c.is_ascii_alphabetic() => {
+                    let end = rest.find(|c: char| !c.is_ascii_alphanumeric() && c != '_').unwrap_or(rest.len());
+                    symbols.push(&rest[..end]);
+                    rest = &rest[end..];
+                }
+                _ => rest = &rest[1..],
+            }
+        }
+        (symbols, terminals)
+    }
+
+    #[test]
+    fn every_symbol_is_defined_and_used() {
+        let names: Vec<_> = GRAMMAR.iter().map(|rule| rule.name).collect();
+        let mut used = vec!["program"];
+        for rule in GRAMMAR {
+            for symbol in symbols_and_terminals(rule.definition).0 {
+                assert!(names.contains(&symbol), "{} refers to {}, which isn't defined", rule.name, symbol);
+                used.push(symbol);
+            }
+        }
+        for name in names {
+            assert!(used.contains(&name), "{} is never used", name);
+        }
+    }
+
+    #[test]
+    fn every_keyword_and_symbol_is_in_the_grammar() {
+        let terminals: Vec<_> = GRAMMAR
+            .iter()
+            .filter(|rule| rule.name.chars().any(|c| c.is_ascii_lowercase()))
+            .flat_map(|rule| symbols_and_terminals(rule.definition).1)
+            .collect();
+        let symbols = "+ - * / % == != < > <= >= = += -= *= /= ( ) { } ,";
+        for keyword in Lexer::KEYWORDS.iter().copied().chain(symbols.split(' ')) {
+            assert!(terminals.contains(&keyword), "{} isn't in the grammar", keyword);
+        }
+        // And every terminal is a single token
+        for terminal in terminals {
+            let tokens = Lexer::lex(terminal).unwrap();
+            assert!(matches!(tokens[..], [ref token] if !matches!(token, Token::Identifier(_))), "{}", terminal);
+        }
+    }
+
+    #[test]
+    fn programs_from_the_grammar_parse() {
+        let program = "\
+extern \"c\" fn puts(str s) i32
+
+pub fn main() u8 {
+    u64 n = (u64) -(1 + 2) * 3 % 4
+    while n != 0 {
+        n -= 1
+    }
+    if n >= 2 {
+        puts(\"many\")
+    } else if true {
+        ret
+    } else {
+        n = f((n), 2)
+    }
+    ret 0
+}
+";
+        let tokens = Lexer::lex(program).unwrap();
+        assert!(Parser::parse_program(&Arena::new(), &tokens).is_ok());
+    }
+}
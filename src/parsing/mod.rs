@@ -5,25 +5,48 @@
 /// which further break down into [expressions](ast::Expr), which break down further into
 /// components.
 ///
-/// For example, `9 * (2 + 3);` corresponds to the following expression:
+/// For example, `9 * (2 + 3);` corresponds to the following expression (where each node's
+/// [NodeId](ast::NodeId) is the order that the parser finished it in):
 ///
 /// ```
 /// # use flick::ast::*;
+/// # use flick::token::Span;
+/// # let arena = Arena::new();
 /// # let _ =
 /// Expr::Binary(
 ///     Binary {
-///         left: Box::new(Expr::IntLiteral("9".to_string())),
+///         left: arena.alloc(Expr::IntLiteral("9", Span { start: 0, end: 1 }, NodeId(0))),
 ///         operator: BinaryOperator::Multiply,
-///         right: Box::new(Expr::Binary(
+///         right: arena.alloc(Expr::Binary(
 ///             Binary {
-///                 left: Box::new(Expr::IntLiteral("2".to_string())),
+///                 left: arena.alloc(Expr::IntLiteral("2", Span { start: 5, end: 6 }, NodeId(1))),
 ///                 operator: BinaryOperator::Add,
-///                 right: Box::new(Expr::IntLiteral("3".to_string())),
+///                 right: arena.alloc(Expr::IntLiteral("3", Span { start: 9, end: 10 }, NodeId(2))),
+///                 span: Span { start: 7, end: 8 },
+///                 id: NodeId(3),
 ///             }
 ///         )),
+///         span: Span { start: 2, end: 3 },
+///         id: NodeId(4),
 ///     }
 /// );
 /// ```
 pub mod ast;
 /// Module that defines the [Parser] struct for converting tokens to an abstract syntax tree.
 pub mod parser;
+/// Module that describes the syntax that the [Parser](parser::Parser) accepts, as an EBNF
+/// grammar (see [grammar::ebnf]).
+pub mod grammar;
+/// Module that defines the errors that the [Parser](parser::Parser) can find.
+pub mod error;
+/// Module that prints abstract syntax trees for people to read (see [printer::print_tree]).
+pub mod printer;
+/// Module that formats source code, keeping its comments (see [format::format_source]).
+pub mod format;
+/// Module that turns abstract syntax trees back into source code (see [unparse::unparse]).
+pub mod unparse;
+/// Module that measures source code, like how many lines of code and functions it has (see
+/// [stats::FileStats]).
+pub mod stats;
+/// Module that walks abstract syntax trees (see [visit::Visitor] and [visit::VisitorMut]).
+pub mod visit;
@@ -1,68 +1,341 @@
 use crate::lexing::token::AssignmentSymbol::*;
 use crate::lexing::token::OperatorSymbol::*;
-use crate::lexing::token::Token;
+use crate::error::{ErrorKind, FlickError};
+use crate::lexing::name::Name;
+use crate::lexing::token::{Span, Token};
 use crate::parsing::ast::*;
+use crate::parsing::error::{Expected, ParsingError};
 use crate::types::Type;
+use std::collections::VecDeque;
+use std::iter;
 
 /// A struct that takes tokens and parses them into a [abstract syntax tree](crate::parsing::ast)
-pub struct Parser<'a> {
-    /// The slice of tokens to parse
-    tokens: &'a [Token],
-    /// The index of the next unparsed token
-    cursor: usize,
+pub struct Parser<'a, 't> {
+    /// Where the tree's expressions are allocated
+    arena: &'a Arena<'a>,
+    /// Where the tokens to parse come from, with their locations in the source code (which are
+    /// all [Span::default()] if unknown); the names and literals in the tree are borrowed from
+    /// the tokens
+    tokens: Box<dyn Iterator<Item = crate::Result<(&'a Token<'a>, Span)>> + 't>,
+    /// The next few unparsed tokens, which have been taken from `tokens` to peek at
+    lookahead: VecDeque<(&'a Token<'a>, Span)>,
+    /// The location of the last token taken from `tokens`, if any (so that an error at the end
+    /// of the file can point just past it)
+    last_span: Option<Span>,
+    /// The error that `tokens` ended with, if any (like a lexing error), after which there's
+    /// nothing left to parse
+    token_error: Option<FlickError>,
+    /// How many expressions (or bodies) the parser is currently inside of
+    depth: usize,
+    /// The deepest that the expression being parsed goes (see [Parser::parse_chain]), at least
+    /// `depth`
+    deepest: usize,
+    /// The most that `depth` is allowed to be; see [Parser::parse_program_with_max_depth]
+    max_depth: usize,
+    /// The syntax errors that the parser has recovered from so far
+    errors: Vec<FlickError>,
+    /// The [NodeId] of the next node to be parsed
+    next_id: NodeId,
 }
 
-impl<'a> Parser<'a> {
-    /// Parses as many global statements as possible and returns a [Program] containing them all.
-    pub fn parse_program(tokens: &'a [Token]) -> Program {
-        let mut parser = Self { 
-            tokens, 
-            cursor: 0 
-        };
+impl<'a: 't, 't> Parser<'a, 't> {
+    /// How deeply expressions and bodies can be nested (e.g. in parentheses) by default.
+    pub const DEFAULT_MAX_DEPTH: usize = 128;
+
+    fn new(arena: &'a Arena<'a>, tokens: &'a [Token<'a>], spans: &'a [Span], max_depth: usize) -> Self {
+        let spans = spans.iter().copied().chain(iter::repeat(Span::default()));
+        Self::with_token_stream(arena, tokens.iter().zip(spans).map(Ok), max_depth)
+    }
+
+    fn with_token_stream(
+        arena: &'a Arena<'a>,
+        tokens: impl Iterator<Item = crate::Result<(&'a Token<'a>, Span)>> + 't,
+        max_depth: usize,
+    ) -> Self {
+        Self {
+            arena,
+            tokens: Box::new(tokens),
+            lookahead: VecDeque::new(),
+            last_span: None,
+            token_error: None,
+            depth: 0,
+            deepest: 0,
+            max_depth,
+            errors: Vec::new(),
+            next_id: NodeId::default(),
+        }
+    }
+
+    /// Parses as many global statements as possible and returns a [Program] containing them all
+    /// (whose expressions are allocated in `arena`, and whose names and literals are borrowed
+    /// from `tokens`, rather than copied).
+    ///
+    /// Returns an `Err()` with every syntax error in `tokens`: after an error, the parser skips
+    /// to the end of the statement (the next newline or `}`) or, if the error isn't inside a
+    /// function body, to the next function, and keeps parsing from there.
+    pub fn parse_program(arena: &'a Arena<'a>, tokens: &'a [Token<'a>]) -> Result<Program<'a>, Vec<FlickError>> {
+        Self::parse_program_with_spans(arena, tokens, &[])
+    }
 
+    /// Like [Parser::parse_program], but `spans` holds the location of each token (see
+    /// [Lexer::lex_with_spans](crate::Lexer::lex_with_spans)), which is recorded in the AST
+    /// (e.g. in [Identifier::span]) so that later passes can point at the code they reject.
+    pub fn parse_program_with_spans(
+        arena: &'a Arena<'a>,
+        tokens: &'a [Token<'a>],
+        spans: &'a [Span],
+    ) -> Result<Program<'a>, Vec<FlickError>> {
+        Self::parse_program_with_max_depth(arena, tokens, spans, Self::DEFAULT_MAX_DEPTH)
+    }
+
+    /// Like [Parser::parse_program_with_spans], but returns an error if expressions or bodies are
+    /// nested more than `max_depth` levels deep (e.g. `((((x))))` is 5 levels deep), rather than
+    /// [Parser::DEFAULT_MAX_DEPTH] levels.
+    ///
+    /// The parser (like the later passes) is recursive, so without a limit, a pathological
+    /// program like ten thousand nested parentheses would overflow the stack.
+    pub fn parse_program_with_max_depth(
+        arena: &'a Arena<'a>,
+        tokens: &'a [Token<'a>],
+        spans: &'a [Span],
+        max_depth: usize,
+    ) -> Result<Program<'a>, Vec<FlickError>> {
+        Self::new(arena, tokens, spans, max_depth).parse_global_statements()
+    }
+
+    /// Like [Parser::parse_program_with_max_depth], but parses the tokens (and their spans) as
+    /// they come, e.g. from a [Lexer](crate::Lexer) as it lexes them, rather than from a slice
+    /// of tokens that have all been lexed already. Only a couple of tokens are looked ahead at,
+    /// and each token is moved into `arena` as it's taken from `tokens`, since the program
+    /// borrows its names and literals from them.
+    ///
+    /// If `tokens` returns an error (like a lexing error), parsing stops there, and the error is
+    /// returned after any syntax errors that were found before it.
+    ///
+    /// ```
+    /// use flick::ast::Arena;
+    /// use flick::{IdentifierPolicy, Lexer, Parser};
+    ///
+    /// let lexer = Lexer::new("pub fn main() u8 {\n    ret 0\n}\n", IdentifierPolicy::default());
+    /// let arena = Arena::new();
+    /// let program = Parser::parse_token_stream(&arena, lexer, Parser::DEFAULT_MAX_DEPTH).unwrap();
+    /// assert_eq!(program.global_statements.len(), 1);
+    /// ```
+    pub fn parse_token_stream(
+        arena: &'a Arena<'a>,
+        tokens: impl IntoIterator<Item = crate::Result<(Token<'a>, Span)>> + 't,
+        max_depth: usize,
+    ) -> Result<Program<'a>, Vec<FlickError>> {
+        let tokens = tokens.into_iter().map(|result| result.map(|(token, span)| (arena.alloc_token(token), span)));
+        Self::with_token_stream(arena, tokens, max_depth).parse_global_statements()
+    }
+
+    /// Parses global statements until the tokens run out (see [Parser::parse_program]).
+    fn parse_global_statements(mut self) -> Result<Program<'a>, Vec<FlickError>> {
         let mut global_statements = Vec::new();
 
         loop {
-            parser.skip_newlines_comments_and_docstrings();
+            self.skip_newlines_comments_and_docstrings();
 
-            match parser.parse_global_statement() {
-                Some(s) => global_statements.push(s),
-                None => break,
+            match self.parse_global_statement() {
+                Ok(Some(s)) => global_statements.push(s),
+                Ok(None) => break,
+                // An error caused by the tokens ending early isn't worth reporting
+                Err(_) if self.token_error.is_some() => break,
+                Err(err) => {
+                    self.errors.push(err);
+                    self.skip_to_next_function();
+                }
             }
         }
+        self.errors.extend(self.token_error.take());
+
+        tracing::debug!(
+            global_statements = global_statements.len(),
+            errors = self.errors.len(),
+            "parsed tokens"
+        );
+        match self.errors.is_empty() {
+            true => Ok(Program { global_statements }),
+            false => Err(self.errors),
+        }
+    }
 
-        Program { global_statements }
+    /// Returns the id of a node that has just been parsed.
+    fn new_id(&mut self) -> NodeId {
+        let id = self.next_id;
+        self.next_id.0 += 1;
+        id
     }
 
     /// Returns a reference to the next token and advances the cursor past it.
-    fn next_token(&mut self) -> Option<&Token> {
-        let token = self.tokens.get(self.cursor);
-        self.cursor += 1;
-        token
+    fn next_token(&mut self) -> Option<&'a Token<'a>> {
+        self.look_ahead(1);
+        self.lookahead.pop_front().map(|(token, _)| token)
     }
 
     /// Returns a reference to the `n`-th token out of the remaining tokens.
     ///
     /// Note: this function returns `None` if fewer than `n` tokens remain.
     ///
-    /// This function doesn't affect the internal state of the lexer (i.e., it doesn't consume
-    /// any characters / it doesn't advance the internal cursor)
-    fn peek_token(&self, n: usize) -> Option<&Token> {
-        self.tokens.get(self.cursor + (n - 1)) // n-1 to fix indexing
+    /// This function doesn't consume any tokens, although it may take them from the token
+    /// stream to look at them.
+    fn peek_token(&mut self, n: usize) -> Option<&'a Token<'a>> {
+        self.look_ahead(n);
+        self.lookahead.get(n - 1).map(|(token, _)| *token) // n-1 to fix indexing
     }
 
-    /// Advances the cursor past the next `n` tokens without returning anything.
+    /// Takes tokens from the token stream until `n` tokens have been looked ahead at (or until
+    /// the stream ends).
+    fn look_ahead(&mut self, n: usize) {
+        while self.lookahead.len() < n && self.token_error.is_none() {
+            match self.tokens.next() {
+                Some(Ok((token, span))) => {
+                    self.lookahead.push_back((token, span));
+                    self.last_span = Some(span);
+                }
+                Some(Err(err)) => self.token_error = Some(err),
+                None => break,
+            }
+        }
+    }
+
+    /// Returns the location of the next token in the source code (or [Span::default()] if the
+    /// locations of the tokens aren't known).
+    fn peek_span(&mut self) -> Span {
+        self.look_ahead(1);
+        self.lookahead.front().map(|(_, span)| *span).unwrap_or_default()
+    }
+
+    /// Returns an error of the given `kind` at the next token.
+    fn err(&mut self, kind: ParsingError) -> FlickError {
+        FlickError {
+            index: self.peek_span().start,
+            kind: ErrorKind::ParsingError(kind),
+        }
+    }
+
+    /// Returns an error saying that the parser expected one of `expected`, rather than the next
+    /// token (or rather than the end of the file).
+    fn unexpected(&mut self, expected: Vec<Expected>) -> FlickError {
+        let span = match self.peek_token(1) {
+            Some(_) => self.peek_span(),
+            // Point just past the last token
+            None => self.last_span.map_or(Span::default(), |last| Span { start: last.end, end: last.end }),
+        };
+        let found = self.peek_token(1).map(|token| Box::new(token.clone().into_owned()));
+        FlickError {
+            index: span.start,
+            kind: ErrorKind::ParsingError(ParsingError::UnexpectedToken { expected, found, span }),
+        }
+    }
+
+    /// Runs `parse` one level deeper, returning an error if that's deeper than `max_depth` (`what`
+    /// is what's being parsed, like `"expression"`).
+    fn nested<T>(&mut self, what: &'static str, parse: impl FnOnce(&mut Self) -> crate::Result<T>) -> crate::Result<T> {
+        if self.depth == self.max_depth {
+            return Err(self.err(ParsingError::TooDeeplyNested { what, max_depth: self.max_depth }));
+        }
+        self.depth += 1;
+        self.deepest = self.deepest.max(self.depth);
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    /// Parses a chain of left-associative binary operators (the ones that `operator` returns for
+    /// the tokens it matches), like `A - B + C`, whose operands `parse_operand` parses.
+    ///
+    /// Each operator nests the chain before it one level deeper (as its left-hand side), so each
+    /// one counts towards `max_depth`, like a pair of parentheses does, on top of the deepest
+    /// operand. Otherwise, a long enough chain (like `1 + 1 + ... + 1`) would parse fine, but
+    /// overflow the stack in one of the later passes, which are recursive.
+    fn parse_chain(
+        &mut self,
+        operator: fn(&Token) -> Option<BinaryOperator>,
+        parse_operand: fn(&mut Self) -> crate::Result<Expr<'a>>,
+    ) -> crate::Result<Expr<'a>> {
+        // Only what's nested in this chain counts, so that its length is added to its own depth
+        let outer_deepest = std::mem::replace(&mut self.deepest, self.depth);
+        let mut left_expr_so_far = parse_operand(self)?;
+
+        let mut operators = 0;
+        while let Some(operator) = self.peek_token(1).and_then(operator) {
+            let span = self.peek_span();
+            self.skip_token();
+            let right = parse_operand(self)?;
+
+            operators += 1;
+            if self.deepest + operators > self.max_depth {
+                return Err(self.err(ParsingError::TooDeeplyNested { what: "expression", max_depth: self.max_depth }));
+            }
+            left_expr_so_far = Expr::Binary(Binary {
+                left: self.arena.alloc(left_expr_so_far),
+                operator,
+                right: self.arena.alloc(right),
+                span,
+                id: self.new_id(),
+            })
+        }
+
+        self.deepest = outer_deepest.max(self.deepest + operators);
+        Ok(left_expr_so_far)
+    }
+
+    /// Advances the cursor past the next token without returning anything.
     fn skip_token(&mut self) {
-        self.cursor += 1;
+        self.next_token();
     }
 
     /// Parses a global statement, like an external function declaration or a function definition.
-    fn parse_global_statement(&mut self) -> Option<GlobalStatement> {
-        match self.peek_token(1) {
-            Some(Token::Extern) => Some(GlobalStatement::Extern(self.parse_func_proto())),
-            Some(Token::Fn | Token::Pub) => Some(GlobalStatement::FuncDef(self.parse_func_def())),
-            Some(t) => panic!("Unknown global statement starting with token '{}'", t),
+    fn parse_global_statement(&mut self) -> crate::Result<Option<GlobalStatement<'a>>> {
+        Ok(match self.peek_token(1) {
+            Some(Token::Extern) => Some(GlobalStatement::Extern(self.parse_func_proto()?)),
+            Some(Token::Fn | Token::Pub) => Some(GlobalStatement::FuncDef(self.parse_func_def()?)),
+            Some(_) => return Err(self.unexpected(vec![Expected::GlobalStatement])),
             None => None,
+        })
+    }
+
+    /// Advances the cursor to the next token that can start a function (`fn`, `pub`, or
+    /// `extern`), so that parsing can continue after a syntax error outside of a function body.
+    ///
+    /// # Assumptions
+    ///
+    /// - The next token can't start a function, or it does but the previous token was already
+    ///   part of the bad global statement (so that the parser doesn't get stuck on it).
+    fn skip_to_next_function(&mut self) {
+        while let Some(token) = self.peek_token(1) {
+            if let Token::Fn | Token::Pub | Token::Extern = token {
+                break;
+            }
+            self.skip_token();
+        }
+    }
+
+    /// Advances the cursor past the rest of a statement that has a syntax error, so that parsing
+    /// can continue with the next statement; any bodies (`{ ... }`) in the statement are skipped
+    /// too.
+    ///
+    /// The statement ends with a newline (which is skipped) or with the `}` that ends the body
+    /// that it's in (which isn't). Returns `false` if it doesn't end before the file does or
+    /// before the next function starts (most likely because the body is missing its `}`), in
+    /// which case the body can't be finished.
+    fn skip_to_next_statement(&mut self) -> bool {
+        let mut open_bodies = 0;
+        loop {
+            match self.peek_token(1) {
+                None | Some(Token::Fn | Token::Pub | Token::Extern) => return false,
+                Some(Token::Newline) if open_bodies == 0 => {
+                    self.skip_token();
+                    return true;
+                }
+                Some(Token::RSquirly) if open_bodies == 0 => return true,
+                Some(Token::LSquirly) => open_bodies += 1,
+                Some(Token::RSquirly) => open_bodies -= 1,
+                Some(_) => {}
+            }
+            self.skip_token();
         }
     }
 
@@ -76,47 +349,50 @@ impl<'a> Parser<'a> {
 
     /// Parses the `fn foo(i64 x) i64` part of a function definition or an external function
     /// declaration.
-    fn parse_func_proto(&mut self) -> FuncProto {
-        let func_visibility = match (self.peek_token(1), self.peek_token(2)) {
-            (Some(Token::Pub), Some(Token::Fn)) => FuncVisibility::Public,
-            (Some(Token::Pub), Some(t)) => panic!("Expected 'pub fn' but received {}", t),
-            (Some(Token::Pub), None) => panic!("Expected 'pub fn' but file ended"),
-            (Some(Token::Extern), Some(Token::Fn)) => FuncVisibility::Extern,
-            (Some(Token::Extern), Some(t)) => panic!("Expected 'extern fn' but received {}", t),
-            (Some(Token::Extern), None) => panic!("Expected 'extern fn' but file ended"),
-            (Some(Token::Fn), _) => FuncVisibility::Private,
-            (Some(t), _) => panic!("Expected 'fn' or 'pub' but received {:?}", t),
-            (None, _) => panic!("Expected 'fn' or 'pub' but file ended"),
+    fn parse_func_proto(&mut self) -> crate::Result<FuncProto> {
+        let func_visibility = match self.peek_token(1) {
+            Some(Token::Pub) => FuncVisibility::Public,
+            Some(Token::Extern) => FuncVisibility::Extern,
+            _ => FuncVisibility::Private,
         };
 
         if func_visibility != FuncVisibility::Private {
             self.skip_token(); // skip the 'extern' / 'pub' in 'extern fn' / 'pub fn'
         }
-        self.skip_token();  // skip the 'fn'
+        // the library in 'extern "m" fn', if there is one
+        let library = match (&func_visibility, self.peek_token(1)) {
+            (FuncVisibility::Extern, Some(Token::StrLiteral(library))) => {
+                let library = library.to_string();
+                self.skip_token();
+                Some(library)
+            }
+            _ => None,
+        };
+        self.assert_next_token(Token::Fn)?;
 
-        let name = self.parse_identifier();
-        let params = self.parse_func_params();
+        let span = self.peek_span();
+        let name = self.parse_identifier()?;
+        let params = self.parse_func_params()?;
 
         let return_type = match self.peek_token(1) {
             Some(Token::LSquirly) => Type::Void,  // implicit void ret-type omitted before body opened
-            Some(Token::Newline) => Type::Void,   // implicit void ret-type omitted but no '{' because, e.g., extern fn
-            Some(Token::Type(_)) => self.parse_type(),
-            Some(t) => panic!(
-                "Expected return type for function '{}' but received {:?}",
-                name, t
-            ),
-            None => panic!(
-                "Expected return type for function '{}' but file ended",
-                name
-            ),
+            Some(Token::Newline) | None => Type::Void,   // implicit void ret-type omitted but no '{' because, e.g., extern fn
+            Some(Token::Type(_)) => self.parse_type()?,
+            Some(_) => {
+                let expected = vec![Expected::Type, Expected::Token(Token::LSquirly), Expected::Token(Token::Newline)];
+                return Err(self.unexpected(expected));
+            }
         };
 
-        FuncProto {
+        Ok(FuncProto {
             func_visibility,
-            name,
+            name: Name::intern(name),
             params,
             return_type: Box::new(return_type),
-        }
+            span,
+            id: self.new_id(),
+            library,
+        })
     }
 
     // TODO: Split the first part into a parse_func_proto function?
@@ -132,10 +408,10 @@ impl<'a> Parser<'a> {
     ///     ret a - x * 2;
     /// }
     /// ```
-    fn parse_func_def(&mut self) -> FuncDef {
-        let proto = self.parse_func_proto();
-        let body = self.parse_body();
-        FuncDef { proto, body }
+    fn parse_func_def(&mut self) -> crate::Result<FuncDef<'a>> {
+        let proto = self.parse_func_proto()?;
+        let body = self.parse_body()?;
+        Ok(FuncDef { proto, body })
     }
 
     /// Parses function parameters, which is useful when parsing a function definition.
@@ -151,36 +427,52 @@ impl<'a> Parser<'a> {
     /// # Assumptions
     ///
     /// - The function parameters are wrapped in parentheses.
-    fn parse_func_params(&mut self) -> Vec<FuncParam> {
-        self.assert_next_token(Token::LParen);
+    fn parse_func_params(&mut self) -> crate::Result<Vec<FuncParam>> {
+        self.assert_next_token(Token::LParen)?;
 
         let mut params = Vec::new();
 
         if let Some(Token::RParen) = self.peek_token(1) {
             self.skip_token();
-            return params;
+            return Ok(params);
         }
 
         loop {
-            let param_type = self.parse_type();
-            let param_name = self.parse_identifier();
+            let param_type = self.parse_type()?;
+            let span = self.peek_span();
+            let param_name = self.parse_identifier()?;
 
             let func_param = FuncParam {
                 param_type,
-                param_name,
+                param_name: Name::intern(param_name),
+                span,
+                id: self.new_id(),
             };
 
             params.push(func_param);
 
-            match self.next_token() {
-                Some(Token::RParen) => break,
-                Some(Token::Comma) => continue,
-                Some(token) => panic!("Expected ')' but received {}", token),
-                None => panic!("Expected ')' but file ended"),
+            if !self.parse_list_separator()? {
+                break;
             }
         }
 
-        params
+        Ok(params)
+    }
+
+    /// Parses the `,` between the items of a list in parentheses (like parameters or arguments),
+    /// or the `)` that ends it; returns `true` if there's another item.
+    fn parse_list_separator(&mut self) -> crate::Result<bool> {
+        match self.peek_token(1) {
+            Some(Token::RParen) => {
+                self.skip_token();
+                Ok(false)
+            }
+            Some(Token::Comma) => {
+                self.skip_token();
+                Ok(true)
+            }
+            _ => Err(self.unexpected(vec![Expected::Token(Token::Comma), Expected::Token(Token::RParen)])),
+        }
     }
 
     /// Parses the next statement, skipping comments and newlines.
@@ -188,54 +480,64 @@ impl<'a> Parser<'a> {
     /// # Flick example code
     /// - `print(x)`
     /// - `i += 1`
-    fn parse_statement(&mut self) -> Option<Statement> {
-        let statement = match (self.peek_token(1)?, self.peek_token(2)) {
-            (Token::Type(_), _) => Statement::VarDeclaration(self.parse_var_declaration()),
-            (Token::While, _) => Statement::WhileLoop(self.parse_while_loop()),
-            (Token::Fn, _) => panic!("Nested function definitions are not allowed"),
-            (Token::Ret, _) => Statement::Return(self.parse_return_statement()),
-            (Token::If, _) => Statement::If(self.parse_if_statement()),
+    fn parse_statement(&mut self) -> crate::Result<Option<Statement<'a>>> {
+        let Some(first_token) = self.peek_token(1) else { return Ok(None) };
+        let statement = match (first_token, self.peek_token(2)) {
+            (Token::Type(_), _) => Statement::VarDeclaration(self.parse_var_declaration()?),
+            (Token::While, _) => Statement::WhileLoop(self.parse_while_loop()?),
+            (Token::Fn, _) => return Err(self.err(ParsingError::NestedFunction)),
+            (Token::Ret, _) => {
+                let span = self.peek_span();
+                Statement::Return(self.parse_return_statement()?, span, self.new_id())
+            }
+            (Token::If, _) => Statement::If(self.parse_if_statement()?),
             (Token::Identifier(_), Some(Token::AssignmentSymbol(_))) => {
-                Statement::Assignment(self.parse_assignment())
+                Statement::Assignment(self.parse_assignment()?)
             }
-            (Token::Identifier(_), Some(Token::LParen)) => Statement::Call(self.parse_call()),
-            (s, _) => panic!("Unexpected token to start statement: {}", s), // TODO: skip this line and keep checking the file for errors
+            (Token::Identifier(_), Some(Token::LParen)) => Statement::Call(self.parse_call()?),
+            _ => return Err(self.unexpected(vec![Expected::Statement])),
         };
 
-        match self.next_token() {
-            Some(Token::Newline | Token::Comment(_) | Token::Docstring(_)) | None => Some(statement),
-            Some(token) => panic!("Expected newline or EOF but received {}", token),
+        match self.peek_token(1) {
+            Some(Token::Newline | Token::Comment(_) | Token::Docstring(_)) => self.skip_token(),
+            None => {}
+            Some(_) => return Err(self.unexpected(vec![Expected::Token(Token::Newline)])),
         }
+        Ok(Some(statement))
     }
 
-    /// Panics if the token stream ended or if the next token doesn't match `expected`.
-    fn assert_next_token(&mut self, expected: Token) {
-        match self.next_token() {
-            Some(token) if *token == expected => (),
-            Some(token) => panic!("Expected {:?} but received {:?}", expected, token),
-            None => panic!("Expected {:?} but file ended", expected),
+    /// Consumes the next token, or returns an error if the token stream ended or if the next
+    /// token doesn't match `expected`.
+    fn assert_next_token(&mut self, expected: Token<'static>) -> crate::Result<()> {
+        match self.peek_token(1) {
+            Some(token) if *token == expected => {
+                self.skip_token();
+                Ok(())
+            }
+            _ => Err(self.unexpected(vec![Expected::Token(expected)])),
         }
     }
 
-    /// Parses a built-in type, like [Type::Void], and panics if the next token isn't one.
-    fn parse_type(&mut self) -> Type {
-        match self.next_token() {
-            Some(Token::Type(var_type)) => var_type.clone(),
-            Some(t) => panic!("Expected type of variable but received {:?}", t),
-            None => panic!("Expected type of variable but file ended"),
+    /// Parses a built-in type, like [Type::Void], and returns an error if the next token isn't one.
+    fn parse_type(&mut self) -> crate::Result<Type> {
+        match self.peek_token(1) {
+            Some(Token::Type(var_type)) => {
+                let var_type = Type::clone(var_type);
+                self.skip_token();
+                Ok(var_type)
+            }
+            _ => Err(self.unexpected(vec![Expected::Type])),
         }
     }
 
-    // TODO: Error messages: split this function into several for caller to be more precise
-    /// Parses an identifier, like `foo` or `x`, and panics if the next token isn't one.
-    fn parse_identifier(&mut self) -> String {
-        match self.next_token() {
-            // TODO: Can we somehow get rid of this clone -- I think we can, but we have a choice:
-            //  1. either have each ast node own its strings (this is what we're doing) -> then we must clone
-            //  2. ast has a lifetime that outlives the token stream, in which case each ast can store &'a str
-            Some(Token::Identifier(id)) => id.clone(),
-            Some(t) => panic!("Expected identifier but received {:?}", t),
-            None => panic!("Expected identifier but received end of file"),
+    /// Parses an identifier, like `foo` or `x`, and returns an error if the next token isn't one.
+    fn parse_identifier(&mut self) -> crate::Result<&'a str> {
+        match self.peek_token(1) {
+            Some(Token::Identifier(id)) => {
+                self.skip_token();
+                Ok(id)
+            }
+            _ => Err(self.unexpected(vec![Expected::Identifier])),
         }
     }
 
@@ -244,25 +546,29 @@ impl<'a> Parser<'a> {
     /// # Flick example code
     /// - `i64 ten = 10`
     /// - `i64 hundred = 10 * ten`
-    fn parse_var_declaration(&mut self) -> VarDeclaration {
-        let var_type = self.parse_type();
+    fn parse_var_declaration(&mut self) -> crate::Result<VarDeclaration<'a>> {
+        let var_type = self.parse_type()?;
 
         // TODO: Error messages: Add custom error message to tell user that variables can't be named the same as types
         //  (e.g. "void" or "i64")
-        let var_name = self.parse_identifier();
+        let span = self.peek_span();
+        let var_name = self.parse_identifier()?;
 
-        self.assert_next_token(Token::AssignmentSymbol(Eq));
+        self.assert_next_token(Token::AssignmentSymbol(Eq))?;
 
-        let var_value = self.parse_expr();
+        let var_value = self.parse_expr()?;
 
-        VarDeclaration {
+        Ok(VarDeclaration {
             var_name,
             var_type,
             var_value,
-        }
+            span,
+            id: self.new_id(),
+        })
     }
 
-    /// Parses 0 or more statements surrounded by curly brackets, and panics if unsuccessful.
+    /// Parses 0 or more statements surrounded by curly brackets, and returns an error if
+    /// unsuccessful.
     ///
     /// # Flick example code
     /// - `{}`
@@ -274,9 +580,14 @@ impl<'a> Parser<'a> {
     ///     print(a)
     /// }
     /// ```
-    fn parse_body(&mut self) -> Vec<Statement> {
+    fn parse_body(&mut self) -> crate::Result<Vec<Statement<'a>>> {
+        self.nested("body", Self::parse_body_statements)
+    }
+
+    /// Parses a body, like [Parser::parse_body], without counting it as a level of nesting.
+    fn parse_body_statements(&mut self) -> crate::Result<Vec<Statement<'a>>> {
         let mut body = Vec::new();
-        self.assert_next_token(Token::LSquirly);
+        self.assert_next_token(Token::LSquirly)?;
 
         loop {
             self.skip_newlines_comments_and_docstrings();
@@ -286,16 +597,22 @@ impl<'a> Parser<'a> {
             }
 
             match self.parse_statement() {
-                Some(statement) => body.push(statement),
-                None => panic!("Expected body to be closed ('}}') but file ended"),
+                Ok(Some(statement)) => body.push(statement),
+                Ok(None) => return Err(self.unexpected(vec![Expected::Token(Token::RSquirly)])),
+                // Report the error and move on to the next statement, unless there isn't one, in
+                // which case the error is reported once the function it's in is given up on
+                Err(err) => match self.skip_to_next_statement() {
+                    true => self.errors.push(err),
+                    false => return Err(err),
+                },
             }
         }
 
-        self.assert_next_token(Token::RSquirly);
-        body
+        self.assert_next_token(Token::RSquirly)?;
+        Ok(body)
     }
 
-    /// Parses an if statement (`if [condition] [body]`) and panics if unsuccessful.
+    /// Parses an if statement (`if [condition] [body]`) and returns an error if unsuccessful.
     ///
     /// See also: [Parser::parse_expr], [Parser::parse_body]
     ///
@@ -304,32 +621,32 @@ impl<'a> Parser<'a> {
     /// if i * i < p {
     ///     i += 1
     /// }
-    fn parse_if_statement(&mut self) -> If {
-        self.assert_next_token(Token::If);
+    fn parse_if_statement(&mut self) -> crate::Result<If<'a>> {
+        let span = self.peek_span();
+        self.assert_next_token(Token::If)?;
 
-        let condition = self.parse_expr();
-        let then_body = self.parse_body();
+        let condition = self.parse_condition()?;
+        let then_body = self.parse_body()?;
 
         let else_body = match self.peek_token(1) {
-            Some(&Token::Else) => Some(self.parse_else_statement()),
+            Some(&Token::Else) => Some(self.parse_else_statement()?),
             _ => None
         };
 
-        If { condition, then_body, else_body }
+        Ok(If { condition, then_body, else_body, span, id: self.new_id() })
     }
     
-    fn parse_else_statement(&mut self) -> Vec<Statement> {
-        self.assert_next_token(Token::Else);
+    fn parse_else_statement(&mut self) -> crate::Result<Vec<Statement<'a>>> {
+        self.assert_next_token(Token::Else)?;
 
         match self.peek_token(1) {
-            Some(Token::If) => vec![Statement::If(self.parse_if_statement())],
+            Some(Token::If) => Ok(vec![Statement::If(self.parse_if_statement()?)]),
             Some(Token::LSquirly) => self.parse_body(),
-            Some(t) => panic!("Unexpected token '{}' after 'else'; expected 'else {{' or 'else if'", t),
-            None => panic!("Unexpected end of file after 'else'; expected 'else {{' or 'else if'")
+            _ => Err(self.unexpected(vec![Expected::Token(Token::LSquirly), Expected::Token(Token::If)])),
         }
     }
 
-    /// Parses a while loop (`while [expr] [body]`) and panics if unsuccessful.
+    /// Parses a while loop (`while [expr] [body]`) and returns an error if unsuccessful.
     ///
     /// See also: [Parser::parse_expr], [Parser::parse_body]
     ///
@@ -338,62 +655,73 @@ impl<'a> Parser<'a> {
     /// while i * i < p {
     ///     i += 1
     /// }
-    fn parse_while_loop(&mut self) -> WhileLoop {
-        self.assert_next_token(Token::While);
+    fn parse_while_loop(&mut self) -> crate::Result<WhileLoop<'a>> {
+        let span = self.peek_span();
+        self.assert_next_token(Token::While)?;
+
+        let condition = self.parse_condition()?;
+        let body = self.parse_body()?;
 
-        let condition = self.parse_expr();
-        let body = self.parse_body();
+        Ok(WhileLoop { condition, body, span, id: self.new_id() })
+    }
 
-        WhileLoop { condition, body }
+    /// Parses the condition of an if statement or a while loop, which can't be an assignment
+    /// (e.g. `x = 1` where `x == 1` was meant).
+    fn parse_condition(&mut self) -> crate::Result<Expr<'a>> {
+        let condition = self.parse_expr()?;
+        if let Some(Token::AssignmentSymbol(Eq)) = self.peek_token(1) {
+            return Err(self.err(ParsingError::AssignmentInCondition));
+        }
+        Ok(condition)
     }
 
-    /// Parses a return statement (`return [expr]` or just `return`), and panics if unsuccessful.
-    fn parse_return_statement(&mut self) -> Option<Expr> {
-        self.assert_next_token(Token::Ret);
+    /// Parses a return statement (`return [expr]` or just `return`), and returns an error if
+    /// unsuccessful.
+    fn parse_return_statement(&mut self) -> crate::Result<Option<Expr<'a>>> {
+        self.assert_next_token(Token::Ret)?;
 
-        match self.peek_token(1)? {
-            Token::Newline => None,
-            _ => Some(self.parse_expr()),
+        match self.peek_token(1) {
+            None | Some(Token::Newline) => Ok(None),
+            _ => Ok(Some(self.parse_expr()?)),
         }
     }
 
     /// Parses assignments like `a = b` or `_ = foo()`;
     /// see [Parser::parse_expr] for expression-parsing details.
-    fn parse_assignment(&mut self) -> Assignment {
-        let name = self.parse_identifier();
-        let operator_symbol = self.next_token().unwrap();
-
-        let name_expr = Expr::Identifier(name.clone());
-
-        let value = match operator_symbol {
-            Token::AssignmentSymbol(PlusEq) => Expr::Binary(Binary {
-                left: Box::new(name_expr),
-                operator: BinaryOperator::Add,
-                right: Box::new(self.parse_expr()),
-            }),
-            Token::AssignmentSymbol(TimesEq) => Expr::Binary(Binary {
-                left: Box::new(name_expr),
-                operator: BinaryOperator::Multiply,
-                right: Box::new(self.parse_expr()),
-            }),
-            Token::AssignmentSymbol(MinusEq) => Expr::Binary(Binary {
-                left: Box::new(name_expr),
-                operator: BinaryOperator::Subtract,
-                right: Box::new(self.parse_expr()),
-            }),
-            Token::AssignmentSymbol(DivideEq) => Expr::Binary(Binary {
-                left: Box::new(name_expr),
-                operator: BinaryOperator::Divide,
-                right: Box::new(self.parse_expr()),
-            }),
-            Token::AssignmentSymbol(Eq) => self.parse_expr(),
+    fn parse_assignment(&mut self) -> crate::Result<Assignment<'a>> {
+        let span = self.peek_span();
+        let name = self.parse_identifier()?;
+        let operator_span = self.peek_span();
+        // Compound assignments like `a += b` are sugar for `a = a + b`
+        let operator = match self.next_token() {
+            Some(Token::AssignmentSymbol(PlusEq)) => Some(BinaryOperator::Add),
+            Some(Token::AssignmentSymbol(TimesEq)) => Some(BinaryOperator::Multiply),
+            Some(Token::AssignmentSymbol(MinusEq)) => Some(BinaryOperator::Subtract),
+            Some(Token::AssignmentSymbol(DivideEq)) => Some(BinaryOperator::Divide),
+            Some(Token::AssignmentSymbol(Eq)) => None,
             _ => unreachable!(),
         };
 
-        Assignment {
+        let value = match operator {
+            Some(operator) => {
+                let name_expr = Expr::Identifier(Identifier { name, span, id: self.new_id() });
+                Expr::Binary(Binary {
+                    left: self.arena.alloc(name_expr),
+                    operator,
+                    right: self.arena.alloc(self.parse_expr()?),
+                    span: operator_span,
+                    id: self.new_id(),
+                })
+            }
+            None => self.parse_expr()?,
+        };
+
+        Ok(Assignment {
             name,
-            value: Box::new(value),
-        }
+            value: self.arena.alloc(value),
+            span,
+            id: self.new_id(),
+        })
     }
 
     /// Parses an expression. Keep reading for order-of-operations details.
@@ -405,148 +733,142 @@ impl<'a> Parser<'a> {
     ///
     /// For example, when parsing `1 + 7 * 8`, `parse_add_sub_expr` (less deep) will call `parse_mul_div_expr`
     /// (more deep) to parse `1` and `7 * 8`,
-    fn parse_expr(&mut self) -> Expr {
+    fn parse_expr(&mut self) -> crate::Result<Expr<'a>> {
         self.parse_logical_or_expr()
     }
 
     // TODO: implement logical or (rn we don't parse it bc it's not even lexed)
     /// Parses expressions like `A or B or C`;
     /// see [Parser::parse_expr] for expression-parsing details.
-    fn parse_logical_or_expr(&mut self) -> Expr {
+    fn parse_logical_or_expr(&mut self) -> crate::Result<Expr<'a>> {
         self.parse_logical_and_expr()
     }
 
     // TODO: implement logical and (rn we don't parse it bc it's not even lexed)
     /// Parses expressions like `A and B and C`;
     /// see [Parser::parse_expr] for expression-parsing details.
-    fn parse_logical_and_expr(&mut self) -> Expr {
+    fn parse_logical_and_expr(&mut self) -> crate::Result<Expr<'a>> {
         self.parse_comparison_expression()
     }
 
     /// Parses expressions like `L < R`;
     /// see [Parser::parse_expr] for expression-parsing details.
-    fn parse_comparison_expression(&mut self) -> Expr {
-        let left = self.parse_add_sub_expr();
+    fn parse_comparison_expression(&mut self) -> crate::Result<Expr<'a>> {
+        let left = self.parse_add_sub_expr()?;
 
         let operator = match self.peek_token(1) {
             Some(Token::ComparatorSymbol(s)) => ComparisonOperator::from(*s),
-            _ => return left,
+            _ => return Ok(left),
         };
 
+        let span = self.peek_span();
         self.skip_token(); // skip the compare symbol
 
-        let right = self.parse_add_sub_expr();
+        let right = self.parse_add_sub_expr()?;
 
         if let Some(Token::ComparatorSymbol(_)) = self.peek_token(1) {
-            // TODO: Error messages: print a useful error message for the user
-            panic!("Comparison operators cannot be chained")
+            return Err(self.err(ParsingError::ChainedComparison));
         }
 
-        Expr::Comparison(Comparison {
-            left: Box::new(left),
+        Ok(Expr::Comparison(Comparison {
+            left: self.arena.alloc(left),
             operator,
-            right: Box::new(right),
-        })
+            right: self.arena.alloc(right),
+            span,
+            id: self.new_id(),
+        }))
     }
 
     /// Parses expressions like `A - B + C`;
     /// see [Parser::parse_expr] for expression-parsing details.
-    fn parse_add_sub_expr(&mut self) -> Expr {
-        let mut left_expr_so_far = self.parse_mul_div_rem_expr();
-
-        while let Some(Token::OperatorSymbol(s @ (Plus | Minus))) = self.peek_token(1) {
-            let operator = BinaryOperator::from(*s);
-            self.skip_token();
-            let right = self.parse_mul_div_rem_expr();
-
-            left_expr_so_far = Expr::Binary(Binary {
-                left: Box::new(left_expr_so_far),
-                operator,
-                right: Box::new(right),
-            })
-        }
-
-        left_expr_so_far
+    fn parse_add_sub_expr(&mut self) -> crate::Result<Expr<'a>> {
+        let operator = |token: &Token| match token {
+            Token::OperatorSymbol(s @ (Plus | Minus)) => Some(BinaryOperator::from(*s)),
+            _ => None,
+        };
+        self.parse_chain(operator, Self::parse_mul_div_rem_expr)
     }
 
     /// Parses expressions like `A / B * C`.
     ///
     /// See [Parser::parse_expr] for expression-parsing details.
-    fn parse_mul_div_rem_expr(&mut self) -> Expr {
-        let mut left_expr_so_far = self.parse_unary_expr();
-
-        while let Some(Token::OperatorSymbol(s @ (Asterisk | Slash | Modulo))) = self.peek_token(1) {
-            let operator = BinaryOperator::from(*s);
-            self.skip_token();
-            let right = self.parse_unary_expr();
-
-            left_expr_so_far = Expr::Binary(Binary {
-                left: Box::new(left_expr_so_far),
-                operator,
-                right: Box::new(right),
-            })
-        }
-
-        left_expr_so_far
+    fn parse_mul_div_rem_expr(&mut self) -> crate::Result<Expr<'a>> {
+        let operator = |token: &Token| match token {
+            Token::OperatorSymbol(s @ (Asterisk | Slash | Modulo)) => Some(BinaryOperator::from(*s)),
+            _ => None,
+        };
+        self.parse_chain(operator, Self::parse_unary_expr)
     }
 
-    /// Parses expressions like `-A` or `(u32) B`
-    fn parse_unary_expr(&mut self) -> Expr {
-        match (self.peek_token(1), self.peek_token(2)) {
-            (Some(Token::OperatorSymbol(Minus)), _) => Expr::Unary(self.parse_negation()),
-            (Some(Token::LParen), Some(Token::Type(_))) => Expr::Unary(self.parse_cast()),
-            _ => self.parse_primary_expr(),
-        }
+    /// Parses expressions like `-A` or `(u32) B`.
+    ///
+    /// Every nested expression (in parentheses, a negation, a cast, or a call's arguments) is
+    /// parsed through here, so this is where the nesting depth is counted.
+    fn parse_unary_expr(&mut self) -> crate::Result<Expr<'a>> {
+        self.nested("expression", |parser| match (parser.peek_token(1), parser.peek_token(2)) {
+            (Some(Token::OperatorSymbol(Minus)), _) => Ok(Expr::Unary(parser.parse_negation()?)),
+            (Some(Token::LParen), Some(Token::Type(_))) => Ok(Expr::Unary(parser.parse_cast()?)),
+            _ => parser.parse_primary_expr(),
+        })
     }
 
     /// Parses negation expressions like `-A`.
-    fn parse_negation(&mut self) -> Unary {
-        self.assert_next_token(Token::OperatorSymbol(Minus));
-        let operand = self.parse_unary_expr();
+    fn parse_negation(&mut self) -> crate::Result<Unary<'a>> {
+        let span = self.peek_span();
+        self.assert_next_token(Token::OperatorSymbol(Minus))?;
+        let operand = self.parse_unary_expr()?;
 
-        Unary {
+        Ok(Unary {
             operator: UnaryOperator::Negate,
-            operand: Box::new(operand),
-        }
+            operand: self.arena.alloc(operand),
+            span,
+            id: self.new_id(),
+        })
     }
 
     /// Parses cast expressions like `(u32) A`.
-    fn parse_cast(&mut self) -> Unary {
-        self.assert_next_token(Token::LParen);
-        let cast_type = self.parse_type();
-        self.assert_next_token(Token::RParen);
-        let operand = self.parse_unary_expr();
-
-        Unary {
+    fn parse_cast(&mut self) -> crate::Result<Unary<'a>> {
+        let span = self.peek_span();
+        self.assert_next_token(Token::LParen)?;
+        let cast_type = self.parse_type()?;
+        self.assert_next_token(Token::RParen)?;
+        let operand = self.parse_unary_expr()?;
+
+        Ok(Unary {
             operator: UnaryOperator::Cast(cast_type),
-            operand: Box::new(operand),
-        }
+            operand: self.arena.alloc(operand),
+            span,
+            id: self.new_id(),
+        })
     }
 
     /// Parses expressions like `(A + B)` or `foo()` or `x`;
     /// see [Parser::parse_expr] for expression-parsing details.
-    fn parse_primary_expr(&mut self) -> Expr {
+    fn parse_primary_expr(&mut self) -> crate::Result<Expr<'a>> {
         match (self.peek_token(1), self.peek_token(2)) {
             (Some(Token::LParen), _) => {
                 self.skip_token();
-                let expr = self.parse_expr();
-                self.assert_next_token(Token::RParen);
-                expr
+                let expr = self.parse_expr()?;
+                self.assert_next_token(Token::RParen)?;
+                Ok(expr)
             }
-            (Some(Token::Identifier(_)), Some(Token::LParen)) => Expr::Call(self.parse_call()),
+            (Some(Token::Identifier(_)), Some(Token::LParen)) => Ok(Expr::Call(self.parse_call()?)),
             _ => self.parse_atom(),
         }
     }
 
     /// Parses expressions like `foo()` or `bar(7, 2)`; see [Parser::parse_expr] for 
     /// expression-parsing details.
-    fn parse_call(&mut self) -> Call {
-        let function_name = self.parse_identifier();
-        let args = self.parse_func_args();
-        Call {
+    fn parse_call(&mut self) -> crate::Result<Call<'a>> {
+        let span = self.peek_span();
+        let function_name = self.parse_identifier()?;
+        let args = self.parse_func_args()?;
+        Ok(Call {
             function_name,
-            args
-        }
+            args,
+            span,
+            id: self.new_id(),
+        })
     }
 
     /// Parses function args, which is useful during a function call.
@@ -560,45 +882,55 @@ impl<'a> Parser<'a> {
     ///
     /// # Assumptions
     /// - The args are wrapped in parentheses and properly comma-separated.
-    fn parse_func_args(&mut self) -> Vec<Expr> {
-        self.assert_next_token(Token::LParen);
+    fn parse_func_args(&mut self) -> crate::Result<Vec<Expr<'a>>> {
+        self.assert_next_token(Token::LParen)?;
 
         let mut args = Vec::new();
 
         if let Some(Token::RParen) = self.peek_token(1) {
             self.skip_token();
-            return args;
+            return Ok(args);
         }
 
         loop {
-            args.push(self.parse_expr());
+            args.push(self.parse_expr()?);
 
-            match self.next_token() {
-                Some(Token::RParen) => break,
-                Some(Token::Comma) => continue,
-                Some(token) => panic!("Expected ')' but received {:?}", token),
-                None => panic!("Expected ')' but file ended"),
+            if !self.parse_list_separator()? {
+                break;
             }
         }
 
-        args
+        Ok(args)
     }
 
-    /// Parses the most atomic expressions (identifiers/literals) and panics if unsuccessful.
+    /// Parses the most atomic expressions (identifiers/literals) and returns an error if
+    /// unsuccessful.
     ///
     /// # Flick example code
     /// - `foo`
     /// - `42`
-    fn parse_atom(&mut self) -> Expr {
+    /// - `"bar"`
+    fn parse_atom(&mut self) -> crate::Result<Expr<'a>> {
         match (self.peek_token(1), self.peek_token(2)) {
-            (Some(Token::Identifier(_)), _) => Expr::Identifier(self.parse_identifier()),
-            (Some(Token::IntLiteral(_)), _) => Expr::IntLiteral(self.parse_int_literal()),
-            (Some(Token::True | Token::False), _) => Expr::BoolLiteral(self.parse_bool_literal()),
-
-            // todo Some(Token::StrLiteral())
+            (Some(Token::Identifier(_)), _) => {
+                let span = self.peek_span();
+                let name = self.parse_identifier()?;
+                Ok(Expr::Identifier(Identifier { name, span, id: self.new_id() }))
+            }
+            (Some(Token::IntLiteral(_)), _) => {
+                let span = self.peek_span();
+                Ok(Expr::IntLiteral(self.parse_int_literal(), span, self.new_id()))
+            }
+            (Some(Token::True | Token::False), _) => {
+                let span = self.peek_span();
+                Ok(Expr::BoolLiteral(self.parse_bool_literal(), span, self.new_id()))
+            }
+            (Some(Token::StrLiteral(_)), _) => {
+                let span = self.peek_span();
+                Ok(Expr::StrLiteral(self.parse_str_literal(), span, self.new_id()))
+            }
 
-            (Some(token), _) => panic!("Expected identifier or literal but received '{}'", token),
-            (None, _) => panic!("Expected identifier or literal but file ended"),
+            _ => Err(self.unexpected(vec![Expected::Expression])),
         }
     }
 
@@ -607,10 +939,10 @@ impl<'a> Parser<'a> {
     /// # Flick example code
     /// - `42`
     /// - `-42`
-    fn parse_int_literal(&mut self) -> String {
+    fn parse_int_literal(&mut self) -> &'a str {
         // The next token should be an integer literal.
         match self.next_token() {
-            Some(Token::IntLiteral(n)) => n.clone(),
+            Some(Token::IntLiteral(n)) => n,
             _ => unreachable!("This function is called from parse_atom, which already checks the next token")
         }
     }
@@ -622,217 +954,280 @@ impl<'a> Parser<'a> {
             _ => unreachable!("This function is called from parse_atom, which already checks the next token")
         }
     }
+
+    fn parse_str_literal(&mut self) -> &'a str {
+        match self.next_token() {
+            Some(Token::StrLiteral(s)) => s,
+            _ => unreachable!("This function is called from parse_atom, which already checks the next token")
+        }
+    }
 }
 
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::error::{ErrorKind, FlickError};
     use crate::lexing::token::ComparatorSymbol::LessOrEqualTo;
     use crate::types::IntType;
+    use crate::lexing::lexer::{IdentifierPolicy, Lexer};
 
     #[test]
     fn var_declaration() {
+        let arena = Arena::new();
         let tokens = vec![
-            Token::Type(Type::Int(IntType { signed: true, width: 64 })),
-            Token::Identifier("x".to_string()),
+            Token::Type(Box::new(Type::Int(IntType { signed: true, width: 64 }))),
+            Token::Identifier("x".into()),
             Token::AssignmentSymbol(Eq),
-            Token::IntLiteral("5".to_string()),
+            Token::IntLiteral("5".into()),
         ];
         let expected = Some(Statement::VarDeclaration(VarDeclaration {
-            var_name: "x".to_string(),
+            var_name: "x",
             var_type: Type::Int(IntType { signed: true, width: 64 }),
-            var_value: Expr::IntLiteral("5".to_string()),
+            var_value: Expr::IntLiteral("5", Span::default(), NodeId(0)),
+            span: Span::default(),
+            id: NodeId(1),
         }));
 
-        let mut parser = Parser { tokens: &tokens, cursor: 0 };
-        let ast = parser.parse_statement();
+        let mut parser = Parser::new(&arena, &tokens, &[], Parser::DEFAULT_MAX_DEPTH);
+        let ast = parser.parse_statement().unwrap();
 
         assert_eq!(expected, ast);
     }
 
+    #[test]
+    fn names_are_borrowed_from_the_tokens() {
+        let arena = Arena::new();
+        let tokens = vec![
+            Token::Identifier("num".into()),
+            Token::AssignmentSymbol(Eq),
+            Token::StrLiteral("ten".into()),
+        ];
+
+        let mut parser = Parser::new(&arena, &tokens, &[], Parser::DEFAULT_MAX_DEPTH);
+        let Some(Statement::Assignment(assignment)) = parser.parse_statement().unwrap() else { panic!() };
+        let Expr::StrLiteral(literal, ..) = assignment.value else { panic!() };
+
+        let [Token::Identifier(name), _, Token::StrLiteral(string)] = &tokens[..] else { unreachable!() };
+        assert!(std::ptr::eq(assignment.name, &**name));
+        assert!(std::ptr::eq(*literal, &**string));
+    }
+
     #[test]
     fn var_modification() {
+        let arena = Arena::new();
         let tokens = vec![
-            Token::Identifier("num".to_string()),
+            Token::Identifier("num".into()),
             Token::AssignmentSymbol(Eq),
-            Token::IntLiteral("10".to_string()),
+            Token::IntLiteral("10".into()),
         ];
         let expected = Some(Statement::Assignment(Assignment {
-            name: "num".to_string(),
-            value: Box::new(Expr::IntLiteral("10".to_string())),
+            name: "num",
+            value: arena.alloc(Expr::IntLiteral("10", Span::default(), NodeId(0))),
+            span: Span::default(),
+            id: NodeId(1),
         }));
 
-        let mut parser = Parser { tokens: &tokens, cursor: 0 };
-        let ast = parser.parse_statement();
+        let mut parser = Parser::new(&arena, &tokens, &[], Parser::DEFAULT_MAX_DEPTH);
+        let ast = parser.parse_statement().unwrap();
 
         assert_eq!(expected, ast);
     }
 
     #[test]
     fn empty_while_loop() {
+        let arena = Arena::new();
         let tokens = vec![
             Token::While,
-            Token::Identifier("i".to_string()),
+            Token::Identifier("i".into()),
             Token::ComparatorSymbol(LessOrEqualTo),
-            Token::Identifier("N".to_string()),
+            Token::Identifier("N".into()),
             Token::LSquirly,
             Token::RSquirly,
         ];
         let expected = Some(Statement::WhileLoop(WhileLoop {
             condition: Expr::Comparison(Comparison {
-                left: Box::new(Expr::Identifier("i".to_string())),
+                left: arena.alloc(Expr::Identifier(Identifier { name: "i", span: Span::default(), id: NodeId(0) })),
                 operator: ComparisonOperator::LessOrEqualTo,
-                right: Box::new(Expr::Identifier("N".to_string())),
+                right: arena.alloc(Expr::Identifier(Identifier { name: "N", span: Span::default(), id: NodeId(1) })),
+                span: Span::default(),
+                id: NodeId(2),
             }),
             body: vec![],
+            span: Span::default(),
+            id: NodeId(3),
         }));
 
-        let mut parser = Parser { tokens: &tokens, cursor: 0 };
-        let ast = parser.parse_statement();
+        let mut parser = Parser::new(&arena, &tokens, &[], Parser::DEFAULT_MAX_DEPTH);
+        let ast = parser.parse_statement().unwrap();
 
         assert_eq!(expected, ast);
     }
 
     #[test]
     fn order_of_operations() {
+        let arena = Arena::new();
         let tokens = vec![
-            Token::IntLiteral("10".to_string()),
+            Token::IntLiteral("10".into()),
             Token::OperatorSymbol(Plus),
-            Token::IntLiteral("3".to_string()),
+            Token::IntLiteral("3".into()),
             Token::OperatorSymbol(Asterisk),
-            Token::IntLiteral("8".to_string()),
+            Token::IntLiteral("8".into()),
             Token::OperatorSymbol(Slash),
-            Token::IntLiteral("4".to_string()),
+            Token::IntLiteral("4".into()),
             Token::OperatorSymbol(Minus),
-            Token::IntLiteral("13".to_string()),
+            Token::IntLiteral("13".into()),
             Token::OperatorSymbol(Plus),
-            Token::IntLiteral("5".to_string()),
+            Token::IntLiteral("5".into()),
         ];
         let expected = Expr::Binary(Binary {
-            left: Box::new(Expr::Binary(Binary {
-                left: Box::new(Expr::Binary(Binary {
-                    left: Box::new(Expr::IntLiteral("10".to_string())),
+            left: arena.alloc(Expr::Binary(Binary {
+                left: arena.alloc(Expr::Binary(Binary {
+                    left: arena.alloc(Expr::IntLiteral("10", Span::default(), NodeId(0))),
                     operator: BinaryOperator::Add,
-                    right: Box::new(Expr::Binary(Binary {
-                        left: Box::new(Expr::Binary(Binary {
-                            left: Box::new(Expr::IntLiteral("3".to_string())),
+                    right: arena.alloc(Expr::Binary(Binary {
+                        left: arena.alloc(Expr::Binary(Binary {
+                            left: arena.alloc(Expr::IntLiteral("3", Span::default(), NodeId(1))),
                             operator: BinaryOperator::Multiply,
-                            right: Box::new(Expr::IntLiteral("8".to_string())),
+                            right: arena.alloc(Expr::IntLiteral("8", Span::default(), NodeId(2))),
+                            span: Span::default(),
+                            id: NodeId(3),
                         })),
                         operator: BinaryOperator::Divide,
-                        right: Box::new(Expr::IntLiteral("4".to_string())),
+                        right: arena.alloc(Expr::IntLiteral("4", Span::default(), NodeId(4))),
+                        span: Span::default(),
+                        id: NodeId(5),
                     })),
+                    span: Span::default(),
+                    id: NodeId(6),
                 })),
                 operator: BinaryOperator::Subtract,
-                right: Box::new(Expr::IntLiteral("13".to_string())),
+                right: arena.alloc(Expr::IntLiteral("13", Span::default(), NodeId(7))),
+                span: Span::default(),
+                id: NodeId(8),
             })),
             operator: BinaryOperator::Add,
-            right: Box::new(Expr::IntLiteral("5".to_string())),
+            right: arena.alloc(Expr::IntLiteral("5", Span::default(), NodeId(9))),
+            span: Span::default(),
+            id: NodeId(10),
         });
 
-        let mut parser = Parser { tokens: &tokens, cursor: 0 };
-        let ast = parser.parse_expr();
+        let mut parser = Parser::new(&arena, &tokens, &[], Parser::DEFAULT_MAX_DEPTH);
+        let ast = parser.parse_expr().unwrap();
 
         assert_eq!(expected, ast);
     }
 
     #[test]
     fn parenthetical_expression() {
+        let arena = Arena::new();
         let tokens = vec![
-            Token::IntLiteral("9".to_string()),
+            Token::IntLiteral("9".into()),
             Token::OperatorSymbol(Asterisk),
             Token::LParen,
-            Token::IntLiteral("2".to_string()),
+            Token::IntLiteral("2".into()),
             Token::OperatorSymbol(Plus),
-            Token::IntLiteral("3".to_string()),
+            Token::IntLiteral("3".into()),
             Token::RParen,
         ];
         let expected = Expr::Binary(Binary {
-            left: Box::new(Expr::IntLiteral("9".to_string())),
+            left: arena.alloc(Expr::IntLiteral("9", Span::default(), NodeId(0))),
             operator: BinaryOperator::Multiply,
-            right: Box::new(Expr::Binary(Binary {
-                left: Box::new(Expr::IntLiteral("2".to_string())),
+            right: arena.alloc(Expr::Binary(Binary {
+                left: arena.alloc(Expr::IntLiteral("2", Span::default(), NodeId(1))),
                 operator: BinaryOperator::Add,
-                right: Box::new(Expr::IntLiteral("3".to_string())),
+                right: arena.alloc(Expr::IntLiteral("3", Span::default(), NodeId(2))),
+                span: Span::default(),
+                id: NodeId(3),
             })),
+            span: Span::default(),
+            id: NodeId(4),
         });
 
-        let mut parser = Parser { tokens: &tokens, cursor: 0 };
-        let ast = parser.parse_expr();
+        let mut parser = Parser::new(&arena, &tokens, &[], Parser::DEFAULT_MAX_DEPTH);
+        let ast = parser.parse_expr().unwrap();
 
         assert_eq!(expected, ast);
     }
 
     #[test]
     fn spacing() {
+        let arena = Arena::new();
         let tokens = vec![
             Token::LSquirly,
             Token::Newline,
             Token::Newline,
             Token::Newline,
-            Token::Identifier("a".to_string()),
+            Token::Identifier("a".into()),
             Token::AssignmentSymbol(Eq),
-            Token::IntLiteral("2".to_string()),
+            Token::IntLiteral("2".into()),
             Token::Newline,
             Token::Newline,
             Token::RSquirly,
         ];
         let expected = vec![Statement::Assignment(Assignment {
-            name: "a".to_string(),
-            value: Box::new(Expr::IntLiteral("2".to_string())),
+            name: "a",
+            value: arena.alloc(Expr::IntLiteral("2", Span::default(), NodeId(0))),
+            span: Span::default(),
+            id: NodeId(1),
         })];
 
-        let mut parser = Parser { tokens: &tokens, cursor: 0 };
-        let ast = parser.parse_body();
+        let mut parser = Parser::new(&arena, &tokens, &[], Parser::DEFAULT_MAX_DEPTH);
+        let ast = parser.parse_body().unwrap();
 
         assert_eq!(expected, ast);
     }
 
     #[test]
     fn function_call() {
+        let arena = Arena::new();
         let tokens = vec![
-            Token::Identifier("print".to_string()),
+            Token::Identifier("print".into()),
             Token::LParen,
-            Token::Identifier("f".to_string()),
+            Token::Identifier("f".into()),
             Token::LParen,
-            Token::IntLiteral("1".to_string()),
+            Token::IntLiteral("1".into()),
             Token::RParen,
             Token::Comma,
-            Token::IntLiteral("10".to_string()),
+            Token::IntLiteral("10".into()),
             Token::Comma,
-            Token::IntLiteral("20".to_string()),
+            Token::IntLiteral("20".into()),
             Token::RParen,
         ];
         let expected = Expr::Call(Call {
-            function_name: "print".to_string(),
+            function_name: "print",
             args: vec![
                 Expr::Call(Call {
-                    function_name: "f".to_string(),
-                    args: vec![Expr::IntLiteral("1".to_string())],
+                    function_name: "f",
+                    args: vec![Expr::IntLiteral("1", Span::default(), NodeId(0))],
+                    span: Span::default(),
+                    id: NodeId(1),
                 }),
-                Expr::IntLiteral("10".to_string()),
-                Expr::IntLiteral("20".to_string()),
+                Expr::IntLiteral("10", Span::default(), NodeId(2)),
+                Expr::IntLiteral("20", Span::default(), NodeId(3)),
             ],
+            span: Span::default(),
+            id: NodeId(4),
         });
 
-        let mut parser = Parser { tokens: &tokens, cursor: 0 };
-        let ast = parser.parse_expr();
+        let mut parser = Parser::new(&arena, &tokens, &[], Parser::DEFAULT_MAX_DEPTH);
+        let ast = parser.parse_expr().unwrap();
 
         assert_eq!(expected, ast);
     }
 
     #[test]
     fn function_definition() {
+        let arena = Arena::new();
         let tokens = vec![
             Token::Pub,
             Token::Fn,
-            Token::Identifier("test".to_string()),
+            Token::Identifier("test".into()),
             Token::LParen,
-            Token::Type(Type::Int(IntType { signed: true, width: 64 })),
-            Token::Identifier("a".to_string()),
+            Token::Type(Box::new(Type::Int(IntType { signed: true, width: 64 }))),
+            Token::Identifier("a".into()),
             Token::RParen,
-            Token::Type(Type::Int(IntType { signed: true, width: 64 })),
+            Token::Type(Box::new(Type::Int(IntType { signed: true, width: 64 }))),
             Token::LSquirly,
             Token::RSquirly,
         ];
@@ -840,38 +1235,44 @@ mod tests {
             global_statements: vec![GlobalStatement::FuncDef(FuncDef {
                 proto: FuncProto {
                     func_visibility: FuncVisibility::Public,
-                    name: "test".to_string(),
+                    name: Name::intern("test"),
                     params: vec![FuncParam {
                         param_type: Type::Int(IntType { signed: true, width: 64 }),
-                        param_name: "a".to_string(),
+                        param_name: Name::intern("a"),
+                        span: Span::default(),
+                        id: NodeId(0),
                     }],
                     return_type: Box::new(Type::Int(IntType { signed: true, width: 64 })),
+                    span: Span::default(),
+                    id: NodeId(1),
+                    library: None,
                 },
                 body: vec![],
             })],
         };
 
-        let ast = Parser::parse_program(&tokens);
+        let ast = Parser::parse_program(&arena, &tokens).unwrap();
 
         assert_eq!(expected, ast);
     }
 
     #[test]
     fn if_statement() {
+        let arena = Arena::new();
         let tokens = vec![
             Token::If,
-            Token::Identifier("x".to_string()),
+            Token::Identifier("x".into()),
             Token::ComparatorSymbol(LessOrEqualTo),
-            Token::IntLiteral("5".to_string()),
+            Token::IntLiteral("5".into()),
             Token::LSquirly,
             Token::Ret,
             Token::Newline,
             Token::RSquirly,
             Token::Else,
             Token::If,
-            Token::Identifier("x".to_string()),
+            Token::Identifier("x".into()),
             Token::ComparatorSymbol(LessOrEqualTo),
-            Token::IntLiteral("10".to_string()),
+            Token::IntLiteral("10".into()),
             Token::LSquirly,
             Token::Ret,
             Token::Newline,
@@ -884,159 +1285,427 @@ mod tests {
         ];
         let expected = Some(Statement::If(If { 
             condition: Expr::Comparison(Comparison { 
-                left: Box::new(Expr::Identifier("x".to_string())), 
+                left: arena.alloc(Expr::Identifier(Identifier { name: "x", span: Span::default(), id: NodeId(0) })), 
                 operator: ComparisonOperator::LessOrEqualTo, 
-                right: Box::new(Expr::IntLiteral("5".to_string())) ,
+                right: arena.alloc(Expr::IntLiteral("5", Span::default(), NodeId(1))) ,
+                span: Span::default(),
+                id: NodeId(2),
             }), 
-            then_body: vec![Statement::Return(None)], 
+            then_body: vec![Statement::Return(None, Span::default(), NodeId(3))], 
             else_body: Some(vec![
                 Statement::If(If { 
                     condition: Expr::Comparison(Comparison { 
-                        left: Box::new(Expr::Identifier("x".to_string())), 
+                        left: arena.alloc(Expr::Identifier(Identifier { name: "x", span: Span::default(), id: NodeId(4) })), 
                         operator: ComparisonOperator::LessOrEqualTo, 
-                        right: Box::new(Expr::IntLiteral("10".to_string())) ,
+                        right: arena.alloc(Expr::IntLiteral("10", Span::default(), NodeId(5))) ,
+                        span: Span::default(),
+                        id: NodeId(6),
                     }),
-                    then_body: vec![Statement::Return(None)],
-                    else_body: Some(vec![Statement::Return(None)]),
+                    then_body: vec![Statement::Return(None, Span::default(), NodeId(7))],
+                    else_body: Some(vec![Statement::Return(None, Span::default(), NodeId(8))]),
+                    span: Span::default(),
+                    id: NodeId(9),
                 })
             ]),
+            span: Span::default(),
+            id: NodeId(10),
         }));
 
-        let mut parser = Parser { tokens: &tokens, cursor: 0 };
-        let ast = parser.parse_statement();
+        let mut parser = Parser::new(&arena, &tokens, &[], Parser::DEFAULT_MAX_DEPTH);
+        let ast = parser.parse_statement().unwrap();
 
         assert_eq!(expected, ast);
     }
 
     #[test]
     fn return_statement() {
+        let arena = Arena::new();
         let tokens = vec![
             Token::Ret,
-            Token::Identifier("x".to_string()),
+            Token::Identifier("x".into()),
             Token::OperatorSymbol(Plus),
-            Token::IntLiteral("5".to_string()),
+            Token::IntLiteral("5".into()),
         ];
         let expected = Some(Statement::Return(Some(Expr::Binary(Binary {
-            left: Box::new(Expr::Identifier("x".to_string())),
+            left: arena.alloc(Expr::Identifier(Identifier { name: "x", span: Span::default(), id: NodeId(0) })),
             operator: BinaryOperator::Add,
-            right: Box::new(Expr::IntLiteral("5".to_string())),
-        }))));
+            right: arena.alloc(Expr::IntLiteral("5", Span::default(), NodeId(1))),
+            span: Span::default(),
+            id: NodeId(2),
+        })), Span::default(), NodeId(3)));
 
-        let mut parser = Parser { tokens: &tokens, cursor: 0 };
-        let ast = parser.parse_statement();
+        let mut parser = Parser::new(&arena, &tokens, &[], Parser::DEFAULT_MAX_DEPTH);
+        let ast = parser.parse_statement().unwrap();
 
         assert_eq!(expected, ast);
     }
 
     #[test]
     fn plus_eq() {
+        let arena = Arena::new();
         let tokens = vec![
-            Token::Identifier("x".to_string()),
+            Token::Identifier("x".into()),
             Token::AssignmentSymbol(PlusEq),
-            Token::IntLiteral("5".to_string()),
+            Token::IntLiteral("5".into()),
         ];
         let expected = Some(Statement::Assignment(Assignment {
-            name: "x".to_string(),
-            value: Box::new(Expr::Binary(Binary {
-                left: Box::new(Expr::Identifier("x".to_string())),
+            name: "x",
+            value: arena.alloc(Expr::Binary(Binary {
+                left: arena.alloc(Expr::Identifier(Identifier { name: "x", span: Span::default(), id: NodeId(0) })),
                 operator: BinaryOperator::Add,
-                right: Box::new(Expr::IntLiteral("5".to_string())),
+                right: arena.alloc(Expr::IntLiteral("5", Span::default(), NodeId(1))),
+                span: Span::default(),
+                id: NodeId(2),
             })),
+            span: Span::default(),
+            id: NodeId(3),
         }));
 
-        let mut parser = Parser { tokens: &tokens, cursor: 0 };
-        let ast = parser.parse_statement();
+        let mut parser = Parser::new(&arena, &tokens, &[], Parser::DEFAULT_MAX_DEPTH);
+        let ast = parser.parse_statement().unwrap();
 
         assert_eq!(expected, ast);
     }
 
     #[test]
     fn arithmetic() {
+        let arena = Arena::new();
         // x=(a+3)/4*5%3*(-2)-2
         let tokens = vec![
-            Token::Identifier("x".to_string()),
+            Token::Identifier("x".into()),
             Token::AssignmentSymbol(Eq),
             Token::LParen,
-            Token::Identifier("a".to_string()),
+            Token::Identifier("a".into()),
             Token::OperatorSymbol(Plus),
-            Token::IntLiteral("3".to_string()),
+            Token::IntLiteral("3".into()),
             Token::RParen,
             Token::OperatorSymbol(Slash),
-            Token::IntLiteral("4".to_string()),
+            Token::IntLiteral("4".into()),
             Token::OperatorSymbol(Asterisk),
-            Token::IntLiteral("5".to_string()),
+            Token::IntLiteral("5".into()),
             Token::OperatorSymbol(Modulo),
-            Token::IntLiteral("3".to_string()),
+            Token::IntLiteral("3".into()),
             Token::OperatorSymbol(Asterisk),
             Token::LParen,
             Token::OperatorSymbol(Minus),
-            Token::IntLiteral("2".to_string()),
+            Token::IntLiteral("2".into()),
             Token::RParen,
             Token::OperatorSymbol(Minus),
-            Token::IntLiteral("2".to_string()),
+            Token::IntLiteral("2".into()),
         ];
 
         let expected = Some(Statement::Assignment(Assignment {
-            name: "x".to_string(),
-            value: Box::new(Expr::Binary(Binary { 
-                left: Box::new(Expr::Binary(Binary { 
-                    left: Box::new(Expr::Binary(Binary { 
-                        left: Box::new(Expr::Binary(Binary { 
-                            left: Box::new(Expr::Binary(Binary { 
-                                left: Box::new(Expr::Binary(Binary { 
-                                    left: Box::new(Expr::Identifier("a".to_string())), 
+            name: "x",
+            value: arena.alloc(Expr::Binary(Binary { 
+                left: arena.alloc(Expr::Binary(Binary { 
+                    left: arena.alloc(Expr::Binary(Binary { 
+                        left: arena.alloc(Expr::Binary(Binary { 
+                            left: arena.alloc(Expr::Binary(Binary { 
+                                left: arena.alloc(Expr::Binary(Binary { 
+                                    left: arena.alloc(Expr::Identifier(Identifier { name: "a", span: Span::default(), id: NodeId(0) })), 
                                     operator: BinaryOperator::Add, 
-                                    right: Box::new(Expr::IntLiteral("3".to_string())) 
+                                    right: arena.alloc(Expr::IntLiteral("3", Span::default(), NodeId(1))),
+                                    span: Span::default(),
+                                    id: NodeId(2),
                                 })), 
                                 operator: BinaryOperator::Divide, 
-                                right: Box::new(Expr::IntLiteral("4".to_string())) 
+                                right: arena.alloc(Expr::IntLiteral("4", Span::default(), NodeId(3))),
+                                span: Span::default(),
+                                id: NodeId(4),
                             })), 
                             operator: BinaryOperator::Multiply, 
-                            right: Box::new(Expr::IntLiteral("5".to_string())) 
+                            right: arena.alloc(Expr::IntLiteral("5", Span::default(), NodeId(5))),
+                            span: Span::default(),
+                            id: NodeId(6),
                         })), 
                         operator: BinaryOperator::Remainder, 
-                        right: Box::new(Expr::IntLiteral("3".to_string())) 
+                        right: arena.alloc(Expr::IntLiteral("3", Span::default(), NodeId(7))),
+                        span: Span::default(),
+                        id: NodeId(8),
                     })), 
                     operator: BinaryOperator::Multiply, 
-                    right: Box::new(Expr::Unary(Unary {
+                    right: arena.alloc(Expr::Unary(Unary {
                         operator: UnaryOperator::Negate, 
-                        operand: Box::new(Expr::IntLiteral("2".to_string()))
-                    }))
+                        operand: arena.alloc(Expr::IntLiteral("2", Span::default(), NodeId(9))),
+                        span: Span::default(),
+                        id: NodeId(10),
+                    })),
+                    span: Span::default(),
+                    id: NodeId(11),
                 })), 
                 operator: BinaryOperator::Subtract, 
-                right: Box::new(Expr::IntLiteral("2".to_string()))
-            }))
+                right: arena.alloc(Expr::IntLiteral("2", Span::default(), NodeId(12))),
+                span: Span::default(),
+                id: NodeId(13),
+            })),
+            span: Span::default(),
+            id: NodeId(14),
         }));
 
-        let mut parser = Parser { tokens: &tokens, cursor: 0 };
-        let ast = parser.parse_statement();
+        let mut parser = Parser::new(&arena, &tokens, &[], Parser::DEFAULT_MAX_DEPTH);
+        let ast = parser.parse_statement().unwrap();
 
         assert_eq!(expected, ast);
     }
 
     #[test]
     fn unary_cast_of_call() {
+        let arena = Arena::new();
         let tokens = vec![
             Token::LParen,
-            Token::Type(Type::Int(IntType { width: 64, signed: true })),
+            Token::Type(Box::new(Type::Int(IntType { width: 64, signed: true }))),
             Token::RParen,
-            Token::Identifier("foo".to_string()),
+            Token::Identifier("foo".into()),
             Token::LParen,
-            Token::IntLiteral("1".to_string()),
+            Token::IntLiteral("1".into()),
             Token::RParen,
         ];
 
         let expected = Expr::Unary(Unary {
             operator: UnaryOperator::Cast(Type::Int(IntType { width: 64, signed: true })),
-            operand: Box::new(Expr::Call(Call {
-                function_name: "foo".to_string(),
-                args: vec![Expr::IntLiteral("1".to_string())],
+            operand: arena.alloc(Expr::Call(Call {
+                function_name: "foo",
+                args: vec![Expr::IntLiteral("1", Span::default(), NodeId(0))],
+                span: Span::default(),
+                id: NodeId(1),
             })),
+            span: Span::default(),
+            id: NodeId(2),
         });
 
-        let mut parser = Parser { tokens: &tokens, cursor: 0 };
-        let ast = parser.parse_expr();
+        let mut parser = Parser::new(&arena, &tokens, &[], Parser::DEFAULT_MAX_DEPTH);
+        let ast = parser.parse_expr().unwrap();
+
+        assert_eq!(expected, ast);
+    }
+
+    #[test]
+    fn spans() {
+        let arena = Arena::new();
+        let source_code = "x = foo(y)";
+        let (tokens, spans) = crate::Lexer::lex_with_spans(source_code).unwrap();
+
+        let expected = Some(Statement::Assignment(Assignment {
+            name: "x",
+            value: arena.alloc(Expr::Call(Call {
+                function_name: "foo",
+                args: vec![Expr::Identifier(Identifier { name: "y", span: Span { start: 8, end: 9 }, id: NodeId(0) })],
+                span: Span { start: 4, end: 7 },
+                id: NodeId(1),
+            })),
+            span: Span { start: 0, end: 1 },
+            id: NodeId(2),
+        }));
+
+        let mut parser = Parser::new(&arena, &tokens, &spans, Parser::DEFAULT_MAX_DEPTH);
+        let ast = parser.parse_statement().unwrap();
+
+        assert_eq!(expected, ast);
+    }
+
+    /// Returns the tokens of `expr` wrapped in `depth` pairs of parentheses.
+    fn nested_parens(depth: usize, expr: Token) -> Vec<Token> {
+        let mut tokens = vec![Token::LParen; depth];
+        tokens.push(expr);
+        tokens.extend(vec![Token::RParen; depth]);
+        tokens
+    }
+
+    #[test]
+    fn nested_parentheses() {
+        let arena = Arena::new();
+        // Each pair of parentheses, plus the innermost expression, is one level
+        let tokens = nested_parens(Parser::DEFAULT_MAX_DEPTH - 1, Token::Identifier("x".into()));
+        let expected = Expr::Identifier(Identifier { name: "x", span: Span::default(), id: NodeId::default() });
+
+        let mut parser = Parser::new(&arena, &tokens, &[], Parser::DEFAULT_MAX_DEPTH);
+        let ast = parser.parse_expr().unwrap();
 
         assert_eq!(expected, ast);
     }
+
+    #[test]
+    fn too_deeply_nested_parentheses() {
+        let arena = Arena::new();
+        let tokens = nested_parens(10_000, Token::Identifier("x".into()));
+        let expected_error = FlickError {
+            index: 0,
+            kind: ErrorKind::ParsingError(ParsingError::TooDeeplyNested { what: "expression", max_depth: 128 }),
+        };
+
+        let mut parser = Parser::new(&arena, &tokens, &[], Parser::DEFAULT_MAX_DEPTH);
+        assert_eq!(parser.parse_expr(), Err(expected_error));
+    }
+
+    #[test]
+    fn too_long_operator_chains() {
+        // Each `+` nests the sum before it one level deeper, so this is as deep as it is long
+        let source_code = format!("fn foo() u64 {{\n    ret 1{}\n}}", " + 1".repeat(1_500));
+        let (tokens, spans) = crate::Lexer::lex_with_spans(&source_code).unwrap();
+        let errors = Parser::parse_program_with_spans(&Arena::new(), &tokens, &spans).unwrap_err();
+        let [FlickError { kind: ErrorKind::ParsingError(err), .. }] = &errors[..] else { panic!("{:?}", errors) };
+        assert_eq!(*err, ParsingError::TooDeeplyNested { what: "expression", max_depth: 128 });
+
+        let source_code = format!("fn foo() u64 {{\n    ret 1{}\n}}", " * 1".repeat(100));
+        let (tokens, spans) = crate::Lexer::lex_with_spans(&source_code).unwrap();
+        assert!(Parser::parse_program_with_spans(&Arena::new(), &tokens, &spans).is_ok());
+
+        // Chains nested in chains add up, even though none of them is too long on its own
+        let mut expr = "1".to_string();
+        for _ in 0..20 {
+            expr = format!("({expr}{})", " + 1".repeat(20));
+        }
+        let source_code = format!("fn foo() u64 {{\n    ret {expr}\n}}");
+        let (tokens, spans) = crate::Lexer::lex_with_spans(&source_code).unwrap();
+        let errors = Parser::parse_program_with_spans(&Arena::new(), &tokens, &spans).unwrap_err();
+        let [FlickError { kind: ErrorKind::ParsingError(err), .. }] = &errors[..] else { panic!("{:?}", errors) };
+        assert_eq!(*err, ParsingError::TooDeeplyNested { what: "expression", max_depth: 128 });
+    }
+
+    #[test]
+    fn too_deeply_nested_bodies() {
+        // The `if` is 3 levels deep, and so is its condition
+        let source_code = "fn foo() {\n    while true {\n        if true {\n        }\n    }\n}";
+        let (tokens, spans) = crate::Lexer::lex_with_spans(source_code).unwrap();
+        let expected_error = FlickError {
+            index: 39,
+            kind: ErrorKind::ParsingError(ParsingError::TooDeeplyNested { what: "expression", max_depth: 2 }),
+        };
+
+        assert_eq!(Parser::parse_program_with_max_depth(&Arena::new(), &tokens, &spans, 2), Err(vec![expected_error]));
+    }
+
+    /// Parses `source_code` and returns the errors (panicking if there aren't any).
+    fn parse_errors(source_code: &str) -> Vec<FlickError> {
+        let (tokens, spans) = crate::Lexer::lex_with_spans(source_code).unwrap();
+        Parser::parse_program_with_spans(&Arena::new(), &tokens, &spans).unwrap_err()
+    }
+
+    /// Like [parse_errors], but panics unless there's exactly one error, which it returns.
+    fn parse_error(source_code: &str) -> FlickError {
+        let mut errors = parse_errors(source_code);
+        assert_eq!(errors.len(), 1, "expected exactly one error, but got {:?}", errors);
+        errors.remove(0)
+    }
+
+    #[test]
+    fn token_stream_is_parsed_like_a_slice() {
+        let source_code = "extern fn f(u8 x)\npub fn main() u8 {\n    f(1 + 2)\n    ret 0\n}\n";
+        let (tokens, spans) = Lexer::lex_with_spans(source_code).unwrap();
+        let arena = Arena::new();
+        let expected = Parser::parse_program_with_spans(&arena, &tokens, &spans).unwrap();
+
+        let lexer = Lexer::new(source_code, IdentifierPolicy::default());
+        let program = Parser::parse_token_stream(&arena, lexer, Parser::DEFAULT_MAX_DEPTH).unwrap();
+
+        assert_eq!(program, expected);
+    }
+
+    #[test]
+    fn token_stream_stops_at_lexing_error() {
+        // The syntax error before the lexing error is reported, but not the missing `}`, since
+        // the function is only unfinished because the tokens end there
+        let source_code = "fn f() {\n    f(1 2)\n}\nfn g() {\n    ret $\n}\n";
+        let lexer = Lexer::new(source_code, IdentifierPolicy::default());
+        let errors = Parser::parse_token_stream(&Arena::new(), lexer, Parser::DEFAULT_MAX_DEPTH).unwrap_err();
+
+        let kinds: Vec<_> = errors.iter().map(|err| err.kind.clone()).collect();
+        assert!(matches!(kinds[..], [ErrorKind::ParsingError(_), ErrorKind::LexingError(_)]), "{:?}", kinds);
+        assert_eq!(errors[1].index, source_code.find('$').unwrap());
+    }
+
+    #[test]
+    fn unexpected_token() {
+        let expected_error = FlickError {
+            index: 25,
+            kind: ErrorKind::ParsingError(ParsingError::UnexpectedToken {
+                expected: vec![Expected::Token(Token::Comma), Expected::Token(Token::RParen)],
+                found: Some(Box::new(Token::Identifier("b".into()))),
+                span: Span { start: 25, end: 26 },
+            }),
+        };
+
+        let received_error = parse_error("fn foo() {\n    bar(1 + a b)\n}");
+        assert_eq!(received_error, expected_error);
+        assert_eq!(received_error.to_string(), "expected ',' or ')', but found 'b'");
+    }
+
+    #[test]
+    fn unexpected_end_of_file() {
+        let expected_error = FlickError {
+            index: 21,
+            kind: ErrorKind::ParsingError(ParsingError::UnexpectedToken {
+                expected: vec![Expected::Token(Token::RSquirly)],
+                found: None,
+                span: Span { start: 21, end: 21 },
+            }),
+        };
+
+        let received_error = parse_error("fn foo() {\n    ret 1\n");
+        assert_eq!(received_error, expected_error);
+        assert_eq!(received_error.to_string(), "expected '}', but the file ended");
+    }
+
+    #[test]
+    fn nested_function() {
+        let expected_error = FlickError {
+            index: 15,
+            kind: ErrorKind::ParsingError(ParsingError::NestedFunction),
+        };
+
+        // The parser can't tell this apart from `foo` missing its `}`, so it moves on to `bar`
+        // (and then reports the `}` after it, too)
+        let errors = parse_errors("fn foo() {\n    fn bar() {\n    }\n}");
+        assert_eq!(errors[0], expected_error);
+    }
+
+    #[test]
+    fn assignment_in_condition() {
+        let expected_error = FlickError {
+            index: 20,
+            kind: ErrorKind::ParsingError(ParsingError::AssignmentInCondition),
+        };
+
+        let errors = parse_errors("fn foo() {\n    if x = 1 {\n        ret\n    }\n    while y = 2 {\n    }\n}");
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0], expected_error);
+        assert_eq!(errors[1].index, 56);
+    }
+
+    #[test]
+    fn every_bad_statement_is_reported() {
+        let source_code = "fn foo() {\n    x = (1\n    if 2 + {\n        bar()\n    }\n    ret 3 4\n}";
+        let errors = parse_errors(source_code);
+
+        let indices: Vec<_> = errors.iter().map(|err| err.index).collect();
+        assert_eq!(indices, vec![21, 33, 65]);
+        assert_eq!(errors[1].to_string(), "expected an expression, but found '{'");
+    }
+
+    #[test]
+    fn bad_statements_in_nested_bodies_are_reported() {
+        let source_code = "fn foo() {\n    while true {\n        x = \n    }\n    u8 = 1\n}";
+        let indices: Vec<_> = parse_errors(source_code).iter().map(|err| err.index).collect();
+
+        assert_eq!(indices, vec![40, 54]);
+    }
+
+    #[test]
+    fn parsing_continues_with_the_next_function() {
+        // The rest of `foo` is skipped, since the error is in its prototype
+        let source_code = "fn foo(u8) {\n    ret\n}\nfn bar() {\n    ret +\n}\nx = 1\npub fn baz() {\n    ret\n}";
+        let errors = parse_errors(source_code);
+
+        let indices: Vec<_> = errors.iter().map(|err| err.index).collect();
+        assert_eq!(indices, vec![9, 42, 46]);
+    }
+
+    #[test]
+    fn unclosed_body_is_reported_once() {
+        let source_code = "fn foo() {\n    if true {\n        x = ,\n}\nfn bar() {\n}";
+        let indices: Vec<_> = parse_errors(source_code).iter().map(|err| err.index).collect();
+
+        // The `,`, then the `fn` where `foo`'s `}` should have been (`bar` itself is fine)
+        assert_eq!(indices, vec![37, 41]);
+    }
 }
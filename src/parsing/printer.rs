@@ -0,0 +1,301 @@
+use std::fmt::{self, Write};
+
+use super::ast::{Call, Expr, GlobalStatement, Program, Statement, UnaryOperator};
+
+/// Returns `program` as an indented tree, one node per line, with each node's children indented
+/// under it (like `flick parse` prints it).
+///
+/// ```
+/// use flick::ast::Arena;
+/// use flick::{print_tree, Lexer, Parser};
+/// let source_code = "pub fn main() u8 {\n    ret 1 + 2\n}\n";
+/// let (tokens, spans) = Lexer::lex_with_spans(source_code).unwrap();
+/// let arena = Arena::new();
+/// let program = Parser::parse_program_with_spans(&arena, &tokens, &spans).unwrap();
+///
+/// let expected = "\
+/// pub fn main() u8
+///   Return
+///     Binary +
+///       IntLiteral 1
+///       IntLiteral 2
+/// ";
+/// assert_eq!(print_tree(&program), expected);
+/// ```
+pub fn print_tree(program: &Program) -> String {
+    let mut tree = String::new();
+    for (depth, label) in TreePrinter::nodes(program) {
+        writeln!(tree, "{}{}", "  ".repeat(depth), label).unwrap();
+    }
+    tree
+}
+
+/// Returns `program` as a [Graphviz](https://graphviz.org) graph (like `flick parse --format=dot`
+/// prints it), with the same nodes as [print_tree], and an arrow from each node to each of its
+/// children. Children are drawn in order, so the left operand of an operator is on the left.
+///
+/// ```
+/// use flick::ast::Arena;
+/// use flick::{print_dot, Lexer, Parser};
+/// let source_code = "pub fn main() u8 {\n    ret 1\n}\n";
+/// let (tokens, spans) = Lexer::lex_with_spans(source_code).unwrap();
+/// let arena = Arena::new();
+/// let program = Parser::parse_program_with_spans(&arena, &tokens, &spans).unwrap();
+///
+/// let expected = r#"digraph ast {
+///     ordering=out
+///     node [shape=box, fontname="monospace"]
+///     n0 [label="pub fn main() u8"]
+///     n1 [label="Return"]
+///     n0 -> n1
+///     n2 [label="IntLiteral 1"]
+///     n1 -> n2
+/// }
+/// "#;
+/// assert_eq!(print_dot(&program), expected);
+/// ```
+pub fn print_dot(program: &Program) -> String {
+    let mut dot = String::from("digraph ast {\n    ordering=out\n    node [shape=box, fontname=\"monospace\"]\n");
+    // The latest node at each depth above the current node, which are its ancestors
+    let mut ancestors = Vec::new();
+    for (index, (depth, label)) in TreePrinter::nodes(program).into_iter().enumerate() {
+        let label = label.replace('\\', "\\\\").replace('"', "\\\"");
+        writeln!(dot, "    n{} [label=\"{}\"]", index, label).unwrap();
+        ancestors.truncate(depth);
+        if let Some(parent) = ancestors.last() {
+            writeln!(dot, "    n{} -> n{}", parent, index).unwrap();
+        }
+        ancestors.push(index);
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+/// Lists the nodes of a tree for [print_tree] and [print_dot], keeping track of how deeply the
+/// current node is nested
+struct TreePrinter {
+    /// Each node's depth and label, in the order that they're printed
+    nodes: Vec<(usize, String)>,
+    depth: usize,
+}
+
+impl TreePrinter {
+    /// Returns the depth and label of each node of `program`, parents before their children.
+    fn nodes(program: &Program) -> Vec<(usize, String)> {
+        let mut printer = Self { nodes: Vec::new(), depth: 0 };
+        for global_statement in &program.global_statements {
+            match global_statement {
+                GlobalStatement::Extern(proto) => printer.line(proto),
+                GlobalStatement::FuncDef(func_def) => {
+                    printer.line(&func_def.proto);
+                    printer.nested(|printer| printer.body(&func_def.body));
+                }
+            }
+        }
+        printer.nodes
+    }
+
+    /// Adds a node at the current depth.
+    fn line(&mut self, node: impl fmt::Display) {
+        self.nodes.push((self.depth, node.to_string()));
+    }
+
+    /// Runs `print_children` one level deeper than the current node.
+    fn nested(&mut self, print_children: impl FnOnce(&mut Self)) {
+        self.depth += 1;
+        print_children(self);
+        self.depth -= 1;
+    }
+
+    /// Appends a line labeled `label` (like `Then`), with `body` nested under it.
+    fn labeled_body(&mut self, label: &str, body: &[Statement]) {
+        self.line(label);
+        self.nested(|printer| printer.body(body));
+    }
+
+    fn body(&mut self, body: &[Statement]) {
+        for statement in body {
+            self.statement(statement);
+        }
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::VarDeclaration(declaration) => {
+                self.line(format_args!("VarDeclaration {} {}", declaration.var_type, declaration.var_name));
+                self.nested(|printer| printer.expr(&declaration.var_value));
+            }
+            Statement::WhileLoop(while_loop) => {
+                self.line("While");
+                self.nested(|printer| {
+                    printer.line("Condition");
+                    printer.nested(|printer| printer.expr(&while_loop.condition));
+                    printer.labeled_body("Body", &while_loop.body);
+                });
+            }
+            Statement::Assignment(assignment) => {
+                self.line(format_args!("Assignment {}", assignment.name));
+                self.nested(|printer| printer.expr(assignment.value));
+            }
+            Statement::Return(value, ..) => {
+                self.line("Return");
+                if let Some(value) = value {
+                    self.nested(|printer| printer.expr(value));
+                }
+            }
+            Statement::Call(call) => self.call(call),
+            Statement::If(if_statement) => {
+                self.line("If");
+                self.nested(|printer| {
+                    printer.line("Condition");
+                    printer.nested(|printer| printer.expr(&if_statement.condition));
+                    printer.labeled_body("Then", &if_statement.then_body);
+                    if let Some(else_body) = &if_statement.else_body {
+                        printer.labeled_body("Else", else_body);
+                    }
+                });
+            }
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Identifier(identifier) => self.line(format_args!("Identifier {}", identifier.name)),
+            Expr::IntLiteral(literal, ..) => self.line(format_args!("IntLiteral {}", literal)),
+            Expr::BoolLiteral(literal, ..) => self.line(format_args!("BoolLiteral {}", literal)),
+            Expr::StrLiteral(literal, ..) => self.line(format_args!("StrLiteral {:?}", literal)),
+            Expr::Binary(binary) => {
+                self.line(format_args!("Binary {}", binary.operator));
+                self.nested(|printer| {
+                    printer.expr(binary.left);
+                    printer.expr(binary.right);
+                });
+            }
+            Expr::Comparison(comparison) => {
+                self.line(format_args!("Comparison {}", comparison.operator));
+                self.nested(|printer| {
+                    printer.expr(comparison.left);
+                    printer.expr(comparison.right);
+                });
+            }
+            Expr::Call(call) => self.call(call),
+            Expr::Unary(unary) => {
+                match &unary.operator {
+                    UnaryOperator::Cast(cast_type) => self.line(format_args!("Cast {}", cast_type)),
+                    UnaryOperator::Negate => self.line("Negate"),
+                }
+                self.nested(|printer| printer.expr(unary.operand));
+            }
+        }
+    }
+
+    fn call(&mut self, call: &Call) {
+        self.line(format_args!("Call {}", call.function_name));
+        self.nested(|printer| call.args.iter().for_each(|arg| printer.expr(arg)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Lexer, Parser};
+    use crate::ast::Arena;
+
+    fn tree(source_code: &str) -> String {
+        let (tokens, spans) = Lexer::lex_with_spans(source_code).unwrap();
+        print_tree(&Parser::parse_program_with_spans(&Arena::new(), &tokens, &spans).unwrap())
+    }
+
+    #[test]
+    fn statements() {
+        let source_code = "\
+extern fn puts(str s) i32
+
+fn count(u8 n) {
+    u8 i = 0
+    while i < n {
+        if i == 3 {
+            puts(\"three\\n\")
+        } else {
+            i = i + 1
+        }
+    }
+    ret
+}
+";
+        let expected = "\
+extern fn puts(str s) i32
+fn count(u8 n) void
+  VarDeclaration u8 i
+    IntLiteral 0
+  While
+    Condition
+      Comparison <
+        Identifier i
+        Identifier n
+    Body
+      If
+        Condition
+          Comparison ==
+            Identifier i
+            IntLiteral 3
+        Then
+          Call puts
+            StrLiteral \"three\\n\"
+        Else
+          Assignment i
+            Binary +
+              Identifier i
+              IntLiteral 1
+  Return
+";
+
+        assert_eq!(tree(source_code), expected);
+    }
+
+    #[test]
+    fn dot() {
+        let source_code = "fn f(str s) {\n    x = 1 + 2 * 3\n    f(\"a \\\"b\\\"\")\n}\n";
+        let (tokens, spans) = Lexer::lex_with_spans(source_code).unwrap();
+        let dot = print_dot(&Parser::parse_program_with_spans(&Arena::new(), &tokens, &spans).unwrap());
+        let expected = r#"digraph ast {
+    ordering=out
+    node [shape=box, fontname="monospace"]
+    n0 [label="fn f(str s) void"]
+    n1 [label="Assignment x"]
+    n0 -> n1
+    n2 [label="Binary +"]
+    n1 -> n2
+    n3 [label="IntLiteral 1"]
+    n2 -> n3
+    n4 [label="Binary *"]
+    n2 -> n4
+    n5 [label="IntLiteral 2"]
+    n4 -> n5
+    n6 [label="IntLiteral 3"]
+    n4 -> n6
+    n7 [label="Call f"]
+    n0 -> n7
+    n8 [label="StrLiteral \"a \\\"b\\\"\""]
+    n7 -> n8
+}
+"#;
+
+        assert_eq!(dot, expected);
+    }
+
+    #[test]
+    fn unary_expressions() {
+        let source_code = "pub fn main() u8 {\n    ret (u8) -(i8) 1\n}\n";
+        let expected = "\
+pub fn main() u8
+  Return
+    Cast u8
+      Negate
+        Cast i8
+          IntLiteral 1
+";
+
+        assert_eq!(tree(source_code), expected);
+    }
+}
@@ -0,0 +1,178 @@
+use std::ops::AddAssign;
+
+use crate::ast::{GlobalStatement, Program};
+use crate::lexing::token::{Span, Token};
+
+/// Statistics about the source code of a file (or, [added](AddAssign) together, of several).
+///
+/// ```
+/// use flick::ast::Arena;
+/// use flick::{Lexer, Parser};
+/// use flick::stats::FileStats;
+///
+/// let source_code = "// Returns 0\npub fn main() u8 {\n\n    ret 0\n}\n";
+/// let (tokens, spans) = Lexer::lex_with_spans(source_code).unwrap();
+/// let arena = Arena::new();
+/// let program = Parser::parse_program_with_spans(&arena, &tokens, &spans).unwrap();
+/// let stats = FileStats::new(source_code, &tokens, &spans, &program);
+/// assert_eq!((stats.lines, stats.code_lines, stats.comment_lines, stats.blank_lines), (5, 3, 1, 1));
+/// assert_eq!((stats.tokens, stats.functions), (10, 1));
+/// assert_eq!(stats.average_function_length(), Some(4.0));
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct FileStats {
+    /// How many lines there are
+    pub lines: usize,
+    /// How many lines have code on them (anything but comments)
+    pub code_lines: usize,
+    /// How many lines have a comment or a docstring on them (including after code)
+    pub comment_lines: usize,
+    /// How many lines are empty, or only have spaces and tabs
+    pub blank_lines: usize,
+    /// How many tokens there are, not counting newlines, comments, and docstrings
+    pub tokens: usize,
+    /// How many functions are defined (`extern` functions aren't counted)
+    pub functions: usize,
+    /// How many lines the functions span altogether, from `fn` to `}`
+    pub function_lines: usize,
+}
+
+impl FileStats {
+    /// Returns the statistics of a file with `source_code`, which lexes to `tokens` (with
+    /// `spans`) and parses to `program`.
+    pub fn new(source_code: &str, tokens: &[Token], spans: &[Span], program: &Program) -> Self {
+        let line_starts: Vec<_> = std::iter::once(0)
+            .chain(source_code.match_indices('\n').map(|(i, _)| i + 1))
+            .filter(|&start| start < source_code.len())
+            .collect();
+        let line_of = |index: usize| line_starts.partition_point(|&start| start <= index) - 1;
+
+        let lines = line_starts.len();
+        let (mut has_code, mut has_comment) = (vec![false; lines], vec![false; lines]);
+        let mut stats = Self { lines, ..Self::default() };
+        for (token, span) in tokens.iter().zip(spans) {
+            match token {
+                Token::Newline => {}
+                Token::Comment(_) | Token::Docstring(_) => has_comment[line_of(span.start)] = true,
+                _ => {
+                    has_code[line_of(span.start)] = true;
+                    stats.tokens += 1;
+                }
+            }
+        }
+        stats.code_lines = has_code.iter().filter(|&&has_code| has_code).count();
+        stats.comment_lines = has_comment.iter().filter(|&&has_comment| has_comment).count();
+        stats.blank_lines = (0..lines)
+            .filter(|&line| {
+                let end = line_starts.get(line + 1).copied().unwrap_or(source_code.len());
+                source_code[line_starts[line]..end].chars().all(char::is_whitespace)
+            })
+            .count();
+
+        for global_statement in &program.global_statements {
+            let GlobalStatement::FuncDef(func_def) = global_statement else { continue };
+            stats.functions += 1;
+            // The body ends at the `}` that matches the first `{` after the function's name
+            let name = spans.iter().position(|span| *span == func_def.proto.span).unwrap_or(0);
+            let mut depth = 0;
+            let end = tokens[name..].iter().zip(&spans[name..]).find_map(|(token, span)| {
+                match token {
+                    Token::LSquirly => depth += 1,
+                    Token::RSquirly if depth == 1 => return Some(span.start),
+                    Token::RSquirly => depth -= 1,
+                    _ => {}
+                }
+                None
+            });
+            let end = end.unwrap_or(source_code.len().saturating_sub(1));
+            stats.function_lines += line_of(end) - line_of(func_def.proto.span.start) + 1;
+        }
+        stats
+    }
+
+    /// Returns how many lines a function spans on average, or `None` if there are no functions.
+    pub fn average_function_length(&self) -> Option<f64> {
+        match self.functions {
+            0 => None,
+            functions => Some(self.function_lines as f64 / functions as f64),
+        }
+    }
+
+    /// Returns the fraction of the lines that aren't blank that have comments, or `None` if
+    /// every line is blank.
+    pub fn comment_ratio(&self) -> Option<f64> {
+        match self.lines - self.blank_lines {
+            0 => None,
+            non_blank_lines => Some(self.comment_lines as f64 / non_blank_lines as f64),
+        }
+    }
+}
+
+impl AddAssign for FileStats {
+    fn add_assign(&mut self, other: Self) {
+        self.lines += other.lines;
+        self.code_lines += other.code_lines;
+        self.comment_lines += other.comment_lines;
+        self.blank_lines += other.blank_lines;
+        self.tokens += other.tokens;
+        self.functions += other.functions;
+        self.function_lines += other.function_lines;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Lexer, Parser};
+    use crate::ast::Arena;
+
+    fn stats(source_code: &str) -> FileStats {
+        let (tokens, spans) = Lexer::lex_with_spans(source_code).unwrap();
+        let arena = Arena::new();
+        let program = Parser::parse_program_with_spans(&arena, &tokens, &spans).unwrap();
+        FileStats::new(source_code, &tokens, &spans, &program)
+    }
+
+    #[test]
+    fn counts_lines_tokens_and_functions() {
+        let stats = stats(
+            "\
+/// Adds one
+fn add_one(u64 n) u64 {
+    ret n + 1  // can overflow
+}
+
+extern fn puts(str s) i32
+
+pub fn main() u8 {
+    if add_one(1) == 2 {
+        puts(\"{}\")
+    }
+    ret 0
+}",
+        );
+        let expected = FileStats {
+            lines: 13,
+            code_lines: 10,
+            comment_lines: 2,
+            blank_lines: 2,
+            tokens: 44,
+            functions: 2,
+            function_lines: 9,
+        };
+        assert_eq!(stats, expected);
+        assert_eq!(stats.average_function_length(), Some(4.5));
+        assert_eq!(stats.comment_ratio(), Some(2.0 / 11.0));
+    }
+
+    #[test]
+    fn empty_file() {
+        let stats = stats("");
+        assert_eq!(stats, FileStats::default());
+        assert_eq!((stats.average_function_length(), stats.comment_ratio()), (None, None));
+
+        let mut total = stats;
+        total += self::stats("\n\n");
+        assert_eq!((total.lines, total.blank_lines), (2, 2));
+    }
+}
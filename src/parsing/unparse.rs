@@ -0,0 +1,381 @@
+use std::fmt::Write;
+
+use super::ast::*;
+use crate::lexing::lexer::Lexer;
+use crate::types::Type;
+
+/// Returns `program` as Flick source code, in a canonical format: four spaces of indentation,
+/// one statement per line, a blank line between functions, and only the parentheses that
+/// are needed. Parsing the result gives back the same tree (apart from spans).
+///
+/// The tree doesn't keep comments, so they're left out, and since `x += 1` is parsed the same
+/// as `x = x + 1`, both come back as `x += 1`.
+///
+/// ```
+/// use flick::ast::Arena;
+/// use flick::{unparse, Lexer, Parser};
+/// let source_code = "pub fn main() u8 {\nret ((1+2))*3\n}";
+/// let tokens = Lexer::lex(source_code).unwrap();
+/// let arena = Arena::new();
+/// let program = Parser::parse_program(&arena, &tokens).unwrap();
+/// assert_eq!(unparse(&program), "pub fn main() u8 {\n    ret (1 + 2) * 3\n}\n");
+/// ```
+pub fn unparse(program: &Program) -> String {
+    let mut unparser = Unparser { source: String::new(), depth: 0 };
+    let mut previous: Option<&GlobalStatement> = None;
+    for global_statement in &program.global_statements {
+        // `extern` functions are grouped together, but function definitions are set apart
+        if previous.is_some_and(|previous| !is_extern(previous) || !is_extern(global_statement)) {
+            unparser.source.push('\n');
+        }
+        match global_statement {
+            GlobalStatement::Extern(proto) => unparser.line(&func_proto(proto)),
+            GlobalStatement::FuncDef(func_def) => {
+                let body = unparser.body(&func_def.body);
+                unparser.line(&format!("{} {}", func_proto(&func_def.proto), body));
+            }
+        }
+        previous = Some(global_statement);
+    }
+    unparser.source
+}
+
+/// Returns `expr` as Flick source code, like `a * (b + 1)` (see [unparse]).
+pub fn unparse_expr(expr: &Expr) -> String {
+    expr_at(expr, Precedence::Lowest)
+}
+
+fn is_extern(global_statement: &GlobalStatement) -> bool {
+    matches!(global_statement, GlobalStatement::Extern(_))
+}
+
+/// Builds up the source code for [unparse], keeping track of how deeply the current statement
+/// is indented
+struct Unparser {
+    source: String,
+    depth: usize,
+}
+
+impl Unparser {
+    /// Appends `line`, indented to the current depth.
+    fn line(&mut self, line: &str) {
+        writeln!(self.source, "{}{}", "    ".repeat(self.depth), line).unwrap();
+    }
+
+    /// Returns `body` in braces, with its statements on their own lines (one level deeper than
+    /// the current depth), and the closing brace indented to the current depth. The opening
+    /// brace is returned rather than appended, so that it can end the line that the body
+    /// belongs to (like `while x < 3 {`).
+    fn body(&mut self, body: &[Statement]) -> String {
+        if body.is_empty() {
+            return "{}".to_string();
+        }
+
+        // The statements are built on their own, since the line that they follow isn't done
+        let outer_source = std::mem::take(&mut self.source);
+        self.depth += 1;
+        for statement in body {
+            self.statement(statement);
+        }
+        self.depth -= 1;
+        let statements = std::mem::replace(&mut self.source, outer_source);
+        format!("{{\n{}{}}}", statements, "    ".repeat(self.depth))
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        let line = match statement {
+            Statement::VarDeclaration(declaration) => format!(
+                "{} {} = {}",
+                declaration.var_type,
+                identifier(declaration.var_name),
+                unparse_expr(&declaration.var_value)
+            ),
+            Statement::WhileLoop(while_loop) => {
+                format!("while {} {}", unparse_expr(&while_loop.condition), self.body(&while_loop.body))
+            }
+            Statement::Assignment(assignment) => Self::assignment(assignment),
+            Statement::Return(None, ..) => "ret".to_string(),
+            Statement::Return(Some(value), ..) => format!("ret {}", unparse_expr(value)),
+            Statement::Call(call) => call_source(call),
+            Statement::If(if_statement) => self.if_statement(if_statement),
+        };
+        self.line(&line);
+    }
+
+    /// Returns an assignment, which is written like `x += y` if it was parsed from one (see
+    /// [unparse]).
+    fn assignment(assignment: &Assignment) -> String {
+        let name = identifier(assignment.name);
+        if let Expr::Binary(binary) = &*assignment.value {
+            let operator = match binary.operator {
+                BinaryOperator::Add => Some("+="),
+                BinaryOperator::Subtract => Some("-="),
+                BinaryOperator::Multiply => Some("*="),
+                BinaryOperator::Divide => Some("/="),
+                BinaryOperator::Remainder => None,
+            };
+            match (&*binary.left, operator) {
+                (Expr::Identifier(left), Some(operator)) if left.name == assignment.name => {
+                    return format!("{} {} {}", name, operator, unparse_expr(binary.right));
+                }
+                _ => {}
+            }
+        }
+        format!("{} = {}", name, unparse_expr(assignment.value))
+    }
+
+    /// Returns an if statement, whose `else` is written as an `else if` if it's just another if
+    /// statement.
+    fn if_statement(&mut self, if_statement: &If) -> String {
+        let mut source = format!("if {} {}", unparse_expr(&if_statement.condition), self.body(&if_statement.then_body));
+        match if_statement.else_body.as_deref() {
+            None => {}
+            Some([Statement::If(else_if)]) => write!(source, " else {}", self.if_statement(else_if)).unwrap(),
+            Some(else_body) => write!(source, " else {}", self.body(else_body)).unwrap(),
+        }
+        source
+    }
+}
+
+/// How tightly an expression binds its operands, from loosest to tightest (so that `1 + 2 * 3`
+/// is `1 + (2 * 3)`).
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+enum Precedence {
+    /// Where any expression can go without parentheses, like a call's argument
+    Lowest,
+    /// A comparison, like `a < b`, whose operands can't be comparisons themselves
+    Comparison,
+    /// `+` and `-`
+    Sum,
+    /// `*`, `/`, and `%`
+    Product,
+    /// A negation or cast, like `-a` or `(u8) a`
+    Unary,
+    /// An identifier, literal, or call, which never needs parentheses
+    Primary,
+}
+
+impl Precedence {
+    fn of(expr: &Expr) -> Self {
+        match expr {
+            Expr::Comparison(_) => Self::Comparison,
+            Expr::Binary(binary) => match binary.operator {
+                BinaryOperator::Add | BinaryOperator::Subtract => Self::Sum,
+                BinaryOperator::Multiply | BinaryOperator::Divide | BinaryOperator::Remainder => Self::Product,
+            },
+            Expr::Unary(_) => Self::Unary,
+            Expr::Identifier(_) | Expr::IntLiteral(..) | Expr::BoolLiteral(..) | Expr::StrLiteral(..) | Expr::Call(_) => {
+                Self::Primary
+            }
+        }
+    }
+
+    /// Returns the next tighter precedence.
+    fn tighter(self) -> Self {
+        match self {
+            Self::Lowest => Self::Comparison,
+            Self::Comparison => Self::Sum,
+            Self::Sum => Self::Product,
+            Self::Product => Self::Unary,
+            Self::Unary | Self::Primary => Self::Primary,
+        }
+    }
+}
+
+/// Returns `expr` where an expression of at least `precedence` is expected, in parentheses if
+/// it binds more loosely than that.
+fn expr_at(expr: &Expr, precedence: Precedence) -> String {
+    let own_precedence = Precedence::of(expr);
+    let source = match expr {
+        Expr::Identifier(name) => identifier(name.name),
+        Expr::IntLiteral(literal, ..) => literal.to_string(),
+        Expr::BoolLiteral(literal, ..) => literal.to_string(),
+        Expr::StrLiteral(literal, ..) => str_literal(literal),
+        // Comparisons can't be chained, and the other operators are left-associative, so
+        // `a - (b - c)` needs its parentheses, but `(a - b) - c` doesn't
+        Expr::Comparison(comparison) => format!(
+            "{} {} {}",
+            expr_at(comparison.left, own_precedence.tighter()),
+            comparison.operator,
+            expr_at(comparison.right, own_precedence.tighter())
+        ),
+        Expr::Binary(binary) => format!(
+            "{} {} {}",
+            expr_at(binary.left, own_precedence),
+            binary.operator,
+            expr_at(binary.right, own_precedence.tighter())
+        ),
+        Expr::Unary(unary) => match &unary.operator {
+            UnaryOperator::Negate => format!("-{}", expr_at(unary.operand, Precedence::Unary)),
+            UnaryOperator::Cast(cast_type) => format!("({}) {}", cast_type, expr_at(unary.operand, Precedence::Unary)),
+        },
+        Expr::Call(call) => call_source(call),
+    };
+    match own_precedence < precedence {
+        true => format!("({})", source),
+        false => source,
+    }
+}
+
+/// Returns a call, like `f(a, b + 1)`.
+fn call_source(call: &Call) -> String {
+    let args: Vec<_> = call.args.iter().map(unparse_expr).collect();
+    format!("{}({})", identifier(call.function_name), args.join(", "))
+}
+
+/// Returns a function prototype, like `pub fn add(u8 a, u8 b) u8` (without its return type if
+/// that's `void`).
+fn func_proto(proto: &FuncProto) -> String {
+    let params: Vec<_> = proto
+        .params
+        .iter()
+        .map(|param| format!("{} {}", param.param_type, identifier(&param.param_name)))
+        .collect();
+    let mut source = format!("{} {}({})", proto.keywords(), identifier(&proto.name), params.join(", "));
+    if *proto.return_type != Type::Void {
+        write!(source, " {}", proto.return_type).unwrap();
+    }
+    source
+}
+
+/// Returns `name` as an identifier, which is a raw identifier (like `r#while`) if `name` is
+/// spelled like a keyword or a type.
+fn identifier(name: &str) -> String {
+    let is_int_type = (name.starts_with('u') || name.starts_with('i'))
+        && name.len() > 1
+        && name.chars().skip(1).all(|c| c.is_ascii_digit());
+    match Lexer::KEYWORDS.contains(&name) || ["bool", "str", "void"].contains(&name) || is_int_type {
+        true => format!("r#{}", name),
+        false => name.to_string(),
+    }
+}
+
+/// Returns a string literal whose value is `value`, escaping the characters that need it.
+fn str_literal(value: &str) -> String {
+    let mut literal = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '\n' => literal.push_str("\\n"),
+            '\t' => literal.push_str("\\t"),
+            '\0' => literal.push_str("\\0"),
+            '\\' => literal.push_str("\\\\"),
+            '"' => literal.push_str("\\\""),
+            c => literal.push(c),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{print_tree, Parser};
+
+    fn parse<'a>(arena: &'a Arena<'a>, source_code: &'a str) -> Program<'a> {
+        // Leaked, so that the program can borrow its names from them for the rest of the test
+        let tokens = Lexer::lex(source_code).unwrap().leak();
+        Parser::parse_program(arena, tokens).unwrap()
+    }
+
+    /// Checks that `source_code` is unparsed as `expected`, and that parsing that gives back
+    /// the same tree.
+    fn assert_unparses_to(source_code: &str, expected: &str) {
+        let arena = Arena::new();
+        let program = parse(&arena, source_code);
+        let unparsed = unparse(&program);
+        assert_eq!(unparsed, expected);
+        assert_eq!(print_tree(&parse(&arena, &unparsed)), print_tree(&program));
+    }
+
+    #[test]
+    fn statements() {
+        let source_code = "\
+extern fn puts(str s) i32
+extern \"c\" fn exit(i32 code)
+// a comment
+fn count(u8 n) {
+    u8 i = 0
+    while i < n {
+        if i == 3 {
+            puts(\"three\\n\")
+        } else {
+            if i == 4 {
+            } else {
+                i = i % 2
+            }
+        }
+        i += 1
+    }
+    ret
+}
+pub fn main() u8 {
+    count(5)
+    ret 0
+}
+";
+        let expected = "\
+extern fn puts(str s) i32
+extern \"c\" fn exit(i32 code)
+
+fn count(u8 n) {
+    u8 i = 0
+    while i < n {
+        if i == 3 {
+            puts(\"three\\n\")
+        } else if i == 4 {} else {
+            i = i % 2
+        }
+        i += 1
+    }
+    ret
+}
+
+pub fn main() u8 {
+    count(5)
+    ret 0
+}
+";
+        assert_unparses_to(source_code, expected);
+    }
+
+    #[test]
+    fn parentheses() {
+        let unparsed = |source_code: &str| {
+            let source_code = format!("fn f() {{\n    x = {}\n}}\n", source_code);
+            let arena = Arena::new();
+            let program = parse(&arena, &source_code);
+            let GlobalStatement::FuncDef(func_def) = &program.global_statements[0] else { unreachable!() };
+            let Statement::Assignment(assignment) = &func_def.body[0] else { unreachable!() };
+            unparse_expr(assignment.value)
+        };
+        assert_eq!(unparsed("((1 + 2)) * 3"), "(1 + 2) * 3");
+        assert_eq!(unparsed("1 + (2 * 3)"), "1 + 2 * 3");
+        assert_eq!(unparsed("(a - b) - c"), "a - b - c");
+        assert_eq!(unparsed("a - (b - c)"), "a - (b - c)");
+        assert_eq!(unparsed("(a < b) == (c + 1 > d)"), "(a < b) == (c + 1 > d)");
+        assert_eq!(unparsed("-(a + b) * -(-c)"), "-(a + b) * --c");
+        assert_eq!(unparsed("(u8) (a * b) + (u8) f((a < b), -1)"), "(u8) (a * b) + (u8) f(a < b, -1)");
+    }
+
+    #[test]
+    fn raw_identifiers_and_escapes() {
+        let source_code = "fn r#if(u8 r#u8, bool r#str) {\n    str r#while = \"tab\\tquote\\\"null\\0\\\\\"\n    r#i32 = r#u8\n}\n";
+        assert_unparses_to(source_code, source_code);
+    }
+
+    #[test]
+    fn examples_round_trip() {
+        let examples = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("examples");
+        for entry in std::fs::read_dir(examples).unwrap() {
+            let source_code = std::fs::read_to_string(entry.unwrap().path()).unwrap();
+            let Ok(tokens) = Lexer::lex(&source_code) else { continue };
+            let arena = Arena::new();
+            let Ok(program) = Parser::parse_program(&arena, &tokens) else { continue };
+
+            let unparsed = unparse(&program);
+            assert_eq!(print_tree(&parse(&arena, &unparsed)), print_tree(&program));
+            assert_eq!(unparse(&parse(&arena, &unparsed)), unparsed);
+        }
+    }
+}
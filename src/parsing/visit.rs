@@ -0,0 +1,332 @@
+use crate::ast::*;
+
+/// Something that walks an [abstract syntax tree](crate::ast), looking at the nodes that it
+/// cares about, like [Program::node_count] counting every node, or a lint looking for calls.
+///
+/// Each `visit_*` method is called on a node of its kind. By default, it just visits the node's
+/// children (with the matching `walk_*` function, like [walk_statement]), so a visitor only
+/// overrides the methods for the nodes that it cares about. An override that still wants the
+/// children visited calls the `walk_*` function itself, before or after looking at the node.
+///
+/// ```
+/// use flick::ast::{Arena, Call, Program};
+/// use flick::visit::{self, Visitor};
+/// use flick::{Lexer, Parser};
+///
+/// /// Collects the name of every function that's called
+/// struct CallCollector<'ast>(Vec<&'ast str>);
+///
+/// impl<'ast> Visitor<'ast> for CallCollector<'ast> {
+///     fn visit_call(&mut self, call: &'ast Call) {
+///         self.0.push(&call.function_name);
+///         visit::walk_call(self, call); // e.g. `f(g())` calls `g` too
+///     }
+/// }
+///
+/// let source_code = "pub fn main() u8 {\n    f(g())\n    ret 0\n}\n";
+/// let tokens = Lexer::lex(source_code).unwrap();
+/// let arena = Arena::new();
+/// let program = Parser::parse_program(&arena, &tokens).unwrap();
+/// let mut collector = CallCollector(Vec::new());
+/// collector.visit_program(&program);
+/// assert_eq!(collector.0, ["f", "g"]);
+/// ```
+pub trait Visitor<'ast> {
+    fn visit_program(&mut self, program: &'ast Program) {
+        walk_program(self, program);
+    }
+
+    fn visit_global_statement(&mut self, global_statement: &'ast GlobalStatement) {
+        walk_global_statement(self, global_statement);
+    }
+
+    /// Visits the prototype of an `extern` function or of a function definition (which has no
+    /// statements or expressions in it, so there's nothing to walk).
+    fn visit_func_proto(&mut self, _func_proto: &'ast FuncProto) {}
+
+    fn visit_func_def(&mut self, func_def: &'ast FuncDef) {
+        walk_func_def(self, func_def);
+    }
+
+    /// Visits a function's body, or the body of an `if` or `while`, which is a scope of its own
+    /// (e.g. for a visitor that keeps track of which variables are declared).
+    fn visit_body(&mut self, body: &'ast [Statement]) {
+        walk_body(self, body);
+    }
+
+    fn visit_statement(&mut self, statement: &'ast Statement) {
+        walk_statement(self, statement);
+    }
+
+    fn visit_expr(&mut self, expr: &'ast Expr) {
+        walk_expr(self, expr);
+    }
+
+    /// Visits a call, whether it's a statement of its own or part of an expression.
+    fn visit_call(&mut self, call: &'ast Call) {
+        walk_call(self, call);
+    }
+}
+
+/// Visits each global statement of `program`.
+pub fn walk_program<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, program: &'ast Program) {
+    for global_statement in &program.global_statements {
+        visitor.visit_global_statement(global_statement);
+    }
+}
+
+/// Visits the prototype of an `extern` function, or the definition of a function.
+pub fn walk_global_statement<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, global_statement: &'ast GlobalStatement) {
+    match global_statement {
+        GlobalStatement::Extern(func_proto) => visitor.visit_func_proto(func_proto),
+        GlobalStatement::FuncDef(func_def) => visitor.visit_func_def(func_def),
+    }
+}
+
+/// Visits the prototype of `func_def`, then its body.
+pub fn walk_func_def<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, func_def: &'ast FuncDef) {
+    visitor.visit_func_proto(&func_def.proto);
+    visitor.visit_body(&func_def.body);
+}
+
+/// Visits each statement in `body`.
+pub fn walk_body<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, body: &'ast [Statement]) {
+    for statement in body {
+        visitor.visit_statement(statement);
+    }
+}
+
+/// Visits the expressions and bodies in `statement`, in the order that they're written.
+pub fn walk_statement<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, statement: &'ast Statement) {
+    match statement {
+        Statement::VarDeclaration(var_declaration) => visitor.visit_expr(&var_declaration.var_value),
+        Statement::WhileLoop(while_loop) => {
+            visitor.visit_expr(&while_loop.condition);
+            visitor.visit_body(&while_loop.body);
+        }
+        Statement::Assignment(assignment) => visitor.visit_expr(assignment.value),
+        Statement::Return(value, ..) => {
+            if let Some(value) = value {
+                visitor.visit_expr(value);
+            }
+        }
+        Statement::Call(call) => visitor.visit_call(call),
+        Statement::If(if_statement) => {
+            visitor.visit_expr(&if_statement.condition);
+            visitor.visit_body(&if_statement.then_body);
+            if let Some(else_body) = &if_statement.else_body {
+                visitor.visit_body(else_body);
+            }
+        }
+    }
+}
+
+/// Visits the operands of `expr` (from left to right), or the call that it is.
+pub fn walk_expr<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, expr: &'ast Expr) {
+    match expr {
+        Expr::Identifier(_) | Expr::IntLiteral(..) | Expr::BoolLiteral(..) | Expr::StrLiteral(..) => {}
+        Expr::Binary(binary) => {
+            visitor.visit_expr(binary.left);
+            visitor.visit_expr(binary.right);
+        }
+        Expr::Comparison(comparison) => {
+            visitor.visit_expr(comparison.left);
+            visitor.visit_expr(comparison.right);
+        }
+        Expr::Call(call) => visitor.visit_call(call),
+        Expr::Unary(unary) => visitor.visit_expr(unary.operand),
+    }
+}
+
+/// Visits each argument of `call`.
+pub fn walk_call<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, call: &'ast Call) {
+    for arg in &call.args {
+        visitor.visit_expr(arg);
+    }
+}
+
+/// Like [Visitor], but can change the nodes that it visits (e.g. to fold `1 + 2` into `3`).
+///
+/// A `visit_*_mut` method that replaces a node can still visit the new node's children, by
+/// calling the matching `walk_*_mut` function on it.
+pub trait VisitorMut {
+    fn visit_program_mut(&mut self, program: &mut Program) {
+        walk_program_mut(self, program);
+    }
+
+    fn visit_global_statement_mut(&mut self, global_statement: &mut GlobalStatement) {
+        walk_global_statement_mut(self, global_statement);
+    }
+
+    fn visit_func_proto_mut(&mut self, _func_proto: &mut FuncProto) {}
+
+    fn visit_func_def_mut(&mut self, func_def: &mut FuncDef) {
+        walk_func_def_mut(self, func_def);
+    }
+
+    /// Visits a body as a whole, so that statements can be added to it or removed from it.
+    fn visit_body_mut(&mut self, body: &mut Vec<Statement>) {
+        walk_body_mut(self, body);
+    }
+
+    fn visit_statement_mut(&mut self, statement: &mut Statement) {
+        walk_statement_mut(self, statement);
+    }
+
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        walk_expr_mut(self, expr);
+    }
+
+    fn visit_call_mut(&mut self, call: &mut Call) {
+        walk_call_mut(self, call);
+    }
+}
+
+/// Like [walk_program], but for a [VisitorMut].
+pub fn walk_program_mut<V: VisitorMut + ?Sized>(visitor: &mut V, program: &mut Program) {
+    for global_statement in &mut program.global_statements {
+        visitor.visit_global_statement_mut(global_statement);
+    }
+}
+
+/// Like [walk_global_statement], but for a [VisitorMut].
+pub fn walk_global_statement_mut<V: VisitorMut + ?Sized>(visitor: &mut V, global_statement: &mut GlobalStatement) {
+    match global_statement {
+        GlobalStatement::Extern(func_proto) => visitor.visit_func_proto_mut(func_proto),
+        GlobalStatement::FuncDef(func_def) => visitor.visit_func_def_mut(func_def),
+    }
+}
+
+/// Like [walk_func_def], but for a [VisitorMut].
+pub fn walk_func_def_mut<V: VisitorMut + ?Sized>(visitor: &mut V, func_def: &mut FuncDef) {
+    visitor.visit_func_proto_mut(&mut func_def.proto);
+    visitor.visit_body_mut(&mut func_def.body);
+}
+
+/// Like [walk_body], but for a [VisitorMut].
+pub fn walk_body_mut<V: VisitorMut + ?Sized>(visitor: &mut V, body: &mut Vec<Statement>) {
+    for statement in body {
+        visitor.visit_statement_mut(statement);
+    }
+}
+
+/// Like [walk_statement], but for a [VisitorMut].
+pub fn walk_statement_mut<V: VisitorMut + ?Sized>(visitor: &mut V, statement: &mut Statement) {
+    match statement {
+        Statement::VarDeclaration(var_declaration) => visitor.visit_expr_mut(&mut var_declaration.var_value),
+        Statement::WhileLoop(while_loop) => {
+            visitor.visit_expr_mut(&mut while_loop.condition);
+            visitor.visit_body_mut(&mut while_loop.body);
+        }
+        Statement::Assignment(assignment) => visitor.visit_expr_mut(assignment.value),
+        Statement::Return(value, ..) => {
+            if let Some(value) = value {
+                visitor.visit_expr_mut(value);
+            }
+        }
+        Statement::Call(call) => visitor.visit_call_mut(call),
+        Statement::If(if_statement) => {
+            visitor.visit_expr_mut(&mut if_statement.condition);
+            visitor.visit_body_mut(&mut if_statement.then_body);
+            if let Some(else_body) = &mut if_statement.else_body {
+                visitor.visit_body_mut(else_body);
+            }
+        }
+    }
+}
+
+/// Like [walk_expr], but for a [VisitorMut].
+pub fn walk_expr_mut<V: VisitorMut + ?Sized>(visitor: &mut V, expr: &mut Expr) {
+    match expr {
+        Expr::Identifier(_) | Expr::IntLiteral(..) | Expr::BoolLiteral(..) | Expr::StrLiteral(..) => {}
+        Expr::Binary(binary) => {
+            visitor.visit_expr_mut(binary.left);
+            visitor.visit_expr_mut(binary.right);
+        }
+        Expr::Comparison(comparison) => {
+            visitor.visit_expr_mut(comparison.left);
+            visitor.visit_expr_mut(comparison.right);
+        }
+        Expr::Call(call) => visitor.visit_call_mut(call),
+        Expr::Unary(unary) => visitor.visit_expr_mut(unary.operand),
+    }
+}
+
+/// Like [walk_call], but for a [VisitorMut].
+pub fn walk_call_mut<V: VisitorMut + ?Sized>(visitor: &mut V, call: &mut Call) {
+    for arg in &mut call.args {
+        visitor.visit_expr_mut(arg);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{print_tree, Lexer, Parser};
+
+    fn parse<'a>(arena: &'a Arena<'a>, source_code: &'a str) -> Program<'a> {
+        // Leaked, so that the program can borrow its names from them for the rest of the test
+        let tokens = Lexer::lex(source_code).unwrap().leak();
+        Parser::parse_program(arena, tokens).unwrap()
+    }
+
+    /// Records each function's name, the braces around each body, and each identifier, in order
+    #[derive(Default)]
+    struct Recorder(Vec<String>);
+
+    impl<'ast> Visitor<'ast> for Recorder {
+        fn visit_func_proto(&mut self, func_proto: &'ast FuncProto) {
+            self.0.push(format!("fn {}", func_proto.name));
+        }
+
+        fn visit_body(&mut self, body: &'ast [Statement]) {
+            self.0.push("{".to_string());
+            walk_body(self, body);
+            self.0.push("}".to_string());
+        }
+
+        fn visit_expr(&mut self, expr: &'ast Expr) {
+            if let Expr::Identifier(identifier) = expr {
+                self.0.push(identifier.name.to_string());
+            }
+            walk_expr(self, expr);
+        }
+    }
+
+    #[test]
+    fn visits_in_source_order() {
+        let arena = Arena::new();
+        let program = parse(
+            &arena,
+            "extern fn f(u8 x)\npub fn main() u8 {\n    u8 a = 1\n    while a < b {\n        f(c)\n    }\n    if d {\n    } else {\n        ret e\n    }\n    ret 0\n}\n",
+        );
+        let mut recorder = Recorder::default();
+        recorder.visit_program(&program);
+        assert_eq!(
+            recorder.0,
+            ["fn f", "fn main", "{", "a", "b", "{", "c", "}", "d", "{", "}", "{", "e", "}", "}"]
+        );
+    }
+
+    /// Replaces every `x` with `0`
+    struct ZeroX;
+
+    impl VisitorMut for ZeroX {
+        fn visit_expr_mut(&mut self, expr: &mut Expr) {
+            match expr {
+                Expr::Identifier(identifier) if identifier.name == "x" => *expr = Expr::IntLiteral("0", identifier.span, NodeId::default()),
+                _ => walk_expr_mut(self, expr),
+            }
+        }
+    }
+
+    #[test]
+    fn visitor_mut_replaces_nodes() {
+        let arena = Arena::new();
+        let mut program = parse(&arena, "pub fn main() u8 {\n    f(x + 1, -x)\n    ret x\n}\n");
+        ZeroX.visit_program_mut(&mut program);
+        // The spans of the new nodes are different, which the printed trees leave out
+        let expected = parse(&arena, "pub fn main() u8 {\n    f(0 + 1, -0)\n    ret 0\n}\n");
+        assert_eq!(print_tree(&program), print_tree(&expected));
+    }
+}
@@ -0,0 +1,300 @@
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::{Lint, LintLevel, OptLevel};
+
+/// A Flick project: a directory with a [CONFIG_FILE_NAME] file in it, which says how to build
+/// the project (so that `flick build` doesn't need to be told).
+///
+/// ```
+/// use std::path::Path;
+/// use flick::{project::Project, OptLevel};
+///
+/// let project = Project::parse("/hello", r#"
+///     [project]
+///     name = "hello"
+///
+///     [build]
+///     opt-level = "s"
+///     libraries = ["m"]
+/// "#).unwrap();
+///
+/// assert_eq!(project.entry_path(), Path::new("/hello/src"));
+/// assert_eq!(project.output_path(), Path::new("/hello/hello"));
+/// assert_eq!(project.config.build.opt_level, Some(OptLevel::Os));
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Project {
+    /// The directory that the config file is in, which the paths in it are relative to
+    pub root: PathBuf,
+    pub config: ProjectConfig,
+}
+
+/// The name of the file that configures a [Project]
+pub const CONFIG_FILE_NAME: &str = "flick.toml";
+
+/// The contents of a [CONFIG_FILE_NAME] file.
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProjectConfig {
+    pub project: ProjectSection,
+    #[serde(default)]
+    pub build: BuildSection,
+    /// The `[lints]` table, which sets the levels of lints by name (like
+    /// `unused-variables = "allow"`), in alphabetical order
+    #[serde(default, deserialize_with = "deserialize_lints")]
+    pub lints: Vec<(&'static Lint, LintLevel)>,
+}
+
+/// The `[project]` table of a [ProjectConfig], which says what the project is.
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProjectSection {
+    pub name: String,
+    /// The file to compile, or a directory whose `.fl` files are compiled together (default:
+    /// `src`)
+    #[serde(default = "default_entry")]
+    pub entry: PathBuf,
+}
+
+/// The `[build]` table of a [ProjectConfig], which has the defaults for `flick build`'s flags.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct BuildSection {
+    /// What to name the executable, without an extension (default: the project's name)
+    pub output: Option<PathBuf>,
+    /// The target triple to compile for (like `--target`)
+    pub target: Option<String>,
+    /// The optimization level (like `-O`), as a string or a number
+    #[serde(default, deserialize_with = "deserialize_opt_level")]
+    pub opt_level: Option<OptLevel>,
+    /// The libraries to link with (like `-l`), like `m` for `libm`
+    #[serde(default)]
+    pub libraries: Vec<String>,
+    /// The directory to cache compiled code in (like `--cache-dir`)
+    pub cache_dir: Option<PathBuf>,
+}
+
+/// An error from reading a [Project]'s config file.
+#[derive(Debug)]
+pub enum ProjectError {
+    /// The config file couldn't be read (or written, by [Project::init]).
+    Io { path: PathBuf, error: io::Error },
+    /// The config file isn't valid TOML, or doesn't have the right fields.
+    Invalid { path: PathBuf, error: toml::de::Error },
+    /// [Project::init] was asked to create a project where there already is one.
+    AlreadyExists(PathBuf),
+}
+
+impl Project {
+    /// Returns the project that `directory` is in: the closest directory to it (starting with
+    /// `directory` itself, then its parent, and so on) that has a [CONFIG_FILE_NAME] file in it.
+    /// Returns `None` if there isn't one.
+    pub fn find(directory: &Path) -> Result<Option<Self>, ProjectError> {
+        for root in directory.ancestors() {
+            if root.join(CONFIG_FILE_NAME).is_file() {
+                return Self::load(root).map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    /// Reads the project whose [CONFIG_FILE_NAME] file is in `root`.
+    pub fn load(root: &Path) -> Result<Self, ProjectError> {
+        let path = root.join(CONFIG_FILE_NAME);
+        match fs::read_to_string(&path) {
+            Ok(config) => Self::parse(root, &config),
+            Err(error) => Err(ProjectError::Io { path, error }),
+        }
+    }
+
+    /// Returns the project in `root` with the config file `config` (the contents of a
+    /// [CONFIG_FILE_NAME] file).
+    pub fn parse(root: impl Into<PathBuf>, config: &str) -> Result<Self, ProjectError> {
+        let root = root.into();
+        match toml::from_str(config) {
+            Ok(config) => Ok(Self { root, config }),
+            Err(error) => Err(ProjectError::Invalid { path: root.join(CONFIG_FILE_NAME), error }),
+        }
+    }
+
+    /// Creates a new project called `name` in `root` (which is created if it doesn't exist): a
+    /// [CONFIG_FILE_NAME] file, and a `src/main.fl` with a hello world program in it.
+    pub fn init(root: &Path, name: &str) -> Result<Self, ProjectError> {
+        let config_path = root.join(CONFIG_FILE_NAME);
+        if config_path.exists() {
+            return Err(ProjectError::AlreadyExists(config_path));
+        }
+
+        let config = format!(
+            "[project]\nname = {}\nentry = \"src\"\n\n[build]\nopt-level = \"1\"\nlibraries = []\n",
+            toml::Value::String(name.to_string())
+        );
+        let main_path = root.join("src").join("main.fl");
+        let write = |path: &Path, contents: &str| {
+            let result = match path.parent() {
+                Some(directory) => fs::create_dir_all(directory).and_then(|_| fs::write(path, contents)),
+                None => fs::write(path, contents),
+            };
+            result.map_err(|error| ProjectError::Io { path: path.to_path_buf(), error })
+        };
+        write(&config_path, &config)?;
+        if !main_path.exists() {
+            write(&main_path, HELLO_WORLD)?;
+        }
+        Self::parse(root, &config)
+    }
+
+    /// Returns the path of the file (or directory) to compile.
+    pub fn entry_path(&self) -> PathBuf {
+        self.root.join(&self.config.project.entry)
+    }
+
+    /// Returns the path of the executable, without an extension.
+    pub fn output_path(&self) -> PathBuf {
+        match &self.config.build.output {
+            Some(output) => self.root.join(output),
+            None => self.root.join(&self.config.project.name),
+        }
+    }
+
+    /// Returns the path of the directory to cache compiled code in, if the project sets one.
+    pub fn cache_dir(&self) -> Option<PathBuf> {
+        self.config.build.cache_dir.as_ref().map(|cache_dir| self.root.join(cache_dir))
+    }
+}
+
+/// The program that [Project::init] starts a project with
+const HELLO_WORLD: &str = "\
+extern fn puts(str s) i32
+
+pub fn main() u8 {
+    puts(\"Hello, world!\")
+    ret 0
+}
+";
+
+fn default_entry() -> PathBuf {
+    PathBuf::from("src")
+}
+
+/// Reads an optimization level like `"2"`, `2`, or `"s"` (without the `-O`).
+fn deserialize_opt_level<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<OptLevel>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum RawOptLevel {
+        Number(u8),
+        String(String),
+    }
+
+    let level = match RawOptLevel::deserialize(deserializer)? {
+        RawOptLevel::Number(level) => level.to_string(),
+        RawOptLevel::String(level) => level,
+    };
+    level.parse().map(Some).map_err(serde::de::Error::custom)
+}
+
+/// Reads the `[lints]` table, whose keys are the names (or codes) of lints, and whose values are
+/// `"allow"`, `"warn"`, or `"deny"`.
+fn deserialize_lints<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Vec<(&'static Lint, LintLevel)>, D::Error> {
+    let levels = std::collections::BTreeMap::<String, LintLevel>::deserialize(deserializer)?;
+    levels
+        .into_iter()
+        .map(|(name, level)| match Lint::find(&name) {
+            Some(lint) => Ok((lint, level)),
+            None => Err(serde::de::Error::custom(format!("there's no lint called '{}'", name))),
+        })
+        .collect()
+}
+
+impl fmt::Display for ProjectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io { path, error } => write!(f, "couldn't access '{}': {}", path.display(), error),
+            Self::Invalid { path, error } => write!(f, "invalid '{}': {}", path.display(), error),
+            Self::AlreadyExists(path) => write!(f, "'{}' already exists", path.display()),
+        }
+    }
+}
+
+impl Error for ProjectError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults() {
+        let project = Project::parse("/hello", "[project]\nname = \"hello\"\n").unwrap();
+        assert_eq!(project.entry_path(), Path::new("/hello/src"));
+        assert_eq!(project.output_path(), Path::new("/hello/hello"));
+        assert_eq!(project.config.build, BuildSection::default());
+        assert_eq!(project.cache_dir(), None);
+        assert!(project.config.lints.is_empty());
+    }
+
+    #[test]
+    fn build_section() {
+        let config = "\
+[project]
+name = \"hello\"
+entry = \"main.fl\"
+
+[build]
+output = \"bin/hi\"
+target = \"wasm32-unknown-unknown\"
+opt-level = 3
+libraries = [\"m\", \"pthread\"]
+cache-dir = \".cache\"
+";
+        let project = Project::parse("/hello", config).unwrap();
+        assert_eq!(project.entry_path(), Path::new("/hello/main.fl"));
+        assert_eq!(project.output_path(), Path::new("/hello/bin/hi"));
+        assert_eq!(project.config.build.target.as_deref(), Some("wasm32-unknown-unknown"));
+        assert_eq!(project.config.build.opt_level, Some(OptLevel::O3));
+        assert_eq!(project.config.build.libraries, ["m", "pthread"]);
+        assert_eq!(project.cache_dir(), Some(PathBuf::from("/hello/.cache")));
+    }
+
+    #[test]
+    fn lints_section() {
+        let config = "[project]\nname = \"hello\"\n\n[lints]\nunused-variables = \"allow\"\nW0201 = \"deny\"\n";
+        let project = Project::parse("/hello", config).unwrap();
+        let levels: Vec<_> = project.config.lints.iter().map(|(lint, level)| (lint.name, *level)).collect();
+        assert_eq!(levels, [("shadowed-variables", LintLevel::Deny), ("unused-variables", LintLevel::Allow)]);
+    }
+
+    #[test]
+    fn invalid_configs() {
+        let invalid = |config| match Project::parse("/hello", config) {
+            Err(ProjectError::Invalid { error, .. }) => error.message().to_string(),
+            result => panic!("expected an error, but got {:?}", result),
+        };
+        assert!(invalid("[build]\nopt-level = \"2\"\n").contains("missing field `project`"));
+        assert!(invalid("[project]\nname = \"hello\"\nversion = \"1.0\"\n").contains("unknown field `version`"));
+        assert!(invalid("[project]\nname = \"hello\"\n[build]\nopt-level = \"4\"\n").contains("unknown optimization level '4'"));
+        assert!(invalid("[project]\nname = \"hello\"\n[lints]\nunused = \"allow\"\n").contains("there's no lint called 'unused'"));
+        assert!(invalid("[project]\nname = \"hello\"\n[lints]\nendless-loops = \"forbid\"\n").contains("unknown variant `forbid`"));
+    }
+
+    #[test]
+    fn init_and_find() {
+        let root = std::env::temp_dir().join(format!("flick-project-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+
+        let project = Project::init(&root, "hello \"world\"").unwrap();
+        assert_eq!(project.config.project.name, "hello \"world\"");
+        assert!(root.join("src").join("main.fl").is_file());
+        assert!(matches!(Project::init(&root, "again"), Err(ProjectError::AlreadyExists(_))));
+
+        let found = Project::find(&root.join("src")).unwrap();
+        assert_eq!(found, Some(project));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,74 @@
+use std::fmt;
+
+use crate::lexing::token::Span;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ResolvingError {
+    /// `suggestion` is a name that's in scope and spelled similarly, if there is one
+    UndefinedVariable { name: String, suggestion: Option<String> },
+    /// `suggestion` is a function (or keyword) that's spelled similarly, if there is one
+    UndefinedFunction { name: String, suggestion: Option<String> },
+    NotAFunction(String),
+    FunctionRedefinition { name: String, first_definition: Span },
+    ParamRedefinition { name: String, first_definition: Span },
+    VariableRedeclaration { name: String, first_definition: Span },
+    /// A warning (rather than an error): a variable shadows one from an outer scope
+    ShadowedVariable { name: String, shadowed: Span },
+}
+
+impl ResolvingError {
+    /// Returns the code that identifies this kind of error (or warning), like `E0201` (see
+    /// [Diagnostic::code](crate::Diagnostic::code)).
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::UndefinedVariable { .. } => "E0201",
+            Self::UndefinedFunction { .. } => "E0202",
+            Self::NotAFunction(_) => "E0203",
+            Self::FunctionRedefinition { .. } => "E0204",
+            Self::ParamRedefinition { .. } => "E0205",
+            Self::VariableRedeclaration { .. } => "E0206",
+            Self::ShadowedVariable { .. } => "W0201",
+        }
+    }
+
+    /// Returns where the name in question was first defined, if the error is about defining it
+    /// again (so that the error can point at both definitions).
+    pub fn first_definition(&self) -> Option<Span> {
+        match self {
+            Self::FunctionRedefinition { first_definition, .. }
+            | Self::ParamRedefinition { first_definition, .. }
+            | Self::VariableRedeclaration { first_definition, .. }
+            | Self::ShadowedVariable { shadowed: first_definition, .. } => Some(*first_definition),
+            _ => None,
+        }
+    }
+
+    /// Returns a similarly spelled name that the code might have meant, if the error is about a
+    /// name that doesn't refer to anything.
+    pub fn suggestion(&self) -> Option<&str> {
+        match self {
+            Self::UndefinedVariable { suggestion, .. } | Self::UndefinedFunction { suggestion, .. } => {
+                suggestion.as_deref()
+            }
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ResolvingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UndefinedVariable { name, .. } => write!(f, "cannot find variable '{}' in this scope", name),
+            Self::UndefinedFunction { name, .. } => write!(f, "cannot find function '{}'", name),
+            Self::NotAFunction(name) => write!(f, "'{}' is a variable, not a function", name),
+            Self::FunctionRedefinition { name, .. } => write!(f, "function '{}' is defined more than once", name),
+            Self::ParamRedefinition { name, .. } => write!(f, "parameter '{}' is declared more than once", name),
+            Self::VariableRedeclaration { name, .. } => {
+                write!(f, "variable '{}' is already declared in this scope", name)
+            }
+            Self::ShadowedVariable { name, .. } => {
+                write!(f, "variable '{}' shadows a variable from an outer scope", name)
+            }
+        }
+    }
+}
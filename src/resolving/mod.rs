@@ -0,0 +1,10 @@
+/// Module that defines the [Resolver](resolver::Resolver) struct for checking that every name
+/// in an abstract syntax tree refers to something.
+pub mod resolver;
+
+/// Module that defines the name resolution errors.
+pub mod error;
+
+/// Module that defines [rename](rename::rename), which renames a function or a variable
+/// everywhere it's used.
+pub mod rename;
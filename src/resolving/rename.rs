@@ -0,0 +1,294 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::ast::{Arena, Call, Expr, FuncProto, GlobalStatement, NodeId, NodeMap, Program, Statement};
+use crate::error::FlickError;
+use crate::lexing::cst::Cst;
+use crate::lexing::lexer::Lexer;
+use crate::lexing::token::{Span, Token};
+use crate::parsing::parser::Parser;
+use crate::resolving::resolver::Resolver;
+use crate::scope_manager::{Symbol, SymbolKind};
+use crate::visit::{self, Visitor};
+
+/// Renames the function, parameter, or local variable called `old_name` in `source_code` to
+/// `new_name`, where it's declared and everywhere that it's used, and returns the new source
+/// code. Other things with the same name (like a variable called `old_name` in another function)
+/// are left alone, and so is everything but the names, like comments and whitespace (see
+/// [Cst]).
+///
+/// If more than one thing is called `old_name`, `line` picks the one that's declared or used on
+/// that line (starting at 1).
+///
+/// Returns an error if `source_code` has errors (since what its names refer to isn't known), if
+/// there's nothing to rename, or if the renamed program would mean something else: if
+/// `new_name` is already declared where the thing is, or if a name would now refer to something
+/// else (like a use of `new_name` that the renamed variable would shadow).
+///
+/// ```
+/// use flick::rename::rename;
+///
+/// let source_code = "fn f(u8 x) u8 {\n    ret x  // x\n}\n\nfn g(u8 x) u8 {\n    ret x\n}\n";
+/// let renamed = rename(source_code, "x", "n", Some(2)).unwrap();
+/// assert_eq!(renamed, "fn f(u8 n) u8 {\n    ret n  // x\n}\n\nfn g(u8 x) u8 {\n    ret x\n}\n");
+/// ```
+pub fn rename(source_code: &str, old_name: &str, new_name: &str, line: Option<usize>) -> Result<String, RenameError> {
+    let (mut cst, tokens, spans) = lex(source_code)?;
+    let arena = Arena::new();
+    let program = Parser::parse_program_with_spans(&arena, &tokens, &spans).map_err(RenameError::Errors)?;
+    Resolver::resolve_program(&program).map_err(RenameError::Errors)?;
+    let old_name = identifier(old_name).ok_or_else(|| RenameError::InvalidName { name: old_name.to_string() })?;
+    if identifier(new_name).is_none() {
+        return Err(RenameError::InvalidName { name: new_name.to_string() });
+    }
+
+    // What each name refers to, and the things called `old_name` that a line was given for
+    let names = collect_names(&program);
+    let symbols = Resolver::resolve_symbols(&program);
+    let line_of = |span: Span| span.start_position(source_code).line;
+    let mut candidates: Vec<&Symbol> = Vec::new();
+    for (span, id, name) in &names {
+        let Some(symbol) = symbols.get(id) else { continue };
+        if *name == old_name && line.is_none_or(|line| line == line_of(*span)) && !candidates.contains(&symbol) {
+            candidates.push(symbol);
+        }
+    }
+    let symbol = match candidates[..] {
+        [] => return Err(RenameError::NotFound { name: old_name, line }),
+        [symbol] => symbol,
+        _ => {
+            let lines = candidates.iter().map(|symbol| line_of(symbol.span)).collect();
+            return Err(RenameError::Ambiguous { name: old_name, lines });
+        }
+    };
+    let reason = match symbol.kind {
+        SymbolKind::Builtin => Some("it's a builtin"),
+        SymbolKind::Function if old_name == "main" => Some("it's where the program starts"),
+        SymbolKind::Function if is_extern(&program, symbol.span) => {
+            Some("it's an 'extern' function, whose name is the one it's linked with")
+        }
+        _ => None,
+    };
+    if let Some(reason) = reason {
+        return Err(RenameError::CantRename { name: old_name, reason });
+    }
+
+    // Each name's token is renamed, as written, so `r#` can be used for names that are keywords
+    for syntax_token in &mut cst.tokens {
+        let renamed = names
+            .iter()
+            .any(|(span, id, _)| *span == syntax_token.span && symbols.get(id) == Some(symbol));
+        if renamed {
+            syntax_token.text = new_name.to_string();
+        }
+    }
+    let renamed = cst.to_source();
+
+    // Renaming mustn't change what any name refers to, which is checked by resolving the names
+    // again (the names are in the same order, so they're compared by their positions)
+    // (`new_name` lexes as one identifier, so the renamed code parses just like the code did)
+    let invalid_name = || RenameError::InvalidName { name: new_name.to_string() };
+    let (_, tokens, spans) = lex(&renamed).map_err(|_| invalid_name())?;
+    let renamed_program = Parser::parse_program_with_spans(&arena, &tokens, &spans).map_err(|_| invalid_name())?;
+    let first_conflict = match Resolver::resolve_program(&renamed_program) {
+        Err(errors) => Some(errors[0].index),
+        Ok(_) => {
+            let renamed_names = collect_names(&renamed_program);
+            let before = bindings(&names, &symbols);
+            let after = bindings(&renamed_names, &Resolver::resolve_symbols(&renamed_program));
+            before.iter().zip(&after).position(|(before, after)| before != after).map(|i| renamed_names[i].0.start)
+        }
+    };
+    match first_conflict {
+        Some(index) => Err(RenameError::Conflict {
+            name: old_name,
+            new_name: new_name.to_string(),
+            line: Span { start: index, end: index }.start_position(&renamed).line,
+        }),
+        None => Ok(renamed),
+    }
+}
+
+/// Lexes `source_code`, returning its CST and the tokens (and their spans) that the parser needs.
+fn lex(source_code: &str) -> Result<(Cst, Vec<Token<'static>>, Vec<Span>), RenameError> {
+    let cst = Cst::new(source_code).map_err(|err| RenameError::Errors(vec![err]))?;
+    let (tokens, spans) = cst.tokens_with_spans();
+    Ok((cst, tokens, spans))
+}
+
+/// Returns the identifier that `name` is (without the `r#` of a raw identifier), or `None` if it
+/// isn't one.
+fn identifier(name: &str) -> Option<String> {
+    match Lexer::lex(name).ok()?.as_slice() {
+        [Token::Identifier(identifier)] => Some(identifier.to_string()),
+        _ => None,
+    }
+}
+
+/// Returns `true` if the function declared at `span` is an `extern` function.
+fn is_extern(program: &Program, span: Span) -> bool {
+    program.global_statements.iter().any(|global_statement| {
+        matches!(global_statement, GlobalStatement::Extern(proto) if proto.span == span)
+    })
+}
+
+/// Returns what each of `names` refers to, as the kind of symbol and the position in `names` of
+/// where it's declared (if it's declared in the program), so that two programs' names can be
+/// compared.
+fn bindings(names: &[(Span, NodeId, String)], symbols: &NodeMap<Symbol>) -> Vec<Option<(SymbolKind, Option<usize>)>> {
+    names
+        .iter()
+        .map(|(_, id, _)| {
+            let symbol = symbols.get(id)?;
+            Some((symbol.kind, names.iter().position(|(span, _, _)| *span == symbol.span)))
+        })
+        .collect()
+}
+
+/// Returns every name in `program` that's declared or used, in the order that they're written,
+/// with where each one is and the id of the node that it belongs to.
+pub(crate) fn collect_names(program: &Program) -> Vec<(Span, NodeId, String)> {
+    let mut name_collector = NameCollector(Vec::new());
+    name_collector.visit_program(program);
+    name_collector.0
+}
+
+struct NameCollector(Vec<(Span, NodeId, String)>);
+
+impl<'ast> Visitor<'ast> for NameCollector {
+    fn visit_func_proto(&mut self, func_proto: &'ast FuncProto) {
+        self.0.push((func_proto.span, func_proto.id, func_proto.name.to_string()));
+        for param in &func_proto.params {
+            self.0.push((param.span, param.id, param.param_name.to_string()));
+        }
+    }
+
+    fn visit_statement(&mut self, statement: &'ast Statement) {
+        match statement {
+            Statement::VarDeclaration(declaration) => {
+                self.0.push((declaration.span, declaration.id, declaration.var_name.to_string()));
+            }
+            Statement::Assignment(assignment) => {
+                self.0.push((assignment.span, assignment.id, assignment.name.to_string()));
+            }
+            _ => {}
+        }
+        visit::walk_statement(self, statement);
+    }
+
+    fn visit_expr(&mut self, expr: &'ast Expr) {
+        if let Expr::Identifier(identifier) = expr {
+            self.0.push((identifier.span, identifier.id, identifier.name.to_string()));
+        }
+        visit::walk_expr(self, expr);
+    }
+
+    fn visit_call(&mut self, call: &'ast Call) {
+        self.0.push((call.span, call.id, call.function_name.to_string()));
+        visit::walk_call(self, call);
+    }
+}
+
+/// An error saying why something couldn't be [renamed](rename).
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum RenameError {
+    /// The source code has errors
+    Errors(Vec<FlickError>),
+    /// `name` isn't an identifier
+    InvalidName { name: String },
+    /// Nothing is called `name` (on `line`, if one was given)
+    NotFound { name: String, line: Option<usize> },
+    /// More than one thing is called `name`; `lines` are where each one is declared
+    Ambiguous { name: String, lines: Vec<usize> },
+    CantRename { name: String, reason: &'static str },
+    /// Renaming `name` to `new_name` would change what the name on `line` refers to (or declare
+    /// `new_name` twice there)
+    Conflict { name: String, new_name: String, line: usize },
+}
+
+impl fmt::Display for RenameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Errors(errors) => match errors.len() {
+                1 => write!(f, "the code has an error, so it can't be renamed in"),
+                len => write!(f, "the code has {} errors, so it can't be renamed in", len),
+            },
+            Self::InvalidName { name } => write!(f, "'{}' isn't a valid name", name),
+            Self::NotFound { name, line: None } => write!(f, "nothing is called '{}'", name),
+            Self::NotFound { name, line: Some(line) } => write!(f, "nothing on line {} is called '{}'", line, name),
+            Self::Ambiguous { name, lines } => {
+                let lines: Vec<_> = lines.iter().map(usize::to_string).collect();
+                write!(
+                    f,
+                    "more than one thing is called '{}' (on lines {}), so give the line of the one to rename",
+                    name,
+                    lines.join(", ")
+                )
+            }
+            Self::CantRename { name, reason } => write!(f, "'{}' can't be renamed, since {}", name, reason),
+            Self::Conflict { name, new_name, line } => write!(
+                f,
+                "renaming '{}' to '{}' would change what the name on line {} refers to",
+                name, new_name, line
+            ),
+        }
+    }
+}
+
+impl Error for RenameError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rename(source_code: &str, old_name: &str, new_name: &str, line: Option<usize>) -> Result<String, RenameError> {
+        super::rename(source_code, old_name, new_name, line)
+    }
+
+    const SOURCE_CODE: &str = "\
+/// Counts down from `n`
+fn count(u64 n) u64 {
+    u64 total = 0
+    while n > 0 {
+        total += n
+        n -= 1
+    }
+    ret total
+}
+
+pub fn main() u8 {
+    u64 n = count(3)
+    ret 0
+}
+";
+
+    #[test]
+    fn renames_functions_and_variables() {
+        let renamed = rename(SOURCE_CODE, "count", "r#sum", None).unwrap();
+        assert!(renamed.contains("fn r#sum(u64 n) u64 {") && renamed.contains("u64 n = r#sum(3)"));
+        assert!(renamed.contains("from `n`"));
+
+        let renamed = rename(SOURCE_CODE, "n", "remaining", Some(6)).unwrap();
+        assert_eq!(renamed.matches("remaining").count(), 4);
+        assert!(renamed.contains("u64 n = count(3)"));
+    }
+
+    #[test]
+    fn refuses_what_would_change_the_program() {
+        let ambiguous = RenameError::Ambiguous { name: "n".to_string(), lines: vec![2, 12] };
+        assert_eq!(rename(SOURCE_CODE, "n", "m", None), Err(ambiguous));
+        assert_eq!(rename(SOURCE_CODE, "n", "m", Some(7)), Err(RenameError::NotFound { name: "n".to_string(), line: Some(7) }));
+        assert!(matches!(rename(SOURCE_CODE, "main", "start", None), Err(RenameError::CantRename { .. })));
+        assert!(matches!(rename(SOURCE_CODE, "count", "1", None), Err(RenameError::InvalidName { .. })));
+
+        // The `total` declared in the function would shadow the parameter
+        let conflict = Err(RenameError::Conflict { name: "n".to_string(), new_name: "total".to_string(), line: 4 });
+        assert_eq!(rename(SOURCE_CODE, "n", "total", Some(2)), conflict);
+        let source_code = "fn f(u8 x, u8 y) {\n}\n";
+        let conflict = Err(RenameError::Conflict { name: "x".to_string(), new_name: "y".to_string(), line: 1 });
+        assert_eq!(rename(source_code, "x", "y", None), conflict);
+        let source_code = "fn f(u8 x) u8 {\n    if x > 0 {\n        u8 y = 1\n        ret x + y\n    }\n    ret 0\n}\n";
+        let conflict = Err(RenameError::Conflict { name: "x".to_string(), new_name: "y".to_string(), line: 4 });
+        assert_eq!(rename(source_code, "x", "y", None), conflict);
+    }
+}
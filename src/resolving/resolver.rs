@@ -0,0 +1,609 @@
+use crate::ast::{Expr, FuncDef, FuncProto, GlobalStatement, NodeId, NodeMap, Program, Statement};
+use crate::builtins::Builtin;
+use crate::diagnostics::suggestions::closest_name;
+use crate::error::{ErrorKind, FlickError};
+use crate::lexing::token::Span;
+use crate::scope_manager::{ScopeManager, Symbol, SymbolKind};
+use crate::Lexer;
+
+use super::error::ResolvingError;
+
+/// A struct that checks that every name in a program refers to a function, parameter, or local
+/// variable that's in scope, before the program is [typed](crate::Typer) or compiled.
+///
+/// To use the resolver, see [Resolver::resolve_program].
+///
+/// # Examples
+/// ```
+/// use flick::ast::Arena;
+/// use flick::{Lexer, Parser, Resolver};
+/// let source_code = "pub fn main() u8 {\n    ret x\n}";
+/// let (tokens, spans) = Lexer::lex_with_spans(source_code).unwrap();
+/// let arena = Arena::new();
+/// let program = Parser::parse_program_with_spans(&arena, &tokens, &spans).unwrap();
+/// let errors = Resolver::resolve_program(&program).unwrap_err();
+/// assert_eq!(errors[0].index, 27); // the index of `x`
+/// ```
+///
+/// # Shadowing
+///
+/// A variable can be declared with the same name as a variable (or parameter) from an outer
+/// scope, which it then shadows until the end of its own scope. This is allowed, but it's
+/// reported as a warning, since it's easy to mistake one variable for the other. Declaring two
+/// variables with the same name in the *same* scope is an error.
+pub struct Resolver {
+    /// The symbol table for each scope that's currently open
+    scope_manager: ScopeManager<Symbol>,
+    /// The errors found so far
+    errors: Vec<FlickError>,
+    /// The warnings found so far
+    warnings: Vec<FlickError>,
+    /// What each name resolved so far refers to (see [Resolver::resolve_symbols])
+    symbols: NodeMap<Symbol>,
+}
+
+impl Resolver {
+    /// Resolves every name in `program`, returning any warnings (like variables that shadow
+    /// other variables).
+    ///
+    /// Returns an `Err()` with every function that's defined more than once, and with the first
+    /// name in each function body that doesn't refer to anything (or that is called, but refers
+    /// to a variable); an error in one function doesn't stop the others from being resolved. For
+    /// the errors to point at the right place, `program` should come from
+    /// [Parser::parse_program_with_spans][a].
+    ///
+    /// [a]: crate::Parser::parse_program_with_spans
+    pub fn resolve_program(program: &Program) -> Result<Vec<FlickError>, Vec<FlickError>> {
+        let resolver = Self::resolve(program);
+        match resolver.errors.is_empty() {
+            true => Ok(resolver.warnings),
+            false => Err(resolver.errors),
+        }
+    }
+
+    /// Returns the [Symbol] that each name in `program` refers to, keyed by the [NodeId] of the
+    /// [Identifier](crate::ast::Identifier), [Call](crate::ast::Call), or
+    /// [Assignment](crate::ast::Assignment) that uses it. Each declaration (of a function,
+    /// parameter, or local variable) refers to its own symbol.
+    ///
+    /// This works even if `program` has errors, but the names in a function after its first
+    /// error aren't resolved (see [Resolver::resolve_program]).
+    ///
+    /// ```
+    /// use flick::{Lexer, Parser, Resolver, SymbolKind};
+    /// use flick::ast::{Arena, GlobalStatement, Statement};
+    /// let source_code = "pub fn main() u8 {\n    u8 x = 1\n    ret x\n}";
+    /// let (tokens, spans) = Lexer::lex_with_spans(source_code).unwrap();
+    /// let arena = Arena::new();
+    /// let program = Parser::parse_program_with_spans(&arena, &tokens, &spans).unwrap();
+    /// let GlobalStatement::FuncDef(main) = &program.global_statements[0] else { unreachable!() };
+    /// let Statement::Return(Some(x), ..) = &main.body[1] else { unreachable!() };
+    ///
+    /// let symbol = &Resolver::resolve_symbols(&program)[&x.id()];
+    /// assert_eq!(symbol.kind, SymbolKind::Local);
+    /// assert_eq!(symbol.span.start, 26); // where `x` is declared
+    /// ```
+    pub fn resolve_symbols(program: &Program) -> NodeMap<Symbol> {
+        Self::resolve(program).symbols
+    }
+
+    /// Like [Resolver::resolve_program], but once every function has been declared, the
+    /// functions are resolved on all of the machine's cores, each of which has its own copy of
+    /// the global scope. The errors and warnings are the same, and in the same order.
+    #[cfg(feature = "parallel")]
+    pub fn resolve_program_in_parallel(program: &Program) -> Result<Vec<FlickError>, Vec<FlickError>> {
+        use rayon::prelude::*;
+
+        let mut resolver = Self::declare_globals(program);
+        let chunk_size = program.global_statements.len().div_ceil(rayon::current_num_threads()).max(1);
+        let chunks: Vec<_> = program
+            .global_statements
+            .par_chunks(chunk_size)
+            .map(|chunk| {
+                let mut chunk_resolver = Self {
+                    scope_manager: resolver.scope_manager.clone(),
+                    errors: Vec::new(),
+                    warnings: Vec::new(),
+                    symbols: NodeMap::new(),
+                };
+                chunk_resolver.resolve_func_defs(chunk);
+                chunk_resolver
+            })
+            .collect();
+        for chunk_resolver in chunks {
+            resolver.errors.extend(chunk_resolver.errors);
+            resolver.warnings.extend(chunk_resolver.warnings);
+        }
+        match resolver.errors.is_empty() {
+            true => Ok(resolver.warnings),
+            false => Err(resolver.errors),
+        }
+    }
+
+    fn resolve(program: &Program) -> Self {
+        let mut resolver = Self::declare_globals(program);
+        resolver.resolve_func_defs(&program.global_statements);
+        resolver
+    }
+
+    /// Returns a resolver whose global scope has the builtins, and every function in `program`
+    /// (since functions can be called before they're defined).
+    fn declare_globals(program: &Program) -> Self {
+        let mut resolver = Self {
+            scope_manager: ScopeManager::new(),
+            errors: Vec::new(),
+            warnings: Vec::new(),
+            symbols: NodeMap::new(),
+        };
+        for builtin in Builtin::ALL {
+            resolver.scope_manager.set(builtin.name(), Symbol::builtin(builtin));
+        }
+        for global_statement in program.global_statements.iter() {
+            match global_statement {
+                GlobalStatement::Extern(proto) => resolver.declare_func(proto),
+                GlobalStatement::FuncDef(f) => resolver.declare_func(&f.proto),
+            }
+        }
+        resolver
+    }
+
+    /// Resolves the names in the bodies of the functions in `global_statements`.
+    fn resolve_func_defs(&mut self, global_statements: &[GlobalStatement]) {
+        for global_statement in global_statements {
+            if let GlobalStatement::FuncDef(f) = global_statement {
+                tracing::debug!(function = %f.proto.name, "resolving names");
+                if let Err(err) = self.resolve_func_def(f) {
+                    self.errors.push(err);
+                }
+            }
+        }
+    }
+
+    /// Adds the function declared by `func_proto` to the global scope, or records an error if
+    /// there's already a function with the same name.
+    fn declare_func(&mut self, func_proto: &FuncProto) {
+        self.symbols.insert(func_proto.id, Symbol::function(func_proto));
+        match self.scope_manager.get(func_proto.name).map(|symbol| (symbol.kind, symbol.span)) {
+            // Redefining a builtin is reported by the Typer, which knows what the builtins are
+            Some((SymbolKind::Builtin, _)) => {}
+            Some((SymbolKind::Function, first_definition)) => {
+                let kind = ResolvingError::FunctionRedefinition {
+                    name: func_proto.name.to_string(),
+                    first_definition,
+                };
+                self.errors.push(Self::err(func_proto.span, kind));
+            }
+            _ => self.scope_manager.set(func_proto.name, Symbol::function(func_proto)),
+        }
+    }
+
+    fn resolve_func_def(&mut self, func_def: &FuncDef) -> crate::Result<()> {
+        self.scope_manager.enter_scope();
+        let result = func_def
+            .proto
+            .params
+            .iter()
+            .try_for_each(|param| self.declare(&param.param_name, param.id, Symbol::param(param)))
+            .and_then(|_| self.resolve_body(&func_def.body));
+        self.scope_manager.exit_scope();
+        result
+    }
+
+    /// Adds a parameter or local variable to the current scope, unless another one with the
+    /// same name has already been declared in it (see [Shadowing](Resolver#shadowing)).
+    fn declare(&mut self, name: &str, id: NodeId, symbol: Symbol) -> crate::Result<()> {
+        assert!(!symbol.is_function(), "functions are declared by declare_func");
+        let span = symbol.span;
+
+        if let Some(first) = self.scope_manager.get_in_current_scope(name).filter(|s| !s.is_function()) {
+            let name = name.to_string();
+            let first_definition = first.span;
+            let kind = match symbol.kind {
+                SymbolKind::Param => ResolvingError::ParamRedefinition { name, first_definition },
+                _ => ResolvingError::VariableRedeclaration { name, first_definition },
+            };
+            return Err(Self::err(span, kind));
+        }
+
+        if let Some(shadowed) = self.scope_manager.get(name).filter(|s| !s.is_function()) {
+            let kind = ResolvingError::ShadowedVariable { name: name.to_string(), shadowed: shadowed.span };
+            self.warnings.push(Self::err(span, kind));
+        }
+
+        self.symbols.insert(id, symbol.clone());
+        self.scope_manager.set(name, symbol);
+        Ok(())
+    }
+
+    /// Resolves each statement in `body`, in a new scope (so that variables declared in the
+    /// body can't be used after it).
+    fn resolve_body(&mut self, body: &[Statement]) -> crate::Result<()> {
+        self.scope_manager.enter_scope();
+        let result = body.iter().try_for_each(|s| self.resolve_statement(s));
+        self.scope_manager.exit_scope();
+        result
+    }
+
+    fn resolve_statement(&mut self, statement: &Statement) -> crate::Result<()> {
+        match statement {
+            Statement::VarDeclaration(v) => {
+                // The value is resolved first, since `i64 x = x` can't refer to the new `x`
+                self.resolve_expr(&v.var_value)?;
+                self.declare(v.var_name, v.id, Symbol::local(v))?;
+            }
+            Statement::WhileLoop(w) => {
+                self.resolve_expr(&w.condition)?;
+                self.resolve_body(&w.body)?;
+            }
+            Statement::Assignment(a) => {
+                self.resolve_variable(a.name, a.span, a.id)?;
+                self.resolve_expr(a.value)?;
+            }
+            Statement::Return(r, ..) => {
+                if let Some(value) = r {
+                    self.resolve_expr(value)?;
+                }
+            }
+            Statement::Call(c) => self.resolve_call(c.function_name, &c.args, c.span, c.id)?,
+            Statement::If(i) => {
+                self.resolve_expr(&i.condition)?;
+                self.resolve_body(&i.then_body)?;
+                if let Some(else_body) = &i.else_body {
+                    self.resolve_body(else_body)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn resolve_expr(&mut self, expr: &Expr) -> crate::Result<()> {
+        match expr {
+            Expr::Identifier(id) => self.resolve_variable(id.name, id.span, id.id),
+            Expr::IntLiteral(..) | Expr::BoolLiteral(..) | Expr::StrLiteral(..) => Ok(()),
+            Expr::Binary(b) => {
+                self.resolve_expr(b.left)?;
+                self.resolve_expr(b.right)
+            }
+            Expr::Comparison(c) => {
+                self.resolve_expr(c.left)?;
+                self.resolve_expr(c.right)
+            }
+            Expr::Call(c) => self.resolve_call(c.function_name, &c.args, c.span, c.id),
+            Expr::Unary(u) => self.resolve_expr(u.operand),
+        }
+    }
+
+    /// Checks that `name` (used by the node `id`) is in scope, whether it's a variable or a
+    /// function; the [Typer](crate::Typer) checks that it's used as one.
+    fn resolve_variable(&mut self, name: &str, span: Span, id: NodeId) -> crate::Result<()> {
+        match self.scope_manager.get(name) {
+            Some(symbol) => {
+                self.symbols.insert(id, symbol.clone());
+                Ok(())
+            }
+            None => {
+                let names = self.scope_manager.iter().map(|(name, _)| name);
+                let suggestion = closest_name(name, names.chain(Lexer::KEYWORDS.iter().copied())).map(str::to_string);
+                Err(Self::err(span, ResolvingError::UndefinedVariable { name: name.to_string(), suggestion }))
+            }
+        }
+    }
+
+    /// Checks that `function_name` (called by the node `id`) refers to a function, then resolves
+    /// each argument.
+    fn resolve_call(&mut self, function_name: &str, args: &[Expr], span: Span, id: NodeId) -> crate::Result<()> {
+        match self.scope_manager.get(function_name) {
+            Some(symbol) if symbol.is_function() => {
+                self.symbols.insert(id, symbol.clone());
+            }
+            Some(_) => {
+                return Err(Self::err(span, ResolvingError::NotAFunction(function_name.to_string())))
+            }
+            None => {
+                let functions = self.scope_manager.iter().filter(|(_, symbol)| symbol.is_function());
+                let names = functions.map(|(name, _)| name).chain(Lexer::KEYWORDS.iter().copied());
+                let suggestion = closest_name(function_name, names).map(str::to_string);
+                let kind = ResolvingError::UndefinedFunction { name: function_name.to_string(), suggestion };
+                return Err(Self::err(span, kind));
+            }
+        }
+        args.iter().try_for_each(|arg| self.resolve_expr(arg))
+    }
+
+    fn err(span: Span, kind: ResolvingError) -> FlickError {
+        FlickError {
+            index: span.start,
+            kind: ErrorKind::ResolvingError(kind),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Lexer, Parser};
+    use crate::ast::Arena;
+
+    fn resolve(source_code: &str) -> Result<Vec<FlickError>, Vec<FlickError>> {
+        let (tokens, spans) = Lexer::lex_with_spans(source_code).unwrap();
+        let arena = Arena::new();
+        let program = Parser::parse_program_with_spans(&arena, &tokens, &spans).unwrap();
+        Resolver::resolve_program(&program)
+    }
+
+    #[test]
+    fn resolved_names() {
+        let source_code = "\
+extern fn print(i64 x) void
+pub fn main() u8 {
+    u8 n = later(3)
+    if n > 0 {
+        u8 m = n
+        n = m
+    }
+    print(0)
+    breakpoint()
+    ret n
+}
+fn later(u8 a) u8 {
+    ret a
+}
+";
+
+        assert_eq!(resolve(source_code), Ok(vec![]));
+    }
+
+    #[test]
+    fn undefined_variable() {
+        let source_code = "pub fn main() u8 {\n    u8 a = 1\n    ret a + b\n}\n";
+        let expected_error = FlickError {
+            index: 44,
+            kind: ErrorKind::ResolvingError(ResolvingError::UndefinedVariable {
+                name: "b".to_string(),
+                suggestion: Some("a".to_string()),
+            }),
+        };
+
+        assert_eq!(resolve(source_code), Err(vec![expected_error]));
+    }
+
+    #[test]
+    fn variable_out_of_scope() {
+        let source_code = "pub fn main() u8 {\n    if true {\n        u8 a = 1\n    }\n    ret a\n}\n";
+        let expected_error = FlickError {
+            index: 64,
+            kind: ErrorKind::ResolvingError(ResolvingError::UndefinedVariable { name: "a".to_string(), suggestion: None }),
+        };
+
+        assert_eq!(resolve(source_code), Err(vec![expected_error]));
+    }
+
+    #[test]
+    fn variable_out_of_scope_after_while_body() {
+        let source_code = "pub fn main() u8 {\n    while false {\n        u8 a = 1\n    }\n    a = 2\n    ret 0\n}\n";
+        let expected_error = FlickError {
+            index: 64,
+            kind: ErrorKind::ResolvingError(ResolvingError::UndefinedVariable { name: "a".to_string(), suggestion: None }),
+        };
+
+        assert_eq!(resolve(source_code), Err(vec![expected_error]));
+    }
+
+    #[test]
+    fn variable_out_of_scope_in_else_body() {
+        let source_code = "pub fn main() u8 {\n    if true {\n        u8 a = 1\n    } else {\n        ret a\n    }\n    ret 0\n}\n";
+        let expected_error = FlickError {
+            index: 75,
+            kind: ErrorKind::ResolvingError(ResolvingError::UndefinedVariable { name: "a".to_string(), suggestion: None }),
+        };
+
+        assert_eq!(resolve(source_code), Err(vec![expected_error]));
+    }
+
+    #[test]
+    fn assignment_to_undefined_variable() {
+        let source_code = "pub fn main() u8 {\n    a += 1\n    ret 0\n}\n";
+        let expected_error = FlickError {
+            index: 23,
+            kind: ErrorKind::ResolvingError(ResolvingError::UndefinedVariable { name: "a".to_string(), suggestion: None }),
+        };
+
+        assert_eq!(resolve(source_code), Err(vec![expected_error]));
+    }
+
+    #[test]
+    fn undefined_function() {
+        let source_code = "pub fn main() u8 {\n    ret foo(1)\n}\n";
+        let expected_error = FlickError {
+            index: 27,
+            kind: ErrorKind::ResolvingError(ResolvingError::UndefinedFunction { name: "foo".to_string(), suggestion: None }),
+        };
+
+        assert_eq!(resolve(source_code), Err(vec![expected_error]));
+    }
+
+    #[test]
+    fn misspelled_function() {
+        let source_code = "fn count(u8 n) u8 {\n    ret n\n}\n\npub fn main() u8 {\n    ret cuont(1)\n}\n";
+        let expected_error = FlickError {
+            index: 60,
+            kind: ErrorKind::ResolvingError(ResolvingError::UndefinedFunction {
+                name: "cuont".to_string(),
+                suggestion: Some("count".to_string()),
+            }),
+        };
+
+        assert_eq!(resolve(source_code), Err(vec![expected_error]));
+    }
+
+    #[test]
+    fn call_to_param() {
+        let source_code = "fn foo(u8 f) u8 {\n    ret f()\n}\npub fn main() u8 {\n    ret foo(1)\n}\n";
+        let expected_error = FlickError {
+            index: 26,
+            kind: ErrorKind::ResolvingError(ResolvingError::NotAFunction("f".to_string())),
+        };
+
+        assert_eq!(resolve(source_code), Err(vec![expected_error]));
+    }
+
+    #[test]
+    fn function_redefinition() {
+        let source_code = "fn foo() {\n}\nextern fn foo()\npub fn main() u8 {\n    ret bar\n}\n";
+        let expected_errors = vec![
+            FlickError {
+                index: 23,
+                kind: ErrorKind::ResolvingError(ResolvingError::FunctionRedefinition {
+                    name: "foo".to_string(),
+                    first_definition: Span { start: 3, end: 6 },
+                }),
+            },
+            // The other functions are still resolved
+            FlickError {
+                index: 56,
+                kind: ErrorKind::ResolvingError(ResolvingError::UndefinedVariable { name: "bar".to_string(), suggestion: None }),
+            },
+        ];
+
+        assert_eq!(resolve(source_code), Err(expected_errors));
+    }
+
+    #[test]
+    fn param_redefinition() {
+        let source_code = "fn foo(i64 a, i64 a) {\n}\npub fn main() u8 {\n    ret 0\n}\n";
+        let expected_error = FlickError {
+            index: 18,
+            kind: ErrorKind::ResolvingError(ResolvingError::ParamRedefinition {
+                name: "a".to_string(),
+                first_definition: Span { start: 11, end: 12 },
+            }),
+        };
+
+        assert_eq!(resolve(source_code), Err(vec![expected_error]));
+    }
+
+    #[test]
+    fn variable_redeclaration() {
+        let source_code = "pub fn main() u8 {\n    u8 a = 1\n    u8 a = a\n    ret a\n}\n";
+        let expected_error = FlickError {
+            index: 39,
+            kind: ErrorKind::ResolvingError(ResolvingError::VariableRedeclaration {
+                name: "a".to_string(),
+                first_definition: Span { start: 26, end: 27 },
+            }),
+        };
+
+        assert_eq!(resolve(source_code), Err(vec![expected_error]));
+    }
+
+    #[test]
+    fn shadowing_in_while_body() {
+        let source_code = "pub fn main() u8 {\n    u8 x = 1\n    while x < 3 {\n        u8 x = 5\n    }\n    ret x\n}\n";
+        let expected_warning = FlickError {
+            index: 61,
+            kind: ErrorKind::ResolvingError(ResolvingError::ShadowedVariable {
+                name: "x".to_string(),
+                shadowed: Span { start: 26, end: 27 },
+            }),
+        };
+
+        assert_eq!(resolve(source_code), Ok(vec![expected_warning]));
+    }
+
+    #[test]
+    fn resolved_symbols() {
+        let source_code = "fn id(u8 x) u8 {\n    ret x\n}\npub fn main() u8 {\n    u8 x = id(1)\n    x += 1\n    ret y\n}\n";
+        let (tokens, spans) = Lexer::lex_with_spans(source_code).unwrap();
+        let arena = Arena::new();
+        let program = Parser::parse_program_with_spans(&arena, &tokens, &spans).unwrap();
+        let symbols = Resolver::resolve_symbols(&program);
+
+        // Each name that's used (and each declaration) maps to where it's declared, up to the
+        // first error in each function (`y`)
+        let mut uses: Vec<_> = symbols
+            .values()
+            .map(|symbol| (symbol.kind, &source_code[symbol.span.start..symbol.span.end], symbol.span.start))
+            .collect();
+        uses.sort_by_key(|&(_, _, start)| start);
+        let expected = [
+            (SymbolKind::Function, "id", 3), // the declaration
+            (SymbolKind::Function, "id", 3), // the call
+            (SymbolKind::Param, "x", 9),
+            (SymbolKind::Param, "x", 9),
+            (SymbolKind::Function, "main", 36),
+            (SymbolKind::Local, "x", 55), // the declaration
+            (SymbolKind::Local, "x", 55), // the assignment
+            (SymbolKind::Local, "x", 55), // the `x` in `x + 1`
+        ];
+        assert_eq!(uses, expected);
+    }
+
+    #[test]
+    fn shadowing_in_nested_blocks() {
+        let source_code = "\
+fn pick(u8 x) u8 {
+    if x > 0 {
+        u8 x = 20
+        if x > 10 {
+            u8 x = 30
+        }
+    }
+    if x > 1 {
+        u8 y = 1
+    } else {
+        u8 y = 2
+    }
+    ret x
+}
+pub fn main() u8 {
+    ret pick(1)
+}
+";
+        // Each `x` shadows the one before it, but the two `y`s are in separate scopes
+        let expected_warnings = vec![
+            FlickError {
+                index: 45,
+                kind: ErrorKind::ResolvingError(ResolvingError::ShadowedVariable {
+                    name: "x".to_string(),
+                    shadowed: Span { start: 11, end: 12 },
+                }),
+            },
+            FlickError {
+                index: 87,
+                kind: ErrorKind::ResolvingError(ResolvingError::ShadowedVariable {
+                    name: "x".to_string(),
+                    shadowed: Span { start: 45, end: 46 },
+                }),
+            },
+        ];
+
+        assert_eq!(resolve(source_code), Ok(expected_warnings));
+    }
+
+    #[test]
+    #[cfg(feature = "parallel")]
+    fn resolving_in_parallel_is_like_resolving_in_order() {
+        let mut source_code = String::from("pub fn main() u8 {\n    ret 0\n}\n");
+        for i in 0..50 {
+            // Every tenth function has an error, and every seventh a warning
+            let value = if i % 10 == 3 { "y" } else { "x" };
+            let shadow = if i % 7 == 0 { "    if x > 0 {\n        u8 x = 1\n    }\n" } else { "" };
+            source_code += &format!("fn f{}(u8 x) u8 {{\n{}    ret {}\n}}\n", i, shadow, value);
+        }
+        source_code += "fn f7() {\n}\n";
+        let (tokens, spans) = Lexer::lex_with_spans(&source_code).unwrap();
+        let arena = Arena::new();
+        let program = Parser::parse_program_with_spans(&arena, &tokens, &spans).unwrap();
+        let errors = Resolver::resolve_program(&program).unwrap_err();
+        assert_eq!(errors.len(), 6);
+        assert_eq!(Resolver::resolve_program_in_parallel(&program), Err(errors));
+
+        let fixed = source_code.replace("ret y", "ret x").replace("fn f7() {", "fn f51() {");
+        let (tokens, spans) = Lexer::lex_with_spans(&fixed).unwrap();
+        let arena = Arena::new();
+        let program = Parser::parse_program_with_spans(&arena, &tokens, &spans).unwrap();
+        let warnings = Resolver::resolve_program(&program).unwrap();
+        assert_eq!(warnings.len(), 8);
+        assert_eq!(Resolver::resolve_program_in_parallel(&program), Ok(warnings));
+    }
+}
@@ -1,4 +1,9 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
+
+use crate::ast::{FuncParam, FuncProto, VarDeclaration};
+use crate::builtins::Builtin;
+use crate::lexing::token::Span;
+use crate::types::Type;
 
 // TODO: Design: what kind of namespaces do we want? Should i64 foo be allowed inside fn foo() { ... }?
 //  What about inside fn bar () { ... }?
@@ -15,6 +20,11 @@ use std::collections::HashMap;
 /// print(a)       // outer scope
 /// ```
 ///
+/// Every pass that walks a program (the [Resolver](crate::Resolver), the [Typer](crate::Typer),
+/// and both backends) enters a scope for each function, holding its parameters, and another one
+/// for each `{}` body inside it. So a variable declared in a `while` body isn't visible after the
+/// loop, and one declared in the `then` body of an `if` isn't visible in its `else` body.
+///
 /// Also, giving a variable the same name as a function is silly but should still work:
 ///
 /// ```text
@@ -32,13 +42,11 @@ use std::collections::HashMap;
 /// handle it:
 ///
 /// ```
-/// use llvm_sys::core::{LLVMConstInt, LLVMIntType};
-/// use llvm_sys::prelude::*;
 /// use flick::ScopeManager;
-/// use flick::types::Type;
 ///
-/// let outer_val: LLVMValueRef = unsafe { LLVMConstInt(LLVMIntType(64), 20345, 1) };
-/// let inner_val: LLVMValueRef = unsafe { LLVMConstInt(LLVMIntType(64), 1999, 1) };
+/// // The compiler stores LLVM values, but any type works
+/// let outer_val: i64 = 20345;
+/// let inner_val: i64 = 1999;
 ///
 /// let mut scope_manager = ScopeManager::new();
 ///
@@ -63,21 +71,25 @@ use std::collections::HashMap;
 ///
 /// assert_eq!(scope_manager.get("x"), Some(&outer_val));
 /// ```
+///
+/// Each scope is a [BTreeMap] rather than a `HashMap`, so that anything iterating over a
+/// scope sees the same order every time (which keeps the compiler's output reproducible).
+#[derive(Clone)]
 pub struct ScopeManager<T> {
-    values: Vec<HashMap<String, T>>,
+    values: Vec<BTreeMap<String, T>>,
 }
 
 impl<T> ScopeManager<T> {
     pub fn new() -> Self {
         Self {
-            values: vec![HashMap::new()],
+            values: vec![BTreeMap::new()],
         }
     }
 
     /// Pushes a new scope; old objects can be overwritten but will regain their
     /// value after [exit_scope()](ScopeManager::exit_scope()).
     pub fn enter_scope(&mut self) {
-        self.values.push(HashMap::new());
+        self.values.push(BTreeMap::new());
     }
 
     /// Pops the current scope; re-enters the next-innermost scope.
@@ -97,6 +109,18 @@ impl<T> ScopeManager<T> {
         self.values.iter().rev().find_map(|s| s.get(name.as_ref()))
     }
 
+    /// Like [get()](ScopeManager::get()), but only searches the innermost scope (e.g. to check
+    /// whether `name` is being declared twice in the same scope).
+    pub fn get_in_current_scope(&self, name: impl AsRef<str>) -> Option<&T> {
+        self.values.last().unwrap().get(name.as_ref())
+    }
+
+    /// Returns every name in scope (with its value), starting with the innermost scope; a name
+    /// that's shadowed comes up once per scope that it's in.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &T)> {
+        self.values.iter().rev().flat_map(|scope| scope.iter().map(|(name, value)| (name.as_str(), value)))
+    }
+
     /// Sets a value named `name` in the current scope.
     pub fn set(&mut self, name: impl AsRef<str>, value: T) {
         let cur_scope = self.values.last_mut().unwrap();
@@ -105,6 +129,92 @@ impl<T> ScopeManager<T> {
     }
 }
 
+/// What a name in scope refers to, for the passes that check a program (the
+/// [Resolver](crate::Resolver) and the [Typer](crate::Typer)), which store a [Symbol] per name in
+/// their [ScopeManager].
+///
+/// ```
+/// use flick::{Parser, Lexer, Symbol, SymbolKind};
+/// use flick::ast::{Arena, GlobalStatement};
+///
+/// let source_code = "fn square(u8 x) u8 {\n    ret x * x\n}";
+/// let (tokens, spans) = Lexer::lex_with_spans(source_code).unwrap();
+/// let arena = Arena::new();
+/// let program = Parser::parse_program_with_spans(&arena, &tokens, &spans).unwrap();
+/// let GlobalStatement::FuncDef(square) = &program.global_statements[0] else { unreachable!() };
+///
+/// let symbol = Symbol::param(&square.proto.params[0]);
+/// assert_eq!(symbol.kind, SymbolKind::Param);
+/// assert_eq!(symbol.span.start, 13); // the index of `x`
+/// assert!(symbol.is_mutable());
+/// assert!(!Symbol::function(&square.proto).is_mutable());
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Symbol {
+    pub kind: SymbolKind,
+    /// The symbol's type; for a function, this is a [Type::Func] with its prototype
+    pub symbol_type: Type,
+    /// Where the symbol is declared (or [Span::default()] for a builtin)
+    pub span: Span,
+}
+
+/// Whether a [Symbol] is a function or a variable, and what kind.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SymbolKind {
+    /// A [builtin function](Builtin)
+    Builtin,
+    /// A function that's defined (or declared with `extern`) in the program
+    Function,
+    Param,
+    /// A local variable
+    Local,
+}
+
+impl Symbol {
+    pub fn builtin(builtin: Builtin) -> Self {
+        Self {
+            kind: SymbolKind::Builtin,
+            symbol_type: Type::Func(builtin.proto()),
+            span: Span::default(),
+        }
+    }
+
+    pub fn function(func_proto: &FuncProto) -> Self {
+        Self {
+            kind: SymbolKind::Function,
+            symbol_type: Type::Func(func_proto.clone()),
+            span: func_proto.span,
+        }
+    }
+
+    pub fn param(param: &FuncParam) -> Self {
+        Self {
+            kind: SymbolKind::Param,
+            symbol_type: param.param_type.clone(),
+            span: param.span,
+        }
+    }
+
+    pub fn local(var_declaration: &VarDeclaration) -> Self {
+        Self {
+            kind: SymbolKind::Local,
+            symbol_type: var_declaration.var_type.clone(),
+            span: var_declaration.span,
+        }
+    }
+
+    /// Returns true if the symbol is a function (including a builtin) rather than a variable.
+    pub fn is_function(&self) -> bool {
+        matches!(self.kind, SymbolKind::Builtin | SymbolKind::Function)
+    }
+
+    /// Returns true if the symbol can be assigned to. Every variable (and parameter) in Flick is
+    /// mutable, so this is true unless the symbol is a function.
+    pub fn is_mutable(&self) -> bool {
+        !self.is_function()
+    }
+}
+
 /// As suggested by Clippy's [new_without_default][a], since [ScopeManager::new()] doesn't
 /// take any arguments, ScopeManager should implement Default.
 ///
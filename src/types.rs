@@ -3,10 +3,13 @@ use crate::ast::FuncProto;
 
 /// An enum to store the built-in Flick types, like `void`
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Type {
     /// Variable-size int type, with `width` bits.
     Int(IntType),
     Bool,
+    /// A pointer to a null-terminated string of bytes (like a C `char *`).
+    Str,
     Void,
     Func(FuncProto),
 }
@@ -16,6 +19,7 @@ impl fmt::Display for Type {
         match self {
             Self::Int(int_type) => write!(f, "{}", int_type),
             Self::Bool => write!(f, "bool"),
+            Self::Str => write!(f, "str"),
             Self::Void => write!(f, "void"),
             Self::Func(func_type) => write!(f, "{}", func_type),
         }
@@ -24,11 +28,17 @@ impl fmt::Display for Type {
 
 /// An enum to store the built-in int type
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct IntType {
     pub signed: bool,
     pub width: u32,
 }
 
+impl IntType {
+    /// The widest int type that LLVM supports, in bits.
+    pub const MAX_WIDTH: u32 = (1 << 23) - 1;
+}
+
 impl fmt::Display for IntType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self.signed {
@@ -0,0 +1,75 @@
+use crate::ast::{FuncParam, FuncProto, FuncVisibility, NodeId};
+use crate::Name;
+use crate::token::Span;
+use crate::types::{IntType, Type};
+
+/// A function that's built into Flick, so that programs can call it without defining or
+/// declaring it (like `breakpoint()`).
+///
+/// Calls to builtins are typed like any other call (see [Builtin::proto]), but each backend
+/// generates special code for them, rather than calling a function of the same name.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Builtin {
+    /// `breakpoint()` stops the program in a debugger (like `gdb` or `lldb`) that's attached to
+    /// it, right where it's called. It compiles to LLVM's `llvm.debugtrap` intrinsic.
+    Breakpoint,
+    /// `arg_count() u32` returns how many command-line arguments the program was given,
+    /// including its own name (like C's `argc`). WebAssembly programs aren't given any.
+    ArgCount,
+    /// `arg(u32 index) str` returns the command-line argument at `index`, where the program's
+    /// own name is at 0 (like C's `argv`), or `""` if there's no argument at `index`.
+    Arg,
+}
+
+impl Builtin {
+    /// Every builtin function.
+    pub const ALL: [Self; 3] = [Self::Breakpoint, Self::ArgCount, Self::Arg];
+
+    /// Returns the name that Flick programs call the builtin by.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Breakpoint => "breakpoint",
+            Self::ArgCount => "arg_count",
+            Self::Arg => "arg",
+        }
+    }
+
+    /// Returns `true` if the builtin reads the program's command-line arguments, which `main`
+    /// has to be given (and save) for it.
+    pub fn reads_args(&self) -> bool {
+        matches!(self, Self::ArgCount | Self::Arg)
+    }
+
+    /// Returns the builtin called `name`, if there is one.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|builtin| builtin.name() == name)
+    }
+
+    /// Returns the prototype that calls to the builtin are checked against.
+    pub fn proto(&self) -> FuncProto {
+        let u32_type = Type::Int(IntType { width: 32, signed: false });
+        let (params, return_type) = match self {
+            Self::Breakpoint => (vec![], Type::Void),
+            Self::ArgCount => (vec![], u32_type),
+            Self::Arg => (vec![("index", u32_type)], Type::Str),
+        };
+        FuncProto {
+            func_visibility: FuncVisibility::Extern,
+            name: Name::intern(self.name()),
+            params: params
+                .into_iter()
+                .map(|(param_name, param_type)| FuncParam {
+                    param_type,
+                    param_name: Name::intern(param_name),
+                    span: Span::default(),
+                    id: NodeId::default(),
+                })
+                .collect(),
+            return_type: Box::new(return_type),
+            // Builtins aren't declared anywhere in the source code (or in the tree)
+            span: Span::default(),
+            id: NodeId::default(),
+            library: None,
+        }
+    }
+}
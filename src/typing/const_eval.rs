@@ -0,0 +1,166 @@
+use crate::ast::{BinaryOperator, UnaryOperator};
+use crate::typed_ast::{TypedBinary, TypedExpr, TypedUnary};
+use crate::types::{IntType, Type};
+
+/// Returns the value of `expr` if it's a constant integer expression (made up of integer
+/// literals, arithmetic, negations, and casts), like `255 + 1` or `-(i8) 5`.
+///
+/// The value is computed exactly, without wrapping around, so that the caller can tell whether
+/// it fits in the expression's type (see [fits]). Casts are the exception, since they wrap (or
+/// truncate) on purpose.
+///
+/// Returns `None` if `expr` isn't constant, or if it can't be evaluated at compile time: for
+/// example, if it divides by zero or uses an integer type that's wider than 64 bits.
+pub fn eval(expr: &TypedExpr) -> Option<i128> {
+    match expr {
+        TypedExpr::IntLiteral(int) => {
+            if int.int_type.width > 64 {
+                return None;
+            }
+            int.int_value.parse().ok()
+        }
+        TypedExpr::Binary(binary) => eval_binary(binary),
+        TypedExpr::Unary(unary) => eval_unary(unary),
+        _ => None,
+    }
+}
+
+/// Like [eval], but for a binary expression.
+pub fn eval_binary(binary: &TypedBinary) -> Option<i128> {
+    let Type::Int(int_type) = binary.result_type else { return None };
+    if int_type.width > 64 {
+        return None;
+    }
+    let left = eval(&binary.left)?;
+    let right = eval(&binary.right)?;
+    // Since the operands are at most 64 bits wide, only multiplying two huge u64s can overflow
+    // an i128 (which is reported as not being constant)
+    match binary.operator {
+        BinaryOperator::Add => left.checked_add(right),
+        BinaryOperator::Subtract => left.checked_sub(right),
+        BinaryOperator::Multiply => left.checked_mul(right),
+        // Like LLVM's sdiv/srem, these round towards zero
+        BinaryOperator::Divide => left.checked_div(right),
+        BinaryOperator::Remainder => left.checked_rem(right),
+    }
+}
+
+/// Like [eval], but for a unary expression.
+pub fn eval_unary(unary: &TypedUnary) -> Option<i128> {
+    let operand = eval(&unary.operand)?;
+    match &unary.operator {
+        UnaryOperator::Negate => Some(-operand),
+        UnaryOperator::Cast(Type::Int(int_type)) => wrap(operand, *int_type),
+        UnaryOperator::Cast(_) => None,
+    }
+}
+
+/// Returns true if `value` is in the range of `int_type` (which must be at most 64 bits wide, like
+/// the type of any expression that [eval] can evaluate).
+pub fn fits(value: i128, int_type: IntType) -> bool {
+    let (min, max) = match int_type.signed {
+        true => (-(1 << (int_type.width - 1)), (1 << (int_type.width - 1)) - 1),
+        false => (0, (1 << int_type.width) - 1),
+    };
+    (min..=max).contains(&value)
+}
+
+/// Like [fits], but for an integer literal (a string of digits, which the
+/// [Lexer](crate::Lexer) only accepts if it fits in a `u128`) and an `int_type` of any width.
+pub fn literal_fits(literal: &str, int_type: IntType) -> bool {
+    let Ok(value) = literal.parse::<u128>() else { return false };
+    let magnitude_bits = int_type.width.saturating_sub(u32::from(int_type.signed));
+    magnitude_bits >= u128::BITS || value < 1 << magnitude_bits
+}
+
+/// Returns `value` converted to `int_type` the way a cast does it at runtime, by keeping just
+/// the lowest `int_type.width` bits (or `None` if `int_type` is wider than 64 bits).
+pub fn wrap(value: i128, int_type: IntType) -> Option<i128> {
+    if int_type.width > 64 {
+        return None;
+    }
+    let modulus = 1 << int_type.width;
+    let wrapped = value.rem_euclid(modulus);
+    match int_type.signed && wrapped >= modulus / 2 {
+        true => Some(wrapped - modulus),
+        false => Some(wrapped),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::typed_ast::TypedIntLiteral;
+
+    fn int_literal(value: &str, int_type: IntType) -> TypedExpr {
+        TypedExpr::IntLiteral(TypedIntLiteral { int_value: value.to_string(), int_type })
+    }
+
+    #[test]
+    fn arithmetic() {
+        let u8_type = IntType { signed: false, width: 8 };
+        // 200 + 100 * 2
+        let expr = TypedExpr::Binary(TypedBinary {
+            left: Box::new(int_literal("200", u8_type)),
+            operator: BinaryOperator::Add,
+            right: Box::new(TypedExpr::Binary(TypedBinary {
+                left: Box::new(int_literal("100", u8_type)),
+                operator: BinaryOperator::Multiply,
+                right: Box::new(int_literal("2", u8_type)),
+                result_type: Type::Int(u8_type),
+            })),
+            result_type: Type::Int(u8_type),
+        });
+
+        assert_eq!(eval(&expr), Some(400));
+        assert!(!fits(400, u8_type));
+    }
+
+    #[test]
+    fn casts_wrap() {
+        let i8_type = IntType { signed: true, width: 8 };
+        let i64_type = IntType { signed: true, width: 64 };
+        // (i8) 200
+        let expr = TypedExpr::Unary(TypedUnary {
+            operator: UnaryOperator::Cast(Type::Int(i8_type)),
+            operand: Box::new(int_literal("200", i64_type)),
+            result_type: Type::Int(i8_type),
+        });
+
+        assert_eq!(eval(&expr), Some(-56));
+    }
+
+    #[test]
+    fn division_by_zero_is_not_constant() {
+        let u64_type = IntType { signed: false, width: 64 };
+        let expr = TypedExpr::Binary(TypedBinary {
+            left: Box::new(int_literal("1", u64_type)),
+            operator: BinaryOperator::Divide,
+            right: Box::new(int_literal("0", u64_type)),
+            result_type: Type::Int(u64_type),
+        });
+
+        assert_eq!(eval(&expr), None);
+    }
+
+    #[test]
+    fn ranges() {
+        assert!(fits(-128, IntType { signed: true, width: 8 }));
+        assert!(!fits(-129, IntType { signed: true, width: 8 }));
+        assert!(fits(i64::MAX as i128, IntType { signed: true, width: 64 }));
+        assert!(!fits(i64::MAX as i128 + 1, IntType { signed: true, width: 64 }));
+        assert!(fits(u64::MAX as i128, IntType { signed: false, width: 64 }));
+        assert!(!fits(-1, IntType { signed: false, width: 64 }));
+    }
+
+    #[test]
+    fn literal_ranges() {
+        assert!(literal_fits("255", IntType { signed: false, width: 8 }));
+        assert!(!literal_fits("256", IntType { signed: false, width: 8 }));
+        assert!(!literal_fits("128", IntType { signed: true, width: 8 }));
+        assert!(!literal_fits("99999999999999999999999", IntType { signed: false, width: 64 }));
+        assert!(literal_fits("99999999999999999999999", IntType { signed: false, width: 128 }));
+        assert!(!literal_fits(&u128::MAX.to_string(), IntType { signed: true, width: 128 }));
+        assert!(literal_fits(&u128::MAX.to_string(), IntType { signed: true, width: 129 }));
+    }
+}
@@ -0,0 +1,251 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::lexing::token::Span;
+use crate::types::{IntType, Type};
+
+/// An error found while [typing](crate::Typer) a program, like a type mismatch or an undefined
+/// identifier.
+///
+/// Every error (except [TypingError::MissingMain]) has a span: where the expression or statement
+/// in question is, or the name of the function that it's about (see [TypingError::span]).
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum TypingError {
+    /// The program has no `main` function.
+    MissingMain,
+    /// `main` is declared as something other than a function.
+    MainNotAFunction { found: Box<Type>, span: Span },
+    /// `main` isn't public.
+    PrivateMain { span: Span },
+    /// `main` takes parameters.
+    MainWithParams { span: Span },
+    /// `main` doesn't return a `u8` (its exit code).
+    InvalidMainReturnType { found: Box<Type>, span: Span },
+    /// A function with this name has already been defined or declared.
+    FunctionRedefinition { name: String, span: Span },
+    /// A function is defined with the same name as a [builtin](crate::builtins::Builtin).
+    BuiltinRedefinition { name: String, span: Span },
+    /// A function is defined with the same name as a variable.
+    FunctionNameTaken { name: String, span: Span },
+    /// Some path through a non-`void` function ends without a `ret`.
+    MissingReturn { func_name: String, span: Span },
+    /// The condition of an `if` or `while` isn't a `bool`.
+    NonBoolCondition { statement_kind: &'static str, found: Box<Type>, span: Span },
+    /// A variable is used or assigned to before it's declared.
+    UndeclaredIdentifier { name: String, span: Span },
+    /// A variable is used where a value of another type is expected.
+    IdentifierTypeMismatch { name: String, found: Box<Type>, expected: Box<Type>, span: Span },
+    /// A function is assigned to, as if it were a variable.
+    AssignmentToFunction { name: String, span: Span },
+    /// A `void` function has a `ret` with a value.
+    UnexpectedReturnValue { span: Span },
+    /// A non-`void` function has a `ret` without a value; `expected` is the function's return type.
+    MissingReturnValue { expected: Box<Type>, span: Span },
+    /// A function has a `ret` whose value isn't of the function's return type.
+    ReturnValueTypeMismatch { expected: Box<Type>, found: Box<Type>, span: Span },
+    /// An integer literal is used where a non-integer value is expected.
+    IntLiteralTypeMismatch { literal: String, expected: Box<Type>, span: Span },
+    /// The two sides of a binary operator or comparison have different types.
+    OperandTypeMismatch { operator: String, left: Box<Type>, right: Box<Type>, span: Span },
+    /// A comparison (which is a `bool`) is used where a value of another type is expected.
+    ComparisonTypeMismatch { expected: Box<Type>, span: Span },
+    /// A call names a variable, rather than a function.
+    NotAFunction { name: String, span: Span },
+    /// A call names a function that hasn't been defined or declared.
+    UndefinedFunction { name: String, span: Span },
+    /// A function is called where a value of a type other than its return type is expected.
+    ReturnTypeMismatch { func_name: String, expected: Box<Type>, found: Box<Type>, span: Span },
+    /// A function is called with the wrong number of arguments.
+    ArgumentCountMismatch { func_name: String, expected: usize, found: usize, span: Span },
+    /// A cast is used where a value of a type other than the one being cast to is expected.
+    CastTypeMismatch { cast_type: Box<Type>, expected: Box<Type>, span: Span },
+    /// A value can't be cast from one type to another (e.g. from signed to unsigned).
+    InvalidCast { from: Box<Type>, to: Box<Type>, span: Span },
+    /// A negation is used where a value of an unsigned or non-integer type is expected.
+    NegationTypeMismatch { expected: Box<Type>, span: Span },
+    /// A value of an unsigned or non-integer type is negated.
+    InvalidNegation { found: Box<Type>, span: Span },
+    /// A constant expression (like `255 + 1`, or just `256`, as a `u8`) has a value that doesn't
+    /// fit in its type; the span is where its (last) operator or its literal is.
+    ConstantOverflow { value: String, int_type: IntType, span: Span },
+    /// A warning (rather than an error): a private function is never called (see
+    /// [TypedProgram::remove_unused_functions](crate::typed_ast::TypedProgram::remove_unused_functions)).
+    UnusedFunction { name: String, span: Span },
+}
+
+impl TypingError {
+    /// Returns the code that identifies this kind of error (or warning), like `E0301` (see
+    /// [Diagnostic::code](crate::Diagnostic::code)).
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::MissingMain => "E0301",
+            Self::MainNotAFunction { .. } => "E0302",
+            Self::PrivateMain { .. } => "E0303",
+            Self::MainWithParams { .. } => "E0304",
+            Self::InvalidMainReturnType { .. } => "E0305",
+            Self::FunctionRedefinition { .. } => "E0306",
+            Self::BuiltinRedefinition { .. } => "E0307",
+            Self::FunctionNameTaken { .. } => "E0308",
+            Self::MissingReturn { .. } => "E0309",
+            Self::NonBoolCondition { .. } => "E0310",
+            Self::UndeclaredIdentifier { .. } => "E0311",
+            Self::IdentifierTypeMismatch { .. } => "E0312",
+            Self::AssignmentToFunction { .. } => "E0313",
+            Self::UnexpectedReturnValue { .. } => "E0314",
+            Self::MissingReturnValue { .. } => "E0315",
+            Self::ReturnValueTypeMismatch { .. } => "E0316",
+            Self::IntLiteralTypeMismatch { .. } => "E0317",
+            Self::OperandTypeMismatch { .. } => "E0318",
+            Self::ComparisonTypeMismatch { .. } => "E0319",
+            Self::NotAFunction { .. } => "E0320",
+            Self::UndefinedFunction { .. } => "E0321",
+            Self::ReturnTypeMismatch { .. } => "E0322",
+            Self::ArgumentCountMismatch { .. } => "E0323",
+            Self::CastTypeMismatch { .. } => "E0324",
+            Self::InvalidCast { .. } => "E0325",
+            Self::NegationTypeMismatch { .. } => "E0326",
+            Self::InvalidNegation { .. } => "E0327",
+            Self::ConstantOverflow { .. } => "E0328",
+            Self::UnusedFunction { .. } => "W0301",
+        }
+    }
+
+    /// Returns where the error is in the source code (which is `None` only for
+    /// [TypingError::MissingMain], since it isn't anywhere).
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Self::MissingMain => None,
+            Self::MainNotAFunction { span, .. }
+            | Self::PrivateMain { span }
+            | Self::MainWithParams { span }
+            | Self::InvalidMainReturnType { span, .. }
+            | Self::FunctionRedefinition { span, .. }
+            | Self::BuiltinRedefinition { span, .. }
+            | Self::FunctionNameTaken { span, .. }
+            | Self::MissingReturn { span, .. }
+            | Self::NonBoolCondition { span, .. }
+            | Self::UndeclaredIdentifier { span, .. }
+            | Self::IdentifierTypeMismatch { span, .. }
+            | Self::AssignmentToFunction { span, .. }
+            | Self::UnexpectedReturnValue { span }
+            | Self::MissingReturnValue { span, .. }
+            | Self::ReturnValueTypeMismatch { span, .. }
+            | Self::IntLiteralTypeMismatch { span, .. }
+            | Self::OperandTypeMismatch { span, .. }
+            | Self::ComparisonTypeMismatch { span, .. }
+            | Self::NotAFunction { span, .. }
+            | Self::UndefinedFunction { span, .. }
+            | Self::ReturnTypeMismatch { span, .. }
+            | Self::ArgumentCountMismatch { span, .. }
+            | Self::CastTypeMismatch { span, .. }
+            | Self::InvalidCast { span, .. }
+            | Self::NegationTypeMismatch { span, .. }
+            | Self::InvalidNegation { span, .. }
+            | Self::ConstantOverflow { span, .. }
+            | Self::UnusedFunction { span, .. } => Some(*span),
+        }
+    }
+}
+
+impl fmt::Display for TypingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingMain => write!(f, "no main function defined"),
+            Self::MainNotAFunction { found, .. } => {
+                write!(f, "expected 'main' to be a function; found 'main' to be of type {}", found)
+            }
+            Self::PrivateMain { .. } => write!(f, "the 'main' function should be public"),
+            Self::MainWithParams { .. } => write!(f, "the 'main' function should not accept any parameters"),
+            Self::InvalidMainReturnType { .. } => write!(f, "the 'main' function should return a u8"),
+            Self::FunctionRedefinition { name, .. } => write!(f, "cannot redefine function '{}'", name),
+            Self::BuiltinRedefinition { name, .. } => {
+                write!(f, "cannot define function '{}' because it's a builtin function", name)
+            }
+            Self::FunctionNameTaken { name, .. } => write!(
+                f,
+                "cannot define function '{}' because variable with same name already exists",
+                name
+            ),
+            Self::MissingReturn { func_name, .. } => write!(f, "function '{}' does not always return a value", func_name),
+            Self::NonBoolCondition { statement_kind, found, .. } => write!(
+                f,
+                "condition of '{}' must be of type 'bool', but found type '{}'",
+                statement_kind, found
+            ),
+            Self::UndeclaredIdentifier { name, .. } => write!(f, "identifier '{}' has not been declared yet", name),
+            Self::IdentifierTypeMismatch { name, found, expected, .. } => write!(
+                f,
+                "identifier '{}' of type '{}' cannot be used as type '{}'",
+                name, found, expected
+            ),
+            Self::AssignmentToFunction { name, .. } => write!(f, "cannot assign a value to function '{}'", name),
+            Self::UnexpectedReturnValue { .. } => {
+                write!(f, "expected function to return nothing, but found 'ret' with a value")
+            }
+            Self::MissingReturnValue { expected, .. } => {
+                write!(f, "expected function to return a '{}', but found 'ret' without a value", expected)
+            }
+            Self::ReturnValueTypeMismatch { expected, found, .. } => write!(
+                f,
+                "expected function to return a '{}', but found 'ret' with a value of type '{}'",
+                expected, found
+            ),
+            Self::IntLiteralTypeMismatch { literal, expected, .. } => write!(
+                f,
+                "expected integer type for literal '{}', but the desired type is '{}'",
+                literal, expected
+            ),
+            Self::OperandTypeMismatch { operator, left, right, .. } => write!(
+                f,
+                "operator '{}' needs left-hand-side ({}) and right-hand-side ({}) to be the same type",
+                operator, left, right
+            ),
+            Self::ComparisonTypeMismatch { expected, .. } => {
+                write!(f, "comparison expressions return a bool but expected '{}'", expected)
+            }
+            Self::NotAFunction { name, .. } => write!(f, "variable '{}' is not a function", name),
+            Self::UndefinedFunction { name, .. } => {
+                write!(f, "function '{}' is called but has not been defined", name)
+            }
+            Self::ReturnTypeMismatch { func_name, expected, found, .. } => write!(
+                f,
+                "expected function '{}' to return '{}' but it has return type '{}'",
+                func_name, expected, found
+            ),
+            Self::ArgumentCountMismatch { func_name, expected, found, .. } => write!(
+                f,
+                "expected {} argument(s) to function '{}'; got {} argument(s)",
+                expected, func_name, found
+            ),
+            Self::CastTypeMismatch { cast_type, expected, .. } => write!(
+                f,
+                "expected expression of type '{}', but found a cast to type '{}'",
+                expected, cast_type
+            ),
+            Self::InvalidCast { from, to, .. } => match (from.as_ref(), to.as_ref()) {
+                (Type::Int(from_int), Type::Int(to_int)) if !from_int.signed && to_int.signed => {
+                    write!(f, "cannot cast from unsigned type '{}' to signed type '{}'", from, to)
+                }
+                (Type::Int(from_int), Type::Int(to_int)) if from_int.signed && !to_int.signed => {
+                    write!(f, "cannot cast from signed type '{}' to unsigned type '{}'", from, to)
+                }
+                _ => write!(f, "cannot cast from type '{}' to type '{}'", from, to),
+            },
+            Self::NegationTypeMismatch { expected, .. } => {
+                write!(f, "expected an expression of type '{}', but found a negation", expected)
+            }
+            Self::InvalidNegation { found, .. } if matches!(**found, Type::Int(_)) => {
+                write!(f, "cannot negate an unsigned type: '{}'", found)
+            }
+            Self::InvalidNegation { found, .. } => write!(f, "cannot negate a non-integer type '{}'", found),
+            Self::ConstantOverflow { value, int_type, .. } => write!(
+                f,
+                "constant expression overflows type '{}' (its value would be {})",
+                int_type, value
+            ),
+            Self::UnusedFunction { name, .. } => write!(f, "function '{}' is never used", name),
+        }
+    }
+}
+
+impl Error for TypingError {}
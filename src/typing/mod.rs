@@ -26,5 +26,13 @@
 /// });
 /// ```
 pub mod typed_ast;
+/// Module that evaluates constant integer expressions (see [const_eval::eval]), so that typing
+/// can reject the ones that overflow.
+pub mod const_eval;
+/// Module that defines the errors found while typing (see [error::TypingError]).
+pub mod error;
+/// Module that defines the functions built into Flick, like `breakpoint()` (see
+/// [builtins::Builtin]).
+pub mod builtins;
 /// Module that defines the [typer::Typer] struct for adding types to an abstract syntax tree.
 pub mod typer;
@@ -1,4 +1,6 @@
-use crate::ast::{BinaryOperator, ComparisonOperator, FuncProto, UnaryOperator};
+use std::collections::{HashMap, HashSet};
+
+use crate::ast::{BinaryOperator, ComparisonOperator, FuncProto, FuncVisibility, UnaryOperator};
 use crate::types::IntType;
 use crate::types::Type;
 
@@ -6,6 +8,97 @@ use crate::types::Type;
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct TypedProgram {
     pub global_statements: Vec<TypedGlobalStatement>,
+    /// The coverage counters in the program, if it's been [instrumented](crate::coverage::instrument)
+    pub coverage: Option<Coverage>,
+}
+
+/// The counters that [coverage::instrument](crate::coverage::instrument) adds to a program (as
+/// [TypedStatement::Count]s), which the program writes to a profile when it exits.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Coverage {
+    /// The path that the program writes its profile to, like `test.flcov` (a relative path is
+    /// relative to the directory that the program runs in)
+    pub profile_path: String,
+    /// Where the statement that each counter counts is, like `src/main.fl:3:5`, by the
+    /// counter's index
+    pub locations: Vec<String>,
+}
+
+impl TypedProgram {
+    /// Removes the definitions of private functions that can't be called, directly or
+    /// indirectly, from any public function (so that they aren't compiled for nothing), and
+    /// returns their prototypes (e.g. to warn about them).
+    pub fn remove_unused_functions(&mut self) -> Vec<FuncProto> {
+        let func_defs: HashMap<&str, &TypedFuncDef> = self
+            .global_statements
+            .iter()
+            .filter_map(|global_statement| match global_statement {
+                TypedGlobalStatement::FuncDef(f) => Some((f.proto.name.as_str(), f)),
+                TypedGlobalStatement::Extern(_) => None,
+            })
+            .collect();
+
+        let mut unvisited: Vec<&str> = func_defs
+            .values()
+            .filter(|f| f.proto.func_visibility == FuncVisibility::Public)
+            .map(|f| f.proto.name.as_str())
+            .collect();
+        let mut used: HashSet<String> = unvisited.iter().map(|name| name.to_string()).collect();
+        while let Some(func_name) = unvisited.pop() {
+            let Some(func_def) = func_defs.get(func_name) else { continue };
+            let mut callees = Vec::new();
+            func_def.body.iter().for_each(|stmt| stmt.collect_calls(&mut callees));
+            for callee in callees {
+                if used.insert(callee.to_string()) {
+                    unvisited.push(callee);
+                }
+            }
+        }
+
+        let mut unused = Vec::new();
+        self.global_statements.retain(|global_statement| match global_statement {
+            TypedGlobalStatement::FuncDef(f) if !used.contains(f.proto.name.as_str()) => {
+                unused.push(f.proto.clone());
+                false
+            }
+            _ => true,
+        });
+        unused
+    }
+
+    /// Returns `true` if the program defines a function called `func_name` (not just declares it,
+    /// like an `extern` function).
+    pub fn defines(&self, func_name: &str) -> bool {
+        self.global_statements.iter().any(|global_statement| {
+            matches!(global_statement, TypedGlobalStatement::FuncDef(f) if f.proto.name == func_name)
+        })
+    }
+
+    /// Returns `true` if any function in the program calls the function called `func_name`.
+    pub fn calls(&self, func_name: &str) -> bool {
+        self.global_statements.iter().any(|global_statement| match global_statement {
+            TypedGlobalStatement::FuncDef(f) => {
+                let mut calls = Vec::new();
+                f.body.iter().for_each(|stmt| stmt.collect_calls(&mut calls));
+                calls.contains(&func_name)
+            }
+            TypedGlobalStatement::Extern(_) => false,
+        })
+    }
+
+    /// Returns the libraries that the program's `extern` functions are in (like `m`, for
+    /// `extern "m" fn cos(...)`), in the order that they're first declared in.
+    pub fn libraries(&self) -> Vec<&str> {
+        let mut libraries = Vec::new();
+        for global_statement in &self.global_statements {
+            if let TypedGlobalStatement::Extern(FuncProto { library: Some(library), .. }) = global_statement {
+                if !libraries.contains(&library.as_str()) {
+                    libraries.push(library.as_str());
+                }
+            }
+        }
+        libraries
+    }
 }
 
 /// A typed version of [GlobalStatement](crate::ast::GlobalStatement)
@@ -38,6 +131,8 @@ pub enum TypedStatement {
     Return(Option<TypedExpr>),
     Call(TypedCall),
     If(TypedIf),
+    /// Adds one to the coverage counter with this index (see [Coverage])
+    Count(usize),
 
     /// Compiles to LLVM's UnreachableInst
     /// 
@@ -57,23 +152,56 @@ impl TypedStatement {
     /// Returns true if this statement always returns, no matter the control flow.
     pub fn always_returns(&self) -> bool {
         match self {
-            Self::VarDeclaration(_) | Self::Assignment(_) | Self::Call(_) => false,
+            Self::VarDeclaration(_) | Self::Assignment(_) | Self::Call(_) | Self::Count(_) => false,
             Self::Return(_) => true,
 
             // While loops can't always return; their condition might be false
             Self::WhileLoop(_) => false,
 
-            // Without an 'else' branch, an if statement doesn't always return
-            Self::If(TypedIf { else_body: None, .. }) => false,
-            // With an 'else' branch, it always returns if both branches always return
-            Self::If(TypedIf { else_body: Some(else_body), then_body, .. }) => {
-                some_statement_always_returns(else_body)
-                && some_statement_always_returns(&then_body)
-            },
+            Self::If(if_statement) => if_statement.always_returns(),
 
             Self::Unreachable => panic!("Unreachable statements should not be analyzed for always_returns"),
         }
     }
+
+    /// Adds the name of every function that this statement calls to `calls`.
+    pub fn collect_calls<'a>(&'a self, calls: &mut Vec<&'a str>) {
+        match self {
+            Self::VarDeclaration(v) => v.var_value.collect_calls(calls),
+            Self::WhileLoop(w) => {
+                w.condition.collect_calls(calls);
+                w.body.iter().for_each(|stmt| stmt.collect_calls(calls));
+            }
+            Self::Assignment(a) => a.value.collect_calls(calls),
+            Self::Return(r) => r.iter().for_each(|value| value.collect_calls(calls)),
+            Self::Call(call) => TypedExpr::collect_calls_in_call(call, calls),
+            Self::If(i) => {
+                i.condition.collect_calls(calls);
+                i.then_body
+                    .iter()
+                    .chain(i.else_body.iter().flatten())
+                    .for_each(|stmt| stmt.collect_calls(calls));
+            }
+            Self::Count(_) | Self::Unreachable => {}
+        }
+    }
+
+    /// Returns true if this statement (or a statement nested inside it) is a tail call to the
+    /// function `func_name`, like `ret foo(n - 1)` inside `foo`.
+    pub fn contains_tail_call_to(&self, func_name: &str) -> bool {
+        match self {
+            Self::Return(Some(TypedExpr::Call(call))) => call.function_name == func_name,
+            Self::WhileLoop(while_loop) => {
+                while_loop.body.iter().any(|stmt| stmt.contains_tail_call_to(func_name))
+            }
+            Self::If(if_statement) => if_statement
+                .then_body
+                .iter()
+                .chain(if_statement.else_body.iter().flatten())
+                .any(|stmt| stmt.contains_tail_call_to(func_name)),
+            _ => false,
+        }
+    }
 }
 
 /// A typed version of [VarDeclaration](crate::ast::VarDeclaration)
@@ -99,12 +227,28 @@ pub struct TypedIf {
     pub else_body: Option<Vec<TypedStatement>>,
 }
 
+impl TypedIf {
+    /// Returns true if this if statement always returns, no matter which branch is taken.
+    pub fn always_returns(&self) -> bool {
+        match &self.else_body {
+            // Without an 'else' branch, an if statement doesn't always return
+            None => false,
+            // With an 'else' branch, it always returns if both branches always return
+            Some(else_body) => {
+                some_statement_always_returns(else_body)
+                    && some_statement_always_returns(&self.then_body)
+            }
+        }
+    }
+}
+
 /// A typed version of [Expr](crate::ast::Expr)
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum TypedExpr {
     Identifier(TypedIdentifier),
     IntLiteral(TypedIntLiteral),
     BoolLiteral(bool),
+    StrLiteral(String),
     Binary(TypedBinary),
     Comparison(TypedComparison),
     Call(TypedCall),
@@ -118,12 +262,35 @@ impl TypedExpr {
             Self::Identifier(id) => id.id_type.clone(),
             Self::IntLiteral(int) => Type::Int(int.int_type),
             Self::BoolLiteral(_) => Type::Bool,
+            Self::StrLiteral(_) => Type::Str,
             Self::Binary(binary) => binary.result_type.clone(),
             Self::Comparison(_) => Type::Bool,
             Self::Call(call) => *call.function_proto.return_type.clone(),
             Self::Unary(unary) => unary.result_type.clone(),
         }
     }
+
+    /// Adds the name of every function that this expression calls to `calls`.
+    pub fn collect_calls<'a>(&'a self, calls: &mut Vec<&'a str>) {
+        match self {
+            Self::Identifier(_) | Self::IntLiteral(_) | Self::BoolLiteral(_) | Self::StrLiteral(_) => {}
+            Self::Binary(binary) => {
+                binary.left.collect_calls(calls);
+                binary.right.collect_calls(calls);
+            }
+            Self::Comparison(comparison) => {
+                comparison.left.collect_calls(calls);
+                comparison.right.collect_calls(calls);
+            }
+            Self::Call(call) => Self::collect_calls_in_call(call, calls),
+            Self::Unary(unary) => unary.operand.collect_calls(calls),
+        }
+    }
+
+    fn collect_calls_in_call<'a>(call: &'a TypedCall, calls: &mut Vec<&'a str>) {
+        calls.push(&call.function_name);
+        call.args.iter().for_each(|arg| arg.collect_calls(calls));
+    }
 }
 
 /// A typed version of [Assignment](crate::ast::Assignment).
@@ -188,3 +355,75 @@ pub struct TypedUnary {
     pub operand: Box<TypedExpr>,
     pub result_type: Type,
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{Lexer, Parser, Typer};
+    use crate::ast::Arena;
+
+    #[test]
+    fn unused_functions() {
+        let source_code = "
+            fn used() u8 {
+                ret 1
+            }
+            fn used_indirectly() u8 {
+                ret used()
+            }
+            fn unused() u8 {
+                ret used_indirectly() + only_called_by_unused()
+            }
+            fn only_called_by_unused() u8 {
+                ret 2
+            }
+            pub fn main() u8 {
+                ret used_indirectly()
+            }
+        ";
+        let tokens = Lexer::lex(source_code).unwrap();
+        let arena = Arena::new();
+        let program = Parser::parse_program(&arena, &tokens).unwrap();
+        let mut typed_program = Typer::new().type_program(&program).unwrap();
+
+        let unused: Vec<_> = typed_program.remove_unused_functions().into_iter().map(|proto| proto.name).collect();
+        assert_eq!(unused, vec!["unused", "only_called_by_unused"]);
+        assert_eq!(typed_program.global_statements.len(), 3);
+    }
+
+    #[test]
+    fn defines() {
+        let source_code = "
+            extern fn puts(str s) i32
+            pub fn main() u8 {
+                ret 0
+            }
+        ";
+        let tokens = Lexer::lex(source_code).unwrap();
+        let arena = Arena::new();
+        let program = Parser::parse_program(&arena, &tokens).unwrap();
+        let typed_program = Typer::new().type_program(&program).unwrap();
+
+        assert!(typed_program.defines("main"));
+        assert!(!typed_program.defines("puts"));
+        assert!(!typed_program.defines("nope"));
+    }
+
+    #[test]
+    fn libraries() {
+        let source_code = "
+            extern \"sqlite3\" fn sqlite3_libversion() str
+            extern fn puts(str s) i32
+            extern \"m\" fn abs(i32 x) i32
+            extern \"sqlite3\" fn sqlite3_threadsafe() i32
+            pub fn main() u8 {
+                ret 0
+            }
+        ";
+        let tokens = Lexer::lex(source_code).unwrap();
+        let arena = Arena::new();
+        let program = Parser::parse_program(&arena, &tokens).unwrap();
+        let typed_program = Typer::new().type_program(&program).unwrap();
+
+        assert_eq!(typed_program.libraries(), ["sqlite3", "m"]);
+    }
+}
@@ -1,31 +1,75 @@
 use crate::ast::{
     Assignment, Binary, Call, Comparison, Expr, FuncDef, FuncProto, FuncVisibility,
-    GlobalStatement, If, Program, Statement, Unary, UnaryOperator, VarDeclaration,
+    GlobalStatement, Identifier, If, NodeMap, Program, Statement, Unary, UnaryOperator, VarDeclaration,
     WhileLoop,
 };
-use crate::scope_manager::ScopeManager;
+use crate::builtins::Builtin;
+use crate::lexing::token::Span;
+use crate::scope_manager::{ScopeManager, Symbol, SymbolKind};
 use crate::typed_ast::{
     TypedAssignment, TypedBinary, TypedCall, TypedComparison, TypedExpr, TypedFuncDef,
     TypedGlobalStatement, TypedIdentifier, TypedIf, TypedIntLiteral, TypedProgram, TypedStatement,
     TypedUnary, TypedVarDeclaration, TypedWhileLoop, some_statement_always_returns,
 };
+use crate::typing::const_eval;
+use crate::typing::error::TypingError;
 use crate::types::IntType;
 use crate::types::Type;
 
 /// This struct handles the conversion from a regular [abstract syntax tree](crate::ast) to a
 /// [typed abstract syntax tree](crate::typed_ast). See [Typer::type_program] for details.
+#[derive(Clone)]
 pub struct Typer {
-    scope_manager: ScopeManager<Type>,
+    scope_manager: ScopeManager<Symbol>,
+    /// The type of every expression that has been typed so far
+    expr_types: NodeMap<Type>,
+    /// Whether programs must have a `pub fn main() u8` (see [Typer::without_main])
+    requires_main: bool,
 }
 
 impl Typer {
     pub fn new() -> Self {
         let scope_manager = ScopeManager::new();
-        Self { scope_manager }
+        Self { scope_manager, expr_types: NodeMap::new(), requires_main: true }
+    }
+
+    /// Returns a typer for programs that don't have to have a `main` function (or whose `main`
+    /// can have any prototype), like the ones that an [Engine](crate::Engine) runs, whose
+    /// functions are called from Rust instead.
+    pub fn without_main() -> Self {
+        Self { requires_main: false, ..Self::new() }
+    }
+
+    /// Returns the type of each expression that [Typer::type_program] has typed, by its
+    /// [NodeId](crate::ast::NodeId) (e.g. for an editor to show the type of the expression under
+    /// the cursor).
+    ///
+    /// ```
+    /// use flick::ast::{Arena, GlobalStatement, Statement};
+    /// use flick::types::{IntType, Type};
+    /// use flick::{Lexer, Parser, Typer};
+    ///
+    /// let source_code = "pub fn main() u8 {\n    ret 1 + 2\n}\n";
+    /// let tokens = Lexer::lex(source_code).unwrap();
+    /// let arena = Arena::new();
+    /// let program = Parser::parse_program(&arena, &tokens).unwrap();
+    /// let mut typer = Typer::new();
+    /// typer.type_program(&program).unwrap();
+    ///
+    /// let GlobalStatement::FuncDef(main) = &program.global_statements[0] else { panic!() };
+    /// let Statement::Return(Some(sum), ..) = &main.body[0] else { panic!() };
+    /// assert_eq!(typer.expr_types()[&sum.id()], Type::Int(IntType { width: 8, signed: false }));
+    /// ```
+    pub fn expr_types(&self) -> &NodeMap<Type> {
+        &self.expr_types
     }
 
     /// This method goes through the entire `program` and converts it to a [TypedProgram],
-    /// panicking if any type mismatches or undefined identifiers are uncovered.
+    /// returning an `Err()` with every type mismatch or undefined identifier that's uncovered
+    /// (at most one per function, since typing a function stops at its first error).
+    ///
+    /// Typing is the compiler's semantic analysis: once a program has been typed, the
+    /// [Compiler](crate::Compiler) can generate code for it without checking anything else.
     ///
     /// In the future, this method may also coerce types as necessary, such as when `i32` and
     /// `i32` are summed and placed into an `i64` (currently, programs can only store `i32 + i32`
@@ -35,81 +79,164 @@ impl Typer {
     ///
     /// This method assumes that `program` represents a well-parsed program; for example, one
     /// returned by [Parser::parse_program()](crate::Parser::parse_program).
-    pub fn type_program(&mut self, program: &Program) -> TypedProgram {
-        let mut global_statements = Vec::with_capacity(program.global_statements.len());
+    pub fn type_program(&mut self, program: &Program) -> Result<TypedProgram, Vec<TypingError>> {
+        self.scope_manager.enter_scope();
+        let global_statements = self.type_global_statements(program);
+        self.scope_manager.exit_scope();
+
+        Ok(TypedProgram { global_statements: global_statements?, coverage: None })
+    }
+
+    /// Like [Typer::type_program], but once every function's prototype is known, the functions
+    /// are typed on all of the machine's cores, each of which has its own copy of the global
+    /// scope (the types of the expressions that they type are merged afterwards). The result,
+    /// and the errors (in the order that they're in the program), are the same.
+    #[cfg(feature = "parallel")]
+    pub fn type_program_in_parallel(&mut self, program: &Program) -> Result<TypedProgram, Vec<TypingError>> {
+        use rayon::prelude::*;
 
         self.scope_manager.enter_scope();
-        for global_statement in program.global_statements.iter() {
-            match global_statement {
-                GlobalStatement::Extern(proto) => self.register_func_proto(proto),
-                GlobalStatement::FuncDef(f) => self.register_func_proto(&f.proto),
+        let mut errors = self.declare_global_statements(program);
+        let threads = rayon::current_num_threads();
+        let chunk_size = program.global_statements.len().div_ceil(threads).max(1);
+        let chunks: Vec<_> = program
+            .global_statements
+            .par_chunks(chunk_size)
+            .map(|chunk| {
+                let mut typer = Self { scope_manager: self.scope_manager.clone(), expr_types: NodeMap::new(), requires_main: self.requires_main };
+                let mut chunk_errors = Vec::new();
+                let typed = typer.type_each_global_statement(chunk, &mut chunk_errors);
+                (typed, chunk_errors, typer.expr_types)
+            })
+            .collect();
+        let mut global_statements = Vec::new();
+        for (typed, chunk_errors, expr_types) in chunks {
+            global_statements.extend(typed);
+            errors.extend(chunk_errors);
+            self.expr_types.extend(expr_types);
+        }
+        errors.extend(self.check_valid_main_func().err());
+        self.scope_manager.exit_scope();
+
+        match errors.is_empty() {
+            true => Ok(TypedProgram { global_statements, coverage: None }),
+            false => Err(errors),
+        }
+    }
+
+    /// Types every global statement of `program`, assuming that the global scope has just been
+    /// entered (so that [Typer::type_program] can exit it, even if typing fails).
+    fn type_global_statements(&mut self, program: &Program) -> Result<Vec<TypedGlobalStatement>, Vec<TypingError>> {
+        let mut errors = self.declare_global_statements(program);
+        let global_statements = self.type_each_global_statement(&program.global_statements, &mut errors);
+        errors.extend(self.check_valid_main_func().err());
+
+        match errors.is_empty() {
+            true => Ok(global_statements),
+            false => Err(errors),
+        }
+    }
+
+    /// Types each of the `global_statements`, adding the error in each function that has one
+    /// to `errors` (and leaving that function out of the result).
+    fn type_each_global_statement(
+        &mut self,
+        global_statements: &[GlobalStatement],
+        errors: &mut Vec<TypingError>,
+    ) -> Vec<TypedGlobalStatement> {
+        let mut typed_global_statements = Vec::new();
+        for global_statement in global_statements {
+            match self.type_global_statement(global_statement) {
+                Ok(typed_global_statement) => typed_global_statements.push(typed_global_statement),
+                Err(err) => errors.push(err),
             }
         }
+        typed_global_statements
+    }
+
+    /// Adds the builtins, and the prototype of every function in `program`, to the global scope,
+    /// so that functions can be called before they're defined. Returns an error for each function
+    /// that can't be declared (e.g. because it's already been).
+    fn declare_global_statements(&mut self, program: &Program) -> Vec<TypingError> {
+        for builtin in Builtin::ALL {
+            self.scope_manager.set(builtin.name(), Symbol::builtin(builtin));
+        }
+        let mut errors = Vec::new();
         for global_statement in program.global_statements.iter() {
-            global_statements.push(self.type_global_statement(global_statement))
+            let proto = match global_statement {
+                GlobalStatement::Extern(proto) => proto,
+                GlobalStatement::FuncDef(f) => &f.proto,
+            };
+            errors.extend(self.register_func_proto(proto).err());
         }
-        self.check_valid_main_func();
-        self.scope_manager.exit_scope();
-
-        TypedProgram { global_statements }
+        errors
     }
 
-    fn check_valid_main_func(&self) {
-        let func_proto = match self.scope_manager.get("main") {
-            Some(Type::Func(proto)) => proto,
-            Some(t) => panic!("Expected 'main' to be a function; found 'main' to be of type {}", t),
-            None => panic!("No main function defined")
+    /// Returns an `Err()` if the program needs a `main` function (see [Typer::without_main]), but
+    /// it doesn't have one with the right prototype.
+    fn check_valid_main_func(&self) -> Result<(), TypingError> {
+        if !self.requires_main {
+            return Ok(());
+        }
+        let Some(symbol) = self.scope_manager.get("main") else { return Err(TypingError::MissingMain) };
+        let span = symbol.span;
+        let func_proto = match &symbol.symbol_type {
+            Type::Func(proto) => proto,
+            t => return Err(TypingError::MainNotAFunction { found: Box::new(t.clone()), span }),
         };
 
         if func_proto.func_visibility != FuncVisibility::Public {
-            panic!("The 'main' function should be public");
+            return Err(TypingError::PrivateMain { span });
         }
 
         if !func_proto.params.is_empty() {
-            panic!("The 'main' function should not accept any parameters");
+            return Err(TypingError::MainWithParams { span });
         }
 
         if *func_proto.return_type != Type::Int(IntType { width: 8, signed: false }) {
-            panic!("The 'main' function should return a u8");
+            return Err(TypingError::InvalidMainReturnType { found: func_proto.return_type.clone(), span });
         }
+
+        Ok(())
     }
 
     /// This method processes the prototype of a function, updating the internal scope manager
     /// and confirming the function isn't being redeclared.
-    fn register_func_proto(&mut self, func_proto: &FuncProto) {
+    fn register_func_proto(&mut self, func_proto: &FuncProto) -> Result<(), TypingError> {
         let func_name = &func_proto.name;
-        match self.scope_manager.get(func_name) {
-            Some(Type::Func(_)) => panic!("Cannot redefine function '{}'", func_name),
-            Some(_) => panic!(
-                "Cannot define function '{}' because variable with same name already exists",
-                func_name
-            ),
+        let (name, span) = (func_name.to_string(), func_proto.span);
+        match self.scope_manager.get(func_name).map(|symbol| symbol.kind) {
+            Some(SymbolKind::Builtin) => return Err(TypingError::BuiltinRedefinition { name, span }),
+            Some(SymbolKind::Function) => return Err(TypingError::FunctionRedefinition { name, span }),
+            Some(_) => return Err(TypingError::FunctionNameTaken { name, span }),
             None => {}
         }
 
-        let func_type = Type::Func(func_proto.clone());
-        self.scope_manager.set(func_name, func_type);
+        self.scope_manager.set(func_name, Symbol::function(func_proto));
+        Ok(())
     }
 
-    fn type_global_statement(&mut self, global_statement: &GlobalStatement) -> TypedGlobalStatement {
-        match global_statement {
+    fn type_global_statement(&mut self, global_statement: &GlobalStatement) -> Result<TypedGlobalStatement, TypingError> {
+        Ok(match global_statement {
             GlobalStatement::Extern(proto) => TypedGlobalStatement::Extern(proto.clone()),
-            GlobalStatement::FuncDef(f) => TypedGlobalStatement::FuncDef(self.type_func_def(f)),
-        }
+            GlobalStatement::FuncDef(f) => TypedGlobalStatement::FuncDef(self.type_func_def(f)?),
+        })
     }
 
     /// This method processes a function definition by processing each statement within the body,
     /// and by confirming that it always returns the correct type (according to its prototype).
-    fn type_func_def(&mut self, func_def: &FuncDef) -> TypedFuncDef {
+    fn type_func_def(&mut self, func_def: &FuncDef) -> Result<TypedFuncDef, TypingError> {
+        tracing::debug!(function = %func_def.proto.name, "type checking");
         self.scope_manager.enter_scope();
 
         for param in func_def.proto.params.iter() {
-            let param_name = &param.param_name;
-            let param_type = param.param_type.clone();
-            self.scope_manager.set(param_name, param_type);
+            self.scope_manager.set(param.param_name, Symbol::param(param));
         }
 
-        let mut func_body = self.type_body(&func_def.body, &func_def.proto.return_type);
+        let func_body = self.type_body(&func_def.body, &func_def.proto.return_type);
+
+        self.scope_manager.exit_scope();
+        let mut func_body = func_body?;
 
         if Type::Void == *func_def.proto.return_type {
             // Void functions: implicitly return to make sure the basic block is terminated
@@ -120,7 +247,8 @@ impl Typer {
         } else {
             // Non-void functions: make sure all control paths lead to a return
             if !some_statement_always_returns(&func_body) {
-                panic!("Function '{}' does not always return a value", func_def.proto.name);
+                let (func_name, span) = (func_def.proto.name.to_string(), func_def.proto.span);
+                return Err(TypingError::MissingReturn { func_name, span });
             }
 
            if !func_body.last().is_some_and(|s| s.always_returns()) {
@@ -128,12 +256,10 @@ impl Typer {
            }
         }
 
-        self.scope_manager.exit_scope();
-
-        TypedFuncDef {
+        Ok(TypedFuncDef {
             proto: func_def.proto.clone(),
             body: func_body,
-        }
+        })
     }
 
     /// This method processes a statement and makes sure that its internals are well-typed.
@@ -146,46 +272,46 @@ impl Typer {
         &mut self,
         statement: &Statement,
         function_return_type: &Type,
-    ) -> TypedStatement {
-        match statement {
+    ) -> Result<TypedStatement, TypingError> {
+        Ok(match statement {
             Statement::VarDeclaration(v) => {
-                TypedStatement::VarDeclaration(self.type_var_declaration(v))
+                TypedStatement::VarDeclaration(self.type_var_declaration(v)?)
             }
             Statement::WhileLoop(w) => {
-                TypedStatement::WhileLoop(self.type_while_loop(w, function_return_type))
+                TypedStatement::WhileLoop(self.type_while_loop(w, function_return_type)?)
             }
-            Statement::Assignment(a) => TypedStatement::Assignment(self.type_assignment(a)),
-            Statement::Return(r) => {
-                TypedStatement::Return(self.type_return(r.as_ref(), function_return_type))
+            Statement::Assignment(a) => TypedStatement::Assignment(self.type_assignment(a)?),
+            Statement::Return(r, span, _) => {
+                TypedStatement::Return(self.type_return(r.as_ref(), *span, function_return_type)?)
             }
             // Below, the desired_type of the call is None because the value returned by the call is never used
-            Statement::Call(c) => TypedStatement::Call(self.type_call(c, None)),
-            Statement::If(i) => TypedStatement::If(self.type_if_statement(i, function_return_type)),
-        }
+            Statement::Call(c) => TypedStatement::Call(self.type_call(c, None)?),
+            Statement::If(i) => TypedStatement::If(self.type_if_statement(i, function_return_type)?),
+        })
     }
 
     /// This method checks that the variable introduced by `var_declaration` is being set to a value
     /// of its declared type.
-    fn type_var_declaration(&mut self, var_declaration: &VarDeclaration) -> TypedVarDeclaration {
-        let var_name = var_declaration.var_name.clone();
+    fn type_var_declaration(&mut self, var_declaration: &VarDeclaration) -> Result<TypedVarDeclaration, TypingError> {
+        let var_name = var_declaration.var_name.to_string();
         let var_type = var_declaration.var_type.clone();
-        let var_value = self.type_expr(&var_declaration.var_value, Some(&var_type));
-        self.scope_manager.set(&var_name, var_type.clone());
+        let var_value = self.type_expr(&var_declaration.var_value, Some(&var_type))?;
+        self.scope_manager.set(&var_name, Symbol::local(var_declaration));
 
-        TypedVarDeclaration {
+        Ok(TypedVarDeclaration {
             var_name,
             var_type,
             var_value,
-        }
+        })
     }
 
     /// This method confirms that a given body (consisting of one or more [Statement][a]) 
     /// is well-typed.
     /// 
     /// [a]: crate::ast::Statement
-    fn type_body(&mut self, body: &[Statement], function_return_type: &Type) -> Vec<TypedStatement> {
+    fn type_body(&mut self, body: &[Statement], function_return_type: &Type) -> Result<Vec<TypedStatement>, TypingError> {
         self.scope_manager.enter_scope();
-        let typed_body: Vec<_> = body
+        let typed_body = body
             .iter()
             .map(|s| self.type_statement(s, function_return_type))
             .collect();
@@ -193,13 +319,32 @@ impl Typer {
         typed_body
     }
 
+    /// This method checks that the condition of an if statement or while loop is a `bool`.
+    ///
+    /// Flick has no notion of "truthy" values: integers must be compared explicitly (e.g.
+    /// `while n != 0`), so conditions always compile to an LLVM `i1` without any conversion.
+    ///
+    /// The `statement_kind` (like `"while"`) is only used to produce a helpful error message.
+    fn type_condition(&mut self, condition: &Expr, statement_kind: &'static str) -> Result<TypedExpr, TypingError> {
+        let typed_condition = self.type_expr(condition, None)?;
+        let condition_type = typed_condition.get_result_type();
+        if condition_type != Type::Bool {
+            let found = Box::new(condition_type);
+            return Err(TypingError::NonBoolCondition { statement_kind, found, span: condition.span() });
+        }
+        Ok(typed_condition)
+    }
+
     /// This method checks that an if statement has a *boolean* condition and a collection of body
     /// statements that are well-typed.
-    fn type_if_statement(&mut self, if_statement: &If, function_return_type: &Type) -> TypedIf {
-        let condition = self.type_expr(&if_statement.condition, Some(&Type::Bool));
-        let then_body = self.type_body(&if_statement.then_body, function_return_type);
-        let else_body = if_statement.else_body.as_ref().map(|body| self.type_body(body, function_return_type));
-        TypedIf { condition, then_body, else_body }
+    fn type_if_statement(&mut self, if_statement: &If, function_return_type: &Type) -> Result<TypedIf, TypingError> {
+        let condition = self.type_condition(&if_statement.condition, "if")?;
+        let then_body = self.type_body(&if_statement.then_body, function_return_type)?;
+        let else_body = match &if_statement.else_body {
+            Some(body) => Some(self.type_body(body, function_return_type)?),
+            None => None,
+        };
+        Ok(TypedIf { condition, then_body, else_body })
     }
 
     /// This method checks that a while loop has a *boolean* condition and a collection of body
@@ -208,44 +353,58 @@ impl Typer {
         &mut self,
         while_loop: &WhileLoop,
         function_return_type: &Type,
-    ) -> TypedWhileLoop {
-        let condition = self.type_expr(&while_loop.condition, Some(&Type::Bool));
-        let body = self.type_body(&while_loop.body, function_return_type);
-        TypedWhileLoop { condition, body }
+    ) -> Result<TypedWhileLoop, TypingError> {
+        let condition = self.type_condition(&while_loop.condition, "while")?;
+        let body = self.type_body(&while_loop.body, function_return_type)?;
+        Ok(TypedWhileLoop { condition, body })
     }
 
     /// This method checks that an assignment is assigning to a declared variable, and that the new
     /// value matches the variable's declared type.
-    fn type_assignment(&mut self, assignment: &Assignment) -> TypedAssignment {
-        let name = assignment.name.clone();
+    fn type_assignment(&mut self, assignment: &Assignment) -> Result<TypedAssignment, TypingError> {
+        let name = assignment.name.to_string();
+        let span = assignment.span;
         let var_type = match self.scope_manager.get(&name) {
-            Some(t) => t.clone(),
-            None => panic!("Variable '{}' has not been declared yet", name),
+            Some(symbol) if symbol.is_mutable() => symbol.symbol_type.clone(),
+            Some(_) => return Err(TypingError::AssignmentToFunction { name, span }),
+            None => return Err(TypingError::UndeclaredIdentifier { name, span }),
         };
-        let value = self.type_expr(assignment.value.as_ref(), Some(&var_type));
+        let value = self.type_expr(assignment.value, Some(&var_type))?;
 
-        self.scope_manager.set(&name, var_type);
-
-        TypedAssignment {
+        Ok(TypedAssignment {
             name,
             value: Box::new(value),
-        }
+        })
     }
 
     /// Processes a return statement by confirming that the returned expression matches the return
-    /// type of †he function.
+    /// type of †he function (and that there's a returned expression iff the function isn't `void`).
+    ///
+    /// The `span` is where the `ret` keyword is.
     fn type_return(
         &mut self,
         ret: Option<&Expr>,
+        span: Span,
         function_return_type: &Type,
-    ) -> Option<TypedExpr> {
+    ) -> Result<Option<TypedExpr>, TypingError> {
         match (ret, function_return_type) {
-            (Some(_), Type::Void) => panic!("Expected function to return nothing, but found 'ret' with a value"),
-            (Some(expr), desired) => Some(self.type_expr(expr, Some(desired))),
-            (None, Type::Void) => None,
-            (None, _) => panic!(
-                "Expected function to return a '{}', but found 'ret' without a value", function_return_type
-            )
+            (Some(_), Type::Void) => Err(TypingError::UnexpectedReturnValue { span }),
+            (Some(expr), desired) => {
+                let typed_expr = self.type_expr(expr, Some(desired))?;
+                // Some expressions (like `true`) ignore the type they're expected to have, so the
+                // returned value's type is checked here, rather than trusted
+                let found = typed_expr.get_result_type();
+                if found != *desired {
+                    return Err(TypingError::ReturnValueTypeMismatch {
+                        expected: Box::new(desired.clone()),
+                        found: Box::new(found),
+                        span,
+                    });
+                }
+                Ok(Some(typed_expr))
+            }
+            (None, Type::Void) => Ok(None),
+            (None, _) => Err(TypingError::MissingReturnValue { expected: Box::new(function_return_type.clone()), span }),
         }
     }
 
@@ -254,56 +413,67 @@ impl Typer {
     ///
     /// Note, if the provided `desired_type` is `None`, then the returned `TypedExpr` is allowed to
     /// be of any type.
-    fn type_expr(&mut self, expr: &Expr, desired_type: Option<&Type>) -> TypedExpr {
-        match expr {
-            Expr::Identifier(name) => {
-                TypedExpr::Identifier(self.type_identifier(name, desired_type))
+    fn type_expr(&mut self, expr: &Expr, desired_type: Option<&Type>) -> Result<TypedExpr, TypingError> {
+        let typed_expr = match expr {
+            Expr::Identifier(id) => {
+                TypedExpr::Identifier(self.type_identifier(id, desired_type)?)
             }
-            Expr::IntLiteral(int) => {
-                TypedExpr::IntLiteral(self.type_int_literal(int, desired_type))
+            Expr::IntLiteral(int, span, _) => {
+                let typed_int = self.type_int_literal(int, *span, desired_type)?;
+                Self::check_int_literal_fits(&typed_int, *span)?;
+                TypedExpr::IntLiteral(typed_int)
             }
-            Expr::BoolLiteral(b) => TypedExpr::BoolLiteral(*b),
-            Expr::Binary(b) => TypedExpr::Binary(self.type_binary_expr(b, desired_type)),
+            Expr::BoolLiteral(b, ..) => TypedExpr::BoolLiteral(*b),
+            Expr::StrLiteral(s, ..) => TypedExpr::StrLiteral(s.to_string()),
+            Expr::Binary(b) => TypedExpr::Binary(self.type_binary_expr(b, desired_type)?),
             Expr::Comparison(c) => {
-                TypedExpr::Comparison(self.type_comparison_expr(c, desired_type))
+                TypedExpr::Comparison(self.type_comparison_expr(c, desired_type)?)
             }
-            Expr::Call(c) => TypedExpr::Call(self.type_call(c, desired_type)),
-            Expr::Unary(u) => TypedExpr::Unary(self.type_unary_expr(u, desired_type)),
-        }
+            Expr::Call(c) => TypedExpr::Call(self.type_call(c, desired_type)?),
+            Expr::Unary(u) => TypedExpr::Unary(self.type_unary_expr(u, desired_type)?),
+        };
+        self.expr_types.insert(expr.id(), typed_expr.get_result_type());
+        Ok(typed_expr)
     }
 
     /// Checks that the unary expression is of the correct type, and wraps it as a `TypedUnary`.
     /// 
     /// For example, if the unary operator is a cast, then the operand must be castable to the
     /// desired type.
-    fn type_unary_expr(&mut self, unary: &Unary, desired_type: Option<&Type>) -> TypedUnary {
-        // desired_operand_type will be used as the desired type when typing the operand
+    fn type_unary_expr(&mut self, unary: &Unary, desired_type: Option<&Type>) -> Result<TypedUnary, TypingError> {
         let desired_operand_type = match (&unary.operator, desired_type) {
             (UnaryOperator::Cast(_), None) => None,
             (UnaryOperator::Cast(cast_type), Some(desired)) if cast_type == desired => None,
             (UnaryOperator::Cast(cast_type), Some(desired)) => {
-                panic!(
-                    "Expected expression of type '{}', but found a cast to type '{}'",
-                    desired,
-                    cast_type
-                )
+                return Err(TypingError::CastTypeMismatch {
+                    cast_type: Box::new(cast_type.clone()),
+                    expected: Box::new(desired.clone()),
+                    span: unary.span,
+                })
             }
 
             (UnaryOperator::Negate, None) => Some(&Type::Int(IntType { signed: true, width: 64 })),
-            (UnaryOperator::Negate, Some(t @ Type::Int(IntType { signed: false, .. }))) => {
-               panic!("Expected an unsigned expression of type '{}', but found a negation", t)
-            },
-            (UnaryOperator::Negate, Some(t @ Type::Int(_))) => Some(t),
-            (UnaryOperator::Negate, Some(t )) => panic!("Cannot negate a non-integer type '{}'", t),
+            (UnaryOperator::Negate, Some(t @ Type::Int(IntType { signed: true, .. }))) => Some(t),
+            (UnaryOperator::Negate, Some(t)) => {
+                return Err(TypingError::NegationTypeMismatch { expected: Box::new(t.clone()), span: unary.span })
+            }
         };
 
-        let typed_operand = self.type_expr(&unary.operand, desired_operand_type);
+        let typed_operand = match (&unary.operator, &*unary.operand) {
+            // `128` doesn't fit in an `i8`, but `-128` does, so only the negation is checked
+            (UnaryOperator::Negate, Expr::IntLiteral(int, span, id)) => {
+                let typed_int = TypedExpr::IntLiteral(self.type_int_literal(int, *span, desired_operand_type)?);
+                self.expr_types.insert(*id, typed_int.get_result_type());
+                typed_int
+            }
+            _ => self.type_expr(unary.operand, desired_operand_type)?,
+        };
         let operand_type = typed_operand.get_result_type();
 
         // Now that we know the type of the operand, we can check if the unary operator is valid
         match &unary.operator {
-            UnaryOperator::Cast(cast_type) => Self::check_valid_cast(&cast_type, &operand_type),
-            UnaryOperator::Negate => Self::check_valid_negation(&operand_type)
+            UnaryOperator::Cast(cast_type) => Self::check_valid_cast(cast_type, &operand_type, unary.span)?,
+            UnaryOperator::Negate => Self::check_valid_negation(&operand_type, unary.span)?,
         }
 
         let result_type = match &unary.operator {
@@ -311,80 +481,93 @@ impl Typer {
             UnaryOperator::Negate => operand_type,
         };
 
-        TypedUnary {
+        let typed_unary = TypedUnary {
             operator: unary.operator.clone(),
             operand: Box::new(typed_operand),
             result_type,
-        }
+        };
+        Self::check_constant_fits(const_eval::eval_unary(&typed_unary), &typed_unary.result_type, unary.span)?;
+        Ok(typed_unary)
     }
 
-    /// Checks that the identifier labeled `name` can be interpreted as the type `desired_type`, and
-    /// wraps it as a `TypedIdentifier`.
+    /// Checks that the `identifier` can be interpreted as the type `desired_type`, and wraps it as
+    /// a `TypedIdentifier`.
     ///
-    /// Note: currently, this function will fail unless `name` was declared to be `desired_type`.
-    /// In the future, this function might support type escalation, like letting an `name` of type
-    /// `i32` but be allowed to be typed as `i64`.
-    fn type_identifier(&self, name: &str, desired_type: Option<&Type>) -> TypedIdentifier {
-        let actual_type = self.scope_manager.get(name);
+    /// Note: currently, this function will fail unless the identifier was declared to be
+    /// `desired_type`. In the future, this function might support type escalation, like letting
+    /// an identifier of type `i32` but be allowed to be typed as `i64`.
+    fn type_identifier(&self, identifier: &Identifier, desired_type: Option<&Type>) -> Result<TypedIdentifier, TypingError> {
+        let name = identifier.name;
+        let actual_type = self.scope_manager.get(name).map(|symbol| &symbol.symbol_type);
         let id_type = match (actual_type, desired_type) {
-            (None, _) => panic!("Identifier '{}' has not been declared yet.", name),
+            (None, _) => {
+                return Err(TypingError::UndeclaredIdentifier { name: name.to_string(), span: identifier.span })
+            }
             (Some(actual), Some(desired)) if actual == desired => actual.clone(),
-            (Some(actual), Some(desired)) => panic!(
-                "Identifier '{}' of type '{}' cannot be used as type '{}'",
-                name, actual, desired
-            ),
+            (Some(actual), Some(desired)) => {
+                return Err(TypingError::IdentifierTypeMismatch {
+                    name: name.to_string(),
+                    found: Box::new(actual.clone()),
+                    expected: Box::new(desired.clone()),
+                    span: identifier.span,
+                })
+            }
             (Some(actual), None) => actual.clone(),
         };
 
-        TypedIdentifier {
+        Ok(TypedIdentifier {
             name: name.to_string(),
             id_type,
-        }
+        })
     }
 
     /// Checks that `desired_type` is a valid type (namely, an integer type) and wraps the
-    /// `int_literal` as a `TypedIntLiteral`.
-    fn type_int_literal(&self, int_literal: &str, desired_type: Option<&Type>) -> TypedIntLiteral {
+    /// `int_literal` as a `TypedIntLiteral` (see [Typer::check_int_literal_fits] for checking
+    /// its value).
+    fn type_int_literal(&self, int_literal: &str, span: Span, desired_type: Option<&Type>) -> Result<TypedIntLiteral, TypingError> {
         let int_type = match desired_type {
             Some(Type::Int(int_type)) => *int_type,
             Some(t) => {
-                panic!(
-                    "Expected integer type for literal '{}', but the desired type is '{}'",
-                    int_literal, t
-                )
+                return Err(TypingError::IntLiteralTypeMismatch {
+                    literal: int_literal.to_string(),
+                    expected: Box::new(t.clone()),
+                    span,
+                })
             }
             None => IntType { signed: false, width: 64 },
         };
 
-        // TODO: Ensure that the int_literal.value fits within the desired width
-
-        TypedIntLiteral {
+        Ok(TypedIntLiteral {
             int_value: int_literal.to_string(),
             int_type,
-        }
+        })
     }
 
     /// Types a binary expression; see [Typer::type_expr] for details.
-    fn type_binary_expr(&mut self, binary_expr: &Binary, desired_type: Option<&Type>) -> TypedBinary {
-        let left = self.type_expr(&binary_expr.left, desired_type);
+    fn type_binary_expr(&mut self, binary_expr: &Binary, desired_type: Option<&Type>) -> Result<TypedBinary, TypingError> {
+        let left = self.type_expr(binary_expr.left, desired_type)?;
         let operator = binary_expr.operator;
-        let right = self.type_expr(&binary_expr.right, desired_type);
+        let right = self.type_expr(binary_expr.right, desired_type)?;
 
         let left_type = left.get_result_type();
         let right_type = right.get_result_type();
         if left_type != right_type {
-            panic!(
-                "Operator '{}' needs left-hand-side ({}) and right-hand-side ({}) to be the same type",
-                operator, left_type, right_type
-            );
+            return Err(TypingError::OperandTypeMismatch {
+                operator: operator.to_string(),
+                left: Box::new(left_type),
+                right: Box::new(right_type),
+                span: binary_expr.span,
+            });
         }
 
-        TypedBinary {
+        let typed_binary = TypedBinary {
             left: Box::new(left),
             operator,
             right: Box::new(right),
             result_type: left_type,  // since both types must be equal
-        }
+        };
+        Self::check_constant_fits(const_eval::eval_binary(&typed_binary), &typed_binary.result_type, binary_expr.span)?;
+        Ok(typed_binary)
     }
 
     /// Types a comparison expression; see [Typer::type_expr] for details.
@@ -392,117 +575,128 @@ impl Typer {
         &mut self,
         comparison: &Comparison,
         desired_type: Option<&Type>,
-    ) -> TypedComparison {
-        if desired_type.is_some() && desired_type != Some(&Type::Bool) {
-            panic!(
-                "Comparison expressions return an i1 but expected '{}'",
-                desired_type.unwrap()
-            );
+    ) -> Result<TypedComparison, TypingError> {
+        if let Some(desired) = desired_type.filter(|&t| *t != Type::Bool) {
+            return Err(TypingError::ComparisonTypeMismatch { expected: Box::new(desired.clone()), span: comparison.span });
         }
         // TODO for future: Find common type (by casting/coalescing), like i64 can fit both i64 and i32
 
-        let left = self.type_expr(&comparison.left, None);
+        let left = self.type_expr(comparison.left, None)?;
         let operator = comparison.operator;
-        let right = self.type_expr(&comparison.right, None);
+        let right = self.type_expr(comparison.right, None)?;
 
         let left_type = left.get_result_type();
         let right_type = right.get_result_type();
 
         if left_type != right_type {
-            panic!(
-                "Comparison '{}' needs left-hand-side ({}) and right-hand-side ({}) to be the same type",
-                comparison.operator, left_type, right_type
-            );
+            return Err(TypingError::OperandTypeMismatch {
+                operator: operator.to_string(),
+                left: Box::new(left_type),
+                right: Box::new(right_type),
+                span: comparison.span,
+            });
         }
 
-        TypedComparison {
+        Ok(TypedComparison {
             left: Box::new(left),
             operator,
             right: Box::new(right),
             operand_type: left_type, // since both types must be equal
-        }
+        })
     }
 
     /// Types a call expression, making sure that it matches the function's prototype, and that the
     /// return type matches the `desired_type`.[^note]
     ///
     /// [^note]: See also [Typer::type_expr] for details about `desired_type`.
-    fn type_call(&mut self, call: &Call, desired_type: Option<&Type>) -> TypedCall {
-        let function_name = call.function_name.clone();
+    fn type_call(&mut self, call: &Call, desired_type: Option<&Type>) -> Result<TypedCall, TypingError> {
+        let function_name = call.function_name.to_string();
+        let span = call.span;
 
-        let function_proto = match self.scope_manager.get(&function_name) {
+        let function_proto = match self.scope_manager.get(&function_name).map(|symbol| &symbol.symbol_type) {
             Some(Type::Func(f)) => f.clone(),
-            Some(_) => panic!("Variable '{}' is not a function", function_name),
-            None => panic!(
-                "Function '{}' is called but has not been defined",
-                function_name
-            ),
-        };
-
-        if desired_type.is_some() && Some(function_proto.return_type.as_ref()) != desired_type {
-            panic!(
-                "Expected function '{}' to return '{}' but it has return type '{}'",
-                function_name,
-                desired_type.unwrap(),
-                function_proto.return_type
-            )
+            Some(_) => return Err(TypingError::NotAFunction { name: function_name, span }),
+            None => return Err(TypingError::UndefinedFunction { name: function_name, span }),
+        };
+
+        if let Some(desired) = desired_type.filter(|&t| t != function_proto.return_type.as_ref()) {
+            return Err(TypingError::ReturnTypeMismatch {
+                func_name: function_name,
+                expected: Box::new(desired.clone()),
+                found: function_proto.return_type,
+                span,
+            });
         }
 
         let num_params = function_proto.params.len();
         if num_params != call.args.len() {
-            panic!(
-                "Expected {} argument(s) to function '{}'; got {} argument(s)",
-                num_params,
-                call.function_name,
-                call.args.len()
-            );
+            return Err(TypingError::ArgumentCountMismatch {
+                func_name: function_name,
+                expected: num_params,
+                found: call.args.len(),
+                span,
+            });
         }
 
-        let args: Vec<_> = call
+        let args = call
             .args
             .iter()
             .zip(function_proto.params.iter())
             .map(|(e, p)| self.type_expr(e, Some(&p.param_type)))
-            .collect();
+            .collect::<Result<Vec<_>, _>>()?;
 
-        TypedCall {
+        Ok(TypedCall {
             function_name,
             function_proto,
             args,
+        })
+    }
+
+    /// Returns an `Err()` if an expression is constant (i.e. its `value` is known at compile time,
+    /// see [const_eval::eval]) but doesn't fit in its type, like `255 + 1` as a `u8`, rather than
+    /// letting it silently wrap around at runtime. The error points at `span`.
+    fn check_constant_fits(value: Option<i128>, expr_type: &Type, span: Span) -> Result<(), TypingError> {
+        match (value, expr_type) {
+            (Some(value), Type::Int(int_type)) if !const_eval::fits(value, *int_type) => {
+                Err(TypingError::ConstantOverflow { value: value.to_string(), int_type: *int_type, span })
+            }
+            _ => Ok(()),
         }
     }
 
-    /// Panics if the cast is invalid, like casting from an unsigned type to a signed type.
-    fn check_valid_cast(cast_type: &Type, operand_type: &Type) {
+    /// Like [Typer::check_constant_fits], but for an integer literal (of any width), like `256`
+    /// as a `u8`; the error points at the literal's `span`.
+    fn check_int_literal_fits(int_literal: &TypedIntLiteral, span: Span) -> Result<(), TypingError> {
+        match const_eval::literal_fits(&int_literal.int_value, int_literal.int_type) {
+            true => Ok(()),
+            false => Err(TypingError::ConstantOverflow {
+                value: int_literal.int_value.clone(),
+                int_type: int_literal.int_type,
+                span,
+            }),
+        }
+    }
+
+    /// Returns an `Err()` (pointing at `span`) if the cast is invalid, like casting from an
+    /// unsigned type to a signed type.
+    fn check_valid_cast(cast_type: &Type, operand_type: &Type, span: Span) -> Result<(), TypingError> {
         match (cast_type, operand_type) {
-            (Type::Int(IntType { signed: true, .. }), Type::Int(IntType { signed: false, .. })) => {
-                panic!(
-                    "Cannot cast from unsigned type '{}' to signed type '{}'",
-                    operand_type, cast_type
-                )
-            }
-            (Type::Int(IntType { signed: false, .. }), Type::Int(IntType { signed: true, .. })) => {
-                panic!(
-                    "Cannot cast from signed type '{}' to unsigned type '{}'",
-                    operand_type, cast_type
-                )
-            }
-            (Type::Int(_), Type::Int(_)) => (), // valid cast
-            _ => panic!(
-                "Cannot cast from type '{}' to type '{}'",
-                operand_type, cast_type
-            ),
+            (Type::Int(cast), Type::Int(operand)) if cast.signed == operand.signed => Ok(()),
+            _ => Err(TypingError::InvalidCast {
+                from: Box::new(operand_type.clone()),
+                to: Box::new(cast_type.clone()),
+                span,
+            }),
         }
     }
 
-    /// Panics if the operand type cannot be negated
+    /// Returns an `Err()` (pointing at `span`) if the operand type cannot be negated
     ///
     /// For example, unsigned integers cannot be negated
-    fn check_valid_negation(operand_type: &Type) {
+    fn check_valid_negation(operand_type: &Type, span: Span) -> Result<(), TypingError> {
         match operand_type {
-            Type::Int(IntType { signed: true, .. }) => (),
-            Type::Int(IntType { signed: false, .. }) => panic!("Cannot negate an unsigned type: '{}'", operand_type),
-            t => panic!("Cannot negate a non-integer type '{}'", t)
+            Type::Int(IntType { signed: true, .. }) => Ok(()),
+            t => Err(TypingError::InvalidNegation { found: Box::new(t.clone()), span }),
         }
     }
 }
@@ -521,9 +715,9 @@ impl Default for Typer {
 mod tests {
     use super::*;
     use crate::ast::*;
+    use crate::lexing::name::Name;
 
     #[test]
-    #[should_panic(expected = "No main function defined")]
     fn missing_main_function() {
         // pub fn not_main() i32 {
         //   ret 0
@@ -533,20 +727,47 @@ mod tests {
             global_statements: vec![GlobalStatement::FuncDef(FuncDef {
                 proto: FuncProto {
                     func_visibility: FuncVisibility::Public,
-                    name: "not_main".to_string(),
+                    name: Name::intern("not_main"),
                     params: vec![],
                     return_type: Box::new(Type::Int(IntType { width: 32, signed: true })),
+                    span: Span::default(),
+                    id: NodeId::default(),
+                    library: None,
                 },
-                body: vec![Statement::Return(Some(Expr::IntLiteral("0".to_string())))],
+                body: vec![Statement::Return(Some(Expr::IntLiteral("0", Span::default(), NodeId::default())), Span::default(), NodeId::default())],
             })],
         };
 
         let mut typer = Typer::new();
-        let _ = typer.type_program(&program);
+        assert_eq!(typer.type_program(&program), Err(vec![TypingError::MissingMain]));
+    }
+
+    #[test]
+    fn main_is_optional_without_main() {
+        // fn answer() i64 {
+        //    ret 42
+        // }
+
+        let program = Program {
+            global_statements: vec![GlobalStatement::FuncDef(FuncDef {
+                proto: FuncProto {
+                    func_visibility: FuncVisibility::Private,
+                    name: Name::intern("answer"),
+                    params: vec![],
+                    return_type: Box::new(Type::Int(IntType { width: 64, signed: true })),
+                    span: Span::default(),
+                    id: NodeId::default(),
+                    library: None,
+                },
+                body: vec![Statement::Return(Some(Expr::IntLiteral("42", Span::default(), NodeId::default())), Span::default(), NodeId::default())],
+            })],
+        };
+
+        assert_eq!(Typer::new().type_program(&program), Err(vec![TypingError::MissingMain]));
+        assert!(Typer::without_main().type_program(&program).is_ok());
     }
 
     #[test]
-    #[should_panic(expected = "The 'main' function should return a u8")]
     fn invalid_main_ret_type() {
         // pub fn main() i32 {
         //    ret 0
@@ -556,20 +777,25 @@ mod tests {
             global_statements: vec![GlobalStatement::FuncDef(FuncDef {
                 proto: FuncProto {
                     func_visibility: FuncVisibility::Public,
-                    name: "main".to_string(),
+                    name: Name::intern("main"),
                     params: vec![],
                     return_type: Box::new(Type::Int(IntType { width: 32, signed: true })),
+                    span: Span::default(),
+                    id: NodeId::default(),
+                    library: None,
                 },
-                body: vec![Statement::Return(Some(Expr::IntLiteral("0".to_string())))],
+                body: vec![Statement::Return(Some(Expr::IntLiteral("0", Span::default(), NodeId::default())), Span::default(), NodeId::default())],
             })],
         };
 
         let mut typer = Typer::new();
-        let _ = typer.type_program(&program);
+        assert_eq!(typer.type_program(&program), Err(vec![TypingError::InvalidMainReturnType {
+            found: Box::new(Type::Int(IntType { width: 32, signed: true })),
+            span: Span::default(),
+        }]));
     }
 
     #[test]
-    #[should_panic(expected = "The 'main' function should not accept any parameters")]
     fn invalid_main_params() {
         // pub fn main(i32 a) i32 {
         //    ret 0
@@ -579,25 +805,29 @@ mod tests {
             global_statements: vec![GlobalStatement::FuncDef(FuncDef {
                 proto: FuncProto {
                     func_visibility: FuncVisibility::Public,
-                    name: "main".to_string(),
+                    name: Name::intern("main"),
                     params: vec![
                         FuncParam {
                             param_type: Type::Int(IntType { width: 32, signed: true }),
-                            param_name: "a".to_string()
+                            param_name: Name::intern("a"),
+                            span: Span::default(),
+                            id: NodeId::default(),
                         }
                     ],
                     return_type: Box::new(Type::Int(IntType { width: 32, signed: true })),
+                    span: Span::default(),
+                    id: NodeId::default(),
+                    library: None,
                 },
-                body: vec![Statement::Return(Some(Expr::IntLiteral("0".to_string())))],
+                body: vec![Statement::Return(Some(Expr::IntLiteral("0", Span::default(), NodeId::default())), Span::default(), NodeId::default())],
             })],
         };
 
         let mut typer = Typer::new();
-        let _ = typer.type_program(&program);
+        assert_eq!(typer.type_program(&program), Err(vec![TypingError::MainWithParams { span: Span::default() }]));
     }
 
     #[test]
-    #[should_panic(expected = "Identifier 'b' of type 'i64' cannot be used as type 'i32'")]
     fn assignment_with_mismatched_types() {
         // pub fn main() i32 {
         //     i64 a = 3
@@ -609,32 +839,47 @@ mod tests {
             global_statements: vec![GlobalStatement::FuncDef(FuncDef {
                 proto: FuncProto {
                     func_visibility: FuncVisibility::Public,
-                    name: "main".to_string(),
+                    name: Name::intern("main"),
                     params: vec![],
                     return_type: Box::new(Type::Int(IntType { width: 32, signed: true })),
+                    span: Span::default(),
+                    id: NodeId::default(),
+                    library: None,
                 },
                 body: vec![
                     Statement::VarDeclaration(VarDeclaration {
-                        var_name: "a".to_string(),
-                        var_value: Expr::IntLiteral("3".to_string()),
+                        var_name: "a",
+                        var_value: Expr::IntLiteral("3", Span::default(), NodeId::default()),
                         var_type: Type::Int(IntType { signed: true, width: 64 }),
+                        span: Span::default(),
+                        id: NodeId::default(),
                     }),
                     Statement::VarDeclaration(VarDeclaration {
-                        var_name: "b".to_string(),
-                        var_value: Expr::Identifier("a".to_string()),
+                        var_name: "b",
+                        var_value: Expr::Identifier(Identifier { name: "a", span: Span::default(), id: NodeId::default() }),
                         var_type: Type::Int(IntType { signed: true, width: 64 }),
+                        span: Span::default(),
+                        id: NodeId::default(),
                     }),
                     Statement::VarDeclaration(VarDeclaration {
-                        var_name: "c".to_string(),
-                        var_value: Expr::Identifier("b".to_string()), // this should panic, since b (i64) can't be in c (i32)
+                        var_name: "c",
+                        var_value: Expr::Identifier(Identifier { name: "b", span: Span::default(), id: NodeId::default() }), // this should fail, since b (i64) can't be in c (i32)
                         var_type: Type::Int(IntType { width: 32, signed: true }),
+                        span: Span::default(),
+                        id: NodeId::default(),
                     }),
                 ],
             })],
         };
 
-        let mut typer = Typer::new();
-        let _ = typer.type_program(&program);
+        let mut typer = Typer::without_main();
+        let expected_error = TypingError::IdentifierTypeMismatch {
+            name: "b".to_string(),
+            found: Box::new(Type::Int(IntType { width: 64, signed: true })),
+            expected: Box::new(Type::Int(IntType { width: 32, signed: true })),
+            span: Span::default(),
+        };
+        assert_eq!(typer.type_program(&program), Err(vec![expected_error]));
     }
 
     #[test]
@@ -649,22 +894,29 @@ mod tests {
             global_statements: vec![GlobalStatement::FuncDef(FuncDef {
                 proto: FuncProto {
                     func_visibility: FuncVisibility::Public,
-                    name: "main".to_string(),
+                    name: Name::intern("main"),
                     params: vec![],
                     return_type: Box::new(Type::Int(IntType { width: 8, signed: false })),
+                    span: Span::default(),
+                    id: NodeId::default(),
+                    library: None,
                 },
                 body: vec![
                     Statement::VarDeclaration(VarDeclaration {
-                        var_name: "a".to_string(),
-                        var_value: Expr::IntLiteral("3".to_string()),
+                        var_name: "a",
+                        var_value: Expr::IntLiteral("3", Span::default(), NodeId::default()),
                         var_type: Type::Int(IntType { width: 8, signed: false }),
+                        span: Span::default(),
+                        id: NodeId::default(),
                     }),
                     Statement::VarDeclaration(VarDeclaration {
-                        var_name: "b".to_string(),
-                        var_value: Expr::Identifier("a".to_string()),
+                        var_name: "b",
+                        var_value: Expr::Identifier(Identifier { name: "a", span: Span::default(), id: NodeId::default() }),
                         var_type: Type::Int(IntType { width: 8, signed: false }),
+                        span: Span::default(),
+                        id: NodeId::default(),
                     }),
-                    Statement::Return(Some(Expr::Identifier("b".to_string()))),
+                    Statement::Return(Some(Expr::Identifier(Identifier { name: "b", span: Span::default(), id: NodeId::default() })), Span::default(), NodeId::default()),
                 ],
             })],
         };
@@ -673,9 +925,12 @@ mod tests {
             global_statements: vec![TypedGlobalStatement::FuncDef(TypedFuncDef {
                 proto: FuncProto {
                     func_visibility: FuncVisibility::Public,
-                    name: "main".to_string(),
+                    name: Name::intern("main"),
                     params: vec![],
                     return_type: Box::new(Type::Int(IntType { width: 8, signed: false })),
+                    span: Span::default(),
+                    id: NodeId::default(),
+                    library: None,
                 },
                 body: vec![
                     TypedStatement::VarDeclaration(TypedVarDeclaration {
@@ -700,16 +955,17 @@ mod tests {
                     }))),
                 ],
             })],
+            coverage: None,
         };
 
         let mut typer = Typer::new();
-        let actual_typed_program = typer.type_program(&program);
+        let actual_typed_program = typer.type_program(&program).unwrap();
         assert_eq!(expected_typed_program, actual_typed_program);
     }
 
     #[test]
-    #[should_panic(expected = "Cannot cast from signed type 'i32' to unsigned type 'u8'")]
     fn cast_signed_to_unsigned() {
+        let arena = Arena::new();
         // pub fn main() u8 {
         //     i32 a = 3
         //     ret (u8) a
@@ -719,30 +975,43 @@ mod tests {
             global_statements: vec![GlobalStatement::FuncDef(FuncDef {
                 proto: FuncProto {
                     func_visibility: FuncVisibility::Public,
-                    name: "main".to_string(),
+                    name: Name::intern("main"),
                     params: vec![],
                     return_type: Box::new(Type::Int(IntType { width: 8, signed: false })),
+                    span: Span::default(),
+                    id: NodeId::default(),
+                    library: None,
                 },
                 body: vec![
                     Statement::VarDeclaration(VarDeclaration {
-                        var_name: "a".to_string(),
-                        var_value: Expr::IntLiteral("3".to_string()),
+                        var_name: "a",
+                        var_value: Expr::IntLiteral("3", Span::default(), NodeId::default()),
                         var_type: Type::Int(IntType { width: 32, signed: true }),
+                        span: Span::default(),
+                        id: NodeId::default(),
                     }),
                     Statement::Return(Some(Expr::Unary(Unary {
                         operator: UnaryOperator::Cast(Type::Int(IntType { width: 8, signed: false })),
-                        operand: Box::new(Expr::Identifier("a".to_string())),
-                    }))),
+                        operand: arena.alloc(Expr::Identifier(Identifier { name: "a", span: Span::default(), id: NodeId::default() })),
+                        span: Span::default(),
+                        id: NodeId::default(),
+                    })), Span::default(), NodeId::default()),
                 ],
             })],
         };
 
         let mut typer = Typer::new();
-        let _ = typer.type_program(&program);  // should panic
+        let expected_error = TypingError::InvalidCast {
+            from: Box::new(Type::Int(IntType { width: 32, signed: true })),
+            to: Box::new(Type::Int(IntType { width: 8, signed: false })),
+            span: Span::default(),
+        };
+        assert_eq!(typer.type_program(&program), Err(vec![expected_error]));
     }
 
     #[test]
     fn cast_u32_to_u8() {
+        let arena = Arena::new();
         // pub fn main() u8 {
         //     u32 a = 3
         //     ret (u8) a
@@ -752,20 +1021,27 @@ mod tests {
             global_statements: vec![GlobalStatement::FuncDef(FuncDef {
                 proto: FuncProto {
                     func_visibility: FuncVisibility::Public,
-                    name: "main".to_string(),
+                    name: Name::intern("main"),
                     params: vec![],
                     return_type: Box::new(Type::Int(IntType { width: 8, signed: false })),
+                    span: Span::default(),
+                    id: NodeId::default(),
+                    library: None,
                 },
                 body: vec![
                     Statement::VarDeclaration(VarDeclaration {
-                        var_name: "a".to_string(),
-                        var_value: Expr::IntLiteral("3".to_string()),
+                        var_name: "a",
+                        var_value: Expr::IntLiteral("3", Span::default(), NodeId::default()),
                         var_type: Type::Int(IntType { width: 32, signed: false }),
+                        span: Span::default(),
+                        id: NodeId::default(),
                     }),
                     Statement::Return(Some(Expr::Unary(Unary {
                         operator: UnaryOperator::Cast(Type::Int(IntType { width: 8, signed: false })),
-                        operand: Box::new(Expr::Identifier("a".to_string())),
-                    }))),
+                        operand: arena.alloc(Expr::Identifier(Identifier { name: "a", span: Span::default(), id: NodeId::default() })),
+                        span: Span::default(),
+                        id: NodeId::default(),
+                    })), Span::default(), NodeId::default()),
                 ],
             })],
         };
@@ -774,9 +1050,12 @@ mod tests {
             global_statements: vec![TypedGlobalStatement::FuncDef(TypedFuncDef {
                 proto: FuncProto {
                     func_visibility: FuncVisibility::Public,
-                    name: "main".to_string(),
+                    name: Name::intern("main"),
                     params: vec![],
                     return_type: Box::new(Type::Int(IntType { width: 8, signed: false })),
+                    span: Span::default(),
+                    id: NodeId::default(),
+                    library: None,
                 },
                 body: vec![
                     TypedStatement::VarDeclaration(TypedVarDeclaration {
@@ -797,15 +1076,15 @@ mod tests {
                     }))),
                 ],
             })],
+            coverage: None,
         };
 
         let mut typer = Typer::new();
-        let actual_typed_program = typer.type_program(&program);
+        let actual_typed_program = typer.type_program(&program).unwrap();
         assert_eq!(expected_typed_program, actual_typed_program);
     }
 
     #[test]
-    #[should_panic(expected = "Function 'main' does not always return a value")]
     fn main_without_return() {
         // pub fn main() u8 {
         // }
@@ -814,15 +1093,506 @@ mod tests {
             global_statements: vec![GlobalStatement::FuncDef(FuncDef {
                 proto: FuncProto {
                     func_visibility: FuncVisibility::Public,
-                    name: "main".to_string(),
+                    name: Name::intern("main"),
                     params: vec![],
                     return_type: Box::new(Type::Int(IntType { width: 8, signed: false })),
+                    span: Span::default(),
+                    id: NodeId::default(),
+                    library: None,
                 },
                 body: vec![],
             })],
         };
 
         let mut typer = Typer::new();
-        let _ = typer.type_program(&program);
+        assert_eq!(typer.type_program(&program), Err(vec![TypingError::MissingReturn { func_name: "main".to_string(), span: Span::default() }]));
+    }
+
+    #[test]
+    fn while_loop_with_int_condition() {
+        let arena = Arena::new();
+        // pub fn main() u8 {
+        //     u8 n = 3
+        //     while n {
+        //         n -= 1
+        //     }
+        //     ret n
+        // }
+
+        let program = Program {
+            global_statements: vec![GlobalStatement::FuncDef(FuncDef {
+                proto: FuncProto {
+                    func_visibility: FuncVisibility::Public,
+                    name: Name::intern("main"),
+                    params: vec![],
+                    return_type: Box::new(Type::Int(IntType { width: 8, signed: false })),
+                    span: Span::default(),
+                    id: NodeId::default(),
+                    library: None,
+                },
+                body: vec![
+                    Statement::VarDeclaration(VarDeclaration {
+                        var_name: "n",
+                        var_value: Expr::IntLiteral("3", Span::default(), NodeId::default()),
+                        var_type: Type::Int(IntType { width: 8, signed: false }),
+                        span: Span::default(),
+                        id: NodeId::default(),
+                    }),
+                    Statement::WhileLoop(WhileLoop {
+                        condition: Expr::Identifier(Identifier { name: "n", span: Span::default(), id: NodeId::default() }),
+                        body: vec![Statement::Assignment(Assignment {
+                            name: "n",
+                            value: arena.alloc(Expr::Binary(Binary {
+                                left: arena.alloc(Expr::Identifier(Identifier { name: "n", span: Span::default(), id: NodeId::default() })),
+                                operator: BinaryOperator::Subtract,
+                                right: arena.alloc(Expr::IntLiteral("1", Span::default(), NodeId::default())),
+                                span: Span::default(),
+                                id: NodeId::default(),
+                            })),
+                            span: Span::default(),
+                            id: NodeId::default(),
+                        })],
+                        span: Span::default(),
+                        id: NodeId::default(),
+                    }),
+                    Statement::Return(Some(Expr::Identifier(Identifier { name: "n", span: Span::default(), id: NodeId::default() })), Span::default(), NodeId::default()),
+                ],
+            })],
+        };
+
+        let mut typer = Typer::new();
+        let expected_error = TypingError::NonBoolCondition {
+            statement_kind: "while",
+            found: Box::new(Type::Int(IntType { width: 8, signed: false })),
+            span: Span::default(),
+        };
+        assert_eq!(typer.type_program(&program), Err(vec![expected_error]));
+    }
+
+    #[test]
+    fn if_statement_with_int_literal_condition() {
+        // pub fn main() u8 {
+        //     if 1 {
+        //         ret 1
+        //     }
+        //     ret 0
+        // }
+
+        let program = Program {
+            global_statements: vec![GlobalStatement::FuncDef(FuncDef {
+                proto: FuncProto {
+                    func_visibility: FuncVisibility::Public,
+                    name: Name::intern("main"),
+                    params: vec![],
+                    return_type: Box::new(Type::Int(IntType { width: 8, signed: false })),
+                    span: Span::default(),
+                    id: NodeId::default(),
+                    library: None,
+                },
+                body: vec![
+                    Statement::If(If {
+                        condition: Expr::IntLiteral("1", Span::default(), NodeId::default()),
+                        then_body: vec![Statement::Return(Some(Expr::IntLiteral("1", Span::default(), NodeId::default())), Span::default(), NodeId::default())],
+                        else_body: None,
+                        span: Span::default(),
+                        id: NodeId::default(),
+                    }),
+                    Statement::Return(Some(Expr::IntLiteral("0", Span::default(), NodeId::default())), Span::default(), NodeId::default()),
+                ],
+            })],
+        };
+
+        let mut typer = Typer::new();
+        let expected_error = TypingError::NonBoolCondition {
+            statement_kind: "if",
+            found: Box::new(Type::Int(IntType { width: 64, signed: false })),
+            span: Span::default(),
+        };
+        assert_eq!(typer.type_program(&program), Err(vec![expected_error]));
+    }
+
+    #[test]
+    fn while_loop_with_bool_variable_condition() {
+        let arena = Arena::new();
+        // pub fn main() u8 {
+        //     bool keep_going = true
+        //     while keep_going {
+        //         keep_going = false
+        //     }
+        //     ret 0
+        // }
+
+        let program = Program {
+            global_statements: vec![GlobalStatement::FuncDef(FuncDef {
+                proto: FuncProto {
+                    func_visibility: FuncVisibility::Public,
+                    name: Name::intern("main"),
+                    params: vec![],
+                    return_type: Box::new(Type::Int(IntType { width: 8, signed: false })),
+                    span: Span::default(),
+                    id: NodeId::default(),
+                    library: None,
+                },
+                body: vec![
+                    Statement::VarDeclaration(VarDeclaration {
+                        var_name: "keep_going",
+                        var_value: Expr::BoolLiteral(true, Span::default(), NodeId::default()),
+                        var_type: Type::Bool,
+                        span: Span::default(),
+                        id: NodeId::default(),
+                    }),
+                    Statement::WhileLoop(WhileLoop {
+                        condition: Expr::Identifier(Identifier { name: "keep_going", span: Span::default(), id: NodeId::default() }),
+                        body: vec![Statement::Assignment(Assignment {
+                            name: "keep_going",
+                            value: arena.alloc(Expr::BoolLiteral(false, Span::default(), NodeId::default())),
+                            span: Span::default(),
+                            id: NodeId::default(),
+                        })],
+                        span: Span::default(),
+                        id: NodeId::default(),
+                    }),
+                    Statement::Return(Some(Expr::IntLiteral("0", Span::default(), NodeId::default())), Span::default(), NodeId::default()),
+                ],
+            })],
+        };
+
+        let mut typer = Typer::new();
+        let typed_program = typer.type_program(&program).unwrap();
+
+        let TypedGlobalStatement::FuncDef(main) = &typed_program.global_statements[0] else {
+            panic!("Expected 'main' to be a function definition");
+        };
+        let TypedStatement::WhileLoop(while_loop) = &main.body[1] else {
+            panic!("Expected the second statement of 'main' to be a while loop");
+        };
+        assert_eq!(while_loop.condition.get_result_type(), Type::Bool);
+    }
+
+    #[test]
+    fn builtin_cannot_be_redefined() {
+        // fn breakpoint() {
+        // }
+
+        let program = Program {
+            global_statements: vec![GlobalStatement::FuncDef(FuncDef {
+                proto: FuncProto {
+                    func_visibility: FuncVisibility::Private,
+                    name: Name::intern("breakpoint"),
+                    params: vec![],
+                    return_type: Box::new(Type::Void),
+                    span: Span::default(),
+                    id: NodeId::default(),
+                    library: None,
+                },
+                body: vec![],
+            })],
+        };
+
+        let mut typer = Typer::without_main();
+        assert_eq!(typer.type_program(&program), Err(vec![TypingError::BuiltinRedefinition { name: "breakpoint".to_string(), span: Span::default() }]));
+    }
+
+    #[test]
+    fn assignment_to_function() {
+        let arena = Arena::new();
+        // pub fn main() u8 {
+        //     main = 1
+        //     ret 0
+        // }
+
+        let program = Program {
+            global_statements: vec![GlobalStatement::FuncDef(FuncDef {
+                proto: FuncProto {
+                    func_visibility: FuncVisibility::Public,
+                    name: Name::intern("main"),
+                    params: vec![],
+                    return_type: Box::new(Type::Int(IntType { width: 8, signed: false })),
+                    span: Span::default(),
+                    id: NodeId::default(),
+                    library: None,
+                },
+                body: vec![
+                    Statement::Assignment(Assignment {
+                        name: "main",
+                        value: arena.alloc(Expr::IntLiteral("1", Span::default(), NodeId::default())),
+                        span: Span::default(),
+                        id: NodeId::default(),
+                    }),
+                    Statement::Return(Some(Expr::IntLiteral("0", Span::default(), NodeId::default())), Span::default(), NodeId::default()),
+                ],
+            })],
+        };
+
+        let mut typer = Typer::new();
+        assert_eq!(typer.type_program(&program), Err(vec![TypingError::AssignmentToFunction { name: "main".to_string(), span: Span::default() }]));
+    }
+
+    /// Returns a `main` function (which returns a `u8`) whose body is just `ret_statement`.
+    fn main_returning(ret_statement: Statement) -> Program {
+        Program {
+            global_statements: vec![GlobalStatement::FuncDef(FuncDef {
+                proto: FuncProto {
+                    func_visibility: FuncVisibility::Public,
+                    name: Name::intern("main"),
+                    params: vec![],
+                    return_type: Box::new(Type::Int(IntType { width: 8, signed: false })),
+                    span: Span::default(),
+                    id: NodeId::default(),
+                    library: None,
+                },
+                body: vec![ret_statement],
+            })],
+        }
+    }
+
+    #[test]
+    fn return_value_of_wrong_type() {
+        // pub fn main() u8 {
+        //     ret true
+        // }
+
+        let program = main_returning(Statement::Return(Some(Expr::BoolLiteral(true, Span::default(), NodeId::default())), Span::default(), NodeId::default()));
+
+        let mut typer = Typer::new();
+        let expected_error = TypingError::ReturnValueTypeMismatch {
+            expected: Box::new(Type::Int(IntType { width: 8, signed: false })),
+            found: Box::new(Type::Bool),
+            span: Span::default(),
+        };
+        assert_eq!(typer.type_program(&program), Err(vec![expected_error]));
+    }
+
+    #[test]
+    fn return_without_value_from_non_void_function() {
+        // pub fn main() u8 {
+        //     ret
+        // }
+
+        let program = main_returning(Statement::Return(None, Span::default(), NodeId::default()));
+
+        let mut typer = Typer::new();
+        let expected_error = TypingError::MissingReturnValue {
+            expected: Box::new(Type::Int(IntType { width: 8, signed: false })),
+            span: Span::default(),
+        };
+        assert_eq!(typer.type_program(&program), Err(vec![expected_error]));
+    }
+
+    #[test]
+    fn return_value_from_void_function() {
+        // fn foo() {
+        //     ret 1
+        // }
+
+        let program = Program {
+            global_statements: vec![GlobalStatement::FuncDef(FuncDef {
+                proto: FuncProto {
+                    func_visibility: FuncVisibility::Private,
+                    name: Name::intern("foo"),
+                    params: vec![],
+                    return_type: Box::new(Type::Void),
+                    span: Span::default(),
+                    id: NodeId::default(),
+                    library: None,
+                },
+                body: vec![Statement::Return(Some(Expr::IntLiteral("1", Span::default(), NodeId::default())), Span::default(), NodeId::default())],
+            })],
+        };
+
+        let mut typer = Typer::without_main();
+        assert_eq!(typer.type_program(&program), Err(vec![TypingError::UnexpectedReturnValue { span: Span::default() }]));
+    }
+
+    #[test]
+    fn constant_overflow() {
+        let arena = Arena::new();
+        // pub fn main() u8 {
+        //     i64 x = 9223372036854775807 + 1
+        //     ret 0
+        // }
+
+        let i64_type = IntType { width: 64, signed: true };
+        let program = Program {
+            global_statements: vec![GlobalStatement::FuncDef(FuncDef {
+                proto: FuncProto {
+                    func_visibility: FuncVisibility::Public,
+                    name: Name::intern("main"),
+                    params: vec![],
+                    return_type: Box::new(Type::Int(IntType { width: 8, signed: false })),
+                    span: Span::default(),
+                    id: NodeId::default(),
+                    library: None,
+                },
+                body: vec![
+                    Statement::VarDeclaration(VarDeclaration {
+                        var_name: "x",
+                        var_type: Type::Int(i64_type),
+                        var_value: Expr::Binary(Binary {
+                            left: arena.alloc(Expr::IntLiteral("9223372036854775807", Span::default(), NodeId::default())),
+                            operator: BinaryOperator::Add,
+                            right: arena.alloc(Expr::IntLiteral("1", Span::default(), NodeId::default())),
+                            span: Span { start: 51, end: 52 },
+                            id: NodeId::default(),
+                        }),
+                        span: Span::default(),
+                        id: NodeId::default(),
+                    }),
+                    Statement::Return(Some(Expr::IntLiteral("0", Span::default(), NodeId::default())), Span::default(), NodeId::default()),
+                ],
+            })],
+        };
+
+        let mut typer = Typer::new();
+        let expected_error = TypingError::ConstantOverflow {
+            value: (i64::MAX as i128 + 1).to_string(),
+            int_type: i64_type,
+            span: Span { start: 51, end: 52 },
+        };
+        assert_eq!(typer.type_program(&program), Err(vec![expected_error]));
+    }
+
+    #[test]
+    fn int_literal_overflow() {
+        // pub fn main() u8 {
+        //     u8 x = 256
+        //     ret 0
+        // }
+
+        let u8_type = IntType { width: 8, signed: false };
+        let program = Program {
+            global_statements: vec![GlobalStatement::FuncDef(FuncDef {
+                proto: FuncProto {
+                    func_visibility: FuncVisibility::Public,
+                    name: Name::intern("main"),
+                    params: vec![],
+                    return_type: Box::new(Type::Int(u8_type)),
+                    span: Span::default(),
+                    id: NodeId::default(),
+                    library: None,
+                },
+                body: vec![
+                    Statement::VarDeclaration(VarDeclaration {
+                        var_name: "x",
+                        var_type: Type::Int(u8_type),
+                        var_value: Expr::IntLiteral("256", Span { start: 30, end: 33 }, NodeId::default()),
+                        span: Span::default(),
+                        id: NodeId::default(),
+                    }),
+                    Statement::Return(Some(Expr::IntLiteral("0", Span::default(), NodeId::default())), Span::default(), NodeId::default()),
+                ],
+            })],
+        };
+
+        let mut typer = Typer::new();
+        let expected_error = TypingError::ConstantOverflow {
+            value: "256".to_string(),
+            int_type: u8_type,
+            span: Span { start: 30, end: 33 },
+        };
+        assert_eq!(typer.type_program(&program), Err(vec![expected_error]));
+    }
+
+    #[test]
+    fn constant_at_the_edge_of_its_type() {
+        let arena = Arena::new();
+        // pub fn main() u8 {
+        //     i8 x = -128
+        //     ret 255
+        // }
+
+        let program = Program {
+            global_statements: vec![GlobalStatement::FuncDef(FuncDef {
+                proto: FuncProto {
+                    func_visibility: FuncVisibility::Public,
+                    name: Name::intern("main"),
+                    params: vec![],
+                    return_type: Box::new(Type::Int(IntType { width: 8, signed: false })),
+                    span: Span::default(),
+                    id: NodeId::default(),
+                    library: None,
+                },
+                body: vec![
+                    Statement::VarDeclaration(VarDeclaration {
+                        var_name: "x",
+                        var_type: Type::Int(IntType { width: 8, signed: true }),
+                        var_value: Expr::Unary(Unary {
+                            operator: UnaryOperator::Negate,
+                            operand: arena.alloc(Expr::IntLiteral("128", Span::default(), NodeId::default())),
+                            span: Span::default(),
+                            id: NodeId::default(),
+                        }),
+                        span: Span::default(),
+                        id: NodeId::default(),
+                    }),
+                    Statement::Return(Some(Expr::IntLiteral("255", Span::default(), NodeId::default())), Span::default(), NodeId::default()),
+                ],
+            })],
+        };
+
+        let mut typer = Typer::new();
+        assert!(typer.type_program(&program).is_ok());
+    }
+
+    #[test]
+    fn reports_an_error_in_each_function() {
+        use crate::{Lexer, Parser};
+
+        let source_code = "\
+pub fn main() u8 {
+    ret true
+}
+
+fn yes() bool {
+    ret 3
+}
+
+fn narrow(i64 x) u8 {
+    u8 y = x
+    ret y
+}
+";
+        let tokens = Lexer::lex(source_code).unwrap();
+        let arena = Arena::new();
+        let program = Parser::parse_program(&arena, &tokens).unwrap();
+        let codes: Vec<_> = Typer::new().type_program(&program).unwrap_err().iter().map(TypingError::code).collect();
+        assert_eq!(codes, ["E0316", "E0317", "E0312"]);
+    }
+
+    #[test]
+    #[cfg(feature = "parallel")]
+    fn typing_in_parallel_is_like_typing_in_order() {
+        use crate::{Lexer, Parser};
+
+        let mut source_code = String::from("pub fn main() u8 {\n    ret f0(1)\n}\n");
+        for i in 0..50 {
+            source_code += &format!("fn f{}(u8 x) u8 {{\n    ret f{}(x + 1)\n}}\n", i, i + 1);
+        }
+        source_code += "fn f50(u8 x) u8 {\n    ret x\n}\n";
+        let tokens = Lexer::lex(&source_code).unwrap();
+        let arena = Arena::new();
+        let program = Parser::parse_program(&arena, &tokens).unwrap();
+        let (mut typer, mut parallel_typer) = (Typer::new(), Typer::new());
+        let typed_program = typer.type_program(&program);
+        assert!(typed_program.is_ok());
+        assert_eq!(parallel_typer.type_program_in_parallel(&program), typed_program);
+        assert_eq!(parallel_typer.expr_types(), typer.expr_types());
+
+        // The errors are in the order that they're in the program, whichever threads find them
+        let broken = source_code.replace("ret f10(x + 1)", "ret f10()").replace("ret f40(x + 1)", "ret f40()");
+        let (tokens, spans) = Lexer::lex_with_spans(&broken).unwrap();
+        let arena = Arena::new();
+        let program = Parser::parse_program_with_spans(&arena, &tokens, &spans).unwrap();
+        let errors = Typer::new().type_program(&program).unwrap_err();
+        let expected_errors: Vec<_> = ["f10", "f40"]
+            .into_iter()
+            .map(|func_name| {
+                let start = broken.find(&format!("{}()", func_name)).unwrap();
+                let span = Span { start, end: start + 3 };
+                TypingError::ArgumentCountMismatch { func_name: func_name.to_string(), expected: 1, found: 0, span }
+            })
+            .collect();
+        assert_eq!(errors, expected_errors);
+        assert_eq!(Typer::new().type_program_in_parallel(&program), Err(errors));
     }
 }